@@ -0,0 +1,101 @@
+//! Compares learning curves of groups of runs.
+//!
+//! ```bash
+//! border-compare --metric "Episode return" --out dqn_vs_iqn \
+//!     --tfrecord dqn=model/dqn_0,model/dqn_1,model/dqn_2 \
+//!     --tfrecord iqn=model/iqn_0,model/iqn_1,model/iqn_2
+//! ```
+//!
+//! It writes `dqn_vs_iqn.csv` and `dqn_vs_iqn.svg`. Runs logged in an MLflow tracking server
+//! can be given with `--mlflow name=run1,run2` together with `--mlflow-uri` and `--experiment`.
+use anyhow::Result;
+use border_analysis::{read_mlflow_curve, read_tfrecord_curve, write_svg, AggregatedCurve};
+use border_mlflow_tracking::MlflowTrackingClient;
+use clap::Parser;
+
+/// Aggregate learning curves across runs and write them as CSV and SVG files
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Name of the metric, e.g., "Episode return"
+    #[arg(long)]
+    metric: String,
+
+    /// Prefix of the output files
+    #[arg(long, default_value = "curves")]
+    out: String,
+
+    /// Group of runs in TensorBoard log directories, given as `name=dir1,dir2,...`
+    #[arg(long)]
+    tfrecord: Vec<String>,
+
+    /// Group of runs in MLflow, given as `name=run_name1,run_name2,...`
+    #[arg(long)]
+    mlflow: Vec<String>,
+
+    /// URI of the MLflow tracking server
+    #[arg(long, default_value = "http://localhost:8080")]
+    mlflow_uri: String,
+
+    /// Name of the MLflow experiment, required with `--mlflow`
+    #[arg(long)]
+    experiment: Option<String>,
+
+    /// The number of evenly spaced steps.
+    /// If not given, all steps logged in any of the runs are used.
+    #[arg(long)]
+    n_points: Option<usize>,
+}
+
+/// Splits `name=a,b,c` into the name and the items.
+fn parse_group(s: &str) -> Result<(String, Vec<String>)> {
+    match s.split_once('=') {
+        Some((name, items)) if !name.is_empty() && !items.is_empty() => Ok((
+            name.to_string(),
+            items.split(',').map(|s| s.to_string()).collect(),
+        )),
+        _ => anyhow::bail!("Invalid group {:?}, expected name=item1,item2,...", s),
+    }
+}
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let args = Args::parse();
+    let mut curves = vec![];
+
+    for group in args.tfrecord.iter() {
+        let (name, dirs) = parse_group(group)?;
+        let runs = dirs
+            .iter()
+            .map(|dir| read_tfrecord_curve(dir, &args.metric))
+            .collect::<Result<Vec<_>>>()?;
+        log::info!("Read {} runs of {}", runs.len(), name);
+        curves.push((name, AggregatedCurve::new(&runs, args.n_points)?));
+    }
+
+    if !args.mlflow.is_empty() {
+        let experiment = match args.experiment.as_ref() {
+            Some(experiment) => experiment,
+            None => anyhow::bail!("--experiment is required with --mlflow"),
+        };
+        let client = MlflowTrackingClient::new(&args.mlflow_uri).set_experiment(experiment)?;
+        for group in args.mlflow.iter() {
+            let (name, run_names) = parse_group(group)?;
+            let runs = run_names
+                .iter()
+                .map(|run_name| read_mlflow_curve(&client, run_name, &args.metric))
+                .collect::<Result<Vec<_>>>()?;
+            log::info!("Read {} runs of {}", runs.len(), name);
+            curves.push((name, AggregatedCurve::new(&runs, args.n_points)?));
+        }
+    }
+
+    if curves.is_empty() {
+        anyhow::bail!("No runs given, use --tfrecord or --mlflow");
+    }
+    AggregatedCurve::write_csv(format!("{}.csv", args.out), &curves)?;
+    write_svg(format!("{}.svg", args.out), &curves, &args.metric)?;
+    log::info!("Wrote {0}.csv and {0}.svg", args.out);
+
+    Ok(())
+}
@@ -0,0 +1,210 @@
+//! Learning curves and their aggregation across runs.
+use anyhow::Result;
+use std::path::Path;
+
+/// Two-sided 95% quantiles of Student's t-distribution for 1 to 30 degrees of freedom.
+const T_95: [f32; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+    2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+    2.052, 2.048, 2.045, 2.042,
+];
+
+/// Values of a metric of a run over steps.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LearningCurve {
+    /// Name of the run.
+    pub name: String,
+
+    /// Pairs of steps and values, sorted by steps.
+    pub points: Vec<(i64, f32)>,
+}
+
+impl LearningCurve {
+    /// Constructs a learning curve.
+    ///
+    /// Points are sorted by steps. For duplicated steps, the last value is kept.
+    pub fn new(name: impl Into<String>, mut points: Vec<(i64, f32)>) -> Self {
+        points.reverse();
+        points.sort_by_key(|(step, _)| *step);
+        points.dedup_by_key(|(step, _)| *step);
+        Self {
+            name: name.into(),
+            points,
+        }
+    }
+
+    /// Returns the value at a step, linearly interpolated between the nearest points.
+    ///
+    /// The step must be in the range of the steps of the curve.
+    fn interpolate(&self, step: i64) -> f32 {
+        let i = self.points.partition_point(|(s, _)| *s < step);
+        let (s1, v1) = self.points[i];
+        if s1 == step || i == 0 {
+            return v1;
+        }
+        let (s0, v0) = self.points[i - 1];
+        let t = (step - s0) as f32 / (s1 - s0) as f32;
+        v0 + t * (v1 - v0)
+    }
+}
+
+/// Mean and 95% confidence interval of learning curves across runs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AggregatedCurve {
+    /// Steps.
+    pub steps: Vec<i64>,
+
+    /// Mean over runs.
+    pub mean: Vec<f32>,
+
+    /// Sample standard deviation over runs.
+    pub std: Vec<f32>,
+
+    /// Lower bound of the 95% confidence interval of the mean.
+    pub lower: Vec<f32>,
+
+    /// Upper bound of the 95% confidence interval of the mean.
+    pub upper: Vec<f32>,
+
+    /// The number of runs.
+    pub n_runs: usize,
+}
+
+impl AggregatedCurve {
+    /// Aggregates learning curves of runs, typically with different random seeds.
+    ///
+    /// Curves are aligned by steps in the range covered by all runs. Steps are those logged
+    /// in any of the runs, or `n_points` evenly spaced steps if given, where values are
+    /// linearly interpolated for runs not logging the steps. The confidence interval is
+    /// based on Student's t-distribution.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `curves` is empty, any of the curves is empty,
+    /// or the ranges of steps of the curves do not overlap.
+    pub fn new(curves: &[LearningCurve], n_points: Option<usize>) -> Result<Self> {
+        if curves.is_empty() || curves.iter().any(|c| c.points.is_empty()) {
+            anyhow::bail!("Learning curves must not be empty");
+        }
+
+        // Range of steps covered by all runs
+        let first = curves.iter().map(|c| c.points[0].0).max().unwrap();
+        let last = curves
+            .iter()
+            .map(|c| c.points[c.points.len() - 1].0)
+            .min()
+            .unwrap();
+        if first > last {
+            anyhow::bail!("Ranges of steps of the learning curves do not overlap");
+        }
+
+        let steps = match n_points {
+            Some(n) if n >= 2 => (0..n)
+                .map(|i| first + ((last - first) as f64 * i as f64 / (n - 1) as f64) as i64)
+                .collect::<Vec<_>>(),
+            _ => {
+                let mut steps = curves
+                    .iter()
+                    .flat_map(|c| c.points.iter().map(|(s, _)| *s))
+                    .filter(|s| first <= *s && *s <= last)
+                    .collect::<Vec<_>>();
+                steps.sort_unstable();
+                steps.dedup();
+                steps
+            }
+        };
+
+        let n_runs = curves.len();
+        let t = match n_runs {
+            1 => 0.0,
+            n if n - 1 <= T_95.len() => T_95[n - 2],
+            _ => 1.96,
+        };
+        let (mut mean, mut std, mut lower, mut upper) = (vec![], vec![], vec![], vec![]);
+        for step in steps.iter() {
+            let values = curves
+                .iter()
+                .map(|c| c.interpolate(*step))
+                .collect::<Vec<_>>();
+            let m = values.iter().sum::<f32>() / n_runs as f32;
+            let s = match n_runs {
+                1 => 0.0,
+                _ => {
+                    let ss = values.iter().map(|v| (v - m).powi(2)).sum::<f32>();
+                    (ss / (n_runs - 1) as f32).sqrt()
+                }
+            };
+            let h = t * s / (n_runs as f32).sqrt();
+            mean.push(m);
+            std.push(s);
+            lower.push(m - h);
+            upper.push(m + h);
+        }
+
+        Ok(Self {
+            steps,
+            mean,
+            std,
+            lower,
+            upper,
+            n_runs,
+        })
+    }
+
+    /// Writes aggregated curves as a CSV file.
+    ///
+    /// Columns are `name`, `step`, `mean`, `std`, `lower`, `upper` and `n_runs`.
+    pub fn write_csv(path: impl AsRef<Path>, curves: &[(String, AggregatedCurve)]) -> Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["name", "step", "mean", "std", "lower", "upper", "n_runs"])?;
+        for (name, c) in curves.iter() {
+            for i in 0..c.steps.len() {
+                wtr.write_record(&[
+                    name.clone(),
+                    c.steps[i].to_string(),
+                    c.mean[i].to_string(),
+                    c.std[i].to_string(),
+                    c.lower[i].to_string(),
+                    c.upper[i].to_string(),
+                    c.n_runs.to_string(),
+                ])?;
+            }
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aggregate() -> Result<()> {
+        let curves = vec![
+            LearningCurve::new("seed0", vec![(0, 0.0), (10, 1.0), (20, 2.0), (30, 9.0)]),
+            LearningCurve::new("seed1", vec![(5, 1.0), (15, 3.0), (25, 5.0)]),
+        ];
+
+        // Steps of both runs in [5, 25], values of seed0 at 5, 15 and 25 are interpolated
+        let c = AggregatedCurve::new(&curves, None)?;
+        assert_eq!(c.steps, vec![5, 10, 15, 20, 25]);
+        assert_eq!(c.mean, vec![0.75, 1.5, 2.25, 3.0, 5.25]);
+        assert_eq!(c.n_runs, 2);
+        let h = 12.706 * c.std[0] / 2f32.sqrt();
+        assert!((c.std[0] - 0.5 / 2f32.sqrt()).abs() < 1e-6);
+        assert!((c.upper[0] - c.mean[0] - h).abs() < 1e-6);
+
+        // Evenly spaced steps
+        let c = AggregatedCurve::new(&curves, Some(3))?;
+        assert_eq!(c.steps, vec![5, 15, 25]);
+
+        // No overlap
+        let curves = vec![
+            LearningCurve::new("a", vec![(0, 0.0), (1, 0.0)]),
+            LearningCurve::new("b", vec![(2, 0.0)]),
+        ];
+        assert!(AggregatedCurve::new(&curves, None).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+//! Analysis of training runs of Border.
+//!
+//! This crate reads a metric of multiple runs, typically trained with different random seeds,
+//! aligns them by steps and computes the mean and the 95% confidence interval of the
+//! learning curves across runs. Metrics are read from TensorBoard event files written by
+//! `border-tensorboard` or from an MLflow tracking server.
+//!
+//! ```no_run
+//! use border_analysis::{read_tfrecord_curve, write_svg, AggregatedCurve};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let curves = ["model/dqn_cartpole_0", "model/dqn_cartpole_1"]
+//!     .iter()
+//!     .map(|dir| read_tfrecord_curve(dir, "Episode return"))
+//!     .collect::<anyhow::Result<Vec<_>>>()?;
+//! let curves = vec![("dqn".to_string(), AggregatedCurve::new(&curves, Some(100))?)];
+//! AggregatedCurve::write_csv("dqn_cartpole.csv", &curves)?;
+//! write_svg("dqn_cartpole.svg", &curves, "Episode return")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The `border-compare` binary does the same from the command line. [`MultiSeedDriver`] trains
+//! a configuration with multiple seeds and reports the interquartile mean of the final scores
+//! with bootstrap confidence intervals, optionally as a parent run in MLflow.
+mod curve;
+mod mlflow;
+mod multi_seed;
+mod plot;
+mod tfrecord;
+pub use curve::{AggregatedCurve, LearningCurve};
+pub use mlflow::read_mlflow_curve;
+pub use multi_seed::{MultiSeedDriver, MultiSeedReport, ScoreSummary};
+pub use plot::write_svg;
+pub use tfrecord::read_tfrecord_curve;
@@ -0,0 +1,28 @@
+//! Reader of metrics logged in an MLflow tracking server.
+use crate::LearningCurve;
+use anyhow::Result;
+use border_mlflow_tracking::MlflowTrackingClient;
+
+/// Reads the history of metric `key` of the run named `run_name`.
+///
+/// The experiment must be set to `client` with [`MlflowTrackingClient::set_experiment()`].
+/// The name of the returned curve is `run_name`.
+///
+/// # Errors
+///
+/// Returns an error if no run or more than one run are found with the name.
+pub fn read_mlflow_curve(
+    client: &MlflowTrackingClient,
+    run_name: &str,
+    key: &str,
+) -> Result<LearningCurve> {
+    let runs = client.get_runs_by_name(run_name)?;
+    let run = match runs.as_slice() {
+        [run] => run,
+        [] => anyhow::bail!("Run {} not found", run_name),
+        _ => anyhow::bail!("More than one run named {}", run_name),
+    };
+    let points = client.get_metric_history(&run.info.run_id, key)?;
+
+    Ok(LearningCurve::new(run_name, points))
+}
@@ -0,0 +1,332 @@
+//! Training of a configuration across multiple seeds and aggregation of the results.
+//!
+//! The evaluation protocol recommended by [rliable](https://github.com/google-research/rliable)
+//! reports the interquartile mean (IQM) of scores over runs with bootstrap confidence intervals,
+//! rather than the mean of a few runs. [`MultiSeedDriver`] trains the same configuration
+//! with several seeds, either in this process or in child processes, and [`MultiSeedReport`]
+//! aggregates the final and intermediate evaluation scores of the runs.
+use crate::{AggregatedCurve, LearningCurve};
+use anyhow::{Context, Result};
+use border_mlflow_tracking::MlflowTrackingClient;
+use std::process::{Child, Command};
+
+/// Mean and interquartile mean of scores with their bootstrap 95% confidence intervals.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScoreSummary {
+    /// Mean of scores.
+    pub mean: f32,
+
+    /// 95% confidence interval of the mean.
+    pub mean_ci: (f32, f32),
+
+    /// Interquartile mean of scores, the mean of the middle 50% of scores.
+    pub iqm: f32,
+
+    /// 95% confidence interval of the interquartile mean.
+    pub iqm_ci: (f32, f32),
+}
+
+impl ScoreSummary {
+    /// Summarizes scores of runs grouped by tasks.
+    ///
+    /// The aggregates are computed over the scores of all tasks. Confidence intervals are
+    /// computed with the stratified bootstrap, which resamples runs within each task,
+    /// with `n_bootstrap` samples and a fixed seed for reproducibility.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no scores.
+    pub fn new(scores: &[Vec<f32>], n_bootstrap: usize) -> Result<Self> {
+        let all = scores.iter().flatten().copied().collect::<Vec<_>>();
+        if all.is_empty() {
+            anyhow::bail!("No scores to summarize");
+        }
+
+        let rng = fastrand::Rng::with_seed(42);
+        let (mut means, mut iqms) = (vec![], vec![]);
+        for _ in 0..n_bootstrap {
+            let mut sample = vec![];
+            for task in scores.iter() {
+                sample.extend((0..task.len()).map(|_| task[rng.usize(..task.len())]));
+            }
+            means.push(mean(&sample));
+            iqms.push(iqm(&sample));
+        }
+
+        Ok(Self {
+            mean: mean(&all),
+            mean_ci: percentile_interval(means).unwrap_or((mean(&all), mean(&all))),
+            iqm: iqm(&all),
+            iqm_ci: percentile_interval(iqms).unwrap_or((iqm(&all), iqm(&all))),
+        })
+    }
+}
+
+fn mean(xs: &[f32]) -> f32 {
+    xs.iter().sum::<f32>() / xs.len() as f32
+}
+
+/// Returns the mean of scores discarding the lowest and highest 25%.
+fn iqm(xs: &[f32]) -> f32 {
+    let mut xs = xs.to_vec();
+    xs.sort_by(|a, b| a.total_cmp(b));
+    let k = xs.len() / 4;
+    mean(&xs[k..xs.len() - k])
+}
+
+/// Returns the 2.5 and 97.5 percentiles.
+fn percentile_interval(mut xs: Vec<f32>) -> Option<(f32, f32)> {
+    if xs.is_empty() {
+        return None;
+    }
+    xs.sort_by(|a, b| a.total_cmp(b));
+    let at = |q: f32| xs[((xs.len() - 1) as f32 * q).round() as usize];
+    Some((at(0.025), at(0.975)))
+}
+
+/// Aggregated results of runs with different seeds.
+#[derive(Clone, Debug)]
+pub struct MultiSeedReport {
+    /// Seeds of the runs.
+    pub seeds: Vec<i64>,
+
+    /// Evaluation scores at the last step of each run.
+    pub final_scores: Vec<f32>,
+
+    /// Summary of the final scores.
+    pub final_summary: ScoreSummary,
+
+    /// Intermediate evaluation scores aggregated across runs.
+    pub curve: AggregatedCurve,
+}
+
+impl MultiSeedReport {
+    /// Logs the report as a parent run of the runs of the seeds in an MLflow tracking server.
+    ///
+    /// The parent run has the summary of the final scores as metrics `final/mean`,
+    /// `final/mean_lower`, `final/mean_upper`, `final/iqm`, `final/iqm_lower` and
+    /// `final/iqm_upper`, and the aggregated curve as metrics `mean`, `lower` and `upper`
+    /// over steps. The runs named `child_run_names` are tagged with `mlflow.parentRunId`,
+    /// so that they are nested under the parent run in the MLflow UI.
+    ///
+    /// The experiment must be set to `client`. Returns the ID of the parent run.
+    pub fn log_mlflow(
+        &self,
+        client: &MlflowTrackingClient,
+        run_name: &str,
+        child_run_names: &[String],
+    ) -> Result<String> {
+        let run_id = client.create_run(run_name)?.info.run_id;
+        let s = &self.final_summary;
+        let metrics = [
+            ("final/mean", s.mean),
+            ("final/mean_lower", s.mean_ci.0),
+            ("final/mean_upper", s.mean_ci.1),
+            ("final/iqm", s.iqm),
+            ("final/iqm_lower", s.iqm_ci.0),
+            ("final/iqm_upper", s.iqm_ci.1),
+        ];
+        for (key, value) in metrics.iter() {
+            client.log_metric(&run_id, key, *value, 0)?;
+        }
+        for (i, step) in self.curve.steps.iter().enumerate() {
+            client.log_metric(&run_id, "mean", self.curve.mean[i], *step)?;
+            client.log_metric(&run_id, "lower", self.curve.lower[i], *step)?;
+            client.log_metric(&run_id, "upper", self.curve.upper[i], *step)?;
+        }
+        let seeds = self.seeds.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        client.set_tag(&run_id, "seeds", seeds.join(","))?;
+
+        for name in child_run_names.iter() {
+            for run in client.get_runs_by_name(name)?.iter() {
+                client.set_tag(&run.info.run_id, "mlflow.parentRunId", &run_id)?;
+            }
+        }
+        client.finish_run(&run_id)?;
+
+        Ok(run_id)
+    }
+}
+
+/// Trains a configuration with multiple seeds and aggregates the evaluation scores.
+///
+/// Each run provides its evaluation scores as a [`LearningCurve`], e.g., read with
+/// [`read_mlflow_curve()`] or [`read_tfrecord_curve()`] after training.
+///
+/// ```no_run
+/// use border_analysis::{read_tfrecord_curve, MultiSeedDriver};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// // Runs `dqn_cartpole --seed <seed>` in 4 parallel processes
+/// let report = MultiSeedDriver::new((0..8).collect()).max_parallel(4).run_processes(
+///     "dqn_cartpole",
+///     &["--seed", "{seed}"],
+///     |seed| read_tfrecord_curve(format!("model/dqn_cartpole_{}", seed), "Episode return"),
+/// )?;
+/// println!("IQM = {}", report.final_summary.iqm);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`read_mlflow_curve()`]: crate::read_mlflow_curve
+/// [`read_tfrecord_curve()`]: crate::read_tfrecord_curve
+pub struct MultiSeedDriver {
+    seeds: Vec<i64>,
+    max_parallel: usize,
+    n_points: Option<usize>,
+    n_bootstrap: usize,
+}
+
+impl MultiSeedDriver {
+    /// Constructs a driver running the given seeds one at a time.
+    pub fn new(seeds: Vec<i64>) -> Self {
+        Self {
+            seeds,
+            max_parallel: 1,
+            n_points: None,
+            n_bootstrap: 2000,
+        }
+    }
+
+    /// Sets the maximum number of processes running in parallel in
+    /// [`MultiSeedDriver::run_processes()`].
+    pub fn max_parallel(mut self, v: usize) -> Self {
+        self.max_parallel = v.max(1);
+        self
+    }
+
+    /// Sets the number of evenly spaced steps of the aggregated curve.
+    pub fn n_points(mut self, v: Option<usize>) -> Self {
+        self.n_points = v;
+        self
+    }
+
+    /// Sets the number of bootstrap samples for confidence intervals.
+    pub fn n_bootstrap(mut self, v: usize) -> Self {
+        self.n_bootstrap = v;
+        self
+    }
+
+    /// Trains with `train` for each seed sequentially in this process.
+    ///
+    /// `train` returns the evaluation scores of the run.
+    pub fn run(
+        &self,
+        mut train: impl FnMut(i64) -> Result<LearningCurve>,
+    ) -> Result<MultiSeedReport> {
+        let mut curves = vec![];
+        for seed in self.seeds.iter() {
+            log::info!("Start training with seed {}", seed);
+            curves.push(train(*seed)?);
+        }
+        self.report(curves)
+    }
+
+    /// Trains in child processes running `program` with `args`, where `{seed}` in the arguments
+    /// is replaced with the seed.
+    ///
+    /// At most `max_parallel` processes run at the same time. After all processes exit,
+    /// the evaluation scores of each seed are obtained with `read`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the processes fails.
+    pub fn run_processes(
+        &self,
+        program: &str,
+        args: &[&str],
+        mut read: impl FnMut(i64) -> Result<LearningCurve>,
+    ) -> Result<MultiSeedReport> {
+        let mut running: Vec<(i64, Child)> = vec![];
+        let mut failed = vec![];
+        let mut wait_first = |running: &mut Vec<(i64, Child)>| -> Result<()> {
+            let (seed, mut child) = running.remove(0);
+            if !child.wait()?.success() {
+                failed.push(seed);
+            }
+            Ok(())
+        };
+
+        for seed in self.seeds.iter() {
+            if running.len() >= self.max_parallel {
+                wait_first(&mut running)?;
+            }
+            let args = args
+                .iter()
+                .map(|a| a.replace("{seed}", &seed.to_string()))
+                .collect::<Vec<_>>();
+            log::info!("Start {} {:?}", program, args);
+            let child = Command::new(program)
+                .args(&args)
+                .spawn()
+                .with_context(|| format!("Failed to run {}", program))?;
+            running.push((*seed, child));
+        }
+        while !running.is_empty() {
+            wait_first(&mut running)?;
+        }
+        if !failed.is_empty() {
+            anyhow::bail!("Training failed with seeds {:?}", failed);
+        }
+
+        let curves = self
+            .seeds
+            .iter()
+            .map(|seed| read(*seed))
+            .collect::<Result<Vec<_>>>()?;
+        self.report(curves)
+    }
+
+    /// Aggregates the evaluation scores of the runs of the seeds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the curves is empty or their steps do not overlap.
+    pub fn report(&self, curves: Vec<LearningCurve>) -> Result<MultiSeedReport> {
+        if curves.iter().any(|c| c.points.is_empty()) {
+            anyhow::bail!("Learning curves must not be empty");
+        }
+        let final_scores = curves
+            .iter()
+            .map(|c| c.points[c.points.len() - 1].1)
+            .collect::<Vec<_>>();
+
+        Ok(MultiSeedReport {
+            seeds: self.seeds.clone(),
+            final_summary: ScoreSummary::new(
+                std::slice::from_ref(&final_scores),
+                self.n_bootstrap,
+            )?,
+            final_scores,
+            curve: AggregatedCurve::new(&curves, self.n_points)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_score_summary() -> Result<()> {
+        let scores = vec![vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 100.0]];
+        let s = ScoreSummary::new(&scores, 1000)?;
+        assert_eq!(s.mean, 121.0 / 8.0);
+        // 0, 1, 6 and 100 are discarded
+        assert_eq!(s.iqm, 3.5);
+        assert!(s.iqm_ci.0 <= s.iqm && s.iqm <= s.iqm_ci.1);
+        assert!(s.mean_ci.1 - s.mean_ci.0 > s.iqm_ci.1 - s.iqm_ci.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_run() -> Result<()> {
+        let driver = MultiSeedDriver::new(vec![1, 2, 3]);
+        let report =
+            driver.run(|seed| Ok(LearningCurve::new("", vec![(0, 0.0), (10, seed as f32)])))?;
+        assert_eq!(report.final_scores, vec![1.0, 2.0, 3.0]);
+        assert_eq!(report.final_summary.mean, 2.0);
+        assert_eq!(report.curve.mean, vec![0.0, 2.0]);
+        Ok(())
+    }
+}
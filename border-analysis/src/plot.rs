@@ -0,0 +1,146 @@
+//! Plot of aggregated learning curves in SVG.
+use crate::AggregatedCurve;
+use anyhow::Result;
+use std::{fmt::Write, fs, path::Path};
+
+const WIDTH: f32 = 640.0;
+const HEIGHT: f32 = 400.0;
+const MARGIN: f32 = 60.0;
+const COLORS: [&str; 8] = [
+    "#1f77b4", "#ff7f0e", "#2ca02c", "#d62728", "#9467bd", "#8c564b", "#e377c2", "#7f7f7f",
+];
+
+/// Writes a plot of aggregated curves as an SVG file.
+///
+/// For each curve, the mean is drawn as a line and the 95% confidence interval
+/// as a shaded band.
+pub fn write_svg(
+    path: impl AsRef<Path>,
+    curves: &[(String, AggregatedCurve)],
+    ylabel: &str,
+) -> Result<()> {
+    let steps = curves.iter().flat_map(|(_, c)| c.steps.iter().copied());
+    let (x_min, x_max) = match (steps.clone().min(), steps.max()) {
+        (Some(min), Some(max)) => (min as f32, max as f32),
+        _ => anyhow::bail!("No points to plot"),
+    };
+    let values = curves
+        .iter()
+        .flat_map(|(_, c)| c.lower.iter().chain(c.upper.iter()).copied())
+        .filter(|v| v.is_finite());
+    let y_min = values.clone().fold(f32::INFINITY, f32::min);
+    let y_max = values.fold(f32::NEG_INFINITY, f32::max);
+
+    // Avoid zero ranges
+    let (x_min, x_max) = if x_min < x_max {
+        (x_min, x_max)
+    } else {
+        (x_min - 1.0, x_max + 1.0)
+    };
+    let (y_min, y_max) = if y_min < y_max {
+        (y_min, y_max)
+    } else {
+        (y_min - 1.0, y_max + 1.0)
+    };
+    let x = |s: i64| MARGIN + (s as f32 - x_min) / (x_max - x_min) * (WIDTH - 2.0 * MARGIN);
+    let y = |v: f32| HEIGHT - MARGIN - (v - y_min) / (y_max - y_min) * (HEIGHT - 2.0 * MARGIN);
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="sans-serif" font-size="12">"#,
+        WIDTH, HEIGHT
+    )?;
+    writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#)?;
+
+    // Axes and labels
+    writeln!(
+        svg,
+        r#"<path d="M{m},{m} V{b} H{r}" fill="none" stroke="black"/>"#,
+        m = MARGIN,
+        b = HEIGHT - MARGIN,
+        r = WIDTH - MARGIN
+    )?;
+    for (v, anchor, px, py) in [
+        (x_min.to_string(), "start", MARGIN, HEIGHT - MARGIN + 16.0),
+        (
+            x_max.to_string(),
+            "end",
+            WIDTH - MARGIN,
+            HEIGHT - MARGIN + 16.0,
+        ),
+        (
+            format!("{:.3}", y_min),
+            "end",
+            MARGIN - 4.0,
+            HEIGHT - MARGIN,
+        ),
+        (format!("{:.3}", y_max), "end", MARGIN - 4.0, MARGIN + 4.0),
+        (
+            "step".to_string(),
+            "middle",
+            WIDTH / 2.0,
+            HEIGHT - MARGIN + 32.0,
+        ),
+        (ylabel.to_string(), "middle", WIDTH / 2.0, MARGIN - 24.0),
+    ]
+    .iter()
+    {
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" text-anchor="{}">{}</text>"#,
+            px,
+            py,
+            anchor,
+            escape(v)
+        )?;
+    }
+
+    // Curves and legend
+    for (i, (name, c)) in curves.iter().enumerate() {
+        let color = COLORS[i % COLORS.len()];
+        let upper = c.steps.iter().zip(c.upper.iter());
+        let lower = c.steps.iter().zip(c.lower.iter()).rev();
+        let band = upper
+            .chain(lower)
+            .map(|(s, v)| format!("{:.1},{:.1}", x(*s), y(*v)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let mean = c
+            .steps
+            .iter()
+            .zip(c.mean.iter())
+            .map(|(s, v)| format!("{:.1},{:.1}", x(*s), y(*v)))
+            .collect::<Vec<_>>()
+            .join(" ");
+        writeln!(
+            svg,
+            r#"<polygon points="{}" fill="{}" fill-opacity="0.2" stroke="none"/>"#,
+            band, color
+        )?;
+        writeln!(
+            svg,
+            r#"<polyline points="{}" fill="none" stroke="{}" stroke-width="1.5"/>"#,
+            mean, color
+        )?;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" fill="{}">{} (n={})</text>"#,
+            MARGIN + 8.0,
+            MARGIN + 16.0 * (i + 1) as f32,
+            color,
+            escape(name),
+            c.n_runs
+        )?;
+    }
+    writeln!(svg, "</svg>")?;
+
+    fs::write(path, svg)?;
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,195 @@
+//! Reader of scalar summaries in TensorBoard event files.
+//!
+//! Only the fields of `Event` messages required to read scalars written by `border-tensorboard`
+//! are decoded, other fields are skipped.
+use crate::LearningCurve;
+use anyhow::{Context, Result};
+use prost::Message;
+use std::{
+    convert::{TryFrom, TryInto},
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Size of the length of a record and its CRC in bytes.
+const HEADER_LEN: usize = 12;
+
+/// Size of the CRC of the data of a record in bytes.
+const FOOTER_LEN: usize = 4;
+
+/// `Event` in `tensorflow/core/util/event.proto`.
+#[derive(Clone, PartialEq, prost::Message)]
+struct Event {
+    #[prost(double, tag = "1")]
+    wall_time: f64,
+
+    #[prost(int64, tag = "2")]
+    step: i64,
+
+    #[prost(message, optional, tag = "5")]
+    summary: Option<Summary>,
+}
+
+/// `Summary` in `tensorflow/core/framework/summary.proto`.
+#[derive(Clone, PartialEq, prost::Message)]
+struct Summary {
+    #[prost(message, repeated, tag = "1")]
+    value: Vec<SummaryValue>,
+}
+
+/// `Summary.Value`, where only scalars are decoded.
+#[derive(Clone, PartialEq, prost::Message)]
+struct SummaryValue {
+    #[prost(string, tag = "1")]
+    tag: String,
+
+    #[prost(float, optional, tag = "2")]
+    simple_value: Option<f32>,
+}
+
+/// Returns the step and the value of `tag` in an `Event` message, if any.
+fn parse_event(buf: &[u8], tag: &str) -> Result<Option<(i64, f32)>> {
+    let event = Event::decode(buf)?;
+    let value = event
+        .summary
+        .iter()
+        .flat_map(|s| s.value.iter())
+        .filter(|v| v.tag == tag)
+        .filter_map(|v| v.simple_value)
+        .next_back();
+    Ok(value.map(|v| (event.step, v)))
+}
+
+/// Reads the values of `tag` in a TFRecord file.
+///
+/// Returns an error if the file ends in the middle of a record.
+fn read_file(path: &Path, tag: &str) -> Result<Vec<(i64, f32)>> {
+    let buf = fs::read(path).with_context(|| format!("Failed to read {:?}", path))?;
+    let mut points = vec![];
+    let mut pos = 0;
+
+    // Each record consists of the length (u64), its CRC (u32), data and its CRC (u32).
+    // CRCs are not checked.
+    while pos < buf.len() {
+        let truncated = || format!("Truncated record at {} in {:?}", pos, path);
+        let len = buf
+            .get(pos..pos + 8)
+            .with_context(truncated)?
+            .try_into()
+            .map(u64::from_le_bytes)?;
+        let start = pos + HEADER_LEN;
+        let end = usize::try_from(len)
+            .ok()
+            .and_then(|len| start.checked_add(len))
+            .with_context(|| format!("Invalid length {} at {} in {:?}", len, pos, path))?;
+        let data = match end.checked_add(FOOTER_LEN) {
+            Some(next) if next <= buf.len() => &buf[start..end],
+            _ => anyhow::bail!(truncated()),
+        };
+        if let Some(p) = parse_event(data, tag)? {
+            points.push(p);
+        }
+        pos = end + FOOTER_LEN;
+    }
+
+    Ok(points)
+}
+
+/// Reads the values of a scalar logged with `tag` from event files in `log_dir`.
+///
+/// All files with `tfevents` in their names are read in lexicographic order of the names.
+/// The name of the returned curve is that of the directory.
+pub fn read_tfrecord_curve(log_dir: impl AsRef<Path>, tag: &str) -> Result<LearningCurve> {
+    let log_dir = log_dir.as_ref();
+    let mut files = fs::read_dir(log_dir)
+        .with_context(|| format!("Failed to read directory {:?}", log_dir))?
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.file_name()
+                .is_some_and(|n| n.to_string_lossy().contains("tfevents"))
+        })
+        .collect::<Vec<PathBuf>>();
+    files.sort();
+    if files.is_empty() {
+        anyhow::bail!("No event files in {:?}", log_dir);
+    }
+
+    let mut points = vec![];
+    for file in files.iter() {
+        points.extend(read_file(file, tag)?);
+    }
+    let name = log_dir
+        .file_name()
+        .map_or(log_dir.to_string_lossy(), |n| n.to_string_lossy());
+
+    Ok(LearningCurve::new(name, points))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use tempdir::TempDir;
+
+    fn event(step: i64, tag: &str, value: f32) -> Vec<u8> {
+        Event {
+            wall_time: 0.0,
+            step,
+            summary: Some(Summary {
+                value: vec![SummaryValue {
+                    tag: tag.to_string(),
+                    simple_value: Some(value),
+                }],
+            }),
+        }
+        .encode_to_vec()
+    }
+
+    fn write_records(path: &Path) -> Result<()> {
+        let mut file = fs::File::create(path)?;
+        let events = [
+            (1, "eval/return", 1.5),
+            (1, "loss", 0.1),
+            (300, "eval/return", 2.5),
+        ];
+        for &(step, tag, value) in events.iter() {
+            let data = event(step, tag, value);
+            file.write_all(&(data.len() as u64).to_le_bytes())?;
+            file.write_all(&[0; 4])?;
+            file.write_all(&data)?;
+            file.write_all(&[0; 4])?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_tfrecord_curve() -> Result<()> {
+        let dir = TempDir::new("tfrecord")?;
+        write_records(&dir.path().join("events.out.tfevents.0"))?;
+
+        let curve = read_tfrecord_curve(dir.path(), "eval/return")?;
+        assert_eq!(curve.points, vec![(1, 1.5), (300, 2.5)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_truncated_file() -> Result<()> {
+        let dir = TempDir::new("tfrecord")?;
+        let path = dir.path().join("events.out.tfevents.0");
+        write_records(&path)?;
+        let bytes = fs::read(&path)?;
+
+        // Cut in the middle of the data and of the header of the last record
+        for len in [bytes.len() - 1, bytes.len() - 10, 3].iter() {
+            fs::write(&path, &bytes[..*len])?;
+            assert!(read_file(&path, "eval/return").is_err());
+        }
+
+        // The length of the first record is garbage
+        let mut garbage = bytes.clone();
+        garbage[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        fs::write(&path, &garbage)?;
+        assert!(read_file(&path, "eval/return").is_err());
+        Ok(())
+    }
+}
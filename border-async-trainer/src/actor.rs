@@ -2,4 +2,5 @@
 mod base;
 mod stat;
 pub use base::Actor;
+pub(crate) use base::PrioritiesFn;
 pub use stat::{actor_stats_fmt, ActorStat};
@@ -1,6 +1,8 @@
 use crate::{ActorStat, PushedItemMessage, ReplayBufferProxy, ReplayBufferProxyConfig, SyncModel};
+use anyhow::Result;
 use border_core::{
-    Agent, Configurable, Env, ExperienceBufferBase, ReplayBufferBase, Sampler, StepProcessor,
+    generic_replay_buffer::BatchBase, Agent, Configurable, Env, ExperienceBufferBase,
+    ReplayBufferBase, Sampler, StepProcessor, TransitionBatch,
 };
 use crossbeam_channel::Sender;
 use log::{debug, info};
@@ -10,6 +12,10 @@ use std::{
     sync::{Arc, Mutex},
 };
 
+/// Function computing the priorities of the transitions in samples with an agent.
+pub(crate) type PrioritiesFn<A, R> =
+    fn(&mut A, &[<R as ExperienceBufferBase>::Item]) -> Result<Option<Vec<Vec<f32>>>>;
+
 #[cfg_attr(doc, aquamarine::aquamarine)]
 /// Generate transitions by running [`Agent`] in [`Env`].
 ///
@@ -29,6 +35,10 @@ use std::{
 /// The [`Agent`] in the [`Actor`] periodically synchronizes with the [`Agent`] in
 /// [`AsyncTrainer`] via [`SyncModel::ModelInfo`].
 ///
+/// For prioritized experience replay, the [`Agent`] in the [`Actor`] can compute the initial
+/// priorities of the transitions with [`Agent::priorities()`], which are sent with them.
+/// See [`ActorManager::with_priorities()`](crate::ActorManager::with_priorities).
+///
 /// See also the diagram in [`AsyncTrainer`].
 ///
 /// [`AsyncTrainer`]: crate::AsyncTrainer
@@ -52,6 +62,7 @@ where
     replay_buffer_config: ReplayBufferProxyConfig,
     env_seed: i64,
     stats: Arc<Mutex<Option<ActorStat>>>,
+    priorities_fn: Option<PrioritiesFn<A, R>>,
     phantom: PhantomData<(A, E, P, R)>,
 }
 
@@ -82,10 +93,18 @@ where
             replay_buffer_config: replay_buffer_config.clone(),
             env_seed,
             stats,
+            priorities_fn: None,
             phantom: PhantomData,
         }
     }
 
+    /// Sets the function computing the initial priorities of samples.
+    ///
+    /// If `None`, samples are sent without priorities.
+    pub(crate) fn set_priorities_fn(&mut self, priorities_fn: Option<PrioritiesFn<A, R>>) {
+        self.priorities_fn = priorities_fn;
+    }
+
     fn sync_model_first(agent: &mut A, model_info: &Arc<Mutex<(usize, A::ModelInfo)>>, id: usize) {
         let model_info = model_info.lock().unwrap();
         agent.sync_model(&model_info.1);
@@ -125,8 +144,12 @@ where
         guard_init_model: Arc<Mutex<bool>>,
     ) {
         let mut agent: Box<dyn Agent<E, R>> = Box::new(A::build(self.agent_config.clone()));
+        let replay_buffer_config = ReplayBufferProxyConfig {
+            compute_priorities: self.priorities_fn.is_some(),
+            ..self.replay_buffer_config.clone()
+        };
         let mut buffer =
-            ReplayBufferProxy::<R>::build_with_sender(self.id, &self.replay_buffer_config, sender);
+            ReplayBufferProxy::<R>::build_with_sender(self.id, &replay_buffer_config, sender);
         let mut sampler = {
             let mut tmp = guard.lock().unwrap();
             let env = E::build(&self.env_config, self.env_seed).unwrap();
@@ -165,6 +188,16 @@ where
             let _record = sampler.sample_and_push(&mut agent, &mut buffer).unwrap();
             env_steps += 1;
 
+            // Sends samples with the initial priorities computed with the agent
+            if let Some(priorities_fn) = self.priorities_fn {
+                if buffer.is_full() {
+                    // TODO: error handling
+                    let agent = Self::downcast_mut(&mut agent);
+                    let priorities = priorities_fn(agent, buffer.items()).unwrap();
+                    buffer.flush(priorities).unwrap();
+                }
+            }
+
             // Stop sampling loop
             if *self.stop.lock().unwrap() {
                 *self.stats.lock().unwrap() = Some(ActorStat {
@@ -177,3 +210,45 @@ where
         info!("Stopped thread for actor {}", self.id);
     }
 }
+
+impl<A, E, P, R> Actor<A, E, P, R>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+    R::Item: BatchBase + TransitionBatch + Clone + Into<R::Batch>,
+{
+    /// Computes the priorities of the transitions in samples with [`Agent::priorities()`].
+    ///
+    /// The samples are concatenated into a batch, which is processed by the agent at once.
+    /// The priorities are split into those of the transitions in each sample.
+    pub(crate) fn priorities(agent: &mut A, items: &[R::Item]) -> Result<Option<Vec<Vec<f32>>>> {
+        let lens = items.iter().map(|item| item.len()).collect::<Vec<_>>();
+        let mut batch = R::Item::new(lens.iter().sum());
+        let mut ix = 0;
+        for (item, len) in items.iter().zip(lens.iter()) {
+            batch.push(ix, item.clone());
+            ix += len;
+        }
+
+        let priorities = match agent.priorities(batch.into())? {
+            Some(priorities) => priorities,
+            None => return Ok(None),
+        };
+        if priorities.len() != ix {
+            anyhow::bail!(
+                "{} priorities are computed for {} transitions",
+                priorities.len(),
+                ix
+            );
+        }
+
+        let mut priorities = priorities.into_iter();
+        Ok(Some(
+            lens.iter()
+                .map(|&len| priorities.by_ref().take(len).collect())
+                .collect(),
+        ))
+    }
+}
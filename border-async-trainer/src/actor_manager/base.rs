@@ -1,8 +1,10 @@
 use crate::{
-    Actor, ActorManagerConfig, ActorStat, PushedItemMessage, ReplayBufferProxyConfig, SyncModel,
+    actor::PrioritiesFn, Actor, ActorManagerConfig, ActorStat, PushedItemMessage,
+    ReplayBufferProxyConfig, SyncModel,
 };
 use border_core::{
-    Agent, Configurable, Env, ExperienceBufferBase, ReplayBufferBase, StepProcessor,
+    generic_replay_buffer::BatchBase, Agent, Configurable, Env, ExperienceBufferBase,
+    ReplayBufferBase, StepProcessor, TransitionBatch,
 };
 use crossbeam_channel::{bounded, /*unbounded,*/ Receiver, Sender};
 use log::info;
@@ -44,6 +46,9 @@ where
     /// This parameter is used as `n_buffer` in [`ReplayBufferProxyConfig`].
     n_buffer: usize,
 
+    /// Function with which actors compute the initial priorities of samples.
+    priorities_fn: Option<PrioritiesFn<A, R>>,
+
     /// Flag to stop training
     stop: Arc<Mutex<bool>>,
 
@@ -94,6 +99,7 @@ where
             env_config: env_config.clone(),
             step_proc_config: step_proc_config.clone(),
             n_buffer: config.n_buffer,
+            priorities_fn: None,
             stop,
             threads: vec![],
             batch_message_receiver: None,
@@ -146,7 +152,9 @@ where
                 let sender = s.clone();
                 let replay_buffer_proxy_config = ReplayBufferProxyConfig {
                     n_buffer: self.n_buffer,
+                    compute_priorities: false,
                 };
+                let priorities_fn = self.priorities_fn;
                 let env_config = self.env_config.clone();
                 let step_proc_config = self.step_proc_config.clone();
                 let stop = self.stop.clone();
@@ -159,7 +167,7 @@ where
 
                 // Spawn actor thread
                 let handle = std::thread::spawn(move || {
-                    Actor::<A, E, P, R>::build(
+                    let mut actor = Actor::<A, E, P, R>::build(
                         id,
                         agent_config,
                         env_config,
@@ -168,8 +176,9 @@ where
                         stop,
                         seed as i64,
                         stats,
-                    )
-                    .run(sender, model_info, guard, guard_init_model);
+                    );
+                    actor.set_priorities_fn(priorities_fn);
+                    actor.run(sender, model_info, guard, guard_init_model);
                 });
                 self.threads.push(handle);
             });
@@ -243,12 +252,13 @@ where
         guard_init_model: Arc<Mutex<bool>>,
     ) {
         // Blocks threads sharing model_info until arriving the first message from AsyncTrainer.
+        // It is not necessarily the initial model in the networked mode, where actors can join
+        // after training started.
         {
             let mut guard_init_model = guard_init_model.lock().unwrap();
             let mut model_info = model_info.lock().unwrap();
             // TODO: error handling
             let msg = model_info_receiver.recv().unwrap();
-            assert_eq!(msg.0, 0);
             *model_info = msg;
             *guard_init_model = true;
         }
@@ -265,3 +275,27 @@ where
         info!("Stopped model info thread");
     }
 }
+
+impl<A, E, R, P> ActorManager<A, E, R, P>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    P: StepProcessor<E>,
+    R: ExperienceBufferBase<Item = P::Output> + Send + 'static + ReplayBufferBase,
+    A::Config: Send + 'static,
+    E::Config: Send + 'static,
+    P::Config: Send + 'static,
+    R::Item: BatchBase + TransitionBatch + Clone + Into<R::Batch> + Send + 'static,
+    A::ModelInfo: Send + 'static,
+{
+    /// Makes [`Actor`]s compute the initial priorities of samples with
+    /// [`Agent::priorities()`] for prioritized experience replay.
+    ///
+    /// The samples buffered in an actor are concatenated and processed by the agent at once
+    /// before being sent. Without this, the samples get the maximum priority in the replay
+    /// buffer.
+    pub fn with_priorities(mut self) -> Self {
+        self.priorities_fn = Some(Actor::<A, E, P, R>::priorities);
+        self
+    }
+}
@@ -46,6 +46,9 @@ use std::{
 /// * [`ReplayBufferProxy`] has a type parameter of [`ReplayBufferBase`] and the proxy accepts
 ///   [`ReplayBufferBase::Item`].
 /// * The proxy sends the transitions into the replay buffer in the [`AsyncTrainer`].
+/// * With prioritized experience replay, the transitions are pushed with the priorities
+///   computed in [`Actor`]s if any. Their priorities are updated with TD errors when
+///   the [`Agent`] is optimized, as in non-asynchronous training.
 ///
 /// [`ActorManager`]: crate::ActorManager
 /// [`Actor`]: crate::Actor
@@ -277,9 +280,19 @@ where
         msgs.into_iter().for_each(|msg| {
             self.samples_counter += msg.pushed_items.len();
             *samples_total += msg.pushed_items.len();
-            msg.pushed_items
-                .into_iter()
-                .for_each(|pushed_item| buffer.push(pushed_item).unwrap())
+            match msg.priorities {
+                None => msg
+                    .pushed_items
+                    .into_iter()
+                    .for_each(|pushed_item| buffer.push(pushed_item).unwrap()),
+                Some(priorities) => msg.pushed_items.into_iter().zip(priorities).for_each(
+                    |(pushed_item, priorities)| {
+                        buffer
+                            .push_with_priorities(pushed_item, &priorities)
+                            .unwrap()
+                    },
+                ),
+            }
         });
     }
 
@@ -353,6 +366,7 @@ where
                 record.insert("average_opt_time", Scalar(avr_opt_time));
                 record.insert("average_sample_time", Scalar(avr_sample_time));
                 self.reset_counters();
+                record = record.merge(buffer.record());
             }
 
             // Store record to the recorder
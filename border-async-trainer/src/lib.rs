@@ -156,6 +156,12 @@
 //! Both [`AsyncTrainer`] and [`ActorManager`] are running in the same machine and
 //! communicate by channels.
 //!
+//! In the networked mode, [`ActorManager`]s run in other processes, possibly on other
+//! machines, and communicate with [`AsyncTrainer`] over TCP through [`ActorClient`] and
+//! [`ActorServer`]. See [`util::train_async_server`] and [`util::run_remote_actors`].
+//! The connections are not authenticated, so the server should listen on a loopback
+//! address unless the network is trusted.
+//!
 //! [`Agent`]: border_core::Agent
 //! [`Env`]: border_core::Env
 mod actor;
@@ -163,6 +169,7 @@ mod actor_manager;
 mod async_trainer;
 mod error;
 mod messages;
+mod remote;
 mod replay_buffer_proxy;
 mod sync_model;
 pub mod util;
@@ -172,6 +179,9 @@ pub use actor_manager::{ActorManager, ActorManagerConfig};
 pub use async_trainer::{AsyncTrainStat, AsyncTrainer, AsyncTrainerConfig};
 pub use error::BorderAsyncTrainerError;
 pub use messages::PushedItemMessage;
+pub use remote::{
+    ActorClient, ActorServer, RemoteConfig, DEFAULT_MAX_FRAME_SIZE, DEFAULT_WRITE_TIMEOUT_MS,
+};
 pub use replay_buffer_proxy::{ReplayBufferProxy, ReplayBufferProxyConfig};
 pub use sync_model::SyncModel;
 
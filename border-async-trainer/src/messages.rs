@@ -1,10 +1,22 @@
+use serde::{Deserialize, Serialize};
+
 /// Message containing a [`ReplayBufferBase`](border_core::ReplayBufferBase)`::Item`.
 ///
 /// It will be sent from [`Actor`](crate::Actor) to [`ActorManager`](crate::ActorManager).
+/// In the networked mode, it is serialized and sent from [`ActorClient`](crate::ActorClient)
+/// to [`ActorServer`](crate::ActorServer).
+#[derive(Serialize, Deserialize)]
 pub struct PushedItemMessage<T> {
     /// ID of [`Actor`](crate::Actor) which generates samples (`pushed_item`).
     pub id: usize,
 
     /// A batch.
     pub pushed_items: Vec<T>,
+
+    /// Priorities of the transitions in each item of `pushed_items`, computed in
+    /// [`Actor`](crate::Actor) for prioritized experience replay.
+    ///
+    /// If `None`, the transitions get the maximum priority in the replay buffer.
+    #[serde(default)]
+    pub priorities: Option<Vec<Vec<f32>>>,
 }
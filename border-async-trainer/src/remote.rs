@@ -0,0 +1,580 @@
+//! Networked mode of asynchronous training.
+//!
+//! In this mode, [`Actor`]s run in processes other than that of [`AsyncTrainer`], possibly on
+//! other machines, and communicate with it over TCP:
+//!
+//! * [`ActorServer`] runs in the process of [`AsyncTrainer`] in place of [`ActorManager`].
+//!   It accepts connections from [`ActorClient`]s, forwards [`PushedItemMessage`]s received
+//!   from them to [`AsyncTrainer`] and broadcasts [`SyncModel::ModelInfo`] to them.
+//! * [`ActorClient`] runs in a process with an [`ActorManager`]. It sends
+//!   [`PushedItemMessage`]s from the [`ActorManager`] to [`ActorServer`] and passes
+//!   [`SyncModel::ModelInfo`] received from it to the [`ActorManager`].
+//!
+//! Messages are serialized with [`bincode`] and sent as frames prefixed by their length.
+//! Thus, [`ExperienceBufferBase::Item`] and [`SyncModel::ModelInfo`] are required to implement
+//! [`Serialize`] and [`Deserialize`]. When an [`ActorClient`] connects to [`ActorServer`],
+//! the latest model information is sent to it, so actors can join after training started.
+//! When training finishes, [`ActorServer`] closes the connections, which stops the
+//! [`ActorManager`]s in the remote processes.
+//!
+//! The protocol is neither authenticated nor encrypted. Any process that can connect to
+//! [`ActorServer`] can push samples into the replay buffer and receive the model parameters.
+//! Listen on a loopback address, e.g., `127.0.0.1:50051`, unless the network is trusted.
+//! A frame longer than `max_frame_size` bytes is rejected before a buffer is allocated for it,
+//! which closes the connection. [`DEFAULT_MAX_FRAME_SIZE`] is enough for the samples and the
+//! model parameters of typical Atari agents. A peer which does not read a frame within
+//! `write_timeout_ms` is dropped, so a stalled actor does not block model sync of the others.
+//! These are given with [`RemoteConfig`].
+//!
+//! [`Actor`]: crate::Actor
+//! [`ActorManager`]: crate::ActorManager
+//! [`AsyncTrainer`]: crate::AsyncTrainer
+//! [`SyncModel::ModelInfo`]: crate::SyncModel::ModelInfo
+//! [`ExperienceBufferBase::Item`]: border_core::ExperienceBufferBase::Item
+//! [`Deserialize`]: serde::Deserialize
+use crate::PushedItemMessage;
+use anyhow::{bail, Result};
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use log::{info, warn};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    io::{ErrorKind, Read, Write},
+    net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+/// Interval of polling the stop flag.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Default value of the maximum size of a frame in bytes, 256 MiB.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 256 * 1024 * 1024;
+
+/// Default value of the timeout of writing a frame in milliseconds, 10 seconds.
+pub const DEFAULT_WRITE_TIMEOUT_MS: u64 = 10_000;
+
+/// Configuration of the connections between [`ActorServer`] and [`ActorClient`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RemoteConfig {
+    /// Address on which [`ActorServer`] listens and to which [`ActorClient`] connects.
+    ///
+    /// Use a loopback address, e.g., `127.0.0.1:50051`, unless the network is trusted,
+    /// as connections are not authenticated.
+    pub addr: String,
+
+    /// Maximum size of a frame received from the peer in bytes.
+    pub max_frame_size: usize,
+
+    /// Timeout of writing a frame to the peer in milliseconds, which must be positive.
+    ///
+    /// The connection is closed if the peer does not read the frame within it.
+    pub write_timeout_ms: u64,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        Self {
+            addr: "127.0.0.1:50051".to_string(),
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            write_timeout_ms: DEFAULT_WRITE_TIMEOUT_MS,
+        }
+    }
+}
+
+impl RemoteConfig {
+    /// Sets the address.
+    pub fn addr(mut self, v: impl Into<String>) -> Self {
+        self.addr = v.into();
+        self
+    }
+
+    /// Sets the maximum size of a received frame in bytes.
+    pub fn max_frame_size(mut self, v: usize) -> Self {
+        self.max_frame_size = v;
+        self
+    }
+
+    /// Sets the timeout of writing a frame in milliseconds.
+    pub fn write_timeout_ms(mut self, v: u64) -> Self {
+        self.write_timeout_ms = v;
+        self
+    }
+
+    /// Returns the write timeout, or an error if it is zero.
+    fn write_timeout(&self) -> Result<Duration> {
+        if self.write_timeout_ms == 0 {
+            bail!("write_timeout_ms must be positive");
+        }
+        Ok(Duration::from_millis(self.write_timeout_ms))
+    }
+}
+
+/// Writes a message as a frame prefixed by its length in bytes.
+fn write_frame<T: Serialize>(stream: &mut TcpStream, msg: &T) -> Result<()> {
+    let body = bincode::serialize(msg)?;
+    let mut buf = Vec::with_capacity(8 + body.len());
+    buf.extend_from_slice(&(body.len() as u64).to_le_bytes());
+    buf.extend_from_slice(&body);
+    stream.write_all(&buf)?;
+    Ok(())
+}
+
+/// Reads a message written with [`write_frame()`].
+///
+/// Returns an error if the frame is longer than `max_frame_size` bytes.
+fn read_frame<T: DeserializeOwned>(stream: &mut TcpStream, max_frame_size: usize) -> Result<T> {
+    let mut len = [0u8; 8];
+    stream.read_exact(&mut len)?;
+    let len = u64::from_le_bytes(len);
+    if len > max_frame_size as u64 {
+        bail!(
+            "Frame of {} bytes exceeds the maximum size of {} bytes",
+            len,
+            max_frame_size
+        );
+    }
+    let mut buf = vec![0u8; len as usize];
+    stream.read_exact(&mut buf)?;
+    Ok(bincode::deserialize(&buf)?)
+}
+
+/// Accepts [`ActorClient`]s in the process of [`AsyncTrainer`](crate::AsyncTrainer).
+pub struct ActorServer<T, M> {
+    /// Thread handles, including those for connections.
+    threads: Arc<Mutex<Vec<JoinHandle<()>>>>,
+
+    /// Flag to stop training.
+    stop: Arc<Mutex<bool>>,
+
+    /// Address the server listens on.
+    local_addr: SocketAddr,
+
+    phantom: std::marker::PhantomData<(T, M)>,
+}
+
+impl<T, M> ActorServer<T, M>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    M: Serialize + DeserializeOwned + Clone + Send + 'static,
+{
+    /// Listens on `config.addr` and runs threads for handling connections.
+    ///
+    /// * `config` - Configuration of the connections.
+    /// * `pushed_item_message_sender` - Sender of [`PushedItemMessage`]s to
+    ///   [`AsyncTrainer`](crate::AsyncTrainer).
+    /// * `model_info_receiver` - Receives model info from [`AsyncTrainer`](crate::AsyncTrainer).
+    /// * `stop` - Flag to stop training, set by [`AsyncTrainer`](crate::AsyncTrainer).
+    pub fn run(
+        config: &RemoteConfig,
+        pushed_item_message_sender: Sender<PushedItemMessage<T>>,
+        model_info_receiver: Receiver<(usize, M)>,
+        stop: Arc<Mutex<bool>>,
+    ) -> Result<Self> {
+        config.write_timeout()?;
+        let listener = TcpListener::bind(config.addr.as_str())?;
+        listener.set_nonblocking(true)?;
+        let local_addr = listener.local_addr()?;
+        info!("ActorServer listens on {}", local_addr);
+
+        let threads = Arc::new(Mutex::new(vec![]));
+        let clients = Arc::new(Mutex::new(Vec::<TcpStream>::new()));
+        let latest = Arc::new(Mutex::new(None));
+
+        // Thread for accepting connections
+        let handle = {
+            let threads = threads.clone();
+            let clients = clients.clone();
+            let latest = latest.clone();
+            let stop = stop.clone();
+            let config = config.clone();
+            std::thread::spawn(move || {
+                Self::accept_loop(
+                    listener,
+                    pushed_item_message_sender,
+                    clients,
+                    latest,
+                    threads,
+                    stop,
+                    config,
+                )
+            })
+        };
+        threads.lock().unwrap().push(handle);
+
+        // Thread for broadcasting model info
+        let handle = {
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                Self::broadcast_loop(model_info_receiver, clients, latest, stop)
+            })
+        };
+        threads.lock().unwrap().push(handle);
+
+        Ok(Self {
+            threads,
+            stop,
+            local_addr,
+            phantom: std::marker::PhantomData,
+        })
+    }
+
+    /// Returns the address the server listens on.
+    ///
+    /// It is useful when listening on port 0, where the port is assigned by the OS.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Waits until all threads finish.
+    pub fn join(self) {
+        loop {
+            let handle = self.threads.lock().unwrap().pop();
+            match handle {
+                Some(handle) => handle.join().unwrap(),
+                None => break,
+            }
+        }
+    }
+
+    /// Stops the threads and closes the connections.
+    pub fn stop(&self) {
+        *self.stop.lock().unwrap() = true;
+    }
+
+    /// Stops and joins the threads.
+    pub fn stop_and_join(self) {
+        self.stop();
+        self.join();
+    }
+
+    fn accept_loop(
+        listener: TcpListener,
+        sender: Sender<PushedItemMessage<T>>,
+        clients: Arc<Mutex<Vec<TcpStream>>>,
+        latest: Arc<Mutex<Option<(usize, M)>>>,
+        threads: Arc<Mutex<Vec<JoinHandle<()>>>>,
+        stop: Arc<Mutex<bool>>,
+        config: RemoteConfig,
+    ) {
+        let max_frame_size = config.max_frame_size;
+        loop {
+            if *stop.lock().unwrap() {
+                break;
+            }
+
+            let (mut stream, peer) = match listener.accept() {
+                Ok(conn) => conn,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {
+                    std::thread::sleep(POLL_INTERVAL);
+                    continue;
+                }
+                Err(e) => {
+                    warn!("Failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            info!("Accepted ActorClient at {}", peer);
+
+            let writer = match Self::setup(&stream, &clients, &latest, &config) {
+                Ok(writer) => writer,
+                Err(e) => {
+                    warn!("Failed to set up connection with {}: {}", peer, e);
+                    continue;
+                }
+            };
+            clients.lock().unwrap().push(writer);
+
+            // Thread for receiving samples from the client
+            let sender = sender.clone();
+            let handle = std::thread::spawn(move || {
+                loop {
+                    match read_frame::<PushedItemMessage<T>>(&mut stream, max_frame_size) {
+                        Ok(msg) => {
+                            if sender.send(msg).is_err() {
+                                break;
+                            }
+                        }
+                        Err(e) => {
+                            info!("Closed connection with {}: {}", peer, e);
+                            break;
+                        }
+                    }
+                }
+                let _ = stream.shutdown(Shutdown::Both);
+            });
+            threads.lock().unwrap().push(handle);
+        }
+        info!("Stopped thread for accepting ActorClients");
+    }
+
+    /// Sends the latest model info to a new client, and returns a stream for writing.
+    ///
+    /// Writing to the stream times out, so a client not reading frames does not block
+    /// the others while the clients are locked.
+    fn setup(
+        stream: &TcpStream,
+        clients: &Arc<Mutex<Vec<TcpStream>>>,
+        latest: &Arc<Mutex<Option<(usize, M)>>>,
+        config: &RemoteConfig,
+    ) -> Result<TcpStream> {
+        stream.set_nonblocking(false)?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(config.write_timeout()?))?;
+        let mut writer = stream.try_clone()?;
+
+        // Locks the clients in order not to miss model info broadcasted meanwhile
+        let _clients = clients.lock().unwrap();
+        if let Some(msg) = latest.lock().unwrap().as_ref() {
+            if let Err(e) = write_frame(&mut writer, msg) {
+                let _ = writer.shutdown(Shutdown::Both);
+                return Err(e);
+            }
+        }
+        Ok(writer)
+    }
+
+    fn broadcast_loop(
+        model_info_receiver: Receiver<(usize, M)>,
+        clients: Arc<Mutex<Vec<TcpStream>>>,
+        latest: Arc<Mutex<Option<(usize, M)>>>,
+        stop: Arc<Mutex<bool>>,
+    ) {
+        loop {
+            if *stop.lock().unwrap() {
+                break;
+            }
+
+            match model_info_receiver.recv_timeout(POLL_INTERVAL) {
+                Ok(msg) => {
+                    let mut clients = clients.lock().unwrap();
+                    clients.retain_mut(|stream| match write_frame(stream, &msg) {
+                        Ok(()) => true,
+                        Err(e) => {
+                            // A partially written frame can not be resumed
+                            warn!("Dropped ActorClient: {}", e);
+                            let _ = stream.shutdown(Shutdown::Both);
+                            false
+                        }
+                    });
+                    *latest.lock().unwrap() = Some(msg);
+                }
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+
+        // Closing connections stops the threads receiving samples and the remote actors
+        for stream in clients.lock().unwrap().drain(..) {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        info!("Stopped thread for broadcasting model info");
+    }
+}
+
+/// Connects an [`ActorManager`](crate::ActorManager) to [`ActorServer`].
+pub struct ActorClient<T, M> {
+    /// Thread handles.
+    threads: Vec<JoinHandle<()>>,
+
+    phantom: std::marker::PhantomData<(T, M)>,
+}
+
+impl<T, M> ActorClient<T, M>
+where
+    T: Serialize + DeserializeOwned + Send + 'static,
+    M: Serialize + DeserializeOwned + Clone + Send + 'static,
+{
+    /// Connects to [`ActorServer`] at `config.addr` and runs threads for communication.
+    ///
+    /// * `config` - Configuration of the connection.
+    /// * `pushed_item_message_receiver` - Receives [`PushedItemMessage`]s from
+    ///   [`ActorManager`](crate::ActorManager).
+    /// * `model_info_sender` - Sender of model info to [`ActorManager`](crate::ActorManager).
+    /// * `stop` - Flag to stop the [`ActorManager`](crate::ActorManager), set when the
+    ///   connection is closed.
+    pub fn run(
+        config: &RemoteConfig,
+        pushed_item_message_receiver: Receiver<PushedItemMessage<T>>,
+        model_info_sender: Sender<(usize, M)>,
+        stop: Arc<Mutex<bool>>,
+    ) -> Result<Self> {
+        let max_frame_size = config.max_frame_size;
+        let stream = TcpStream::connect(config.addr.as_str())?;
+        stream.set_nodelay(true)?;
+        stream.set_write_timeout(Some(config.write_timeout()?))?;
+        info!("ActorClient connected to {}", stream.peer_addr()?);
+
+        // Thread for receiving model info
+        let handle_recv = {
+            let mut stream = stream.try_clone()?;
+            let stop = stop.clone();
+            std::thread::spawn(move || {
+                let mut latest = None;
+                while let Ok(msg) = read_frame::<(usize, M)>(&mut stream, max_frame_size) {
+                    latest = Some(msg.clone());
+                    if model_info_sender.send(msg).is_err() {
+                        break;
+                    }
+                }
+                *stop.lock().unwrap() = true;
+
+                // Unblocks the thread waiting model info in ActorManager, as AsyncTrainer does
+                // at the end of training
+                if let Some(msg) = latest {
+                    let _ = model_info_sender.send(msg);
+                }
+                info!("Stopped thread for receiving model info");
+            })
+        };
+
+        // Thread for sending samples
+        let handle_send = {
+            let mut stream = stream;
+            std::thread::spawn(move || {
+                loop {
+                    match pushed_item_message_receiver.recv_timeout(POLL_INTERVAL) {
+                        Ok(msg) => {
+                            if let Err(e) = write_frame(&mut stream, &msg) {
+                                warn!("Failed to send samples: {}", e);
+                                break;
+                            }
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                    if *stop.lock().unwrap() {
+                        break;
+                    }
+                }
+                *stop.lock().unwrap() = true;
+                let _ = stream.shutdown(Shutdown::Both);
+                info!("Stopped thread for sending samples");
+            })
+        };
+
+        Ok(Self {
+            threads: vec![handle_recv, handle_send],
+            phantom: std::marker::PhantomData,
+        })
+    }
+
+    /// Waits until the connection is closed.
+    pub fn join(self) {
+        for h in self.threads {
+            h.join().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crossbeam_channel::unbounded;
+
+    type Server = ActorServer<Vec<f32>, usize>;
+    type Client = ActorClient<Vec<f32>, usize>;
+
+    #[test]
+    fn test_loopback() -> Result<()> {
+        let (item_s, item_r) = unbounded();
+        let (model_s, model_r) = unbounded();
+        let stop_server = Arc::new(Mutex::new(false));
+        let config = RemoteConfig::default().addr("127.0.0.1:0");
+        let server = Server::run(&config, item_s, model_r, stop_server.clone())?;
+        let config = config.addr(server.local_addr().to_string());
+
+        // The model info sent before the client connects is delivered on connection
+        model_s.send((1, 10))?;
+        std::thread::sleep(POLL_INTERVAL * 2);
+
+        let (item_s_client, item_r_client) = unbounded();
+        let (model_s_client, model_r_client) = unbounded();
+        let stop_client = Arc::new(Mutex::new(false));
+        let client = Client::run(&config, item_r_client, model_s_client, stop_client.clone())?;
+        let timeout = Duration::from_secs(5);
+        assert_eq!(model_r_client.recv_timeout(timeout)?, (1, 10));
+
+        // Model info is broadcasted to the client
+        model_s.send((2, 20))?;
+        assert_eq!(model_r_client.recv_timeout(timeout)?, (2, 20));
+
+        // Samples are forwarded to the server
+        item_s_client.send(PushedItemMessage {
+            id: 3,
+            pushed_items: vec![vec![1.0, 2.0]],
+            priorities: None,
+        })?;
+        let msg = item_r.recv_timeout(timeout)?;
+        assert_eq!(msg.id, 3);
+        assert_eq!(msg.pushed_items, vec![vec![1.0, 2.0]]);
+
+        // Stopping the server closes the connection and stops the client
+        server.stop_and_join();
+        client.join();
+        assert!(*stop_client.lock().unwrap());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_drop_stalled_client() -> Result<()> {
+        let (item_s, _item_r) = unbounded();
+        let (model_s, model_r) = unbounded();
+        let config = RemoteConfig::default()
+            .addr("127.0.0.1:0")
+            .write_timeout_ms(100);
+        let server =
+            ActorServer::<Vec<f32>, Vec<f32>>::run(&config, item_s, model_r, Default::default())?;
+        let config = config.addr(server.local_addr().to_string());
+
+        // A client which never reads model info
+        let mut stalled = TcpStream::connect(server.local_addr())?;
+        std::thread::sleep(POLL_INTERVAL * 2);
+
+        let (_item_s_client, item_r_client) = unbounded::<PushedItemMessage<Vec<f32>>>();
+        let (model_s_client, model_r_client) = unbounded();
+        let client = ActorClient::<Vec<f32>, Vec<f32>>::run(
+            &config,
+            item_r_client,
+            model_s_client,
+            Default::default(),
+        )?;
+        std::thread::sleep(POLL_INTERVAL * 2);
+
+        // Model info larger than the socket buffers is still delivered to the other client
+        let timeout = Duration::from_secs(5);
+        let model = vec![1f32; 2 * 1024 * 1024];
+        for i in 0..3 {
+            model_s.send((i, model.clone()))?;
+            assert_eq!(model_r_client.recv_timeout(timeout)?.0, i);
+        }
+
+        // The connection with the stalled client has been closed
+        stalled.set_read_timeout(Some(timeout))?;
+        std::io::copy(&mut stalled, &mut std::io::sink())?;
+
+        server.stop_and_join();
+        client.join();
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reject_large_frame() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let mut writer = TcpStream::connect(listener.local_addr()?)?;
+        let (mut reader, _) = listener.accept()?;
+
+        write_frame(&mut writer, &vec![0f32; 16])?;
+        assert!(read_frame::<Vec<f32>>(&mut reader, 8).is_err());
+
+        // The length prefix is checked before a buffer is allocated
+        let mut writer = TcpStream::connect(listener.local_addr()?)?;
+        let (mut reader, _) = listener.accept()?;
+        writer.write_all(&u64::MAX.to_le_bytes())?;
+        assert!(read_frame::<Vec<f32>>(&mut reader, DEFAULT_MAX_FRAME_SIZE).is_err());
+
+        Ok(())
+    }
+}
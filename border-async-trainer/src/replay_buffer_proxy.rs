@@ -11,6 +11,10 @@ pub struct ReplayBufferProxyConfig {
     ///
     /// A sample is a `R::Item` for [`ReplayBufferProxy`]`<R>`.
     pub n_buffer: usize,
+
+    /// If `true`, buffered samples are not sent until [`ReplayBufferProxy::flush()`] is
+    /// called with their priorities.
+    pub compute_priorities: bool,
 }
 
 /// A wrapper of replay buffer for asynchronous trainer.
@@ -26,6 +30,9 @@ pub struct ReplayBufferProxy<R: ExperienceBufferBase> {
     /// Buffer of `R::Item`s.
     buffer: Vec<R::Item>,
 
+    /// If `true`, samples are sent with [`ReplayBufferProxy::flush()`].
+    compute_priorities: bool,
+
     phantom: PhantomData<R>,
 }
 
@@ -41,9 +48,38 @@ impl<R: ExperienceBufferBase> ReplayBufferProxy<R> {
             sender,
             n_buffer,
             buffer: Vec::with_capacity(n_buffer),
+            compute_priorities: config.compute_priorities,
             phantom: PhantomData,
         }
     }
+
+    /// Returns `true` if the number of buffered samples reaches `n_buffer`.
+    pub fn is_full(&self) -> bool {
+        self.buffer.len() >= self.n_buffer
+    }
+
+    /// Returns the buffered samples.
+    pub fn items(&self) -> &[R::Item] {
+        &self.buffer
+    }
+
+    /// Sends the buffered samples to the trainer with the priorities of the transitions
+    /// in each sample.
+    pub fn flush(&mut self, priorities: Option<Vec<Vec<f32>>>) -> Result<()> {
+        let mut buffer = Vec::with_capacity(self.n_buffer);
+        std::mem::swap(&mut self.buffer, &mut buffer);
+
+        let msg = PushedItemMessage {
+            id: self.id,
+            pushed_items: buffer,
+            priorities,
+        };
+
+        match self.sender.try_send(msg) {
+            Ok(()) => Ok(()),
+            Err(_e) => Err(crate::BorderAsyncTrainerError::SendMsgForPush)?,
+        }
+    }
 }
 
 impl<R: ExperienceBufferBase> ExperienceBufferBase for ReplayBufferProxy<R> {
@@ -51,21 +87,8 @@ impl<R: ExperienceBufferBase> ExperienceBufferBase for ReplayBufferProxy<R> {
 
     fn push(&mut self, tr: Self::Item) -> Result<()> {
         self.buffer.push(tr);
-        if self.buffer.len() == self.n_buffer {
-            let mut buffer = Vec::with_capacity(self.n_buffer);
-            std::mem::swap(&mut self.buffer, &mut buffer);
-
-            let msg = PushedItemMessage {
-                id: self.id,
-                pushed_items: buffer,
-            };
-
-            match self.sender.try_send(msg) {
-                Ok(()) => {}
-                Err(_e) => {
-                    return Err(crate::BorderAsyncTrainerError::SendMsgForPush)?;
-                }
-            }
+        if self.is_full() && !self.compute_priorities {
+            self.flush(None)?;
         }
 
         Ok(())
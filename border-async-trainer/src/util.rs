@@ -1,13 +1,16 @@
 //! Utility function.
 use crate::{
-    actor_stats_fmt, ActorManager, ActorManagerConfig, AsyncTrainer, AsyncTrainerConfig, SyncModel,
+    actor_stats_fmt, ActorClient, ActorManager, ActorManagerConfig, ActorServer, ActorStat,
+    AsyncTrainer, AsyncTrainerConfig, RemoteConfig, SyncModel,
 };
+use anyhow::Result;
 use border_core::{
-    record::Recorder, Agent, Configurable, Env, Evaluator, ExperienceBufferBase, ReplayBufferBase,
-    StepProcessor,
+    generic_replay_buffer::BatchBase, record::Recorder, Agent, Configurable, Env, Evaluator,
+    ExperienceBufferBase, ReplayBufferBase, StepProcessor, TransitionBatch,
 };
 use crossbeam_channel::unbounded;
 use log::info;
+use serde::{de::DeserializeOwned, Serialize};
 use std::sync::{Arc, Mutex};
 
 /// Runs asynchronous training.
@@ -49,6 +52,95 @@ pub fn train_async<A, E, R, S>(
     S::Config: Send + 'static,
     R::Item: Send + 'static,
     A::ModelInfo: Send + 'static,
+{
+    let configs = AsyncTrainConfigs {
+        agent_config,
+        agent_configs,
+        env_config_train,
+        env_config_eval,
+        step_proc_config,
+        replay_buffer_config,
+        actor_man_config,
+        async_trainer_config,
+    };
+    train_async_::<A, E, R, S>(&configs, recorder, evaluator, |actors| actors)
+}
+
+/// Configurations of asynchronous training in a single process.
+///
+/// The fields are the configurations given to [`train_async`] as arguments.
+pub struct AsyncTrainConfigs<'a, A, E, R, S>
+where
+    A: Configurable,
+    E: Env,
+    R: ReplayBufferBase,
+    S: StepProcessor<E>,
+{
+    /// Configuration of the agent to be trained.
+    pub agent_config: &'a A::Config,
+
+    /// Configurations of agents for asynchronous sampling.
+    pub agent_configs: &'a Vec<A::Config>,
+
+    /// Configuration of the environment with which transitions are sampled.
+    pub env_config_train: &'a E::Config,
+
+    /// Configuration of the environment on which the agent being trained is evaluated.
+    pub env_config_eval: &'a E::Config,
+
+    /// Configuration of the step processor.
+    pub step_proc_config: &'a S::Config,
+
+    /// Configuration of the replay buffer.
+    pub replay_buffer_config: &'a R::Config,
+
+    /// Configuration of [`ActorManager`].
+    pub actor_man_config: &'a ActorManagerConfig,
+
+    /// Configuration of [`AsyncTrainer`].
+    pub async_trainer_config: &'a AsyncTrainerConfig,
+}
+
+/// Runs asynchronous training with prioritized experience replay, where actors compute
+/// the initial priorities of samples.
+///
+/// The configurations are the same as the arguments of [`train_async`]. The replay buffer
+/// should be configured for prioritized experience replay, e.g., with `per_config` of
+/// [`SimpleReplayBufferConfig`](border_core::generic_replay_buffer::SimpleReplayBufferConfig).
+/// See [`ActorManager::with_priorities()`].
+pub fn train_async_with_priorities<A, E, R, S>(
+    configs: &AsyncTrainConfigs<A, E, R, S>,
+    recorder: &mut Box<dyn Recorder<E, R>>,
+    evaluator: &mut impl Evaluator<E>,
+) where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
+    S: StepProcessor<E>,
+    A::Config: Send + 'static,
+    E::Config: Send + 'static,
+    S::Config: Send + 'static,
+    R::Item: BatchBase + TransitionBatch + Clone + Into<R::Batch> + Send + 'static,
+    A::ModelInfo: Send + 'static,
+{
+    train_async_::<A, E, R, S>(configs, recorder, evaluator, ActorManager::with_priorities)
+}
+
+fn train_async_<A, E, R, S>(
+    configs: &AsyncTrainConfigs<A, E, R, S>,
+    recorder: &mut Box<dyn Recorder<E, R>>,
+    evaluator: &mut impl Evaluator<E>,
+    setup_actors: impl FnOnce(ActorManager<A, E, R, S>) -> ActorManager<A, E, R, S>,
+) where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
+    S: StepProcessor<E>,
+    A::Config: Send + 'static,
+    E::Config: Send + 'static,
+    S::Config: Send + 'static,
+    R::Item: Send + 'static,
+    A::ModelInfo: Send + 'static,
 {
     // Shared flag to stop actor threads
     let stop = Arc::new(Mutex::new(false));
@@ -61,15 +153,75 @@ pub fn train_async<A, E, R, S>(
     let guard_init_env = Arc::new(Mutex::new(true));
 
     // Actor manager and async trainer
-    let mut actors = ActorManager::<A, E, R, S>::build(
-        actor_man_config,
-        agent_configs,
-        env_config_train,
-        step_proc_config,
+    let mut actors = setup_actors(ActorManager::<A, E, R, S>::build(
+        configs.actor_man_config,
+        configs.agent_configs,
+        configs.env_config_train,
+        configs.step_proc_config,
         item_s,
         model_r,
         stop.clone(),
+    ));
+    let mut trainer = AsyncTrainer::<A, E, R>::build(
+        configs.async_trainer_config,
+        configs.agent_config,
+        configs.env_config_eval,
+        configs.replay_buffer_config,
+        item_r,
+        model_s,
+        stop.clone(),
     );
+
+    // Starts sampling and training
+    actors.run(guard_init_env.clone());
+    let stats = trainer.train(recorder, evaluator, guard_init_env);
+    info!("Stats of async trainer");
+    info!("{}", stats.fmt());
+
+    let stats = actors.stop_and_join();
+    info!("Stats of generated samples in actors");
+    info!("{}", actor_stats_fmt(&stats));
+}
+
+/// Runs asynchronous training with [`Actor`](crate::Actor)s in other processes.
+///
+/// This function runs [`AsyncTrainer`] and [`ActorServer`], which listens on
+/// `remote_config.addr` for [`ActorManager`]s run with [`run_remote_actors`]. Training starts
+/// when samples for the warmup period are pushed from them.
+///
+/// * `agent_config` - Configuration of the agent to be trained.
+/// * `env_config_eval` - Configuration of the environment on which the agent being trained
+///   is evaluated.
+/// * `replay_buffer_config` - Configuration of the replay buffer.
+/// * `async_trainer_config` - Configuration of [`AsyncTrainer`].
+/// * `remote_config` - Configuration of [`ActorServer`]. As connections are not
+///   authenticated, listen on a non-loopback address only in a trusted network.
+pub fn train_async_server<A, E, R>(
+    agent_config: &A::Config,
+    env_config_eval: &E::Config,
+    replay_buffer_config: &R::Config,
+    async_trainer_config: &AsyncTrainerConfig,
+    recorder: &mut Box<dyn Recorder<E, R>>,
+    evaluator: &mut impl Evaluator<E>,
+    remote_config: &RemoteConfig,
+) -> Result<()>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    R: ExperienceBufferBase + Send + 'static + ReplayBufferBase,
+    R::Item: Serialize + DeserializeOwned + Send + 'static,
+    A::ModelInfo: Serialize + DeserializeOwned + Send + 'static,
+{
+    // Shared flag to stop the server
+    let stop = Arc::new(Mutex::new(false));
+
+    // Creates channels
+    let (item_s, item_r) = unbounded(); // items pushed to replay buffer
+    let (model_s, model_r) = unbounded(); // model_info
+
+    // Server and async trainer
+    let server =
+        ActorServer::<R::Item, A::ModelInfo>::run(remote_config, item_s, model_r, stop.clone())?;
     let mut trainer = AsyncTrainer::<A, E, R>::build(
         async_trainer_config,
         agent_config,
@@ -80,13 +232,146 @@ pub fn train_async<A, E, R, S>(
         stop.clone(),
     );
 
-    // Starts sampling and training
-    actors.run(guard_init_env.clone());
-    let stats = trainer.train(recorder, evaluator, guard_init_env);
+    // Starts training
+    let stats = trainer.train(recorder, evaluator, Arc::new(Mutex::new(true)));
     info!("Stats of async trainer");
     info!("{}", stats.fmt());
 
-    let stats = actors.stop_and_join();
+    server.stop_and_join();
+    Ok(())
+}
+
+/// Runs [`Actor`](crate::Actor)s which send samples to [`AsyncTrainer`] in another process.
+///
+/// This function runs [`ActorManager`] and [`ActorClient`], which connects to
+/// [`ActorServer`] run with [`train_async_server`] at `remote_config.addr`. It returns when
+/// the connection is closed, i.e., training finished.
+///
+/// * `agent_configs` - Configurations of agents for asynchronous sampling.
+/// * `env_config` - Configuration of the environment with which transitions are sampled.
+/// * `step_proc_config` - Configuration of the step processor.
+/// * `actor_man_config` - Configuration of [`ActorManager`].
+/// * `remote_config` - Configuration of [`ActorClient`].
+pub fn run_remote_actors<A, E, R, S>(
+    agent_configs: &Vec<A::Config>,
+    env_config: &E::Config,
+    step_proc_config: &S::Config,
+    actor_man_config: &ActorManagerConfig,
+    remote_config: &RemoteConfig,
+) -> Result<Vec<ActorStat>>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
+    S: StepProcessor<E>,
+    A::Config: Send + 'static,
+    E::Config: Send + 'static,
+    S::Config: Send + 'static,
+    R::Item: Serialize + DeserializeOwned + Send + 'static,
+    A::ModelInfo: Serialize + DeserializeOwned + Send + 'static,
+{
+    run_remote_actors_::<A, E, R, S>(
+        agent_configs,
+        env_config,
+        step_proc_config,
+        actor_man_config,
+        remote_config,
+        |actors| actors,
+    )
+}
+
+/// Runs [`Actor`](crate::Actor)s which compute the initial priorities of samples and send them
+/// to [`AsyncTrainer`] in another process.
+///
+/// The arguments are the same as [`run_remote_actors`]. See
+/// [`ActorManager::with_priorities()`].
+pub fn run_remote_actors_with_priorities<A, E, R, S>(
+    agent_configs: &Vec<A::Config>,
+    env_config: &E::Config,
+    step_proc_config: &S::Config,
+    actor_man_config: &ActorManagerConfig,
+    remote_config: &RemoteConfig,
+) -> Result<Vec<ActorStat>>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
+    S: StepProcessor<E>,
+    A::Config: Send + 'static,
+    E::Config: Send + 'static,
+    S::Config: Send + 'static,
+    R::Item: BatchBase
+        + TransitionBatch
+        + Clone
+        + Into<R::Batch>
+        + Serialize
+        + DeserializeOwned
+        + Send
+        + 'static,
+    A::ModelInfo: Serialize + DeserializeOwned + Send + 'static,
+{
+    run_remote_actors_::<A, E, R, S>(
+        agent_configs,
+        env_config,
+        step_proc_config,
+        actor_man_config,
+        remote_config,
+        ActorManager::with_priorities,
+    )
+}
+
+fn run_remote_actors_<A, E, R, S>(
+    agent_configs: &Vec<A::Config>,
+    env_config: &E::Config,
+    step_proc_config: &S::Config,
+    actor_man_config: &ActorManagerConfig,
+    remote_config: &RemoteConfig,
+    setup_actors: impl FnOnce(ActorManager<A, E, R, S>) -> ActorManager<A, E, R, S>,
+) -> Result<Vec<ActorStat>>
+where
+    A: Agent<E, R> + Configurable + SyncModel + 'static,
+    E: Env,
+    R: ExperienceBufferBase<Item = S::Output> + Send + 'static + ReplayBufferBase,
+    S: StepProcessor<E>,
+    A::Config: Send + 'static,
+    E::Config: Send + 'static,
+    S::Config: Send + 'static,
+    R::Item: Serialize + DeserializeOwned + Send + 'static,
+    A::ModelInfo: Serialize + DeserializeOwned + Send + 'static,
+{
+    // Shared flag to stop actor threads
+    let stop = Arc::new(Mutex::new(false));
+
+    // Creates channels
+    let (item_s, item_r) = unbounded(); // items pushed to replay buffer
+    let (model_s, model_r) = unbounded(); // model_info
+
+    // Client and actor manager
+    let client = ActorClient::<R::Item, A::ModelInfo>::run(
+        remote_config,
+        item_r.clone(),
+        model_s,
+        stop.clone(),
+    )?;
+    let mut actors = setup_actors(ActorManager::<A, E, R, S>::build(
+        actor_man_config,
+        agent_configs,
+        env_config,
+        step_proc_config,
+        item_s,
+        model_r,
+        stop.clone(),
+    ));
+
+    // Starts sampling
+    actors.run(Arc::new(Mutex::new(true)));
+    client.join();
+
+    // `item_r` is kept until here, as samples are pushed until actors stop
+    let stats = actors.join();
+    drop(item_r);
     info!("Stats of generated samples in actors");
     info!("{}", actor_stats_fmt(&stats));
+
+    Ok(stats)
 }
@@ -1,32 +1,53 @@
 //! Action for [BorderAtariEnv](crate::BorderAtariEnv)
 use anyhow::Result;
-use border_core::{record::Record, Act};
+use border_core::{record::Record, Act, ActValues, SplitAct};
 use serde::{Deserialize, Serialize};
 use std::{default::Default, marker::PhantomData};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 /// Action for [`BorderAtariEnv`](crate::BorderAtariEnv).
 ///
 /// This action is a discrete action and denotes pushing a button.
+/// It has an element for each environment when the environments are vectorized
+/// with [`VecEnv`](border_core::VecEnv).
 pub struct BorderAtariAct {
-    pub act: u8,
+    pub act: Vec<u8>,
 }
 
 impl BorderAtariAct {
     pub fn new(act: u8) -> Self {
-        Self { act }
+        Self { act: vec![act] }
     }
 }
 
 impl Act for BorderAtariAct {
     fn len(&self) -> usize {
-        1
+        self.act.len()
+    }
+
+    fn values(&self) -> Option<ActValues> {
+        Some(ActValues::Discrete(
+            self.act.iter().map(|a| *a as usize).collect(),
+        ))
+    }
+}
+
+impl SplitAct for BorderAtariAct {
+    fn split(&self, n: usize) -> Vec<Self> {
+        assert_eq!(self.act.len(), n);
+        self.act.iter().map(|a| Self::new(*a)).collect()
+    }
+
+    fn stack(acts: Vec<Self>) -> Self {
+        Self {
+            act: acts.into_iter().flat_map(|a| a.act).collect(),
+        }
     }
 }
 
 impl From<u8> for BorderAtariAct {
     fn from(act: u8) -> Self {
-        Self { act }
+        Self::new(act)
     }
 }
 
@@ -38,7 +59,8 @@ pub mod candle {
 
     impl From<BorderAtariAct> for Tensor {
         fn from(act: BorderAtariAct) -> Tensor {
-            Tensor::from_vec(vec![act.act as u8], &[1, 1], &Cpu).unwrap()
+            let n = act.act.len();
+            Tensor::from_vec(act.act, &[n, 1], &Cpu).unwrap()
         }
     }
 
@@ -50,9 +72,12 @@ pub mod candle {
     }
 
     impl From<Tensor> for BorderAtariAct {
-        /// `t` must have single item.
+        /// `t` must be a 1-dimensional tensor with an element for each environment.
         fn from(t: Tensor) -> Self {
-            (t.to_vec1::<i64>().unwrap()[0] as u8).into()
+            let act = t.to_vec1::<i64>().unwrap();
+            Self {
+                act: act.into_iter().map(|a| a as u8).collect(),
+            }
         }
     }
 }
@@ -61,12 +86,13 @@ pub mod candle {
 pub mod tch_ {
     use super::*;
     use border_tch_agent::TensorBatch;
-    use std::convert::TryInto;
+    use std::convert::TryFrom;
     use tch::Tensor;
 
     impl From<BorderAtariAct> for Tensor {
         fn from(act: BorderAtariAct) -> Tensor {
-            Tensor::from_slice(&[act.act as i64]).unsqueeze(-1)
+            let act = act.act.iter().map(|a| *a as i64).collect::<Vec<_>>();
+            Tensor::from_slice(&act).unsqueeze(-1)
         }
     }
 
@@ -78,9 +104,12 @@ pub mod tch_ {
     }
 
     impl From<Tensor> for BorderAtariAct {
-        /// `t` must have single item.
+        /// `t` has an element for each environment.
         fn from(t: Tensor) -> Self {
-            (TryInto::<i64>::try_into(t).unwrap() as u8).into()
+            let act = Vec::<i64>::try_from(&t.flatten(0, -1)).unwrap();
+            Self {
+                act: act.into_iter().map(|a| a as u8).collect(),
+            }
         }
     }
 }
@@ -100,6 +129,14 @@ pub trait BorderAtariActFilter<A: Act> {
 
     /// Resets the filter. Does nothing in the default implementation.
     fn reset(&mut self, _is_done: &Option<&Vec<i8>>) {}
+
+    /// Converts actions of [`BorderAtariAct`] into `A` to probe the environment.
+    ///
+    /// It is used by [`Env::probe_actions()`](border_core::Env::probe_actions).
+    /// The default implementation returns no action.
+    fn probe_acts(&self, _acts: Vec<BorderAtariAct>) -> Vec<A> {
+        vec![]
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -120,7 +157,7 @@ pub struct BorderAtariActRawFilter<A> {
 
 impl<A> BorderAtariActFilter<A> for BorderAtariActRawFilter<A>
 where
-    A: Act + Into<BorderAtariAct>,
+    A: Act + Into<BorderAtariAct> + From<BorderAtariAct>,
 {
     type Config = BorderAtariActRawFilterConfig;
 
@@ -133,4 +170,8 @@ where
     fn filt(&mut self, act: A) -> (BorderAtariAct, Record) {
         (act.into(), Record::empty())
     }
+
+    fn probe_acts(&self, acts: Vec<BorderAtariAct>) -> Vec<A> {
+        acts.into_iter().map(A::from).collect()
+    }
 }
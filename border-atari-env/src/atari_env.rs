@@ -38,6 +38,15 @@ impl AtariEnv {
     pub fn available_difficulty_settings(&self) -> Vec<i32> {
         self.ale.available_difficulty_settings()
     }
+    pub fn available_mode_settings(&self) -> Vec<i32> {
+        self.ale.available_mode_settings()
+    }
+    pub fn set_mode(&mut self, mode: i32) {
+        self.ale.set_mode(mode)
+    }
+    pub fn set_difficulty(&mut self, difficulty: i32) {
+        self.ale.set_difficulty(difficulty)
+    }
     pub fn lives(&self) -> usize {
         self.ale.lives() as usize
     }
@@ -236,6 +236,47 @@ impl Ale {
         unimplemented!();
     }
 
+    pub fn available_mode_settings(&self) -> Vec<i32> {
+        #[cfg(feature = "atari-env-sys")]
+        {
+            let n = unsafe { atari_env_sys::getAvailableModesSize(self.inner) } as usize;
+            let mut buf = vec![0i32; n];
+            unsafe {
+                atari_env_sys::getAvailableModes(self.inner, buf.as_mut_ptr() as *mut i32);
+            }
+            buf
+        }
+
+        #[cfg(not(feature = "atari-env-sys"))]
+        unimplemented!();
+    }
+
+    /// Sets the game mode. It takes effect after the next reset.
+    ///
+    /// The mode must be one of [`Ale::available_mode_settings()`].
+    pub fn set_mode(&mut self, mode: i32) {
+        #[cfg(feature = "atari-env-sys")]
+        unsafe {
+            atari_env_sys::setMode(self.inner, mode);
+        }
+
+        #[cfg(not(feature = "atari-env-sys"))]
+        unimplemented!();
+    }
+
+    /// Sets the difficulty. It takes effect after the next reset.
+    ///
+    /// The difficulty must be one of [`Ale::available_difficulty_settings()`].
+    pub fn set_difficulty(&mut self, difficulty: i32) {
+        #[cfg(feature = "atari-env-sys")]
+        unsafe {
+            atari_env_sys::setDifficulty(self.inner, difficulty);
+        }
+
+        #[cfg(not(feature = "atari-env-sys"))]
+        unimplemented!();
+    }
+
     pub fn width(&self) -> u32 {
         #[cfg(feature = "atari-env-sys")]
         unsafe {
@@ -1,28 +1,42 @@
 mod config;
 mod window;
 use super::BorderAtariAct;
-use super::{BorderAtariActFilter, BorderAtariObsFilter};
+use super::{BorderAtariActFilter, BorderAtariObs, BorderAtariObsFilter};
 use crate::atari_env::{AtariAction, AtariEnv, EmulatorConfig};
 use anyhow::Result;
-use border_core::{record::Record, Act, Env, Info, Obs, Step};
+use border_core::{
+    record::{Record, RecordValue},
+    Act, Env, Info, Obs, Step,
+};
 pub use config::BorderAtariEnvConfig;
 use image::{
     imageops::{/*grayscale,*/ resize, FilterType::Triangle},
     ImageBuffer, /*Luma,*/ Rgb,
 };
 use itertools::izip;
-use std::ptr::copy;
 use std::{default::Default, marker::PhantomData};
 use window::AtariWindow;
 #[cfg(feature = "atari-env-sys")]
 use winit::{event_loop::ControlFlow, platform::run_return::EventLoopExtRunReturn};
 
-/// Empty struct.
-pub struct NullInfo;
+/// Information of a step of [`BorderAtariEnv`].
+#[derive(Clone, Debug)]
+pub struct BorderAtariInfo {
+    /// `true` if a life has been lost in the step without the game being over.
+    pub life_lost: bool,
 
-impl Info for NullInfo {}
+    /// The number of lives after the step.
+    pub lives: usize,
+}
 
-fn env(rom_dir: &str, name: &str) -> AtariEnv {
+impl Info for BorderAtariInfo {
+    /// Returns `[1]` if a life has been lost in the step, `[0]` otherwise.
+    fn is_life_lost(&self) -> Option<Vec<i8>> {
+        Some(vec![self.life_lost as i8])
+    }
+}
+
+fn env(rom_dir: &str, name: &str, repeat_action_probability: f32, random_seed: i32) -> AtariEnv {
     AtariEnv::new(
         rom_dir.to_string() + format!("/{}.bin", name).as_str(),
         EmulatorConfig {
@@ -30,7 +44,8 @@ fn env(rom_dir: &str, name: &str) -> AtariEnv {
             // sound: true,
             frame_skip: 1,
             color_averaging: false,
-            repeat_action_probability: 0.0,
+            repeat_action_probability,
+            random_seed,
             ..EmulatorConfig::default()
         },
     )
@@ -40,6 +55,29 @@ fn env(rom_dir: &str, name: &str) -> AtariEnv {
 ///
 /// Preprocessing is the same in the link:
 /// <https://stable-baselines3.readthedocs.io/en/master/common/atari_wrappers.html#stable_baselines3.common.atari_wrappers.AtariWrapper>.
+/// Parts of the preprocessing can be changed in [`BorderAtariEnvConfig`] to reproduce those of
+/// other papers, i.e., [`BorderAtariEnvConfig::clip_reward()`],
+/// [`BorderAtariEnvConfig::fire_reset()`], [`BorderAtariEnvConfig::grayscale()`] and
+/// [`BorderAtariEnvConfig::resize()`]. An observation consists of four stacked frames,
+/// each of which has the shape [`BorderAtariEnv::frame_shape()`], which is kept in
+/// [`BorderAtariObs`] for the conversion into tensors.
+///
+/// An episode ends when the game is over. The loss of a life is given separately by
+/// [`BorderAtariInfo`] in [`Step::info`] and by `life_lost` and `lives` in the record of each step,
+/// so that it can be treated as termination for bootstrapping while the episode continues,
+/// e.g., with [`SimpleStepProcessorConfig::terminate_on_life_loss()`].
+///
+/// The ALE evaluation protocol of recent papers is configured with
+/// [`BorderAtariEnvConfig::repeat_action_probability()`] for sticky actions,
+/// [`BorderAtariEnvConfig::full_action_space()`] and
+/// [`BorderAtariEnvConfig::max_episode_frames()`], with which episodes are truncated.
+///
+/// The screen is rendered offscreen by the emulator. [`BorderAtariEnv::screen()`] returns
+/// the current RGB frame, and [`BorderAtariEnvConfig::record_frames()`] stores the frame of each
+/// step as `frame` in the record of the step. Neither opens a window, so they work on headless
+/// servers, while [`BorderAtariEnvConfig::render()`] displays the screen in a window.
+///
+/// [`SimpleStepProcessorConfig::terminate_on_life_loss()`]: border_core::generic_replay_buffer::SimpleStepProcessorConfig::terminate_on_life_loss
 pub struct BorderAtariEnv<O, A, OF, AF>
 where
     O: Obs,
@@ -53,9 +91,15 @@ where
     // Environment
     env: AtariEnv,
 
+    // Action set, minimal or full, indexed by actions of the agent
+    actions: Vec<AtariAction>,
+
     // Window for displaying the current game state
     window: Option<AtariWindow>,
 
+    // If true, the RGB frame is stored in the record of each step
+    record_frames: bool,
+
     // Observation buffer for frame skipping
     obs_buffer: [Vec<u8>; 2],
 
@@ -65,6 +109,24 @@ where
     // If the game was done.
     was_real_done: bool,
 
+    // Maximum number of frames in an episode
+    max_episode_frames: Option<usize>,
+
+    // Number of frames in the current episode
+    episode_frames: usize,
+
+    // If true, rewards are clipped in training
+    clip_reward: bool,
+
+    // If true, FIRE is taken on reset
+    fire_reset: bool,
+
+    // If true, frames are converted to grayscale
+    grayscale: bool,
+
+    // Shape of a preprocessed frame, (channels, height, width)
+    frame_shape: [usize; 3],
+
     // Buffer for stacking frames
     frames: Vec<u8>,
 
@@ -93,16 +155,78 @@ where
         Ok(())
     }
 
+    /// Returns the RGB frame of the current screen and its shape `[height, width, 3]`.
+    ///
+    /// The frame is rendered offscreen by the emulator, so it is available without a window,
+    /// e.g., on headless servers. The shape is that of [`RecordValue::Image`].
+    pub fn screen(&self) -> (Vec<u8>, [usize; 3]) {
+        let (w, h) = (self.env.width(), self.env.height());
+        let mut frame = vec![0u8; w * h * 3];
+        self.env.render_rgb24(&mut frame);
+        (frame, [h, w, 3])
+    }
+
+    /// Returns the shape `[channels, height, width]` of a preprocessed frame.
+    ///
+    /// It is `[1, 84, 84]` with the default configuration.
+    pub fn frame_shape(&self) -> [usize; 3] {
+        self.frame_shape
+    }
+
     /// Returns the number of actions.
+    ///
+    /// It is 18 if [`BorderAtariEnvConfig::full_action_space`] is `true`,
+    /// otherwise the size of the minimal action set of the game.
     pub fn get_num_actions_atari(&self) -> i64 {
-        // self.env.available_actions().len() as i64
-        self.env.minimal_actions().len() as i64
+        self.actions.len() as i64
+    }
+
+    /// Returns the game modes available in the game.
+    pub fn available_modes(&self) -> Vec<i32> {
+        self.env.available_mode_settings()
+    }
+
+    /// Returns the difficulties available in the game.
+    pub fn available_difficulties(&self) -> Vec<i32> {
+        self.env.available_difficulty_settings()
     }
 
-    fn episodic_life_env_step(&mut self, a: &BorderAtariAct) -> (Vec<u8>, f32, i8) {
-        let actions = self.env.minimal_actions();
-        let ix = a.act;
-        let reward = self.env.step(actions[ix as usize]) as f32;
+    /// Applies the game mode and difficulty in the configuration, then resets the game.
+    fn set_mode_and_difficulty(
+        &mut self,
+        mode: Option<i32>,
+        difficulty: Option<i32>,
+    ) -> Result<()> {
+        if let Some(mode) = mode {
+            let modes = self.available_modes();
+            if !modes.contains(&mode) {
+                return Err(anyhow::anyhow!(
+                    "Mode {} is not available, must be one of {:?}",
+                    mode,
+                    modes
+                ));
+            }
+            self.env.set_mode(mode);
+        }
+        if let Some(difficulty) = difficulty {
+            let difficulties = self.available_difficulties();
+            if !difficulties.contains(&difficulty) {
+                return Err(anyhow::anyhow!(
+                    "Difficulty {} is not available, must be one of {:?}",
+                    difficulty,
+                    difficulties
+                ));
+            }
+            self.env.set_difficulty(difficulty);
+        }
+        self.env.reset();
+        Ok(())
+    }
+
+    fn episodic_life_env_step(&mut self, a: &BorderAtariAct) -> (Vec<u8>, f32, i8, bool) {
+        let ix = a.act[0];
+        let reward = self.env.step(self.actions[ix as usize]) as f32;
+        self.episode_frames += 1;
 
         let is_terminated = match self.env.is_game_over() {
             true => 1,
@@ -110,27 +234,26 @@ where
         };
         self.was_real_done = is_terminated == 1;
         let lives = self.env.lives();
-
-        // if self.train && lives < self.lives && lives > 0 {
-        //     done = true;
-        // }
+        let life_lost = lives < self.lives && lives > 0;
         self.lives = lives;
 
         let (w, h) = (self.env.width(), self.env.height());
         let mut obs = vec![0u8; w * h * 3];
         self.env.render_rgb24(&mut obs);
 
-        (obs, reward, is_terminated)
+        (obs, reward, is_terminated, life_lost)
     }
 
-    fn skip_and_max(&mut self, a: &BorderAtariAct) -> (Vec<u8>, f32, Vec<i8>) {
+    fn skip_and_max(&mut self, a: &BorderAtariAct) -> (Vec<u8>, f32, Vec<i8>, bool) {
         let mut total_reward = 0f32;
         let mut is_terminated = 0;
+        let mut life_lost = false;
 
         for i in 0..4 {
-            let (obs, reward, is_terminated_) = self.episodic_life_env_step(a);
+            let (obs, reward, is_terminated_, life_lost_) = self.episodic_life_env_step(a);
             total_reward += reward;
             is_terminated = is_terminated_;
+            life_lost |= life_lost_;
             if i == 2 {
                 self.obs_buffer[0] = obs;
             } else if i == 3 {
@@ -148,11 +271,11 @@ where
             .map(|(&a, &b)| a.max(b))
             .collect::<Vec<_>>();
 
-        (obs, total_reward, vec![is_terminated])
+        (obs, total_reward, vec![is_terminated], life_lost)
     }
 
     fn clip_reward(&self, r: f32) -> Vec<f32> {
-        if self.train {
+        if self.train && self.clip_reward {
             if r == 0.0 {
                 vec![0.0]
             } else {
@@ -163,12 +286,16 @@ where
         }
     }
 
-    fn warp_and_grayscale(w: u32, h: u32, obs: Vec<u8>) -> Vec<u8> {
+    fn warp_and_grayscale(&self, w: u32, h: u32, obs: Vec<u8>) -> Vec<u8> {
         // `obs.len()` is w * h * 3 where (w, h) is the size of the frame.
-        let img = ImageBuffer::<Rgb<_>, _>::from_vec(w, h, obs).unwrap();
-        let img = resize(&img, 84, 84, Triangle);
-        let buf = {
-            let buf = img.to_vec();
+        let [c, fh, fw] = self.frame_shape;
+        let buf = if (fw, fh) == (w as usize, h as usize) {
+            obs
+        } else {
+            let img = ImageBuffer::<Rgb<_>, _>::from_vec(w, h, obs).unwrap();
+            resize(&img, fw as u32, fh as u32, Triangle).into_raw()
+        };
+        let buf = if self.grayscale {
             let i1 = buf.iter().step_by(3);
             let i2 = buf.iter().skip(1).step_by(3);
             let i3 = buf.iter().skip(2).step_by(3);
@@ -177,25 +304,35 @@ where
                     ((0.299 * r as f32) + (0.587 * g as f32) + (0.114 * b as f32)) as u8
                 })
                 .collect::<Vec<_>>()
+        } else {
+            // Channel first
+            (0..3)
+                .flat_map(|i| buf.iter().skip(i).step_by(3).copied())
+                .collect::<Vec<_>>()
         };
         // let buf = {
         //     let img: ImageBuffer<Luma<u8>, _> = grayscale(&img);
         //     img.to_vec()
         // };
-        assert_eq!(buf.len(), 84 * 84);
+        assert_eq!(buf.len(), c * fh * fw);
         buf
     }
 
     fn stack_frame(&mut self, obs: Vec<u8>) {
-        unsafe {
-            let src: *const u8 = &self.frames[0];
-            let dst: *mut u8 = &mut self.frames[1 * 84 * 84];
-            copy(src, dst, 3 * 84 * 84);
-
-            let src: *const u8 = &obs[0];
-            let dst: *mut u8 = &mut self.frames[0];
-            copy(src, dst, 1 * 84 * 84);
+        let n = obs.len();
+        self.frames.copy_within(0..3 * n, n);
+        self.frames[0..n].copy_from_slice(&obs);
+    }
+
+    /// Takes `FIRE` and `UP`, resetting the game if it is over, as `FireResetEnv` of the baselines.
+    fn fire(&mut self) {
+        for &a in [AtariAction::Fire, AtariAction::Up].iter() {
+            self.env.step(a);
+            if self.env.is_game_over() {
+                self.env.reset();
+            }
         }
+        self.episode_frames += 2;
     }
 }
 
@@ -208,14 +345,23 @@ where
 {
     fn default() -> Self {
         let config = BorderAtariEnvConfig::<O, A, OF, AF>::default();
+        let env = env(config.rom_dir.as_str(), "pong", 0.0, 0);
 
         Self {
             train: false,
-            env: env(config.rom_dir.as_str(), "pong"),
+            actions: env.minimal_actions(),
+            env,
             window: None,
+            record_frames: false,
             obs_buffer: [vec![], vec![]],
             lives: 0,
             was_real_done: true,
+            max_episode_frames: None,
+            episode_frames: 0,
+            clip_reward: true,
+            fire_reset: false,
+            grayscale: true,
+            frame_shape: [1, 84, 84],
             frames: vec![0; 4 * 84 * 84],
             obs_filter: OF::build(&config.obs_filter_config).unwrap(),
             act_filter: AF::build(&config.act_filter_config).unwrap(),
@@ -234,25 +380,52 @@ where
     type Config = BorderAtariEnvConfig<O, A, OF, AF>;
     type Obs = O;
     type Act = A;
-    type Info = NullInfo;
+    type Info = BorderAtariInfo;
 
-    fn build(config: &Self::Config, _seed: i64) -> Result<Self>
+    /// Builds the environment, where `seed` is given to the emulator.
+    fn build(config: &Self::Config, seed: i64) -> Result<Self>
     where
         Self: Sized,
     {
+        let atari_env = env(
+            config.rom_dir.as_str(),
+            config.name.as_str(),
+            config.repeat_action_probability,
+            seed as i32,
+        );
+        let actions = match config.full_action_space {
+            true => atari_env.available_actions(),
+            false => atari_env.minimal_actions(),
+        };
+        let (h, w) = config
+            .resize
+            .unwrap_or((atari_env.height(), atari_env.width()));
+        let frame_shape = [if config.grayscale { 1 } else { 3 }, h, w];
         let mut env = Self {
             train: config.train,
-            env: env(config.rom_dir.as_str(), config.name.as_str()),
+            env: atari_env,
+            actions,
             window: None,
+            record_frames: config.record_frames,
             obs_buffer: [vec![], vec![]],
             lives: 0,
             was_real_done: true,
-            frames: vec![0; 4 * 84 * 84],
+            max_episode_frames: config.max_episode_frames,
+            episode_frames: 0,
+            clip_reward: config.clip_reward,
+            fire_reset: config.fire_reset,
+            grayscale: config.grayscale,
+            frame_shape,
+            frames: vec![0; 4 * frame_shape.iter().product::<usize>()],
             obs_filter: OF::build(&config.obs_filter_config)?,
             act_filter: AF::build(&config.act_filter_config)?,
             phantom: PhantomData,
         };
 
+        if config.mode.is_some() || config.difficulty.is_some() {
+            env.set_mode_and_difficulty(config.mode, config.difficulty)?;
+        }
+
         if config.render {
             let _ = env.open();
         }
@@ -263,6 +436,7 @@ where
     fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
         if self.was_real_done {
             self.env.reset();
+            self.episode_frames = 0;
             // println!("RESET");
         } else {
             // no-op step to advance from terminal/lost life state
@@ -272,9 +446,12 @@ where
             for _ in 0..n {
                 self.env.step(AtariAction::Noop);
             }
+            self.episode_frames += n as usize + 1;
         }
 
-        // TODO: noop random steps (?)
+        if self.fire_reset {
+            self.fire();
+        }
 
         self.was_real_done = false;
         self.lives = self.env.lives();
@@ -285,17 +462,14 @@ where
         self.obs_buffer[0] = obs.clone();
         self.obs_buffer[1] = obs.clone();
 
-        let obs = Self::warp_and_grayscale(w as u32, h as u32, obs);
+        let obs = self.warp_and_grayscale(w as u32, h as u32, obs);
 
-        unsafe {
-            let src: *const u8 = &obs[0];
-            for i in 0..4 {
-                let dst: *mut u8 = &mut self.frames[i * 84 * 84];
-                copy(src, dst, 84 * 84);
-            }
+        for frame in self.frames.chunks_exact_mut(obs.len()) {
+            frame.copy_from_slice(&obs);
         }
 
-        Ok(self.obs_filter.filt(self.frames.clone().into()).0)
+        let obs = BorderAtariObs::new(self.frames.clone(), self.frame_shape);
+        Ok(self.obs_filter.filt(obs).0)
     }
 
     fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
@@ -303,6 +477,21 @@ where
         self.reset(None)
     }
 
+    /// Returns every action of the action set followed by `n_random` random ones, converted
+    /// with [`BorderAtariActFilter::probe_acts()`].
+    ///
+    /// Random actions are sampled with a generator of a fixed seed, separate from that of
+    /// the environment.
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        let n = self.actions.len() as u8;
+        let rng = fastrand::Rng::with_seed(0);
+        let acts = (0..n)
+            .chain((0..n_random).map(|_| rng.u8(0..n)))
+            .map(BorderAtariAct::new)
+            .collect();
+        self.act_filter.probe_acts(acts)
+    }
+
     fn step(&mut self, act: &Self::Act) -> (border_core::Step<Self>, border_core::record::Record)
     where
         Self: Sized,
@@ -311,23 +500,42 @@ where
         {
             let act_org = act.clone();
             let (act, _record) = self.act_filter.filt(act_org.clone());
-            let (obs, reward, is_terminated) = self.skip_and_max(&act);
-            let is_truncated = vec![0]; // not compatible with the official implementation
+            let (obs, reward, is_terminated, life_lost) = self.skip_and_max(&act);
+            let is_truncated = match self.max_episode_frames {
+                Some(n) if is_terminated[0] == 0 && self.episode_frames >= n => {
+                    // The game is reset at the next reset
+                    self.was_real_done = true;
+                    vec![1]
+                }
+                _ => vec![0],
+            };
             let (w, h) = (self.env.width() as u32, self.env.height() as u32);
-            let obs = Self::warp_and_grayscale(w, h, obs);
+            let obs = self.warp_and_grayscale(w, h, obs);
             let reward = self.clip_reward(reward); // in training
             self.stack_frame(obs);
-            let (obs, _record) = self.obs_filter.filt(self.frames.clone().into());
+            let obs = BorderAtariObs::new(self.frames.clone(), self.frame_shape);
+            let (obs, _record) = self.obs_filter.filt(obs);
+            let info = BorderAtariInfo {
+                life_lost,
+                lives: self.lives,
+            };
+            let mut record = Record::from_slice(&[
+                ("life_lost", RecordValue::Scalar(life_lost as i32 as f32)),
+                ("lives", RecordValue::Scalar(self.lives as f32)),
+            ]);
+            if self.record_frames {
+                let (frame, shape) = self.screen();
+                record.insert("frame", RecordValue::Image(frame, shape));
+            }
             let step = Step::new(
                 obs,
                 act_org,
                 reward,
                 is_terminated,
                 is_truncated,
-                NullInfo,
+                info,
                 None,
             );
-            let record = Record::empty();
 
             if let Some(window) = self.window.as_mut() {
                 window.event_loop.run_return(|_event, _, control_flow| {
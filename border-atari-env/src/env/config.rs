@@ -7,6 +7,18 @@ use border_core::{Act, Obs};
 use serde::{Deserialize, Serialize};
 use std::{default::Default, env};
 
+fn default_clip_reward() -> bool {
+    true
+}
+
+fn default_grayscale() -> bool {
+    true
+}
+
+fn default_resize() -> Option<(usize, usize)> {
+    Some((84, 84))
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 /// Configuration of [`BorderAtariEnv`](super::BorderAtariEnv).
 pub struct BorderAtariEnvConfig<O, A, OF, AF>
@@ -22,6 +34,52 @@ where
     pub act_filter_config: AF::Config,
     pub train: bool,
     pub render: bool,
+
+    /// If `true`, the RGB frame of the screen after each step is stored as `frame`
+    /// in the record of the step, without opening a window.
+    #[serde(default)]
+    pub record_frames: bool,
+
+    /// If `true`, the full set of 18 Atari actions is used instead of the minimal action set
+    /// of the game.
+    #[serde(default)]
+    pub full_action_space: bool,
+
+    /// Game mode. If `None`, the default mode of the game is used.
+    #[serde(default)]
+    pub mode: Option<i32>,
+
+    /// Difficulty of the game. If `None`, the default difficulty of the game is used.
+    #[serde(default)]
+    pub difficulty: Option<i32>,
+
+    /// Probability that the emulator repeats the previous action instead of the given one,
+    /// i.e., sticky actions. 0.25 is used in the evaluation protocol of
+    /// [Machado et al. (2018)](https://arxiv.org/abs/1709.06009).
+    #[serde(default)]
+    pub repeat_action_probability: f32,
+
+    /// Maximum number of emulator frames in an episode, after which the episode is truncated.
+    /// If `None`, episodes are not truncated.
+    #[serde(default)]
+    pub max_episode_frames: Option<usize>,
+
+    /// If `true`, rewards are clipped to {-1, 0, 1} in training.
+    #[serde(default = "default_clip_reward")]
+    pub clip_reward: bool,
+
+    /// If `true`, `FIRE` and `UP` are taken on reset, as `FireResetEnv` of the baselines,
+    /// for games which do not start until the fire button is pressed.
+    #[serde(default)]
+    pub fire_reset: bool,
+
+    /// If `true`, frames are converted to grayscale, otherwise they have three RGB channels.
+    #[serde(default = "default_grayscale")]
+    pub grayscale: bool,
+
+    /// Size `(height, width)` to which frames are resized. If `None`, frames are not resized.
+    #[serde(default = "default_resize")]
+    pub resize: Option<(usize, usize)>,
 }
 
 impl<O, A, OF, AF> Clone for BorderAtariEnvConfig<O, A, OF, AF>
@@ -39,6 +97,16 @@ where
             act_filter_config: self.act_filter_config.clone(),
             train: self.train,
             render: self.render,
+            record_frames: self.record_frames,
+            full_action_space: self.full_action_space,
+            mode: self.mode,
+            difficulty: self.difficulty,
+            repeat_action_probability: self.repeat_action_probability,
+            max_episode_frames: self.max_episode_frames,
+            clip_reward: self.clip_reward,
+            fire_reset: self.fire_reset,
+            grayscale: self.grayscale,
+            resize: self.resize,
         }
     }
 }
@@ -64,6 +132,16 @@ where
             act_filter_config: Default::default(),
             train: true,
             render: false,
+            record_frames: false,
+            full_action_space: false,
+            mode: None,
+            difficulty: None,
+            repeat_action_probability: 0.0,
+            max_episode_frames: None,
+            clip_reward: default_clip_reward(),
+            fire_reset: false,
+            grayscale: default_grayscale(),
+            resize: default_resize(),
         }
     }
 }
@@ -91,4 +169,67 @@ where
         self.render = render;
         self
     }
+
+    /// Sets the flag to store the RGB frame of each step in the record of the step.
+    ///
+    /// Unlike [`BorderAtariEnvConfig::render()`], it does not open a window, so that frames
+    /// are available on headless servers, e.g., for videos of evaluation episodes.
+    pub fn record_frames(mut self, v: bool) -> Self {
+        self.record_frames = v;
+        self
+    }
+
+    /// Sets the flag to use the full action space (18 actions).
+    pub fn full_action_space(mut self, v: bool) -> Self {
+        self.full_action_space = v;
+        self
+    }
+
+    /// Sets the game mode.
+    pub fn mode(mut self, mode: Option<i32>) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the difficulty of the game.
+    pub fn difficulty(mut self, difficulty: Option<i32>) -> Self {
+        self.difficulty = difficulty;
+        self
+    }
+
+    /// Sets the probability of sticky actions.
+    pub fn repeat_action_probability(mut self, v: f32) -> Self {
+        self.repeat_action_probability = v;
+        self
+    }
+
+    /// Sets the maximum number of emulator frames in an episode.
+    pub fn max_episode_frames(mut self, v: Option<usize>) -> Self {
+        self.max_episode_frames = v;
+        self
+    }
+
+    /// Sets the flag to clip rewards in training.
+    pub fn clip_reward(mut self, v: bool) -> Self {
+        self.clip_reward = v;
+        self
+    }
+
+    /// Sets the flag to take `FIRE` on reset.
+    pub fn fire_reset(mut self, v: bool) -> Self {
+        self.fire_reset = v;
+        self
+    }
+
+    /// Sets the flag to convert frames to grayscale.
+    pub fn grayscale(mut self, v: bool) -> Self {
+        self.grayscale = v;
+        self
+    }
+
+    /// Sets the size `(height, width)` to which frames are resized.
+    pub fn resize(mut self, v: Option<(usize, usize)>) -> Self {
+        self.resize = v;
+        self
+    }
 }
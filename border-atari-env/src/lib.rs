@@ -83,12 +83,18 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! [`BorderAtariVecEnv`] runs a number of instances of the environment on a pool of threads,
+//! which gives batched observations for batched or asynchronous agents.
+//!
 //! [`atari_env::AtariEnv::lives()`]: atari_env::AtariEnv::lives
 mod act;
 pub mod atari_env;
 mod env;
 mod obs;
 pub mod util;
+mod vec_env;
 pub use act::{BorderAtariAct, BorderAtariActFilter, BorderAtariActRawFilter};
-pub use env::{BorderAtariEnv, BorderAtariEnvConfig};
+pub use env::{BorderAtariEnv, BorderAtariEnvConfig, BorderAtariInfo};
 pub use obs::{BorderAtariObs, BorderAtariObsFilter, BorderAtariObsRawFilter};
+pub use vec_env::{BorderAtariVecEnv, BorderAtariVecEnvConfig};
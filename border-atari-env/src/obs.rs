@@ -10,29 +10,91 @@
 //! * Grayscale
 //! * Clip reward to {-1, 0, 1} in training
 //! * Stacking four frames
+//!
+//! Reward clipping, fire on reset, grayscale and resizing can be changed in
+//! [`BorderAtariEnvConfig`](super::BorderAtariEnvConfig). The shape of frames is kept in
+//! [`BorderAtariObs`] and used in the conversion into tensors.
+//!
 //! It does not apply pixel scaling from 255 to 1.0 for saving memory of the replay buffer.
 //! Instead, the scaling is applied in CNN model.
 use anyhow::Result;
-use border_core::{record::Record, Obs};
+use border_core::{record::Record, Obs, StackObs};
 use serde::{Deserialize, Serialize};
 use std::{default::Default, marker::PhantomData};
 
+/// The number of stacked frames in an observation.
+const N_STACK: usize = 4;
+
+/// The shape `[channels, height, width]` of a frame with the default preprocessing.
+const DEFAULT_FRAME_SHAPE: [usize; 3] = [1, 84, 84];
+
 /// Observation of [`BorderAtariEnv`](super::BorderAtariEnv).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct BorderAtariObs {
-    /// Four frames of 84 * 84 pixels.
+    /// Four frames of shape `frame_shape`.
+    ///
+    /// Observations of environments are concatenated when the environments are vectorized
+    /// with [`VecEnv`](border_core::VecEnv).
     pub frames: Vec<u8>,
+
+    /// The shape `[channels, height, width]` of a frame,
+    /// given by [`BorderAtariEnv::frame_shape()`](super::BorderAtariEnv::frame_shape).
+    pub frame_shape: [usize; 3],
+}
+
+impl BorderAtariObs {
+    /// Constructs an observation from frames of the given shape.
+    pub fn new(frames: Vec<u8>, frame_shape: [usize; 3]) -> Self {
+        Self {
+            frames,
+            frame_shape,
+        }
+    }
+
+    /// Returns the number of bytes of an observation of an environment.
+    fn obs_in_bytes(&self) -> usize {
+        N_STACK * self.frame_shape.iter().product::<usize>()
+    }
+
+    /// Returns the shape `[n, 4, channels, height, width]` of the observation.
+    pub fn shape(&self) -> [usize; 5] {
+        let [c, h, w] = self.frame_shape;
+        [self.len(), N_STACK, c, h, w]
+    }
 }
 
+/// Frames are assumed to have the shape of the default preprocessing, i.e., `[1, 84, 84]`.
 impl From<Vec<u8>> for BorderAtariObs {
     fn from(frames: Vec<u8>) -> Self {
-        Self { frames }
+        Self::new(frames, DEFAULT_FRAME_SHAPE)
     }
 }
 
 impl Obs for BorderAtariObs {
     fn len(&self) -> usize {
-        1
+        self.frames.len() / self.obs_in_bytes()
+    }
+
+    /// Returns `[4, channels, height, width]`.
+    fn shape(&self) -> Option<Vec<usize>> {
+        Some(self.shape()[1..].to_vec())
+    }
+}
+
+impl StackObs for BorderAtariObs {
+    /// # Panics
+    ///
+    /// Panics if the observations have frames of different shapes.
+    fn stack(obs: Vec<Self>) -> Self {
+        let frame_shape = obs.first().map_or(DEFAULT_FRAME_SHAPE, |o| o.frame_shape);
+        let frames = obs
+            .into_iter()
+            .flat_map(|o| {
+                assert_eq!(o.frame_shape, frame_shape, "Shapes of frames differ");
+                o.frames
+            })
+            .collect();
+        Self::new(frames, frame_shape)
     }
 }
 
@@ -44,9 +106,9 @@ pub mod tch_ {
 
     impl From<BorderAtariObs> for Tensor {
         fn from(obs: BorderAtariObs) -> Tensor {
-            // Assumes the batch size is 1, implying non-vectorized environment
+            let shape = obs.shape().map(|d| d as i64);
             Tensor::from_slice(&obs.frames)
-                .reshape(&[1, 4, 1, 84, 84])
+                .reshape(&shape)
                 .to_kind(tch::Kind::Float)
         }
     }
@@ -67,12 +129,8 @@ pub mod canadle {
 
     impl From<BorderAtariObs> for Tensor {
         fn from(obs: BorderAtariObs) -> Tensor {
-            let tmp = obs.frames;
-            // Assumes the batch size is 1, implying non-vectorized environment
-            Tensor::from_vec(tmp, &[1 * 4 * 1 * 84 * 84], &Cpu)
-                .unwrap()
-                .reshape(&[1, 4, 1, 84, 84])
-                .unwrap()
+            let shape = obs.shape();
+            Tensor::from_vec(obs.frames, &shape, &Cpu).unwrap()
         }
     }
 
@@ -128,7 +128,7 @@ impl From<Act> for ActBatch {
         Self {
             n: 1,
             m: 1,
-            buf: vec![act.act],
+            buf: act.act,
         }
     }
 }
@@ -0,0 +1,381 @@
+//! Vectorized Atari environment running ALE instances on a pool of threads.
+use crate::{
+    BorderAtariAct, BorderAtariActRawFilter, BorderAtariEnv, BorderAtariEnvConfig, BorderAtariInfo,
+    BorderAtariObs, BorderAtariObsRawFilter,
+};
+use anyhow::{Context, Result};
+use border_core::{record::Record, Env, SplitAct, StackObs, Step};
+use serde::{Deserialize, Serialize};
+use std::{
+    ops::Range,
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+type Obs = BorderAtariObs;
+type Act = BorderAtariAct;
+type ObsFilter = BorderAtariObsRawFilter<Obs>;
+type ActFilter = BorderAtariActRawFilter<Act>;
+type RawEnv = BorderAtariEnv<Obs, Act, ObsFilter, ActFilter>;
+
+/// Configuration of [`BorderAtariVecEnv`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BorderAtariVecEnvConfig {
+    /// Configuration of each ALE instance.
+    pub env_config: BorderAtariEnvConfig<Obs, Act, ObsFilter, ActFilter>,
+
+    /// The number of ALE instances.
+    pub n_envs: usize,
+
+    /// The number of threads on which the instances are run.
+    /// If `None`, it is the number of instances, up to the available parallelism.
+    pub n_threads: Option<usize>,
+}
+
+impl Default for BorderAtariVecEnvConfig {
+    fn default() -> Self {
+        Self {
+            env_config: Default::default(),
+            n_envs: 8,
+            n_threads: None,
+        }
+    }
+}
+
+impl BorderAtariVecEnvConfig {
+    /// Sets the configuration of each ALE instance.
+    pub fn env_config(mut self, v: BorderAtariEnvConfig<Obs, Act, ObsFilter, ActFilter>) -> Self {
+        self.env_config = v;
+        self
+    }
+
+    /// Sets the number of ALE instances.
+    pub fn n_envs(mut self, v: usize) -> Self {
+        self.n_envs = v;
+        self
+    }
+
+    /// Sets the number of threads.
+    pub fn n_threads(mut self, v: Option<usize>) -> Self {
+        self.n_threads = v;
+        self
+    }
+
+    /// Returns the range of the indices of instances run on each thread.
+    fn ranges(&self) -> Vec<Range<usize>> {
+        let n_threads = self
+            .n_threads
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+            .clamp(1, self.n_envs.max(1));
+        let (n, rem) = (self.n_envs / n_threads, self.n_envs % n_threads);
+        let mut start = 0;
+        (0..n_threads)
+            .map(|i| {
+                let end = start + n + (i < rem) as usize;
+                let range = start..end;
+                start = end;
+                range
+            })
+            .collect()
+    }
+}
+
+/// Commands sent to worker threads.
+enum Command {
+    /// Runs a step of each instance, resetting it if the episode ends when the flag is `true`.
+    Step(Vec<Act>, bool),
+
+    /// Resets the instances with the flag `true`, and returns the observations of all instances.
+    Reset(Vec<bool>),
+
+    /// Resets the instances with the indices.
+    ResetWithIndex(Vec<usize>),
+}
+
+/// Responses of worker threads.
+enum Response {
+    Step(Vec<(Step<RawEnv>, Record)>),
+    Obs(Result<Vec<Obs>>),
+}
+
+/// Runs ALE instances until the channel of commands is closed.
+fn worker(mut envs: Vec<RawEnv>, rx: Receiver<Command>, tx: Sender<Response>) {
+    // The current observations, returned when the instances are not reset
+    let mut obs: Vec<Option<Obs>> = vec![None; envs.len()];
+
+    for command in rx.iter() {
+        let response = match command {
+            Command::Step(acts, with_reset) => Response::Step(
+                envs.iter_mut()
+                    .zip(acts.iter())
+                    .zip(obs.iter_mut())
+                    .map(|((env, act), o)| {
+                        let (step, record) = match with_reset {
+                            true => env.step_with_reset(act),
+                            false => env.step(act),
+                        };
+                        *o = Some(step.init_obs.as_ref().unwrap_or(&step.obs).clone());
+                        (step, record)
+                    })
+                    .collect(),
+            ),
+            Command::Reset(flags) => Response::Obs(
+                envs.iter_mut()
+                    .zip(flags)
+                    .zip(obs.iter_mut())
+                    .map(|((env, flag), o)| {
+                        if flag {
+                            *o = Some(env.reset(None)?);
+                        }
+                        o.clone()
+                            .context("Environment must be reset before the first step")
+                    })
+                    .collect(),
+            ),
+            Command::ResetWithIndex(ixs) => Response::Obs(
+                envs.iter_mut()
+                    .zip(ixs)
+                    .zip(obs.iter_mut())
+                    .map(|((env, ix), o)| {
+                        let obs = env.reset_with_index(ix)?;
+                        *o = Some(obs.clone());
+                        Ok(obs)
+                    })
+                    .collect(),
+            ),
+        };
+        if tx.send(response).is_err() {
+            break;
+        }
+    }
+}
+
+/// Runs ALE instances in parallel on a pool of threads.
+///
+/// [`BorderAtariVecEnvConfig::n_envs`] instances of [`BorderAtariEnv`] are distributed over
+/// [`BorderAtariVecEnvConfig::n_threads`] threads, where each thread runs its instances in turn.
+/// Unlike [`VecEnv`], which runs an environment on each thread, rollouts of a large number of
+/// instances can be collected for batched or asynchronous agents without spawning a thread,
+/// or a Python process, for each instance. The `i`-th instance is built with seed `seed + i`,
+/// which is given to the emulator.
+///
+/// An observation is the concatenation of the frames of the instances, i.e., a batch of `u8`
+/// frames of shape `[n_envs, 4, channels, height, width]` as [`BorderAtariObs`], and an
+/// action has an element for each instance. Rewards and flags of [`Step`] have an element for
+/// each instance, and the info is a vector of [`BorderAtariInfo`] of the instances. Scalars in
+/// the records of the instances are averaged.
+///
+/// As [`VecEnv`], [`Env::reset()`] with `is_done` resets the instances where `is_done[i] == 1`
+/// and [`Env::step_with_reset()`] resets the instances where the episode has ended, where
+/// `init_obs` of the returned [`Step`] holds the observations after the reset.
+///
+/// [`VecEnv`]: border_core::VecEnv
+pub struct BorderAtariVecEnv {
+    txs: Vec<Sender<Command>>,
+    rxs: Vec<Receiver<Response>>,
+    handles: Vec<JoinHandle<()>>,
+    ranges: Vec<Range<usize>>,
+}
+
+impl BorderAtariVecEnv {
+    fn recv(&self, i: usize) -> Response {
+        self.rxs[i]
+            .recv()
+            .expect("Worker thread of BorderAtariVecEnv terminated")
+    }
+
+    fn send(&self, i: usize, command: Command) {
+        self.txs[i]
+            .send(command)
+            .expect("Worker thread of BorderAtariVecEnv terminated");
+    }
+
+    /// Sends commands to all threads and stacks the returned observations.
+    fn reset_envs(&mut self, commands: Vec<Command>) -> Result<Obs> {
+        for (i, command) in commands.into_iter().enumerate() {
+            self.send(i, command);
+        }
+        let mut obs = Vec::with_capacity(self.n_envs());
+        for i in 0..self.txs.len() {
+            match self.recv(i) {
+                Response::Obs(o) => obs.extend(o?),
+                Response::Step(_) => unreachable!(),
+            }
+        }
+
+        Ok(Obs::stack(obs))
+    }
+
+    /// Runs a step of all instances.
+    fn step_envs(&mut self, a: &Act, with_reset: bool) -> (Step<Self>, Record) {
+        let n_envs = self.n_envs();
+        let mut acts = a.split(n_envs).into_iter();
+        for (i, range) in self.ranges.iter().enumerate() {
+            let acts = acts.by_ref().take(range.len()).collect();
+            self.send(i, Command::Step(acts, with_reset));
+        }
+
+        let mut obs = Vec::with_capacity(n_envs);
+        let mut init_obs = Vec::with_capacity(n_envs);
+        let mut reward = Vec::with_capacity(n_envs);
+        let mut is_terminated = Vec::with_capacity(n_envs);
+        let mut is_truncated = Vec::with_capacity(n_envs);
+        let mut info = Vec::with_capacity(n_envs);
+        let mut records = Vec::with_capacity(n_envs);
+        for i in 0..self.txs.len() {
+            let steps = match self.recv(i) {
+                Response::Step(steps) => steps,
+                Response::Obs(_) => unreachable!(),
+            };
+            for (step, record) in steps.into_iter() {
+                reward.extend(step.reward);
+                is_terminated.extend(step.is_terminated);
+                is_truncated.extend(step.is_truncated);
+                info.push(step.info);
+                init_obs.push(step.init_obs);
+                obs.push(step.obs);
+                records.push(record);
+            }
+        }
+
+        // Observations of all instances after resetting those where the episode has ended
+        let init_obs = match init_obs.iter().any(|o| o.is_some()) {
+            true => Some(Obs::stack(
+                init_obs
+                    .into_iter()
+                    .zip(obs.iter())
+                    .map(|(init_obs, obs)| init_obs.unwrap_or_else(|| obs.clone()))
+                    .collect(),
+            )),
+            false => None,
+        };
+        let step = Step::new(
+            Obs::stack(obs),
+            a.clone(),
+            reward,
+            is_terminated,
+            is_truncated,
+            info,
+            init_obs,
+        );
+
+        (step, Record::merge_mean(records))
+    }
+}
+
+impl Env for BorderAtariVecEnv {
+    type Config = BorderAtariVecEnvConfig;
+    type Obs = Obs;
+    type Act = Act;
+    type Info = Vec<BorderAtariInfo>;
+
+    /// Builds ALE instances on worker threads.
+    ///
+    /// Returns an error if any of the instances fails to be built.
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        if config.n_envs == 0 {
+            return Err(anyhow::anyhow!("The number of instances must be positive"));
+        }
+        let ranges = config.ranges();
+        let (mut txs, mut rxs, mut handles) = (vec![], vec![], vec![]);
+        let (tx_built, rx_built) = channel();
+
+        for (i, range) in ranges.iter().cloned().enumerate() {
+            let (tx_command, rx_command) = channel();
+            let (tx_response, rx_response) = channel();
+            let env_config = config.env_config.clone();
+            let tx_built = tx_built.clone();
+            let handle = thread::Builder::new()
+                .name(format!("atari-vec-env-{}", i))
+                .spawn(move || {
+                    let envs = range
+                        .map(|ix| RawEnv::build(&env_config, seed + ix as i64))
+                        .collect::<Result<Vec<_>>>();
+                    let envs = match envs {
+                        Ok(envs) => {
+                            let _ = tx_built.send(Ok(()));
+                            envs
+                        }
+                        Err(e) => {
+                            let _ = tx_built.send(Err(e));
+                            return;
+                        }
+                    };
+                    drop(tx_built);
+                    worker(envs, rx_command, tx_response);
+                })?;
+            txs.push(tx_command);
+            rxs.push(rx_response);
+            handles.push(handle);
+        }
+        drop(tx_built);
+
+        let env = Self {
+            txs,
+            rxs,
+            handles,
+            ranges,
+        };
+        for _ in 0..env.txs.len() {
+            rx_built
+                .recv()
+                .context("Worker thread of BorderAtariVecEnv terminated")??;
+        }
+
+        Ok(env)
+    }
+
+    /// Runs a step of the instances.
+    ///
+    /// Instances where the episode has ended are not reset in this method.
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.step_envs(a, false)
+    }
+
+    /// Runs a step and resets the instances where the episode has ended.
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.step_envs(a, true)
+    }
+
+    /// Resets the instances where `is_done[i] == 1`, or all instances if `is_done` is `None`.
+    ///
+    /// It returns the observations of all instances.
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        let commands = self
+            .ranges
+            .iter()
+            .map(|range| {
+                let flags = range
+                    .clone()
+                    .map(|i| is_done.is_none_or(|is_done| is_done[i] == 1))
+                    .collect();
+                Command::Reset(flags)
+            })
+            .collect();
+        self.reset_envs(commands)
+    }
+
+    /// Resets all instances, where the `i`-th instance is reset with index `ix + i`.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        let commands = self
+            .ranges
+            .iter()
+            .map(|range| Command::ResetWithIndex(range.clone().map(|i| ix + i).collect()))
+            .collect();
+        self.reset_envs(commands)
+    }
+
+    fn n_envs(&self) -> usize {
+        self.ranges.last().map_or(0, |range| range.end)
+    }
+}
+
+impl Drop for BorderAtariVecEnv {
+    fn drop(&mut self) {
+        // Closing the channels stops the worker threads
+        self.txs.clear();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
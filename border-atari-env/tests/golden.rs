@@ -0,0 +1,52 @@
+//! Golden trajectory tests of the dynamics of Atari environments with preprocessing.
+//!
+//! These tests require Atari ROMs under `ATARI_ROM_DIR` and are ignored by default.
+//! Run them with `cargo test -p border-atari-env -- --ignored`. They fail if the fixtures
+//! under `tests/golden` do not exist. Record the fixtures with
+//! `BORDER_UPDATE_GOLDEN=1 cargo test -p border-atari-env --test golden -- --ignored`
+//! on a machine with the ROMs, then review and commit them.
+use anyhow::Result;
+use border_atari_env::{
+    BorderAtariAct, BorderAtariActRawFilter, BorderAtariEnv, BorderAtariEnvConfig, BorderAtariObs,
+    BorderAtariObsRawFilter,
+};
+use border_core::assert_golden;
+
+type Obs = BorderAtariObs;
+type Act = BorderAtariAct;
+type ObsFilter = BorderAtariObsRawFilter<Obs>;
+type ActFilter = BorderAtariActRawFilter<Act>;
+type EnvConfig = BorderAtariEnvConfig<Obs, Act, ObsFilter, ActFilter>;
+type Env = BorderAtariEnv<Obs, Act, ObsFilter, ActFilter>;
+
+fn check(name: &str, n_acts: usize, eval: bool) -> Result<()> {
+    // Rewards are clipped in training mode only
+    let (config, mode) = match eval {
+        true => (EnvConfig::default().name(name).eval(), "eval"),
+        false => (EnvConfig::default().name(name), "train"),
+    };
+    let path = format!(
+        "{}/tests/golden/{}_{}.yaml",
+        env!("CARGO_MANIFEST_DIR"),
+        name,
+        mode
+    );
+    let actions = (0..2000)
+        .map(|t| (t / 4 % n_acts) as u8)
+        .collect::<Vec<_>>();
+    assert_golden::<Env, _>(path, &config, 42, actions)
+}
+
+#[test]
+#[ignore]
+fn test_golden_pong() -> Result<()> {
+    check("pong", 6, false)?;
+    check("pong", 6, true)
+}
+
+#[test]
+#[ignore]
+fn test_golden_breakout() -> Result<()> {
+    check("breakout", 4, false)?;
+    check("breakout", 4, true)
+}
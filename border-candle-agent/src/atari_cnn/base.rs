@@ -1,12 +1,15 @@
 use super::AtariCnnConfig;
-use crate::model::SubModel1;
+use crate::{
+    model::SubModel1,
+    onnx::{Attribute, ElemType, OnnxGraph, OnnxModel},
+};
 use anyhow::Result;
 use candle_core::{DType::F32, Device, Tensor};
 use candle_nn::{
     conv::Conv2dConfig,
     conv2d, linear,
     sequential::{seq, Sequential},
-    Module, VarBuilder,
+    Conv2d, Linear, Module, VarBuilder,
 };
 
 #[allow(clippy::upper_case_acronyms)]
@@ -18,6 +21,8 @@ pub struct AtariCnn {
     device: Device,
     seq: Sequential,
     skip_linear: bool,
+    convs: Vec<Conv2d>,
+    linears: Vec<Linear>,
 }
 
 impl AtariCnn {
@@ -28,33 +33,34 @@ impl AtariCnn {
         }
     }
 
-    fn create_net(vb: &VarBuilder, n_stack: i64, out_dim: i64) -> Result<Sequential> {
-        let seq = seq()
-            .add_fn(|xs| xs.squeeze(2)?.to_dtype(F32)? / 255.0)
-            .add(conv2d(n_stack as _, 32, 8, Self::stride(4), vb.pp("c1"))?)
-            .add_fn(|xs| xs.relu())
-            .add(conv2d(32, 64, 4, Self::stride(2), vb.pp("c2"))?)
-            .add_fn(|xs| xs.relu())
-            .add(conv2d(64, 64, 3, Self::stride(1), vb.pp("c3"))?)
-            .add_fn(|xs| xs.relu()?.flatten_from(1))
-            .add(linear(3136, 512, vb.pp("l1"))?)
-            .add_fn(|xs| xs.relu())
-            .add(linear(512, out_dim as _, vb.pp("l2"))?);
-
-        Ok(seq)
+    fn create_convs(vb: &VarBuilder, n_stack: i64) -> Result<Vec<Conv2d>> {
+        Ok(vec![
+            conv2d(n_stack as _, 32, 8, Self::stride(4), vb.pp("c1"))?,
+            conv2d(32, 64, 4, Self::stride(2), vb.pp("c2"))?,
+            conv2d(64, 64, 3, Self::stride(1), vb.pp("c3"))?,
+        ])
     }
 
-    fn create_net_wo_linear(vb: &VarBuilder, n_stack: i64) -> Result<Sequential> {
-        let seq = seq()
-            .add_fn(|xs| xs.squeeze(2)?.to_dtype(F32)? / 255.0)
-            .add(conv2d(n_stack as _, 32, 8, Self::stride(4), vb.pp("c1"))?)
-            .add_fn(|xs| xs.relu())
-            .add(conv2d(32, 64, 4, Self::stride(2), vb.pp("c2"))?)
-            .add_fn(|xs| xs.relu())
-            .add(conv2d(64, 64, 3, Self::stride(1), vb.pp("c3"))?)
-            .add_fn(|xs| xs.relu()?.flatten_from(1));
+    fn create_linears(vb: &VarBuilder, out_dim: i64) -> Result<Vec<Linear>> {
+        Ok(vec![
+            linear(3136, 512, vb.pp("l1"))?,
+            linear(512, out_dim as _, vb.pp("l2"))?,
+        ])
+    }
 
-        Ok(seq)
+    fn create_net(convs: &[Conv2d], linears: &[Linear]) -> Sequential {
+        let mut seq = seq().add_fn(|xs| xs.squeeze(2)?.to_dtype(F32)? / 255.0);
+        for conv in convs.iter() {
+            seq = seq.add(conv.clone()).add_fn(|xs| xs.relu());
+        }
+        seq = seq.add_fn(|xs| xs.flatten_from(1));
+        for (i, l) in linears.iter().enumerate() {
+            seq = seq.add(l.clone());
+            if i < linears.len() - 1 {
+                seq = seq.add_fn(|xs| xs.relu());
+            }
+        }
+        seq
     }
 }
 
@@ -74,12 +80,12 @@ impl SubModel1 for AtariCnn {
         let out_dim = config.out_dim;
         let device = vb.device().clone();
         let skip_linear = config.skip_linear;
-        let seq = if config.skip_linear {
-            Self::create_net_wo_linear(&vb, n_stack)
-        } else {
-            Self::create_net(&vb, n_stack, out_dim)
-        }
-        .unwrap();
+        let convs = Self::create_convs(&vb, n_stack).unwrap();
+        let linears = match skip_linear {
+            true => vec![],
+            false => Self::create_linears(&vb, out_dim).unwrap(),
+        };
+        let seq = Self::create_net(&convs, &linears);
 
         Self {
             n_stack,
@@ -87,6 +93,48 @@ impl SubModel1 for AtariCnn {
             device,
             seq,
             skip_linear,
+            convs,
+            linears,
+        }
+    }
+
+    fn input_as_tensor(input: &Self::Input) -> Option<&Tensor> {
+        Some(input)
+    }
+
+    fn input_from_tensor(xs: Tensor) -> Option<Self::Input> {
+        Some(xs)
+    }
+}
+
+impl OnnxModel for AtariCnn {
+    /// Stacked frames of `u8`, `[n_stack, 1, 84, 84]`.
+    fn onnx_input(&self) -> (ElemType, Vec<i64>) {
+        (ElemType::Uint8, vec![self.n_stack, 1, 84, 84])
+    }
+
+    /// Includes the scaling of pixel intensities.
+    fn add_to_graph(&self, graph: &mut OnnxGraph, input: &str) -> Result<Vec<String>> {
+        let axes = graph.add_ints("atari_cnn.squeeze_axes", &[2]);
+        let xs = graph.add_node("Squeeze", &[input, &axes], vec![]);
+        let xs = graph.add_node(
+            "Cast",
+            &[&xs],
+            vec![("to", Attribute::Int(ElemType::Float as i64))],
+        );
+        let scale = graph.add_scalar("atari_cnn.scale", 255.0);
+        let mut xs = graph.add_node("Div", &[&xs, &scale], vec![]);
+        for (i, conv) in self.convs.iter().enumerate() {
+            xs = graph.add_conv2d(&xs, conv, &format!("c{}", i + 1))?;
+            xs = graph.add_node("Relu", &[&xs], vec![]);
+        }
+        xs = graph.add_node("Flatten", &[&xs], vec![("axis", Attribute::Int(1))]);
+        for (i, l) in self.linears.iter().enumerate() {
+            xs = graph.add_linear(&xs, l, &format!("l{}", i + 1))?;
+            if i < self.linears.len() - 1 {
+                xs = graph.add_node("Relu", &[&xs], vec![]);
+            }
         }
+        Ok(vec![xs])
     }
 }
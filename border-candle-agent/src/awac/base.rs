@@ -1,9 +1,10 @@
 use super::AwacConfig;
 use crate::{
+    error::opt_result,
     model::{SubModel1, SubModel2},
     util::{
-        actor::GaussianActor, critic::MultiCritic, gamma_not_done, smooth_l1_loss, CriticLoss,
-        OutDim,
+        actor::GaussianActor, critic::MultiCritic, gamma_not_done, save_manifest, smooth_l1_loss,
+        CriticLoss, OutDim,
     },
 };
 use anyhow::Result;
@@ -66,7 +67,7 @@ where
     fn update_critic(&mut self, batch: R::Batch) -> Result<(f32, f32, f32, f32)> {
         let (loss, q_tgt_abs_mean, reward_mean, next_q_mean) = {
             // Extract items in the batch
-            let (obs, act, next_obs, reward, is_terminated, is_truncated, _, _) = batch.unpack();
+            let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
             let batch_size = reward.len();
             let reward = Tensor::from_slice(&reward[..], (batch_size,), &self.device)?;
 
@@ -75,12 +76,9 @@ where
 
             // Target
             let (tgt, reward, next_q) = {
-                let gamma_not_done = gamma_not_done(
-                    self.gamma as f32,
-                    is_terminated,
-                    Some(is_truncated),
-                    &self.device,
-                )?;
+                // Bootstrap on truncated transitions, e.g., timeouts in D4RL datasets
+                let gamma_not_done =
+                    gamma_not_done(self.gamma as f32, is_terminated, None, &self.device)?;
                 let next_act = self.actor.sample(&next_obs.clone().into(), self.train)?;
                 let next_q = self
                     .critic
@@ -305,7 +303,12 @@ where
     }
 
     fn opt_with_record(&mut self, buffer: &mut R) -> Record {
-        self.opt_(buffer).expect("Failed in Awac::opt_()")
+        self.try_opt_with_record(buffer)
+            .expect("Failed in Awac::opt_()")
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
+        Ok(opt_result(self.opt_(buffer))?)
     }
 
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
@@ -315,7 +318,9 @@ where
         let actor_path = self.actor.save(path.join("actor"))?;
         let (critic_path, critic_tgt_path) = self.critic.save(path.join("critic"))?;
 
-        Ok(vec![actor_path, critic_path, critic_tgt_path])
+        let mut paths = vec![actor_path, critic_path, critic_tgt_path];
+        save_manifest(path, "Awac", self.actor.policy_config(), &mut paths)?;
+        Ok(paths)
     }
 
     fn load_params(&mut self, path: &Path) -> Result<()> {
@@ -3,6 +3,6 @@ mod base;
 mod config;
 mod model;
 
-pub use base::Bc;
-pub use config::{BcActionType, BcConfig};
+pub use base::{AdvantageFn, Bc};
+pub use config::{AdvWeighting, BcActionType, BcConfig};
 pub use model::{BcModel, BcModelConfig};
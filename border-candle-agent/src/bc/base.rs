@@ -1,6 +1,11 @@
 //! Behavior cloning (BC) agent implemented with candle.
-use super::{BcActionType, BcConfig, BcModel};
-use crate::{model::SubModel1, util::OutDim};
+use super::{AdvWeighting, BcActionType, BcConfig, BcModel};
+use crate::{
+    error::opt_result,
+    model::SubModel1,
+    onnx::{ElemType, OnnxGraph, OnnxModel},
+    util::{find_params, gamma_not_done, params_path, reward, save_manifest, OutDim},
+};
 use anyhow::Result;
 use border_core::{
     record::{Record, RecordValue},
@@ -15,16 +20,28 @@ use std::{
     path::{Path, PathBuf},
 };
 
+/// A function returning advantages of actions, given observations and actions in a batch.
+///
+/// The returned tensor has the shape `(batch_size,)`.
+pub type AdvantageFn<I> = Box<dyn FnMut(&I, &Tensor) -> Result<Tensor>>;
+
 #[allow(dead_code)]
 /// Behavior cloning (BC) agent implemented with candle.
 ///
 /// `P` is the type parameter of the policy model.
+///
+/// Optionally, a value model is co-trained and samples are weighted by advantages.
+/// See [`BcConfig`].
 pub struct Bc<E, P, R>
 where
     P: SubModel1<Output = Tensor>,
     P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
 {
     policy_model: BcModel<P>,
+    value_model: Option<BcModel<P>>,
+    gamma: f64,
+    adv_weighting: Option<AdvWeighting>,
+    advantage_fn: Option<AdvantageFn<P::Input>>,
     batch_size: usize,
     action_type: BcActionType,
     device: Device,
@@ -32,6 +49,36 @@ where
     phantom: PhantomData<(E, R)>,
 }
 
+impl<E, P, R> Bc<E, P, R>
+where
+    P: SubModel1<Output = Tensor> + OnnxModel,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Exports the policy to ONNX, see [`crate::onnx`].
+    ///
+    /// The graph outputs the actions `action` taken by the agent. With
+    /// [`BcActionType::Discrete`], they are the indices of the maximum outputs of the policy model,
+    /// of `i64`, `[batch]`. With [`BcActionType::Continuous`], they are the outputs of the policy
+    /// model, `[batch, action_dim]`. The value model is not exported.
+    pub fn export_onnx(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut graph = OnnxGraph::new("bc");
+        let (elem_type, shape) = self.policy_model.onnx_input();
+        let obs = graph.add_input("obs", elem_type, &shape);
+        let xs = self.policy_model.add_to_graph(&mut graph, &obs)?.remove(0);
+        match self.action_type {
+            BcActionType::Discrete => {
+                let act = graph.add_argmax(&xs);
+                graph.add_output("action", ElemType::Int64, &[], &act);
+            }
+            BcActionType::Continuous => {
+                let out_dim = self.policy_model.out_dim();
+                graph.add_output("action", ElemType::Float, &[out_dim], &xs);
+            }
+        }
+        graph.save(path)
+    }
+}
+
 impl<E, P, R> Policy<E> for Bc<E, P, R>
 where
     E: Env,
@@ -76,9 +123,16 @@ where
             .into();
         let policy_model =
             BcModel::build(config.policy_model_config.clone(), device.clone()).unwrap();
+        let value_model = config
+            .value_model_config
+            .map(|c| BcModel::build(c, device.clone()).unwrap());
 
         Self {
             policy_model,
+            value_model,
+            gamma: config.gamma,
+            adv_weighting: config.adv_weighting,
+            advantage_fn: None,
             batch_size: config.batch_size,
             action_type: config.action_type,
             device,
@@ -112,12 +166,22 @@ where
     }
 
     fn opt(&mut self, buffer: &mut R) {
-        self.opt_(buffer);
+        self.try_opt(buffer).expect("Failed to optimize BC agent");
     }
 
     fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.try_opt_with_record(buffer)
+            .expect("Failed to optimize BC agent")
+    }
+
+    fn try_opt(&mut self, buffer: &mut R) -> Result<()> {
+        opt_result(self.opt_(buffer))?;
+        Ok(())
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
         let record = {
-            let record = self.opt_(buffer);
+            let record = opt_result(self.opt_(buffer))?;
 
             match self.record_verbose_level >= 2 {
                 true => {
@@ -129,26 +193,39 @@ where
             }
         };
 
-        record
+        Ok(record)
     }
 
     /// Save model parameters in the given directory.
     ///
-    /// The parameters of the policy_model are saved as `policy_model.pt`.
+    /// The parameters of the policy_model are saved as `policy_model.safetensors`,
+    /// and those of the value model, if any, as `value_model.safetensors`.
+    /// [`ModelManifest`](border_core::ModelManifest) is saved as `manifest.yaml`.
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
         // TODO: consider to rename the path if it already exists
         fs::create_dir_all(&path)?;
-        let path = path.join("policy_model.pt").to_path_buf();
-        self.policy_model.save(&path)?;
-        Ok(vec![path])
+        let mut paths = vec![params_path(path.join("policy_model"))];
+        self.policy_model.save(&paths[0])?;
+        if let Some(value_model) = self.value_model.as_ref() {
+            paths.push(params_path(path.join("value_model")));
+            value_model.save(&paths[1])?;
+        }
+        let config = self.policy_model.policy_model_config();
+        save_manifest(path, "Bc", config, &mut paths)?;
+        Ok(paths)
     }
 
     /// Load model parameters in the given directory.
     ///
-    /// The parameters of the policy_model are loaded from `policy_model.pt`.
+    /// The parameters of the policy_model are loaded from `policy_model.safetensors`,
+    /// and those of the value model, if any, from `value_model.safetensors`.
+    /// Files with the extension `.pt` saved by older versions are also loaded.
     fn load_params(&mut self, path: &Path) -> Result<()> {
         self.policy_model
-            .load(&path.join("policy_model.pt").as_path())?;
+            .load(find_params(path.join("policy_model")))?;
+        if let Some(value_model) = self.value_model.as_mut() {
+            value_model.load(find_params(path.join("value_model")))?;
+        }
         Ok(())
     }
 }
@@ -163,25 +240,109 @@ where
     <R::Batch as TransitionBatch>::ObsBatch: Into<P::Input>,
     <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
 {
+    /// Sets the function giving advantages for weighting samples, e.g., with a critic
+    /// trained beforehand.
+    ///
+    /// It is used instead of the TD errors of the value model when
+    /// [`BcConfig::adv_weighting`] is set.
+    pub fn advantage_fn(mut self, f: AdvantageFn<P::Input>) -> Self {
+        self.advantage_fn = Some(f);
+        self
+    }
+
+    /// Updates the value model with TD targets and returns TD errors as advantages.
+    fn update_value(
+        &mut self,
+        obs: &P::Input,
+        next_obs: &P::Input,
+        reward: &Tensor,
+        gamma_not_done: &Tensor,
+        record: &mut Record,
+    ) -> Result<Option<Tensor>> {
+        let value_model = match self.value_model.as_mut() {
+            Some(value_model) => value_model,
+            None => return Ok(None),
+        };
+        let v = value_model.forward(obs).squeeze(D::Minus1)?;
+        let next_v = value_model.forward(next_obs).squeeze(D::Minus1)?.detach();
+        let tgt = (reward + (gamma_not_done * next_v)?)?;
+        let loss = mse(&v, &tgt)?;
+        value_model.backward_step(&loss)?;
+        record.insert("loss_value", RecordValue::Scalar(loss.to_scalar()?));
+
+        Ok(Some((tgt - v)?.detach()))
+    }
+
+    /// Returns weights of samples in the batch, or `None` if samples are not weighted.
+    fn weights(
+        &mut self,
+        obs: &P::Input,
+        act: &Tensor,
+        td_errors: Option<Tensor>,
+        record: &mut Record,
+    ) -> Result<Option<Tensor>> {
+        let config = match self.adv_weighting.as_ref() {
+            Some(config) => config.clone(),
+            None => return Ok(None),
+        };
+        let adv = match (self.advantage_fn.as_mut(), td_errors) {
+            (Some(f), _) => f(obs, act)?.detach(),
+            (None, Some(td_errors)) => td_errors,
+            (None, None) => anyhow::bail!(
+                "Advantage weighting requires the value model or the advantage function"
+            ),
+        };
+        let w = (adv.to_device(&self.device)? * config.inv_lambda)?
+            .exp()?
+            .clamp(0f64, config.exp_adv_max)?;
+        record.insert(
+            "adv_mean",
+            RecordValue::Scalar(adv.mean_all()?.to_scalar::<f32>()?),
+        );
+        record.insert(
+            "weight_mean",
+            RecordValue::Scalar(w.mean_all()?.to_scalar::<f32>()?),
+        );
+        Ok(Some(w))
+    }
+
     // Currently, this method supports only continuous action.
-    fn opt_(&mut self, buffer: &mut R) -> Record {
-        let batch = buffer.batch(self.batch_size).unwrap();
-        let (obs, act, _, _, _, _, _, _) = batch.unpack();
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let batch = buffer.batch(self.batch_size)?;
+        let (obs, act, next_obs, reward_, is_terminated, _, _, _) = batch.unpack();
         let obs = obs.into();
-        let act = act.into().to_device(&self.device).unwrap();
+        let act = act.into().to_device(&self.device)?;
+        let mut record = Record::empty();
+
+        // Value model and weights of samples
+        let td_errors = match self.value_model.is_some() {
+            true => {
+                let reward = reward(reward_, &self.device)?;
+                // Bootstrap on truncated transitions
+                let gnd = gamma_not_done(self.gamma as f32, is_terminated, None, &self.device)?;
+                self.update_value(&obs, &next_obs.into(), &reward, &gnd, &mut record)?
+            }
+            false => None,
+        };
+        let w = self.weights(&obs, &act, td_errors, &mut record)?;
+
         let loss = match self.action_type {
             BcActionType::Discrete => {
                 panic!();
             }
             BcActionType::Continuous => {
                 let act_ = self.policy_model.forward(&obs);
-                mse(&act_, &act)
+                match w {
+                    None => mse(&act_, &act)?,
+                    Some(w) => {
+                        let se = (act_ - act)?.sqr()?.mean(D::Minus1)?;
+                        (se * w)?.mean_all()?
+                    }
+                }
             }
-        }
-        .unwrap();
-        self.policy_model.backward_step(&loss).unwrap();
+        };
+        self.policy_model.backward_step(&loss)?;
 
-        let mut record = Record::empty();
         record.insert(
             "loss",
             RecordValue::Scalar(
@@ -193,6 +354,6 @@ where
                     .unwrap(),
             ),
         );
-        record
+        Ok(record)
     }
 }
@@ -23,9 +23,43 @@ pub enum BcActionType {
     Continuous,
 }
 
+/// Weighting of samples by advantages in [`Bc`](super::Bc) agent.
+///
+/// The loss of each sample is weighted by `exp(inv_lambda * advantage)`, clipped by
+/// `exp_adv_max`, as the actor loss of AWAC.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct AdvWeighting {
+    /// The inverse of the temperature of weights.
+    pub inv_lambda: f64,
+
+    /// Maximum of weights.
+    pub exp_adv_max: f64,
+}
+
+impl Default for AdvWeighting {
+    fn default() -> Self {
+        Self {
+            inv_lambda: 10.0,
+            exp_adv_max: 100.0,
+        }
+    }
+}
+
+fn default_gamma() -> f64 {
+    0.99
+}
+
 /// Configuration of [`Bc`](super::Bc) agent.
 ///
 /// `P` is the type parameter of the policy model.
+///
+/// With `value_model_config`, a value model of the same architecture as the policy model,
+/// whose output dimension should be 1, is trained with TD targets along with the policy.
+/// With `adv_weighting`, samples are weighted by their advantages, turning the agent into
+/// advantage-weighted BC. Advantages are the TD errors of the value model, or given by
+/// a critic trained beforehand, e.g., that of AWAC or IQL, with [`Bc::advantage_fn()`].
+///
+/// [`Bc::advantage_fn()`]: super::Bc::advantage_fn
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct BcConfig<P>
 where
@@ -37,6 +71,19 @@ where
     pub action_type: BcActionType,
     pub device: Option<Device>,
     pub record_verbose_level: usize,
+
+    /// Configuration of the value model co-trained with the policy model.
+    #[serde(default)]
+    pub value_model_config: Option<BcModelConfig<P::Config>>,
+
+    /// Discount factor of TD targets of the value model.
+    #[serde(default = "default_gamma")]
+    pub gamma: f64,
+
+    /// Weighting of samples by advantages.
+    #[serde(default)]
+    pub adv_weighting: Option<AdvWeighting>,
+
     pub phantom: PhantomData<P>,
 }
 
@@ -52,6 +99,9 @@ where
             action_type: self.action_type.clone(),
             device: self.device.clone(),
             record_verbose_level: self.record_verbose_level,
+            value_model_config: self.value_model_config.clone(),
+            gamma: self.gamma,
+            adv_weighting: self.adv_weighting.clone(),
             phantom: PhantomData,
         }
     }
@@ -70,6 +120,9 @@ where
             action_type: BcActionType::Discrete,
             device: None,
             record_verbose_level: 0,
+            value_model_config: None,
+            gamma: default_gamma(),
+            adv_weighting: None,
             phantom: PhantomData,
         }
     }
@@ -111,6 +164,24 @@ where
         self
     }
 
+    /// Sets the configuration of the value model co-trained with the policy model.
+    pub fn value_model_config(mut self, v: BcModelConfig<P::Config>) -> Self {
+        self.value_model_config = Some(v);
+        self
+    }
+
+    /// Sets the discount factor of TD targets of the value model.
+    pub fn gamma(mut self, v: f64) -> Self {
+        self.gamma = v;
+        self
+    }
+
+    /// Sets the weighting of samples by advantages.
+    pub fn adv_weighting(mut self, v: AdvWeighting) -> Self {
+        self.adv_weighting = Some(v);
+        self
+    }
+
     /// Sets optimizer.
     pub fn optimizer(mut self, opt_config: OptimizerConfig) -> Self {
         self.policy_model_config.opt_config = opt_config;
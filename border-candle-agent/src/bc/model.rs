@@ -1,5 +1,6 @@
 use crate::{
     model::SubModel1,
+    onnx::{ElemType, OnnxGraph, OnnxModel},
     opt::{Optimizer, OptimizerConfig},
     util::OutDim,
 };
@@ -176,6 +177,16 @@ where
         self.policy_model.forward(obs)
     }
 
+    /// Returns the dimension of the output vector.
+    pub fn out_dim(&self) -> i64 {
+        self.out_dim
+    }
+
+    /// Returns the configuration of the policy model.
+    pub fn policy_model_config(&self) -> &P::Config {
+        &self.policy_model_config
+    }
+
     pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
         // Consider to use gradient clipping, below code
         // let mut grads = loss.backward()?;
@@ -237,3 +248,17 @@ where
         )
     }
 }
+
+impl<P> OnnxModel for BcModel<P>
+where
+    P: SubModel1<Output = Tensor> + OnnxModel,
+    P::Config: DeserializeOwned + Serialize + OutDim,
+{
+    fn onnx_input(&self) -> (ElemType, Vec<i64>) {
+        self.policy_model.onnx_input()
+    }
+
+    fn add_to_graph(&self, graph: &mut OnnxGraph, input: &str) -> Result<Vec<String>> {
+        self.policy_model.add_to_graph(graph, input)
+    }
+}
@@ -0,0 +1,21 @@
+//! Bootstrapped DQN agent.
+//!
+//! Bootstrapped DQN ([Osband et al., 2016](https://arxiv.org/abs/1602.04621)) trains an
+//! ensemble of Q-functions, called heads, on bootstrap samples of the shared replay buffer.
+//! During training, a head is chosen at random at the beginning of each episode and the agent
+//! acts greedily with respect to it, which gives temporally consistent deep exploration.
+//! In evaluation, actions are chosen by majority vote of the heads.
+//!
+//! The bootstrap masks are stored in the replay buffer with each transition;
+//! enable them with [`SimpleReplayBufferConfig::bootstrap()`], setting the number of heads
+//! to that of the agent.
+//!
+//! The heads are implemented as a single Q-network whose output dimension is
+//! `n_heads * n_actions`, so any model for [`Dqn`](crate::dqn::Dqn) can be used.
+//! For example, an MLP with shared hidden layers gives heads with a shared torso.
+//!
+//! [`SimpleReplayBufferConfig::bootstrap()`]: border_core::generic_replay_buffer::SimpleReplayBufferConfig::bootstrap
+mod base;
+mod config;
+pub use base::BootstrappedDqn;
+pub use config::BootstrappedDqnConfig;
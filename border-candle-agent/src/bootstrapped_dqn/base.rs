@@ -0,0 +1,371 @@
+//! Bootstrapped DQN agent implemented with candle.
+use super::config::BootstrappedDqnConfig;
+use crate::{
+    dqn::DqnModel,
+    error::opt_result,
+    model::SubModel1,
+    util::{find_params, params_path, save_manifest, track, CriticLoss, OutDim},
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+};
+use candle_core::{shape::D, DType, Device, Tensor};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, marker::PhantomData, path::Path, path::PathBuf};
+
+#[allow(clippy::upper_case_acronyms)]
+/// Bootstrapped DQN agent implemented with candle.
+///
+/// The Q-network outputs `n_heads * n_actions` values for each observation,
+/// which are interpreted as Q-values of `n_heads` heads.
+pub struct BootstrappedDqn<E, Q, R>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    soft_update_interval: usize,
+    soft_update_counter: usize,
+    n_updates_per_opt: usize,
+    batch_size: usize,
+    qnet: DqnModel<Q>,
+    qnet_tgt: DqnModel<Q>,
+    n_heads: usize,
+    active_head: usize,
+    train: bool,
+    phantom: PhantomData<(E, R)>,
+    discount_factor: f64,
+    tau: f64,
+    device: Device,
+    n_opts: usize,
+    double_dqn: bool,
+    critic_loss: CriticLoss,
+    record_verbose_level: usize,
+    rng: SmallRng,
+}
+
+impl<E, Q, R> BootstrappedDqn<E, Q, R>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Returns the index of the head used for acting in the current episode.
+    pub fn active_head(&self) -> usize {
+        self.active_head
+    }
+
+    /// Reshapes the output of the Q-network into `[batch_size, n_heads, n_actions]`.
+    fn heads(&self, x: Tensor) -> Tensor {
+        let (batch_size, out_dim) = x.dims2().unwrap();
+        x.reshape((batch_size, self.n_heads, out_dim / self.n_heads))
+            .unwrap()
+    }
+}
+
+impl<E, Q, R> BootstrappedDqn<E, Q, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    R: ReplayBufferBase,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn update_critic(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut record = Record::empty();
+        let batch = buffer.batch(self.batch_size)?;
+
+        // Bootstrap masks of shape [batch_size, n_heads]; all transitions are used
+        // for all heads if the replay buffer does not provide masks
+        let mask = match batch.bootstrap_mask() {
+            Some(mask) => {
+                let batch_size = mask.len() / self.n_heads;
+                Tensor::from_slice(&mask[..], &[batch_size, self.n_heads], &self.device)?
+            }
+            None => Tensor::ones(&[self.batch_size, self.n_heads], DType::F32, &self.device)?,
+        };
+
+        let (obs, act, next_obs, reward, is_terminated, _is_truncated, _ixs, _weight) =
+            batch.unpack();
+        let obs = obs.into();
+        let act = act.into().to_device(&self.device)?;
+        let next_obs = next_obs.into();
+        let batch_size = reward.len();
+        let reward = Tensor::from_slice(&reward[..], &[batch_size, 1], &self.device)?;
+        let is_not_terminated = {
+            let is_not_terminated = is_terminated
+                .into_iter()
+                .map(|v| (1 - v) as f32)
+                .collect::<Vec<_>>();
+            Tensor::from_slice(&is_not_terminated[..], &[batch_size, 1], &self.device)?
+        };
+
+        // Action indices broadcast over heads: [batch_size, n_heads, 1]
+        let act = act
+            .reshape((batch_size, 1, 1))?
+            .broadcast_as((batch_size, self.n_heads, 1))?
+            .contiguous()?;
+
+        let pred = {
+            let x = self.heads(self.qnet.forward(&obs));
+            x.gather(&act, D::Minus1)?.squeeze(D::Minus1)?
+        };
+
+        let tgt = {
+            let q = if self.double_dqn {
+                let x = self.heads(self.qnet.forward(&next_obs));
+                let y = x.argmax_keepdim(D::Minus1)?;
+                let tgt = self.heads(self.qnet_tgt.forward(&next_obs));
+                tgt.gather(&y, D::Minus1)?
+            } else {
+                let x = self.heads(self.qnet_tgt.forward(&next_obs));
+                x.max_keepdim(D::Minus1)?
+            }
+            .squeeze(D::Minus1)?;
+
+            let q = (is_not_terminated * self.discount_factor)?.broadcast_mul(&q)?;
+            reward.broadcast_add(&q)
+        }?
+        .detach();
+
+        if self.record_verbose_level >= 2 {
+            record.insert(
+                "pred_mean",
+                RecordValue::Scalar(pred.mean_all()?.to_vec0::<f32>()?),
+            );
+            record.insert(
+                "tgt_mean",
+                RecordValue::Scalar(tgt.mean_all()?.to_vec0::<f32>()?),
+            );
+        }
+
+        // Elementwise loss of shape [batch_size, n_heads], averaged over unmasked elements
+        let loss = {
+            let d = (&pred - &tgt)?;
+            let loss = match self.critic_loss {
+                CriticLoss::Mse => d.sqr()?,
+                CriticLoss::SmoothL1 => {
+                    let d = d.abs()?;
+                    let m = d.lt(1.0)?.to_dtype(DType::F32)?;
+                    let l1 = (&m * (0.5 * d.sqr()?)?)?;
+                    let l2 = ((1.0 - &m)? * (d - 0.5)?)?;
+                    (l1 + l2)?
+                }
+            };
+            let n = mask.sum_all()?.to_scalar::<f32>()?.max(1.0);
+            ((loss * &mask)?.sum_all()? / n as f64)?
+        };
+
+        // Backprop
+        self.qnet.backward_step(&loss)?;
+
+        record.insert("loss", RecordValue::Scalar(loss.to_scalar::<f32>()?));
+
+        Ok(record)
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut record_ = Record::empty();
+
+        for _ in 0..self.n_updates_per_opt {
+            let record = self.update_critic(buffer)?;
+            record_ = record_.merge(record);
+        }
+
+        self.soft_update_counter += 1;
+        if self.soft_update_counter == self.soft_update_interval {
+            self.soft_update_counter = 0;
+            let _ = track(self.qnet_tgt.get_varmap(), self.qnet.get_varmap(), self.tau);
+        }
+
+        self.n_opts += 1;
+
+        Ok(record_)
+    }
+}
+
+impl<E, Q, R> Policy<E> for BootstrappedDqn<E, Q, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    E::Obs: Into<Q::Input>,
+    E::Act: From<Q::Output>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// In training mode, takes the greedy action of the active head.
+    /// In evaluation mode, takes the action with the most votes of the heads,
+    /// where each head votes for its greedy action.
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        let x = self.qnet.forward(&obs.clone().into()).detach();
+        let x = self.heads(x);
+        let a = if self.train {
+            x.narrow(1, self.active_head, 1)
+                .unwrap()
+                .squeeze(1)
+                .unwrap()
+                .argmax(D::Minus1)
+                .unwrap()
+                .to_dtype(DType::I64)
+                .unwrap()
+        } else {
+            let (_, _, n_actions) = x.dims3().unwrap();
+            let votes = x
+                .argmax(D::Minus1)
+                .unwrap()
+                .to_dtype(DType::I64)
+                .unwrap()
+                .to_vec2::<i64>()
+                .unwrap();
+            let a = votes
+                .into_iter()
+                .map(|heads| {
+                    let mut counts = vec![0usize; n_actions];
+                    heads.into_iter().for_each(|a| counts[a as usize] += 1);
+                    // Ties are broken by the smallest action index
+                    let max = *counts.iter().max().unwrap();
+                    counts.iter().position(|&c| c == max).unwrap() as i64
+                })
+                .collect::<Vec<_>>();
+            Tensor::new(a, &self.device).unwrap()
+        };
+        a.into()
+    }
+}
+
+impl<E, Q, R> Configurable for BootstrappedDqn<E, Q, R>
+where
+    E: Env,
+    Q: SubModel1<Output = Tensor>,
+    E::Obs: Into<Q::Input>,
+    E::Act: From<Q::Output>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = BootstrappedDqnConfig<Q>;
+
+    /// Constructs bootstrapped DQN agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for bootstrapped DQN agent")
+            .into();
+        let out_dim = config.model_config.q_config.as_ref().unwrap().get_out_dim();
+        assert_eq!(
+            out_dim % config.n_heads as i64,
+            0,
+            "Output dimension of the Q-network must be a multiple of n_heads"
+        );
+        let qnet = DqnModel::build(config.model_config.clone(), device.clone()).unwrap();
+        let qnet_tgt = DqnModel::build(config.model_config.clone(), device.clone()).unwrap();
+        let _ = track(qnet_tgt.get_varmap(), qnet.get_varmap(), 1.0);
+        let mut rng = SmallRng::seed_from_u64(config.seed);
+        let active_head = rng.gen_range(0..config.n_heads);
+
+        BootstrappedDqn {
+            qnet,
+            qnet_tgt,
+            n_heads: config.n_heads,
+            active_head,
+            soft_update_interval: config.soft_update_interval,
+            soft_update_counter: 0,
+            n_updates_per_opt: config.n_updates_per_opt,
+            batch_size: config.batch_size,
+            discount_factor: config.discount_factor,
+            tau: config.tau,
+            train: config.train,
+            device,
+            n_opts: 0,
+            double_dqn: config.double_dqn,
+            critic_loss: config.critic_loss,
+            phantom: PhantomData,
+            record_verbose_level: config.record_verbose_level,
+            rng,
+        }
+    }
+}
+
+impl<E, Q, R> Agent<E, R> for BootstrappedDqn<E, Q, R>
+where
+    E: Env + 'static,
+    Q: SubModel1<Output = Tensor> + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<Q::Input>,
+    E::Act: From<Q::Output>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt(&mut self, buffer: &mut R) {
+        self.try_opt(buffer)
+            .expect("Failed in BootstrappedDqn::opt_()");
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.try_opt_with_record(buffer)
+            .expect("Failed in BootstrappedDqn::opt_()")
+    }
+
+    fn try_opt(&mut self, buffer: &mut R) -> Result<()> {
+        opt_result(self.opt_(buffer))?;
+        Ok(())
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
+        let record = opt_result(self.opt_(buffer))?;
+
+        Ok(match self.record_verbose_level >= 2 {
+            true => record.merge(self.qnet.param_stats()),
+            false => record,
+        })
+    }
+
+    /// Samples the head used for acting in the next episode.
+    fn on_episode_end(&mut self) {
+        self.active_head = self.rng.gen_range(0..self.n_heads);
+    }
+
+    /// Save model parameters in the given directory.
+    ///
+    /// The parameters of the model are saved as `qnet.safetensors`.
+    /// The parameters of the target model are saved as `qnet_tgt.safetensors`.
+    /// [`ModelManifest`](border_core::ModelManifest) is saved as `manifest.yaml`.
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+        let path1 = params_path(path.join("qnet"));
+        let path2 = params_path(path.join("qnet_tgt"));
+        self.qnet.save(&path1)?;
+        self.qnet_tgt.save(&path2)?;
+        let mut paths = vec![path1, path2];
+        save_manifest(path, "BootstrappedDqn", self.qnet.q_config(), &mut paths)?;
+        Ok(paths)
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.qnet.load(find_params(path.join("qnet")))?;
+        self.qnet_tgt.load(find_params(path.join("qnet_tgt")))?;
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
@@ -0,0 +1,209 @@
+//! Configuration of bootstrapped DQN agent.
+use crate::{
+    dqn::DqnModelConfig,
+    model::SubModel1,
+    util::{CriticLoss, OutDim},
+    Device,
+};
+use anyhow::Result;
+use candle_core::Tensor;
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    default::Default,
+    fs::File,
+    io::{BufReader, Write},
+    marker::PhantomData,
+    path::Path,
+};
+
+/// Configuration of [`BootstrappedDqn`](super::BootstrappedDqn) agent.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct BootstrappedDqnConfig<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Configuration of the Q-network, whose output dimension is `n_heads * n_actions`.
+    pub model_config: DqnModelConfig<Q::Config>,
+    pub n_heads: usize,
+    pub soft_update_interval: usize,
+    pub n_updates_per_opt: usize,
+    pub batch_size: usize,
+    pub discount_factor: f64,
+    pub tau: f64,
+    pub train: bool,
+    #[serde(default)]
+    pub double_dqn: bool,
+    pub device: Option<Device>,
+    pub critic_loss: CriticLoss,
+    pub record_verbose_level: usize,
+    pub seed: u64,
+    pub phantom: PhantomData<Q>,
+}
+
+impl<Q> Clone for BootstrappedDqnConfig<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            model_config: self.model_config.clone(),
+            n_heads: self.n_heads,
+            soft_update_interval: self.soft_update_interval,
+            n_updates_per_opt: self.n_updates_per_opt,
+            batch_size: self.batch_size,
+            discount_factor: self.discount_factor,
+            tau: self.tau,
+            train: self.train,
+            double_dqn: self.double_dqn,
+            device: self.device,
+            critic_loss: self.critic_loss.clone(),
+            record_verbose_level: self.record_verbose_level,
+            seed: self.seed,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<Q> Default for BootstrappedDqnConfig<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Constructs bootstrapped DQN builder with default parameters.
+    fn default() -> Self {
+        Self {
+            model_config: Default::default(),
+            n_heads: 10,
+            soft_update_interval: 1,
+            n_updates_per_opt: 1,
+            batch_size: 1,
+            discount_factor: 0.99,
+            tau: 0.005,
+            train: false,
+            double_dqn: false,
+            device: None,
+            critic_loss: CriticLoss::Mse,
+            record_verbose_level: 0,
+            seed: 42,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<Q> BootstrappedDqnConfig<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Sets the number of heads.
+    ///
+    /// It must be equal to the number of heads of the bootstrap masks in the replay buffer.
+    pub fn n_heads(mut self, v: usize) -> Self {
+        self.n_heads = v;
+        self
+    }
+
+    /// Sets soft update interval.
+    pub fn soft_update_interval(mut self, v: usize) -> Self {
+        self.soft_update_interval = v;
+        self
+    }
+
+    /// Sets the numper of parameter update steps per optimization step.
+    pub fn n_updates_per_opt(mut self, v: usize) -> Self {
+        self.n_updates_per_opt = v;
+        self
+    }
+
+    /// Batch size.
+    pub fn batch_size(mut self, v: usize) -> Self {
+        self.batch_size = v;
+        self
+    }
+
+    /// Discount factor.
+    pub fn discount_factor(mut self, v: f64) -> Self {
+        self.discount_factor = v;
+        self
+    }
+
+    /// Soft update coefficient.
+    pub fn tau(mut self, v: f64) -> Self {
+        self.tau = v;
+        self
+    }
+
+    /// Sets the configuration of the model.
+    pub fn model_config(mut self, model_config: DqnModelConfig<Q::Config>) -> Self {
+        self.model_config = model_config;
+        self
+    }
+
+    /// Sets the number of actions.
+    ///
+    /// The output dimension of the Q-network is set to `n_heads * n_actions`,
+    /// so this method must be called after [`BootstrappedDqnConfig::n_heads()`].
+    pub fn n_actions(mut self, n_actions: i64) -> Self {
+        let model_config = self.model_config.clone();
+        self.model_config = model_config.out_dim(n_actions * self.n_heads as i64);
+        self
+    }
+
+    /// Double DQN
+    pub fn double_dqn(mut self, double_dqn: bool) -> Self {
+        self.double_dqn = double_dqn;
+        self
+    }
+
+    /// Device.
+    pub fn device(mut self, device: candle_core::Device) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Sets critic loss.
+    pub fn critic_loss(mut self, v: CriticLoss) -> Self {
+        self.critic_loss = v;
+        self
+    }
+
+    /// Sets verbose level.
+    pub fn record_verbose_level(mut self, v: usize) -> Self {
+        self.record_verbose_level = v;
+        self
+    }
+
+    /// Sets the random seed for choosing heads.
+    pub fn seed(mut self, v: u64) -> Self {
+        self.seed = v;
+        self
+    }
+
+    /// Loads [`BootstrappedDqnConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path_ = path.as_ref().to_owned();
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        info!(
+            "Load config of bootstrapped DQN agent from {}",
+            path_.to_str().unwrap()
+        );
+        Ok(b)
+    }
+
+    /// Saves [`BootstrappedDqnConfig`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path_ = path.as_ref().to_owned();
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        info!(
+            "Save config of bootstrapped DQN agent into {}",
+            path_.to_str().unwrap()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,10 @@
+//! Convolutional encoder of pixel observations.
+//!
+//! The architecture is the same as the encoder of DrQ-v2, followed by a linear layer,
+//! layer normalization and tanh, which output features given to actors and critics.
+//! If [`ConvEncoderConfig::normalize`] is `true`, the input is scaled into `[-0.5, 0.5]`
+//! with casting from `u8` to `f32`.
+mod base;
+mod config;
+pub use base::ConvEncoder;
+pub use config::ConvEncoderConfig;
@@ -0,0 +1,97 @@
+use super::ConvEncoderConfig;
+use crate::model::SubModel1;
+use candle_core::{DType::F32, Device, Module, Tensor};
+use candle_nn::{
+    conv::Conv2dConfig, conv2d, layer_norm, linear, Conv2d, LayerNorm, Linear, VarBuilder,
+};
+
+/// Convolutional encoder of pixel observations.
+///
+/// The input is `[batch_size, channels, height, width]` and the output is
+/// `[batch_size, out_dim]`.
+pub struct ConvEncoder {
+    device: Device,
+    normalize: bool,
+    convs: Vec<Conv2d>,
+    linear: Linear,
+    norm: LayerNorm,
+}
+
+impl ConvEncoder {
+    fn forward_(&self, xs: &Tensor) -> candle_core::Result<Tensor> {
+        let mut xs = xs.to_device(&self.device)?.to_dtype(F32)?;
+        if self.normalize {
+            xs = ((xs / 255.0)? - 0.5)?;
+        }
+        for conv in self.convs.iter() {
+            xs = conv.forward(&xs)?.relu()?;
+        }
+        let xs = self.linear.forward(&xs.flatten_from(1)?)?;
+        self.norm.forward(&xs)?.tanh()
+    }
+}
+
+impl SubModel1 for ConvEncoder {
+    type Config = ConvEncoderConfig;
+    type Input = Tensor;
+    type Output = Tensor;
+
+    fn forward(&self, xs: &Self::Input) -> Tensor {
+        self.forward_(xs).unwrap()
+    }
+
+    fn build(vb: VarBuilder, config: Self::Config) -> Self {
+        let in_channels = config.in_shape[0] as usize;
+        let n_filters = config.n_filters as usize;
+        let convs = (0..config.n_layers as usize)
+            .map(|i| {
+                let (in_channels, stride) = match i {
+                    0 => (in_channels, 2),
+                    _ => (n_filters, 1),
+                };
+                let cfg = Conv2dConfig {
+                    stride,
+                    ..Default::default()
+                };
+                conv2d(in_channels, n_filters, 3, cfg, vb.pp(format!("c{}", i + 1))).unwrap()
+            })
+            .collect();
+        let out_dim = config.out_dim as usize;
+        let linear = linear(config.conv_out_dim() as _, out_dim, vb.pp("l1")).unwrap();
+        let norm = layer_norm(out_dim, 1e-5, vb.pp("ln")).unwrap();
+
+        Self {
+            device: vb.device().clone(),
+            normalize: config.normalize,
+            convs,
+            linear,
+            norm,
+        }
+    }
+
+    fn input_as_tensor(input: &Self::Input) -> Option<&Tensor> {
+        Some(input)
+    }
+
+    fn input_from_tensor(xs: Tensor) -> Option<Self::Input> {
+        Some(xs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_nn::VarMap;
+
+    #[test]
+    fn test_conv_encoder() -> anyhow::Result<()> {
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, F32, &Device::Cpu);
+        let config = ConvEncoderConfig::new([3, 84, 84], 50);
+        assert_eq!(config.conv_out_dim(), 32 * 35 * 35);
+        let encoder = ConvEncoder::build(vb, config);
+        let xs = Tensor::zeros((2, 3, 84, 84), candle_core::DType::U8, &Device::Cpu)?;
+        assert_eq!(encoder.forward(&xs).dims(), [2, 50]);
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use crate::util::OutDim;
+use serde::{Deserialize, Serialize};
+
+fn default_normalize() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// Configuration of [`ConvEncoder`](super::ConvEncoder).
+pub struct ConvEncoderConfig {
+    /// Shape of the input images, `[channels, height, width]`.
+    pub in_shape: [i64; 3],
+
+    /// The number of filters of convolutional layers.
+    pub n_filters: i64,
+
+    /// The number of convolutional layers. The first layer has stride 2.
+    pub n_layers: i64,
+
+    /// Dimension of output features.
+    pub out_dim: i64,
+
+    /// If `true`, pixel intensities in `[0, 255]` are scaled into `[-0.5, 0.5]`.
+    #[serde(default = "default_normalize")]
+    pub normalize: bool,
+}
+
+impl ConvEncoderConfig {
+    /// Constructs [`ConvEncoderConfig`] with the architecture of DrQ-v2.
+    pub fn new(in_shape: [i64; 3], out_dim: i64) -> Self {
+        Self {
+            in_shape,
+            n_filters: 32,
+            n_layers: 4,
+            out_dim,
+            normalize: true,
+        }
+    }
+
+    /// Sets the number of filters.
+    pub fn n_filters(mut self, v: i64) -> Self {
+        self.n_filters = v;
+        self
+    }
+
+    /// Sets the number of convolutional layers.
+    pub fn n_layers(mut self, v: i64) -> Self {
+        self.n_layers = v;
+        self
+    }
+
+    /// Sets the flag to scale pixel intensities.
+    pub fn normalize(mut self, v: bool) -> Self {
+        self.normalize = v;
+        self
+    }
+
+    /// Returns the dimension of the output of the convolutional layers.
+    pub(super) fn conv_out_dim(&self) -> i64 {
+        let [_, h, w] = self.in_shape;
+        let size = |x: i64| (x - 3) / 2 + 1 - 2 * (self.n_layers - 1);
+        self.n_filters * size(h) * size(w)
+    }
+}
+
+impl OutDim for ConvEncoderConfig {
+    fn get_out_dim(&self) -> i64 {
+        self.out_dim
+    }
+
+    fn set_out_dim(&mut self, v: i64) {
+        self.out_dim = v;
+    }
+
+    fn get_in_shape(&self) -> Option<Vec<i64>> {
+        Some(self.in_shape.to_vec())
+    }
+}
@@ -0,0 +1,7 @@
+//! Conservative Q-learning (CQL) agent.
+mod alpha;
+mod base;
+mod config;
+pub use alpha::{CqlAlpha, CqlAlphaMode};
+pub use base::Cql;
+pub use config::CqlConfig;
@@ -0,0 +1,133 @@
+//! Weight of the conservative penalty of CQL.
+use crate::opt::{Optimizer, OptimizerConfig};
+use anyhow::Result;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::{init::Init, VarBuilder, VarMap};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Mode of the weight of the conservative penalty of CQL.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub enum CqlAlphaMode {
+    /// Use a constant as the weight.
+    Fix(f64),
+
+    /// Automatic tuning with dual gradient descent.
+    ///
+    /// The weight increases when the penalty, the gap between the action values of sampled
+    /// actions and those of actions in the dataset, exceeds `target_action_gap`.
+    Lagrangian {
+        target_action_gap: f64,
+        learning_rate: f64,
+    },
+}
+
+/// The weight of the conservative penalty of CQL.
+pub struct CqlAlpha {
+    varmap: VarMap,
+    log_alpha: Tensor,
+    target_action_gap: Option<f64>,
+    opt: Option<Optimizer>,
+}
+
+impl CqlAlpha {
+    /// Constructs an instance of `CqlAlpha`.
+    pub fn new(mode: CqlAlphaMode, device: Device) -> Result<Self> {
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+        let (log_alpha, target_action_gap, opt) = match mode {
+            CqlAlphaMode::Fix(alpha) => {
+                let init = Init::Const(alpha.ln());
+                let log_alpha = vb.get_with_hints(1, "log_alpha", init)?;
+                (log_alpha, None, None)
+            }
+            CqlAlphaMode::Lagrangian {
+                target_action_gap,
+                learning_rate,
+            } => {
+                let init = Init::Const(0.0);
+                let log_alpha = vb.get_with_hints(1, "log_alpha", init)?;
+                let opt = OptimizerConfig::default()
+                    .learning_rate(learning_rate)
+                    .build(varmap.all_vars())?;
+                (log_alpha, Some(target_action_gap), Some(opt))
+            }
+        };
+
+        Ok(Self {
+            varmap,
+            log_alpha,
+            target_action_gap,
+            opt,
+        })
+    }
+
+    /// Returns the weight, clipped to `[0, 1e6]`.
+    pub fn alpha(&self) -> Result<Tensor> {
+        Ok(self.log_alpha.detach().exp()?.clamp(0.0, 1e6)?)
+    }
+
+    /// Updates the weight given the penalty of a mini-batch.
+    ///
+    /// It does nothing when the weight is fixed.
+    pub fn update(&mut self, penalty: &Tensor) -> Result<()> {
+        if let (Some(target_action_gap), Some(opt)) = (&self.target_action_gap, &mut self.opt) {
+            let loss = {
+                let gap = (penalty.detach() - *target_action_gap)?;
+                let alpha = self.log_alpha.exp()?.clamp(0.0, 1e6)?;
+                (alpha.broadcast_mul(&gap)? * -1f64)?.mean_all()?
+            };
+            opt.backward_step(&loss)?;
+        }
+        Ok(())
+    }
+
+    /// Save the parameter into a file.
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<()> {
+        self.varmap.save(&path)?;
+        info!("Save CQL alpha to {:?}", path.as_ref());
+        Ok(())
+    }
+
+    /// Load the parameter from a file.
+    pub fn load<T: AsRef<Path>>(&mut self, path: T) -> Result<()> {
+        self.varmap.load(&path)?;
+        info!("Load CQL alpha from {:?}", path.as_ref());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lagrangian() -> Result<()> {
+        let mode = CqlAlphaMode::Lagrangian {
+            target_action_gap: 1.0,
+            learning_rate: 0.1,
+        };
+        let mut alpha = CqlAlpha::new(mode, Device::Cpu)?;
+        let alpha0 = alpha.alpha()?.to_vec1::<f32>()?[0];
+        assert_eq!(alpha0, 1.0);
+
+        // The penalty exceeds the target, then the weight increases
+        let penalty = Tensor::new(3f32, &Device::Cpu)?;
+        alpha.update(&penalty)?;
+        let alpha1 = alpha.alpha()?.to_vec1::<f32>()?[0];
+        assert!(alpha1 > alpha0);
+
+        // The penalty is below the target, then the weight decreases
+        let penalty = Tensor::new(-3f32, &Device::Cpu)?;
+        alpha.update(&penalty)?;
+        alpha.update(&penalty)?;
+        assert!(alpha.alpha()?.to_vec1::<f32>()?[0] < alpha1);
+
+        // A fixed weight is not updated
+        let mut alpha = CqlAlpha::new(CqlAlphaMode::Fix(5.0), Device::Cpu)?;
+        alpha.update(&penalty)?;
+        assert!((alpha.alpha()?.to_vec1::<f32>()?[0] - 5.0).abs() < 1e-5);
+        Ok(())
+    }
+}
@@ -0,0 +1,369 @@
+use super::{CqlAlpha, CqlConfig};
+use crate::{
+    error::opt_result,
+    model::{SubModel1, SubModel2},
+    sac::EntCoef,
+    util::{
+        actor::GaussianActor, critic::MultiCritic, find_params, gamma_not_done, params_path,
+        save_manifest, smooth_l1_loss, CriticLoss, OutDim,
+    },
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+};
+use candle_core::{Device, Tensor, D};
+use candle_nn::loss::mse;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+type ActionValue = Tensor;
+type ActMean = Tensor;
+type ActStd = Tensor;
+
+/// Conservative Q-learning (CQL) agent.
+///
+/// The actor and the critic are trained as in SAC, and the critic loss has a penalty
+/// pushing down action values of actions sampled from the uniform distribution and
+/// the current policy, while pushing up action values of actions in the dataset.
+/// The action values of sampled actions are aggregated with log-sum-exp, where the
+/// actions are importance weighted by their densities (the CQL(H) variant).
+/// See <https://arxiv.org/abs/2006.04779>.
+pub struct Cql<E, Q, P, R>
+where
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = (ActMean, ActStd)>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    critic: MultiCritic<Q>,
+    actor: GaussianActor<P>,
+    gamma: f64,
+    ent_coef: EntCoef,
+    cql_alpha: CqlAlpha,
+    n_action_samples: usize,
+    temperature: f64,
+    action_range: (f32, f32),
+    n_updates_per_opt: usize,
+    batch_size: usize,
+    train: bool,
+    n_opts: usize,
+    critic_loss: CriticLoss,
+    phantom: PhantomData<(E, R)>,
+    device: Device,
+}
+
+impl<E, Q, P, R> Cql<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = (ActMean, ActStd)>,
+    R: ReplayBufferBase,
+    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Act: Into<Q::Input2>,
+    Q::Input2: From<ActMean>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input1> + Into<P::Input> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Q::Input2> + Into<Tensor>,
+{
+    /// Returns the action values of sampled actions minus their log densities for each critic,
+    /// `[batch_size, 3 * n_action_samples]`.
+    fn sampled_qvals(
+        &mut self,
+        obs_q: &Q::Input1,
+        obs_p: &P::Input,
+        next_obs_p: &P::Input,
+        act: &Tensor,
+    ) -> Result<Vec<Tensor>> {
+        let (action_min, action_max) = self.action_range;
+        let logp_rand = -(act.dims()[1] as f64) * ((action_max - action_min) as f64).ln();
+        let mut qvals: Vec<Vec<Tensor>> = vec![];
+
+        for _ in 0..self.n_action_samples {
+            // Uniform distribution
+            let act_rand = act.rand_like(action_min as f64, action_max as f64)?;
+            let qs = self.critic.qvals(obs_q, &act_rand.into());
+            qvals.push(qs.iter().map(|q| q - logp_rand).collect::<Result<_, _>>()?);
+
+            // Current policy on observations and next observations
+            for obs_p in [obs_p, next_obs_p] {
+                let act_pi = self.actor.sample(obs_p, true)?.detach();
+                let logp_pi = self.actor.logp(obs_p, &act_pi)?.detach();
+                let qs = self.critic.qvals(obs_q, &act_pi.into());
+                qvals.push(qs.iter().map(|q| q - &logp_pi).collect::<Result<_, _>>()?);
+            }
+        }
+
+        // Stack sampled values for each critic
+        let n_critics = qvals[0].len();
+        (0..n_critics)
+            .map(|i| {
+                let qs: Vec<_> = qvals.iter().map(|qs| qs[i].clone()).collect();
+                Ok(Tensor::stack(&qs, D::Minus1)?)
+            })
+            .collect()
+    }
+
+    fn update_critic(&mut self, batch: R::Batch) -> Result<(f32, f32)> {
+        let (loss, penalty) = {
+            // Extract items in the batch
+            let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
+            let batch_size = reward.len();
+            let reward = Tensor::from_slice(&reward[..], (batch_size,), &self.device)?;
+            let act: Tensor = act.into();
+            let act = act.to_device(&self.device)?;
+            let obs_q: Q::Input1 = obs.clone().into();
+            let obs_p: P::Input = obs.into();
+            let next_obs_p: P::Input = next_obs.clone().into();
+
+            // Prediction
+            let qs = self.critic.qvals(&obs_q, &act.clone().into());
+
+            // Target
+            let tgt = {
+                let gamma_not_done =
+                    gamma_not_done(self.gamma as f32, is_terminated, None, &self.device)?;
+                let next_act = self.actor.sample(&next_obs_p, self.train)?;
+                let next_log_p = self.actor.logp(&next_obs_p, &next_act)?;
+                let next_q = self
+                    .critic
+                    .qvals_min_tgt(&next_obs.into(), &next_act.into())?;
+                let next_q = (next_q - self.ent_coef.alpha()?.broadcast_mul(&next_log_p)?)?;
+                (&reward + (&gamma_not_done * next_q)?)?.squeeze(D::Minus1)?
+            }
+            .detach();
+            debug_assert_eq!(tgt.dims(), [self.batch_size]);
+
+            // Bellman error
+            let losses: Vec<_> = match self.critic_loss {
+                CriticLoss::Mse => qs.iter().map(|pred| mse(pred, &tgt).unwrap()).collect(),
+                CriticLoss::SmoothL1 => qs
+                    .iter()
+                    .map(|pred| smooth_l1_loss(pred, &tgt).unwrap())
+                    .collect(),
+            };
+            let loss = Tensor::stack(&losses, 0)?.mean_all()?;
+
+            // Conservative penalty
+            let sampled_qs = self.sampled_qvals(&obs_q, &obs_p, &next_obs_p, &act)?;
+            let penalties = sampled_qs
+                .iter()
+                .zip(qs.iter())
+                .map(|(sampled_q, q)| {
+                    let lse = ((sampled_q / self.temperature)?.log_sum_exp(D::Minus1)?
+                        * self.temperature)?;
+                    Ok((lse - q)?.mean_all()?)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let penalty = Tensor::stack(&penalties, 0)?.mean_all()?;
+            self.cql_alpha.update(&penalty)?;
+            let alpha = self.cql_alpha.alpha()?.squeeze(0)?;
+
+            ((loss + (alpha * &penalty)?)?, penalty)
+        };
+
+        self.critic.backward_step(&loss)?;
+
+        Ok((loss.to_scalar::<f32>()?, penalty.to_scalar::<f32>()?))
+    }
+
+    fn update_actor(&mut self, batch: &R::Batch) -> Result<f32> {
+        let loss = {
+            let obs = batch.obs().clone();
+            let act = self.actor.sample(&obs.clone().into(), self.train)?;
+            let log_p = self.actor.logp(&obs.clone().into(), &act)?;
+
+            // Update the entropy coefficient
+            self.ent_coef.update(&log_p.detach())?;
+
+            // Loss
+            let q = self.critic.qvals_min(&obs.into(), &act.into())?;
+            let alpha = self.ent_coef.alpha()?.detach();
+            (alpha.broadcast_mul(&log_p)? - &q)?.mean_all()?
+        };
+
+        self.actor.backward_step(&loss)?;
+
+        Ok(loss.to_scalar::<f32>()?)
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut loss_critic = 0f32;
+        let mut loss_actor = 0f32;
+        let mut cql_penalty = 0f32;
+
+        for _ in 0..self.n_updates_per_opt {
+            let batch = buffer.batch(self.batch_size).unwrap();
+            loss_actor += self.update_actor(&batch)?;
+            let (loss, penalty) = self.update_critic(batch)?;
+            loss_critic += loss;
+            cql_penalty += penalty;
+            self.critic.soft_update()?;
+            self.n_opts += 1;
+        }
+
+        loss_critic /= self.n_updates_per_opt as f32;
+        loss_actor /= self.n_updates_per_opt as f32;
+        cql_penalty /= self.n_updates_per_opt as f32;
+
+        let record = Record::from_slice(&[
+            ("loss_critic", RecordValue::Scalar(loss_critic)),
+            ("loss_actor", RecordValue::Scalar(loss_actor)),
+            ("cql_penalty", RecordValue::Scalar(cql_penalty)),
+            (
+                "ent_coef",
+                RecordValue::Scalar(self.ent_coef.alpha()?.to_vec1::<f32>()?[0]),
+            ),
+            (
+                "cql_alpha",
+                RecordValue::Scalar(self.cql_alpha.alpha()?.to_vec1::<f32>()?[0]),
+            ),
+        ]);
+
+        Ok(record)
+    }
+}
+
+impl<E, Q, P, R> Policy<E> for Cql<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = (ActMean, ActStd)>,
+    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Act: Into<Q::Input2> + From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        self.actor
+            .sample(&obs.clone().into(), self.train)
+            .unwrap()
+            .into()
+    }
+}
+
+impl<E, Q, P, R> Configurable for Cql<E, Q, P, R>
+where
+    E: Env,
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = (ActMean, ActStd)>,
+    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Act: Into<Q::Input2> + From<Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = CqlConfig<Q, P>;
+
+    /// Constructs [`Cql`] agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for CQL agent")
+            .into();
+        let action_range = config.actor_config.action_limit.range();
+        let actor = GaussianActor::build(config.actor_config, device.clone()).unwrap();
+        let critic = MultiCritic::build(config.critic_config, device.clone()).unwrap();
+        let ent_coef = EntCoef::new(config.ent_coef_mode, device.clone()).unwrap();
+        let cql_alpha = CqlAlpha::new(config.cql_alpha_mode, device.clone()).unwrap();
+
+        Cql {
+            actor,
+            critic,
+            gamma: config.gamma,
+            ent_coef,
+            cql_alpha,
+            n_action_samples: config.n_action_samples,
+            temperature: config.temperature,
+            action_range,
+            n_updates_per_opt: config.n_updates_per_opt,
+            batch_size: config.batch_size,
+            train: false,
+            critic_loss: config.critic_loss,
+            n_opts: 0,
+            device,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, Q, P, R> Agent<E, R> for Cql<E, Q, P, R>
+where
+    E: Env + 'static,
+    Q: SubModel2<Output = ActionValue> + 'static,
+    P: SubModel1<Output = (ActMean, ActStd)> + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Act: Into<Q::Input2> + From<Tensor>,
+    Q::Input2: From<ActMean>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input1> + Into<P::Input> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Q::Input2> + Into<Tensor>,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.try_opt_with_record(buffer)
+            .expect("Failed in Cql::opt_()")
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
+        Ok(opt_result(self.opt_(buffer))?)
+    }
+
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+
+        let actor_path = self.actor.save(path.join("actor"))?;
+        let (critic_path, critic_tgt_path) = self.critic.save(path.join("critic"))?;
+        let ent_coef_path = params_path(path.join("ent_coef"));
+        self.ent_coef.save(&ent_coef_path)?;
+        let cql_alpha_path = params_path(path.join("cql_alpha"));
+        self.cql_alpha.save(&cql_alpha_path)?;
+
+        let mut paths = vec![
+            actor_path,
+            critic_path,
+            critic_tgt_path,
+            ent_coef_path,
+            cql_alpha_path,
+        ];
+        save_manifest(path, "Cql", self.actor.policy_config(), &mut paths)?;
+        Ok(paths)
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.actor.load(path.join("actor").as_path())?;
+        self.critic.load(path.join("critic").as_path())?;
+        self.ent_coef.load(find_params(path.join("ent_coef")))?;
+        self.cql_alpha.load(find_params(path.join("cql_alpha")))?;
+
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
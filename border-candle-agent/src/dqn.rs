@@ -1,9 +1,9 @@
 //! DQN agent.
 mod base;
+mod c51;
 mod config;
-mod explorer;
 mod model;
 pub use base::Dqn;
+pub use c51::C51Config;
 pub use config::DqnConfig;
-pub use explorer::{DqnExplorer, EpsilonGreedy, Softmax};
 pub use model::{DqnModel, DqnModelConfig};
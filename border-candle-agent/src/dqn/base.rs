@@ -1,10 +1,17 @@
 //! DQN agent implemented with candle.
-use super::{config::DqnConfig, explorer::DqnExplorer, model::DqnModel};
+use super::{config::DqnConfig, model::DqnModel, C51Config};
 use crate::{
-    model::SubModel1,
-    util::{smooth_l1_loss, track, CriticLoss, OutDim},
+    error::opt_result,
+    explorer::{Backend, Explorer},
+    model::{map_input, SubModel1},
+    onnx::{ElemType, OnnxGraph, OnnxModel},
+    util::{
+        aux_heads::AuxHeads, batch_transform::BatchTransform, find_params, params_path,
+        save_manifest, smooth_l1_loss, track, weighted_critic_loss, CriticLoss, OutDim,
+        SoftUpdater,
+    },
 };
-use anyhow::Result;
+use anyhow::{Context, Result};
 use border_core::{
     record::{Record, RecordValue},
     Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
@@ -23,8 +30,7 @@ where
     Q: SubModel1<Output = Tensor>,
     Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
 {
-    pub(in crate::dqn) soft_update_interval: usize,
-    pub(in crate::dqn) soft_update_counter: usize,
+    pub(in crate::dqn) soft_updater: SoftUpdater,
     pub(in crate::dqn) n_updates_per_opt: usize,
     pub(in crate::dqn) batch_size: usize,
     pub(in crate::dqn) qnet: DqnModel<Q>,
@@ -32,8 +38,7 @@ where
     pub(in crate::dqn) train: bool,
     pub(in crate::dqn) phantom: PhantomData<(E, R)>,
     pub(in crate::dqn) discount_factor: f64,
-    pub(in crate::dqn) tau: f64,
-    pub(in crate::dqn) explorer: DqnExplorer,
+    pub(in crate::dqn) explorer: Box<dyn Explorer<Backend>>,
     pub(in crate::dqn) device: Device,
     pub(in crate::dqn) n_opts: usize,
     pub(in crate::dqn) double_dqn: bool,
@@ -43,9 +48,71 @@ where
     n_samples_act: usize,
     n_samples_best_act: usize,
     record_verbose_level: usize,
+    c51: Option<C51Config>,
+    aux_heads: AuxHeads,
+    noisy_nets: bool,
+    batch_transform: BatchTransform,
+    mc_target: bool,
     rng: SmallRng,
 }
 
+impl<E, Q, R> Dqn<E, Q, R>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Sets the explorer used in training, replacing the one given in the configuration.
+    pub fn set_explorer(&mut self, explorer: impl Explorer<Backend> + 'static) {
+        self.explorer = Box::new(explorer);
+    }
+
+    /// Returns action values given observations.
+    ///
+    /// With the distributional head, they are the expectations of the value distributions.
+    fn q_values(&self, qnet: &DqnModel<Q>, obs: &Q::Input) -> Tensor {
+        self.q_values_from_outputs(&qnet.forward(obs))
+    }
+
+    fn q_values_from_outputs(&self, x: &Tensor) -> Tensor {
+        let x = self.aux_heads.main(x).unwrap();
+        match &self.c51 {
+            None => x,
+            Some(c51) => c51.q_values(&x).unwrap(),
+        }
+    }
+}
+
+impl<E, Q, R> Dqn<E, Q, R>
+where
+    Q: SubModel1<Output = Tensor> + OnnxModel,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Exports the policy to ONNX, see [`crate::onnx`].
+    ///
+    /// The graph outputs the action values `q_values`, `[batch, n_actions]`, and the greedy
+    /// actions `action` of `i64`, `[batch]`. The random actions taken with a small probability
+    /// in evaluation mode are not included. The distributional head of C51 is not supported.
+    pub fn export_onnx(&self, path: impl AsRef<Path>) -> Result<()> {
+        if self.c51.is_some() {
+            anyhow::bail!("Export of the distributional head of C51 to ONNX is not supported");
+        }
+        let mut graph = OnnxGraph::new("dqn");
+        let (elem_type, shape) = self.qnet.onnx_input();
+        let obs = graph.add_input("obs", elem_type, &shape);
+        let xs = self.qnet.add_to_graph(&mut graph, &obs)?.remove(0);
+        let q = self.aux_heads.add_main_to_graph(&mut graph, &xs);
+        let act = graph.add_argmax(&q);
+        graph.add_output(
+            "q_values",
+            ElemType::Float,
+            &[self.aux_heads.main_dim() as i64],
+            &q,
+        );
+        graph.add_output("action", ElemType::Int64, &[], &act);
+        graph.save(path)
+    }
+}
+
 impl<E, Q, R> Dqn<E, Q, R>
 where
     E: Env,
@@ -56,15 +123,117 @@ where
     <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input>,
     <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
 {
-    fn update_critic(&mut self, buffer: &mut R) -> Record {
+    /// Converts observations sampled from the replay buffer into the input of the Q-network,
+    /// applying augmentations if any.
+    ///
+    /// With mixed precision training, observations are cast into half precision.
+    fn transform_obs(&mut self, obs: <R::Batch as TransitionBatch>::ObsBatch) -> Result<Q::Input> {
+        let obs = obs.into();
+        let obs = match self.batch_transform.is_empty() {
+            true => obs,
+            false => map_input::<Q>(&obs, |xs| self.batch_transform.apply(xs))?,
+        };
+        self.cast_obs(obs)
+    }
+
+    /// Casts observations to the precision of training.
+    fn cast_obs(&self, obs: Q::Input) -> Result<Q::Input> {
+        match self.qnet.amp_dtype() {
+            None => Ok(obs),
+            Some(dtype) => map_input::<Q>(&obs, |xs| Ok(xs.to_dtype(dtype)?)),
+        }
+    }
+
+    /// Returns action values computed in the precision of training.
+    fn q_values_amp(&self, qnet: &DqnModel<Q>, obs: &Q::Input) -> Result<Tensor> {
+        Ok(self.q_values_from_outputs(&qnet.forward_batch(obs)?))
+    }
+
+    /// Returns the targets of action values, the bootstrapped returns with the target network.
+    fn target(
+        &self,
+        next_obs: &Q::Input,
+        reward: &Tensor,
+        is_not_terminated: &Tensor,
+    ) -> Result<Tensor> {
+        let q = if self.double_dqn {
+            let x = self.q_values_amp(&self.qnet, next_obs)?;
+            let y = x.argmax(D::Minus1)?;
+            let tgt = self.q_values_amp(&self.qnet_tgt, next_obs)?;
+            tgt.gather(&y.unsqueeze(D::Minus1)?, D::Minus1)?
+        } else {
+            let x = self.q_values_amp(&self.qnet_tgt, next_obs)?;
+            let y = x.argmax(D::Minus1)?;
+            x.gather(&y.unsqueeze(D::Minus1)?, D::Minus1)?
+        };
+        let tgt = (reward + (is_not_terminated * self.discount_factor) * q.squeeze(D::Minus1)?)?;
+        Ok(tgt.detach())
+    }
+
+    /// Returns the Monte Carlo returns of the transitions in a batch as the targets of
+    /// action values, if enabled. This must be called before unpacking the batch.
+    fn mc_returns(&self, batch: &R::Batch) -> Result<Option<Tensor>> {
+        if !self.mc_target {
+            return Ok(None);
+        }
+        let g = batch
+            .mc_return()
+            .context("Monte Carlo returns are not given by the replay buffer")?;
+        Ok(Some(Tensor::from_slice(&g[..], &[g.len()], &self.device)?))
+    }
+
+    /// Returns the absolute TD errors of the transitions in a batch, used as their priorities
+    /// in prioritized experience replay.
+    ///
+    /// With the distributional head, the TD errors are computed with the expectations of
+    /// the value distributions.
+    fn td_errors(&mut self, batch: R::Batch) -> Result<Vec<f32>> {
+        let mc_returns = self.mc_returns(&batch)?;
+        let (obs, act, next_obs, reward, is_terminated, _is_truncated, _ixs, _weight) =
+            batch.unpack();
+        // Augmentation is not applied, not to consume the random number generator of training
+        let obs = self.cast_obs(obs.into())?;
+        let act = act.into().to_device(&self.device)?;
+        let next_obs = self.cast_obs(next_obs.into())?;
+        let n = reward.len();
+        let reward = Tensor::from_slice(&reward[..], &[n], &self.device)?;
+        let is_not_terminated = {
+            let is_not_terminated = is_terminated
+                .into_iter()
+                .map(|v| (1 - v) as f32)
+                .collect::<Vec<_>>();
+            Tensor::from_slice(&is_not_terminated[..], &[n], &self.device)?
+        };
+        let pred = self
+            .q_values_amp(&self.qnet, &obs)?
+            .gather(&act, D::Minus1)?
+            .squeeze(D::Minus1)?;
+        let tgt = match mc_returns {
+            Some(g) => g,
+            None => self.target(&next_obs, &reward, &is_not_terminated)?,
+        };
+        let td_errs = (pred.detach() - tgt)?.abs()?;
+        let td_errs = match self.clip_td_err {
+            None => td_errs,
+            Some((min, max)) => td_errs.clamp(min, max)?,
+        };
+        Ok(td_errs.to_dtype(DType::F32)?.to_vec1::<f32>()?)
+    }
+
+    fn update_critic(&mut self, buffer: &mut R) -> Result<Record> {
+        if let Some(c51) = self.c51.clone() {
+            return self.update_critic_c51(buffer, &c51);
+        }
+
         let mut record = Record::empty();
-        let batch = buffer.batch(self.batch_size).unwrap();
-        let (obs, act, next_obs, reward, is_terminated, _is_truncated, _ixs, weight) =
+        let batch = buffer.batch(self.batch_size)?;
+        let mc_returns = self.mc_returns(&batch)?;
+        let (obs, act, next_obs, reward, is_terminated, _is_truncated, ixs, weight) =
             batch.unpack();
-        let obs = obs.into();
-        let act = act.into().to_device(&self.device).unwrap();
-        let next_obs = next_obs.into();
-        let reward = Tensor::from_slice(&reward[..], &[reward.len()], &self.device).unwrap();
+        let obs = self.transform_obs(obs)?;
+        let act = act.into().to_device(&self.device)?;
+        let next_obs = self.transform_obs(next_obs)?;
+        let reward = Tensor::from_slice(&reward[..], &[reward.len()], &self.device)?;
         let is_not_terminated = {
             let is_not_terminated = is_terminated
                 .into_iter()
@@ -74,118 +243,221 @@ where
                 &is_not_terminated[..],
                 &[is_not_terminated.len()],
                 &self.device,
-            )
-            .unwrap()
+            )?
         };
+        let x = self.qnet.forward_batch(&obs)?;
         let pred = {
-            let x = self.qnet.forward(&obs);
-            x.gather(&act, D::Minus1)
-                .unwrap()
-                .squeeze(D::Minus1)
-                .unwrap()
+            let x = self.aux_heads.main(&x)?;
+            x.gather(&act, D::Minus1)?.squeeze(D::Minus1)?
         };
 
         if self.record_verbose_level >= 2 {
             record.insert(
                 "pred_mean",
-                RecordValue::Scalar(pred.mean_all().unwrap().to_vec0::<f32>().unwrap()),
+                RecordValue::Scalar(pred.mean_all()?.to_vec0::<f32>()?),
             );
         }
 
         if self.record_verbose_level >= 2 {
-            let reward_mean: f32 = reward.mean_all().unwrap().to_vec0().unwrap();
+            let reward_mean: f32 = reward.mean_all()?.to_vec0()?;
             record.insert("reward_mean", RecordValue::Scalar(reward_mean));
         }
 
-        let tgt = {
-            let q = if self.double_dqn {
-                let x = self.qnet.forward(&next_obs);
-                let y = x.argmax(D::Minus1).unwrap();
-                let tgt = self.qnet_tgt.forward(&next_obs);
-                tgt.gather(&y, D::Minus1).unwrap()
-            } else {
-                let x = self.qnet_tgt.forward(&next_obs);
-                let y = x.argmax(D::Minus1).unwrap();
-                x.gather(&y.unsqueeze(D::Minus1).unwrap(), D::Minus1)
-                    .unwrap()
-            };
-
-            reward + is_not_terminated * self.discount_factor * q.squeeze(D::Minus1).unwrap()
-        }
-        .unwrap()
-        .detach();
+        let tgt = match mc_returns {
+            Some(g) => g,
+            None => self.target(&next_obs, &reward, &is_not_terminated)?,
+        };
 
         if self.record_verbose_level >= 2 {
             record.insert(
                 "tgt_mean",
-                RecordValue::Scalar(tgt.mean_all().unwrap().to_vec0::<f32>().unwrap()),
+                RecordValue::Scalar(tgt.mean_all()?.to_vec0::<f32>()?),
             );
-            let tgt_minus_pred_mean: f32 = (&tgt - &pred)
-                .unwrap()
-                .mean_all()
-                .unwrap()
-                .to_vec0()
-                .unwrap();
+            let tgt_minus_pred_mean: f32 = (&tgt - &pred)?.mean_all()?.to_vec0()?;
             record.insert(
                 "tgt_minus_pred_mean",
                 RecordValue::Scalar(tgt_minus_pred_mean),
             );
         }
 
-        let loss = if let Some(_ws) = weight {
-            // Prioritized weighting loss, will be implemented later
-            panic!();
-            // let n = ws.len() as i64;
-            // let td_errs = match self.clip_td_err {
-            //     None => (&pred - &tgt).abs(),
-            //     Some((min, max)) => (&pred - &tgt).abs().clip(min, max),
-            // };
-            // let loss = Tensor::of_slice(&ws[..]).to(self.device) * &td_errs;
-            // let loss = loss.smooth_l1_loss(
-            //     &Tensor::zeros(&[n], tch::kind::FLOAT_CPU).to(self.device),
-            //     tch::Reduction::Mean,
-            //     1.0,
-            // );
-            // self.qnet.backward_step(&loss);
-            // let td_errs = Vec::<f32>::from(td_errs);
-            // buffer.update_priority(&ixs, &Some(td_errs));
-            // loss
+        let loss = if let Some(ws) = weight {
+            // Prioritized weighting loss
+            let n = ws.len();
+            let td_errs = match self.clip_td_err {
+                None => (&pred - &tgt)?.abs()?,
+                Some((min, max)) => (&pred - &tgt)?.abs()?.clamp(min, max)?,
+            };
+            let ws = Tensor::from_slice(&ws[..], &[n], &self.device)?.to_dtype(pred.dtype())?;
+            let loss = weighted_critic_loss(&self.critic_loss, &pred, &tgt, &ws)?;
+            let td_errs = td_errs.detach().to_dtype(DType::F32)?.to_vec1::<f32>()?;
+            buffer.update_priority(&ixs, &Some(td_errs));
+            loss
         } else {
             match self.critic_loss {
-                CriticLoss::Mse => mse(&pred, &tgt).unwrap(),
-                CriticLoss::SmoothL1 => smooth_l1_loss(&pred, &tgt).unwrap(),
+                CriticLoss::Mse => mse(&pred, &tgt)?,
+                CriticLoss::SmoothL1 => smooth_l1_loss(&pred, &tgt)?,
             }
         };
 
+        // Auxiliary losses
+        let loss = if self.aux_heads.is_empty() {
+            loss
+        } else {
+            let is_terminated = is_not_terminated.affine(-1.0, 1.0)?;
+            let (aux_loss, aux_record) = self.aux_heads.loss(&x, &act, &reward, &is_terminated)?;
+            record = record.merge(aux_record);
+            (loss + aux_loss)?
+        };
+
         // Backprop
-        self.qnet.backward_step(&loss).unwrap();
+        self.qnet.backward_step(&loss)?;
 
-        record.insert(
-            "loss",
-            RecordValue::Scalar(loss.to_scalar::<f32>().unwrap()),
-        );
+        record.insert("loss", RecordValue::Scalar(loss.to_scalar::<f32>()?));
 
-        record
-        // f32::from(loss.to_scalar::<f32>().unwrap())
+        Ok(record)
+        // f32::from(loss.to_scalar::<f32>()?)
     }
 
-    fn opt_(&mut self, buffer: &mut R) -> Record {
+    /// Updates the distributional Q-network with the cross entropy between the predicted
+    /// distributions and the target distributions projected onto the support.
+    fn update_critic_c51(&mut self, buffer: &mut R, c51: &C51Config) -> Result<Record> {
+        let mut record = Record::empty();
+        let batch = buffer.batch(self.batch_size)?;
+        let (obs, act, next_obs, reward, is_terminated, _is_truncated, ixs, weight) =
+            batch.unpack();
+        let obs = self.transform_obs(obs)?;
+        let act = act.into().to_device(&self.device)?;
+        let next_obs = self.transform_obs(next_obs)?;
+        let n = reward.len();
+        let n_atoms = c51.n_atoms;
+        let reward = Tensor::from_slice(&reward[..], &[n], &self.device)?;
+        let is_terminated = {
+            let is_terminated = is_terminated
+                .into_iter()
+                .map(|v| v as f32)
+                .collect::<Vec<_>>();
+            Tensor::from_slice(&is_terminated[..], &[n], &self.device)?
+        };
+        let discount = (is_terminated.affine(-1.0, 1.0)? * self.discount_factor)?;
+
+        // Log probabilities of the value distributions of the taken actions
+        let x = self.qnet.forward_batch(&obs)?;
+        let log_probs = {
+            let x = c51.log_probs(&self.aux_heads.main(&x)?)?;
+            let ix = act
+                .reshape((n, 1, 1))?
+                .broadcast_as((n, 1, n_atoms))?
+                .contiguous()?;
+            x.gather(&ix, 1)?.squeeze(1)?
+        };
+
+        // Target distributions
+        let tgt = {
+            let x = self
+                .aux_heads
+                .main(&self.qnet_tgt.forward_batch(&next_obs)?)?;
+            let probs = c51.probs(&x)?;
+            let y = if self.double_dqn {
+                self.q_values_amp(&self.qnet, &next_obs)?
+            } else {
+                c51.q_values(&x)?
+            }
+            .argmax(D::Minus1)?;
+            let ix = y
+                .reshape((n, 1, 1))?
+                .broadcast_as((n, 1, n_atoms))?
+                .contiguous()?;
+            let probs = probs.gather(&ix, 1)?.squeeze(1)?;
+            c51.projection(&probs, &reward, &discount)?
+        }
+        .detach();
+
+        // Cross entropy for each sample
+        let ce = (&tgt * &log_probs)?.sum(D::Minus1)?.neg()?;
+
+        let loss = if let Some(ws) = weight {
+            // Prioritized weighting loss, the priorities are updated with the cross entropy
+            let ws = Tensor::from_slice(&ws[..], &[n], &self.device)?;
+            let loss = (ws * &ce)?.mean_all()?;
+            let ce = ce.detach().to_vec1::<f32>()?;
+            buffer.update_priority(&ixs, &Some(ce));
+            loss
+        } else {
+            ce.mean_all()?
+        };
+
+        // Auxiliary losses
+        let loss = if self.aux_heads.is_empty() {
+            loss
+        } else {
+            let (aux_loss, aux_record) = self.aux_heads.loss(&x, &act, &reward, &is_terminated)?;
+            record = record.merge(aux_record);
+            (loss + aux_loss)?
+        };
+
+        let entropy = C51Config::entropy(&log_probs.exp()?)?
+            .mean_all()?
+            .to_scalar::<f32>()?;
+        record.insert("value_dist_entropy", RecordValue::Scalar(entropy));
+
+        if self.record_verbose_level >= 2 {
+            let z = c51.support(&self.device)?;
+            let pred_mean: f32 = log_probs
+                .exp()?
+                .broadcast_mul(&z)?
+                .sum(D::Minus1)?
+                .mean_all()?
+                .to_scalar()?;
+            let tgt_mean: f32 = tgt
+                .broadcast_mul(&z)?
+                .sum(D::Minus1)?
+                .mean_all()?
+                .to_scalar()?;
+            let tgt_entropy = C51Config::entropy(&tgt)?.mean_all()?.to_scalar::<f32>()?;
+            record.insert("pred_mean", RecordValue::Scalar(pred_mean));
+            record.insert("tgt_mean", RecordValue::Scalar(tgt_mean));
+            record.insert("tgt_value_dist_entropy", RecordValue::Scalar(tgt_entropy));
+        }
+
+        // Backprop
+        self.qnet.backward_step(&loss)?;
+
+        record.insert("loss", RecordValue::Scalar(loss.to_scalar::<f32>()?));
+
+        Ok(record)
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
         let mut record_ = Record::empty();
 
         for _ in 0..self.n_updates_per_opt {
-            let record = self.update_critic(buffer);
+            if self.noisy_nets {
+                self.qnet.reset_noise();
+                self.qnet_tgt.reset_noise();
+            }
+            let record = self.update_critic(buffer)?;
             record_ = record_.merge(record);
         }
 
-        self.soft_update_counter += 1;
-        if self.soft_update_counter == self.soft_update_interval {
-            self.soft_update_counter = 0;
-            let _ = track(self.qnet_tgt.get_varmap(), self.qnet.get_varmap(), self.tau);
+        if self
+            .soft_updater
+            .update(self.qnet_tgt.get_varmap(), self.qnet.get_varmap(), ("", ""))?
+        {
+            self.qnet_tgt.sync_amp();
+            self.qnet_tgt.sync_replicas()?;
+        }
+
+        if let Some(amp) = self.qnet.amp() {
+            record_.insert("amp_loss_scale", RecordValue::Scalar(amp.scale() as f32));
+            record_.insert(
+                "amp_n_overflows",
+                RecordValue::Scalar(amp.n_overflows() as f32),
+            );
         }
 
         self.n_opts += 1;
 
-        record_
+        Ok(record_)
         // Record::from_slice(&[("loss", RecordValue::Scalar(loss_critic))])
     }
 }
@@ -200,22 +472,19 @@ where
 {
     /// In evaluation mode, take a random action with probability 0.01.
     fn sample(&mut self, obs: &E::Obs) -> E::Act {
-        let a = self.qnet.forward(&obs.clone().into()).detach();
-        let a = if self.train {
+        let a = self.q_values(&self.qnet, &obs.clone().into()).detach();
+        let a = if self.train && self.noisy_nets {
+            a.argmax(D::Minus1).unwrap().to_dtype(DType::I64).unwrap()
+        } else if self.train {
             self.n_samples_act += 1;
-            match &mut self.explorer {
-                DqnExplorer::Softmax(softmax) => softmax.action(&a, &mut self.rng),
-                DqnExplorer::EpsilonGreedy(egreedy) => {
-                    if self.record_verbose_level >= 2 {
-                        let (act, best) = egreedy.action_with_best(&a, &mut self.rng);
-                        if best {
-                            self.n_samples_best_act += 1;
-                        }
-                        act
-                    } else {
-                        egreedy.action(&a, &mut self.rng)
-                    }
+            if self.record_verbose_level >= 2 {
+                let (act, best) = self.explorer.action_with_best(&a, &mut self.rng);
+                if best {
+                    self.n_samples_best_act += 1;
                 }
+                act
+            } else {
+                self.explorer.action(&a, &mut self.rng)
             }
         } else {
             if self.rng.gen::<f32>() < 0.01 {
@@ -246,21 +515,40 @@ where
             .device
             .expect("No device is given for DQN agent")
             .into();
-        let qnet = DqnModel::build(config.model_config.clone(), device.clone()).unwrap();
-        let qnet_tgt = DqnModel::build(config.model_config.clone(), device.clone()).unwrap();
+        // The Q-network outputs logits of atoms for each action with the distributional head,
+        // followed by the outputs of auxiliary heads
+        let n_actions = match &config.model_config.q_config {
+            Some(q_config) => q_config.get_out_dim() as usize,
+            None => 0,
+        };
+        let main_dim = match &config.c51 {
+            Some(c51) => n_actions * c51.n_atoms,
+            None => n_actions,
+        };
+        assert!(
+            !(config.mc_target && config.c51.is_some()),
+            "Monte Carlo targets are not supported with the distributional head"
+        );
+        let aux_heads = AuxHeads::new(config.aux_heads.clone(), main_dim, n_actions);
+        let batch_transform =
+            BatchTransform::new(config.batch_transforms.clone(), device.clone(), 42);
+        let model_config = config
+            .model_config
+            .clone()
+            .out_dim(aux_heads.out_dim() as i64);
+        let qnet = DqnModel::build(model_config.clone(), device.clone()).unwrap();
+        let qnet_tgt = DqnModel::build(model_config, device.clone()).unwrap();
         let _ = track(qnet_tgt.get_varmap(), qnet.get_varmap(), 1.0);
 
         Dqn {
             qnet,
             qnet_tgt,
-            soft_update_interval: config.soft_update_interval,
-            soft_update_counter: 0,
+            soft_updater: SoftUpdater::new(config.soft_update),
             n_updates_per_opt: config.n_updates_per_opt,
             batch_size: config.batch_size,
             discount_factor: config.discount_factor,
-            tau: config.tau,
             train: config.train,
-            explorer: config.explorer,
+            explorer: Box::new(config.explorer),
             device,
             n_opts: 0,
             _clip_reward: config.clip_reward,
@@ -271,6 +559,11 @@ where
             n_samples_act: 0,
             n_samples_best_act: 0,
             record_verbose_level: config.record_verbose_level,
+            c51: config.c51,
+            aux_heads,
+            noisy_nets: config.noisy_nets,
+            batch_transform,
+            mc_target: config.mc_target,
             rng: SmallRng::seed_from_u64(42),
         }
     }
@@ -301,12 +594,22 @@ where
     }
 
     fn opt(&mut self, buffer: &mut R) {
-        self.opt_(buffer);
+        self.try_opt(buffer).expect("Failed in Dqn::opt_()");
     }
 
     fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.try_opt_with_record(buffer)
+            .expect("Failed in Dqn::opt_()")
+    }
+
+    fn try_opt(&mut self, buffer: &mut R) -> Result<()> {
+        opt_result(self.opt_(buffer))?;
+        Ok(())
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
         let mut record = {
-            let record = self.opt_(buffer);
+            let record = opt_result(self.opt_(buffer))?;
 
             match self.record_verbose_level >= 2 {
                 true => {
@@ -324,29 +627,60 @@ where
             false => self.n_samples_best_act as f32 / self.n_samples_act as f32,
         };
         record.insert("ratio_best_act", RecordValue::Scalar(ratio));
+        record.insert("lr", RecordValue::Scalar(self.qnet.learning_rate() as f32));
+        if let Some(grad_norm) = self.qnet.grad_norm() {
+            record.insert("grad_norm", RecordValue::Scalar(grad_norm));
+        }
         self.n_samples_act = 0;
         self.n_samples_best_act = 0;
 
-        record
+        Ok(record)
+    }
+
+    /// Supports `lr`, the learning rate of the Q-network, and hyperparameters of the explorer,
+    /// e.g., `eps` of epsilon-greedy, which is kept constant after it is set.
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool {
+        match name {
+            "lr" => {
+                self.qnet.set_learning_rate(value);
+                true
+            }
+            _ => self.explorer.set_hyperparam(name, value),
+        }
+    }
+
+    /// Returns the action values for the observation as `q_values`.
+    fn probe(&mut self, obs: &E::Obs) -> Record {
+        let q = self.q_values(&self.qnet, &obs.clone().into()).detach();
+        let q = q.flatten_all().unwrap().to_vec1::<f32>().unwrap();
+        Record::from_slice(&[("q_values", RecordValue::Array1(q))])
+    }
+
+    /// Returns the absolute TD errors of the transitions as their priorities.
+    fn priorities(&mut self, batch: R::Batch) -> Result<Option<Vec<f32>>> {
+        Ok(Some(self.td_errors(batch)?))
     }
 
     /// Save model parameters in the given directory.
     ///
-    /// The parameters of the model are saved as `qnet.pt`.
-    /// The parameters of the target model are saved as `qnet_tgt.pt`.
+    /// The parameters of the model are saved as `qnet.safetensors`.
+    /// The parameters of the target model are saved as `qnet_tgt.safetensors`.
+    /// [`ModelManifest`](border_core::ModelManifest) is saved as `manifest.yaml`.
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
         // TODO: consider to rename the path if it already exists
         fs::create_dir_all(&path)?;
-        let path1 = path.join("qnet.pt").to_path_buf();
-        let path2 = path.join("qnet_tgt.pt").to_path_buf();
+        let path1 = params_path(path.join("qnet"));
+        let path2 = params_path(path.join("qnet_tgt"));
         self.qnet.save(&path1)?;
         self.qnet_tgt.save(&path2)?;
-        Ok(vec![path1, path2])
+        let mut paths = vec![path1, path2];
+        save_manifest(path, "Dqn", self.qnet.q_config(), &mut paths)?;
+        Ok(paths)
     }
 
     fn load_params(&mut self, path: &Path) -> Result<()> {
-        self.qnet.load(path.join("qnet.pt").as_path())?;
-        self.qnet_tgt.load(path.join("qnet_tgt.pt").as_path())?;
+        self.qnet.load(find_params(path.join("qnet")))?;
+        self.qnet_tgt.load(find_params(path.join("qnet_tgt")))?;
         Ok(())
     }
 
@@ -375,19 +709,27 @@ where
     <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input>,
     <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
 {
-    type ModelInfo = NamedTensors;
+    /// Parameters of the Q-network and the target network.
+    ///
+    /// The target network is used to compute the priorities of samples in actors.
+    type ModelInfo = (NamedTensors, NamedTensors);
 
     fn model_info(&self) -> (usize, Self::ModelInfo) {
-        unimplemented!();
-        // (
-        //     self.n_opts,
-        //     NamedTensors::copy_from(self.qnet.get_var_store()),
-        // )
+        let copy = |qnet: &DqnModel<Q>| {
+            NamedTensors::copy_from(qnet.get_varmap()).expect("Failed to copy parameters")
+        };
+        (self.n_opts, (copy(&self.qnet), copy(&self.qnet_tgt)))
     }
 
-    fn sync_model(&mut self, _model_info: &Self::ModelInfo) {
-        unimplemented!();
-        // let vs = self.qnet.get_var_store_mut();
-        // model_info.copy_to(vs);
+    fn sync_model(&mut self, model_info: &Self::ModelInfo) {
+        for (qnet, src) in [
+            (&mut self.qnet, &model_info.0),
+            (&mut self.qnet_tgt, &model_info.1),
+        ] {
+            src.copy_to(qnet.get_varmap_mut())
+                .expect("Failed to copy parameters");
+            qnet.sync_amp();
+            qnet.sync_replicas().expect("Failed to sync replicas");
+        }
     }
 }
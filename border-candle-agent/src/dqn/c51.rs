@@ -0,0 +1,157 @@
+//! Categorical DQN (C51).
+//!
+//! The Q-network outputs logits of a categorical distribution over fixed atoms of returns for
+//! each action, i.e., its output dimension is the number of actions times the number of atoms.
+//! See <https://arxiv.org/abs/1707.06887>.
+use anyhow::Result;
+use candle_core::{shape::D, DType, Device, Tensor};
+use candle_nn::ops::{log_softmax, softmax};
+use serde::{Deserialize, Serialize};
+
+/// Configuration of the distributional head of [`Dqn`](super::Dqn).
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct C51Config {
+    /// The number of atoms of the value distribution.
+    pub n_atoms: usize,
+
+    /// The minimum value of the support.
+    pub v_min: f64,
+
+    /// The maximum value of the support.
+    pub v_max: f64,
+}
+
+impl Default for C51Config {
+    fn default() -> Self {
+        Self {
+            n_atoms: 51,
+            v_min: -10.0,
+            v_max: 10.0,
+        }
+    }
+}
+
+impl C51Config {
+    /// Sets the number of atoms.
+    pub fn n_atoms(mut self, v: usize) -> Self {
+        self.n_atoms = v;
+        self
+    }
+
+    /// Sets the range of the support.
+    pub fn value_range(mut self, v_min: f64, v_max: f64) -> Self {
+        self.v_min = v_min;
+        self.v_max = v_max;
+        self
+    }
+
+    /// Returns the interval between atoms.
+    pub fn delta_z(&self) -> f64 {
+        (self.v_max - self.v_min) / (self.n_atoms - 1) as f64
+    }
+
+    /// Returns the atoms as a tensor of shape `[n_atoms]`.
+    pub fn support(&self, device: &Device) -> Result<Tensor> {
+        let dz = self.delta_z();
+        let z = (0..self.n_atoms)
+            .map(|i| (self.v_min + dz * i as f64) as f32)
+            .collect::<Vec<_>>();
+        Ok(Tensor::from_vec(z, &[self.n_atoms], device)?)
+    }
+
+    /// Reshapes the output of the Q-network into `[batch_size, n_actions, n_atoms]`.
+    pub(super) fn reshape(&self, logits: &Tensor) -> Result<Tensor> {
+        let n = logits.dims()[0];
+        Ok(logits.reshape((n, (), self.n_atoms))?)
+    }
+
+    /// Returns probabilities of atoms, `[batch_size, n_actions, n_atoms]`.
+    pub(super) fn probs(&self, logits: &Tensor) -> Result<Tensor> {
+        Ok(softmax(&self.reshape(logits)?, D::Minus1)?)
+    }
+
+    /// Returns log probabilities of atoms, `[batch_size, n_actions, n_atoms]`.
+    pub(super) fn log_probs(&self, logits: &Tensor) -> Result<Tensor> {
+        Ok(log_softmax(&self.reshape(logits)?, D::Minus1)?)
+    }
+
+    /// Returns action values, the expectations of the value distributions,
+    /// `[batch_size, n_actions]`.
+    pub(super) fn q_values(&self, logits: &Tensor) -> Result<Tensor> {
+        let probs = self.probs(logits)?;
+        let z = self.support(logits.device())?;
+        Ok(probs.broadcast_mul(&z)?.sum(D::Minus1)?)
+    }
+
+    /// Projects the distribution of `reward + discount * z` onto the support.
+    ///
+    /// * `probs` - Probabilities of the next state-action pairs, `[batch_size, n_atoms]`.
+    /// * `reward` - `[batch_size]`.
+    /// * `discount` - Discount factor multiplied by the non-terminal flag, `[batch_size]`.
+    ///
+    /// Returns the projected probabilities, `[batch_size, n_atoms]`.
+    pub fn projection(&self, probs: &Tensor, reward: &Tensor, discount: &Tensor) -> Result<Tensor> {
+        let device = probs.device();
+        let n_atoms = self.n_atoms;
+        let z = self.support(device)?.unsqueeze(0)?;
+        let tz = discount
+            .unsqueeze(1)?
+            .broadcast_mul(&z)?
+            .broadcast_add(&reward.unsqueeze(1)?)?
+            .clamp(self.v_min, self.v_max)?;
+        let b = ((tz - self.v_min)? / self.delta_z())?.clamp(0.0, (n_atoms - 1) as f64)?;
+        let l = b.floor()?;
+        let u = b.ceil()?;
+
+        // When `b` is on an atom, `l == u`, and the whole mass goes to the lower atom
+        let is_on_atom = l.eq(&u)?.to_dtype(DType::F32)?;
+        let w_l = ((&u - &b)? + is_on_atom)?;
+        let w_u = (&b - &l)?;
+        let m = probs.zeros_like()?;
+        let m = m.scatter_add(&l.to_dtype(DType::U32)?, &(probs * w_l)?, 1)?;
+        let m = m.scatter_add(&u.to_dtype(DType::U32)?, &(probs * w_u)?, 1)?;
+        Ok(m)
+    }
+
+    /// Returns the entropy of categorical distributions, `[batch_size]`.
+    ///
+    /// * `probs` - Probabilities of atoms, `[batch_size, n_atoms]`.
+    pub fn entropy(probs: &Tensor) -> Result<Tensor> {
+        let log_probs = probs.clamp(1e-8, 1.0)?.log()?;
+        Ok((probs * log_probs)?.sum(D::Minus1)?.neg()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_projection() -> Result<()> {
+        let config = C51Config::default().n_atoms(5).value_range(-2.0, 2.0);
+        let device = Device::Cpu;
+        let probs = Tensor::from_slice(
+            &[
+                0f32, 0., 1., 0., 0., 0., 0., 1., 0., 0., 0.2, 0.2, 0.2, 0.2, 0.2,
+            ],
+            &[3, 5],
+            &device,
+        )?;
+        let reward = Tensor::from_slice(&[0.5f32, 1.0, 5.0], &[3], &device)?;
+        let discount = Tensor::from_slice(&[0f32, 1.0, 0.9], &[3], &device)?;
+        let m = config
+            .projection(&probs, &reward, &discount)?
+            .to_vec2::<f32>()?;
+
+        // Terminal state: the mass is split between the atoms next to the reward
+        assert_eq!(m[0], vec![0., 0., 0.5, 0.5, 0.]);
+        // The mass on an atom is shifted to another atom
+        assert_eq!(m[1], vec![0., 0., 0., 1., 0.]);
+        // Values out of the support are clipped
+        assert_eq!(m[2], vec![0., 0., 0., 0., 1.]);
+        for m in m.iter() {
+            assert!((m.iter().sum::<f32>() - 1.0).abs() < 1e-6);
+        }
+        Ok(())
+    }
+}
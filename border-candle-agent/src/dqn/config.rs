@@ -1,11 +1,12 @@
 //! Configuration of DQN agent.
-use super::{
-    explorer::{DqnExplorer, Softmax},
-    DqnModelConfig,
-};
+use super::{C51Config, DqnModelConfig};
 use crate::{
+    explorer::{DiscreteExplorer, Softmax},
     model::SubModel1,
-    util::{CriticLoss, OutDim},
+    util::{
+        aux_heads::AuxHeadConfig, batch_transform::BatchTransformConfig, CriticLoss, OutDim,
+        SoftUpdateConfig,
+    },
     Device,
 };
 use anyhow::Result;
@@ -28,13 +29,13 @@ where
     Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
 {
     pub model_config: DqnModelConfig<Q::Config>,
-    pub soft_update_interval: usize,
+    #[serde(flatten)]
+    pub soft_update: SoftUpdateConfig,
     pub n_updates_per_opt: usize,
     pub batch_size: usize,
     pub discount_factor: f64,
-    pub tau: f64,
     pub train: bool,
-    pub explorer: DqnExplorer,
+    pub explorer: DiscreteExplorer,
     #[serde(default)]
     pub clip_reward: Option<f64>,
     #[serde(default)]
@@ -43,6 +44,16 @@ where
     pub device: Option<Device>,
     pub critic_loss: CriticLoss,
     pub record_verbose_level: usize,
+    #[serde(default)]
+    pub c51: Option<C51Config>,
+    #[serde(default)]
+    pub aux_heads: Vec<AuxHeadConfig>,
+    #[serde(default)]
+    pub noisy_nets: bool,
+    #[serde(default)]
+    pub batch_transforms: Vec<BatchTransformConfig>,
+    #[serde(default)]
+    pub mc_target: bool,
     pub phantom: PhantomData<Q>,
 }
 
@@ -54,11 +65,10 @@ where
     fn clone(&self) -> Self {
         Self {
             model_config: self.model_config.clone(),
-            soft_update_interval: self.soft_update_interval,
+            soft_update: self.soft_update.clone(),
             n_updates_per_opt: self.n_updates_per_opt,
             batch_size: self.batch_size,
             discount_factor: self.discount_factor,
-            tau: self.tau,
             train: self.train,
             explorer: self.explorer.clone(),
             clip_reward: self.clip_reward,
@@ -67,6 +77,11 @@ where
             device: self.device.clone(),
             critic_loss: self.critic_loss.clone(),
             record_verbose_level: self.record_verbose_level,
+            c51: self.c51.clone(),
+            aux_heads: self.aux_heads.clone(),
+            noisy_nets: self.noisy_nets,
+            batch_transforms: self.batch_transforms.clone(),
+            mc_target: self.mc_target,
             phantom: PhantomData,
         }
     }
@@ -81,14 +96,13 @@ where
     fn default() -> Self {
         Self {
             model_config: Default::default(),
-            soft_update_interval: 1,
+            soft_update: SoftUpdateConfig::default(),
             n_updates_per_opt: 1,
             batch_size: 1,
             discount_factor: 0.99,
-            tau: 0.005,
             train: false,
             // replay_burffer_capacity: 100,
-            explorer: DqnExplorer::Softmax(Softmax::new()),
+            explorer: DiscreteExplorer::Softmax(Softmax::new()),
             // expr_sampling: ExperienceSampling::Uniform,
             clip_reward: None,
             double_dqn: false,
@@ -96,6 +110,11 @@ where
             device: None,
             critic_loss: CriticLoss::Mse,
             record_verbose_level: 0,
+            c51: None,
+            aux_heads: vec![],
+            noisy_nets: false,
+            batch_transforms: vec![],
+            mc_target: false,
             phantom: PhantomData,
         }
     }
@@ -108,7 +127,7 @@ where
 {
     /// Sets soft update interval.
     pub fn soft_update_interval(mut self, v: usize) -> Self {
-        self.soft_update_interval = v;
+        self.soft_update.update_interval = v;
         self
     }
 
@@ -132,12 +151,18 @@ where
 
     /// Soft update coefficient.
     pub fn tau(mut self, v: f64) -> Self {
-        self.tau = v;
+        self.soft_update.tau = v;
+        self
+    }
+
+    /// Sets the configuration of the updates of the target network.
+    pub fn soft_update_config(mut self, v: SoftUpdateConfig) -> Self {
+        self.soft_update = v;
         self
     }
 
     /// Explorer.
-    pub fn explorer(mut self, v: DqnExplorer) -> Self {
+    pub fn explorer(mut self, v: DiscreteExplorer) -> Self {
         self.explorer = v;
         self
     }
@@ -191,6 +216,56 @@ where
         self
     }
 
+    /// Sets the configuration of the distributional head (C51).
+    ///
+    /// If it is set, the Q-network outputs logits of the value distributions of all actions,
+    /// of which dimension is the number of actions given by [`DqnConfig::out_dim()`]
+    /// times the number of atoms.
+    pub fn c51(mut self, v: Option<C51Config>) -> Self {
+        self.c51 = v;
+        self
+    }
+
+    /// Adds an auxiliary head of the Q-network.
+    ///
+    /// The outputs of auxiliary heads, one value for each action, are appended to the output of
+    /// the Q-network, and trained jointly with the Q-network with the weighted auxiliary losses.
+    pub fn aux_head(mut self, v: AuxHeadConfig) -> Self {
+        self.aux_heads.push(v);
+        self
+    }
+
+    /// Sets the flag to explore with noisy networks instead of the explorer.
+    ///
+    /// If `true`, the greedy action is taken in training and the noise of the Q-networks is
+    /// resampled at every parameter update. The Q-network should have noisy layers,
+    /// e.g., [`MlpConfig::noisy`](crate::mlp::MlpConfig::noisy).
+    pub fn noisy_nets(mut self, v: bool) -> Self {
+        self.noisy_nets = v;
+        self
+    }
+
+    /// Adds an augmentation of observations sampled from the replay buffer.
+    ///
+    /// Augmentations are applied in order to observations and next observations independently,
+    /// e.g., [`BatchTransformConfig::RandomShift`] for DrQ on Atari games. The input of
+    /// the Q-network is required to be a tensor, see [`SubModel1::input_as_tensor()`].
+    pub fn batch_transform(mut self, v: BatchTransformConfig) -> Self {
+        self.batch_transforms.push(v);
+        self
+    }
+
+    /// Sets the flag to use Monte Carlo returns as the targets of action values.
+    ///
+    /// If `true`, the targets are the returns given by the replay buffer instead of
+    /// the bootstrapped returns, e.g., with
+    /// [`SimpleReplayBufferConfig::mc_gamma`](border_core::generic_replay_buffer::SimpleReplayBufferConfig::mc_gamma).
+    /// Cannot be combined with [`DqnConfig::c51()`].
+    pub fn mc_target(mut self, v: bool) -> Self {
+        self.mc_target = v;
+        self
+    }
+
     /// Loads [`DqnConfig`] from YAML file.
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let path_ = path.as_ref().to_owned();
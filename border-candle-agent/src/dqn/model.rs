@@ -1,7 +1,12 @@
 use crate::{
     model::SubModel1,
+    onnx::{ElemType, OnnxGraph, OnnxModel},
     opt::{Optimizer, OptimizerConfig},
-    util::OutDim,
+    util::{
+        amp::{Amp, AmpConfig},
+        data_parallel::{DataParallel, DataParallelConfig},
+        OutDim,
+    },
 };
 use anyhow::{Context, Result};
 use border_core::record::Record;
@@ -24,6 +29,10 @@ where
     pub q_config: Option<Q>,
     #[serde(default)]
     pub opt_config: OptimizerConfig,
+    #[serde(default)]
+    pub amp: Option<AmpConfig>,
+    #[serde(default)]
+    pub data_parallel: Option<DataParallelConfig>,
 }
 
 impl<Q> Default for DqnModelConfig<Q>
@@ -34,6 +43,8 @@ where
         Self {
             q_config: None,
             opt_config: OptimizerConfig::default(),
+            amp: None,
+            data_parallel: None,
         }
     }
 }
@@ -63,6 +74,18 @@ where
         self
     }
 
+    /// Enables mixed precision training, see [`crate::util::amp`].
+    pub fn amp(mut self, v: AmpConfig) -> Self {
+        self.amp = Some(v);
+        self
+    }
+
+    /// Enables data-parallel training, see [`crate::util::data_parallel`].
+    pub fn data_parallel(mut self, v: DataParallelConfig) -> Self {
+        self.data_parallel = Some(v);
+        self
+    }
+
     /// Constructs [`DqnModelConfig`] from YAML file.
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let file = File::open(path)?;
@@ -85,6 +108,12 @@ where
 /// which should implement [`SubModel1`].
 /// This takes [`SubModel1::Input`] as input and outputs a tensor.
 /// The output tensor should have the same dimension as the number of actions.
+///
+/// With mixed precision training, the model has a copy of `Q` in half precision,
+/// which is used in [`DqnModel::forward_amp()`].
+///
+/// With data-parallel training, the model has replicas of `Q` on other devices,
+/// which are used in [`DqnModel::forward_batch()`].
 pub struct DqnModel<Q>
 where
     Q: SubModel1<Output = Tensor>,
@@ -103,6 +132,15 @@ where
     opt_config: OptimizerConfig,
     q_config: Q::Config,
     opt: Optimizer,
+
+    // Mixed precision training
+    amp_config: Option<AmpConfig>,
+    amp: Option<Amp>,
+    q_amp: Option<Q>,
+
+    // Data-parallel training
+    data_parallel_config: Option<DataParallelConfig>,
+    data_parallel: Option<DataParallel<Q>>,
 }
 
 impl<Q> DqnModel<Q>
@@ -115,6 +153,11 @@ where
         let out_dim = config.q_config.as_ref().unwrap().get_out_dim();
         let q_config = config.q_config.context("q_config is not set.")?;
         let opt_config = config.opt_config;
+        let amp_config = config.amp;
+        let data_parallel_config = config.data_parallel;
+        if amp_config.is_some() && data_parallel_config.is_some() {
+            anyhow::bail!("Mixed precision training with data-parallel training is not supported");
+        }
         let varmap = VarMap::new();
         let q = {
             let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
@@ -129,9 +172,12 @@ where
             q,
             varmap,
             None,
+            amp_config,
+            data_parallel_config,
         ))
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn _build(
         device: Device,
         out_dim: i64,
@@ -140,6 +186,8 @@ where
         q: Q,
         mut varmap: VarMap,
         varmap_src: Option<&VarMap>,
+        amp_config: Option<AmpConfig>,
+        data_parallel_config: Option<DataParallelConfig>,
     ) -> Self {
         // Optimizer
         let opt = opt_config.build(varmap.all_vars()).unwrap();
@@ -149,7 +197,11 @@ where
             varmap.clone_from(varmap_src);
         }
 
-        Self {
+        let amp = amp_config.as_ref().map(Amp::new);
+        let data_parallel = data_parallel_config
+            .as_ref()
+            .map(|config| DataParallel::build(config, &q_config));
+        let mut model = Self {
             device,
             out_dim,
             opt_config,
@@ -157,7 +209,15 @@ where
             opt,
             q,
             q_config,
-        }
+            amp_config,
+            amp,
+            q_amp: None,
+            data_parallel_config,
+            data_parallel,
+        };
+        model.sync_amp();
+        model.sync_replicas().unwrap();
+        model
     }
 
     /// Outputs the action-value given observation(s).
@@ -165,6 +225,54 @@ where
         self.q.forward(obs)
     }
 
+    /// Outputs the action-value in `f32` computed in the precision of training.
+    ///
+    /// With mixed precision training, observations should be given in the type of
+    /// [`DqnModel::amp_dtype()`].
+    pub fn forward_amp(&self, obs: &Q::Input) -> Tensor {
+        match &self.q_amp {
+            None => self.q.forward(obs),
+            Some(q) => q.forward(obs).to_dtype(DType::F32).unwrap(),
+        }
+    }
+
+    /// Returns the floating point type of the forward pass in mixed precision training.
+    pub fn amp_dtype(&self) -> Option<DType> {
+        self.amp.as_ref().map(Amp::dtype)
+    }
+
+    /// Returns the state of mixed precision training.
+    pub fn amp(&self) -> Option<&Amp> {
+        self.amp.as_ref()
+    }
+
+    /// Rebuilds the model in half precision from the current parameters.
+    ///
+    /// It should be called when the parameters are changed outside of
+    /// [`DqnModel::backward_step()`], e.g., with soft update.
+    pub fn sync_amp(&mut self) {
+        if let Some(amp) = &self.amp {
+            let vb = amp.var_builder(&self.varmap, &self.device);
+            self.q_amp = Some(Q::build(vb, self.q_config.clone()));
+        }
+    }
+
+    /// Copies the parameters to the replicas of data-parallel training.
+    ///
+    /// It should be called when the parameters are changed outside of
+    /// [`DqnModel::backward_step()`], e.g., with soft update.
+    pub fn sync_replicas(&self) -> Result<()> {
+        match &self.data_parallel {
+            None => Ok(()),
+            Some(dp) => dp.sync(&self.varmap),
+        }
+    }
+
+    /// Returns the configuration of the action-value function.
+    pub fn q_config(&self) -> &Q::Config {
+        &self.q_config
+    }
+
     pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
         // Consider to use gradient clipping, below code
         // let mut grads = loss.backward()?;
@@ -175,13 +283,60 @@ where
         //     let _ = grads.insert(&var, g2);
         // }
         // self.opt.step(&grads)
-        self.opt.backward_step(loss)
+        if let Some(dp) = &self.data_parallel {
+            let mut grads = loss.backward()?;
+            dp.reduce_grads(&mut grads, &self.varmap)?;
+            self.opt.step(&mut grads)?;
+            return dp.sync(&self.varmap);
+        }
+        match self.amp.as_mut() {
+            None => self.opt.backward_step(loss),
+            Some(amp) => {
+                if let Some(mut grads) = amp.backward(loss, &self.varmap.all_vars())? {
+                    self.opt.step(&mut grads)?;
+                }
+                self.sync_amp();
+                Ok(())
+            }
+        }
+    }
+
+    /// Resamples the noise of noisy layers in the model.
+    pub fn reset_noise(&mut self) {
+        self.q.reset_noise();
+        if let Some(dp) = self.data_parallel.as_mut() {
+            dp.reset_noise();
+        }
+    }
+
+    /// Sets the learning rate of the optimizer.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        self.opt.set_learning_rate(lr);
+    }
+
+    /// Returns the current learning rate of the optimizer.
+    pub fn learning_rate(&self) -> f64 {
+        self.opt.learning_rate()
+    }
+
+    /// Returns the global norm of gradients before clipping in the last update,
+    /// if gradients are clipped.
+    pub fn grad_norm(&self) -> Option<f32> {
+        self.opt.grad_norm()
     }
 
     pub fn get_varmap(&self) -> &VarMap {
         &self.varmap
     }
 
+    /// Returns the parameters to be modified.
+    ///
+    /// [`DqnModel::sync_amp()`] and [`DqnModel::sync_replicas()`] should be called
+    /// after the parameters are changed.
+    pub fn get_varmap_mut(&mut self) -> &mut VarMap {
+        &mut self.varmap
+    }
+
     pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<()> {
         self.varmap.save(&path)?;
         info!("Save dqnmodel to {:?}", path.as_ref());
@@ -190,6 +345,8 @@ where
 
     pub fn load<T: AsRef<Path>>(&mut self, path: T) -> Result<()> {
         self.varmap.load(&path)?;
+        self.sync_amp();
+        self.sync_replicas()?;
         info!("Load dqnmodel from {:?}", path.as_ref());
         Ok(())
     }
@@ -199,6 +356,24 @@ where
     }
 }
 
+impl<Q> DqnModel<Q>
+where
+    Q: SubModel1<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    /// Outputs the action-value for a batch of observations in training.
+    ///
+    /// With data-parallel training, the batch is split across the replicas, where
+    /// the input of the Q-network is required to be a tensor. Otherwise,
+    /// it is the same as [`DqnModel::forward_amp()`].
+    pub fn forward_batch(&self, obs: &Q::Input) -> Result<Tensor> {
+        match &self.data_parallel {
+            None => Ok(self.forward_amp(obs)),
+            Some(dp) => dp.forward(&self.q, obs),
+        }
+    }
+}
+
 impl<Q> Clone for DqnModel<Q>
 where
     Q: SubModel1<Output = Tensor>,
@@ -223,6 +398,22 @@ where
             q,
             varmap,
             Some(&self.varmap),
+            self.amp_config.clone(),
+            self.data_parallel_config.clone(),
         )
     }
 }
+
+impl<Q> OnnxModel for DqnModel<Q>
+where
+    Q: SubModel1<Output = Tensor> + OnnxModel,
+    Q::Config: DeserializeOwned + Serialize + OutDim,
+{
+    fn onnx_input(&self) -> (ElemType, Vec<i64>) {
+        self.q.onnx_input()
+    }
+
+    fn add_to_graph(&self, graph: &mut OnnxGraph, input: &str) -> Result<Vec<String>> {
+        self.q.add_to_graph(graph, input)
+    }
+}
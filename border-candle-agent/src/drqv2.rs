@@ -0,0 +1,5 @@
+//! DrQ-v2 agent.
+mod base;
+mod config;
+pub use base::DrqV2;
+pub use config::DrqV2Config;
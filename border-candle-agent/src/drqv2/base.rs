@@ -0,0 +1,453 @@
+use super::DrqV2Config;
+use crate::{
+    error::opt_result,
+    model::{SubModel1, SubModel2},
+    opt::Optimizer,
+    util::{
+        batch_transform::BatchTransform, critic::MultiCritic, encoder::Encoder, find_params,
+        gamma_not_done, params_path, smooth_l1_loss, CriticLoss, OutDim,
+    },
+};
+use anyhow::{Context, Result};
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, ModelManifest, Policy, ReplayBufferBase, TransitionBatch,
+};
+use candle_core::{DType::F32, Device, Tensor, D};
+use candle_nn::{loss::mse, VarBuilder, VarMap};
+use log::info;
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+type Feature = Tensor;
+type ActionValue = Tensor;
+type Action = Tensor;
+
+/// DrQ-v2 agent learning continuous control from pixels.
+///
+/// Observations are encoded with an encoder `F`, e.g.,
+/// [`ConvEncoder`](crate::conv_encoder::ConvEncoder), into features given to
+/// a deterministic actor `P` and critics `Q`. By default, the encoder is trained only with
+/// the loss of the critics, and the actor is trained on the features detached from
+/// the encoder, see [`EncoderConfig`](crate::util::encoder::EncoderConfig).
+/// Observations sampled from the replay buffer are augmented with
+/// [`DrqV2Config::batch_transforms`], random shifts by default.
+///
+/// Actions are the outputs of the actor in `[-1, 1]`, with Gaussian noise of which
+/// the standard deviation decays linearly from [`DrqV2Config::std_init`] to
+/// [`DrqV2Config::std_final`] in training mode. Target values are computed with the target
+/// encoder and the target critics, both of which track the online networks with
+/// exponential moving average.
+///
+/// See [Yarats et al., 2021](https://arxiv.org/abs/2107.09645).
+pub struct DrqV2<E, F, Q, P, R>
+where
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    Q: SubModel2<Input1 = Feature, Input2 = Action, Output = ActionValue>,
+    P: SubModel1<Input = Feature, Output = Action>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    Q::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    encoder: Encoder<F>,
+    actor: P,
+    actor_config: P::Config,
+    actor_varmap: VarMap,
+    actor_opt: Optimizer,
+    critic: MultiCritic<Q>,
+    batch_transform: BatchTransform,
+    gamma: f64,
+    std_init: f64,
+    std_final: f64,
+    std_decay_steps: usize,
+    std_clip: f64,
+    n_updates_per_opt: usize,
+    batch_size: usize,
+    train: bool,
+    n_opts: usize,
+    critic_loss: CriticLoss,
+    phantom: PhantomData<(E, R)>,
+    device: Device,
+}
+
+impl<E, F, Q, P, R> DrqV2<E, F, Q, P, R>
+where
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    Q: SubModel2<Input1 = Feature, Input2 = Action, Output = ActionValue>,
+    P: SubModel1<Input = Feature, Output = Action>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    Q::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Returns the current standard deviation of the exploration noise.
+    fn std(&self) -> f64 {
+        let r = (self.n_opts as f64 / self.std_decay_steps.max(1) as f64).min(1.0);
+        self.std_init + r * (self.std_final - self.std_init)
+    }
+
+    /// Adds clipped Gaussian noise to actions and limits them into `[-1, 1]`.
+    fn add_noise(&self, act: &Tensor, clip: Option<f64>) -> Result<Tensor> {
+        let noise = act.randn_like(0.0, self.std())?;
+        let noise = match clip {
+            Some(c) => noise.clamp(-c, c)?,
+            None => noise,
+        };
+        Ok((act + noise)?.clamp(-1f32, 1f32)?)
+    }
+}
+
+impl<E, F, Q, P, R> DrqV2<E, F, Q, P, R>
+where
+    E: Env,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    Q: SubModel2<Input1 = Feature, Input2 = Action, Output = ActionValue>,
+    P: SubModel1<Input = Feature, Output = Action>,
+    R: ReplayBufferBase,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    Q::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Tensor>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    /// Updates the critics and the encoder.
+    ///
+    /// It returns the loss, the augmented observations and their detached features.
+    fn update_critic(&mut self, batch: R::Batch) -> Result<(f32, Tensor, Feature)> {
+        // Extract items in the batch
+        let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
+        let batch_size = reward.len();
+        let reward = Tensor::from_slice(&reward[..], (batch_size,), &self.device)?;
+        let obs = self.batch_transform.apply(&obs.into())?;
+        let next_obs = self.batch_transform.apply(&next_obs.into())?;
+        let act: Tensor = act.into().to_device(&self.device)?;
+
+        // Prediction
+        let feature = self.encoder.forward_critic(&obs);
+        let qs = self.critic.qvals(&feature, &act);
+
+        // Target
+        let tgt = {
+            let gamma_not_done =
+                gamma_not_done(self.gamma as f32, is_terminated, None, &self.device)?;
+            let next_feature = self.encoder.forward_tgt(&next_obs);
+            let next_act = self.actor.forward(&next_feature);
+            let next_act = self.add_noise(&next_act, Some(self.std_clip))?;
+            let next_q = self.critic.qvals_min_tgt(&next_feature, &next_act)?;
+            (&reward + (&gamma_not_done * next_q)?)?.squeeze(D::Minus1)?
+        }
+        .detach();
+        debug_assert_eq!(tgt.dims(), [self.batch_size]);
+
+        // Loss
+        let losses: Vec<_> = match self.critic_loss {
+            CriticLoss::Mse => qs.iter().map(|pred| mse(pred, &tgt).unwrap()).collect(),
+            CriticLoss::SmoothL1 => qs
+                .iter()
+                .map(|pred| smooth_l1_loss(pred, &tgt).unwrap())
+                .collect(),
+        };
+        let loss = Tensor::stack(&losses, 0)?.mean_all()?;
+
+        // Gradients are shared by the critics and the encoder
+        let mut grads = loss.backward()?;
+        self.critic.step(&mut grads)?;
+        self.encoder.step_critic(&mut grads)?;
+
+        Ok((loss.to_scalar::<f32>()?, obs, feature.detach()))
+    }
+
+    fn update_actor(&mut self, obs: &Tensor, feature: Feature) -> Result<f32> {
+        // Features are computed again if the actor loss trains the encoder
+        let feature = match self.encoder.stop_grad_actor() {
+            true => feature,
+            false => self.encoder.forward_actor(obs),
+        };
+        let loss = {
+            let act = self.actor.forward(&feature);
+            let act = self.add_noise(&act, Some(self.std_clip))?;
+            let q = self.critic.qvals_min(&feature, &act)?;
+            q.neg()?.mean_all()?
+        };
+
+        let mut grads = loss.backward()?;
+        self.actor_opt.step(&mut grads)?;
+        self.encoder.step_actor(&mut grads)?;
+
+        Ok(loss.to_scalar::<f32>()?)
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        let mut loss_critic = 0f32;
+        let mut loss_actor = 0f32;
+
+        for _ in 0..self.n_updates_per_opt {
+            let batch = buffer.batch(self.batch_size)?;
+            let (loss, obs, feature) = self.update_critic(batch)?;
+            loss_critic += loss;
+            loss_actor += self.update_actor(&obs, feature)?;
+            self.critic.soft_update()?;
+            self.encoder.soft_update()?;
+            self.n_opts += 1;
+        }
+
+        loss_critic /= self.n_updates_per_opt as f32;
+        loss_actor /= self.n_updates_per_opt as f32;
+
+        let mut record = Record::from_slice(&[
+            ("loss_critic", RecordValue::Scalar(loss_critic)),
+            ("loss_actor", RecordValue::Scalar(loss_actor)),
+            (
+                "lr_critic",
+                RecordValue::Scalar(self.critic.learning_rate() as f32),
+            ),
+            (
+                "lr_actor",
+                RecordValue::Scalar(self.actor_opt.learning_rate() as f32),
+            ),
+            ("std", RecordValue::Scalar(self.std() as f32)),
+        ]);
+        if let Some(lr) = self.encoder.learning_rate() {
+            record.insert("lr_encoder", RecordValue::Scalar(lr as f32));
+        }
+        if let Some(grad_norm) = self.critic.grad_norm() {
+            record.insert("grad_norm_critic", RecordValue::Scalar(grad_norm));
+        }
+
+        Ok(record)
+    }
+}
+
+impl<E, F, Q, P, R> Policy<E> for DrqV2<E, F, Q, P, R>
+where
+    E: Env,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    Q: SubModel2<Input1 = Feature, Input2 = Action, Output = ActionValue>,
+    P: SubModel1<Input = Feature, Output = Action>,
+    E::Obs: Into<Tensor>,
+    E::Act: From<Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    Q::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        let feature = self.encoder.forward(&obs.clone().into());
+        let act = self.actor.forward(&feature).detach();
+        match self.train {
+            true => self.add_noise(&act, None).unwrap().into(),
+            false => act.into(),
+        }
+    }
+}
+
+impl<E, F, Q, P, R> Configurable for DrqV2<E, F, Q, P, R>
+where
+    E: Env,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    Q: SubModel2<Input1 = Feature, Input2 = Action, Output = ActionValue>,
+    P: SubModel1<Input = Feature, Output = Action>,
+    E::Obs: Into<Tensor>,
+    E::Act: From<Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    Q::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = DrqV2Config<F, Q, P>;
+
+    /// Constructs [`DrqV2`] agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for DrQ-v2 agent")
+            .into();
+        let encoder = Encoder::build(config.encoder_config, device.clone()).unwrap();
+        let critic = MultiCritic::build_with_vars(
+            config.critic_config,
+            device.clone(),
+            encoder.shared_vars(),
+        )
+        .unwrap();
+        let actor_config = config
+            .actor_config
+            .context("actor_config is not set.")
+            .unwrap();
+        let actor_varmap = VarMap::new();
+        let actor = {
+            let vb = VarBuilder::from_varmap(&actor_varmap, F32, &device).set_prefix("actor");
+            P::build(vb, actor_config.clone())
+        };
+        let actor_opt = config
+            .actor_opt_config
+            .build(actor_varmap.all_vars())
+            .unwrap();
+        let batch_transform = BatchTransform::new(config.batch_transforms, device.clone(), 42);
+
+        DrqV2 {
+            encoder,
+            actor,
+            actor_config,
+            actor_varmap,
+            actor_opt,
+            critic,
+            batch_transform,
+            gamma: config.gamma,
+            std_init: config.std_init,
+            std_final: config.std_final,
+            std_decay_steps: config.std_decay_steps,
+            std_clip: config.std_clip,
+            n_updates_per_opt: config.n_updates_per_opt,
+            batch_size: config.batch_size,
+            train: false,
+            n_opts: 0,
+            critic_loss: config.critic_loss,
+            phantom: PhantomData,
+            device,
+        }
+    }
+}
+
+impl<E, F, Q, P, R> Agent<E, R> for DrqV2<E, F, Q, P, R>
+where
+    E: Env + 'static,
+    F: SubModel1<Input = Tensor, Output = Feature> + 'static,
+    Q: SubModel2<Input1 = Feature, Input2 = Action, Output = ActionValue> + 'static,
+    P: SubModel1<Input = Feature, Output = Action> + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<Tensor>,
+    E::Act: From<Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    Q::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Tensor>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.try_opt_with_record(buffer)
+            .expect("Failed in DrqV2::opt_()")
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
+        Ok(opt_result(self.opt_(buffer))?)
+    }
+
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+
+        let (encoder_path, encoder_tgt_path) = self.encoder.save(path.join("encoder"))?;
+        let actor_path = params_path(path.join("actor"));
+        self.actor_varmap.save(&actor_path)?;
+        info!("Save actor to {:?}", actor_path);
+        let (critic_path, critic_tgt_path) = self.critic.save(path.join("critic"))?;
+
+        let mut paths = vec![
+            encoder_path,
+            encoder_tgt_path,
+            actor_path,
+            critic_path,
+            critic_tgt_path,
+        ];
+
+        // The policy consists of the encoder and the actor
+        let encoder_config = self.encoder.config();
+        ModelManifest::new("candle", "DrqV2")
+            .obs_shape(encoder_config.get_in_shape())
+            .act_dim(self.actor_config.get_out_dim())
+            .config(&(encoder_config, &self.actor_config))?
+            .save_in(path, &mut paths)?;
+
+        Ok(paths)
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.encoder.load(path.join("encoder"))?;
+        let actor_path = find_params(path.join("actor"));
+        self.actor_varmap.load(&actor_path)?;
+        info!("Load actor from {:?}", actor_path);
+        self.critic.load(path.join("critic"))?;
+
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        conv_encoder::{ConvEncoder, ConvEncoderConfig},
+        mlp::{Mlp, MlpConfig},
+        test_env::{replay_buffer, PixelEnv, PixelObs, DIM_ACT, OBS_SHAPE},
+        util::{critic::MultiCriticConfig, encoder::EncoderConfig},
+        Activation, TensorBatch,
+    };
+    use border_core::generic_replay_buffer::SimpleReplayBuffer;
+    use candle_core::DType;
+
+    type Buffer = SimpleReplayBuffer<TensorBatch, TensorBatch>;
+
+    #[test]
+    fn test_drqv2_opt() -> Result<()> {
+        let [c, h, w] = OBS_SHAPE;
+        let dim_feature = 8;
+        let dim_act = DIM_ACT as i64;
+        let config = DrqV2Config::<ConvEncoder, Mlp, Mlp>::default()
+            .encoder_config(EncoderConfig::default().encoder_config(
+                ConvEncoderConfig::new([c as _, h as _, w as _], dim_feature).n_filters(4),
+            ))
+            .actor_config(MlpConfig::new(
+                dim_feature,
+                vec![16],
+                dim_act,
+                Activation::Tanh,
+            ))
+            .critic_config(MultiCriticConfig::default().q_config(MlpConfig::new(
+                dim_feature + dim_act,
+                vec![16],
+                1,
+                Activation::None,
+            )))
+            .batch_size(4)
+            .n_updates_per_opt(2)
+            .device(Device::Cpu);
+        let mut agent = DrqV2::<PixelEnv, ConvEncoder, Mlp, Mlp, Buffer>::build(config);
+        let mut buffer = replay_buffer(16)?;
+
+        agent.train();
+        for _ in 0..3 {
+            let record = agent.try_opt_with_record(&mut buffer)?;
+            for key in ["loss_critic", "loss_actor"] {
+                assert!(record.get_scalar(key)?.is_finite(), "{} is not finite", key);
+            }
+        }
+        assert_eq!(agent.n_opts, 6);
+
+        let obs = PixelObs(Tensor::zeros((2, c, h, w), DType::U8, &Device::Cpu)?);
+        let act: Tensor = agent.sample(&obs).into();
+        assert_eq!(act.dims(), [2, DIM_ACT]);
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+//! Configuration of DrQ-v2 agent.
+use crate::{
+    model::{SubModel1, SubModel2},
+    opt::OptimizerConfig,
+    util::{
+        batch_transform::BatchTransformConfig, critic::MultiCriticConfig, encoder::EncoderConfig,
+        CriticLoss, OutDim,
+    },
+    Device,
+};
+use anyhow::Result;
+use candle_core::Tensor;
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Configuration of [`DrqV2`](super::DrqV2).
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct DrqV2Config<F, Q, P>
+where
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    /// Configuration of the encoder shared by the actor and the critics.
+    pub encoder_config: EncoderConfig<F::Config>,
+
+    /// Configuration of the actor network, which outputs actions in `[-1, 1]`.
+    pub actor_config: Option<P::Config>,
+
+    /// Configuration of the optimizer of the actor.
+    pub actor_opt_config: OptimizerConfig,
+
+    /// Configuration of the critic model.
+    pub critic_config: MultiCriticConfig<Q::Config>,
+
+    /// Augmentations of observations sampled from the replay buffer.
+    pub batch_transforms: Vec<BatchTransformConfig>,
+
+    /// Discont factor.
+    pub gamma: f64,
+
+    /// Initial standard deviation of the exploration noise.
+    pub std_init: f64,
+
+    /// Final standard deviation of the exploration noise.
+    pub std_final: f64,
+
+    /// The number of optimization steps in which the standard deviation decays linearly.
+    pub std_decay_steps: usize,
+
+    /// Clipping value of the noise added to next actions in target values.
+    pub std_clip: f64,
+
+    /// Number of parameter updates per optimization step.
+    pub n_updates_per_opt: usize,
+
+    /// Batch size for training.
+    pub batch_size: usize,
+
+    /// Type of critic loss function.
+    pub critic_loss: CriticLoss,
+
+    /// Device for models.
+    pub device: Option<Device>,
+}
+
+impl<F, Q, P> Clone for DrqV2Config<F, Q, P>
+where
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            encoder_config: self.encoder_config.clone(),
+            actor_config: self.actor_config.clone(),
+            actor_opt_config: self.actor_opt_config.clone(),
+            critic_config: self.critic_config.clone(),
+            batch_transforms: self.batch_transforms.clone(),
+            gamma: self.gamma,
+            std_init: self.std_init,
+            std_final: self.std_final,
+            std_decay_steps: self.std_decay_steps,
+            std_clip: self.std_clip,
+            n_updates_per_opt: self.n_updates_per_opt,
+            batch_size: self.batch_size,
+            critic_loss: self.critic_loss.clone(),
+            device: self.device,
+        }
+    }
+}
+
+impl<F, Q, P> Default for DrqV2Config<F, Q, P>
+where
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    fn default() -> Self {
+        Self {
+            encoder_config: Default::default(),
+            actor_config: None,
+            actor_opt_config: OptimizerConfig::Adam { lr: 0.0001 },
+            critic_config: MultiCriticConfig::default()
+                .opt_config(OptimizerConfig::Adam { lr: 0.0001 })
+                .tau(0.01),
+            batch_transforms: vec![BatchTransformConfig::RandomShift { pad: 4 }],
+            gamma: 0.99,
+            std_init: 1.0,
+            std_final: 0.1,
+            std_decay_steps: 500_000,
+            std_clip: 0.3,
+            n_updates_per_opt: 1,
+            batch_size: 256,
+            critic_loss: CriticLoss::Mse,
+            device: None,
+        }
+    }
+}
+
+impl<F, Q, P> DrqV2Config<F, Q, P>
+where
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    Q: SubModel2<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+{
+    /// Configuration of encoder.
+    pub fn encoder_config(mut self, v: EncoderConfig<F::Config>) -> Self {
+        self.encoder_config = v;
+        self
+    }
+
+    /// Configuration of actor.
+    pub fn actor_config(mut self, v: P::Config) -> Self {
+        self.actor_config = Some(v);
+        self
+    }
+
+    /// Configuration of the optimizer of actor.
+    pub fn actor_opt_config(mut self, v: OptimizerConfig) -> Self {
+        self.actor_opt_config = v;
+        self
+    }
+
+    /// Configuration of critic.
+    pub fn critic_config(mut self, v: MultiCriticConfig<Q::Config>) -> Self {
+        self.critic_config = v;
+        self
+    }
+
+    /// Augmentations of observations.
+    pub fn batch_transforms(mut self, v: Vec<BatchTransformConfig>) -> Self {
+        self.batch_transforms = v;
+        self
+    }
+
+    /// Discount factor.
+    pub fn discount_factor(mut self, v: f64) -> Self {
+        self.gamma = v;
+        self
+    }
+
+    /// Schedule of the standard deviation of the exploration noise.
+    pub fn std_schedule(mut self, std_init: f64, std_final: f64, decay_steps: usize) -> Self {
+        self.std_init = std_init;
+        self.std_final = std_final;
+        self.std_decay_steps = decay_steps;
+        self
+    }
+
+    /// Clipping value of the target policy noise.
+    pub fn std_clip(mut self, v: f64) -> Self {
+        self.std_clip = v;
+        self
+    }
+
+    /// Sets the numper of parameter update steps per optimization step.
+    pub fn n_updates_per_opt(mut self, v: usize) -> Self {
+        self.n_updates_per_opt = v;
+        self
+    }
+
+    /// Batch size.
+    pub fn batch_size(mut self, v: usize) -> Self {
+        self.batch_size = v;
+        self
+    }
+
+    /// Critic loss.
+    pub fn critic_loss(mut self, v: CriticLoss) -> Self {
+        self.critic_loss = v;
+        self
+    }
+
+    /// Device.
+    pub fn device(mut self, device: candle_core::Device) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Constructs [`DrqV2Config`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path_ = path.as_ref().to_owned();
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        info!(
+            "Load config of DrQ-v2 agent from {}",
+            path_.to_str().unwrap()
+        );
+        Ok(b)
+    }
+
+    /// Saves [`DrqV2Config`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path_ = path.as_ref().to_owned();
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        info!(
+            "Save config of DrQ-v2 agent into {}",
+            path_.to_str().unwrap()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,136 @@
+//! Errors in optimization steps of agents.
+use border_core::record::{Record, RecordValue};
+use thiserror::Error;
+
+/// Errors in optimization steps of agents.
+///
+/// Errors of candle in the update of models are classified into this type, and returned by
+/// [`Agent::try_opt()`] and [`Agent::try_opt_with_record()`] of the agents in this crate,
+/// so that [`Trainer`] can save the model and stop training with a clear message, instead
+/// of panicking deep in model code. The error can be taken from [`anyhow::Error`] with
+/// `downcast_ref::<AgentError>()`.
+///
+/// [`Agent::try_opt()`]: border_core::Agent::try_opt
+/// [`Agent::try_opt_with_record()`]: border_core::Agent::try_opt_with_record
+/// [`Trainer`]: border_core::Trainer
+#[derive(Error, Debug)]
+pub enum AgentError {
+    /// Errors of devices, e.g., out of memory of CUDA or tensors on different devices.
+    #[error("Device error: {0}")]
+    Device(String),
+
+    /// Errors of shapes and data types of tensors, e.g., an observation of unexpected shape.
+    #[error("Shape error: {0}")]
+    Shape(String),
+
+    /// Non-finite values, e.g., a loss diverging to NaN.
+    #[error("Numerical error: {0}")]
+    Numerical(String),
+
+    /// Other errors.
+    #[error(transparent)]
+    Other(anyhow::Error),
+}
+
+impl From<candle_core::Error> for AgentError {
+    fn from(err: candle_core::Error) -> Self {
+        use candle_core::Error as E;
+
+        // Unwrap the context of the error for classification, keeping the message
+        let mut inner = &err;
+        while let E::Context { inner: e, .. }
+        | E::WithPath { inner: e, .. }
+        | E::WithBacktrace { inner: e, .. } = inner
+        {
+            inner = e;
+        }
+        let msg = err.to_string();
+
+        match inner {
+            E::Cuda(_)
+            | E::Metal(_)
+            | E::DeviceMismatchBinaryOp { .. }
+            | E::NotCompiledWithCudaSupport
+            | E::NotCompiledWithMetalSupport => Self::Device(msg),
+            E::UnexpectedDType { .. }
+            | E::DTypeMismatchBinaryOp { .. }
+            | E::UnsupportedDTypeForOp(..)
+            | E::DimOutOfRange { .. }
+            | E::UnexpectedNumberOfDims { .. }
+            | E::UnexpectedShape { .. }
+            | E::ShapeMismatch { .. }
+            | E::ShapeMismatchBinaryOp { .. }
+            | E::ShapeMismatchCat { .. }
+            | E::ShapeMismatchSplit { .. }
+            | E::BroadcastIncompatibleShapes { .. }
+            | E::EmptyTensor { .. }
+            | E::NarrowInvalidArgs { .. }
+            | E::InvalidIndex { .. } => Self::Shape(msg),
+            _ if msg.contains("out of memory") => Self::Device(msg),
+            _ => Self::Other(err.into()),
+        }
+    }
+}
+
+impl From<anyhow::Error> for AgentError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<AgentError>() {
+            Ok(err) => err,
+            Err(err) => match err.downcast::<candle_core::Error>() {
+                Ok(err) => err.into(),
+                Err(err) => Self::Other(err),
+            },
+        }
+    }
+}
+
+/// Returns [`AgentError::Numerical`] if the value, e.g., a loss, is not finite.
+pub fn check_finite(name: &str, v: f32) -> Result<f32, AgentError> {
+    match v.is_finite() {
+        true => Ok(v),
+        false => Err(AgentError::Numerical(format!("{} is {}", name, v))),
+    }
+}
+
+/// Converts the result of an optimization step into that with [`AgentError`].
+///
+/// Non-finite losses in the record, i.e., scalars with keys containing `loss`,
+/// are returned as [`AgentError::Numerical`].
+pub(crate) fn opt_result(result: anyhow::Result<Record>) -> Result<Record, AgentError> {
+    let record = result?;
+    for (k, v) in record.iter() {
+        if let RecordValue::Scalar(v) = v {
+            if k.contains("loss") {
+                check_finite(k, *v)?;
+            }
+        }
+    }
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::{Device, Tensor};
+
+    #[test]
+    fn test_agent_error() {
+        let x = Tensor::zeros((2, 3), candle_core::DType::F32, &Device::Cpu).unwrap();
+        let y = Tensor::zeros((3, 2), candle_core::DType::F32, &Device::Cpu).unwrap();
+        let err = anyhow::Error::from((&x + &y).unwrap_err());
+        assert!(matches!(AgentError::from(err), AgentError::Shape(_)));
+
+        let err = anyhow::anyhow!("Failed to sample a batch");
+        assert!(matches!(AgentError::from(err), AgentError::Other(_)));
+        assert!(matches!(
+            check_finite("loss", f32::NAN),
+            Err(AgentError::Numerical(_))
+        ));
+
+        let record = Record::from_scalar("loss_critic", f32::INFINITY);
+        assert!(matches!(
+            opt_result(Ok(record)),
+            Err(AgentError::Numerical(_))
+        ));
+    }
+}
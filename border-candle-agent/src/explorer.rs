@@ -0,0 +1,43 @@
+//! Exploration strategies of agents with discrete actions.
+//!
+//! Explorers are defined in [`border_core::explorer`], and [`Backend`] converts action values
+//! and actions of them from and into [`Tensor`]s. Built-in explorers are selected with
+//! [`DiscreteExplorer`] in configurations of agents, and any type implementing
+//! `Explorer<Backend>` can be given to agents after they are built, e.g., with
+//! [`Dqn::set_explorer()`](crate::dqn::Dqn::set_explorer).
+use border_core::explorer::ExplorerBackend;
+pub use border_core::explorer::{DiscreteExplorer, EpsilonGreedy, Explorer, Softmax, Ucb};
+use candle_core::{DType, Tensor};
+
+/// Marker type of the candle backend of explorers.
+pub struct Backend;
+
+impl ExplorerBackend for Backend {
+    type Tensor = Tensor;
+
+    fn values(a: &Tensor) -> Vec<Vec<f32>> {
+        a.to_dtype(DType::F32).unwrap().to_vec2().unwrap()
+    }
+
+    fn actions(a: &Tensor, acts: Vec<i64>) -> Tensor {
+        let n = acts.len();
+        Tensor::from_vec(acts, &[n], a.device()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::Device;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    #[test]
+    fn test_backend() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut explorer = EpsilonGreedy::with_params(0.0, 0.0, 1);
+        let a =
+            Tensor::from_slice(&[0.1f32, 0.0, 0.0, 0.0, 0.0, 0.2], &[2, 3], &Device::Cpu).unwrap();
+        let act = Explorer::<Backend>::action(&mut explorer, &a, &mut rng);
+        assert_eq!(act.to_vec1::<i64>().unwrap(), vec![0, 2]);
+    }
+}
@@ -1,9 +1,10 @@
 use super::{IqlConfig, Value};
 use crate::{
+    error::opt_result,
     model::{SubModel1, SubModel2},
     util::{
         actor::GaussianActor, asymmetric_l2_loss, critic::MultiCritic, gamma_not_done, reward,
-        smooth_l1_loss, CriticLoss, OutDim,
+        save_manifest, smooth_l1_loss, CriticLoss, OutDim,
     },
 };
 use anyhow::Result;
@@ -161,9 +162,10 @@ where
 
         for _ in 0..self.n_updates_per_opt {
             let batch = buffer.batch(self.batch_size).unwrap();
-            let (obs, act, next_obs, _reward, is_terminated, is_truncated, _, _) = batch.unpack();
+            let (obs, act, next_obs, _reward, is_terminated, _, _, _) = batch.unpack();
             let reward = reward(_reward, &self.device)?;
-            let gnd = gamma_not_done(self.gamma, is_terminated, Some(is_truncated), &self.device)?;
+            // Bootstrap on truncated transitions, e.g., timeouts in D4RL datasets
+            let gnd = gamma_not_done(self.gamma, is_terminated, None, &self.device)?;
             let obs = &obs.into();
             let act = &act.into();
             let next_obs = &next_obs.into();
@@ -286,7 +288,12 @@ where
     }
 
     fn opt_with_record(&mut self, buffer: &mut R) -> Record {
-        self.opt_(buffer).expect("Failed in Iql::opt_()")
+        self.try_opt_with_record(buffer)
+            .expect("Failed in Iql::opt_()")
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
+        Ok(opt_result(self.opt_(buffer))?)
     }
 
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
@@ -297,7 +304,9 @@ where
         let (critic_path, critic_tgt_path) = self.critic.save(path.join("critic"))?;
         let value_path = self.value.save(path.join("value"))?;
 
-        Ok(vec![actor_path, critic_path, critic_tgt_path, value_path])
+        let mut paths = vec![actor_path, critic_path, critic_tgt_path, value_path];
+        save_manifest(path, "Iql", self.actor.policy_config(), &mut paths)?;
+        Ok(paths)
     }
 
     fn load_params(&mut self, path: &Path) -> Result<()> {
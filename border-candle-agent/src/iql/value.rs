@@ -2,6 +2,7 @@
 use crate::{
     model::SubModel1,
     opt::{Optimizer, OptimizerConfig},
+    util::{find_params, params_path},
 };
 use anyhow::{Context, Result};
 use candle_core::{DType, Device, Tensor};
@@ -122,20 +123,18 @@ where
         self.opt.backward_step(loss)
     }
 
-    /// Save variables to prefix + ".pt".
+    /// Save variables to prefix + ".safetensors".
     pub fn save(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
-        let mut path = PathBuf::from(prefix.as_ref());
-        path.set_extension("pt");
+        let path = params_path(prefix);
         self.varmap.save(&path.as_path())?;
         info!("Save value network parameters to {:?}", path);
 
         Ok(path)
     }
 
-    /// Load variables from prefix + ".pt".
+    /// Load variables from prefix + ".safetensors", or prefix + ".pt" saved by older versions.
     pub fn load(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
-        let mut path = PathBuf::from(prefix.as_ref());
-        path.set_extension("pt");
+        let path = find_params(prefix);
         self.varmap.load(&path.as_path())?;
         info!("Load value network parameters from {:?}", path);
 
@@ -1,15 +1,25 @@
 //! RL agents implemented with [candle](https://crates.io/crates/candle-core).
 pub mod atari_cnn;
+pub mod bootstrapped_dqn;
+pub mod conv_encoder;
 pub mod dqn;
+pub mod drqv2;
+pub mod error;
+pub mod explorer;
 // pub mod iqn;
 pub mod awac;
 pub mod bc;
+pub mod cql;
 pub mod iql;
 pub mod mlp;
 pub mod model;
+pub mod onnx;
 pub mod opt;
+pub mod ppo;
 pub mod sac;
 mod tensor_batch;
+#[cfg(test)]
+mod test_env;
 pub mod util;
 use candle_core::{backend::BackendDevice, DeviceLocation, Module};
 use serde::{Deserialize, Serialize};
@@ -27,6 +37,11 @@ pub enum Device {
 
     /// The main GPU device.
     Cuda(usize),
+
+    /// The GPU device of Apple Silicon.
+    ///
+    /// It requires the `metal` feature.
+    Metal(usize),
 }
 
 impl From<candle_core::Device> for Device {
@@ -40,7 +55,13 @@ impl From<candle_core::Device> for Device {
                     _ => panic!(),
                 }
             }
-            _ => unimplemented!(),
+            candle_core::Device::Metal(metal_device) => {
+                let loc = metal_device.location();
+                match loc {
+                    DeviceLocation::Metal { gpu_id } => Self::Metal(gpu_id),
+                    _ => panic!(),
+                }
+            }
         }
     }
 }
@@ -50,10 +71,26 @@ impl Into<candle_core::Device> for Device {
         match self {
             Self::Cpu => candle_core::Device::Cpu,
             Self::Cuda(n) => candle_core::Device::new_cuda(n).unwrap(),
+            Self::Metal(n) => candle_core::Device::new_metal(n).unwrap(),
         }
     }
 }
 
+/// Returns a CUDA device if available, a Metal device if available, or the CPU otherwise.
+///
+/// It is [`candle_core::Device::cuda_if_available()`] falling back to Metal on macOS.
+/// CUDA and Metal are available when candle is compiled with the `cuda` and `metal`
+/// features, respectively.
+pub fn gpu_if_available(ordinal: usize) -> candle_core::Result<candle_core::Device> {
+    if candle_core::utils::cuda_is_available() {
+        candle_core::Device::new_cuda(ordinal)
+    } else if candle_core::utils::metal_is_available() {
+        candle_core::Device::new_metal(ordinal)
+    } else {
+        Ok(candle_core::Device::Cpu)
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
 pub enum Activation {
     None,
@@ -3,15 +3,17 @@ mod base;
 mod config;
 mod mlp2;
 mod mlp3;
+mod noisy_linear;
 use crate::Activation;
 pub use base::Mlp;
 use candle_core::Tensor;
-use candle_nn::{Linear, Module};
+use candle_nn::Module;
 pub use config::MlpConfig;
 pub use mlp2::Mlp2;
 pub use mlp3::Mlp3;
+pub use noisy_linear::NoisyLinear;
 
-fn mlp_forward(xs: Tensor, layers: &Vec<Linear>, final_act: &Activation) -> Tensor {
+fn mlp_forward<M: Module>(xs: Tensor, layers: &[M], final_act: &Activation) -> Tensor {
     let n_layers = layers.len();
     let mut xs = xs;
 
@@ -1,32 +1,75 @@
-use super::{mlp_forward, MlpConfig};
-use crate::model::{SubModel1, SubModel2};
+use super::{mlp_forward, MlpConfig, NoisyLinear};
+use crate::{
+    model::{SubModel1, SubModel2},
+    onnx::{ElemType, OnnxGraph, OnnxModel},
+    Activation,
+};
 use anyhow::Result;
 use candle_core::{Device, Tensor, D};
 use candle_nn::{linear, Linear, VarBuilder};
 
-/// Returns vector of linear modules from [`MlpConfig`].
-fn create_linear_layers(prefix: &str, vs: VarBuilder, config: &MlpConfig) -> Result<Vec<Linear>> {
+/// Returns pairs of input and output dimensions of layers.
+fn in_out_pairs(config: &MlpConfig) -> Vec<(i64, i64)> {
     let mut in_out_pairs: Vec<(i64, i64)> = (0..config.units.len() - 1)
         .map(|i| (config.units[i], config.units[i + 1]))
         .collect();
     in_out_pairs.insert(0, (config.in_dim, config.units[0]));
     in_out_pairs.push((*config.units.last().unwrap(), config.out_dim));
+    in_out_pairs
+}
+
+/// Returns vector of linear modules from [`MlpConfig`].
+fn create_linear_layers(prefix: &str, vs: VarBuilder, config: &MlpConfig) -> Result<MlpLayers> {
     let vs = vs.pp(prefix);
+    let in_out_pairs = in_out_pairs(config).into_iter().enumerate();
+
+    match config.noisy {
+        false => Ok(MlpLayers::Linear(
+            in_out_pairs
+                .map(|(i, (in_dim, out_dim))| {
+                    linear(in_dim as _, out_dim as _, vs.pp(format!("ln{}", i))).unwrap()
+                })
+                .collect(),
+        )),
+        true => Ok(MlpLayers::Noisy(
+            in_out_pairs
+                .map(|(i, (in_dim, out_dim))| {
+                    NoisyLinear::new(in_dim as _, out_dim as _, 0.5, vs.pp(format!("ln{}", i)))
+                        .unwrap()
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// Layers of [`Mlp`].
+enum MlpLayers {
+    Linear(Vec<Linear>),
+    Noisy(Vec<NoisyLinear>),
+}
 
-    Ok(in_out_pairs
-        .iter()
-        .enumerate()
-        .map(|(i, &(in_dim, out_dim))| {
-            linear(in_dim as _, out_dim as _, vs.pp(format!("ln{}", i))).unwrap()
-        })
-        .collect())
+impl MlpLayers {
+    fn forward(&self, xs: Tensor, final_act: &Activation) -> Tensor {
+        match self {
+            Self::Linear(layers) => mlp_forward(xs, layers, final_act),
+            Self::Noisy(layers) => mlp_forward(xs, layers, final_act),
+        }
+    }
+
+    fn reset_noise(&mut self) {
+        if let Self::Noisy(layers) = self {
+            layers.iter_mut().for_each(|l| l.reset_noise().unwrap());
+        }
+    }
 }
 
 /// Multilayer perceptron with ReLU activation function.
+///
+/// If [`MlpConfig::noisy`] is `true`, [`NoisyLinear`] layers are used.
 pub struct Mlp {
     config: MlpConfig,
     device: Device,
-    layers: Vec<Linear>,
+    layers: MlpLayers,
 }
 
 fn _build(vs: VarBuilder, config: MlpConfig) -> Mlp {
@@ -47,12 +90,46 @@ impl SubModel1 for Mlp {
 
     fn forward(&self, xs: &Self::Input) -> Tensor {
         let xs = xs.to_device(&self.device).unwrap();
-        mlp_forward(xs, &self.layers, &self.config.activation_out)
+        self.layers.forward(xs, &self.config.activation_out)
     }
 
     fn build(vs: VarBuilder, config: Self::Config) -> Self {
         _build(vs, config)
     }
+
+    fn reset_noise(&mut self) {
+        self.layers.reset_noise();
+    }
+
+    fn input_as_tensor(input: &Self::Input) -> Option<&Tensor> {
+        Some(input)
+    }
+
+    fn input_from_tensor(xs: Tensor) -> Option<Self::Input> {
+        Some(xs)
+    }
+}
+
+impl OnnxModel for Mlp {
+    fn onnx_input(&self) -> (ElemType, Vec<i64>) {
+        (ElemType::Float, vec![self.config.in_dim])
+    }
+
+    /// Returns an error if [`NoisyLinear`] layers are used.
+    fn add_to_graph(&self, graph: &mut OnnxGraph, input: &str) -> Result<Vec<String>> {
+        let layers = match &self.layers {
+            MlpLayers::Linear(layers) => layers,
+            MlpLayers::Noisy(_) => anyhow::bail!("Export of NoisyLinear to ONNX is not supported"),
+        };
+        let mut xs = input.to_string();
+        for (i, layer) in layers.iter().enumerate() {
+            xs = graph.add_linear(&xs, layer, &format!("mlp.ln{}", i))?;
+            if i < layers.len() - 1 {
+                xs = graph.add_activation(&xs, &Activation::ReLU);
+            }
+        }
+        Ok(vec![graph.add_activation(&xs, &self.config.activation_out)])
+    }
 }
 
 impl SubModel2 for Mlp {
@@ -69,7 +146,7 @@ impl SubModel2 for Mlp {
             .unwrap()
             .to_device(&self.device)
             .unwrap();
-        mlp_forward(input, &self.layers, &self.config.activation_out)
+        self.layers.forward(input, &self.config.activation_out)
     }
 
     fn build(vs: VarBuilder, config: Self::Config) -> Self {
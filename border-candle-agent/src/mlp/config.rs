@@ -8,6 +8,10 @@ pub struct MlpConfig {
     pub units: Vec<i64>,
     pub out_dim: i64,
     pub activation_out: Activation,
+
+    /// If `true`, [`NoisyLinear`](super::NoisyLinear) layers are used instead of linear layers.
+    #[serde(default)]
+    pub noisy: bool,
 }
 
 impl MlpConfig {
@@ -20,8 +24,15 @@ impl MlpConfig {
             units,
             out_dim,
             activation_out,
+            noisy: false,
         }
     }
+
+    /// Sets the flag to use noisy linear layers.
+    pub fn noisy(mut self, v: bool) -> Self {
+        self.noisy = v;
+        self
+    }
 }
 
 impl OutDim for MlpConfig {
@@ -32,4 +43,8 @@ impl OutDim for MlpConfig {
     fn set_out_dim(&mut self, out_dim: i64) {
         self.out_dim = out_dim;
     }
+
+    fn get_in_shape(&self) -> Option<Vec<i64>> {
+        Some(vec![self.in_dim])
+    }
 }
@@ -1,5 +1,9 @@
 use super::{mlp_forward, MlpConfig};
-use crate::model::SubModel1;
+use crate::{
+    model::SubModel1,
+    onnx::{ElemType, OnnxGraph, OnnxModel},
+    Activation,
+};
 use anyhow::Result;
 use candle_core::{Device, Module, Tensor};
 use candle_nn::{linear, Linear, VarBuilder};
@@ -62,4 +66,30 @@ impl SubModel1 for Mlp2 {
             layers,
         }
     }
+
+    fn input_as_tensor(input: &Self::Input) -> Option<&Tensor> {
+        Some(input)
+    }
+
+    fn input_from_tensor(xs: Tensor) -> Option<Self::Input> {
+        Some(xs)
+    }
+}
+
+impl OnnxModel for Mlp2 {
+    fn onnx_input(&self) -> (ElemType, Vec<i64>) {
+        (ElemType::Float, vec![self._config.in_dim])
+    }
+
+    fn add_to_graph(&self, graph: &mut OnnxGraph, input: &str) -> Result<Vec<String>> {
+        let mut xs = input.to_string();
+        for (i, layer) in self.layers.iter().enumerate() {
+            xs = graph.add_linear(&xs, layer, &format!("mlp.ln{}", i))?;
+            xs = graph.add_activation(&xs, &Activation::ReLU);
+        }
+        let mean = graph.add_linear(&xs, &self.head1, "mean")?;
+        let std = graph.add_linear(&xs, &self.head2, "std")?;
+        let std = graph.add_node("Exp", &[&std], vec![]);
+        Ok(vec![mean, std])
+    }
 }
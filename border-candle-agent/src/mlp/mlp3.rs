@@ -1,5 +1,9 @@
 use super::{mlp_forward, MlpConfig};
-use crate::model::SubModel1;
+use crate::{
+    model::SubModel1,
+    onnx::{ElemType, OnnxGraph, OnnxModel},
+    Activation,
+};
 use anyhow::Result;
 use candle_core::{Device, Tensor};
 use candle_nn::{init::Init, linear, Linear, VarBuilder};
@@ -63,4 +67,32 @@ impl SubModel1 for Mlp3 {
             head2,
         }
     }
+
+    fn input_as_tensor(input: &Self::Input) -> Option<&Tensor> {
+        Some(input)
+    }
+
+    fn input_from_tensor(xs: Tensor) -> Option<Self::Input> {
+        Some(xs)
+    }
+}
+
+impl OnnxModel for Mlp3 {
+    fn onnx_input(&self) -> (ElemType, Vec<i64>) {
+        (ElemType::Float, vec![self._config.in_dim])
+    }
+
+    fn add_to_graph(&self, graph: &mut OnnxGraph, input: &str) -> Result<Vec<String>> {
+        let mut ys = input.to_string();
+        for (i, layer) in self.layers.iter().enumerate() {
+            ys = graph.add_linear(&ys, layer, &format!("mlp.ln{}", i))?;
+            if i < self.layers.len() - 1 {
+                ys = graph.add_activation(&ys, &Activation::ReLU);
+            }
+        }
+        let head2 = graph.add_tensor("head2", &self.head2)?;
+        let shape = graph.add_node("Shape", &[&ys], vec![]);
+        let zs = graph.add_node("Expand", &[&head2, &shape], vec![]);
+        Ok(vec![ys, zs])
+    }
 }
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use candle_core::{DType, Device, Tensor};
+use candle_nn::{Init, Linear, Module, VarBuilder};
+
+/// Linear layer with factorized Gaussian noise on its weights and biases.
+///
+/// Weights are `mu + sigma * eps`, where `mu` and `sigma` are trainable and `eps` is
+/// the noise, resampled with [`NoisyLinear::reset_noise()`].
+/// See <https://arxiv.org/abs/1706.10295>.
+pub struct NoisyLinear {
+    weight_mu: Tensor,
+    weight_sigma: Tensor,
+    bias_mu: Tensor,
+    bias_sigma: Tensor,
+    eps_in: Tensor,
+    eps_out: Tensor,
+    device: Device,
+}
+
+impl NoisyLinear {
+    /// Constructs a noisy linear layer.
+    ///
+    /// * `sigma0` - Initial scale of the noise, which is 0.5 in the paper.
+    pub fn new(in_dim: usize, out_dim: usize, sigma0: f64, vb: VarBuilder) -> Result<Self> {
+        let bound = 1. / (in_dim as f64).sqrt();
+        let init_mu = Init::Uniform {
+            lo: -bound,
+            up: bound,
+        };
+        let init_sigma = Init::Const(sigma0 * bound);
+        let device = vb.device().clone();
+        let mut layer = Self {
+            weight_mu: vb.get_with_hints((out_dim, in_dim), "weight_mu", init_mu)?,
+            weight_sigma: vb.get_with_hints((out_dim, in_dim), "weight_sigma", init_sigma)?,
+            bias_mu: vb.get_with_hints(out_dim, "bias_mu", init_mu)?,
+            bias_sigma: vb.get_with_hints(out_dim, "bias_sigma", init_sigma)?,
+            eps_in: Tensor::zeros(in_dim, DType::F32, &device)?,
+            eps_out: Tensor::zeros(out_dim, DType::F32, &device)?,
+            device,
+        };
+        layer.reset_noise()?;
+        Ok(layer)
+    }
+
+    /// Samples noise `sign(x) * sqrt(|x|)` with `x ~ N(0, 1)`.
+    fn noise(n: usize, device: &Device) -> Result<Tensor> {
+        let x = Tensor::randn(0f32, 1f32, n, device)?;
+        Ok((x.sign()? * x.abs()?.sqrt()?)?)
+    }
+
+    /// Resamples the noise.
+    pub fn reset_noise(&mut self) -> Result<()> {
+        self.eps_in = Self::noise(self.eps_in.dims1()?, &self.device)?;
+        self.eps_out = Self::noise(self.eps_out.dims1()?, &self.device)?;
+        Ok(())
+    }
+}
+
+impl Module for NoisyLinear {
+    fn forward(&self, xs: &Tensor) -> candle_core::Result<Tensor> {
+        let eps_w = self
+            .eps_out
+            .unsqueeze(1)?
+            .broadcast_mul(&self.eps_in.unsqueeze(0)?)?;
+        let weight = (&self.weight_mu + (&self.weight_sigma * eps_w)?)?;
+        let bias = (&self.bias_mu + (&self.bias_sigma * &self.eps_out)?)?;
+        Linear::new(weight, Some(bias)).forward(xs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_nn::VarMap;
+
+    #[test]
+    fn test_reset_noise() -> Result<()> {
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+        let mut layer = NoisyLinear::new(3, 2, 0.5, vb)?;
+        assert_eq!(varmap.all_vars().len(), 4);
+
+        let xs = Tensor::ones((1, 3), DType::F32, &Device::Cpu)?;
+        let y1 = layer.forward(&xs)?;
+        assert_eq!(y1.dims(), [1, 2]);
+        assert_eq!(layer.forward(&xs)?.to_vec2::<f32>()?, y1.to_vec2::<f32>()?);
+        layer.reset_noise()?;
+        assert_ne!(layer.forward(&xs)?.to_vec2::<f32>()?, y1.to_vec2::<f32>()?);
+        Ok(())
+    }
+}
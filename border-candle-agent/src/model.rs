@@ -1,6 +1,6 @@
 //! Interface of neural networks used in RL agents.
-// use anyhow::Result;
-// use candle_core::Tensor;
+use anyhow::{Context, Result};
+use candle_core::Tensor;
 use candle_nn::VarBuilder;
 // use std::path::Path;
 // use tch::{nn, nn::VarStore, Tensor};
@@ -25,6 +25,38 @@ pub trait SubModel1 {
 
     /// A generalized forward function.
     fn forward(&self, input: &Self::Input) -> Self::Output;
+
+    /// Resamples the noise of noisy layers, e.g., [`NoisyLinear`].
+    ///
+    /// It does nothing by default.
+    ///
+    /// [`NoisyLinear`]: crate::mlp::NoisyLinear
+    fn reset_noise(&mut self) {}
+
+    /// Returns the input as a tensor.
+    ///
+    /// Features processing inputs as tensors, e.g., augmentation of observations, require it.
+    /// It returns `None` by default. Models taking a tensor override it together with
+    /// [`SubModel1::input_from_tensor()`].
+    fn input_as_tensor(_input: &Self::Input) -> Option<&Tensor> {
+        None
+    }
+
+    /// Creates the input from a tensor, see [`SubModel1::input_as_tensor()`].
+    fn input_from_tensor(_xs: Tensor) -> Option<Self::Input> {
+        None
+    }
+}
+
+/// Applies `f` to the input of a model as a tensor.
+///
+/// Returns an error if the input is not a tensor, see [`SubModel1::input_as_tensor()`].
+pub(crate) fn map_input<M: SubModel1>(
+    input: &M::Input,
+    f: impl FnOnce(&Tensor) -> Result<Tensor>,
+) -> Result<M::Input> {
+    let xs = M::input_as_tensor(input).context("Input of the model is not a tensor")?;
+    M::input_from_tensor(f(xs)?).context("Input of the model is not a tensor")
 }
 
 /// Neural network model not owing its [`VarMap`] internally.
@@ -0,0 +1,406 @@
+//! Export of trained policies to [ONNX](https://onnx.ai/).
+//!
+//! Policies of [`Dqn`], [`Sac`] and [`Bc`] can be exported with their `export_onnx()` methods,
+//! so that they can be served with ONNX runtimes outside of Rust or Python training stacks.
+//! Only the part of the agent needed to take actions is exported. The exported graph takes
+//! a batch of observations as input `obs`, and outputs actions as `action`, which are the same as
+//! those taken by the agent in evaluation mode. Preprocessing of observations done in models,
+//! e.g., scaling of pixels in [`AtariCnn`], is included in the graph.
+//!
+//! Models used in the agents should implement [`OnnxModel`], which is implemented for
+//! [`Mlp`], [`Mlp2`] and [`AtariCnn`] in this crate.
+//!
+//! The graph is written in ONNX IR version 8 with operator set 13 of the default domain.
+//!
+//! [`Dqn`]: crate::dqn::Dqn
+//! [`Sac`]: crate::sac::Sac
+//! [`Bc`]: crate::bc::Bc
+//! [`AtariCnn`]: crate::atari_cnn::AtariCnn
+//! [`Mlp`]: crate::mlp::Mlp
+//! [`Mlp2`]: crate::mlp::Mlp2
+mod proto;
+use crate::Activation;
+use anyhow::Result;
+use candle_core::{DType, Tensor};
+use candle_nn::{Conv2d, Linear};
+use prost::Message;
+use proto::{AttributeType, DimensionValue};
+use std::path::Path;
+
+/// IR version of ONNX in exported graphs.
+const IR_VERSION: i64 = 8;
+
+/// Version of the operator set in exported graphs.
+const OPSET_VERSION: i64 = 13;
+
+/// Name of the batch dimension of inputs and outputs.
+const BATCH_DIM: &str = "batch";
+
+/// Element types of tensors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ElemType {
+    /// 32-bit floating point.
+    Float = 1,
+
+    /// 8-bit unsigned integer.
+    Uint8 = 2,
+
+    /// 64-bit signed integer.
+    Int64 = 7,
+}
+
+/// Attributes of nodes.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Attribute {
+    /// Float.
+    Float(f32),
+
+    /// Integer.
+    Int(i64),
+
+    /// List of integers.
+    Ints(Vec<i64>),
+}
+
+impl Attribute {
+    /// Creates `AttributeProto`.
+    fn to_proto(&self, name: &str) -> proto::AttributeProto {
+        let mut attr = proto::AttributeProto {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        match self {
+            Self::Float(v) => {
+                attr.f = Some(*v);
+                attr.set_type(AttributeType::Float);
+            }
+            Self::Int(v) => {
+                attr.i = Some(*v);
+                attr.set_type(AttributeType::Int);
+            }
+            Self::Ints(vs) => {
+                attr.ints = vs.clone();
+                attr.set_type(AttributeType::Ints);
+            }
+        }
+        attr
+    }
+}
+
+/// Creates `ValueInfoProto`, where the first dimension is the batch dimension.
+fn value_info(name: &str, elem_type: ElemType, shape: &[i64]) -> proto::ValueInfoProto {
+    let dim = |value| proto::Dimension { value: Some(value) };
+    let dims = std::iter::once(dim(DimensionValue::DimParam(BATCH_DIM.to_string())))
+        .chain(shape.iter().map(|&d| dim(DimensionValue::DimValue(d))))
+        .collect();
+    proto::ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(proto::TypeProto {
+            tensor_type: Some(proto::TensorTypeProto {
+                elem_type: elem_type as i32,
+                shape: Some(proto::TensorShapeProto { dim: dims }),
+            }),
+        }),
+    }
+}
+
+/// Computation graph in ONNX.
+///
+/// Nodes are added in the order of computation, each of which has a single output
+/// named automatically. Inputs and outputs of the graph have the batch dimension
+/// `batch` of dynamic size in addition to the given shapes.
+pub struct OnnxGraph {
+    graph: proto::GraphProto,
+}
+
+impl OnnxGraph {
+    /// Creates an empty graph.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            graph: proto::GraphProto {
+                name: name.into(),
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Adds an input of the graph and returns its name.
+    ///
+    /// * `shape` - Shape of the input without the batch dimension.
+    pub fn add_input(&mut self, name: &str, elem_type: ElemType, shape: &[i64]) -> String {
+        self.graph.input.push(value_info(name, elem_type, shape));
+        name.to_string()
+    }
+
+    /// Adds an output of the graph computed from `value`, the output of a node.
+    ///
+    /// * `shape` - Shape of the output without the batch dimension.
+    pub fn add_output(&mut self, name: &str, elem_type: ElemType, shape: &[i64], value: &str) {
+        self.push_node("Identity", &[value], name, vec![]);
+        self.graph.output.push(value_info(name, elem_type, shape));
+    }
+
+    /// Adds a constant tensor of `f32` and returns its name.
+    pub fn add_tensor(&mut self, name: &str, tensor: &Tensor) -> Result<String> {
+        let tensor = tensor.to_dtype(DType::F32)?;
+        let raw_data = tensor
+            .flatten_all()?
+            .to_vec1::<f32>()?
+            .iter()
+            .flat_map(|v| v.to_le_bytes())
+            .collect();
+        let dims = tensor.dims().iter().map(|&d| d as i64).collect();
+        Ok(self.push_initializer(name, ElemType::Float, dims, raw_data))
+    }
+
+    /// Adds a constant scalar of `f32` and returns its name.
+    pub fn add_scalar(&mut self, name: &str, v: f32) -> String {
+        self.push_initializer(name, ElemType::Float, vec![], v.to_le_bytes().to_vec())
+    }
+
+    /// Adds a constant vector of `i64` and returns its name.
+    pub fn add_ints(&mut self, name: &str, vs: &[i64]) -> String {
+        let raw_data = vs.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.push_initializer(name, ElemType::Int64, vec![vs.len() as i64], raw_data)
+    }
+
+    fn push_initializer(
+        &mut self,
+        name: &str,
+        elem_type: ElemType,
+        dims: Vec<i64>,
+        raw_data: Vec<u8>,
+    ) -> String {
+        self.graph.initializer.push(proto::TensorProto {
+            dims,
+            data_type: elem_type as i32,
+            name: name.to_string(),
+            raw_data,
+        });
+        name.to_string()
+    }
+
+    fn push_node(
+        &mut self,
+        op_type: &str,
+        inputs: &[&str],
+        output: &str,
+        attributes: Vec<(&str, Attribute)>,
+    ) {
+        self.graph.node.push(proto::NodeProto {
+            input: inputs.iter().map(|s| s.to_string()).collect(),
+            output: vec![output.to_string()],
+            name: output.to_string(),
+            op_type: op_type.to_string(),
+            attribute: attributes
+                .iter()
+                .map(|(name, attr)| attr.to_proto(name))
+                .collect(),
+        });
+    }
+
+    /// Adds a node and returns the name of its output.
+    pub fn add_node(
+        &mut self,
+        op_type: &str,
+        inputs: &[&str],
+        attributes: Vec<(&str, Attribute)>,
+    ) -> String {
+        let output = format!("{}_{}", op_type, self.graph.node.len());
+        self.push_node(op_type, inputs, &output, attributes);
+        output
+    }
+
+    /// Adds a linear layer and returns the name of its output.
+    ///
+    /// * `prefix` - Prefix of the names of the weight and the bias.
+    pub fn add_linear(&mut self, x: &str, linear: &Linear, prefix: &str) -> Result<String> {
+        let w = self.add_tensor(&format!("{}.weight", prefix), linear.weight())?;
+        let mut inputs = vec![x.to_string(), w];
+        if let Some(b) = linear.bias() {
+            inputs.push(self.add_tensor(&format!("{}.bias", prefix), b)?);
+        }
+        let inputs = inputs.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        Ok(self.add_node("Gemm", &inputs, vec![("transB", Attribute::Int(1))]))
+    }
+
+    /// Adds a 2D convolution layer and returns the name of its output.
+    ///
+    /// * `prefix` - Prefix of the names of the weight and the bias.
+    pub fn add_conv2d(&mut self, x: &str, conv: &Conv2d, prefix: &str) -> Result<String> {
+        let config = conv.config();
+        let w = self.add_tensor(&format!("{}.weight", prefix), conv.weight())?;
+        let mut inputs = vec![x.to_string(), w];
+        if let Some(b) = conv.bias() {
+            inputs.push(self.add_tensor(&format!("{}.bias", prefix), b)?);
+        }
+        let inputs = inputs.iter().map(|s| s.as_str()).collect::<Vec<_>>();
+        let (p, s, d) = (
+            config.padding as i64,
+            config.stride as i64,
+            config.dilation as i64,
+        );
+        Ok(self.add_node(
+            "Conv",
+            &inputs,
+            vec![
+                ("pads", Attribute::Ints(vec![p, p, p, p])),
+                ("strides", Attribute::Ints(vec![s, s])),
+                ("dilations", Attribute::Ints(vec![d, d])),
+                ("group", Attribute::Int(config.groups as i64)),
+            ],
+        ))
+    }
+
+    /// Adds an activation function and returns the name of its output.
+    pub fn add_activation(&mut self, x: &str, activation: &Activation) -> String {
+        match activation {
+            Activation::None => x.to_string(),
+            Activation::ReLU => self.add_node("Relu", &[x], vec![]),
+            Activation::Tanh => self.add_node("Tanh", &[x], vec![]),
+            Activation::Sigmoid => self.add_node("Sigmoid", &[x], vec![]),
+        }
+    }
+
+    /// Adds the index of the maximum along the last axis, e.g., a discrete action
+    /// from action values, and returns the name of its output.
+    pub fn add_argmax(&mut self, x: &str) -> String {
+        self.add_node(
+            "ArgMax",
+            &[x],
+            vec![
+                ("axis", Attribute::Int(-1)),
+                ("keepdims", Attribute::Int(0)),
+            ],
+        )
+    }
+
+    /// Returns the serialized `ModelProto`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        proto::ModelProto {
+            ir_version: IR_VERSION,
+            producer_name: "border".to_string(),
+            producer_version: env!("CARGO_PKG_VERSION").to_string(),
+            graph: Some(self.graph.clone()),
+            opset_import: vec![proto::OperatorSetIdProto {
+                domain: String::new(),
+                version: OPSET_VERSION,
+            }],
+        }
+        .encode_to_vec()
+    }
+
+    /// Saves the graph as an ONNX model file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        std::fs::write(path.as_ref(), self.to_bytes())?;
+        log::info!("Save ONNX model to {:?}", path.as_ref());
+        Ok(())
+    }
+}
+
+/// Models that can be exported to ONNX.
+pub trait OnnxModel {
+    /// Returns the element type and the shape, without the batch dimension, of the input.
+    fn onnx_input(&self) -> (ElemType, Vec<i64>);
+
+    /// Adds the computation of the model to the graph.
+    ///
+    /// Returns the names of the outputs, which correspond to the outputs of the forward
+    /// computation of the model.
+    fn add_to_graph(&self, graph: &mut OnnxGraph, input: &str) -> Result<Vec<String>>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mlp::{Mlp, MlpConfig},
+        model::SubModel1,
+    };
+    use candle_core::Device;
+    use candle_nn::{VarBuilder, VarMap};
+
+    /// Evaluates a graph of `Gemm` and activation functions for a batch of inputs.
+    fn eval_graph(graph: &proto::GraphProto, x: (Vec<usize>, Vec<f32>)) -> Result<Vec<f32>> {
+        let to_f32 = |raw: &[u8]| {
+            raw.chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect::<Vec<_>>()
+        };
+        let mut values = std::collections::HashMap::new();
+        for t in graph.initializer.iter() {
+            let dims = t.dims.iter().map(|&d| d as usize).collect::<Vec<_>>();
+            values.insert(t.name.clone(), (dims, to_f32(&t.raw_data)));
+        }
+        values.insert(graph.input[0].name.clone(), x);
+
+        for node in graph.node.iter() {
+            let (dims, xs) = values[&node.input[0]].clone();
+            let y = match node.op_type.as_str() {
+                "Gemm" => {
+                    let (w_dims, w) = &values[&node.input[1]];
+                    let (_, b) = &values[&node.input[2]];
+                    let (n, k, m) = (dims[0], dims[1], w_dims[0]);
+                    let mut y = vec![0f32; n * m];
+                    for i in 0..n {
+                        for j in 0..m {
+                            let dot = (0..k).map(|l| xs[i * k + l] * w[j * k + l]).sum::<f32>();
+                            y[i * m + j] = dot + b[j];
+                        }
+                    }
+                    (vec![n, m], y)
+                }
+                "Relu" => (dims, xs.iter().map(|v| v.max(0.0)).collect()),
+                "Tanh" => (dims, xs.iter().map(|v| v.tanh()).collect()),
+                "Identity" => (dims, xs),
+                op_type => anyhow::bail!("Unexpected op_type {}", op_type),
+            };
+            values.insert(node.output[0].clone(), y);
+        }
+        Ok(values[&graph.output[0].name].1.clone())
+    }
+
+    #[test]
+    fn test_export_mlp() -> Result<()> {
+        let build = |config: MlpConfig| {
+            let varmap = VarMap::new();
+            let vb = VarBuilder::from_varmap(&varmap, DType::F32, &Device::Cpu);
+            Mlp::build(vb, config)
+        };
+        let mlp = build(MlpConfig::new(3, vec![8, 8], 2, Activation::Tanh));
+        let mut graph = OnnxGraph::new("test");
+        let (elem_type, shape) = mlp.onnx_input();
+        assert_eq!((elem_type, shape.clone()), (ElemType::Float, vec![3]));
+        let obs = graph.add_input("obs", elem_type, &shape);
+        let ys = mlp.add_to_graph(&mut graph, &obs)?;
+        graph.add_output("action", ElemType::Float, &[2], &ys[0]);
+
+        // Gemm and Relu for hidden layers, Gemm and Tanh for the output layer, and Identity
+        assert_eq!(graph.graph.node.len(), 7);
+        assert_eq!(graph.graph.initializer.len(), 6);
+        assert_eq!(graph.graph.node[5].op_type, "Tanh");
+
+        // ir_version (field 1) comes first
+        let bytes = graph.to_bytes();
+        assert_eq!(bytes[..2], [0x08, 0x08]);
+
+        // The decoded graph computes the same output as the model
+        let model = proto::ModelProto::decode(bytes.as_slice())?;
+        assert_eq!(model.opset_import[0].version, OPSET_VERSION);
+        let xs = (0..12).map(|i| i as f32 * 0.25 - 1.5).collect::<Vec<_>>();
+        let expected = mlp
+            .forward(&Tensor::from_vec(xs.clone(), (4, 3), &Device::Cpu)?)
+            .flatten_all()?
+            .to_vec1::<f32>()?;
+        let actual = eval_graph(model.graph.as_ref().unwrap(), (vec![4, 3], xs))?;
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert!((a - e).abs() < 1e-5, "{} != {}", a, e);
+        }
+
+        // Noisy layers are not supported
+        let mlp = build(MlpConfig::new(3, vec![8], 2, Activation::None).noisy(true));
+        assert!(mlp.add_to_graph(&mut graph, &obs).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+//! Messages of `onnx.proto` used in exported graphs.
+//!
+//! Only the fields written by [`OnnxGraph`](super::OnnxGraph) are defined. Field numbers follow
+//! [onnx.proto](https://github.com/onnx/onnx/blob/main/onnx/onnx.proto).
+
+/// `ModelProto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ModelProto {
+    #[prost(int64, tag = "1")]
+    pub ir_version: i64,
+
+    #[prost(string, tag = "2")]
+    pub producer_name: String,
+
+    #[prost(string, tag = "3")]
+    pub producer_version: String,
+
+    #[prost(message, optional, tag = "7")]
+    pub graph: Option<GraphProto>,
+
+    #[prost(message, repeated, tag = "8")]
+    pub opset_import: Vec<OperatorSetIdProto>,
+}
+
+/// `OperatorSetIdProto`, where the empty domain is the default one.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct OperatorSetIdProto {
+    #[prost(string, tag = "1")]
+    pub domain: String,
+
+    #[prost(int64, tag = "2")]
+    pub version: i64,
+}
+
+/// `GraphProto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct GraphProto {
+    #[prost(message, repeated, tag = "1")]
+    pub node: Vec<NodeProto>,
+
+    #[prost(string, tag = "2")]
+    pub name: String,
+
+    #[prost(message, repeated, tag = "5")]
+    pub initializer: Vec<TensorProto>,
+
+    #[prost(message, repeated, tag = "11")]
+    pub input: Vec<ValueInfoProto>,
+
+    #[prost(message, repeated, tag = "12")]
+    pub output: Vec<ValueInfoProto>,
+}
+
+/// `NodeProto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct NodeProto {
+    #[prost(string, repeated, tag = "1")]
+    pub input: Vec<String>,
+
+    #[prost(string, repeated, tag = "2")]
+    pub output: Vec<String>,
+
+    #[prost(string, tag = "3")]
+    pub name: String,
+
+    #[prost(string, tag = "4")]
+    pub op_type: String,
+
+    #[prost(message, repeated, tag = "5")]
+    pub attribute: Vec<AttributeProto>,
+}
+
+/// `AttributeProto`.
+///
+/// `f` and `i` are optional as in proto2, so that zeros are written explicitly.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct AttributeProto {
+    #[prost(string, tag = "1")]
+    pub name: String,
+
+    #[prost(float, optional, tag = "2")]
+    pub f: Option<f32>,
+
+    #[prost(int64, optional, tag = "3")]
+    pub i: Option<i64>,
+
+    #[prost(int64, repeated, packed = "false", tag = "8")]
+    pub ints: Vec<i64>,
+
+    #[prost(enumeration = "AttributeType", tag = "20")]
+    pub r#type: i32,
+}
+
+/// `AttributeProto.AttributeType`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, prost::Enumeration)]
+#[repr(i32)]
+pub enum AttributeType {
+    Undefined = 0,
+    Float = 1,
+    Int = 2,
+    Ints = 7,
+}
+
+/// `TensorProto`, whose values are stored in `raw_data` in little endian.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TensorProto {
+    #[prost(int64, repeated, packed = "false", tag = "1")]
+    pub dims: Vec<i64>,
+
+    #[prost(int32, tag = "2")]
+    pub data_type: i32,
+
+    #[prost(string, tag = "8")]
+    pub name: String,
+
+    #[prost(bytes = "vec", tag = "9")]
+    pub raw_data: Vec<u8>,
+}
+
+/// `ValueInfoProto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ValueInfoProto {
+    #[prost(string, tag = "1")]
+    pub name: String,
+
+    #[prost(message, optional, tag = "2")]
+    pub r#type: Option<TypeProto>,
+}
+
+/// `TypeProto`, where only tensor types are defined.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TypeProto {
+    #[prost(message, optional, tag = "1")]
+    pub tensor_type: Option<TensorTypeProto>,
+}
+
+/// `TypeProto.Tensor`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TensorTypeProto {
+    #[prost(int32, tag = "1")]
+    pub elem_type: i32,
+
+    #[prost(message, optional, tag = "2")]
+    pub shape: Option<TensorShapeProto>,
+}
+
+/// `TensorShapeProto`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct TensorShapeProto {
+    #[prost(message, repeated, tag = "1")]
+    pub dim: Vec<Dimension>,
+}
+
+/// `TensorShapeProto.Dimension`.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Dimension {
+    #[prost(oneof = "DimensionValue", tags = "1, 2")]
+    pub value: Option<DimensionValue>,
+}
+
+/// Value of [`Dimension`].
+#[derive(Clone, PartialEq, prost::Oneof)]
+pub enum DimensionValue {
+    /// Fixed size.
+    #[prost(int64, tag = "1")]
+    DimValue(i64),
+
+    /// Name of a dynamic size.
+    #[prost(string, tag = "2")]
+    DimParam(String),
+}
@@ -1,6 +1,8 @@
 //! Optimizers.
 use anyhow::Result;
-use candle_core::{Tensor, Var};
+use border_core::LrScheduler;
+pub use border_core::{LrDecay, SchedulerConfig};
+use candle_core::{backprop::GradStore, Tensor, Var};
 use candle_nn::{AdamW, Optimizer as _, ParamsAdamW};
 use candle_optimisers::adam::{Adam, ParamsAdam};
 use serde::{Deserialize, Serialize};
@@ -26,6 +28,31 @@ pub enum OptimizerConfig {
         /// Learning rate.
         lr: f64,
     },
+
+    /// An optimizer with the learning rate scheduled over optimization steps.
+    ///
+    /// The learning rate of `optimizer` is used as the base learning rate of the schedule.
+    Scheduled {
+        optimizer: Box<OptimizerConfig>,
+        scheduler: SchedulerConfig,
+    },
+
+    /// An optimizer with gradients clipped before each update.
+    Clipped {
+        optimizer: Box<OptimizerConfig>,
+        clip: GradClip,
+    },
+}
+
+/// Clipping of gradients in [`OptimizerConfig::Clipped`].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub enum GradClip {
+    /// Scales gradients so that their global L2 norm over all parameters does not exceed
+    /// `max_norm`.
+    GlobalNorm { max_norm: f64 },
+
+    /// Clamps each element of gradients to `[-max_value, max_value]`.
+    Value { max_value: f64 },
 }
 
 fn default_beta1() -> f64 {
@@ -48,6 +75,26 @@ impl OptimizerConfig {
     /// Constructs [`AdamW`] optimizer.
     pub fn build(&self, vars: Vec<Var>) -> Result<Optimizer> {
         match &self {
+            OptimizerConfig::Scheduled {
+                optimizer,
+                scheduler,
+            } => {
+                let mut opt = optimizer.build(vars)?;
+                let scheduler = LrScheduler::new(scheduler.clone(), optimizer.lr());
+                opt.set_learning_rate(scheduler.lr());
+                Ok(Optimizer::Scheduled(Box::new(opt), scheduler))
+            }
+            OptimizerConfig::Clipped { optimizer, clip } => {
+                let opt = optimizer.build(vars.clone())?;
+                Ok(Optimizer::Clipped(
+                    Box::new(opt),
+                    Clipper {
+                        clip: clip.clone(),
+                        vars,
+                        grad_norm: None,
+                    },
+                ))
+            }
             OptimizerConfig::AdamW {
                 lr,
                 beta1,
@@ -93,6 +140,52 @@ impl OptimizerConfig {
                 weight_decay,
             },
             Self::Adam { lr: _ } => Self::Adam { lr },
+            Self::Scheduled {
+                optimizer,
+                scheduler,
+            } => Self::Scheduled {
+                optimizer: Box::new(optimizer.learning_rate(lr)),
+                scheduler,
+            },
+            Self::Clipped { optimizer, clip } => Self::Clipped {
+                optimizer: Box::new(optimizer.learning_rate(lr)),
+                clip,
+            },
+        }
+    }
+
+    /// Clips gradients before each update.
+    pub fn grad_clip(self, clip: GradClip) -> Self {
+        match self {
+            Self::Clipped { optimizer, .. } => Self::Clipped { optimizer, clip },
+            opt => Self::Clipped {
+                optimizer: Box::new(opt),
+                clip,
+            },
+        }
+    }
+
+    /// Schedules the learning rate, which is used as the base learning rate.
+    pub fn scheduler(self, scheduler: SchedulerConfig) -> Self {
+        match self {
+            Self::Scheduled { optimizer, .. } => Self::Scheduled {
+                optimizer,
+                scheduler,
+            },
+            opt => Self::Scheduled {
+                optimizer: Box::new(opt),
+                scheduler,
+            },
+        }
+    }
+
+    /// Returns the (base) learning rate.
+    pub fn lr(&self) -> f64 {
+        match self {
+            Self::AdamW { lr, .. } => *lr,
+            Self::Adam { lr } => *lr,
+            Self::Scheduled { optimizer, .. } => optimizer.lr(),
+            Self::Clipped { optimizer, .. } => optimizer.lr(),
         }
     }
 }
@@ -120,21 +213,141 @@ pub enum Optimizer {
     AdamW(AdamW),
 
     Adam(Adam),
+
+    /// Optimizer with the learning rate scheduled.
+    Scheduled(Box<Optimizer>, LrScheduler),
+
+    /// Optimizer with gradients clipped.
+    Clipped(Box<Optimizer>, Clipper),
+}
+
+/// State of the clipping of gradients.
+pub struct Clipper {
+    clip: GradClip,
+    vars: Vec<Var>,
+    grad_norm: Option<f32>,
+}
+
+impl Clipper {
+    /// Clips gradients in place, keeping their global norm before clipping.
+    fn clip(&mut self, grads: &mut GradStore) -> Result<()> {
+        let mut sum_sq = 0f32;
+        for var in self.vars.iter() {
+            if let Some(g) = grads.get(var) {
+                sum_sq += g.sqr()?.sum_all()?.to_scalar::<f32>()?;
+            }
+        }
+        let norm = sum_sq.sqrt();
+        self.grad_norm = Some(norm);
+
+        for var in self.vars.iter() {
+            if let Some(g) = grads.get(var) {
+                let g = match self.clip {
+                    GradClip::GlobalNorm { max_norm } if norm as f64 > max_norm => {
+                        (g * (max_norm / (norm as f64 + 1e-6)))?
+                    }
+                    GradClip::GlobalNorm { .. } => continue,
+                    GradClip::Value { max_value } => g.clamp(-max_value, max_value)?,
+                };
+                grads.insert(var, g);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Optimizer {
     /// Applies a backward step pass.
     pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
-        match self {
-            Self::AdamW(opt) => Ok(opt.backward_step(loss)?),
-            Self::Adam(opt) => Ok(opt.backward_step(loss)?),
-        }
+        let mut grads = loss.backward()?;
+        self.step(&mut grads)
     }
 
-    pub fn step(&mut self, grads: &candle_core::backprop::GradStore) -> Result<()> {
+    /// Updates parameters with the given gradients, which are clipped in place if configured.
+    pub fn step(&mut self, grads: &mut GradStore) -> Result<()> {
         match self {
             Self::AdamW(opt) => Ok(opt.step(grads)?),
             Self::Adam(opt) => Ok(opt.step(grads)?),
+            Self::Scheduled(opt, scheduler) => {
+                opt.step(grads)?;
+                opt.set_learning_rate(scheduler.step());
+                Ok(())
+            }
+            Self::Clipped(opt, clipper) => {
+                clipper.clip(grads)?;
+                opt.step(grads)
+            }
+        }
+    }
+
+    /// Sets the learning rate.
+    ///
+    /// For a scheduled optimizer, it sets the base learning rate of the schedule.
+    pub fn set_learning_rate(&mut self, lr: f64) {
+        match self {
+            Self::AdamW(opt) => opt.set_learning_rate(lr),
+            Self::Adam(opt) => opt.set_learning_rate(lr),
+            Self::Scheduled(opt, scheduler) => opt.set_learning_rate(scheduler.set_base_lr(lr)),
+            Self::Clipped(opt, _) => opt.set_learning_rate(lr),
+        }
+    }
+
+    /// Returns the current learning rate.
+    pub fn learning_rate(&self) -> f64 {
+        match self {
+            Self::AdamW(opt) => opt.learning_rate(),
+            Self::Adam(opt) => opt.learning_rate(),
+            Self::Scheduled(opt, _) => opt.learning_rate(),
+            Self::Clipped(opt, _) => opt.learning_rate(),
+        }
+    }
+
+    /// Returns the global norm of gradients before clipping in the last update,
+    /// if gradients are clipped.
+    pub fn grad_norm(&self) -> Option<f32> {
+        match self {
+            Self::AdamW(_) | Self::Adam(_) => None,
+            Self::Scheduled(opt, _) => opt.grad_norm(),
+            Self::Clipped(_, clipper) => clipper.grad_norm,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grad_clip() -> Result<()> {
+        let device = candle_core::Device::Cpu;
+        let var = Var::new(&[0f32, 0.], &device)?;
+        let target = Tensor::new(&[3f32, 4.], &device)?;
+
+        // Gradients of the loss are [-3, -4] with the global norm 5
+        let loss = || -> Result<Tensor> { Ok(var.as_tensor().mul(&target)?.sum_all()?.neg()?) };
+        let config =
+            OptimizerConfig::Adam { lr: 0.1 }.grad_clip(GradClip::GlobalNorm { max_norm: 1.0 });
+        let mut opt = config.build(vec![var.clone()])?;
+        opt.backward_step(&loss()?)?;
+        assert_eq!(opt.grad_norm(), Some(5.0));
+
+        let mut clipper = Clipper {
+            clip: GradClip::Value { max_value: 3.5 },
+            vars: vec![var.clone()],
+            grad_norm: None,
+        };
+        let mut grads = loss()?.backward()?;
+        clipper.clip(&mut grads)?;
+        assert_eq!(grads.get(&var).unwrap().to_vec1::<f32>()?, vec![-3.0, -3.5]);
+
+        let mut clipper = Clipper {
+            clip: GradClip::GlobalNorm { max_norm: 1.0 },
+            ..clipper
+        };
+        let mut grads = loss()?.backward()?;
+        clipper.clip(&mut grads)?;
+        let g = grads.get(&var).unwrap().to_vec1::<f32>()?;
+        assert!((g[0] + 0.6).abs() < 1e-5 && (g[1] + 0.8).abs() < 1e-5);
+        Ok(())
+    }
+}
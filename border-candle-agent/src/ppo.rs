@@ -0,0 +1,20 @@
+//! Proximal policy optimization (PPO) agent.
+//!
+//! [`Ppo`] is an on-policy agent for discrete action spaces. It collects a rollout of transitions
+//! in [`RolloutBuffer`], estimates advantages with generalized advantage estimation (GAE), and
+//! updates a categorical policy with the clipped surrogate objective over several epochs of
+//! minibatches. The rollout buffer is emptied at each optimization step.
+//!
+//! The length of a rollout is given by the optimization interval of the trainer, i.e.,
+//! `opt_interval` of [`TrainerConfig`] should be set to the length of the rollout and
+//! `capacity` of [`RolloutBufferConfig`] should be equal to or larger than it.
+//!
+//! [`TrainerConfig`]: border_core::TrainerConfig
+mod actor;
+mod base;
+mod config;
+mod rollout_buffer;
+pub use actor::{CategoricalActor, CategoricalActorConfig};
+pub use base::{gae, Ppo};
+pub use config::PpoConfig;
+pub use rollout_buffer::{RolloutBuffer, RolloutBufferConfig};
@@ -0,0 +1,194 @@
+//! Categorical policy for discrete actions.
+use crate::{
+    model::SubModel1,
+    opt::{Optimizer, OptimizerConfig},
+    util::{find_params, params_path, OutDim},
+};
+use anyhow::{Context, Result};
+use candle_core::{DType, Device, Tensor, D};
+use candle_nn::{
+    ops::{log_softmax, softmax},
+    VarBuilder, VarMap,
+};
+use log::info;
+use rand::{distributions::WeightedIndex, Rng};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+/// Configuration of [`CategoricalActor`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct CategoricalActorConfig<P> {
+    /// Configuration of the policy network, which outputs logits of actions.
+    pub policy_config: Option<P>,
+
+    /// Configuration of optimizer.
+    pub opt_config: OptimizerConfig,
+}
+
+impl<P> Default for CategoricalActorConfig<P> {
+    fn default() -> Self {
+        Self {
+            policy_config: None,
+            opt_config: OptimizerConfig::Adam { lr: 0.0003 },
+        }
+    }
+}
+
+impl<P> CategoricalActorConfig<P>
+where
+    P: DeserializeOwned + Serialize + OutDim,
+{
+    /// Sets configurations for the policy network.
+    pub fn policy_config(mut self, v: P) -> Self {
+        self.policy_config = Some(v);
+        self
+    }
+
+    /// Sets the number of actions, i.e., the output dimension of the policy network.
+    pub fn out_dim(mut self, v: i64) -> Self {
+        match &mut self.policy_config {
+            None => {}
+            Some(policy_config) => policy_config.set_out_dim(v),
+        };
+        self
+    }
+
+    /// Sets optimizer configuration.
+    pub fn opt_config(mut self, v: OptimizerConfig) -> Self {
+        self.opt_config = v;
+        self
+    }
+
+    /// Loads [`CategoricalActorConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves [`CategoricalActorConfig`] as YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Categorical policy for discrete actions.
+pub struct CategoricalActor<P>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    device: Device,
+    varmap: VarMap,
+
+    // Policy network
+    policy_config: P::Config,
+    policy: P,
+
+    // Optimizer
+    opt: Optimizer,
+}
+
+impl<P> CategoricalActor<P>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    /// Constructs [`CategoricalActor`].
+    pub fn build(config: CategoricalActorConfig<P::Config>, device: Device) -> Result<Self> {
+        let policy_config = config.policy_config.context("policy_config is not set.")?;
+        let varmap = VarMap::new();
+        let policy = {
+            let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device).set_prefix("actor");
+            P::build(vb, policy_config.clone())
+        };
+        let opt = config.opt_config.build(varmap.all_vars())?;
+
+        Ok(Self {
+            device,
+            varmap,
+            policy_config,
+            policy,
+            opt,
+        })
+    }
+
+    /// Returns logits of actions for given observations.
+    pub fn forward(&self, obs: &P::Input) -> Tensor {
+        self.policy.forward(obs)
+    }
+
+    /// Returns the configuration of the policy network.
+    pub fn policy_config(&self) -> &P::Config {
+        &self.policy_config
+    }
+
+    /// Returns log probabilities of given actions and entropies of the policy.
+    ///
+    /// `act` is a tensor of action indices whose first dimension is the batch.
+    /// Both of the returned tensors have shape `[batch_size]`.
+    pub fn logp_entropy(&self, obs: &P::Input, act: &Tensor) -> Result<(Tensor, Tensor)> {
+        let logits = self.forward(obs);
+        let batch_size = logits.dims()[0];
+        let log_probs = log_softmax(&logits, D::Minus1)?;
+        let act = act
+            .to_device(&self.device)?
+            .to_dtype(DType::I64)?
+            .reshape((batch_size, 1))?;
+        let logp = log_probs.gather(&act, D::Minus1)?.squeeze(D::Minus1)?;
+        let entropy = (softmax(&logits, D::Minus1)? * log_probs)?
+            .sum(D::Minus1)?
+            .neg()?;
+
+        Ok((logp, entropy))
+    }
+
+    /// Samples actions for given observations, returns i64 tensor.
+    ///
+    /// In evaluation mode (`train = false`), the most probable actions are taken.
+    pub fn sample(&self, obs: &P::Input, train: bool, rng: &mut impl Rng) -> Result<Tensor> {
+        let logits = self.forward(obs);
+        match train {
+            true => {
+                let probs = softmax(&logits, D::Minus1)?.to_vec2::<f32>()?;
+                let n_samples = probs.len();
+                let data = probs
+                    .into_iter()
+                    .map(|p| Ok(rng.sample(WeightedIndex::new(&p)?) as i64))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Tensor::from_vec(data, &[n_samples], &self.device)?)
+            }
+            false => Ok(logits.argmax(D::Minus1)?.to_dtype(DType::I64)?),
+        }
+    }
+
+    /// Backward step for all variables in the policy network.
+    pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
+        self.opt.backward_step(loss)
+    }
+
+    /// Save variables to prefix + ".safetensors".
+    pub fn save(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
+        let path = params_path(prefix);
+        self.varmap.save(path.as_path())?;
+        info!("Save actor parameters to {:?}", path);
+
+        Ok(path)
+    }
+
+    /// Load variables from prefix + ".safetensors", or prefix + ".pt" saved by older versions.
+    pub fn load(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
+        let path = find_params(prefix);
+        self.varmap.load(path.as_path())?;
+        info!("Load actor parameters from {:?}", path);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+use super::{CategoricalActor, PpoConfig};
+use crate::{
+    error::opt_result,
+    iql::Value,
+    model::SubModel1,
+    util::{save_manifest, OutDim},
+};
+use anyhow::Result;
+use border_core::{
+    generic_replay_buffer::BatchBase,
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+};
+use candle_core::{Device, Tensor};
+use candle_nn::loss::mse;
+use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    fs,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+/// Computes advantages and returns with generalized advantage estimation (GAE).
+///
+/// Transitions are assumed to be in the order they were collected. `values` and `next_values`
+/// are state values of observations and next observations. Values of next observations are
+/// bootstrapped unless the episode terminated, so truncated episodes and the end of the rollout
+/// are handled without special care.
+///
+/// Returns advantages and returns, i.e., advantages plus state values.
+pub fn gae(
+    reward: &[f32],
+    is_terminated: &[i8],
+    is_truncated: &[i8],
+    values: &[f32],
+    next_values: &[f32],
+    gamma: f32,
+    lambda: f32,
+) -> (Vec<f32>, Vec<f32>) {
+    let n = reward.len();
+    let mut adv = vec![0f32; n];
+    let mut last = 0f32;
+    for t in (0..n).rev() {
+        let not_terminated = (1 - is_terminated[t]) as f32;
+        let not_done = match is_terminated[t] == 1 || is_truncated[t] == 1 {
+            true => 0f32,
+            false => 1f32,
+        };
+        let delta = reward[t] + gamma * not_terminated * next_values[t] - values[t];
+        last = delta + gamma * lambda * not_done * last;
+        adv[t] = last;
+    }
+    let ret = adv.iter().zip(values.iter()).map(|(a, v)| a + v).collect();
+
+    (adv, ret)
+}
+
+/// Proximal policy optimization (PPO) agent for discrete actions.
+pub struct Ppo<E, P, V, R>
+where
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+{
+    actor: CategoricalActor<P>,
+    critic: Value<V>,
+    gamma: f64,
+    gae_lambda: f64,
+    clip_eps: f64,
+    n_epochs: usize,
+    batch_size: usize,
+    ent_coef: f64,
+    normalize_advantage: bool,
+    train: bool,
+    n_opts: usize,
+    rng: SmallRng,
+    device: Device,
+    phantom: PhantomData<(E, R)>,
+}
+
+impl<E, P, V, R> Ppo<E, P, V, R>
+where
+    E: Env,
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    R: ReplayBufferBase,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: BatchBase + Into<P::Input> + Into<V::Input> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: BatchBase + Into<Tensor> + Clone,
+{
+    fn to_tensor(&self, v: &[f32], ixs: &[usize]) -> Result<Tensor> {
+        let v = ixs.iter().map(|&ix| v[ix]).collect::<Vec<_>>();
+        Ok(Tensor::from_vec(v, ixs.len(), &self.device)?)
+    }
+
+    fn opt_(&mut self, buffer: &mut R) -> Result<Record> {
+        // All of the transitions in the rollout
+        let batch = buffer.batch(usize::MAX)?;
+        let (obs, act, next_obs, reward, is_terminated, is_truncated, _, _) = batch.unpack();
+        let n = reward.len();
+
+        // Advantages and returns
+        let values = self
+            .critic
+            .forward(&obs.clone().into())
+            .flatten_all()?
+            .to_vec1::<f32>()?;
+        let next_values = self
+            .critic
+            .forward(&next_obs.into())
+            .flatten_all()?
+            .to_vec1::<f32>()?;
+        let (mut adv, ret) = gae(
+            &reward,
+            &is_terminated,
+            &is_truncated,
+            &values,
+            &next_values,
+            self.gamma as f32,
+            self.gae_lambda as f32,
+        );
+        if self.normalize_advantage && n > 1 {
+            let mean = adv.iter().sum::<f32>() / n as f32;
+            let std = (adv.iter().map(|a| (a - mean).powi(2)).sum::<f32>() / n as f32).sqrt();
+            adv.iter_mut().for_each(|a| *a = (*a - mean) / (std + 1e-8));
+        }
+
+        // Log probabilities of actions under the policy which collected the rollout
+        let (old_logp, _) = self
+            .actor
+            .logp_entropy(&obs.clone().into(), &act.clone().into())?;
+        let old_logp = old_logp.to_vec1::<f32>()?;
+
+        let mut loss_actor = 0f32;
+        let mut loss_critic = 0f32;
+        let mut entropy = 0f32;
+        let mut approx_kl = 0f32;
+        let mut clip_fraction = 0f32;
+        let mut n_updates = 0;
+        let mut ixs = (0..n).collect::<Vec<_>>();
+
+        for _ in 0..self.n_epochs {
+            ixs.shuffle(&mut self.rng);
+            for ixs in ixs.chunks(self.batch_size) {
+                let ixs = ixs.to_vec();
+                let obs = obs.sample(&ixs);
+                let act = act.sample(&ixs);
+                let adv = self.to_tensor(&adv, &ixs)?;
+                let ret = self.to_tensor(&ret, &ixs)?;
+                let old_logp = self.to_tensor(&old_logp, &ixs)?;
+
+                // Clipped surrogate objective
+                let (logp, ent) = self.actor.logp_entropy(&obs.clone().into(), &act.into())?;
+                let log_ratio = (&logp - &old_logp)?;
+                let ratio = log_ratio.exp()?;
+                let surr1 = (&ratio * &adv)?;
+                let surr2 = (ratio.clamp(1.0 - self.clip_eps, 1.0 + self.clip_eps)? * &adv)?;
+                let ent = ent.mean_all()?;
+                let loss = (surr1.minimum(&surr2)?.mean_all()?.neg()? - (&ent * self.ent_coef)?)?;
+                self.actor.backward_step(&loss)?;
+                loss_actor += loss.to_scalar::<f32>()?;
+                entropy += ent.to_scalar::<f32>()?;
+
+                // Diagnostics
+                approx_kl += ((ratio.clone() - 1.0)? - &log_ratio)?
+                    .mean_all()?
+                    .to_scalar::<f32>()?;
+                clip_fraction += ((ratio - 1.0)?.abs()?.gt(self.clip_eps)?)
+                    .to_dtype(candle_core::DType::F32)?
+                    .mean_all()?
+                    .to_scalar::<f32>()?;
+
+                // Value function
+                let pred = self.critic.forward(&obs.into()).flatten_all()?;
+                let loss = mse(&pred, &ret)?;
+                self.critic.backward_step(&loss)?;
+                loss_critic += loss.to_scalar::<f32>()?;
+
+                n_updates += 1;
+            }
+        }
+        self.n_opts += 1;
+
+        let n_updates = n_updates.max(1) as f32;
+        Ok(Record::from_slice(&[
+            ("loss_actor", RecordValue::Scalar(loss_actor / n_updates)),
+            ("loss_critic", RecordValue::Scalar(loss_critic / n_updates)),
+            ("entropy", RecordValue::Scalar(entropy / n_updates)),
+            ("approx_kl", RecordValue::Scalar(approx_kl / n_updates)),
+            (
+                "clip_fraction",
+                RecordValue::Scalar(clip_fraction / n_updates),
+            ),
+        ]))
+    }
+}
+
+impl<E, P, V, R> Policy<E> for Ppo<E, P, V, R>
+where
+    E: Env,
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    E::Obs: Into<P::Input>,
+    E::Act: From<Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+{
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        self.actor
+            .sample(&obs.clone().into(), self.train, &mut self.rng)
+            .unwrap()
+            .into()
+    }
+}
+
+impl<E, P, V, R> Configurable for Ppo<E, P, V, R>
+where
+    E: Env,
+    P: SubModel1<Output = Tensor>,
+    V: SubModel1<Output = Tensor>,
+    E::Obs: Into<P::Input>,
+    E::Act: From<Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+{
+    type Config = PpoConfig<P, V>;
+
+    /// Constructs [`Ppo`] agent.
+    fn build(config: Self::Config) -> Self {
+        let device: Device = config
+            .device
+            .expect("No device is given for PPO agent")
+            .into();
+        let actor = CategoricalActor::build(config.actor_config, device.clone()).unwrap();
+        let critic = Value::build(config.critic_config, device.clone()).unwrap();
+
+        Ppo {
+            actor,
+            critic,
+            gamma: config.gamma,
+            gae_lambda: config.gae_lambda,
+            clip_eps: config.clip_eps,
+            n_epochs: config.n_epochs,
+            batch_size: config.batch_size,
+            ent_coef: config.ent_coef,
+            normalize_advantage: config.normalize_advantage,
+            train: false,
+            n_opts: 0,
+            rng: SmallRng::seed_from_u64(config.seed),
+            device,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, P, V, R> Agent<E, R> for Ppo<E, P, V, R>
+where
+    E: Env + 'static,
+    P: SubModel1<Output = Tensor> + 'static,
+    V: SubModel1<Output = Tensor> + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<P::Input>,
+    E::Act: From<Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    V::Config: DeserializeOwned + Serialize + std::fmt::Debug + PartialEq + Clone,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: BatchBase + Into<P::Input> + Into<V::Input> + Clone,
+    <R::Batch as TransitionBatch>::ActBatch: BatchBase + Into<Tensor> + Clone,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.try_opt_with_record(buffer)
+            .expect("Failed in Ppo::opt_()")
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
+        Ok(opt_result(self.opt_(buffer))?)
+    }
+
+    /// Returns the action probabilities for the observation as `action_probs`.
+    fn probe(&mut self, obs: &E::Obs) -> Record {
+        let logits = self.actor.forward(&obs.clone().into()).detach();
+        let probs = candle_nn::ops::softmax_last_dim(&logits)
+            .and_then(|p| p.flatten_all()?.to_vec1::<f32>())
+            .unwrap();
+        Record::from_slice(&[("action_probs", RecordValue::Array1(probs))])
+    }
+
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+        let actor_path = self.actor.save(path.join("actor"))?;
+        let critic_path = self.critic.save(path.join("critic"))?;
+
+        let mut paths = vec![actor_path, critic_path];
+        save_manifest(path, "Ppo", self.actor.policy_config(), &mut paths)?;
+        Ok(paths)
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        self.actor.load(path.join("actor"))?;
+        self.critic.load(path.join("critic"))?;
+
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::gae;
+
+    #[test]
+    fn test_gae() {
+        // Episode terminated at t = 1, truncated at t = 3
+        let reward = [1.0, 1.0, 1.0, 1.0];
+        let is_terminated = [0, 1, 0, 0];
+        let is_truncated = [0, 0, 0, 1];
+        let values = [0.5, 0.5, 0.5, 0.5];
+        let next_values = [0.5, 0.5, 0.5, 2.0];
+        let (adv, ret) = gae(
+            &reward,
+            &is_terminated,
+            &is_truncated,
+            &values,
+            &next_values,
+            0.9,
+            0.5,
+        );
+
+        // t = 1: no bootstrap
+        assert!((adv[1] - 0.5).abs() < 1e-6);
+        // t = 0: delta = 1 + 0.45 - 0.5, plus 0.45 * adv[1]
+        assert!((adv[0] - (0.95 + 0.45 * 0.5)).abs() < 1e-6);
+        // t = 3: bootstrap from the next value of the truncated episode
+        assert!((adv[3] - (1.0 + 1.8 - 0.5)).abs() < 1e-6);
+        // t = 2: accumulates adv[3]
+        assert!((adv[2] - (0.95 + 0.45 * 2.3)).abs() < 1e-6);
+        assert!((ret[2] - (adv[2] + 0.5)).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,197 @@
+//! Configuration of PPO agent.
+use super::CategoricalActorConfig;
+use crate::{iql::ValueConfig, model::SubModel1, util::OutDim, Device};
+use anyhow::Result;
+use candle_core::Tensor;
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Configuration of [`Ppo`](super::Ppo).
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct PpoConfig<P, V>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V: SubModel1<Output = Tensor>,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    /// Configuration of the actor model.
+    pub actor_config: CategoricalActorConfig<P::Config>,
+
+    /// Configuration of the state value function.
+    pub critic_config: ValueConfig<V::Config>,
+
+    /// Discont factor.
+    pub gamma: f64,
+
+    /// Parameter of generalized advantage estimation.
+    pub gae_lambda: f64,
+
+    /// Clipping range of the probability ratio in the surrogate objective.
+    pub clip_eps: f64,
+
+    /// Number of epochs over a rollout per optimization step.
+    pub n_epochs: usize,
+
+    /// Size of minibatches.
+    pub batch_size: usize,
+
+    /// Coefficient of the entropy bonus.
+    pub ent_coef: f64,
+
+    /// If `true`, advantages are normalized over the rollout.
+    pub normalize_advantage: bool,
+
+    /// Random seed for sampling actions and minibatches.
+    pub seed: u64,
+
+    /// Device for actor/critic models.
+    pub device: Option<Device>,
+}
+
+impl<P, V> Clone for PpoConfig<P, V>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V: SubModel1<Output = Tensor>,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            actor_config: self.actor_config.clone(),
+            critic_config: self.critic_config.clone(),
+            gamma: self.gamma,
+            gae_lambda: self.gae_lambda,
+            clip_eps: self.clip_eps,
+            n_epochs: self.n_epochs,
+            batch_size: self.batch_size,
+            ent_coef: self.ent_coef,
+            normalize_advantage: self.normalize_advantage,
+            seed: self.seed,
+            device: self.device,
+        }
+    }
+}
+
+impl<P, V> Default for PpoConfig<P, V>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V: SubModel1<Output = Tensor>,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    fn default() -> Self {
+        Self {
+            actor_config: Default::default(),
+            critic_config: Default::default(),
+            gamma: 0.99,
+            gae_lambda: 0.95,
+            clip_eps: 0.2,
+            n_epochs: 10,
+            batch_size: 64,
+            ent_coef: 0.0,
+            normalize_advantage: true,
+            seed: 42,
+            device: None,
+        }
+    }
+}
+
+impl<P, V> PpoConfig<P, V>
+where
+    P: SubModel1<Output = Tensor>,
+    P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    V: SubModel1<Output = Tensor>,
+    V::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
+{
+    /// Configuration of actor.
+    pub fn actor_config(mut self, actor_config: CategoricalActorConfig<P::Config>) -> Self {
+        self.actor_config = actor_config;
+        self
+    }
+
+    /// Configuration of critic.
+    pub fn critic_config(mut self, critic_config: ValueConfig<V::Config>) -> Self {
+        self.critic_config = critic_config;
+        self
+    }
+
+    /// Discount factor.
+    pub fn discount_factor(mut self, v: f64) -> Self {
+        self.gamma = v;
+        self
+    }
+
+    /// Parameter of generalized advantage estimation.
+    pub fn gae_lambda(mut self, v: f64) -> Self {
+        self.gae_lambda = v;
+        self
+    }
+
+    /// Clipping range of the probability ratio.
+    pub fn clip_eps(mut self, v: f64) -> Self {
+        self.clip_eps = v;
+        self
+    }
+
+    /// Number of epochs per optimization step.
+    pub fn n_epochs(mut self, v: usize) -> Self {
+        self.n_epochs = v;
+        self
+    }
+
+    /// Minibatch size.
+    pub fn batch_size(mut self, v: usize) -> Self {
+        self.batch_size = v;
+        self
+    }
+
+    /// Coefficient of the entropy bonus.
+    pub fn ent_coef(mut self, v: f64) -> Self {
+        self.ent_coef = v;
+        self
+    }
+
+    /// Normalization of advantages.
+    pub fn normalize_advantage(mut self, v: bool) -> Self {
+        self.normalize_advantage = v;
+        self
+    }
+
+    /// Random seed.
+    pub fn seed(mut self, v: u64) -> Self {
+        self.seed = v;
+        self
+    }
+
+    /// Device.
+    pub fn device(mut self, device: candle_core::Device) -> Self {
+        self.device = Some(device.into());
+        self
+    }
+
+    /// Constructs [`PpoConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path_ = path.as_ref().to_owned();
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        info!("Load config of PPO agent from {}", path_.to_str().unwrap());
+        Ok(b)
+    }
+
+    /// Saves [`PpoConfig`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path_ = path.as_ref().to_owned();
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        info!("Save config of PPO agent into {}", path_.to_str().unwrap());
+        Ok(())
+    }
+}
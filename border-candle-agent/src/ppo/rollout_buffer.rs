@@ -0,0 +1,130 @@
+//! Rollout buffer for on-policy agents.
+use anyhow::Result;
+use border_core::{
+    generic_replay_buffer::{BatchBase, GenericTransitionBatch},
+    ExperienceBufferBase, ReplayBufferBase, TransitionBatch,
+};
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`RolloutBuffer`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RolloutBufferConfig {
+    /// Maximum number of transitions in a rollout.
+    pub capacity: usize,
+}
+
+impl Default for RolloutBufferConfig {
+    fn default() -> Self {
+        Self { capacity: 2048 }
+    }
+}
+
+impl RolloutBufferConfig {
+    /// Sets the capacity of the buffer.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+/// Buffer of transitions collected by the current policy.
+///
+/// Unlike replay buffers, [`RolloutBuffer::batch()`] returns all of the transitions in the order
+/// they were pushed, ignoring the given batch size, and empties the buffer.
+/// Pushing transitions more than the capacity is an error.
+pub struct RolloutBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    capacity: usize,
+    len: usize,
+    obs: O,
+    act: A,
+    next_obs: O,
+    reward: Vec<f32>,
+    is_terminated: Vec<i8>,
+    is_truncated: Vec<i8>,
+}
+
+impl<O, A> ExperienceBufferBase for RolloutBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    type Item = GenericTransitionBatch<O, A>;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push(&mut self, tr: Self::Item) -> Result<()> {
+        let len = tr.len();
+        if self.len + len > self.capacity {
+            anyhow::bail!(
+                "Rollout buffer overflows (capacity = {}); opt_interval of the trainer should not exceed it",
+                self.capacity
+            );
+        }
+        let (obs, act, next_obs, reward, is_terminated, is_truncated, _, _) = tr.unpack();
+        self.obs.push(self.len, obs);
+        self.act.push(self.len, act);
+        self.next_obs.push(self.len, next_obs);
+        self.reward.extend(reward);
+        self.is_terminated.extend(is_terminated);
+        self.is_truncated.extend(is_truncated);
+        self.len += len;
+
+        Ok(())
+    }
+}
+
+impl<O, A> ReplayBufferBase for RolloutBuffer<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    type Config = RolloutBufferConfig;
+    type Batch = GenericTransitionBatch<O, A>;
+
+    fn build(config: &Self::Config) -> Self {
+        let capacity = config.capacity;
+        Self {
+            capacity,
+            len: 0,
+            obs: O::new(capacity),
+            act: A::new(capacity),
+            next_obs: O::new(capacity),
+            reward: Vec::with_capacity(capacity),
+            is_terminated: Vec::with_capacity(capacity),
+            is_truncated: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns all of the transitions in the buffer and empties it.
+    fn batch(&mut self, _size: usize) -> Result<Self::Batch> {
+        if self.len == 0 {
+            anyhow::bail!("The rollout buffer is empty");
+        }
+        let ixs = (0..self.len).collect::<Vec<_>>();
+        let obs = std::mem::replace(&mut self.obs, O::new(self.capacity));
+        let act = std::mem::replace(&mut self.act, A::new(self.capacity));
+        let next_obs = std::mem::replace(&mut self.next_obs, O::new(self.capacity));
+        self.len = 0;
+
+        Ok(Self::Batch {
+            obs: obs.sample(&ixs),
+            act: act.sample(&ixs),
+            next_obs: next_obs.sample(&ixs),
+            reward: std::mem::take(&mut self.reward),
+            is_terminated: std::mem::take(&mut self.is_terminated),
+            is_truncated: std::mem::take(&mut self.is_truncated),
+            weight: None,
+            ix_sample: None,
+            mask: None,
+            mc_return: None,
+        })
+    }
+
+    fn update_priority(&mut self, _ixs: &Option<Vec<usize>>, _td_err: &Option<Vec<f32>>) {}
+}
@@ -1,15 +1,18 @@
 use super::{EntCoef, SacConfig};
 use crate::{
+    error::opt_result,
+    mlp::Mlp,
     model::{SubModel1, SubModel2},
+    onnx::{ElemType, OnnxGraph, OnnxModel},
     util::{
-        actor::GaussianActor, critic::MultiCritic, gamma_not_done, smooth_l1_loss, CriticLoss,
-        OutDim,
+        actor::GaussianActor, critic::MultiCritic, encoder::Encoder, find_params, gamma_not_done,
+        params_path, save_manifest, smooth_l1_loss, CriticLoss, OutDim,
     },
 };
 use anyhow::Result;
 use border_core::{
     record::{Record, RecordValue},
-    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+    Agent, Configurable, Env, ModelManifest, Policy, ReplayBufferBase, TransitionBatch,
 };
 use candle_core::{Device, Tensor, D};
 use candle_nn::loss::mse;
@@ -23,15 +26,24 @@ use std::{
 type ActionValue = Tensor;
 type ActMean = Tensor;
 type ActStd = Tensor;
+type Feature = Tensor;
 
 /// Soft actor critic (SAC) agent.
-pub struct Sac<E, Q, P, R>
+///
+/// If [`SacConfig::encoder_config`] is given, observations are encoded with [`Encoder`]
+/// into features shared by the actor and the critics, e.g., for pixel observations.
+/// Otherwise, observations are given to the actor and the critics as they are, and
+/// the type parameter `F` is not used.
+pub struct Sac<E, Q, P, R, F = Mlp>
 where
     Q: SubModel2<Output = ActionValue>,
     P: SubModel1<Output = (ActMean, ActStd)>,
     Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
     P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
 {
+    encoder: Option<Encoder<F>>,
     critic: MultiCritic<Q>,
     actor: GaussianActor<P>,
     gamma: f64,
@@ -45,19 +57,65 @@ where
     device: Device,
 }
 
-impl<E, Q, P, R> Sac<E, Q, P, R>
+impl<E, Q, P, R, F> Sac<E, Q, P, R, F>
+where
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = (ActMean, ActStd)>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Returns features of observations given to the critics.
+    fn critic_feature(&self, obs: Tensor) -> Feature {
+        match self.encoder.as_ref() {
+            Some(encoder) => encoder.forward_critic(&obs),
+            None => obs,
+        }
+    }
+
+    /// Returns features of observations given to the actor.
+    fn actor_feature(&self, obs: Tensor) -> Feature {
+        match self.encoder.as_ref() {
+            Some(encoder) => encoder.forward_actor(&obs),
+            None => obs,
+        }
+    }
+
+    /// Returns features of next observations with the target encoder.
+    fn target_feature(&self, obs: Tensor) -> Feature {
+        match self.encoder.as_ref() {
+            Some(encoder) => encoder.forward_tgt(&obs),
+            None => obs,
+        }
+    }
+
+    /// Casts inputs of the critics into half precision in mixed precision training.
+    fn amp_cast(&self, x: &Tensor) -> Result<Tensor> {
+        match self.critic.amp_dtype() {
+            Some(dtype) => Ok(x.to_dtype(dtype)?),
+            None => Ok(x.clone()),
+        }
+    }
+}
+
+impl<E, Q, P, R, F> Sac<E, Q, P, R, F>
 where
     E: Env,
     Q: SubModel2<Output = ActionValue>,
     P: SubModel1<Output = (ActMean, ActStd)>,
     R: ReplayBufferBase,
-    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Obs: Into<Tensor>,
     E::Act: Into<Q::Input2>,
+    Q::Input1: From<Feature>,
     Q::Input2: From<ActMean>,
+    P::Input: From<Feature>,
     Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
     P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
     R::Batch: TransitionBatch,
-    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input1> + Into<P::Input> + Clone,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Tensor> + Clone,
     <R::Batch as TransitionBatch>::ActBatch: Into<Q::Input2> + Into<Tensor>,
 {
     fn update_critic(&mut self, batch: R::Batch) -> Result<f32> {
@@ -68,17 +126,23 @@ where
             let reward = Tensor::from_slice(&reward[..], (batch_size,), &self.device)?;
 
             // Prediction
-            let qs = self.critic.qvals(&obs.into(), &act.into());
+            let obs = self.critic_feature(obs.into());
+            let act: Tensor = act.into();
+            let qs = self
+                .critic
+                .qvals_amp(&self.amp_cast(&obs)?.into(), &self.amp_cast(&act)?.into());
 
             // Target
             let tgt = {
                 let gamma_not_done =
                     gamma_not_done(self.gamma as f32, is_terminated, None, &self.device)?;
+                let next_obs = self.target_feature(next_obs.into());
                 let next_act = self.actor.sample(&next_obs.clone().into(), self.train)?;
                 let next_log_p = self.actor.logp(&next_obs.clone().into(), &next_act)?;
-                let next_q = self
-                    .critic
-                    .qvals_min_tgt(&next_obs.into(), &next_act.into())?;
+                let next_q = self.critic.qvals_min_tgt_amp(
+                    &self.amp_cast(&next_obs)?.into(),
+                    &self.amp_cast(&next_act)?.into(),
+                )?;
                 let next_q = (next_q - self.ent_coef.alpha()?.broadcast_mul(&next_log_p)?)?;
                 (&reward + (&gamma_not_done * next_q)?)?.squeeze(D::Minus1)?
             }
@@ -86,24 +150,34 @@ where
             debug_assert_eq!(tgt.dims(), [self.batch_size]);
 
             // Loss
-            let losses: Vec<_> = match self.critic_loss {
-                CriticLoss::Mse => qs.iter().map(|pred| mse(&pred, &tgt).unwrap()).collect(),
+            let losses = match self.critic_loss {
+                CriticLoss::Mse => qs
+                    .iter()
+                    .map(|pred| mse(pred, &tgt))
+                    .collect::<candle_core::Result<Vec<_>>>()?,
                 CriticLoss::SmoothL1 => qs
                     .iter()
-                    .map(|pred| smooth_l1_loss(&pred, &tgt).unwrap())
-                    .collect(),
+                    .map(|pred| smooth_l1_loss(pred, &tgt))
+                    .collect::<candle_core::Result<Vec<_>>>()?,
             };
             Tensor::stack(&losses, 0)?.mean_all()?
         };
 
-        self.critic.backward_step(&loss)?;
+        // Gradients are shared by the critics and the encoder
+        let vars = self.encoder.as_ref().map_or(vec![], |e| e.vars());
+        if let Some(mut grads) = self.critic.backward(&loss, &vars)? {
+            self.critic.step(&mut grads)?;
+            if let Some(encoder) = self.encoder.as_mut() {
+                encoder.step_critic(&mut grads)?;
+            }
+        }
 
         Ok(loss.to_scalar::<f32>()?)
     }
 
     fn update_actor(&mut self, batch: &R::Batch) -> Result<f32> {
         let loss = {
-            let obs = batch.obs().clone();
+            let obs = self.actor_feature(batch.obs().clone().into());
             let act = self.actor.sample(&obs.clone().into(), self.train)?;
             let log_p = self.actor.logp(&obs.clone().into(), &act)?;
 
@@ -116,7 +190,11 @@ where
             (alpha.broadcast_mul(&log_p)? - &q)?.mean_all()?
         };
 
-        self.actor.backward_step(&loss)?;
+        let mut grads = loss.backward()?;
+        self.actor.step(&mut grads)?;
+        if let Some(encoder) = self.encoder.as_mut() {
+            encoder.step_actor(&mut grads)?;
+        }
 
         Ok(loss.to_scalar::<f32>()?)
     }
@@ -126,58 +204,119 @@ where
         let mut loss_actor = 0f32;
 
         for _ in 0..self.n_updates_per_opt {
-            let batch = buffer.batch(self.batch_size).unwrap();
+            let batch = buffer.batch(self.batch_size)?;
             loss_actor += self.update_actor(&batch)?;
             loss_critic += self.update_critic(batch)?;
             self.critic.soft_update()?;
+            if let Some(encoder) = self.encoder.as_mut() {
+                encoder.soft_update()?;
+            }
             self.n_opts += 1;
         }
 
         loss_critic /= self.n_updates_per_opt as f32;
         loss_actor /= self.n_updates_per_opt as f32;
 
-        let record = Record::from_slice(&[
+        let mut record = Record::from_slice(&[
             ("loss_critic", RecordValue::Scalar(loss_critic)),
             ("loss_actor", RecordValue::Scalar(loss_actor)),
+            (
+                "lr_critic",
+                RecordValue::Scalar(self.critic.learning_rate() as f32),
+            ),
+            (
+                "lr_actor",
+                RecordValue::Scalar(self.actor.learning_rate() as f32),
+            ),
             (
                 "ent_coef",
                 RecordValue::Scalar(self.ent_coef.alpha()?.to_vec1::<f32>()?[0]),
             ),
         ]);
+        if let Some(lr) = self.encoder.as_ref().and_then(|e| e.learning_rate()) {
+            record.insert("lr_encoder", RecordValue::Scalar(lr as f32));
+        }
+        if let Some(grad_norm) = self.critic.grad_norm() {
+            record.insert("grad_norm_critic", RecordValue::Scalar(grad_norm));
+        }
+        if let Some(amp) = self.critic.amp() {
+            record.insert("amp_loss_scale", RecordValue::Scalar(amp.scale() as f32));
+            record.insert(
+                "amp_n_overflows",
+                RecordValue::Scalar(amp.n_overflows() as f32),
+            );
+        }
+        if let Some(grad_norm) = self.actor.grad_norm() {
+            record.insert("grad_norm_actor", RecordValue::Scalar(grad_norm));
+        }
 
         Ok(record)
     }
 }
 
-impl<E, Q, P, R> Policy<E> for Sac<E, Q, P, R>
+impl<E, Q, P, R, F> Sac<E, Q, P, R, F>
+where
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel1<Output = (ActMean, ActStd)> + OnnxModel,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Exports the policy to ONNX, see [`crate::onnx`].
+    ///
+    /// The graph outputs the actions `action`, `[batch, action_dim]`, taken in evaluation mode,
+    /// i.e., the means of the Gaussian distributions limited as configured in the actor.
+    /// The critics are not exported. Agents with an encoder are not supported.
+    pub fn export_onnx(&self, path: impl AsRef<Path>) -> Result<()> {
+        if self.encoder.is_some() {
+            anyhow::bail!("Export of SAC agents with an encoder to ONNX is not supported");
+        }
+        let mut graph = OnnxGraph::new("sac");
+        let (elem_type, shape) = self.actor.onnx_input();
+        let obs = graph.add_input("obs", elem_type, &shape);
+        let act = self.actor.add_to_graph(&mut graph, &obs)?.remove(0);
+        graph.add_output("action", ElemType::Float, &[self.actor.out_dim()], &act);
+        graph.save(path)
+    }
+}
+
+impl<E, Q, P, R, F> Policy<E> for Sac<E, Q, P, R, F>
 where
     E: Env,
     Q: SubModel2<Output = ActionValue>,
     P: SubModel1<Output = (ActMean, ActStd)>,
-    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Obs: Into<Tensor>,
     E::Act: Into<Q::Input2> + From<Tensor>,
+    P::Input: From<Feature>,
     Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
     P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
 {
     fn sample(&mut self, obs: &E::Obs) -> E::Act {
-        self.actor
-            .sample(&obs.clone().into(), self.train)
-            .unwrap()
-            .into()
+        self.try_sample(obs).expect("Failed in Sac::sample()")
+    }
+
+    fn try_sample(&mut self, obs: &E::Obs) -> Result<E::Act> {
+        let obs = self.actor_feature(obs.clone().into()).detach();
+        Ok(self.actor.sample(&obs.into(), self.train)?.into())
     }
 }
 
-impl<E, Q, P, R> Configurable for Sac<E, Q, P, R>
+impl<E, Q, P, R, F> Configurable for Sac<E, Q, P, R, F>
 where
     E: Env,
     Q: SubModel2<Output = ActionValue>,
     P: SubModel1<Output = (ActMean, ActStd)>,
-    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    E::Obs: Into<Tensor>,
     E::Act: Into<Q::Input2> + From<Tensor>,
     Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
     P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
 {
-    type Config = SacConfig<Q, P>;
+    type Config = SacConfig<Q, P, F>;
 
     /// Constructs [`Sac`] agent.
     fn build(config: Self::Config) -> Self {
@@ -187,7 +326,15 @@ where
             .into();
         // let device = config.device.expect("No device is given for SAC agent");
         let actor = GaussianActor::build(config.actor_config, device.clone().into()).unwrap();
-        let critic = MultiCritic::build(config.critic_config, device.clone().into()).unwrap();
+        let encoder = config
+            .encoder_config
+            .map(|config| Encoder::build(config, device.clone()))
+            .transpose()
+            .unwrap();
+        let shared_vars = encoder.as_ref().map_or(vec![], |e| e.shared_vars());
+        let critic =
+            MultiCritic::build_with_vars(config.critic_config, device.clone(), shared_vars)
+                .unwrap();
         let ent_coef = EntCoef::new(config.ent_coef_mode, device.clone().into()).unwrap();
 
         // if let Some(seed) = config.seed.as_ref() {
@@ -195,6 +342,7 @@ where
         // }
 
         Sac {
+            encoder,
             actor,
             critic,
             gamma: config.gamma,
@@ -210,19 +358,24 @@ where
     }
 }
 
-impl<E, Q, P, R> Agent<E, R> for Sac<E, Q, P, R>
+impl<E, Q, P, R, F> Agent<E, R> for Sac<E, Q, P, R, F>
 where
     E: Env + 'static,
     Q: SubModel2<Output = ActionValue> + 'static,
     P: SubModel1<Output = (ActMean, ActStd)> + 'static,
     R: ReplayBufferBase + 'static,
-    E::Obs: Into<Q::Input1> + Into<P::Input>,
+    F: 'static,
+    E::Obs: Into<Tensor>,
     E::Act: Into<Q::Input2> + From<Tensor>,
+    Q::Input1: From<Feature>,
     Q::Input2: From<ActMean>,
+    P::Input: From<Feature>,
     Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
     P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    F: SubModel1<Input = Tensor, Output = Feature>,
+    F::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
     R::Batch: TransitionBatch,
-    <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input1> + Into<P::Input> + Clone,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Tensor> + Clone,
     <R::Batch as TransitionBatch>::ActBatch: Into<Q::Input2> + Into<Tensor>,
 {
     fn train(&mut self) {
@@ -238,7 +391,12 @@ where
     }
 
     fn opt_with_record(&mut self, buffer: &mut R) -> Record {
-        self.opt_(buffer).expect("Failed in Sac::opt_()")
+        self.try_opt_with_record(buffer)
+            .expect("Failed in Sac::opt_()")
+    }
+
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
+        Ok(opt_result(self.opt_(buffer))?)
     }
 
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
@@ -248,23 +406,37 @@ where
         let actor_path = self.actor.save(path.join("actor"))?;
         let (critic_path, critic_tgt_path) = self.critic.save(path.join("critic"))?;
         let ent_coef_path = {
-            let ent_coef_path = path.join("ent_coef.pt").to_path_buf();
+            let ent_coef_path = params_path(path.join("ent_coef"));
             self.ent_coef.save(&ent_coef_path)?;
             ent_coef_path
         };
 
-        Ok(vec![
-            actor_path,
-            critic_path,
-            critic_tgt_path,
-            ent_coef_path,
-        ])
+        let mut paths = vec![actor_path, critic_path, critic_tgt_path, ent_coef_path];
+        match self.encoder.as_ref() {
+            None => save_manifest(path, "Sac", self.actor.policy_config(), &mut paths)?,
+            Some(encoder) => {
+                let (encoder_path, encoder_tgt_path) = encoder.save(path.join("encoder"))?;
+                paths.extend([encoder_path, encoder_tgt_path]);
+
+                // The policy consists of the encoder and the actor
+                let policy_config = (encoder.config(), self.actor.policy_config());
+                ModelManifest::new("candle", "SacWithEncoder")
+                    .obs_shape(encoder.config().get_in_shape())
+                    .act_dim(self.actor.out_dim())
+                    .config(&policy_config)?
+                    .save_in(path, &mut paths)?;
+            }
+        }
+        Ok(paths)
     }
 
     fn load_params(&mut self, path: &Path) -> Result<()> {
+        if let Some(encoder) = self.encoder.as_mut() {
+            encoder.load(path.join("encoder"))?;
+        }
         self.actor.load(path.join("actor").as_path())?;
         self.critic.load(path.join("critic").as_path())?;
-        self.ent_coef.load(path.join("ent_coef.pt").as_path())?;
+        self.ent_coef.load(find_params(path.join("ent_coef")))?;
 
         Ok(())
     }
@@ -277,3 +449,65 @@ where
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        conv_encoder::{ConvEncoder, ConvEncoderConfig},
+        mlp::{Mlp2, MlpConfig},
+        test_env::{replay_buffer, PixelEnv, PixelObs, DIM_ACT, OBS_SHAPE},
+        util::{actor::GaussianActorConfig, critic::MultiCriticConfig, encoder::EncoderConfig},
+        Activation, TensorBatch,
+    };
+    use border_core::generic_replay_buffer::SimpleReplayBuffer;
+    use candle_core::DType;
+
+    type Buffer = SimpleReplayBuffer<TensorBatch, TensorBatch>;
+
+    #[test]
+    fn test_sac_encoder_opt() -> Result<()> {
+        let [c, h, w] = OBS_SHAPE;
+        let dim_feature = 8;
+        let dim_act = DIM_ACT as i64;
+        let config = SacConfig::<Mlp, Mlp2, ConvEncoder>::default()
+            .encoder_config(EncoderConfig::default().encoder_config(
+                ConvEncoderConfig::new([c as _, h as _, w as _], dim_feature).n_filters(4),
+            ))
+            .actor_config(
+                GaussianActorConfig::default()
+                    .out_dim(dim_act)
+                    .policy_config(MlpConfig::new(
+                        dim_feature,
+                        vec![16, 16],
+                        dim_act,
+                        Activation::None,
+                    )),
+            )
+            .critic_config(MultiCriticConfig::default().q_config(MlpConfig::new(
+                dim_feature + dim_act,
+                vec![16],
+                1,
+                Activation::None,
+            )))
+            .batch_size(4)
+            .n_updates_per_opt(2)
+            .device(Device::Cpu);
+        let mut agent = Sac::<PixelEnv, Mlp, Mlp2, Buffer, ConvEncoder>::build(config);
+        let mut buffer = replay_buffer(16)?;
+
+        agent.train();
+        for _ in 0..3 {
+            let record = agent.try_opt_with_record(&mut buffer)?;
+            for key in ["loss_critic", "loss_actor", "lr_encoder"] {
+                assert!(record.get_scalar(key)?.is_finite(), "{} is not finite", key);
+            }
+        }
+        assert_eq!(agent.n_opts, 6);
+
+        let obs = PixelObs(Tensor::zeros((2, c, h, w), DType::U8, &Device::Cpu)?);
+        let act: Tensor = agent.sample(&obs).into();
+        assert_eq!(act.dims(), [2, DIM_ACT]);
+        Ok(())
+    }
+}
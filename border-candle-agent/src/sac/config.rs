@@ -1,8 +1,12 @@
 //! Configuration of SAC agent.
 use crate::{
+    mlp::Mlp,
     model::{SubModel1, SubModel2},
     sac::ent_coef::EntCoefMode,
-    util::{actor::GaussianActorConfig, critic::MultiCriticConfig, CriticLoss, OutDim},
+    util::{
+        actor::GaussianActorConfig, critic::MultiCriticConfig, encoder::EncoderConfig, CriticLoss,
+        OutDim,
+    },
     Device,
 };
 use anyhow::Result;
@@ -19,12 +23,14 @@ use std::{
 /// Configuration of [`Sac`](super::Sac).
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
-pub struct SacConfig<Q, P>
+pub struct SacConfig<Q, P, F = Mlp>
 where
     Q: SubModel2<Output = Tensor>,
     Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
     P: SubModel1<Output = (Tensor, Tensor)>,
     P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
 {
     /// Configuration of the actor model.
     pub actor_config: GaussianActorConfig<P::Config>,
@@ -32,6 +38,12 @@ where
     /// Configuration of the critic model.
     pub critic_config: MultiCriticConfig<Q::Config>,
 
+    /// Configuration of the encoder of observations shared by the actor and the critics.
+    ///
+    /// If `None`, observations are given to the actor and the critics as they are.
+    #[serde(default)]
+    pub encoder_config: Option<EncoderConfig<F::Config>>,
+
     /// Discont factor.
     pub gamma: f64,
 
@@ -51,17 +63,20 @@ where
     pub device: Option<Device>,
 }
 
-impl<Q, P> Clone for SacConfig<Q, P>
+impl<Q, P, F> Clone for SacConfig<Q, P, F>
 where
     Q: SubModel2<Output = Tensor>,
     Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
     P: SubModel1<Output = (Tensor, Tensor)>,
     P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
 {
     fn clone(&self) -> Self {
         Self {
             actor_config: self.actor_config.clone(),
             critic_config: self.critic_config.clone(),
+            encoder_config: self.encoder_config.clone(),
             gamma: self.gamma.clone(),
             ent_coef_mode: self.ent_coef_mode.clone(),
             n_updates_per_opt: self.n_updates_per_opt.clone(),
@@ -72,17 +87,20 @@ where
     }
 }
 
-impl<Q, P> Default for SacConfig<Q, P>
+impl<Q, P, F> Default for SacConfig<Q, P, F>
 where
     Q: SubModel2<Output = Tensor>,
     Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
     P: SubModel1<Output = (Tensor, Tensor)>,
     P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
 {
     fn default() -> Self {
         Self {
             actor_config: Default::default(),
             critic_config: Default::default(),
+            encoder_config: None,
             gamma: 0.99,
             ent_coef_mode: EntCoefMode::Fix(1.0),
             n_updates_per_opt: 1,
@@ -93,12 +111,14 @@ where
     }
 }
 
-impl<Q, P> SacConfig<Q, P>
+impl<Q, P, F> SacConfig<Q, P, F>
 where
     Q: SubModel2<Output = Tensor>,
     Q::Config: DeserializeOwned + Serialize + Debug + PartialEq + Clone,
     P: SubModel1<Output = (Tensor, Tensor)>,
     P::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + OutDim + Debug + PartialEq + Clone,
 {
     /// Sets the numper of parameter update steps per optimization step.
     pub fn n_updates_per_opt(mut self, v: usize) -> Self {
@@ -142,6 +162,12 @@ where
         self
     }
 
+    /// Configuration of encoder shared by actor and critic.
+    pub fn encoder_config(mut self, encoder_config: EncoderConfig<F::Config>) -> Self {
+        self.encoder_config = Some(encoder_config);
+        self
+    }
+
     /// Device.
     pub fn device(mut self, device: candle_core::Device) -> Self {
         self.device = Some(device.into());
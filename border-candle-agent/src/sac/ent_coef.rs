@@ -3,6 +3,7 @@ use std::convert::TryFrom;
 
 use crate::opt::{Optimizer, OptimizerConfig};
 use anyhow::Result;
+use border_core::Schedule;
 use candle_core::{DType, Device, Tensor};
 use candle_nn::{init::Init, VarBuilder, VarMap};
 use log::info;
@@ -16,6 +17,8 @@ pub enum EntCoefMode {
     Fix(f64),
     /// Automatic tuning given `(target_entropy, learning_rate)`.
     Auto(f64, f64),
+    /// Alpha following a schedule over updates.
+    Scheduled(Schedule),
 }
 
 /// The entropy coefficient of SAC.
@@ -24,6 +27,8 @@ pub struct EntCoef {
     log_alpha: Tensor,
     target_entropy: Option<f64>,
     opt: Option<Optimizer>,
+    schedule: Option<Schedule>,
+    n_updates: usize,
 }
 
 impl EntCoef {
@@ -31,6 +36,7 @@ impl EntCoef {
     pub fn new(mode: EntCoefMode, device: Device) -> Result<Self> {
         let varmap = VarMap::new();
         let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+        let mut schedule = None;
         let (log_alpha, target_entropy, opt) = match mode {
             EntCoefMode::Fix(alpha) => {
                 let init = Init::Const(alpha.ln());
@@ -45,6 +51,12 @@ impl EntCoef {
                     .build(varmap.all_vars())?;
                 (log_alpha, Some(target_entropy), Some(opt))
             }
+            EntCoefMode::Scheduled(s) => {
+                let init = Init::Const(s.value(0).ln());
+                let log_alpha = vb.get_with_hints(1, "log_alpha", init)?;
+                schedule = Some(s);
+                (log_alpha, None, None)
+            }
         };
 
         Ok(Self {
@@ -52,6 +64,8 @@ impl EntCoef {
             log_alpha,
             opt,
             target_entropy,
+            schedule,
+            n_updates: 0,
         })
     }
 
@@ -80,6 +94,14 @@ impl EntCoef {
             };
             self.backward_step(&loss);
         }
+        if let Some(schedule) = &self.schedule {
+            self.n_updates += 1;
+            let log_alpha = schedule.value(self.n_updates).ln() as f32;
+            let log_alpha = Tensor::new(&[log_alpha], self.log_alpha.device())?;
+            if let Some(var) = self.varmap.data().lock().unwrap().get("log_alpha") {
+                var.set(&log_alpha)?;
+            }
+        }
         Ok(())
     }
 
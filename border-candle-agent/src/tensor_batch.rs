@@ -1,5 +1,8 @@
-use border_core::generic_replay_buffer::BatchBase;
+use crate::util::TensorData;
+use border_core::generic_replay_buffer::{BatchBase, DumpBatch};
 use candle_core::{error::Result, DType, Device, IndexOp, Tensor};
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::path::{Path, PathBuf};
 
 /// Adds capability of constructing [`Tensor`] with a static method.
 ///
@@ -115,6 +118,52 @@ impl BatchBase for TensorBatch {
         let buf = Some(self.buf.as_ref().unwrap().index_select(&ixs, 0).unwrap());
         Self { buf, capacity }
     }
+
+    fn size_in_bytes(&self) -> Option<usize> {
+        self.buf
+            .as_ref()
+            .map(|t| t.elem_count() * t.dtype().size_in_bytes())
+    }
+}
+
+impl DumpBatch for TensorBatch {
+    /// Writes the `i`-th element as a `.npy` array.
+    fn dump(&self, i: usize, path: &Path) -> anyhow::Result<PathBuf> {
+        let path = path.with_extension("npy");
+        match &self.buf {
+            Some(buf) => buf.i(i)?.write_npy(&path)?,
+            None => anyhow::bail!("The batch is empty"),
+        }
+        Ok(path)
+    }
+}
+
+/// Serializes the shape, the data type and the values of the buffer.
+///
+/// It is used to send samples over the network, e.g., in the networked mode of
+/// `border-async-trainer`.
+impl Serialize for TensorBatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let buf = self
+            .buf
+            .as_ref()
+            .map(TensorData::from_tensor)
+            .transpose()
+            .map_err(S::Error::custom)?;
+        (self.capacity, buf).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TensorBatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let (capacity, buf) =
+            <(usize, Option<(Vec<usize>, TensorData)>)>::deserialize(deserializer)?;
+        let buf = buf
+            .map(|(shape, data)| data.into_tensor(shape))
+            .transpose()
+            .map_err(D::Error::custom)?;
+        Ok(Self { buf, capacity })
+    }
 }
 
 impl From<TensorBatch> for Tensor {
@@ -122,3 +171,30 @@ impl From<TensorBatch> for Tensor {
         b.buf.unwrap()
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_serde_tensor_batch() -> anyhow::Result<()> {
+        let t = Tensor::arange(0u8, 12u8, &Device::Cpu)?.reshape((3, 2, 2))?;
+        let batch = TensorBatch::from_tensor(t.clone());
+        let batch: TensorBatch = serde_yaml::from_str(&serde_yaml::to_string(&batch)?)?;
+        let buf = batch.buf.unwrap();
+        assert_eq!(buf.dtype(), DType::U8);
+        assert_eq!(buf.dims(), &[3, 2, 2]);
+        assert_eq!(
+            buf.flatten_all()?.to_vec1::<u8>()?,
+            t.flatten_all()?.to_vec1::<u8>()?
+        );
+
+        // The number of values does not match the shape
+        let s = "- 1\n- - [3]\n  - F32: [1.0, 2.0, 3.0]\n";
+        assert!(serde_yaml::from_str::<TensorBatch>(s).is_ok());
+        let s = "- 1\n- - [2, 2]\n  - F32: [1.0, 2.0, 3.0]\n";
+        assert!(serde_yaml::from_str::<TensorBatch>(s).is_err());
+
+        Ok(())
+    }
+}
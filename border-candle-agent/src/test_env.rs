@@ -0,0 +1,112 @@
+//! Environment and replay buffer shared by smoke tests of agents with pixel observations.
+use crate::TensorBatch;
+use anyhow::Result;
+use border_core::{
+    generic_replay_buffer::{GenericTransitionBatch, SimpleReplayBuffer, SimpleReplayBufferConfig},
+    record::Record,
+    Act, Env, ExperienceBufferBase, Info, Obs, ReplayBufferBase, Step,
+};
+use candle_core::{DType, Device, Tensor};
+
+/// Shape of observations of [`PixelEnv`].
+pub const OBS_SHAPE: [usize; 3] = [3, 20, 20];
+
+/// Dimension of actions of [`PixelEnv`].
+pub const DIM_ACT: usize = 2;
+
+/// Random images of shape [`OBS_SHAPE`] with a batch dimension.
+#[derive(Clone, Debug)]
+pub struct PixelObs(pub Tensor);
+
+impl PixelObs {
+    fn random(n: usize) -> Result<Self> {
+        let [c, h, w] = OBS_SHAPE;
+        let t = Tensor::rand(0f32, 255f32, (n, c, h, w), &Device::Cpu)?;
+        Ok(Self(t.to_dtype(DType::U8)?))
+    }
+}
+
+impl Obs for PixelObs {
+    fn len(&self) -> usize {
+        self.0.dims()[0]
+    }
+}
+
+impl From<PixelObs> for Tensor {
+    fn from(obs: PixelObs) -> Self {
+        obs.0
+    }
+}
+
+/// Continuous actions of dimension [`DIM_ACT`] with a batch dimension.
+#[derive(Clone, Debug)]
+pub struct PixelAct(pub Tensor);
+
+impl Act for PixelAct {}
+
+impl From<Tensor> for PixelAct {
+    fn from(t: Tensor) -> Self {
+        Self(t)
+    }
+}
+
+impl From<PixelAct> for Tensor {
+    fn from(act: PixelAct) -> Self {
+        act.0
+    }
+}
+
+/// Info of [`PixelEnv`].
+pub struct PixelInfo;
+
+impl Info for PixelInfo {}
+
+/// Returns random images as observations and a constant reward.
+pub struct PixelEnv;
+
+impl Env for PixelEnv {
+    type Config = ();
+    type Obs = PixelObs;
+    type Act = PixelAct;
+    type Info = PixelInfo;
+
+    fn build(_config: &Self::Config, _seed: i64) -> Result<Self> {
+        Ok(Self)
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let obs = PixelObs::random(1).unwrap();
+        let step = Step::new(obs, a.clone(), vec![1.0], vec![0], vec![0], PixelInfo, None);
+        (step, Record::empty())
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        PixelObs::random(1)
+    }
+
+    fn reset_with_index(&mut self, _ix: usize) -> Result<Self::Obs> {
+        PixelObs::random(1)
+    }
+}
+
+/// Returns a replay buffer filled with `n` random transitions of [`PixelEnv`].
+pub fn replay_buffer(n: usize) -> Result<SimpleReplayBuffer<TensorBatch, TensorBatch>> {
+    let config = SimpleReplayBufferConfig::default().capacity(n);
+    let mut buffer = SimpleReplayBuffer::build(&config);
+    for _ in 0..n {
+        let act = Tensor::rand(-1f32, 1f32, (1, DIM_ACT), &Device::Cpu)?;
+        buffer.push(GenericTransitionBatch {
+            obs: TensorBatch::from_tensor(PixelObs::random(1)?.into()),
+            act: TensorBatch::from_tensor(act),
+            next_obs: TensorBatch::from_tensor(PixelObs::random(1)?.into()),
+            reward: vec![1.0],
+            is_terminated: vec![0],
+            is_truncated: vec![0],
+            weight: None,
+            ix_sample: None,
+            mask: None,
+            mc_return: None,
+        })?;
+    }
+    Ok(buffer)
+}
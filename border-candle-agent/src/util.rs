@@ -2,18 +2,33 @@
 use anyhow::Result;
 use candle_core::{DType, Device, Tensor, WithDType, D};
 use candle_nn::VarMap;
-use log::trace;
 use serde::{Deserialize, Serialize};
 mod named_tensors;
 mod quantile_loss;
-use border_core::record::{Record, RecordValue};
+mod tensor_data;
+pub use border_core::params_path;
+use border_core::{
+    record::{Record, RecordValue},
+    ModelManifest,
+};
 pub use named_tensors::NamedTensors;
 use ndarray::ArrayD;
 use num_traits::AsPrimitive;
 pub use quantile_loss::quantile_huber_loss;
-use std::convert::TryFrom;
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+};
+pub(crate) use tensor_data::TensorData;
 pub mod actor;
+pub mod amp;
+pub mod aux_heads;
+pub mod batch_transform;
 pub mod critic;
+pub mod data_parallel;
+pub mod encoder;
+pub mod soft_update;
+pub use soft_update::{soft_update, SoftUpdateConfig, SoftUpdater, TargetUpdateMode};
 
 /// Critic loss type.
 #[allow(clippy::upper_case_acronyms)]
@@ -32,43 +47,20 @@ pub enum CriticLoss {
 ///
 /// dest = tau * src + (1.0 - tau) * dest
 pub fn track(dest: &VarMap, src: &VarMap, tau: f64) -> Result<()> {
-    trace!("dest");
-    let dest = dest.data().lock().unwrap();
-    trace!("src");
-    let src = src.data().lock().unwrap();
-
-    dest.iter().for_each(|(k_dest, v_dest)| {
-        let v_src = src.get(k_dest).unwrap();
-        let t_src = v_src.as_tensor();
-        let t_dest = v_dest.as_tensor();
-        let t_dest = ((tau * t_src).unwrap() + (1.0 - tau) * t_dest).unwrap();
-        v_dest.set(&t_dest).unwrap();
-    });
-
-    Ok(())
+    soft_update(dest, src, |_| tau, ("", ""))
 }
 
+/// Apply soft update on variables, of which names in `dest` have `ss_dest` in place of
+/// `ss_src` in `src`.
+///
+/// See [`soft_update()`].
 pub fn track_with_replace_substring(
     dest: &VarMap,
     src: &VarMap,
     tau: f64,
-    (ss_src, ss_dest): (&str, &str),
+    names: (&str, &str),
 ) -> Result<()> {
-    trace!("dest");
-    let dest = dest.data().lock().unwrap();
-    trace!("src");
-    let src = src.data().lock().unwrap();
-
-    dest.iter().for_each(|(k_dest, v_dest)| {
-        let k_src = k_dest.replace(ss_dest, ss_src);
-        let v_src = src.get(&k_src).unwrap();
-        let t_src = v_src.as_tensor();
-        let t_dest = v_dest.as_tensor();
-        let t_dest = ((tau * t_src).unwrap() + (1.0 - tau) * t_dest).unwrap();
-        v_dest.set(&t_dest).unwrap();
-    });
-
-    Ok(())
+    soft_update(dest, src, |_| tau, names)
 }
 
 // /// Concatenates slices.
@@ -85,6 +77,45 @@ pub trait OutDim {
 
     /// Sets the  output dimension.
     fn set_out_dim(&mut self, v: i64);
+
+    /// Returns the shape of an input, e.g., an observation, if known.
+    fn get_in_shape(&self) -> Option<Vec<i64>> {
+        None
+    }
+}
+
+/// Appends an extension to a path, e.g., `critic` to `critic.tgt`.
+pub(crate) fn append_ext(prefix: impl AsRef<Path>, ext: &str) -> PathBuf {
+    let mut path = prefix.as_ref().as_os_str().to_owned();
+    path.push(".");
+    path.push(ext);
+    PathBuf::from(path)
+}
+
+/// Returns the path of a file of parameters to be loaded.
+///
+/// If prefix + `.safetensors` does not exist, prefix + `.pt`, saved by older versions
+/// of this crate in the same format, is returned if it exists.
+pub fn find_params(prefix: impl AsRef<Path>) -> PathBuf {
+    border_core::find_params(prefix, "pt")
+}
+
+/// Saves [`ModelManifest`] of parameters saved in a directory.
+///
+/// The shape of observations, the dimension of actions and the config hash are taken
+/// from the configuration of the policy network. The path of the manifest is appended
+/// to `paths`.
+pub(crate) fn save_manifest<C: Serialize + OutDim>(
+    dir: &Path,
+    model_type: &str,
+    policy_config: &C,
+    paths: &mut Vec<PathBuf>,
+) -> Result<()> {
+    ModelManifest::new("candle", model_type)
+        .obs_shape(policy_config.get_in_shape())
+        .act_dim(policy_config.get_out_dim())
+        .config(policy_config)?
+        .save_in(dir, paths)
 }
 
 #[test]
@@ -142,13 +173,61 @@ fn test_track() -> Result<()> {
 
 /// See <https://pytorch.org/docs/stable/generated/torch.nn.SmoothL1Loss.html>.
 pub fn smooth_l1_loss(x: &Tensor, y: &Tensor) -> Result<Tensor, candle_core::Error> {
-    let device = x.device();
-    let d = (x - y)?.abs()?;
+    smooth_l1((x - y)?)?.mean_all()
+}
+
+/// Returns element-wise smooth L1 losses of differences.
+fn smooth_l1(d: Tensor) -> Result<Tensor, candle_core::Error> {
+    let device = d.device().clone();
+    let d = d.abs()?;
     let m1 = d.lt(1.0)?.to_dtype(DType::F32)?.to_device(&device)?;
     let m2 = Tensor::try_from(1f32)?
         .to_device(&device)?
         .broadcast_sub(&m1)?;
-    (((0.5 * m1)? * d.powf(2.0))? + m2 * (d - 0.5))?.mean_all()
+    ((0.5 * m1)? * d.powf(2.0))? + m2 * (d - 0.5)
+}
+
+/// Returns the mean of element-wise critic losses weighted by `ws`.
+///
+/// It is used with importance sampling weights in prioritized experience replay, where
+/// each weight scales the loss of a sample, e.g., `(ws * (x - y)^2).mean()` for MSE.
+pub fn weighted_critic_loss(
+    critic_loss: &CriticLoss,
+    x: &Tensor,
+    y: &Tensor,
+    ws: &Tensor,
+) -> Result<Tensor, candle_core::Error> {
+    let d = (x - y)?;
+    let loss = match critic_loss {
+        CriticLoss::Mse => d.sqr()?,
+        CriticLoss::SmoothL1 => smooth_l1(d)?,
+    };
+    (ws * loss)?.mean_all()
+}
+
+#[test]
+fn test_weighted_critic_loss() -> Result<()> {
+    use candle_core::Device;
+
+    let x = Tensor::from_slice(&[1.0f32, 2.0, 3.0], (3,), &Device::Cpu)?;
+    let y = Tensor::from_slice(&[1.5f32, 0.0, 3.0], (3,), &Device::Cpu)?;
+    let ws = Tensor::from_slice(&[0.5f32, 0.25, 1.0], (3,), &Device::Cpu)?;
+
+    // (0.5 * 0.25 + 0.25 * 4.0 + 0.0) / 3
+    let loss = weighted_critic_loss(&CriticLoss::Mse, &x, &y, &ws)?.to_scalar::<f32>()?;
+    assert!((loss - 1.125 / 3.0).abs() < 1e-6);
+
+    // (0.5 * 0.125 + 0.25 * 1.5 + 0.0) / 3
+    let loss = weighted_critic_loss(&CriticLoss::SmoothL1, &x, &y, &ws)?.to_scalar::<f32>()?;
+    assert!((loss - 0.4375 / 3.0).abs() < 1e-6);
+
+    // Unit weights give the unweighted losses
+    let ws = Tensor::ones(3, DType::F32, &Device::Cpu)?;
+    let loss1 = weighted_critic_loss(&CriticLoss::SmoothL1, &x, &y, &ws)?.to_scalar::<f32>()?;
+    let loss2 = smooth_l1_loss(&x, &y)?.to_scalar::<f32>()?;
+    assert!((loss1 - loss2).abs() < 1e-6);
+
+    Ok(())
 }
 
 /// Returns the standard deviation of a tensor.
@@ -231,7 +310,9 @@ where
 /// Returns gamma values multipied by done flag values.
 ///
 /// When `is_truncated` is given, done flag is set to 1 if either of
-/// `is_terminated` and `is_truncated` is true.
+/// `is_terminated` and `is_truncated` is true. Agents computing TD targets give `None`
+/// to bootstrap from the next observation of truncated transitions, e.g., at timeouts,
+/// which do not mean the end of the task.
 pub fn gamma_not_done(
     gamma: f32,
     is_terminated: Vec<i8>,
@@ -277,3 +358,35 @@ pub fn log_jacobian_tanh(a: &Tensor) -> Result<Tensor> {
     let a = a.clamp(-0.999999, 0.999999)?;
     Ok((-1f64 * (1f64 - a.powf(2.0)?)?.log()?)?.sum(D::Minus1)?)
 }
+
+#[test]
+fn test_save_manifest() -> Result<()> {
+    use crate::{mlp::MlpConfig, Activation};
+    use tempdir::TempDir;
+
+    let dir = TempDir::new("manifest")?;
+    let prefix = dir.path().join("qnet");
+    assert_eq!(params_path(&prefix), dir.path().join("qnet.safetensors"));
+    assert_eq!(
+        params_path(append_ext(&prefix, "tgt")),
+        dir.path().join("qnet.tgt.safetensors")
+    );
+
+    // Fall back to parameters saved by older versions
+    std::fs::write(dir.path().join("qnet.pt"), [])?;
+    assert_eq!(find_params(&prefix), dir.path().join("qnet.pt"));
+    std::fs::write(dir.path().join("qnet.safetensors"), [])?;
+    assert_eq!(find_params(&prefix), dir.path().join("qnet.safetensors"));
+
+    let config = MlpConfig::new(4, vec![64], 2, Activation::None);
+    let mut paths = vec![params_path(&prefix)];
+    save_manifest(dir.path(), "Dqn", &config, &mut paths)?;
+    let manifest = ModelManifest::load(&paths[1])?;
+    assert_eq!(manifest.backend, "candle");
+    assert_eq!(manifest.obs_shape, Some(vec![4]));
+    assert_eq!(manifest.act_dim, 2);
+    assert_eq!(manifest.config_hash, ModelManifest::config_hash(&config)?);
+    assert_eq!(manifest.files, vec!["qnet.safetensors".to_string()]);
+
+    Ok(())
+}
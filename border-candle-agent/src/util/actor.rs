@@ -1,11 +1,12 @@
 //! Actor with Gaussian policy.
 use crate::{
     model::SubModel1,
+    onnx::{ElemType, OnnxGraph, OnnxModel},
     opt::{Optimizer, OptimizerConfig},
-    util::{atanh, log_jacobian_tanh, OutDim},
+    util::{atanh, find_params, log_jacobian_tanh, params_path, OutDim},
 };
 use anyhow::{Context, Result};
-use candle_core::{DType, Device, Tensor, D};
+use candle_core::{backprop::GradStore, DType, Device, Tensor, D};
 use candle_nn::{VarBuilder, VarMap};
 use log::info;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -31,6 +32,19 @@ pub enum ActionLimit {
     Clamp { action_min: f32, action_max: f32 },
 }
 
+impl ActionLimit {
+    /// Returns the minimum and maximum of actions.
+    pub fn range(&self) -> (f32, f32) {
+        match self {
+            Self::Tanh { action_scale } => (-action_scale, *action_scale),
+            Self::Clamp {
+                action_min,
+                action_max,
+            } => (*action_min, *action_max),
+        }
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
 /// Configuration of [`GaussianActor`].
 pub struct GaussianActorConfig<P: OutDim> {
@@ -179,6 +193,16 @@ where
         })
     }
 
+    /// Returns the dimension of the action vector.
+    pub fn out_dim(&self) -> i64 {
+        self.out_dim
+    }
+
+    /// Returns the configuration of the policy network.
+    pub fn policy_config(&self) -> &P::Config {
+        &self.policy_config
+    }
+
     /// Returns the parameters of Gaussian distribution given an observation.
     ///
     /// The type of return values is `(Tensor, Tensor)`.
@@ -245,20 +269,36 @@ where
         Ok(())
     }
 
-    /// Save variables to prefix + ".pt".
+    /// Updates variables in the policy network with the given gradients.
+    ///
+    /// It is used when gradients of a loss are shared with other modules, e.g., an encoder.
+    pub fn step(&mut self, grads: &mut GradStore) -> Result<()> {
+        self.opt.step(grads)
+    }
+
+    /// Returns the current learning rate of the optimizer.
+    pub fn learning_rate(&self) -> f64 {
+        self.opt.learning_rate()
+    }
+
+    /// Returns the global norm of gradients before clipping in the last update,
+    /// if gradients are clipped.
+    pub fn grad_norm(&self) -> Option<f32> {
+        self.opt.grad_norm()
+    }
+
+    /// Save variables to prefix + ".safetensors".
     pub fn save(&self, prefix: impl AsRef<Path>) -> Result<PathBuf> {
-        let mut path = PathBuf::from(prefix.as_ref());
-        path.set_extension("pt");
+        let path = params_path(prefix);
         self.varmap.save(&path.as_path())?;
         info!("Save actor parameters to {:?}", path);
 
         Ok(path.to_path_buf())
     }
 
-    /// Load variables from prefix + ".pt".
+    /// Load variables from prefix + ".safetensors", or prefix + ".pt" saved by older versions.
     pub fn load(&mut self, prefix: impl AsRef<Path>) -> Result<()> {
-        let mut path = PathBuf::from(prefix.as_ref());
-        path.set_extension("pt");
+        let path = find_params(prefix);
         self.varmap.load(&path.as_path())?;
         info!("Load actor parameters from {:?}", path);
 
@@ -303,3 +343,35 @@ where
         }
     }
 }
+
+impl<P> OnnxModel for GaussianActor<P>
+where
+    P: SubModel1<Output = (Tensor, Tensor)> + OnnxModel,
+    P::Config: DeserializeOwned + Serialize + OutDim + Clone,
+{
+    fn onnx_input(&self) -> (ElemType, Vec<i64>) {
+        self.policy.onnx_input()
+    }
+
+    /// Outputs the action in evaluation mode, i.e., the mean of the Gaussian distribution
+    /// limited with [`ActionLimit`].
+    fn add_to_graph(&self, graph: &mut OnnxGraph, input: &str) -> Result<Vec<String>> {
+        let mean = self.policy.add_to_graph(graph, input)?.remove(0);
+        let act = match self.action_limit {
+            ActionLimit::Clamp {
+                action_min,
+                action_max,
+            } => {
+                let min = graph.add_scalar("action_min", action_min);
+                let max = graph.add_scalar("action_max", action_max);
+                graph.add_node("Clip", &[&mean, &min, &max], vec![])
+            }
+            ActionLimit::Tanh { action_scale } => {
+                let act = graph.add_node("Tanh", &[&mean], vec![]);
+                let scale = graph.add_scalar("action_scale", action_scale);
+                graph.add_node("Mul", &[&act, &scale], vec![])
+            }
+        };
+        Ok(vec![act])
+    }
+}
@@ -0,0 +1,258 @@
+//! Mixed precision training.
+//!
+//! Parameters are kept in `f32` as master weights, while the forward pass is computed in
+//! half precision with copies of the models built by [`Amp::var_builder()`]. The copies hold
+//! the parameters cast from the master weights, through which gradients are propagated back
+//! to the master weights in `f32`. The copies are rebuilt after the master weights are updated.
+//!
+//! To prevent small gradients from underflowing in half precision, the loss is multiplied by
+//! a scale before backpropagation and the gradients are divided by it afterwards.
+//! [`Amp::backward()`] skips updates with overflowed gradients and adjusts the scale dynamically.
+use anyhow::Result;
+use candle_core::{backprop::GradStore, DType, Device, Shape, Tensor, Var};
+use candle_nn::{var_builder::SimpleBackend, Init, VarBuilder, VarMap};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Floating point type of the forward pass in mixed precision training.
+///
+/// Matrix multiplication in bfloat16 is not supported on CPU.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, Default)]
+pub enum AmpDType {
+    /// bfloat16.
+    #[default]
+    BF16,
+
+    /// float16.
+    F16,
+}
+
+impl From<AmpDType> for DType {
+    fn from(dtype: AmpDType) -> Self {
+        match dtype {
+            AmpDType::BF16 => DType::BF16,
+            AmpDType::F16 => DType::F16,
+        }
+    }
+}
+
+/// Configuration of [`Amp`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct AmpConfig {
+    /// Floating point type of the forward pass.
+    pub dtype: AmpDType,
+
+    /// Initial loss scale.
+    pub init_scale: f64,
+
+    /// Factor multiplied to the loss scale after `growth_interval` steps without overflow.
+    pub growth_factor: f64,
+
+    /// Factor multiplied to the loss scale when gradients overflow.
+    pub backoff_factor: f64,
+
+    /// The number of steps without overflow before the loss scale grows.
+    pub growth_interval: usize,
+}
+
+impl Default for AmpConfig {
+    fn default() -> Self {
+        Self {
+            dtype: AmpDType::BF16,
+            init_scale: 65536.0,
+            growth_factor: 2.0,
+            backoff_factor: 0.5,
+            growth_interval: 2000,
+        }
+    }
+}
+
+impl AmpConfig {
+    /// Sets the floating point type of the forward pass.
+    pub fn dtype(mut self, v: AmpDType) -> Self {
+        self.dtype = v;
+        self
+    }
+
+    /// Sets the initial loss scale.
+    pub fn init_scale(mut self, v: f64) -> Self {
+        self.init_scale = v;
+        self
+    }
+
+    /// Sets the growth factor of the loss scale.
+    pub fn growth_factor(mut self, v: f64) -> Self {
+        self.growth_factor = v;
+        self
+    }
+
+    /// Sets the backoff factor of the loss scale.
+    pub fn backoff_factor(mut self, v: f64) -> Self {
+        self.backoff_factor = v;
+        self
+    }
+
+    /// Sets the number of steps without overflow before the loss scale grows.
+    pub fn growth_interval(mut self, v: usize) -> Self {
+        self.growth_interval = v;
+        self
+    }
+}
+
+/// Gives variables of a [`VarMap`] cast into the requested type.
+struct CastVarMap(VarMap);
+
+impl SimpleBackend for CastVarMap {
+    fn get(
+        &self,
+        s: Shape,
+        name: &str,
+        h: Init,
+        dtype: DType,
+        dev: &Device,
+    ) -> candle_core::Result<Tensor> {
+        if !self.contains_tensor(name) {
+            candle_core::bail!("{name} is not found in the master weights")
+        }
+        self.0.get(s, name, h, DType::F32, dev)?.to_dtype(dtype)
+    }
+
+    fn contains_tensor(&self, name: &str) -> bool {
+        self.0.data().lock().unwrap().contains_key(name)
+    }
+}
+
+/// State of mixed precision training.
+pub struct Amp {
+    dtype: DType,
+    scale: f64,
+    growth_factor: f64,
+    backoff_factor: f64,
+    growth_interval: usize,
+    n_good_steps: usize,
+    n_overflows: usize,
+}
+
+impl Amp {
+    /// Constructs [`Amp`].
+    pub fn new(config: &AmpConfig) -> Self {
+        Self {
+            dtype: config.dtype.into(),
+            scale: config.init_scale,
+            growth_factor: config.growth_factor,
+            backoff_factor: config.backoff_factor,
+            growth_interval: config.growth_interval,
+            n_good_steps: 0,
+            n_overflows: 0,
+        }
+    }
+
+    /// Returns the floating point type of the forward pass.
+    pub fn dtype(&self) -> DType {
+        self.dtype
+    }
+
+    /// Returns the current loss scale.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Returns the number of updates skipped due to overflowed gradients.
+    pub fn n_overflows(&self) -> usize {
+        self.n_overflows
+    }
+
+    /// Returns a [`VarBuilder`] giving the variables in `varmap` cast into half precision.
+    ///
+    /// Models built with it share the master weights in `varmap`, which must have
+    /// all the variables of the models.
+    pub fn var_builder(&self, varmap: &VarMap, device: &Device) -> VarBuilder<'static> {
+        VarBuilder::from_backend(
+            Box::new(CastVarMap(varmap.clone())),
+            self.dtype,
+            device.clone(),
+        )
+    }
+
+    /// Computes gradients of the scaled loss and unscales those of `vars`.
+    ///
+    /// It returns `None` if the gradients overflow, in which case the update should be skipped.
+    pub fn backward(&mut self, loss: &Tensor, vars: &[Var]) -> Result<Option<GradStore>> {
+        let mut grads = (loss.to_dtype(DType::F32)? * self.scale)?.backward()?;
+
+        // Unscale the gradients once for each variable
+        let mut ids = HashSet::new();
+        let mut sums = vec![];
+        for var in vars.iter().filter(|var| ids.insert(var.id())) {
+            if let Some(grad) = grads.remove(var.as_tensor()) {
+                let grad = (grad.to_dtype(DType::F32)? / self.scale)?;
+                sums.push(grad.sum_all()?);
+                grads.insert(var.as_tensor(), grad);
+            }
+        }
+        let is_finite = match sums.is_empty() {
+            true => true,
+            false => Tensor::stack(&sums, 0)?
+                .sum_all()?
+                .to_scalar::<f32>()?
+                .is_finite(),
+        };
+
+        // Adjust the loss scale
+        if !is_finite {
+            self.scale *= self.backoff_factor;
+            self.n_good_steps = 0;
+            self.n_overflows += 1;
+            return Ok(None);
+        }
+        self.n_good_steps += 1;
+        if self.n_good_steps == self.growth_interval {
+            self.scale *= self.growth_factor;
+            self.n_good_steps = 0;
+        }
+
+        Ok(Some(grads))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_nn::Module;
+
+    #[test]
+    fn test_amp() -> Result<()> {
+        let device = Device::Cpu;
+        let varmap = VarMap::new();
+        let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+        let _ = candle_nn::linear(3, 1, vb.pp("fc"))?;
+        let config = AmpConfig::default()
+            .dtype(AmpDType::F16)
+            .init_scale(1024.0)
+            .growth_interval(1);
+        let mut amp = Amp::new(&config);
+
+        // The model in half precision shares the master weights
+        let fc = candle_nn::linear(3, 1, amp.var_builder(&varmap, &device).pp("fc"))?;
+        let xs = Tensor::ones((2, 3), DType::F32, &device)?;
+        let loss = fc.forward(&xs.to_dtype(amp.dtype())?)?.sum_all()?;
+        assert_eq!(loss.dtype(), DType::F16);
+
+        // Gradients are given to the master weights without the loss scale
+        let vars = varmap.all_vars();
+        let grads = amp.backward(&loss, &vars)?.unwrap();
+        let data = varmap.data().lock().unwrap();
+        let grad = grads.get(data.get("fc.weight").unwrap()).unwrap();
+        assert_eq!(grad.dtype(), DType::F32);
+        assert_eq!(grad.to_vec2::<f32>()?, [[2.0, 2.0, 2.0]]);
+        assert_eq!(amp.scale(), 2048.0);
+
+        // Overflow
+        let loss = (loss * f64::INFINITY)?;
+        assert!(amp.backward(&loss, &vars)?.is_none());
+        assert_eq!(amp.scale(), 1024.0);
+        assert_eq!(amp.n_overflows(), 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+//! Auxiliary heads trained jointly with the main output of a network.
+//!
+//! Auxiliary heads are additional outputs of a network appended after its main output,
+//! such as action values. Each head outputs a value for every action, and the value of
+//! the taken action is trained to predict a target taken from transitions, e.g., reward.
+//! The losses of the heads, multiplied by their weights, are added to the loss of the agent.
+use crate::onnx::OnnxGraph;
+use anyhow::Result;
+use border_core::record::{Record, RecordValue};
+use candle_core::{Tensor, D};
+use candle_nn::loss::{binary_cross_entropy_with_logit, mse};
+use serde::{Deserialize, Serialize};
+
+/// Target of an auxiliary head.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub enum AuxTarget {
+    /// Reward of the transition, trained with the mean squared error.
+    Reward,
+
+    /// Termination flag of the transition, trained with the binary cross entropy of logits.
+    Termination,
+}
+
+impl AuxTarget {
+    /// Returns the name used as the key of records.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Reward => "reward",
+            Self::Termination => "termination",
+        }
+    }
+}
+
+/// Configuration of an auxiliary head.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct AuxHeadConfig {
+    /// Target of the head.
+    pub target: AuxTarget,
+
+    /// Weight of the loss of the head.
+    pub weight: f64,
+}
+
+impl AuxHeadConfig {
+    /// Constructs the configuration of an auxiliary head.
+    pub fn new(target: AuxTarget, weight: f64) -> Self {
+        Self { target, weight }
+    }
+}
+
+/// Auxiliary heads appended to the main output of a network.
+///
+/// The output of the network is `[batch_size, main_dim + n_heads * n_actions]`.
+#[derive(Debug, Clone)]
+pub struct AuxHeads {
+    heads: Vec<AuxHeadConfig>,
+    main_dim: usize,
+    n_actions: usize,
+}
+
+impl AuxHeads {
+    /// Constructs auxiliary heads.
+    ///
+    /// * `heads` - Configurations of the heads.
+    /// * `main_dim` - Dimension of the main output of the network.
+    /// * `n_actions` - The number of actions, the output dimension of each head.
+    pub fn new(heads: Vec<AuxHeadConfig>, main_dim: usize, n_actions: usize) -> Self {
+        Self {
+            heads,
+            main_dim,
+            n_actions,
+        }
+    }
+
+    /// Returns `true` if there is no auxiliary head.
+    pub fn is_empty(&self) -> bool {
+        self.heads.is_empty()
+    }
+
+    /// Returns the dimension of the main output of the network.
+    pub fn main_dim(&self) -> usize {
+        self.main_dim
+    }
+
+    /// Returns the output dimension of the network including the auxiliary heads.
+    pub fn out_dim(&self) -> usize {
+        self.main_dim + self.heads.len() * self.n_actions
+    }
+
+    /// Returns the main output of the network.
+    pub fn main(&self, x: &Tensor) -> Result<Tensor> {
+        match self.is_empty() {
+            true => Ok(x.clone()),
+            false => Ok(x.narrow(D::Minus1, 0, self.main_dim)?.contiguous()?),
+        }
+    }
+
+    /// Adds the slice of the main output to an ONNX graph, see [`AuxHeads::main()`].
+    pub fn add_main_to_graph(&self, graph: &mut OnnxGraph, x: &str) -> String {
+        match self.is_empty() {
+            true => x.to_string(),
+            false => {
+                let starts = graph.add_ints("aux_heads.starts", &[0]);
+                let ends = graph.add_ints("aux_heads.ends", &[self.main_dim as i64]);
+                let axes = graph.add_ints("aux_heads.axes", &[-1]);
+                graph.add_node("Slice", &[x, &starts, &ends, &axes], vec![])
+            }
+        }
+    }
+
+    /// Returns the weighted sum of the losses of the auxiliary heads.
+    ///
+    /// * `x` - Output of the network, `[batch_size, out_dim]`.
+    /// * `act` - Indices of the taken actions, `[batch_size, 1]`.
+    /// * `reward` - `[batch_size]`.
+    /// * `is_terminated` - `[batch_size]`, 1 for terminated, 0 otherwise.
+    ///
+    /// The loss of each head is recorded as `aux_loss_<target>`.
+    pub fn loss(
+        &self,
+        x: &Tensor,
+        act: &Tensor,
+        reward: &Tensor,
+        is_terminated: &Tensor,
+    ) -> Result<(Tensor, Record)> {
+        let mut record = Record::empty();
+        let mut loss = Tensor::zeros((), x.dtype(), x.device())?;
+
+        for (i, head) in self.heads.iter().enumerate() {
+            let pred = x
+                .narrow(
+                    D::Minus1,
+                    self.main_dim + i * self.n_actions,
+                    self.n_actions,
+                )?
+                .contiguous()?
+                .gather(act, D::Minus1)?
+                .squeeze(D::Minus1)?;
+            let loss_ = match head.target {
+                AuxTarget::Reward => mse(&pred, reward)?,
+                AuxTarget::Termination => binary_cross_entropy_with_logit(&pred, is_terminated)?,
+            };
+            record.insert(
+                format!("aux_loss_{}", head.target.name()),
+                RecordValue::Scalar(loss_.to_scalar::<f32>()?),
+            );
+            loss = (loss + (loss_ * head.weight)?)?;
+        }
+
+        Ok((loss, record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::Device;
+
+    #[test]
+    fn test_aux_heads() -> Result<()> {
+        let heads = AuxHeads::new(
+            vec![
+                AuxHeadConfig::new(AuxTarget::Reward, 1.0),
+                AuxHeadConfig::new(AuxTarget::Termination, 0.5),
+            ],
+            2,
+            2,
+        );
+        assert_eq!(heads.out_dim(), 6);
+
+        let device = Device::Cpu;
+        let x = Tensor::from_slice(
+            &[1f32, 2., 0.5, 3., 0., 0., 5., 6., 0., 0., 0., 0.],
+            &[2, 6],
+            &device,
+        )?;
+        let act = Tensor::from_slice(&[0i64, 1], &[2, 1], &device)?;
+        let reward = Tensor::from_slice(&[1f32, 0.], &[2], &device)?;
+        let is_terminated = Tensor::from_slice(&[0f32, 0.], &[2], &device)?;
+        assert_eq!(heads.main(&x)?.to_vec2::<f32>()?, [[1., 2.], [5., 6.]]);
+
+        let (loss, record) = heads.loss(&x, &act, &reward, &is_terminated)?;
+        let ln2 = 2f32.ln();
+        assert_eq!(record.get_scalar("aux_loss_reward")?, 0.125);
+        assert!((record.get_scalar("aux_loss_termination")? - ln2).abs() < 1e-6);
+        assert!((loss.to_scalar::<f32>()? - 0.125 - 0.5 * ln2).abs() < 1e-6);
+        Ok(())
+    }
+}
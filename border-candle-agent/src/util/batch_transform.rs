@@ -0,0 +1,168 @@
+//! Data augmentation of observations sampled from replay buffers.
+//!
+//! [`BatchTransform`] applies augmentations to batches of observations on the device of the
+//! agent before they are given to the models, e.g., random shifts of images in
+//! [DrQ](https://arxiv.org/abs/2004.13649). Images are assumed to be
+//! `[batch_size, ..., height, width]`, e.g., stacked frames of Atari games.
+use anyhow::Result;
+use candle_core::{DType, Device, Tensor, D};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Configuration of an augmentation of observations.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub enum BatchTransformConfig {
+    /// Pads images with `pad` pixels replicating the borders, then crops them into
+    /// the original size at random positions, as in DrQ.
+    RandomShift {
+        /// The number of padded pixels.
+        pad: usize,
+    },
+
+    /// Crops images into `height` x `width` at random positions.
+    RandomCrop {
+        /// Height of cropped images.
+        height: usize,
+        /// Width of cropped images.
+        width: usize,
+    },
+
+    /// Adds Gaussian noise, where observations are converted into `f32`.
+    GaussianNoise {
+        /// Standard deviation of the noise.
+        std: f64,
+    },
+}
+
+/// Augmentations applied to batches of observations in order.
+///
+/// Each sample in a batch is transformed independently.
+pub struct BatchTransform {
+    transforms: Vec<BatchTransformConfig>,
+    device: Device,
+    rng: SmallRng,
+}
+
+impl BatchTransform {
+    /// Constructs augmentations applied on the given device.
+    pub fn new(transforms: Vec<BatchTransformConfig>, device: Device, seed: u64) -> Self {
+        Self {
+            transforms,
+            device,
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Returns `true` if no augmentations are applied.
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+
+    /// Applies the augmentations to a batch.
+    ///
+    /// The batch is moved to the device if any augmentations are applied.
+    pub fn apply(&mut self, xs: &Tensor) -> Result<Tensor> {
+        if self.transforms.is_empty() {
+            return Ok(xs.clone());
+        }
+
+        let mut xs = xs.to_device(&self.device)?;
+        for transform in self.transforms.iter() {
+            xs = match transform {
+                BatchTransformConfig::RandomShift { pad } => {
+                    let (h, w) = (xs.dim(D::Minus2)?, xs.dim(D::Minus1)?);
+                    let xs = xs.pad_with_same(D::Minus2, *pad, *pad)?;
+                    let xs = xs.pad_with_same(D::Minus1, *pad, *pad)?;
+                    random_crop(&mut self.rng, &xs, h, w)?
+                }
+                BatchTransformConfig::RandomCrop { height, width } => {
+                    random_crop(&mut self.rng, &xs, *height, *width)?
+                }
+                BatchTransformConfig::GaussianNoise { std } => {
+                    let xs = xs.to_dtype(DType::F32)?;
+                    (&xs + xs.randn_like(0.0, *std)?)?
+                }
+            };
+        }
+        Ok(xs)
+    }
+}
+
+/// Crops each sample into `h` x `w` at a random position.
+///
+/// Rows and then columns are gathered for the whole batch at once.
+fn random_crop(rng: &mut SmallRng, xs: &Tensor, h: usize, w: usize) -> Result<Tensor> {
+    let (h_in, w_in) = (xs.dim(D::Minus2)?, xs.dim(D::Minus1)?);
+    if h > h_in || w > w_in {
+        anyhow::bail!("Cannot crop {}x{} images into {}x{}", h_in, w_in, h, w);
+    }
+    let xs = gather_random_range(rng, &xs.contiguous()?, xs.rank() - 2, h)?;
+    gather_random_range(rng, &xs, xs.rank() - 1, w)
+}
+
+/// Takes `len` consecutive elements along `dim` from a random offset for each sample.
+fn gather_random_range(rng: &mut SmallRng, xs: &Tensor, dim: usize, len: usize) -> Result<Tensor> {
+    let (n, max_offset) = (xs.dim(0)?, xs.dim(dim)? - len);
+    let ixs = (0..n)
+        .flat_map(|_| {
+            let offset = rng.gen_range(0..=max_offset) as u32;
+            offset..offset + len as u32
+        })
+        .collect::<Vec<_>>();
+
+    // Indices of shape [n, 1, ..., len, 1, ...] broadcasted to the shape of the output
+    let mut ixs_shape = vec![1; xs.rank()];
+    ixs_shape[0] = n;
+    ixs_shape[dim] = len;
+    let mut shape = xs.dims().to_vec();
+    shape[dim] = len;
+    let ixs = Tensor::from_vec(ixs, ixs_shape, xs.device())?
+        .broadcast_as(shape)?
+        .contiguous()?;
+    Ok(xs.gather(&ixs, dim)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random_shift() -> Result<()> {
+        let xs = Tensor::arange(0f32, 32., &Device::Cpu)?.reshape((2, 1, 4, 4))?;
+        let mut transform = BatchTransform::new(
+            vec![BatchTransformConfig::RandomShift { pad: 1 }],
+            Device::Cpu,
+            42,
+        );
+        let ys = transform.apply(&xs)?;
+        assert_eq!(ys.dims(), xs.dims());
+
+        // Shifted images are within the range of the original ones
+        for i in 0..2 {
+            let (x, y) = (xs.get(i)?.flatten_all()?, ys.get(i)?.flatten_all()?);
+            assert!(y.min(0)?.to_scalar::<f32>()? >= x.min(0)?.to_scalar::<f32>()?);
+            assert!(y.max(0)?.to_scalar::<f32>()? <= x.max(0)?.to_scalar::<f32>()?);
+        }
+
+        let mut transform = BatchTransform::new(
+            vec![BatchTransformConfig::RandomCrop {
+                height: 3,
+                width: 2,
+            }],
+            Device::Cpu,
+            42,
+        );
+        let ys = transform.apply(&xs)?;
+        assert_eq!(ys.dims(), [2, 1, 3, 2]);
+
+        // Each sample is a window of the original image
+        for i in 0..2 {
+            let v = ys.get(i)?.flatten_all()?.to_vec1::<f32>()?[0] as usize - 16 * i;
+            let (y, x) = (v / 4, v % 4);
+            let window = xs.get(i)?.narrow(1, y, 3)?.narrow(2, x, 2)?;
+            let diff = (ys.get(i)? - window)?.abs()?.max_all()?;
+            assert_eq!(diff.to_scalar::<f32>()?, 0.0);
+        }
+        Ok(())
+    }
+}
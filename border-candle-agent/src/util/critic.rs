@@ -2,10 +2,13 @@
 use crate::{
     model::SubModel2,
     opt::{Optimizer, OptimizerConfig},
-    util::track_with_replace_substring,
+    util::{
+        amp::{Amp, AmpConfig},
+        append_ext, find_params, params_path, soft_update, SoftUpdateConfig, SoftUpdater,
+    },
 };
 use anyhow::{Context, Result};
-use candle_core::{DType::F32, Device, Tensor, D};
+use candle_core::{backprop::GradStore, DType, DType::F32, Device, Tensor, Var, D};
 use candle_nn::{VarBuilder, VarMap};
 use log::info;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -27,8 +30,13 @@ pub struct MultiCriticConfig<Q> {
     /// Configuration of the optimizer.
     pub opt_config: OptimizerConfig,
 
-    /// Soft update coefficient.
-    pub tau: f64,
+    /// Configuration of the updates of target networks.
+    #[serde(flatten)]
+    pub soft_update: SoftUpdateConfig,
+
+    /// Configuration of mixed precision training of the critics.
+    #[serde(default)]
+    pub amp: Option<AmpConfig>,
 }
 
 impl<Q> Default for MultiCriticConfig<Q> {
@@ -37,7 +45,8 @@ impl<Q> Default for MultiCriticConfig<Q> {
             n_nets: 2,
             q_config: None,
             opt_config: OptimizerConfig::Adam { lr: 0.0003 },
-            tau: 0.005,
+            soft_update: SoftUpdateConfig::default(),
+            amp: None,
         }
     }
 }
@@ -66,7 +75,19 @@ where
 
     /// Sets soft update parameter tau.
     pub fn tau(mut self, v: f64) -> Self {
-        self.tau = v;
+        self.soft_update.tau = v;
+        self
+    }
+
+    /// Sets the configuration of the updates of target networks.
+    pub fn soft_update_config(mut self, v: SoftUpdateConfig) -> Self {
+        self.soft_update = v;
+        self
+    }
+
+    /// Enables mixed precision training, see [`crate::util::amp`].
+    pub fn amp(mut self, v: AmpConfig) -> Self {
+        self.amp = Some(v);
         self
     }
 
@@ -91,13 +112,16 @@ where
 /// It takes observations and actions as inputs and outputs action values.
 ///
 /// This struct has multiple q functions and corresponding target networks.
+///
+/// With mixed precision training, it also has copies of them in half precision, which are
+/// used in [`MultiCritic::qvals_amp()`] and [`MultiCritic::qvals_min_tgt_amp()`].
 pub struct MultiCritic<Q>
 where
     Q: SubModel2<Output = Tensor>,
     Q::Config: DeserializeOwned + Serialize,
 {
     n_nets: usize,
-    tau: f64,
+    soft_updater: SoftUpdater,
     device: Device,
     varmap: VarMap,
     varmap_tgt: VarMap, // for target network
@@ -109,6 +133,13 @@ where
 
     opt_config: OptimizerConfig,
     opt: Optimizer, // no optimizer required for tatget networks
+    shared_vars: Vec<Var>,
+
+    // Mixed precision training
+    amp_config: Option<AmpConfig>,
+    amp: Option<Amp>,
+    qs_amp: Vec<Q>,
+    qs_tgt_amp: Vec<Q>,
 }
 
 impl<Q> MultiCritic<Q>
@@ -118,7 +149,17 @@ where
 {
     /// Constructs [`MultiCritic`].
     pub fn build(config: MultiCriticConfig<Q::Config>, device: Device) -> Result<MultiCritic<Q>> {
-        let tau = config.tau;
+        Self::build_with_vars(config, device, vec![])
+    }
+
+    /// Constructs [`MultiCritic`] of which the optimizer also updates the given variables,
+    /// e.g., the parameters of an encoder shared with the actor.
+    pub fn build_with_vars(
+        config: MultiCriticConfig<Q::Config>,
+        device: Device,
+        vars: Vec<Var>,
+    ) -> Result<MultiCritic<Q>> {
+        let soft_updater = SoftUpdater::new(config.soft_update);
         let n_nets = config.n_nets;
         let q_config = config.q_config.context("q_config is not set.")?;
         let opt_config = config.opt_config;
@@ -131,13 +172,14 @@ where
             Self::build_critic_networks(&q_config, &device, n_nets, "critic_tgt");
 
         // Optimizer, shared with critic networks
-        let opt = opt_config.build(varmap.all_vars())?;
+        let opt = opt_config.build([varmap.all_vars(), vars.clone()].concat())?;
 
         // Copy parameters
-        track_with_replace_substring(&varmap_tgt, &varmap, 1.0, ("critic", "critic_tgt"))?;
+        soft_update(&varmap_tgt, &varmap, |_| 1.0, ("critic", "critic_tgt"))?;
 
-        Ok(Self {
-            tau,
+        let amp = config.amp.as_ref().map(Amp::new);
+        let mut critic = Self {
+            soft_updater,
             n_nets,
             device,
             varmap,
@@ -147,7 +189,15 @@ where
             qs_tgt,
             opt_config,
             opt,
-        })
+            shared_vars: vars,
+            amp_config: config.amp,
+            amp,
+            qs_amp: vec![],
+            qs_tgt_amp: vec![],
+        };
+        critic.sync_amp(true, true);
+
+        Ok(critic)
     }
 
     fn build_critic_networks(
@@ -171,28 +221,95 @@ where
         (varmap, qs)
     }
 
+    /// Rebuilds the networks in half precision from the current parameters.
+    fn sync_amp(&mut self, online: bool, target: bool) {
+        let amp = match &self.amp {
+            None => return,
+            Some(amp) => amp,
+        };
+        let build = |varmap: &VarMap, prefix: &str| -> Vec<Q> {
+            (0..self.n_nets)
+                .map(|ix| {
+                    let vb = amp
+                        .var_builder(varmap, &self.device)
+                        .set_prefix(format!("{}{}", prefix, ix));
+                    Q::build(vb, self.q_config.clone())
+                })
+                .collect()
+        };
+        let qs_amp = online.then(|| build(&self.varmap, "critic"));
+        let qs_tgt_amp = target.then(|| build(&self.varmap_tgt, "critic_tgt"));
+        if let Some(qs_amp) = qs_amp {
+            self.qs_amp = qs_amp;
+        }
+        if let Some(qs_tgt_amp) = qs_tgt_amp {
+            self.qs_tgt_amp = qs_tgt_amp;
+        }
+    }
+
+    /// Updates the target networks every
+    /// [`SoftUpdateConfig::update_interval`] calls.
     pub fn soft_update(&mut self) -> Result<()> {
-        track_with_replace_substring(
-            &self.varmap_tgt,
-            &self.varmap,
-            self.tau,
-            ("critic", "critic_tgt"),
-        )?;
+        if self
+            .soft_updater
+            .update(&self.varmap_tgt, &self.varmap, ("critic", "critic_tgt"))?
+        {
+            self.sync_amp(false, true);
+        }
         Ok(())
     }
 
-    /// Returns action values of all critics.
-    pub fn qvals(&self, obs: &Q::Input1, act: &Q::Input2) -> Vec<Tensor> {
-        self.qs
-            .iter()
+    /// Returns the floating point type of the forward pass in mixed precision training.
+    pub fn amp_dtype(&self) -> Option<DType> {
+        self.amp.as_ref().map(Amp::dtype)
+    }
+
+    /// Returns the state of mixed precision training.
+    pub fn amp(&self) -> Option<&Amp> {
+        self.amp.as_ref()
+    }
+
+    fn forward_all(qs: &[Q], obs: &Q::Input1, act: &Q::Input2) -> Vec<Tensor> {
+        qs.iter()
             .map(|critic| {
                 let q = critic.forward(obs, act).squeeze(D::Minus1).unwrap();
                 // debug_assert_eq!(q.dims(), &[self.batch_size]);
-                q
+                q.to_dtype(F32).unwrap()
             })
             .collect()
     }
 
+    /// Returns action values of all critics.
+    pub fn qvals(&self, obs: &Q::Input1, act: &Q::Input2) -> Vec<Tensor> {
+        Self::forward_all(&self.qs, obs, act)
+    }
+
+    /// Returns action values of all critics in `f32`, computed in the precision of training.
+    ///
+    /// With mixed precision training, inputs should be given in the type of
+    /// [`MultiCritic::amp_dtype()`].
+    pub fn qvals_amp(&self, obs: &Q::Input1, act: &Q::Input2) -> Vec<Tensor> {
+        match self.amp {
+            None => self.qvals(obs, act),
+            Some(_) => Self::forward_all(&self.qs_amp, obs, act),
+        }
+    }
+
+    /// Returns minimum action values of all target critics in `f32`, computed in
+    /// the precision of training.
+    ///
+    /// With mixed precision training, inputs should be given in the type of
+    /// [`MultiCritic::amp_dtype()`].
+    pub fn qvals_min_tgt_amp(&self, obs: &Q::Input1, act: &Q::Input2) -> Result<Tensor> {
+        let qvals = match self.amp {
+            None => return self.qvals_min_tgt(obs, act),
+            Some(_) => Self::forward_all(&self.qs_tgt_amp, obs, act),
+        };
+        let qvals = Tensor::stack(&qvals, 0)?; // [self.n_nets, batch_size]
+        let qvals_min = qvals.min(0)?.squeeze(D::Minus1)?; // [batch_size]
+        Ok(qvals_min)
+    }
+
     /// Returns minimum action values of all target critics.
     pub fn qvals_min(&self, obs: &Q::Input1, act: &Q::Input2) -> Result<Tensor> {
         let qvals = self.qvals(obs, act);
@@ -203,15 +320,7 @@ where
 
     /// Returns minimum action values of all target critics.
     pub fn qvals_min_tgt(&self, obs: &Q::Input1, act: &Q::Input2) -> Result<Tensor> {
-        let qvals: Vec<Tensor> = self
-            .qs_tgt
-            .iter()
-            .map(|critic| {
-                let q = critic.forward(obs, act).squeeze(D::Minus1).unwrap();
-                // debug_assert_eq!(q.dims(), &[self.batch_size]);
-                q
-            })
-            .collect();
+        let qvals = Self::forward_all(&self.qs_tgt, obs, act);
         let qvals = Tensor::stack(&qvals, 0)?; // [self.n_nets, batch_size]
         let qvals_min = qvals.min(0)?.squeeze(D::Minus1)?; // [batch_size]
         Ok(qvals_min)
@@ -224,7 +333,7 @@ where
     Q::Config: DeserializeOwned + Serialize + Clone,
 {
     fn clone(&self) -> Self {
-        let tau = self.tau;
+        let soft_updater = self.soft_updater.clone();
         let n_nets = self.n_nets;
         let device = self.device.clone();
         let q_config = self.q_config.clone();
@@ -244,8 +353,9 @@ where
         varmap.clone_from(&self.varmap);
         varmap_tgt.clone_from(&self.varmap_tgt);
 
-        Self {
-            tau,
+        let amp = self.amp_config.as_ref().map(Amp::new);
+        let mut critic = Self {
+            soft_updater,
             n_nets,
             device,
             varmap,
@@ -255,46 +365,93 @@ where
             qs_tgt,
             opt_config,
             opt,
-        }
+            shared_vars: vec![],
+            amp_config: self.amp_config.clone(),
+            amp,
+            qs_amp: vec![],
+            qs_tgt_amp: vec![],
+        };
+        critic.sync_amp(true, true);
+        critic
     }
 }
 
 impl<Q> MultiCritic<Q>
 where
     Q: SubModel2<Output = Tensor>,
-    Q::Config: DeserializeOwned + Serialize,
+    Q::Config: DeserializeOwned + Serialize + Clone,
 {
     /// Backward step for all variables in critic networks.
     pub fn backward_step(&mut self, loss: &Tensor) -> Result<()> {
-        self.opt.backward_step(loss)
+        if let Some(mut grads) = self.backward(loss, &[])? {
+            self.step(&mut grads)?;
+        }
+        Ok(())
+    }
+
+    /// Computes gradients of the loss.
+    ///
+    /// With mixed precision training, the loss is scaled and the gradients of the critics
+    /// and `vars`, e.g., the parameters of an encoder, are unscaled. It returns `None`
+    /// if the gradients overflow.
+    pub fn backward(&mut self, loss: &Tensor, vars: &[Var]) -> Result<Option<GradStore>> {
+        match self.amp.as_mut() {
+            None => Ok(Some(loss.backward()?)),
+            Some(amp) => {
+                let vars = [
+                    self.varmap.all_vars(),
+                    self.shared_vars.clone(),
+                    vars.to_vec(),
+                ];
+                amp.backward(loss, &vars.concat())
+            }
+        }
+    }
+
+    /// Updates variables in critic networks with the given gradients.
+    ///
+    /// It is used when gradients of a loss are shared with other modules, e.g., an encoder.
+    pub fn step(&mut self, grads: &mut GradStore) -> Result<()> {
+        self.opt.step(grads)?;
+        self.sync_amp(true, false);
+        Ok(())
+    }
+
+    /// Returns the current learning rate of the optimizer.
+    pub fn learning_rate(&self) -> f64 {
+        self.opt.learning_rate()
+    }
+
+    /// Returns the global norm of gradients before clipping in the last update,
+    /// if gradients are clipped.
+    pub fn grad_norm(&self) -> Option<f32> {
+        self.opt.grad_norm()
     }
 
-    /// Save variables to prefix + ".pt" and + "_tgt.pt".
+    /// Save variables to prefix + ".safetensors" and + ".tgt.safetensors".
     pub fn save<T: AsRef<Path>>(&self, prefix: T) -> Result<(PathBuf, PathBuf)> {
-        let mut path = PathBuf::from(prefix.as_ref());
-        path.set_extension("pt");
+        let path = params_path(&prefix);
         self.varmap.save(&path.as_path())?;
         info!("Save critics to {:?}", path);
 
-        let mut path_tgt = PathBuf::from(prefix.as_ref());
-        path_tgt.set_extension("tgt.pt");
+        let path_tgt = params_path(append_ext(&prefix, "tgt"));
         self.varmap.save(&path_tgt.as_path())?;
         info!("Save target critics to {:?}", path_tgt);
 
         Ok((path, path_tgt))
     }
 
-    /// Load variables from prefix + ".pt" and + "_tgt.pt".
+    /// Load variables from prefix + ".safetensors" and + ".tgt.safetensors",
+    /// or those with ".pt" saved by older versions.
     pub fn load<T: AsRef<Path>>(&mut self, prefix: T) -> Result<()> {
-        let mut path = PathBuf::from(prefix.as_ref());
-        path.set_extension("pt");
+        let path = find_params(&prefix);
         self.varmap.load(&path.as_path())?;
         info!("Load critics from {:?}", path);
 
-        let mut path = PathBuf::from(prefix.as_ref());
-        path.set_extension("tgt.pt");
+        let path = find_params(append_ext(&prefix, "tgt"));
         self.varmap.load(&path.as_path())?;
         info!("Load target critics from {:?}", path);
+        self.sync_amp(true, true);
 
         Ok(())
     }
@@ -0,0 +1,188 @@
+//! Data-parallel training on multiple devices.
+//!
+//! [`DataParallel`] holds replicas of a model on devices other than that of the model.
+//! In the forward pass, a batch is split into chunks along the first dimension, which are
+//! processed by the model and the replicas, and the outputs are gathered on the device of
+//! the model. The loss is computed on the gathered outputs as usual. Gradients are propagated
+//! back to the parameters of the replicas, which are summed up into those of the model with
+//! [`DataParallel::reduce_grads()`]. After the parameters of the model are updated,
+//! they are copied to the replicas with [`DataParallel::sync()`].
+//!
+//! As the gradients are reduced on the device of the model, it works as a parameter server.
+use crate::{model::SubModel1, Device};
+use anyhow::{Context, Result};
+use candle_core::{backprop::GradStore, DType, Tensor};
+use candle_nn::{VarBuilder, VarMap};
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`DataParallel`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub struct DataParallelConfig {
+    /// Devices of the replicas, in addition to the device of the agent.
+    ///
+    /// A batch is split evenly into chunks for the device of the agent and these devices.
+    pub devices: Vec<Device>,
+}
+
+impl DataParallelConfig {
+    /// Sets the devices of the replicas.
+    pub fn devices(mut self, v: Vec<Device>) -> Self {
+        self.devices = v;
+        self
+    }
+}
+
+struct Replica<M> {
+    device: candle_core::Device,
+    varmap: VarMap,
+    model: M,
+}
+
+/// Replicas of a model for data-parallel training.
+pub struct DataParallel<M> {
+    replicas: Vec<Replica<M>>,
+}
+
+impl<M> DataParallel<M>
+where
+    M: SubModel1<Output = Tensor>,
+    M::Config: Clone,
+{
+    /// Builds replicas of a model on the devices given in the configuration.
+    ///
+    /// The parameters of the replicas should be copied from the model with
+    /// [`DataParallel::sync()`] before use.
+    pub fn build(config: &DataParallelConfig, model_config: &M::Config) -> Self {
+        let replicas = config
+            .devices
+            .iter()
+            .map(|&device| {
+                let device: candle_core::Device = device.into();
+                let varmap = VarMap::new();
+                let vb = VarBuilder::from_varmap(&varmap, DType::F32, &device);
+                let model = M::build(vb, model_config.clone());
+                Replica {
+                    device,
+                    varmap,
+                    model,
+                }
+            })
+            .collect();
+        Self { replicas }
+    }
+
+    /// Returns the number of replicas.
+    pub fn n_replicas(&self) -> usize {
+        self.replicas.len()
+    }
+
+    /// Copies the parameters in `varmap` of the model to the replicas.
+    pub fn sync(&self, varmap: &VarMap) -> Result<()> {
+        let src = varmap.data().lock().unwrap();
+        for replica in self.replicas.iter() {
+            for (name, var) in replica.varmap.data().lock().unwrap().iter() {
+                let t = src
+                    .get(name)
+                    .with_context(|| format!("{} is not found in the model", name))?;
+                var.set(&t.as_tensor().detach().to_device(&replica.device)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies `model` and the replicas to chunks of `xs` and concatenates the outputs
+    /// on the device of `xs`.
+    ///
+    /// Returns an error if the input of the model is not a tensor,
+    /// see [`SubModel1::input_as_tensor()`].
+    pub fn forward(&self, model: &M, xs: &M::Input) -> Result<Tensor> {
+        let input = |xs| M::input_from_tensor(xs).context("Input of the model is not a tensor");
+        let xs = M::input_as_tensor(xs).context("Input of the model is not a tensor")?;
+        let device = xs.device();
+        let chunks = xs.chunk(self.replicas.len() + 1, 0)?;
+        let mut chunks = chunks.into_iter();
+        let mut ys = vec![];
+        if let Some(chunk) = chunks.next() {
+            ys.push(model.forward(&input(chunk)?));
+        }
+        for (chunk, replica) in chunks.zip(self.replicas.iter()) {
+            let chunk = chunk.to_device(&replica.device)?;
+            ys.push(replica.model.forward(&input(chunk)?).to_device(device)?);
+        }
+        Ok(Tensor::cat(&ys, 0)?)
+    }
+
+    /// Adds the gradients of the parameters of the replicas to those of the model,
+    /// which are given in `varmap`.
+    pub fn reduce_grads(&self, grads: &mut GradStore, varmap: &VarMap) -> Result<()> {
+        let dest = varmap.data().lock().unwrap();
+        for replica in self.replicas.iter() {
+            for (name, var) in replica.varmap.data().lock().unwrap().iter() {
+                let Some(grad) = grads.remove(var.as_tensor()) else {
+                    continue;
+                };
+                let var_dest = dest
+                    .get(name)
+                    .with_context(|| format!("{} is not found in the model", name))?
+                    .as_tensor();
+                let grad = grad.to_device(var_dest.device())?;
+                let grad = match grads.remove(var_dest) {
+                    None => grad,
+                    Some(g) => (g + grad)?,
+                };
+                grads.insert(var_dest, grad);
+            }
+        }
+        Ok(())
+    }
+
+    /// Resamples the noise of noisy layers in the replicas.
+    pub fn reset_noise(&mut self) {
+        self.replicas
+            .iter_mut()
+            .for_each(|replica| replica.model.reset_noise());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        mlp::{Mlp, MlpConfig},
+        Activation,
+    };
+
+    #[test]
+    fn test_data_parallel() -> Result<()> {
+        let device = candle_core::Device::Cpu;
+        let config = MlpConfig::new(3, vec![4], 2, Activation::None);
+        let varmap = VarMap::new();
+        let model = Mlp::build(
+            VarBuilder::from_varmap(&varmap, DType::F32, &device),
+            config.clone(),
+        );
+        let dp_config = DataParallelConfig::default().devices(vec![Device::Cpu, Device::Cpu]);
+        let dp = DataParallel::<Mlp>::build(&dp_config, &config);
+        dp.sync(&varmap)?;
+
+        // The outputs are the same as those of the model
+        let xs = Tensor::randn(0f32, 1f32, (5, 3), &device)?;
+        let ys = dp.forward(&model, &xs)?;
+        let diff = (ys - model.forward(&xs))?.abs()?.max_all()?;
+        assert!(diff.to_scalar::<f32>()? < 1e-6);
+
+        // The gradients are the same as those computed by the model
+        let loss = dp.forward(&model, &xs)?.sqr()?.sum_all()?;
+        let mut grads = loss.backward()?;
+        dp.reduce_grads(&mut grads, &varmap)?;
+        let grads_ = model.forward(&xs).sqr()?.sum_all()?.backward()?;
+        for var in varmap.all_vars() {
+            let g1 = grads.get(var.as_tensor()).unwrap();
+            let g2 = grads_.get(var.as_tensor()).unwrap();
+            let diff = (g1 - g2)?.abs()?.max_all()?;
+            assert!(diff.to_scalar::<f32>()? < 1e-5);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,326 @@
+//! Encoder of observations shared by actors and critics.
+//!
+//! [`Encoder`] extracts features from observations, e.g., pixels, which are given to both
+//! the actor and the critics of an agent instead of building a feature extractor for each
+//! of them. [`EncoderConfig::opt_mode`] determines the optimizer of the encoder, and
+//! [`EncoderConfig::stop_grad_actor`] and [`EncoderConfig::stop_grad_critic`] determine
+//! the losses with which the encoder is trained.
+use crate::{
+    model::SubModel1,
+    opt::{Optimizer, OptimizerConfig},
+    util::{append_ext, find_params, params_path, soft_update, SoftUpdateConfig, SoftUpdater},
+};
+use anyhow::{Context, Result};
+use candle_core::{backprop::GradStore, DType::F32, Device, Tensor, Var};
+use candle_nn::{VarBuilder, VarMap};
+use log::info;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+};
+
+/// Optimizer of [`Encoder`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub enum EncoderOptMode {
+    /// The encoder has its own optimizer configured with [`EncoderConfig::opt_config`],
+    /// which is stepped with the gradients of the critic and actor losses.
+    #[default]
+    Separate,
+
+    /// The parameters of the encoder are optimized with the optimizer of the critics.
+    ///
+    /// The encoder is trained only with the critic loss, so that
+    /// [`EncoderConfig::stop_grad_actor`] must be `true`.
+    SharedWithCritic,
+}
+
+fn default_stop_grad_actor() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+/// Configuration of [`Encoder`].
+pub struct EncoderConfig<F> {
+    /// Configuration of the encoder network.
+    pub encoder_config: Option<F>,
+
+    /// Configuration of the optimizer.
+    pub opt_config: OptimizerConfig,
+
+    /// Configuration of the updates of the target network.
+    #[serde(flatten)]
+    pub soft_update: SoftUpdateConfig,
+
+    /// Optimizer of the encoder.
+    #[serde(default)]
+    pub opt_mode: EncoderOptMode,
+
+    /// If `true`, features given to the actor are detached, i.e., the encoder is not
+    /// trained with the actor loss.
+    #[serde(default = "default_stop_grad_actor")]
+    pub stop_grad_actor: bool,
+
+    /// If `true`, features given to the critics are detached, i.e., the encoder is not
+    /// trained with the critic loss.
+    #[serde(default)]
+    pub stop_grad_critic: bool,
+}
+
+impl<F> Default for EncoderConfig<F> {
+    fn default() -> Self {
+        Self {
+            encoder_config: None,
+            opt_config: OptimizerConfig::Adam { lr: 0.0001 },
+            soft_update: SoftUpdateConfig::default().tau(0.01),
+            opt_mode: EncoderOptMode::Separate,
+            stop_grad_actor: true,
+            stop_grad_critic: false,
+        }
+    }
+}
+
+impl<F> EncoderConfig<F>
+where
+    F: DeserializeOwned + Serialize,
+{
+    /// Sets the configuration of the encoder network.
+    pub fn encoder_config(mut self, v: F) -> Self {
+        self.encoder_config = Some(v);
+        self
+    }
+
+    /// Sets optimizer configuration.
+    pub fn opt_config(mut self, v: OptimizerConfig) -> Self {
+        self.opt_config = v;
+        self
+    }
+
+    /// Sets soft update parameter tau.
+    pub fn tau(mut self, v: f64) -> Self {
+        self.soft_update.tau = v;
+        self
+    }
+
+    /// Sets the configuration of the updates of the target network.
+    pub fn soft_update_config(mut self, v: SoftUpdateConfig) -> Self {
+        self.soft_update = v;
+        self
+    }
+
+    /// Sets the optimizer of the encoder.
+    pub fn opt_mode(mut self, v: EncoderOptMode) -> Self {
+        self.opt_mode = v;
+        self
+    }
+
+    /// Sets the flag to detach features given to the actor.
+    pub fn stop_grad_actor(mut self, v: bool) -> Self {
+        self.stop_grad_actor = v;
+        self
+    }
+
+    /// Sets the flag to detach features given to the critics.
+    pub fn stop_grad_critic(mut self, v: bool) -> Self {
+        self.stop_grad_critic = v;
+        self
+    }
+
+    /// Constructs [`EncoderConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves [`EncoderConfig`] as YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Encoder of observations into features, e.g., a CNN of pixel observations.
+///
+/// It has a target network, updated with exponential moving average in
+/// [`Encoder::soft_update()`], which encodes next observations for target values.
+/// Features for the critics and the actor are given by [`Encoder::forward_critic()`] and
+/// [`Encoder::forward_actor()`], and gradients of their losses are applied with
+/// [`Encoder::step_critic()`] and [`Encoder::step_actor()`].
+///
+/// With [`EncoderOptMode::SharedWithCritic`], the encoder has no optimizer. The parameters
+/// given by [`Encoder::vars()`] should be added to the optimizer of the critics, e.g., with
+/// [`MultiCritic::build_with_vars()`](crate::util::critic::MultiCritic::build_with_vars).
+pub struct Encoder<F>
+where
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize,
+{
+    soft_updater: SoftUpdater,
+    encoder_config: F::Config,
+    varmap: VarMap,
+    varmap_tgt: VarMap, // for target network
+    encoder: F,
+    encoder_tgt: F,         // for target network
+    opt: Option<Optimizer>, // no optimizer required for tatget network
+    opt_mode: EncoderOptMode,
+    stop_grad_actor: bool,
+    stop_grad_critic: bool,
+}
+
+impl<F> Encoder<F>
+where
+    F: SubModel1<Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize + Clone,
+{
+    /// Constructs [`Encoder`].
+    pub fn build(config: EncoderConfig<F::Config>, device: Device) -> Result<Self> {
+        let encoder_config = config
+            .encoder_config
+            .context("encoder_config is not set.")?;
+        let build = |prefix: &str| {
+            let varmap = VarMap::new();
+            let vb = VarBuilder::from_varmap(&varmap, F32, &device).set_prefix(prefix);
+            let encoder = F::build(vb, encoder_config.clone());
+            (varmap, encoder)
+        };
+        let (varmap, encoder) = build("encoder");
+        let (varmap_tgt, encoder_tgt) = build("encoder_tgt");
+        let opt = match config.opt_mode {
+            EncoderOptMode::Separate => Some(config.opt_config.build(varmap.all_vars())?),
+            EncoderOptMode::SharedWithCritic if !config.stop_grad_actor => {
+                anyhow::bail!("stop_grad_actor must be true when the optimizer is shared")
+            }
+            EncoderOptMode::SharedWithCritic => None,
+        };
+
+        // Copy parameters
+        soft_update(&varmap_tgt, &varmap, |_| 1.0, ("encoder", "encoder_tgt"))?;
+
+        Ok(Self {
+            soft_updater: SoftUpdater::new(config.soft_update),
+            encoder_config,
+            varmap,
+            varmap_tgt,
+            encoder,
+            encoder_tgt,
+            opt,
+            opt_mode: config.opt_mode,
+            stop_grad_actor: config.stop_grad_actor,
+            stop_grad_critic: config.stop_grad_critic,
+        })
+    }
+
+    /// Returns the configuration of the encoder network.
+    pub fn config(&self) -> &F::Config {
+        &self.encoder_config
+    }
+
+    /// Returns `true` if features given to the actor are detached.
+    pub fn stop_grad_actor(&self) -> bool {
+        self.stop_grad_actor
+    }
+
+    /// Returns the parameters of the encoder optimized with the critics, which are empty
+    /// unless [`EncoderOptMode::SharedWithCritic`] is used.
+    pub fn shared_vars(&self) -> Vec<Var> {
+        match self.opt_mode {
+            EncoderOptMode::SharedWithCritic => self.vars(),
+            EncoderOptMode::Separate => vec![],
+        }
+    }
+
+    /// Returns the parameters of the encoder, excluding the target network.
+    pub fn vars(&self) -> Vec<Var> {
+        self.varmap.all_vars()
+    }
+
+    /// Encodes observations.
+    pub fn forward(&self, obs: &F::Input) -> Tensor {
+        self.encoder.forward(obs)
+    }
+
+    /// Encodes observations given to the critics.
+    pub fn forward_critic(&self, obs: &F::Input) -> Tensor {
+        match self.stop_grad_critic {
+            true => self.encoder.forward(obs).detach(),
+            false => self.encoder.forward(obs),
+        }
+    }
+
+    /// Encodes observations given to the actor.
+    pub fn forward_actor(&self, obs: &F::Input) -> Tensor {
+        match self.stop_grad_actor {
+            true => self.encoder.forward(obs).detach(),
+            false => self.encoder.forward(obs),
+        }
+    }
+
+    /// Encodes observations with the target network.
+    pub fn forward_tgt(&self, obs: &F::Input) -> Tensor {
+        self.encoder_tgt.forward(obs).detach()
+    }
+
+    /// Updates the target network with exponential moving average every
+    /// [`SoftUpdateConfig::update_interval`] calls.
+    pub fn soft_update(&mut self) -> Result<()> {
+        self.soft_updater
+            .update(&self.varmap_tgt, &self.varmap, ("encoder", "encoder_tgt"))?;
+        Ok(())
+    }
+
+    /// Updates parameters with the gradients of the critic loss.
+    ///
+    /// It does nothing if the optimizer is shared with the critics or
+    /// [`EncoderConfig::stop_grad_critic`] is `true`.
+    pub fn step_critic(&mut self, grads: &mut GradStore) -> Result<()> {
+        match (self.opt.as_mut(), self.stop_grad_critic) {
+            (Some(opt), false) => opt.step(grads),
+            _ => Ok(()),
+        }
+    }
+
+    /// Updates parameters with the gradients of the actor loss.
+    ///
+    /// It does nothing if [`EncoderConfig::stop_grad_actor`] is `true`.
+    pub fn step_actor(&mut self, grads: &mut GradStore) -> Result<()> {
+        match (self.opt.as_mut(), self.stop_grad_actor) {
+            (Some(opt), false) => opt.step(grads),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the current learning rate of the optimizer, if the encoder has its own one.
+    pub fn learning_rate(&self) -> Option<f64> {
+        self.opt.as_ref().map(|opt| opt.learning_rate())
+    }
+
+    /// Save variables to prefix + ".safetensors" and + ".tgt.safetensors".
+    pub fn save<T: AsRef<Path>>(&self, prefix: T) -> Result<(PathBuf, PathBuf)> {
+        let path = params_path(&prefix);
+        self.varmap.save(&path)?;
+        info!("Save encoder to {:?}", path);
+
+        let path_tgt = params_path(append_ext(&prefix, "tgt"));
+        self.varmap_tgt.save(&path_tgt)?;
+        info!("Save target encoder to {:?}", path_tgt);
+
+        Ok((path, path_tgt))
+    }
+
+    /// Load variables from prefix + ".safetensors" and + ".tgt.safetensors".
+    pub fn load<T: AsRef<Path>>(&mut self, prefix: T) -> Result<()> {
+        let path = find_params(&prefix);
+        self.varmap.load(&path)?;
+        info!("Load encoder from {:?}", path);
+
+        let path = find_params(append_ext(&prefix, "tgt"));
+        self.varmap_tgt.load(&path)?;
+        info!("Load target encoder from {:?}", path);
+
+        Ok(())
+    }
+}
@@ -1,104 +1,116 @@
-use candle_core::Tensor;
+use super::TensorData;
+use candle_core::{bail, Device, Result, Tensor};
 use candle_nn::VarMap;
+use serde::{
+    de::{Deserializer, Error as _},
+    ser::{Error as _, SerializeMap, Serializer},
+    Deserialize, Serialize,
+};
 use std::collections::HashMap;
-// use tch::{nn::VarStore, Device::Cpu, Tensor};
 
 /// Named tensors to send model parameters using a channel.
+#[derive(Clone)]
 pub struct NamedTensors {
     pub named_tensors: HashMap<String, Tensor>,
 }
 
 impl NamedTensors {
     /// Copy data of [`VarMap`] to CPU.
-    pub fn copy_from(_vs: &VarMap) -> Self {
-        unimplemented!();
-
-        // let src = vs.variables();
-
-        // tch::no_grad(|| NamedTensors {
-        //     named_tensors: HashMap::from_iter(src.iter().map(|(k, v)| {
-        //         let v = v.detach().to(Cpu).data();
-        //         (k.clone(), v)
-        //     })),
-        // })
+    pub fn copy_from(vs: &VarMap) -> Result<Self> {
+        let src = vs.data().lock().unwrap();
+        let mut named_tensors = HashMap::with_capacity(src.len());
+        for (k, v) in src.iter() {
+            let v = v.as_tensor().to_device(&Device::Cpu)?.copy()?;
+            named_tensors.insert(k.clone(), v);
+        }
+        Ok(Self { named_tensors })
     }
 
     /// Copy named tensors to [`VarMap`].
-    pub fn copy_to(&self, _vs: &mut VarMap) {
-        unimplemented!();
-
-        // let src = &self.named_tensors;
-        // let dest = &mut vs.variables();
-        // // let device = vs.device();
-        // debug_assert_eq!(src.len(), dest.len());
-
-        // tch::no_grad(|| {
-        //     for (name, src) in src.iter() {
-        //         let dest = dest.get_mut(name).unwrap();
-        //         dest.copy_(src);
-        //     }
-        // });
+    ///
+    /// Returns an error if a tensor is not found in the [`VarMap`] or its shape does not match.
+    pub fn copy_to(&self, vs: &mut VarMap) -> Result<()> {
+        let dest = vs.data().lock().unwrap();
+        debug_assert_eq!(self.named_tensors.len(), dest.len());
+
+        for (name, src) in self.named_tensors.iter() {
+            match dest.get(name) {
+                None => bail!("cannot find {} in VarMap", name),
+                Some(var) => var.set(&src.to_device(var.device())?)?,
+            }
+        }
+        Ok(())
     }
 }
 
-impl Clone for NamedTensors {
-    fn clone(&self) -> Self {
-        unimplemented!();
-
-        // let src = &self.named_tensors;
+/// Serializes tensors as pairs of shapes and values.
+///
+/// It is used to send model parameters over the network, e.g., in the networked mode of
+/// `border-async-trainer`.
+impl Serialize for NamedTensors {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.named_tensors.len()))?;
+        for (name, t) in self.named_tensors.iter() {
+            let data = TensorData::from_tensor(t).map_err(S::Error::custom)?;
+            map.serialize_entry(name, &data)?;
+        }
+        map.end()
+    }
+}
 
-        // tch::no_grad(|| NamedTensors {
-        //     named_tensors: HashMap::from_iter(src.iter().map(|(k, v)| {
-        //         let v = v.detach().to(Cpu).data();
-        //         (k.clone(), v)
-        //     })),
-        // })
+/// Returns an error if the number of values does not match the shape of a tensor.
+impl<'de> Deserialize<'de> for NamedTensors {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let src = HashMap::<String, (Vec<usize>, TensorData)>::deserialize(deserializer)?;
+        let mut named_tensors = HashMap::with_capacity(src.len());
+        for (k, (shape, data)) in src.into_iter() {
+            let t = data.into_tensor(shape).map_err(D::Error::custom)?;
+            named_tensors.insert(k, t);
+        }
+        Ok(NamedTensors { named_tensors })
     }
 }
 
 #[cfg(test)]
 mod test {
-    // use super::NamedTensors;
-    // use std::convert::{TryFrom, TryInto};
-    // use tch::{
-    //     nn::{self, Module},
-    //     Device::Cpu,
-    //     Tensor,
-    // };
+    use super::NamedTensors;
+    use candle_core::{DType, Device, Module, Tensor};
+    use candle_nn::{linear, seq, Sequential, VarBuilder, VarMap};
+
+    fn build(vm: &VarMap) -> candle_core::Result<Sequential> {
+        let vb = VarBuilder::from_varmap(vm, DType::F32, &Device::Cpu);
+        Ok(seq()
+            .add(linear(3, 8, vb.pp("layer1"))?)
+            .add(linear(8, 2, vb.pp("layer2"))?))
+    }
 
     #[test]
-    fn test_named_tensors() {
-        // tch::manual_seed(42);
-
-        // let tensor1 = Tensor::try_from(vec![1., 2., 3.])
-        //     .unwrap()
-        //     .internal_cast_float(false);
-
-        // let vs1 = nn::VarStore::new(Cpu);
-        // let model1 = nn::seq()
-        //     .add(nn::linear(&vs1.root() / "layer1", 3, 8, Default::default()))
-        //     .add(nn::linear(&vs1.root() / "layer2", 8, 2, Default::default()));
-
-        // let mut vs2 = nn::VarStore::new(tch::Device::cuda_if_available());
-        // let model2 = nn::seq()
-        //     .add(nn::linear(&vs2.root() / "layer1", 3, 8, Default::default()))
-        //     .add(nn::linear(&vs2.root() / "layer2", 8, 2, Default::default()));
-        // let device = vs2.device();
-
-        // let t1: Vec<f64> = model1.forward(&tensor1).try_into().unwrap();
-        // let t2: Vec<f64> = model2.forward(&tensor1.to(device)).try_into().unwrap();
-
-        // let nt = NamedTensors::copy_from(&vs1);
-        // nt.copy_to(&mut vs2);
-
-        // let t3: Vec<f64> = model2.forward(&tensor1.to(device)).try_into().unwrap();
-
-        // for i in 0..2 {
-        //     assert!((t1[i] - t2[i]).abs() >= t1[i].abs() * 0.001);
-        //     assert!((t1[i] - t3[i]).abs() < t1[i].abs() * 0.001);
-        // }
-        // // println!("{:?}", t1);
-        // // println!("{:?}", t2);
-        // // println!("{:?}", t3);
+    fn test_named_tensors() -> candle_core::Result<()> {
+        let device = Device::Cpu;
+        let vm1 = VarMap::new();
+        let model1 = build(&vm1)?;
+        let mut vm2 = VarMap::new();
+        let model2 = build(&vm2)?;
+
+        let x = Tensor::new(&[[1f32, 2., 3.]], &device)?;
+        let t1 = model1.forward(&x)?.flatten_all()?.to_vec1::<f32>()?;
+        let t2 = model2.forward(&x)?.flatten_all()?.to_vec1::<f32>()?;
+
+        let nt = NamedTensors::copy_from(&vm1)?;
+        nt.copy_to(&mut vm2)?;
+        let t3 = model2.forward(&x)?.flatten_all()?.to_vec1::<f32>()?;
+
+        for i in 0..2 {
+            assert!((t1[i] - t2[i]).abs() >= t1[i].abs() * 0.001);
+            assert!((t1[i] - t3[i]).abs() < t1[i].abs() * 0.001);
+        }
+
+        // Copied tensors do not share the storage with variables
+        let var = vm1.data().lock().unwrap()["layer1.weight"].clone();
+        var.set(&var.zeros_like()?)?;
+        let w = nt.named_tensors["layer1.weight"].abs()?.sum_all()?;
+        assert!(w.to_scalar::<f32>()? > 0.0);
+
+        Ok(())
     }
 }
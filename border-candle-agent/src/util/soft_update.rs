@@ -0,0 +1,220 @@
+//! Updates of target networks.
+//!
+//! [`soft_update()`] moves the parameters of a target network towards those of the online
+//! network, identifying parameters by their names in [`VarMap`]s. [`SoftUpdater`] calls it
+//! every [`SoftUpdateConfig::update_interval`] steps, with coefficients given per parameter
+//! or all set to `1.0` with [`TargetUpdateMode::Hard`].
+use anyhow::{Context, Result};
+use candle_nn::VarMap;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// How target networks follow online networks.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Default)]
+pub enum TargetUpdateMode {
+    /// Parameters are updated as `tgt = tau * src + (1 - tau) * tgt`.
+    #[default]
+    Soft,
+
+    /// Parameters are copied from the online network, ignoring `tau`.
+    Hard,
+}
+
+fn default_update_interval() -> usize {
+    1
+}
+
+/// Configuration of [`SoftUpdater`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct SoftUpdateConfig {
+    /// Soft update coefficient.
+    pub tau: f64,
+
+    /// Soft update coefficients of parameters whose names contain the keys.
+    ///
+    /// Names are those of the online network. If more than one key matches a parameter,
+    /// the longest one is used.
+    #[serde(default)]
+    pub tau_overrides: BTreeMap<String, f64>,
+
+    /// The number of steps between updates, where target networks are never updated if `0`.
+    #[serde(default = "default_update_interval", alias = "soft_update_interval")]
+    pub update_interval: usize,
+
+    /// How target networks are updated.
+    #[serde(default)]
+    pub update_mode: TargetUpdateMode,
+}
+
+impl Default for SoftUpdateConfig {
+    fn default() -> Self {
+        Self {
+            tau: 0.005,
+            tau_overrides: BTreeMap::new(),
+            update_interval: 1,
+            update_mode: TargetUpdateMode::Soft,
+        }
+    }
+}
+
+impl SoftUpdateConfig {
+    /// Sets soft update coefficient.
+    pub fn tau(mut self, v: f64) -> Self {
+        self.tau = v;
+        self
+    }
+
+    /// Sets soft update coefficient of parameters whose names contain `key`.
+    pub fn tau_override(mut self, key: impl Into<String>, v: f64) -> Self {
+        self.tau_overrides.insert(key.into(), v);
+        self
+    }
+
+    /// Sets the number of steps between updates.
+    pub fn update_interval(mut self, v: usize) -> Self {
+        self.update_interval = v;
+        self
+    }
+
+    /// Sets how target networks are updated.
+    pub fn update_mode(mut self, v: TargetUpdateMode) -> Self {
+        self.update_mode = v;
+        self
+    }
+
+    /// Returns the soft update coefficient of the parameter of the given name.
+    pub fn tau_of(&self, name: &str) -> f64 {
+        match self.update_mode {
+            TargetUpdateMode::Hard => 1.0,
+            TargetUpdateMode::Soft => self
+                .tau_overrides
+                .iter()
+                .filter(|(key, _)| name.contains(key.as_str()))
+                .max_by_key(|(key, _)| key.len())
+                .map_or(self.tau, |(_, tau)| *tau),
+        }
+    }
+}
+
+/// Applies soft update on variables.
+///
+/// Variables are identified by their names, where `ss_dest` in the names of `dest` is
+/// replaced with `ss_src` to find the corresponding variables in `src`. `tau` gives
+/// the coefficient of each variable from its name in `src`.
+///
+/// dest = tau * src + (1.0 - tau) * dest
+pub fn soft_update(
+    dest: &VarMap,
+    src: &VarMap,
+    tau: impl Fn(&str) -> f64,
+    (ss_src, ss_dest): (&str, &str),
+) -> Result<()> {
+    let dest = dest.data().lock().unwrap();
+    let src = src.data().lock().unwrap();
+
+    for (k_dest, v_dest) in dest.iter() {
+        let k_src = k_dest.replace(ss_dest, ss_src);
+        let t_src = src
+            .get(&k_src)
+            .with_context(|| format!("{} is not found in the source", k_src))?
+            .as_tensor();
+        let tau = tau(&k_src);
+        let t_dest = match tau == 1.0 {
+            true => t_src.clone(),
+            false => ((tau * t_src)? + (1.0 - tau) * v_dest.as_tensor())?,
+        };
+        v_dest.set(&t_dest)?;
+    }
+
+    Ok(())
+}
+
+/// Updates target networks with [`soft_update()`] at a fixed interval.
+///
+/// Call [`SoftUpdater::update()`] once per optimization step.
+#[derive(Debug, Clone)]
+pub struct SoftUpdater {
+    config: SoftUpdateConfig,
+    counter: usize,
+}
+
+impl SoftUpdater {
+    /// Constructs [`SoftUpdater`].
+    pub fn new(config: SoftUpdateConfig) -> Self {
+        Self { config, counter: 0 }
+    }
+
+    /// Returns the configuration.
+    pub fn config(&self) -> &SoftUpdateConfig {
+        &self.config
+    }
+
+    /// Counts a step and updates `dest` towards `src` every `update_interval` steps.
+    ///
+    /// See [`soft_update()`] for `names`. It returns `true` if `dest` is updated.
+    pub fn update(&mut self, dest: &VarMap, src: &VarMap, names: (&str, &str)) -> Result<bool> {
+        if self.config.update_interval == 0 {
+            return Ok(false);
+        }
+        self.counter += 1;
+        if self.counter < self.config.update_interval {
+            return Ok(false);
+        }
+        self.counter = 0;
+        soft_update(dest, src, |name| self.config.tau_of(name), names)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use candle_core::{DType, Device, Tensor};
+    use candle_nn::Init;
+
+    fn varmap(prefix: &str, v: f32) -> Result<VarMap> {
+        let varmap = VarMap::new();
+        for name in ["encoder.weight", "head.weight"] {
+            let name = format!("{}.{}", prefix, name);
+            varmap.get(2, &name, Init::Const(v as _), DType::F32, &Device::Cpu)?;
+        }
+        Ok(varmap)
+    }
+
+    fn value(varmap: &VarMap, name: &str) -> Result<Vec<f32>> {
+        let data = varmap.data().lock().unwrap();
+        let t: &Tensor = data.get(name).unwrap().as_tensor();
+        Ok(t.to_vec1()?)
+    }
+
+    #[test]
+    fn test_soft_updater() -> Result<()> {
+        let src = varmap("q", 1.0)?;
+        let dest = varmap("q_tgt", 0.0)?;
+        let config = SoftUpdateConfig::default()
+            .tau(0.5)
+            .tau_override("encoder", 0.25)
+            .update_interval(2);
+        let mut updater = SoftUpdater::new(config);
+
+        assert!(!updater.update(&dest, &src, ("q", "q_tgt"))?);
+        assert!(updater.update(&dest, &src, ("q", "q_tgt"))?);
+        assert_eq!(value(&dest, "q_tgt.encoder.weight")?, [0.25, 0.25]);
+        assert_eq!(value(&dest, "q_tgt.head.weight")?, [0.5, 0.5]);
+
+        let config = updater.config().clone().update_mode(TargetUpdateMode::Hard);
+        let mut updater = SoftUpdater::new(config);
+        updater.update(&dest, &src, ("q", "q_tgt"))?;
+        updater.update(&dest, &src, ("q", "q_tgt"))?;
+        assert_eq!(value(&dest, "q_tgt.encoder.weight")?, [1.0, 1.0]);
+        assert_eq!(value(&dest, "q_tgt.head.weight")?, [1.0, 1.0]);
+
+        // Never updated with the interval of 0
+        let dest = varmap("q_tgt", 0.0)?;
+        let mut updater = SoftUpdater::new(updater.config().clone().update_interval(0));
+        assert!(!updater.update(&dest, &src, ("q", "q_tgt"))?);
+        assert_eq!(value(&dest, "q_tgt.head.weight")?, [0.0, 0.0]);
+
+        Ok(())
+    }
+}
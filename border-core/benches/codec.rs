@@ -0,0 +1,58 @@
+//! Throughput of compression codecs on Atari-like frames.
+//!
+//! The compression ratio of each codec is printed before the measurement.
+use border_core::codec::{Codec, CodecConfig};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Four stacked 84x84 frames with flat regions and a moving object, like preprocessed Atari frames.
+fn frames() -> Vec<u8> {
+    let mut data = vec![0u8; 4 * 84 * 84];
+    for (f, frame) in data.chunks_mut(84 * 84).enumerate() {
+        for (i, v) in frame.iter_mut().enumerate() {
+            let (y, x) = (i / 84, i % 84);
+            *v = match y {
+                0..=9 => 142,
+                _ if (x + f * 2) / 4 == 10 && (40..48).contains(&y) => 236,
+                _ => ((y / 12) * 20) as u8,
+            };
+        }
+    }
+    data
+}
+
+fn bench_codecs(c: &mut Criterion) {
+    let data = frames();
+    let configs = [
+        CodecConfig::None,
+        CodecConfig::Lz4,
+        CodecConfig::Zstd(1),
+        CodecConfig::Zstd(3),
+        CodecConfig::Zstd(9),
+    ];
+    let mut group = c.benchmark_group("codec");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+
+    for config in configs.iter() {
+        let codec: Box<dyn Codec> = config.build().unwrap();
+        let compressed = codec.compress(&data).unwrap();
+        println!(
+            "{:?}: ratio = {:.2}",
+            config,
+            data.len() as f32 / compressed.len() as f32
+        );
+
+        let id = format!("{:?}", config);
+        group.bench_with_input(BenchmarkId::new("compress", &id), &data, |b, data| {
+            b.iter(|| codec.compress(data).unwrap())
+        });
+        group.bench_with_input(
+            BenchmarkId::new("decompress", &id),
+            &compressed,
+            |b, compressed| b.iter(|| codec.decompress(compressed).unwrap()),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_codecs);
+criterion_main!(benches);
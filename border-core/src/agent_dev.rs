@@ -0,0 +1,134 @@
+//! Stable API for implementing agents in external crates.
+//!
+//! This module is enabled with the `agent-dev` feature. It re-exports exactly the traits and
+//! types needed to implement an [`Agent`] outside of this workspace, together with a few
+//! backend-agnostic helpers and a test harness. Items exported from this module follow
+//! semantic versioning; breaking changes to them are made only with a minor version bump
+//! while the crate is at `0.x`, and are listed in the changelog.
+//!
+//! An agent crate typically needs the following:
+//! - [`Agent`], [`Policy`] and [`Configurable`] to implement the agent itself
+//! - [`ReplayBufferBase`] and [`TransitionBatch`] to take training batches in [`Agent::opt()`]
+//! - [`Record`] and [`RecordValue`] to report metrics from [`Agent::opt_with_record()`]
+//! - [`Schedule`] for annealing hyperparameters like exploration rates
+//! - [`TargetUpdater`] and [`polyak_update()`] for target networks
+//!
+//! The harness [`run_cartpole()`] trains an agent on [`CartPole`], a native implementation
+//! of the classic control task, with [`Trainer`]. It then evaluates the agent and checks that
+//! its parameters can be saved and loaded. It is intended to be called from tests of agent
+//! crates, so that they can be tested without Python or deep learning backends.
+//!
+//! [`Corridor`] is a gridworld even smaller than [`CartPole`]. Its documentation trains
+//! a tabular agent with [`Trainer`], so that the training loop with [`StepProcessor`] and
+//! [`SimpleReplayBuffer`] is tested as a doctest.
+//!
+//! # Example
+//!
+//! The following agent acts with a fixed rule instead of a learned policy.
+//! It can be used as a template of agents.
+//!
+//! ```
+//! use anyhow::Result;
+//! use border_core::agent_dev::{
+//!     run_cartpole, Agent, CartPole, CartPoleAct, CartPoleBuffer, CartPoleObs, Configurable,
+//!     HarnessConfig, Policy, Record, RecordValue, ReplayBufferBase, Schedule, TransitionBatch,
+//! };
+//! use std::path::{Path, PathBuf};
+//!
+//! struct HeuristicAgent {
+//!     train: bool,
+//!     n_opts: usize,
+//!     eps: Schedule,
+//! }
+//!
+//! impl Policy<CartPole> for HeuristicAgent {
+//!     fn sample(&mut self, obs: &CartPoleObs) -> CartPoleAct {
+//!         // Push the cart towards the side to which the pole leans
+//!         CartPoleAct((obs.0[2] > 0.0) as i64)
+//!     }
+//! }
+//!
+//! impl Configurable for HeuristicAgent {
+//!     type Config = ();
+//!
+//!     fn build(_config: ()) -> Self {
+//!         let eps = Schedule::Linear { start: 1.0, end: 0.1, n_steps: 100 };
+//!         Self { train: false, n_opts: 0, eps }
+//!     }
+//! }
+//!
+//! impl Agent<CartPole, CartPoleBuffer> for HeuristicAgent {
+//!     fn train(&mut self) {
+//!         self.train = true;
+//!     }
+//!
+//!     fn eval(&mut self) {
+//!         self.train = false;
+//!     }
+//!
+//!     fn is_train(&self) -> bool {
+//!         self.train
+//!     }
+//!
+//!     fn opt_with_record(&mut self, buffer: &mut CartPoleBuffer) -> Record {
+//!         let batch = buffer.batch(32).unwrap();
+//!         let (_obs, _act, _next_obs, reward, ..) = batch.unpack();
+//!         self.n_opts += 1;
+//!         Record::from_slice(&[
+//!             ("reward", RecordValue::Scalar(reward.iter().sum::<f32>() / 32.0)),
+//!             ("eps", RecordValue::Scalar(self.eps.value(self.n_opts) as f32)),
+//!         ])
+//!     }
+//!
+//!     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+//!         std::fs::create_dir_all(path)?;
+//!         let file = path.join("n_opts.txt");
+//!         std::fs::write(&file, self.n_opts.to_string())?;
+//!         Ok(vec![file])
+//!     }
+//!
+//!     fn load_params(&mut self, path: &Path) -> Result<()> {
+//!         self.n_opts = std::fs::read_to_string(path.join("n_opts.txt"))?.parse()?;
+//!         Ok(())
+//!     }
+//!
+//!     fn as_any_ref(&self) -> &dyn std::any::Any {
+//!         self
+//!     }
+//!
+//!     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+//!         self
+//!     }
+//! }
+//!
+//! # fn main() -> Result<()> {
+//! let mut agent: Box<dyn Agent<CartPole, CartPoleBuffer>> = Box::new(HeuristicAgent::build(()));
+//! let model_dir = std::env::temp_dir().join("border_agent_dev_doctest");
+//! let config = HarnessConfig::default().max_opts(100).model_dir(Some(model_dir));
+//! let report = run_cartpole(&mut agent, &config)?;
+//! assert!(report.eval_return > 0.0);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Trainer`]: crate::Trainer
+//! [`StepProcessor`]: crate::StepProcessor
+//! [`SimpleReplayBuffer`]: crate::generic_replay_buffer::SimpleReplayBuffer
+mod cartpole;
+mod corridor;
+mod harness;
+mod target;
+pub use crate::{
+    record::{Record, RecordValue},
+    Act, Agent, Configurable, Env, Obs, Policy, ReplayBufferBase, Schedule, Step, TransitionBatch,
+};
+pub use cartpole::{
+    CartPole, CartPoleAct, CartPoleActBatch, CartPoleConfig, CartPoleInfo, CartPoleObs,
+    CartPoleObsBatch,
+};
+pub use corridor::{
+    Corridor, CorridorAct, CorridorActBatch, CorridorBuffer, CorridorConfig, CorridorInfo,
+    CorridorObs, CorridorObsBatch, CorridorStepProcessor,
+};
+pub use harness::{run_cartpole, CartPoleBuffer, HarnessConfig, HarnessReport};
+pub use target::{polyak_update, TargetUpdater};
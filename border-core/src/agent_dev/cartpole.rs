@@ -0,0 +1,289 @@
+//! Native implementation of the CartPole environment.
+use crate::{
+    generic_replay_buffer::BatchBase,
+    record::{Record, RecordValue},
+    Act, ActValues, Env, Info, Obs, Step,
+};
+use anyhow::Result;
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+const GRAVITY: f32 = 9.8;
+const MASS_CART: f32 = 1.0;
+const MASS_POLE: f32 = 0.1;
+const TOTAL_MASS: f32 = MASS_CART + MASS_POLE;
+const LENGTH: f32 = 0.5; // half of the length of the pole
+const POLE_MASS_LENGTH: f32 = MASS_POLE * LENGTH;
+const FORCE_MAG: f32 = 10.0;
+const TAU: f32 = 0.02;
+const THETA_THRESHOLD: f32 = 12.0 * 2.0 * std::f32::consts::PI / 360.0;
+const X_THRESHOLD: f32 = 2.4;
+
+/// Observation of [`CartPole`].
+///
+/// It consists of the cart position, the cart velocity, the pole angle and
+/// the pole angular velocity.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CartPoleObs(pub [f32; 4]);
+
+impl Obs for CartPoleObs {
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn shape(&self) -> Option<Vec<usize>> {
+        Some(vec![4])
+    }
+
+    fn is_finite(&self) -> bool {
+        self.0.iter().all(|v| v.is_finite())
+    }
+}
+
+/// Action of [`CartPole`].
+///
+/// `0` pushes the cart to the left and `1` pushes it to the right.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CartPoleAct(pub i64);
+
+impl Act for CartPoleAct {
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn values(&self) -> Option<ActValues> {
+        ActValues::from_indices([self.0])
+    }
+}
+
+impl From<i64> for CartPoleAct {
+    fn from(a: i64) -> Self {
+        Self(a)
+    }
+}
+
+/// Info of [`CartPole`], which is empty.
+pub struct CartPoleInfo {}
+
+impl Info for CartPoleInfo {}
+
+/// Configuration of [`CartPole`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CartPoleConfig {
+    /// The maximum number of steps in an episode, after which the episode is truncated.
+    pub max_steps: usize,
+}
+
+impl Default for CartPoleConfig {
+    fn default() -> Self {
+        Self { max_steps: 500 }
+    }
+}
+
+/// Native implementation of the CartPole environment.
+///
+/// The dynamics, the reward and the termination condition follow `CartPole-v1` of
+/// [Gymnasium](https://gymnasium.farama.org/environments/classic_control/cart_pole/).
+/// The agent receives reward 1 for every step, including the terminal step.
+pub struct CartPole {
+    state: [f32; 4],
+    n_steps: usize,
+    max_steps: usize,
+    seed: i64,
+    rng: SmallRng,
+}
+
+impl CartPole {
+    fn reset_state(&mut self) -> CartPoleObs {
+        for v in self.state.iter_mut() {
+            *v = self.rng.gen_range(-0.05..0.05);
+        }
+        self.n_steps = 0;
+        CartPoleObs(self.state)
+    }
+}
+
+impl Env for CartPole {
+    type Config = CartPoleConfig;
+    type Obs = CartPoleObs;
+    type Act = CartPoleAct;
+    type Info = CartPoleInfo;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        Ok(Self {
+            state: [0.0; 4],
+            n_steps: 0,
+            max_steps: config.max_steps,
+            seed,
+            rng: SmallRng::seed_from_u64(seed as _),
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let [x, x_dot, theta, theta_dot] = self.state;
+        let force = if a.0 == 1 { FORCE_MAG } else { -FORCE_MAG };
+        let (sin, cos) = theta.sin_cos();
+        let temp = (force + POLE_MASS_LENGTH * theta_dot * theta_dot * sin) / TOTAL_MASS;
+        let theta_acc = (GRAVITY * sin - cos * temp)
+            / (LENGTH * (4.0 / 3.0 - MASS_POLE * cos * cos / TOTAL_MASS));
+        let x_acc = temp - POLE_MASS_LENGTH * theta_acc * cos / TOTAL_MASS;
+
+        // Euler integration
+        self.state = [
+            x + TAU * x_dot,
+            x_dot + TAU * x_acc,
+            theta + TAU * theta_dot,
+            theta_dot + TAU * theta_acc,
+        ];
+        self.n_steps += 1;
+
+        let is_terminated =
+            self.state[0].abs() > X_THRESHOLD || self.state[2].abs() > THETA_THRESHOLD;
+        let is_truncated = !is_terminated && self.n_steps >= self.max_steps;
+        let step = Step::new(
+            CartPoleObs(self.state),
+            a.clone(),
+            vec![1.0],
+            vec![is_terminated as i8],
+            vec![is_truncated as i8],
+            CartPoleInfo {},
+            None,
+        );
+        let record = match is_terminated || is_truncated {
+            true => {
+                Record::from_slice(&[("episode_length", RecordValue::Scalar(self.n_steps as f32))])
+            }
+            false => Record::empty(),
+        };
+
+        (step, record)
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        Ok(self.reset_state())
+    }
+
+    /// Returns both actions followed by random ones, which are sampled with a generator
+    /// separate from that of the environment.
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        let mut rng = SmallRng::seed_from_u64(self.seed as _);
+        (0..2)
+            .chain((0..n_random).map(|_| rng.gen_range(0..2)))
+            .map(CartPoleAct)
+            .collect()
+    }
+
+    /// Resets the environment with the random number generator seeded by `ix`,
+    /// so that evaluation episodes start from the same states.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.rng =
+            SmallRng::seed_from_u64(self.seed as u64 ^ (ix as u64).wrapping_mul(0x9e37_79b9));
+        Ok(self.reset_state())
+    }
+}
+
+/// Batch of observations of [`CartPole`].
+#[derive(Clone, Debug)]
+pub struct CartPoleObsBatch(pub Vec<[f32; 4]>);
+
+impl BatchBase for CartPoleObsBatch {
+    fn new(capacity: usize) -> Self {
+        Self(vec![[0.0; 4]; capacity])
+    }
+
+    fn push(&mut self, ix: usize, data: Self) {
+        let capacity = self.0.len();
+        for (j, v) in data.0.into_iter().enumerate() {
+            self.0[(ix + j) % capacity] = v;
+        }
+    }
+
+    fn sample(&self, ixs: &Vec<usize>) -> Self {
+        Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+    }
+}
+
+impl From<CartPoleObs> for CartPoleObsBatch {
+    fn from(obs: CartPoleObs) -> Self {
+        Self(vec![obs.0])
+    }
+}
+
+/// Batch of actions of [`CartPole`].
+#[derive(Clone, Debug)]
+pub struct CartPoleActBatch(pub Vec<i64>);
+
+impl BatchBase for CartPoleActBatch {
+    fn new(capacity: usize) -> Self {
+        Self(vec![0; capacity])
+    }
+
+    fn push(&mut self, ix: usize, data: Self) {
+        let capacity = self.0.len();
+        for (j, v) in data.0.into_iter().enumerate() {
+            self.0[(ix + j) % capacity] = v;
+        }
+    }
+
+    fn sample(&self, ixs: &Vec<usize>) -> Self {
+        Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+    }
+}
+
+impl From<CartPoleAct> for CartPoleActBatch {
+    fn from(act: CartPoleAct) -> Self {
+        Self(vec![act.0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cartpole() -> Result<()> {
+        let mut env = CartPole::build(&CartPoleConfig::default(), 42)?;
+
+        // Evaluation episodes are reproducible
+        let obs1 = env.reset_with_index(3)?;
+        let obs2 = env.reset_with_index(3)?;
+        assert_eq!(obs1, obs2);
+
+        // Pushing the cart to one side makes the pole fall
+        let mut n_steps = 0;
+        loop {
+            let (step, _) = env.step(&CartPoleAct(1));
+            n_steps += 1;
+            if step.is_done() {
+                assert_eq!(step.is_terminated[0], 1);
+                break;
+            }
+        }
+        assert!(n_steps < 50);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cartpole_golden() -> Result<()> {
+        // Episodes of alternating actions are truncated with `max_steps: 20`
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden/cartpole.yaml");
+        let config = CartPoleConfig { max_steps: 20 };
+        let actions = (0..60).map(|t| ((t / 3) % 2) as i64).collect::<Vec<_>>();
+        crate::assert_golden::<CartPole, _>(path, &config, 42, actions)
+    }
+
+    #[test]
+    fn test_cartpole_probe() -> Result<()> {
+        let mut env = CartPole::build(&CartPoleConfig::default(), 42)?;
+        let config = crate::ProbeConfig::default().n_random(5).obs_shape(vec![4]);
+        let report = crate::probe_env(&mut env, &config)?;
+        assert!(report.is_ok());
+        assert_eq!(report.n_steps, 7);
+
+        // Every observation has the unexpected shape
+        let config = config.obs_shape(vec![3]);
+        let report = crate::probe_env(&mut env, &config)?;
+        assert_eq!(report.issues.len(), 8);
+        Ok(())
+    }
+}
@@ -0,0 +1,408 @@
+//! Native implementation of a corridor gridworld.
+use crate::{
+    generic_replay_buffer::{BatchBase, SimpleReplayBuffer, SimpleStepProcessor},
+    record::{Record, RecordValue},
+    Act, ActValues, Env, Info, Obs, Step,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Observation of [`Corridor`], the position of the agent.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CorridorObs(pub usize);
+
+impl Obs for CorridorObs {
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn shape(&self) -> Option<Vec<usize>> {
+        Some(vec![1])
+    }
+}
+
+/// Action of [`Corridor`].
+///
+/// `0` moves the agent to the left and `1` moves it to the right.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CorridorAct(pub usize);
+
+impl Act for CorridorAct {
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn values(&self) -> Option<ActValues> {
+        Some(ActValues::Discrete(vec![self.0]))
+    }
+}
+
+impl From<usize> for CorridorAct {
+    fn from(a: usize) -> Self {
+        Self(a)
+    }
+}
+
+/// Info of [`Corridor`].
+pub struct CorridorInfo {
+    /// `true` if the agent reached the goal.
+    pub is_success: bool,
+}
+
+impl Info for CorridorInfo {}
+
+/// Configuration of [`Corridor`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CorridorConfig {
+    /// The number of cells of the corridor.
+    pub length: usize,
+
+    /// The maximum number of steps in an episode, after which the episode is truncated.
+    pub max_steps: usize,
+}
+
+impl Default for CorridorConfig {
+    fn default() -> Self {
+        Self {
+            length: 5,
+            max_steps: 20,
+        }
+    }
+}
+
+impl CorridorConfig {
+    /// Sets the number of cells of the corridor.
+    pub fn length(mut self, v: usize) -> Self {
+        self.length = v;
+        self
+    }
+
+    /// Sets the maximum number of steps in an episode.
+    pub fn max_steps(mut self, v: usize) -> Self {
+        self.max_steps = v;
+        self
+    }
+}
+
+/// Step processor for [`Corridor`].
+pub type CorridorStepProcessor = SimpleStepProcessor<Corridor, CorridorObsBatch, CorridorActBatch>;
+
+/// Replay buffer for [`Corridor`].
+pub type CorridorBuffer = SimpleReplayBuffer<CorridorObsBatch, CorridorActBatch>;
+
+/// A corridor gridworld, the smallest environment for testing the training loop.
+///
+/// The agent starts at the left end of a corridor of `length` cells and moves to the left or
+/// the right at each step. The episode terminates with reward 1 when the agent reaches the right
+/// end, and the reward is 0 otherwise. The dynamics are deterministic, so the optimal return is 1
+/// and a tabular agent learns it in a few hundred optimization steps.
+///
+/// # Collecting Transitions
+///
+/// [`CorridorStepProcessor`] converts steps of the environment into transitions,
+/// which are pushed into [`CorridorBuffer`].
+///
+/// ```
+/// use border_core::{
+///     agent_dev::{Corridor, CorridorAct, CorridorBuffer, CorridorConfig, CorridorStepProcessor},
+///     generic_replay_buffer::{SimpleReplayBufferConfig, SimpleStepProcessorConfig},
+///     Env, ExperienceBufferBase, ReplayBufferBase, StepProcessor, TransitionBatch,
+/// };
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let mut env = Corridor::build(&CorridorConfig::default().length(3), 0)?;
+/// let mut step_proc = CorridorStepProcessor::build(&SimpleStepProcessorConfig::default());
+/// let mut buffer = CorridorBuffer::build(&SimpleReplayBufferConfig::default().capacity(10));
+///
+/// step_proc.reset(env.reset(None)?);
+/// for _ in 0..2 {
+///     let (step, _) = env.step_with_reset(&CorridorAct(1));
+///     buffer.push(step_proc.process(step))?;
+/// }
+/// assert_eq!(buffer.len(), 2);
+///
+/// // The second transition reaches the goal
+/// let (obs, act, next_obs, reward, is_terminated, ..) = buffer.batch(10)?.unpack();
+/// let ix = next_obs.0.iter().position(|&pos| pos == 2).unwrap();
+/// assert_eq!((obs.0[ix], act.0[ix]), (1, 1));
+/// assert_eq!((reward[ix], is_terminated[ix]), (1.0, 1));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Training
+///
+/// The following tabular Q-learning agent is trained with [`Trainer`], which runs
+/// the environment, [`CorridorStepProcessor`], [`CorridorBuffer`] and [`DefaultEvaluator`].
+///
+/// ```
+/// use anyhow::Result;
+/// use border_core::{
+///     agent_dev::{
+///         Agent, Configurable, Corridor, CorridorAct, CorridorBuffer, CorridorConfig,
+///         CorridorObs, CorridorStepProcessor, Env, Policy, Record, RecordValue,
+///         ReplayBufferBase, TransitionBatch,
+///     },
+///     generic_replay_buffer::{SimpleReplayBufferConfig, SimpleStepProcessorConfig},
+///     record::{NullRecorder, Recorder},
+///     DefaultEvaluator, Evaluator, StepProcessor, Trainer, TrainerConfig,
+/// };
+/// use rand::{rngs::SmallRng, Rng, SeedableRng};
+/// use std::path::{Path, PathBuf};
+///
+/// struct QAgent {
+///     q: Vec<[f32; 2]>,
+///     train: bool,
+///     rng: SmallRng,
+/// }
+///
+/// impl Policy<Corridor> for QAgent {
+///     fn sample(&mut self, obs: &CorridorObs) -> CorridorAct {
+///         let q = self.q[obs.0];
+///         match self.train && self.rng.gen::<f32>() < 0.3 {
+///             true => CorridorAct(self.rng.gen_range(0..2)),
+///             false => CorridorAct((q[1] >= q[0]) as usize),
+///         }
+///     }
+/// }
+///
+/// impl Configurable for QAgent {
+///     type Config = usize;
+///
+///     fn build(length: usize) -> Self {
+///         Self { q: vec![[0.0; 2]; length], train: false, rng: SmallRng::seed_from_u64(0) }
+///     }
+/// }
+///
+/// impl Agent<Corridor, CorridorBuffer> for QAgent {
+///     fn train(&mut self) {
+///         self.train = true;
+///     }
+///
+///     fn eval(&mut self) {
+///         self.train = false;
+///     }
+///
+///     fn is_train(&self) -> bool {
+///         self.train
+///     }
+///
+///     fn opt_with_record(&mut self, buffer: &mut CorridorBuffer) -> Record {
+///         let batch = buffer.batch(16).unwrap();
+///         let (obs, act, next_obs, reward, is_terminated, ..) = batch.unpack();
+///         for i in 0..reward.len() {
+///             let next_q = self.q[next_obs.0[i]];
+///             let not_done = (1 - is_terminated[i]) as f32;
+///             let target = reward[i] + 0.9 * not_done * next_q[0].max(next_q[1]);
+///             let q = &mut self.q[obs.0[i]][act.0[i]];
+///             *q += 0.5 * (target - *q);
+///         }
+///         Record::from_slice(&[("q_start", RecordValue::Scalar(self.q[0][1]))])
+///     }
+///
+///     fn save_params(&self, _path: &Path) -> Result<Vec<PathBuf>> {
+///         Ok(vec![])
+///     }
+///
+///     fn load_params(&mut self, _path: &Path) -> Result<()> {
+///         Ok(())
+///     }
+///
+///     fn as_any_ref(&self) -> &dyn std::any::Any {
+///         self
+///     }
+///
+///     fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+///         self
+///     }
+/// }
+///
+/// # fn main() -> Result<()> {
+/// let env_config = CorridorConfig::default();
+/// let env = Corridor::build(&env_config, 0)?;
+/// let step_proc = CorridorStepProcessor::build(&SimpleStepProcessorConfig::default());
+/// let mut buffer = CorridorBuffer::build(&SimpleReplayBufferConfig::default().capacity(1000));
+/// let mut agent: Box<dyn Agent<Corridor, CorridorBuffer>> =
+///     Box::new(QAgent::build(env_config.length));
+/// let mut recorder: Box<dyn Recorder<Corridor, CorridorBuffer>> = Box::new(NullRecorder::new());
+/// let mut evaluator = DefaultEvaluator::<Corridor>::new(&env_config, 1, 3)?;
+/// let mut trainer = Trainer::build(
+///     TrainerConfig::default()
+///         .max_opts(500)
+///         .warmup_period(50)
+///         .eval_interval(100),
+/// );
+/// trainer.train(env, step_proc, &mut agent, &mut buffer, &mut recorder, &mut evaluator)?;
+///
+/// // The trained agent walks straight to the goal
+/// agent.eval();
+/// let (eval_return, _) = evaluator.evaluate(&mut agent)?;
+/// assert_eq!(eval_return, 1.0);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Trainer`]: crate::Trainer
+/// [`DefaultEvaluator`]: crate::DefaultEvaluator
+pub struct Corridor {
+    pos: usize,
+    n_steps: usize,
+    length: usize,
+    max_steps: usize,
+}
+
+impl Env for Corridor {
+    type Config = CorridorConfig;
+    type Obs = CorridorObs;
+    type Act = CorridorAct;
+    type Info = CorridorInfo;
+
+    fn build(config: &Self::Config, _seed: i64) -> Result<Self> {
+        if config.length < 2 {
+            anyhow::bail!(
+                "Length of the corridor must be at least 2, got {}",
+                config.length
+            );
+        }
+        Ok(Self {
+            pos: 0,
+            n_steps: 0,
+            length: config.length,
+            max_steps: config.max_steps,
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.pos = match a.0 {
+            0 => self.pos.saturating_sub(1),
+            _ => (self.pos + 1).min(self.length - 1),
+        };
+        self.n_steps += 1;
+
+        let is_terminated = self.pos == self.length - 1;
+        let is_truncated = !is_terminated && self.n_steps >= self.max_steps;
+        let step = Step::new(
+            CorridorObs(self.pos),
+            a.clone(),
+            vec![is_terminated as i32 as f32],
+            vec![is_terminated as i8],
+            vec![is_truncated as i8],
+            CorridorInfo {
+                is_success: is_terminated,
+            },
+            None,
+        );
+        let record = match is_terminated || is_truncated {
+            true => {
+                Record::from_slice(&[("episode_length", RecordValue::Scalar(self.n_steps as f32))])
+            }
+            false => Record::empty(),
+        };
+
+        (step, record)
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        self.pos = 0;
+        self.n_steps = 0;
+        Ok(CorridorObs(self.pos))
+    }
+
+    /// Returns both actions, as the dynamics are deterministic.
+    fn probe_actions(&mut self, _n_random: usize) -> Vec<Self::Act> {
+        vec![CorridorAct(0), CorridorAct(1)]
+    }
+
+    /// Resets the environment, as every episode starts from the left end.
+    fn reset_with_index(&mut self, _ix: usize) -> Result<Self::Obs> {
+        self.reset(None)
+    }
+}
+
+/// Batch of observations of [`Corridor`].
+#[derive(Clone, Debug)]
+pub struct CorridorObsBatch(pub Vec<usize>);
+
+impl BatchBase for CorridorObsBatch {
+    fn new(capacity: usize) -> Self {
+        Self(vec![0; capacity])
+    }
+
+    fn push(&mut self, ix: usize, data: Self) {
+        let capacity = self.0.len();
+        for (j, v) in data.0.into_iter().enumerate() {
+            self.0[(ix + j) % capacity] = v;
+        }
+    }
+
+    fn sample(&self, ixs: &Vec<usize>) -> Self {
+        Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+    }
+}
+
+impl From<CorridorObs> for CorridorObsBatch {
+    fn from(obs: CorridorObs) -> Self {
+        Self(vec![obs.0])
+    }
+}
+
+/// Batch of actions of [`Corridor`].
+#[derive(Clone, Debug)]
+pub struct CorridorActBatch(pub Vec<usize>);
+
+impl BatchBase for CorridorActBatch {
+    fn new(capacity: usize) -> Self {
+        Self(vec![0; capacity])
+    }
+
+    fn push(&mut self, ix: usize, data: Self) {
+        let capacity = self.0.len();
+        for (j, v) in data.0.into_iter().enumerate() {
+            self.0[(ix + j) % capacity] = v;
+        }
+    }
+
+    fn sample(&self, ixs: &Vec<usize>) -> Self {
+        Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+    }
+}
+
+impl From<CorridorAct> for CorridorActBatch {
+    fn from(act: CorridorAct) -> Self {
+        Self(vec![act.0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_corridor() -> Result<()> {
+        let mut env = Corridor::build(&CorridorConfig::default().max_steps(3), 0)?;
+
+        // The agent stays at the left end and the episode is truncated
+        env.reset(None)?;
+        for t in 0..3 {
+            let (step, _) = env.step(&CorridorAct(0));
+            assert_eq!(step.obs, CorridorObs(0));
+            assert_eq!(step.is_truncated[0], (t == 2) as i8);
+        }
+
+        // The agent reaches the goal at the right end
+        let mut env = Corridor::build(&CorridorConfig::default(), 0)?;
+        env.reset(None)?;
+        let steps = (0..4)
+            .map(|_| env.step(&CorridorAct(1)).0)
+            .collect::<Vec<_>>();
+        assert!(steps[..3]
+            .iter()
+            .all(|s| !s.is_done() && s.reward[0] == 0.0));
+        assert_eq!(steps[3].obs, CorridorObs(4));
+        assert_eq!((steps[3].reward[0], steps[3].is_terminated[0]), (1.0, 1));
+        assert!(steps[3].info.is_success);
+
+        assert!(Corridor::build(&CorridorConfig::default().length(1), 0).is_err());
+        Ok(())
+    }
+}
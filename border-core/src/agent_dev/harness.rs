@@ -0,0 +1,196 @@
+//! Test harness of agents on the native CartPole environment.
+use super::cartpole::{CartPole, CartPoleActBatch, CartPoleConfig, CartPoleObsBatch};
+use crate::{
+    generic_replay_buffer::{
+        SimpleReplayBuffer, SimpleReplayBufferConfig, SimpleStepProcessor,
+        SimpleStepProcessorConfig,
+    },
+    record::{NullRecorder, Record, Recorder},
+    Agent, DefaultEvaluator, Env, Evaluator, ExperienceBufferBase, ReplayBufferBase, StepProcessor,
+    Trainer, TrainerConfig,
+};
+use anyhow::{ensure, Result};
+use std::path::PathBuf;
+
+/// Replay buffer used in [`run_cartpole()`].
+pub type CartPoleBuffer = SimpleReplayBuffer<CartPoleObsBatch, CartPoleActBatch>;
+
+/// Configuration of [`run_cartpole()`].
+#[derive(Clone, Debug)]
+pub struct HarnessConfig {
+    /// Configuration of the environment.
+    pub env_config: CartPoleConfig,
+
+    /// The number of optimization steps.
+    pub max_opts: usize,
+
+    /// The number of environment steps before the first optimization step.
+    pub warmup_period: usize,
+
+    /// Capacity of the replay buffer.
+    pub capacity: usize,
+
+    /// The number of evaluation episodes.
+    pub n_eval_episodes: usize,
+
+    /// Random seed of the environments and the replay buffer.
+    pub seed: i64,
+
+    /// Directory where parameters of the agent are saved and loaded.
+    /// If `None`, saving and loading are not tested.
+    pub model_dir: Option<PathBuf>,
+}
+
+impl Default for HarnessConfig {
+    fn default() -> Self {
+        Self {
+            env_config: CartPoleConfig::default(),
+            max_opts: 1000,
+            warmup_period: 100,
+            capacity: 10000,
+            n_eval_episodes: 5,
+            seed: 42,
+            model_dir: None,
+        }
+    }
+}
+
+impl HarnessConfig {
+    /// Sets the number of optimization steps.
+    pub fn max_opts(mut self, v: usize) -> Self {
+        self.max_opts = v;
+        self
+    }
+
+    /// Sets the number of environment steps before the first optimization step.
+    pub fn warmup_period(mut self, v: usize) -> Self {
+        self.warmup_period = v;
+        self
+    }
+
+    /// Sets the capacity of the replay buffer.
+    pub fn capacity(mut self, v: usize) -> Self {
+        self.capacity = v;
+        self
+    }
+
+    /// Sets the number of evaluation episodes.
+    pub fn n_eval_episodes(mut self, v: usize) -> Self {
+        self.n_eval_episodes = v;
+        self
+    }
+
+    /// Sets the random seed.
+    pub fn seed(mut self, v: i64) -> Self {
+        self.seed = v;
+        self
+    }
+
+    /// Sets the directory where parameters of the agent are saved and loaded.
+    pub fn model_dir(mut self, v: Option<PathBuf>) -> Self {
+        self.model_dir = v;
+        self
+    }
+}
+
+/// Result of [`run_cartpole()`].
+#[derive(Debug)]
+pub struct HarnessReport {
+    /// Average return of evaluation episodes after training.
+    pub eval_return: f32,
+
+    /// Average return of evaluation episodes after loading the saved parameters.
+    pub eval_return_loaded: Option<f32>,
+
+    /// Record of the last evaluation.
+    pub record: Record,
+}
+
+/// Trains an agent on [`CartPole`] and checks that it behaves as expected by [`Trainer`].
+///
+/// The harness does the following:
+/// 1. Trains the agent for `max_opts` optimization steps with [`Trainer`]
+/// 2. Checks that the agent can be switched between training and evaluation modes
+/// 3. Evaluates the agent with [`DefaultEvaluator`]
+/// 4. If `model_dir` is given, saves the parameters, loads them and evaluates the agent again
+///
+/// It returns an error if any of these steps fails. The returns of evaluation are not checked,
+/// as they depend on the algorithm and its hyperparameters.
+pub fn run_cartpole(
+    agent: &mut Box<dyn Agent<CartPole, CartPoleBuffer>>,
+    config: &HarnessConfig,
+) -> Result<HarnessReport> {
+    let env = CartPole::build(&config.env_config, config.seed)?;
+    let step_proc = SimpleStepProcessor::<CartPole, CartPoleObsBatch, CartPoleActBatch>::build(
+        &SimpleStepProcessorConfig::default(),
+    );
+    let mut buffer = CartPoleBuffer::build(
+        &SimpleReplayBufferConfig::default()
+            .capacity(config.capacity)
+            .seed(config.seed as _),
+    );
+    let mut recorder: Box<dyn Recorder<CartPole, CartPoleBuffer>> = Box::new(NullRecorder::new());
+    let mut evaluator = DefaultEvaluator::<CartPole>::new(
+        &config.env_config,
+        config.seed + 1,
+        config.n_eval_episodes,
+    )?;
+    let mut trainer = Trainer::build(
+        TrainerConfig::default()
+            .max_opts(config.max_opts)
+            .warmup_period(config.warmup_period)
+            .eval_interval(usize::MAX),
+    );
+
+    // Training
+    trainer.train(
+        env,
+        step_proc,
+        agent,
+        &mut buffer,
+        &mut recorder,
+        &mut evaluator,
+    )?;
+    ensure!(
+        buffer.len() > 0,
+        "No transition was pushed into the replay buffer"
+    );
+
+    // Modes
+    agent.train();
+    ensure!(
+        agent.is_train(),
+        "Agent::is_train() returns false after Agent::train()"
+    );
+    agent.eval();
+    ensure!(
+        !agent.is_train(),
+        "Agent::is_train() returns true after Agent::eval()"
+    );
+
+    // Evaluation
+    let (eval_return, record) = evaluator.evaluate(agent)?;
+    ensure!(eval_return.is_finite(), "Evaluation return is not finite");
+
+    // Saving and loading
+    let (eval_return_loaded, record) = match &config.model_dir {
+        None => (None, record),
+        Some(model_dir) => {
+            let files = agent.save_params(model_dir)?;
+            ensure!(
+                files.iter().all(|f| f.exists()),
+                "Agent::save_params() returns files that do not exist"
+            );
+            agent.load_params(model_dir)?;
+            let (eval_return, record) = evaluator.evaluate(agent)?;
+            ensure!(eval_return.is_finite(), "Evaluation return is not finite");
+            (Some(eval_return), record)
+        }
+    };
+
+    Ok(HarnessReport {
+        eval_return,
+        eval_return_loaded,
+        record,
+    })
+}
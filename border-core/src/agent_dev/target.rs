@@ -0,0 +1,70 @@
+//! Utilities for target networks.
+use serde::{Deserialize, Serialize};
+
+/// Updates parameters of a target network towards those of the source network.
+///
+/// Each parameter is updated as `tgt = tau * src + (1 - tau) * tgt`. With `tau = 1`,
+/// the source parameters are copied into the target.
+pub fn polyak_update(tgt: &mut [f32], src: &[f32], tau: f32) {
+    assert_eq!(tgt.len(), src.len());
+    for (t, s) in tgt.iter_mut().zip(src.iter()) {
+        *t = tau * s + (1.0 - tau) * *t;
+    }
+}
+
+/// Decides when target networks are updated.
+///
+/// Call [`TargetUpdater::step()`] once per optimization step. It returns the coefficient
+/// of the soft update every `interval` steps and `None` otherwise. The coefficient can be
+/// given to [`polyak_update()`] or the corresponding function of a deep learning backend.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct TargetUpdater {
+    interval: usize,
+    tau: f32,
+    counter: usize,
+}
+
+impl TargetUpdater {
+    /// Constructs an updater.
+    ///
+    /// * `interval` - The number of optimization steps between updates
+    /// * `tau` - The coefficient of the soft update, `1.0` for hard updates
+    pub fn new(interval: usize, tau: f32) -> Self {
+        assert!(interval > 0, "interval must be positive");
+        Self {
+            interval,
+            tau,
+            counter: 0,
+        }
+    }
+
+    /// Counts an optimization step and returns the coefficient if the target should be updated.
+    pub fn step(&mut self) -> Option<f32> {
+        self.counter += 1;
+        if self.counter == self.interval {
+            self.counter = 0;
+            Some(self.tau)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_updater() {
+        let mut updater = TargetUpdater::new(2, 0.5);
+        let mut tgt = vec![0.0, 2.0];
+        let src = vec![2.0, 2.0];
+
+        for _ in 0..4 {
+            if let Some(tau) = updater.step() {
+                polyak_update(&mut tgt, &src, tau);
+            }
+        }
+        assert_eq!(tgt, vec![1.5, 2.0]);
+    }
+}
@@ -16,7 +16,7 @@ pub use batch::TransitionBatch;
 pub use env::Env;
 pub use policy::{Configurable, Policy};
 pub use replay_buffer::{ExperienceBufferBase, NullReplayBuffer, ReplayBufferBase};
-use std::fmt::Debug;
+use std::{convert::TryFrom, fmt::Debug};
 pub use step::{Info, Step, StepProcessor};
 
 /// A trait representing observations from an environment.
@@ -57,9 +57,25 @@ pub trait Obs: Clone + Debug {
     ///
     /// # Returns
     ///
-    /// The number of observations. Currently, this should always return 1
-    /// as vectorized environments are not supported.
+    /// The number of observations, which is the number of environments
+    /// for vectorized environments and 1 otherwise.
     fn len(&self) -> usize;
+
+    /// Returns the shape of an observation excluding the batch dimension, if known.
+    ///
+    /// It is checked by [`probe_env()`](crate::probe_env). The default implementation
+    /// returns `None`.
+    fn shape(&self) -> Option<Vec<usize>> {
+        None
+    }
+
+    /// Returns `false` if the observation has NaN or infinite values.
+    ///
+    /// It is checked by [`probe_env()`](crate::probe_env). The default implementation
+    /// returns `true`.
+    fn is_finite(&self) -> bool {
+        true
+    }
 }
 
 /// A trait representing actions that can be taken in an environment.
@@ -98,4 +114,39 @@ pub trait Act: Clone + Debug {
     fn len(&self) -> usize {
         unimplemented!();
     }
+
+    /// Returns the values of the actions for monitoring their distribution.
+    ///
+    /// It is used, e.g., by [`SimpleStepProcessor`] to log the empirical distribution of
+    /// executed actions. The default implementation returns `None`, for which no distribution
+    /// is logged.
+    ///
+    /// [`SimpleStepProcessor`]: crate::generic_replay_buffer::SimpleStepProcessor
+    fn values(&self) -> Option<ActValues> {
+        None
+    }
+}
+
+/// Values of actions returned by [`Act::values()`].
+///
+/// They have an element for each environment when the environments are vectorized.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActValues {
+    /// Indices of discrete actions.
+    Discrete(Vec<usize>),
+
+    /// Continuous actions, each of which is a vector.
+    Continuous(Vec<Vec<f32>>),
+}
+
+impl ActValues {
+    /// Creates [`ActValues::Discrete`] from indices of discrete actions given as integers.
+    ///
+    /// Returns `None` if any of the indices is negative, as it is not a valid action.
+    pub fn from_indices(ixs: impl IntoIterator<Item = i64>) -> Option<Self> {
+        ixs.into_iter()
+            .map(|ix| usize::try_from(ix).ok())
+            .collect::<Option<Vec<_>>>()
+            .map(Self::Discrete)
+    }
 }
@@ -80,6 +80,80 @@ pub trait Agent<E: Env, R: ReplayBufferBase>: Policy<E> {
         unimplemented!();
     }
 
+    /// Performs a single optimization step, returning an error instead of panicking.
+    ///
+    /// [`Trainer`] calls this method, and saves the model and stops training if it fails,
+    /// e.g., by out of memory of a GPU. The default implementation calls [`opt`].
+    ///
+    /// [`Trainer`]: crate::Trainer
+    /// [`opt`]: Agent::opt
+    fn try_opt(&mut self, buffer: &mut R) -> Result<()> {
+        self.opt(buffer);
+        Ok(())
+    }
+
+    /// Performs an optimization step and returns training metrics, returning an error
+    /// instead of panicking.
+    ///
+    /// See [`try_opt`] for the usage. The default implementation calls [`opt_with_record`].
+    ///
+    /// [`try_opt`]: Agent::try_opt
+    /// [`opt_with_record`]: Agent::opt_with_record
+    fn try_opt_with_record(&mut self, buffer: &mut R) -> Result<Record> {
+        Ok(self.opt_with_record(buffer))
+    }
+
+    /// Notifies the agent that an episode has ended in the training environment.
+    ///
+    /// This method is called by [`Sampler`] after the last step of each episode.
+    /// Agents with per-episode state, such as bootstrapped DQN choosing the head used
+    /// for exploration in each episode, can override it. The default implementation
+    /// does nothing.
+    ///
+    /// [`Sampler`]: crate::Sampler
+    fn on_episode_end(&mut self) {}
+
+    /// Returns the outputs of the agent's networks for an observation.
+    ///
+    /// This method is called by [`ProbeSetEvaluator`] for each observation of a fixed probe set,
+    /// so that the outputs, e.g., action values of DQN or action probabilities of PPO, can be
+    /// logged over the course of training. Values are typically [`RecordValue::Array1`] with
+    /// an element for each action. The default implementation returns an empty record.
+    ///
+    /// [`ProbeSetEvaluator`]: crate::ProbeSetEvaluator
+    /// [`RecordValue::Array1`]: crate::record::RecordValue::Array1
+    #[allow(unused_variables)]
+    fn probe(&mut self, obs: &E::Obs) -> Record {
+        Record::empty()
+    }
+
+    /// Computes the priorities of the transitions in a batch for prioritized experience replay.
+    ///
+    /// The priorities are typically absolute TD errors with the current model, the same as
+    /// those given to [`ReplayBufferBase::update_priority()`] in optimization steps.
+    /// This method is called by actors in asynchronous training to give initial priorities
+    /// to the transitions they collect. It must not change the state of the agent used in
+    /// training, e.g., random number generators. The default implementation returns `None`,
+    /// in which case the transitions get the maximum priority in the replay buffer.
+    #[allow(unused_variables)]
+    fn priorities(&mut self, batch: R::Batch) -> Result<Option<Vec<f32>>> {
+        Ok(None)
+    }
+
+    /// Sets a hyperparameter during training.
+    ///
+    /// This method is called by [`Trainer`] when a hyperparameter is changed in the control file
+    /// of hot-reload. Names are taken from [`HOT_RELOADABLE`], e.g., `lr` and `eps`.
+    /// Returns `true` if the hyperparameter is applied. The default implementation
+    /// supports no hyperparameters and returns `false`.
+    ///
+    /// [`Trainer`]: crate::Trainer
+    /// [`HOT_RELOADABLE`]: crate::HOT_RELOADABLE
+    #[allow(unused_variables)]
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool {
+        false
+    }
+
     /// Saves the agent's parameters to the specified directory.
     ///
     /// This method serializes the agent's current state (e.g., neural network weights,
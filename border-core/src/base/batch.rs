@@ -93,4 +93,22 @@ pub trait TransitionBatch {
     /// This provides efficient access to the actions without unpacking the
     /// entire batch.
     fn act(&self) -> &Self::ActBatch;
+
+    /// Returns bootstrap masks of the transitions, if stored in the replay buffer.
+    ///
+    /// The masks are flattened in the shape of `[batch_size, n_heads]`, where `n_heads` is
+    /// the number of heads of an ensemble agent like bootstrapped DQN. The value is 1 if
+    /// the transition is used to train the head, 0 otherwise. This method must be called
+    /// before [`TransitionBatch::unpack()`].
+    fn bootstrap_mask(&self) -> Option<&Vec<f32>> {
+        None
+    }
+
+    /// Returns Monte Carlo returns of the transitions, if computed by the replay buffer.
+    ///
+    /// The return of a transition is the discounted sum of the rewards from the transition
+    /// to the end of its episode. This method must be called before [`TransitionBatch::unpack()`].
+    fn mc_return(&self) -> Option<&Vec<f32>> {
+        None
+    }
 }
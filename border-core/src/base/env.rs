@@ -100,6 +100,19 @@ pub trait Env {
     where
         Self: Sized;
 
+    /// Performs a step, returning an error instead of panicking.
+    ///
+    /// Environments that can fail in a step, e.g., those accessed over a network, should
+    /// override it. The default implementation calls [`step`].
+    ///
+    /// [`step`]: Env::step
+    fn try_step(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)>
+    where
+        Self: Sized,
+    {
+        Ok(self.step(a))
+    }
+
     /// Resets the environment to its initial state.
     ///
     /// This method resets the environment when:
@@ -112,17 +125,54 @@ pub trait Env {
     ///
     /// # Note
     ///
-    /// While the interface supports vectorized environments through `is_done`,
-    /// the current implementation only supports single environments.
-    /// Therefore, `is_done.len()` is expected to be 1.
+    /// For single environments, `is_done` is expected to be `None`.
+    /// Vectorized environments reset the environments where `is_done[i] == 1`,
+    /// or all environments if `is_done` is `None`, and return the observations
+    /// of all environments.
     fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs>;
 
+    /// Returns the number of environments.
+    ///
+    /// Vectorized environments return the number of environments running in parallel,
+    /// where observations, rewards and flags of [`Step`] have an element for each environment.
+    /// The default implementation returns 1.
+    fn n_envs(&self) -> usize {
+        1
+    }
+
+    /// Returns actions to probe the environment with [`probe_env()`] before training.
+    ///
+    /// They should cover the boundaries of the action space, e.g., every discrete action or
+    /// the corners of the bounds of continuous actions, followed by `n_random` random actions.
+    /// The default implementation returns no action, so that only the observation after reset
+    /// is checked.
+    ///
+    /// [`probe_env()`]: crate::probe_env
+    fn probe_actions(&mut self, _n_random: usize) -> Vec<Self::Act> {
+        vec![]
+    }
+
+    /// Releases resources of the environment, such as render windows and subprocesses.
+    ///
+    /// It is called by [`Trainer`] at the end of training. The environment should not be used
+    /// after it is closed. Environments releasing resources should also do it when dropped,
+    /// so calling this method more than once must be safe. The default implementation does nothing.
+    ///
+    /// [`Trainer`]: crate::Trainer
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+
     /// Performs a step and automatically resets the environment if the episode ends.
     ///
     /// This is a convenience method that combines [`step`] and [`reset`] operations.
     /// If the step results in episode termination, the environment is automatically
     /// reset and the initial observation is included in the returned step.
     ///
+    /// The default implementation supports only single environments. Vectorized environments
+    /// should override it, so that the environments where the episode has ended are reset
+    /// and `init_obs` holds the observations of all environments after the reset.
+    ///
     /// # Arguments
     ///
     /// * `a` - The action to apply to the environment
@@ -159,6 +209,22 @@ pub trait Env {
         (step, record)
     }
 
+    /// Performs a step with [`step_with_reset`], returning an error instead of panicking.
+    ///
+    /// It is used by [`Sampler`] in training. Environments that can fail in a step should
+    /// override it as well as [`try_step`], and wrappers of environments should forward it.
+    /// The default implementation calls [`step_with_reset`].
+    ///
+    /// [`step_with_reset`]: Env::step_with_reset
+    /// [`try_step`]: Env::try_step
+    /// [`Sampler`]: crate::Sampler
+    fn try_step_with_reset(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)>
+    where
+        Self: Sized,
+    {
+        Ok(self.step_with_reset(a))
+    }
+
     /// Resets the environment with a specific index.
     ///
     /// This method is primarily used during evaluation to control the initial state
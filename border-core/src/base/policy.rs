@@ -60,6 +60,15 @@ pub trait Policy<E: Env> {
     ///
     /// An action to be taken in the environment
     fn sample(&mut self, obs: &E::Obs) -> E::Act;
+
+    /// Samples an action, returning an error instead of panicking.
+    ///
+    /// The default implementation calls [`sample`].
+    ///
+    /// [`sample`]: Policy::sample
+    fn try_sample(&mut self, obs: &E::Obs) -> Result<E::Act> {
+        Ok(self.sample(obs))
+    }
 }
 
 /// A trait for objects that can be configured and built from configuration files.
@@ -4,6 +4,7 @@
 //! Replay buffers are essential components that store and sample experiences (transitions)
 //! for training agents, enabling more efficient learning through experience replay.
 
+use crate::record::Record;
 use anyhow::Result;
 
 /// Interface for buffers that store experiences from environments.
@@ -59,6 +60,27 @@ pub trait ExperienceBufferBase {
     ///
     /// The number of experiences currently stored
     fn len(&self) -> usize;
+
+    /// Returns a record of the state of the buffer, like its memory usage, and statistics of
+    /// the batches sampled since the last call, then resets the statistics of the batches.
+    ///
+    /// The record is written by the trainer along with the computation cost to detect
+    /// problems like stale or skewed transitions in the buffer.
+    /// The default implementation returns an empty record.
+    fn record(&mut self) -> Record {
+        Record::empty()
+    }
+
+    /// Pushes a new experience into the buffer with the priorities of the transitions in it.
+    ///
+    /// The priorities are TD errors of the transitions, as given to
+    /// [`ReplayBufferBase::update_priority()`], computed when the experience was collected,
+    /// e.g., by actors in asynchronous training. The default implementation ignores them
+    /// and calls [`ExperienceBufferBase::push()`].
+    #[allow(unused_variables)]
+    fn push_with_priorities(&mut self, tr: Self::Item, priorities: &[f32]) -> Result<()> {
+        self.push(tr)
+    }
 }
 
 /// Interface for replay buffers that generate batches for training.
@@ -5,6 +5,7 @@
 //! observations, actions, rewards, and episode termination information.
 
 use super::Env;
+use crate::record::Record;
 
 /// Additional information that can be associated with environment steps.
 ///
@@ -23,10 +24,36 @@ use super::Env;
 ///
 /// impl Info for CustomInfo {}
 /// ```
-pub trait Info {}
+pub trait Info {
+    /// Returns flags indicating the loss of a life, one for each environment.
+    ///
+    /// In games with multiple lives, such as Atari games, the loss of a life ends a segment
+    /// of an episode while the episode itself continues. Step processors can treat it as
+    /// termination for bootstrapping, as in the training setup of DQN on Atari games.
+    /// The default implementation returns `None`, meaning that the environment has no lives.
+    fn is_life_lost(&self) -> Option<Vec<i8>> {
+        None
+    }
+}
 
 impl Info for () {}
 
+impl<T: Info> Info for Vec<T> {
+    /// Concatenates the flags of the elements, where elements without lives have no life loss.
+    fn is_life_lost(&self) -> Option<Vec<i8>> {
+        let flags = self.iter().map(|i| i.is_life_lost()).collect::<Vec<_>>();
+        match flags.iter().any(|f| f.is_some()) {
+            true => Some(
+                flags
+                    .into_iter()
+                    .flat_map(|f| f.unwrap_or_else(|| vec![0]))
+                    .collect(),
+            ),
+            false => None,
+        }
+    }
+}
+
 /// Represents a single step in the environment, containing the action taken,
 /// the resulting observation, reward, and episode status.
 ///
@@ -129,12 +156,31 @@ impl<E: Env> Step<E> {
 
     /// Checks if the episode has ended, either through termination or truncation.
     ///
+    /// For vectorized environments, it checks if the episode has ended in any of the environments.
+    ///
     /// # Returns
     ///
     /// `true` if the episode has ended, `false` otherwise
     #[inline]
     pub fn is_done(&self) -> bool {
-        self.is_terminated[0] == 1 || self.is_truncated[0] == 1
+        self.is_terminated
+            .iter()
+            .zip(self.is_truncated.iter())
+            .any(|(t, u)| *t == 1 || *u == 1)
+    }
+
+    /// Returns flags indicating if the episode has ended in each environment.
+    ///
+    /// The flags can be given to [`Env::reset()`] of vectorized environments
+    /// to reset the environments where the episode has ended.
+    ///
+    /// [`Env::reset()`]: crate::Env::reset
+    pub fn is_done_vec(&self) -> Vec<i8> {
+        self.is_terminated
+            .iter()
+            .zip(self.is_truncated.iter())
+            .map(|(t, u)| t | u)
+            .collect()
     }
 }
 
@@ -223,4 +269,16 @@ pub trait StepProcessor<E: Env> {
     ///
     /// An item ready to be stored in a replay buffer
     fn process(&mut self, step: Step<E>) -> Self::Output;
+
+    /// Returns the record of statistics of the steps processed since the last call,
+    /// e.g., the empirical distribution of actions.
+    ///
+    /// It is called by [`Trainer`] before records are flushed, so the statistics cover
+    /// the steps in the last interval of recording. The default implementation returns
+    /// an empty record.
+    ///
+    /// [`Trainer`]: crate::Trainer
+    fn record(&mut self) -> Record {
+        Record::empty()
+    }
 }
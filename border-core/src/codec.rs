@@ -0,0 +1,178 @@
+//! Compression codecs for observations and other byte sequences.
+//!
+//! A [`Codec`] compresses and decompresses byte slices to reduce the disk footprint of large
+//! observations, such as Atari frames. Chunks of persisted replay buffers are compressed with
+//! the codec given by their [`ReplayBufferSchema`].
+//!
+//! The following codecs are provided:
+//! - [`NoCodec`] - Stores bytes as they are
+//! - [`Lz4Codec`] - LZ4 block compression, fast with a moderate ratio (feature `lz4`)
+//! - [`ZstdCodec`] - Zstandard compression, slower with a better ratio (feature `zstd`)
+//!
+//! [`CodecConfig`] selects a codec in configuration files. The tradeoffs between throughput
+//! and compression ratio can be measured with `cargo bench -p border-core --features lz4,zstd`.
+//!
+//! [`ReplayBufferSchema`]: crate::generic_replay_buffer::ReplayBufferSchema
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// Interface of compression codecs.
+///
+/// Implementations must be lossless: `decompress(compress(data))` returns `data`.
+pub trait Codec: Send + Sync {
+    /// Name of the codec.
+    fn name(&self) -> &'static str;
+
+    /// Compresses a byte slice.
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    /// Decompresses a byte slice compressed with [`Codec::compress()`].
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>>;
+}
+
+/// A codec storing bytes without compression.
+#[derive(Debug, Clone, Default)]
+pub struct NoCodec;
+
+impl Codec for NoCodec {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(data.to_vec())
+    }
+}
+
+/// LZ4 block compression.
+///
+/// The length of the original data is prepended to the compressed bytes.
+#[cfg(feature = "lz4")]
+#[derive(Debug, Clone, Default)]
+pub struct Lz4Codec;
+
+#[cfg(feature = "lz4")]
+impl Codec for Lz4Codec {
+    fn name(&self) -> &'static str {
+        "lz4"
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(lz4_flex::compress_prepend_size(data))
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(lz4_flex::decompress_size_prepended(data)?)
+    }
+}
+
+/// Zstandard compression.
+#[cfg(feature = "zstd")]
+#[derive(Debug, Clone)]
+pub struct ZstdCodec {
+    level: i32,
+}
+
+#[cfg(feature = "zstd")]
+impl Default for ZstdCodec {
+    fn default() -> Self {
+        Self {
+            level: zstd::DEFAULT_COMPRESSION_LEVEL,
+        }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl ZstdCodec {
+    /// Constructs a codec with the given compression level.
+    ///
+    /// Higher levels give better ratios at the cost of throughput.
+    pub fn new(level: i32) -> Self {
+        Self { level }
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl Codec for ZstdCodec {
+    fn name(&self) -> &'static str {
+        "zstd"
+    }
+
+    fn compress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(zstd::bulk::compress(data, self.level)?)
+    }
+
+    fn decompress(&self, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(zstd::stream::decode_all(data)?)
+    }
+}
+
+/// Configuration of a codec.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub enum CodecConfig {
+    /// No compression.
+    #[default]
+    None,
+
+    /// LZ4 block compression.
+    Lz4,
+
+    /// Zstandard compression with a compression level.
+    Zstd(i32),
+}
+
+impl CodecConfig {
+    /// Builds the codec.
+    ///
+    /// Returns an error if the codec is disabled by feature flags.
+    pub fn build(&self) -> Result<Box<dyn Codec>> {
+        match self {
+            Self::None => Ok(Box::new(NoCodec)),
+            #[cfg(feature = "lz4")]
+            Self::Lz4 => Ok(Box::new(Lz4Codec)),
+            #[cfg(feature = "zstd")]
+            Self::Zstd(level) => Ok(Box::new(ZstdCodec::new(*level))),
+            #[allow(unreachable_patterns)]
+            codec => Err(anyhow::anyhow!(
+                "{:?} is not available, enable the corresponding feature of border-core",
+                codec
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bytes resembling an image with flat regions.
+    fn frame() -> Vec<u8> {
+        (0..84 * 84).map(|i| ((i / 84) / 8 * 16) as u8).collect()
+    }
+
+    #[test]
+    fn test_roundtrip() -> Result<()> {
+        let data = frame();
+        let mut configs = vec![CodecConfig::None];
+        if cfg!(feature = "lz4") {
+            configs.push(CodecConfig::Lz4);
+        }
+        if cfg!(feature = "zstd") {
+            configs.push(CodecConfig::Zstd(3));
+        }
+
+        for config in configs {
+            let codec = config.build()?;
+            let compressed = codec.compress(&data)?;
+            assert_eq!(codec.decompress(&compressed)?, data, "{}", codec.name());
+            if config != CodecConfig::None {
+                assert!(compressed.len() < data.len());
+            }
+        }
+        Ok(())
+    }
+}
@@ -11,7 +11,19 @@
 use crate::{record::Record, Agent, Env, ReplayBufferBase};
 use anyhow::Result;
 mod default_evaluator;
-pub use default_evaluator::DefaultEvaluator;
+mod eval_report;
+mod eval_result;
+mod multi_env_evaluator;
+mod probe_set_evaluator;
+mod reward_hacking;
+mod robustness_sweep;
+pub use default_evaluator::{DefaultEvaluator, EarlyTermination, SuccessFn};
+pub use eval_report::EvalReport;
+pub use eval_result::EvalResult;
+pub use multi_env_evaluator::MultiEnvEvaluator;
+pub use probe_set_evaluator::ProbeSetEvaluator;
+pub use reward_hacking::RewardHackingChecks;
+pub use robustness_sweep::{sample_standard_normal, AddNoise, RobustnessSweep};
 
 /// Interface for evaluating reinforcement learning agents.
 ///
@@ -34,7 +46,7 @@ pub use default_evaluator::DefaultEvaluator;
 /// }
 ///
 /// impl<E: Env> Evaluator<E> for CustomEvaluator<E> {
-///     fn evaluate<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<Record>
+///     fn evaluate_result<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<EvalResult>
 ///     where
 ///         R: ReplayBufferBase,
 ///     {
@@ -47,10 +59,10 @@ pub trait Evaluator<E: Env> {
     /// Evaluates an agent's performance in the environment.
     ///
     /// This method should:
-    /// 1. Set the agent to evaluation mode
-    /// 2. Run the agent in the environment for the specified number of episodes
-    /// 3. Collect and aggregate performance metrics
-    /// 4. Return the results in a [`Record`]
+    /// 1. Run the agent in the environment for the specified number of episodes
+    /// 2. Collect and aggregate performance metrics
+    /// 3. Return the results in an [`EvalResult`], with the records of individual episodes
+    ///    if available
     ///
     /// # Arguments
     ///
@@ -58,7 +70,7 @@ pub trait Evaluator<E: Env> {
     ///
     /// # Returns
     ///
-    /// A tuple of (performance metric, [`Record`] containing the evaluation results)
+    /// [`EvalResult`] with the performance metric and the [`Record`] of the evaluation
     ///
     /// In [`Trainer`], the performance metric is used to choose the best model.
     ///
@@ -77,7 +89,49 @@ pub trait Evaluator<E: Env> {
     /// configurations.
     ///
     /// [`Trainer`]: crate::Trainer
-    fn evaluate<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<(f32, Record)>
+    fn evaluate_result<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<EvalResult>
     where
         R: ReplayBufferBase;
+
+    /// Evaluates an agent's performance and returns the performance metric and the record.
+    ///
+    /// It converts the result of [`Evaluator::evaluate_result()`] into a tuple. The statistics
+    /// of individual episodes are given by [`EvalResult::report()`] of the result.
+    fn evaluate<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<(f32, Record)>
+    where
+        R: ReplayBufferBase,
+    {
+        Ok(self.evaluate_result(agent)?.into())
+    }
+
+    /// Evaluates an agent's performance with an episode for each of the given indices.
+    ///
+    /// Each episode should start with [`Env::reset_with_index()`] given the index, so that
+    /// evaluations with the same indices, e.g., seeds, run the same set of initial states.
+    /// The records of individual episodes should have `Episode index`.
+    ///
+    /// [`Trainer`] calls this method instead of [`Evaluator::evaluate_result()`] if
+    /// [`TrainerConfig::eval_seeds`] is set. The default implementation returns an error.
+    ///
+    /// [`Trainer`]: crate::Trainer
+    /// [`TrainerConfig::eval_seeds`]: crate::TrainerConfig::eval_seeds
+    fn evaluate_with_indices<R>(
+        &mut self,
+        _agent: &mut Box<dyn Agent<E, R>>,
+        _ixs: &[usize],
+    ) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        anyhow::bail!("The evaluator does not support evaluation with episode indices")
+    }
+
+    /// Closes the environments used for evaluation with [`Env::close()`].
+    ///
+    /// It is called by [`Trainer`] at the end of training. The default implementation does nothing.
+    ///
+    /// [`Trainer`]: crate::Trainer
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
 }
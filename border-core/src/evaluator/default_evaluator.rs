@@ -3,10 +3,26 @@
 //! This module provides a simple evaluator that runs a fixed number of episodes
 //! and calculates the average return across all episodes.
 
-use super::Evaluator;
-use crate::{record::Record, Agent, Env, ReplayBufferBase};
+use super::{EvalReport, EvalResult, Evaluator, RewardHackingChecks};
+use crate::{
+    record::{Record, RecordValue},
+    Agent, Env, ReplayBufferBase, ReturnStats, SplitAct, StackObs, VecEnv, VecEnvConfig,
+};
 use anyhow::Result;
 
+/// A predicate for terminating evaluation episodes early.
+///
+/// It is called after each step with the observation, the info, the number of steps
+/// taken in the episode and the index of the environment. The episode ends when it returns `true`.
+/// The index is 0 except in vectorized environments, where the predicate is called for each
+/// environment with the batched observation and info.
+pub type EarlyTermination<E> =
+    Box<dyn FnMut(&<E as Env>::Obs, &<E as Env>::Info, usize, usize) -> bool>;
+
+/// A predicate of the success of the task given the info of a step and the index of
+/// the environment, which is 0 except in vectorized environments.
+pub type SuccessFn<E> = Box<dyn Fn(&<E as Env>::Info, usize) -> bool>;
+
 /// A default implementation of the [`Evaluator`] trait.
 ///
 /// This evaluator runs a specified number of episodes and calculates the average
@@ -29,12 +45,108 @@ use anyhow::Result;
 /// let record = evaluator.evaluate(&mut agent)?;
 /// println!("Average return: {}", record.get_scalar("Episode return")?);
 /// ```
+///
+/// Episodes can be terminated early by a predicate over the observation, the info and
+/// the step count, without modifying the environment, e.g., when a robot leaves its workspace:
+///
+/// ```ignore
+/// let mut evaluator = DefaultEvaluator::new(&config, 42, 10)?
+///     .early_termination(Box::new(|obs: &Obs, _info, _n_steps, _ix| obs.out_of_workspace()));
+/// ```
+///
+/// In this case, the record also contains:
+/// - `Natural terminations` - The number of episodes ended by the environment
+/// - `Early terminations` - The number of episodes ended by the predicate
+/// - `Early termination rate` - The ratio of episodes ended by the predicate
+///
+/// Evaluation of policies which never end episodes can be bounded by the number of steps
+/// per episode and the total number of steps over all episodes:
+///
+/// ```ignore
+/// let mut evaluator = DefaultEvaluator::new(&config, 42, 10)?
+///     .max_steps_per_episode(1000)
+///     .max_total_steps(5000);
+/// ```
+///
+/// Episodes reaching either limit are truncated, and no more episodes are started once the
+/// total budget is exhausted. The average return is taken over the episodes run. In this case,
+/// the record also contains:
+/// - `Evaluated episodes` - The number of episodes run
+/// - `Truncated episodes` - The number of episodes ended by the limits
+/// - `Budget exhausted` - 1 if the total budget ran out before all the episodes were run, 0 otherwise
+///
+/// Besides the average return, the record contains the statistics of [`EvalReport::record()`],
+/// i.e., the standard deviation, the minimum and the maximum of episode returns, the returns
+/// of episodes as a histogram and the average episode length. The success rate is also
+/// recorded given a predicate of the success of the task on the info of the environment,
+/// where an episode is successful if the predicate holds at any step:
+///
+/// ```ignore
+/// let mut evaluator = DefaultEvaluator::new(&config, 42, 10)?
+///     .success(Box::new(|info: &Info, _ix| info.is_success));
+/// ```
+///
+/// Suspicious patterns of rewards and actions can be flagged with [`RewardHackingChecks`]:
+///
+/// ```ignore
+/// let checks = RewardHackingChecks::default()
+///     .success(Box::new(|info: &Info, _ix| info.is_success))
+///     .return_bounds(-100.0, 300.0);
+/// let mut evaluator = DefaultEvaluator::new(&config, 42, 10)?.reward_hacking_checks(checks);
+/// ```
+///
+/// When the agent is trained on rewards normalized with [`NormalizeReward`], the evaluator
+/// should run the environment without the wrapper, so that `Episode return` is the return of
+/// the original rewards. Given the statistics of returns shared with the wrapper for training,
+/// the record also contains `Episode return (normalized)`, the return of the rewards normalized
+/// with the current statistics, which is comparable to the returns seen by the agent:
+///
+/// ```ignore
+/// let env_config = NormalizeRewardConfig::<Env>::new(config.clone());
+/// let mut evaluator = DefaultEvaluator::new(&config, 42, 10)?
+///     .return_stats(env_config.return_stats());
+/// ```
+///
+/// Vectorized environments, whose [`Env::n_envs()`] is more than 1, are also supported.
+/// Episodes are run in parallel, and each environment starts a new episode after its episode ends,
+/// by resetting it with [`Env::reset()`] given the flags of the ended episodes. The predicates
+/// and the checks above are applied to each environment, given its index, with the same
+/// semantics as in a single environment.
+///
+/// [`DefaultEvaluator::batched()`] runs episodes in a [`VecEnv`] of copies of the environment,
+/// so that the observations of several episodes go through the policy in a single forward pass.
+/// It reduces evaluation time of policies on GPU, e.g., those with CNNs:
+///
+/// ```ignore
+/// // 10 episodes in 2 rounds of 5 parallel episodes
+/// let mut evaluator = DefaultEvaluator::<VecEnv<Env>>::batched(&config, 42, 10, 8)?;
+/// ```
+///
+/// [`NormalizeReward`]: crate::NormalizeReward
 pub struct DefaultEvaluator<E: Env> {
     /// The number of episodes to run during evaluation.
     n_episodes: usize,
 
     /// The environment instance used for evaluation.
     env: E,
+
+    /// The predicate for terminating episodes early.
+    early_termination: Option<EarlyTermination<E>>,
+
+    /// The maximum number of steps in an episode.
+    max_steps_per_episode: Option<usize>,
+
+    /// The maximum number of steps over all episodes.
+    max_total_steps: Option<usize>,
+
+    /// Heuristics for detecting reward hacking.
+    reward_hacking_checks: Option<RewardHackingChecks<E>>,
+
+    /// Statistics of returns for reporting returns of normalized rewards.
+    return_stats: Option<ReturnStats>,
+
+    /// The predicate of the success of the task.
+    is_success: Option<SuccessFn<E>>,
 }
 
 impl<E: Env> Evaluator<E> for DefaultEvaluator<E> {
@@ -44,9 +156,10 @@ impl<E: Env> Evaluator<E> for DefaultEvaluator<E> {
     /// 1. Runs the specified number of episodes
     /// 2. For each episode:
     ///    - Resets the environment with a unique index
-    ///    - Runs the episode until termination
+    ///    - Runs the episode until termination, either by the environment,
+    ///      by the early termination predicate or by the step limits
     ///    - Accumulates the total reward
-    /// 3. Returns the average return across all episodes
+    /// 3. Returns the average return across all episodes run
     ///
     /// # Arguments
     ///
@@ -54,41 +167,345 @@ impl<E: Env> Evaluator<E> for DefaultEvaluator<E> {
     ///
     /// # Returns
     ///
-    /// A tuple of (performance metric, [`Record`] containing the evaluation results)
+    /// [`EvalResult`] with the average return as the performance metric, the aggregate
+    /// [`Record`] and the records of episodes with `Episode return` and `Episode length`
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - The environment fails to reset
     /// - The environment fails to step
-    fn evaluate<R>(&mut self, policy: &mut Box<dyn Agent<E, R>>) -> Result<(f32, Record)>
+    fn evaluate_result<R>(&mut self, policy: &mut Box<dyn Agent<E, R>>) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        if self.env.n_envs() > 1 {
+            return self.evaluate_vec(policy);
+        }
+        self.evaluate_episodes(policy, 0..self.n_episodes)
+    }
+
+    /// Evaluates a policy by running an episode for each of the given indices.
+    ///
+    /// Each episode starts with [`Env::reset_with_index()`] given the index, and its record
+    /// has `Episode index` in addition to those of [`Evaluator::evaluate_result()`].
+    /// The number of episodes given in the constructor is ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error in vectorized environments, where episodes are reset together.
+    fn evaluate_with_indices<R>(
+        &mut self,
+        policy: &mut Box<dyn Agent<E, R>>,
+        ixs: &[usize],
+    ) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        if self.env.n_envs() > 1 {
+            anyhow::bail!(
+                "Evaluation with episode indices is not supported in vectorized environments"
+            );
+        }
+        let mut result = self.evaluate_episodes(policy, ixs.iter().copied())?;
+        for (episode, &ix) in result.episodes.iter_mut().zip(ixs) {
+            episode.insert("Episode index", RecordValue::Scalar(ix as f32));
+        }
+        Ok(result)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.env.close()
+    }
+}
+
+impl<E: Env> DefaultEvaluator<E> {
+    /// Evaluates a policy by running episodes starting with the given indices.
+    fn evaluate_episodes<R>(
+        &mut self,
+        policy: &mut Box<dyn Agent<E, R>>,
+        ixs: impl Iterator<Item = usize>,
+    ) -> Result<EvalResult>
     where
         R: ReplayBufferBase,
     {
         let mut r_total = 0f32;
+        let mut r_normalized = 0f32;
+        let mut n_early_terminations = 0;
+        let mut n_truncations = 0;
+        let mut n_evaluated_episodes = 0;
+        let mut n_total_steps = 0;
+        let mut budget_exhausted = false;
+        let max_steps_per_episode = self.max_steps_per_episode.unwrap_or(usize::MAX);
+        let max_total_steps = self.max_total_steps.unwrap_or(usize::MAX);
+        let mut tracker = self.reward_hacking_checks.as_ref().map(|c| c.tracker(1));
+        let mut episodes = vec![];
 
-        for ix in 0..self.n_episodes {
+        for ix in ixs {
+            if n_total_steps >= max_total_steps {
+                budget_exhausted = true;
+                break;
+            }
             let mut prev_obs = self.env.reset_with_index(ix)?;
+            let mut n_steps = 0;
+            let mut r_episode = 0f32;
+            let mut is_success = false;
+            n_evaluated_episodes += 1;
 
             loop {
                 let act = policy.sample(&prev_obs);
-                let (step, _) = self.env.step(&act);
+                let (step, _) = self.env.try_step(&act)?;
                 r_total += step.reward[0];
+                r_episode += step.reward[0];
+                if let Some(stats) = self.return_stats.as_ref() {
+                    r_normalized += stats.normalize(&step.reward[..1])[0];
+                }
+                n_steps += 1;
+                n_total_steps += 1;
+                if let Some(tracker) = tracker.as_mut() {
+                    tracker.step(0, &act, step.reward[0], &step.info);
+                }
+                if let Some(f) = self.is_success.as_ref() {
+                    is_success |= f(&step.info, 0);
+                }
                 if step.is_done() {
                     break;
                 }
+                if let Some(f) = self.early_termination.as_mut() {
+                    if f(&step.obs, &step.info, n_steps, 0) {
+                        n_early_terminations += 1;
+                        break;
+                    }
+                }
+                if n_steps >= max_steps_per_episode || n_total_steps >= max_total_steps {
+                    n_truncations += 1;
+                    break;
+                }
                 prev_obs = step.obs;
             }
+
+            if let Some(tracker) = tracker.as_mut() {
+                tracker.end_episode(0);
+            }
+            episodes.push(self.episode_record(r_episode, n_steps, is_success));
         }
+        let record_checks = tracker.map(|t| t.record());
 
-        let performance = r_total / self.n_episodes as f32;
-        let record = Record::from_scalar("Episode return", performance);
+        let performance = r_total / n_evaluated_episodes.max(1) as f32;
+        let record = self.record(
+            performance,
+            r_normalized / n_evaluated_episodes.max(1) as f32,
+            n_evaluated_episodes,
+            n_early_terminations,
+            n_truncations,
+            budget_exhausted,
+        );
+        let record = EvalReport::from_episodes(&episodes).record().merge(record);
+        let record = match record_checks {
+            Some(r) => record.merge(r),
+            None => record,
+        };
 
-        Ok((performance, record))
+        Ok(EvalResult::new(performance, record).episodes(episodes))
+    }
+
+    /// Evaluates a policy in a vectorized environment.
+    ///
+    /// Episodes are run in parallel, and each environment starts a new episode after its
+    /// episode ends until the specified number of episodes are started. When the total budget
+    /// of steps runs out, the running episodes are truncated, except those which have not
+    /// taken any step yet, which are not counted as evaluated.
+    fn evaluate_vec<R>(&mut self, policy: &mut Box<dyn Agent<E, R>>) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        let n_envs = self.env.n_envs();
+        let max_steps_per_episode = self.max_steps_per_episode.unwrap_or(usize::MAX);
+        let max_total_steps = self.max_total_steps.unwrap_or(usize::MAX);
+        let mut r_total = 0f32;
+        let mut r_normalized = 0f32;
+        let mut n_early_terminations = 0;
+        let mut n_truncations = 0;
+        let mut n_total_steps = 0;
+        let mut n_steps = vec![0; n_envs];
+        let mut r_episodes = vec![0f32; n_envs];
+        let mut is_success = vec![false; n_envs];
+        let mut tracker = self
+            .reward_hacking_checks
+            .as_ref()
+            .map(|c| c.tracker(n_envs));
+        let mut episodes = vec![];
+        let mut is_running = (0..n_envs).map(|i| i < self.n_episodes).collect::<Vec<_>>();
+        let mut n_evaluated_episodes = n_envs.min(self.n_episodes);
+        let mut obs = self.env.reset_with_index(0)?;
+
+        while is_running.contains(&true) {
+            let act = policy.sample(&obs);
+            let (step, _) = self.env.try_step(&act)?;
+            let mut is_reset = vec![0; n_envs];
+            let normalized = self
+                .return_stats
+                .as_ref()
+                .map(|s| s.normalize(&step.reward));
+
+            for i in 0..n_envs {
+                if !is_running[i] {
+                    continue;
+                }
+                if n_total_steps >= max_total_steps {
+                    // The budget has run out before the step of this environment
+                    is_running[i] = false;
+                    if n_steps[i] == 0 {
+                        n_evaluated_episodes -= 1;
+                        continue;
+                    }
+                    n_truncations += 1;
+                    if let Some(tracker) = tracker.as_mut() {
+                        tracker.end_episode(i);
+                    }
+                    episodes.push(self.episode_record(r_episodes[i], n_steps[i], is_success[i]));
+                    continue;
+                }
+                r_total += step.reward[i];
+                r_episodes[i] += step.reward[i];
+                if let Some(normalized) = normalized.as_ref() {
+                    r_normalized += normalized[i];
+                }
+                n_steps[i] += 1;
+                n_total_steps += 1;
+                if let Some(tracker) = tracker.as_mut() {
+                    tracker.step(i, &act, step.reward[i], &step.info);
+                }
+                if let Some(f) = self.is_success.as_ref() {
+                    is_success[i] |= f(&step.info, i);
+                }
+
+                // Check if the episode has ended
+                if step.is_terminated[i] == 0 && step.is_truncated[i] == 0 {
+                    let is_early_terminated = match self.early_termination.as_mut() {
+                        Some(f) => f(&step.obs, &step.info, n_steps[i], i),
+                        None => false,
+                    };
+                    if is_early_terminated {
+                        n_early_terminations += 1;
+                    } else if n_steps[i] >= max_steps_per_episode
+                        || n_total_steps >= max_total_steps
+                    {
+                        n_truncations += 1;
+                    } else {
+                        continue;
+                    }
+                }
+
+                // Start a new episode if required
+                if let Some(tracker) = tracker.as_mut() {
+                    tracker.end_episode(i);
+                }
+                episodes.push(self.episode_record(r_episodes[i], n_steps[i], is_success[i]));
+                is_reset[i] = 1;
+                n_steps[i] = 0;
+                r_episodes[i] = 0.0;
+                is_success[i] = false;
+                if n_evaluated_episodes < self.n_episodes && n_total_steps < max_total_steps {
+                    n_evaluated_episodes += 1;
+                } else {
+                    is_running[i] = false;
+                }
+            }
+
+            obs = match is_reset.contains(&1) && is_running.contains(&true) {
+                true => self.env.reset(Some(&is_reset))?,
+                false => step.obs,
+            };
+        }
+
+        let performance = r_total / n_evaluated_episodes.max(1) as f32;
+        let budget_exhausted = n_evaluated_episodes < self.n_episodes;
+        let record = self.record(
+            performance,
+            r_normalized / n_evaluated_episodes.max(1) as f32,
+            n_evaluated_episodes,
+            n_early_terminations,
+            n_truncations,
+            budget_exhausted,
+        );
+        let record = EvalReport::from_episodes(&episodes).record().merge(record);
+        let record = match tracker.map(|t| t.record()) {
+            Some(r) => record.merge(r),
+            None => record,
+        };
+
+        Ok(EvalResult::new(performance, record).episodes(episodes))
+    }
+
+    /// Returns the record of an episode, with `Success` if the predicate of success is given.
+    fn episode_record(&self, episode_return: f32, n_steps: usize, is_success: bool) -> Record {
+        let mut record = Record::from_slice(&[
+            ("Episode return", RecordValue::Scalar(episode_return)),
+            ("Episode length", RecordValue::Scalar(n_steps as f32)),
+        ]);
+        if self.is_success.is_some() {
+            record.insert("Success", RecordValue::Scalar(is_success as i32 as f32));
+        }
+        record
+    }
+
+    /// Returns the record of an evaluation.
+    fn record(
+        &self,
+        performance: f32,
+        normalized_return: f32,
+        n_evaluated_episodes: usize,
+        n_early_terminations: usize,
+        n_truncations: usize,
+        budget_exhausted: bool,
+    ) -> Record {
+        let mut record = Record::from_scalar("Episode return", performance);
+
+        if self.return_stats.is_some() {
+            record.insert(
+                "Episode return (normalized)",
+                RecordValue::Scalar(normalized_return),
+            );
+        }
+
+        if self.max_steps_per_episode.is_some() || self.max_total_steps.is_some() {
+            record.insert(
+                "Evaluated episodes",
+                RecordValue::Scalar(n_evaluated_episodes as f32),
+            );
+            record.insert(
+                "Truncated episodes",
+                RecordValue::Scalar(n_truncations as f32),
+            );
+            record.insert(
+                "Budget exhausted",
+                RecordValue::Scalar(budget_exhausted as i32 as f32),
+            );
+        }
+
+        if self.early_termination.is_some() {
+            let n_natural_terminations =
+                n_evaluated_episodes - n_early_terminations - n_truncations;
+            record.insert(
+                "Natural terminations",
+                RecordValue::Scalar(n_natural_terminations as f32),
+            );
+            record.insert(
+                "Early terminations",
+                RecordValue::Scalar(n_early_terminations as f32),
+            );
+            record.insert(
+                "Early termination rate",
+                RecordValue::Scalar(
+                    n_early_terminations as f32 / n_evaluated_episodes.max(1) as f32,
+                ),
+            );
+        }
+
+        record
     }
-}
 
-impl<E: Env> DefaultEvaluator<E> {
     /// Constructs a new [`DefaultEvaluator`].
     ///
     /// # Arguments
@@ -111,6 +528,303 @@ impl<E: Env> DefaultEvaluator<E> {
         Ok(Self {
             n_episodes,
             env: E::build(config, seed)?,
+            early_termination: None,
+            max_steps_per_episode: None,
+            max_total_steps: None,
+            reward_hacking_checks: None,
+            return_stats: None,
+            is_success: None,
         })
     }
+
+    /// Sets the predicate for terminating episodes early.
+    ///
+    /// The predicate is evaluated after each step that does not end the episode.
+    pub fn early_termination(mut self, f: EarlyTermination<E>) -> Self {
+        self.early_termination = Some(f);
+        self
+    }
+
+    /// Sets the heuristics for detecting reward hacking.
+    pub fn reward_hacking_checks(mut self, checks: RewardHackingChecks<E>) -> Self {
+        self.reward_hacking_checks = Some(checks);
+        self
+    }
+
+    /// Sets the predicate of the success of the task given the info, to record the success rate.
+    pub fn success(mut self, f: SuccessFn<E>) -> Self {
+        self.is_success = Some(f);
+        self
+    }
+
+    /// Sets the statistics of returns, shared with [`NormalizeReward`] for training,
+    /// to report returns of normalized rewards.
+    ///
+    /// [`NormalizeReward`]: crate::NormalizeReward
+    pub fn return_stats(mut self, stats: ReturnStats) -> Self {
+        self.return_stats = Some(stats);
+        self
+    }
+
+    /// Sets the maximum number of steps in an episode.
+    pub fn max_steps_per_episode(mut self, v: usize) -> Self {
+        self.max_steps_per_episode = Some(v);
+        self
+    }
+
+    /// Sets the maximum number of steps over all episodes in an evaluation.
+    pub fn max_total_steps(mut self, v: usize) -> Self {
+        self.max_total_steps = Some(v);
+        self
+    }
+}
+
+impl<E> DefaultEvaluator<VecEnv<E>>
+where
+    E: Env + 'static,
+    E::Obs: StackObs + Send + 'static,
+    E::Act: SplitAct + Send + 'static,
+    E::Info: Send + 'static,
+    E::Config: Send + 'static,
+{
+    /// Constructs a [`DefaultEvaluator`] running episodes in batches of at most `max_batch_size`.
+    ///
+    /// `max_batch_size` is the budget of the batch size of the policy, e.g., bounded by GPU memory.
+    /// The number of copies of the environment is chosen to run `n_episodes` episodes in as few
+    /// rounds as possible with batches of nearly equal sizes, so that fewer environments are
+    /// stepped after their last episode has ended. The `i`-th copy is built with seed `seed + i`.
+    pub fn batched(
+        config: &E::Config,
+        seed: i64,
+        n_episodes: usize,
+        max_batch_size: usize,
+    ) -> Result<Self> {
+        let n_envs = batch_size(n_episodes, max_batch_size);
+        let config = VecEnvConfig::<E>::new(config.clone(), n_envs);
+        Self::new(&config, seed, n_episodes)
+    }
+}
+
+/// Returns the batch size running `n_episodes` in the fewest rounds of balanced batches
+/// of at most `max_batch_size` episodes.
+fn batch_size(n_episodes: usize, max_batch_size: usize) -> usize {
+    let max_batch_size = max_batch_size.max(1);
+    let n_rounds = n_episodes.div_ceil(max_batch_size).max(1);
+    n_episodes.div_ceil(n_rounds).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic_replay_buffer::SimpleReplayBuffer,
+        test::{TestActBatch, TestAgent, TestEnv, TestObsBatch},
+        test_env::{CountAct, CountEnv, CountEnvConfig, CountObs},
+        Obs, VecEnv,
+    };
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+
+    #[test]
+    fn test_early_termination() -> Result<()> {
+        // TestEnv never terminates episodes by itself
+        let mut evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 3)?
+            .early_termination(Box::new(|_obs, _info, n_steps, _ix| n_steps >= 5));
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let (_, record) = evaluator.evaluate(&mut agent)?;
+
+        assert_eq!(record.get_scalar("Natural terminations")?, 0.0);
+        assert_eq!(record.get_scalar("Early terminations")?, 3.0);
+        assert_eq!(record.get_scalar("Early termination rate")?, 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_step_budget() -> Result<()> {
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+
+        // Every episode is truncated
+        let mut evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 3)?.max_steps_per_episode(4);
+        let (_, record) = evaluator.evaluate(&mut agent)?;
+        assert_eq!(record.get_scalar("Evaluated episodes")?, 3.0);
+        assert_eq!(record.get_scalar("Truncated episodes")?, 3.0);
+        assert_eq!(record.get_scalar("Budget exhausted")?, 0.0);
+
+        // The budget runs out in the second episode
+        let mut evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 3)?
+            .max_steps_per_episode(4)
+            .max_total_steps(6);
+        let result = evaluator.evaluate_result(&mut agent)?;
+        assert_eq!(result.record.get_scalar("Evaluated episodes")?, 2.0);
+        assert_eq!(result.record.get_scalar("Truncated episodes")?, 2.0);
+        assert_eq!(result.record.get_scalar("Budget exhausted")?, 1.0);
+        assert_eq!(result.episode_scalars("Episode length"), vec![4.0, 2.0]);
+        assert_eq!(result.record.get_scalar("Episode length")?, 3.0);
+        assert_eq!(result.record.get_scalar("Episode return std")?, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_reward_hacking_checks() -> Result<()> {
+        // TestEnv gives zero rewards
+        let checks = RewardHackingChecks::default()
+            .success(Box::new(|_info, _ix| false))
+            .return_bounds(1.0, 2.0)
+            .saturation(Box::new(|_act, _ix| true));
+        let mut evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 3)?
+            .max_steps_per_episode(4)
+            .reward_hacking_checks(checks);
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let (_, record) = evaluator.evaluate(&mut agent)?;
+
+        assert_eq!(record.get_scalar("reward_hacking/Rewarded failures")?, 0.0);
+        assert_eq!(
+            record.get_scalar("reward_hacking/Out-of-bounds returns")?,
+            3.0
+        );
+        assert_eq!(
+            record.get_scalar("reward_hacking/Saturated action rate")?,
+            1.0
+        );
+        assert_eq!(record.get_scalar("reward_hacking/Warnings")?, 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_success_rate() -> Result<()> {
+        let mut evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 3)?
+            .max_steps_per_episode(4)
+            .success(Box::new(|_info, _ix| true));
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let result = evaluator.evaluate_result(&mut agent)?;
+
+        assert_eq!(result.record.get_scalar("Success rate")?, 1.0);
+        assert_eq!(result.report().success_rate(), Some(1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_evaluate_with_indices() -> Result<()> {
+        let mut evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 3)?.max_steps_per_episode(4);
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let result = evaluator.evaluate_with_indices(&mut agent, &[3, 5])?;
+
+        assert_eq!(result.episode_scalars("Episode index"), vec![3.0, 5.0]);
+        assert_eq!(result.record.get_scalar("Evaluated episodes")?, 2.0);
+        Ok(())
+    }
+
+    /// Vectorized environment, where the episode in the `i`-th environment ends after `i + 2` steps.
+    type CountVecEnv = VecEnv<CountEnv>;
+
+    /// Takes action 1 in all environments, receiving reward 1 at every step.
+    struct VecTestAgent;
+
+    impl crate::Policy<CountVecEnv> for VecTestAgent {
+        fn sample(&mut self, obs: &CountObs) -> CountAct {
+            CountAct(vec![1; obs.len()])
+        }
+    }
+
+    impl Agent<CountVecEnv, ReplayBuffer> for VecTestAgent {}
+
+    #[test]
+    fn test_batch_size() {
+        assert_eq!(batch_size(10, 8), 5);
+        assert_eq!(batch_size(16, 8), 8);
+        assert_eq!(batch_size(17, 8), 6);
+        assert_eq!(batch_size(3, 8), 3);
+        assert_eq!(batch_size(3, 0), 1);
+    }
+
+    #[test]
+    fn test_vectorized_env() -> Result<()> {
+        let mut agent: Box<dyn Agent<CountVecEnv, ReplayBuffer>> = Box::new(VecTestAgent);
+        let config = VecEnvConfig::new(CountEnvConfig::default().length(2), 2);
+
+        // The first environment runs the first and third episodes
+        let mut evaluator = DefaultEvaluator::<CountVecEnv>::new(&config, 0, 3)?;
+        let result = evaluator.evaluate_result(&mut agent)?;
+        assert_eq!(result.score, 7.0 / 3.0);
+        let returns = result.episode_scalars("Episode return");
+        assert_eq!(returns.len(), 3);
+        assert_eq!(returns.iter().sum::<f32>(), 7.0);
+
+        // The budget runs out in the second episode, before the third one takes a step
+        let mut evaluator = DefaultEvaluator::<CountVecEnv>::new(&config, 0, 3)?.max_total_steps(4);
+        let (performance, record) = evaluator.evaluate(&mut agent)?;
+        assert_eq!(performance, 4.0 / 2.0);
+        assert_eq!(record.get_scalar("Evaluated episodes")?, 2.0);
+        assert_eq!(record.get_scalar("Truncated episodes")?, 1.0);
+        assert_eq!(record.get_scalar("Budget exhausted")?, 1.0);
+        Ok(())
+    }
+
+    /// Takes action 1, receiving reward 1 at every step.
+    struct CountTestAgent;
+
+    impl crate::Policy<CountEnv> for CountTestAgent {
+        fn sample(&mut self, obs: &CountObs) -> CountAct {
+            CountAct(vec![1; obs.len()])
+        }
+    }
+
+    impl Agent<CountEnv, ReplayBuffer> for CountTestAgent {}
+
+    #[test]
+    fn test_vectorized_env_checks() -> Result<()> {
+        // Episodes of the environment with seed `i` end after `2 + i` steps
+        let config = CountEnvConfig::default().length(2);
+        fn checks<E: Env>() -> RewardHackingChecks<E> {
+            RewardHackingChecks::default()
+                .success(Box::new(|_info, _ix| false))
+                .return_bounds(0.0, 2.5)
+        }
+
+        // Single environments with seeds 0, 1 and 2
+        let mut single = vec![];
+        for seed in 0..3 {
+            let mut agent: Box<dyn Agent<CountEnv, ReplayBuffer>> = Box::new(CountTestAgent);
+            let mut evaluator = DefaultEvaluator::<CountEnv>::new(&config, seed, 1)?
+                .max_steps_per_episode(10)
+                .early_termination(Box::new(|_obs, _info, n_steps, _ix| n_steps >= 3))
+                .success(Box::new(|_info, _ix| true))
+                .reward_hacking_checks(checks());
+            single.push(evaluator.evaluate_result(&mut agent)?.record);
+        }
+        let sum =
+            |key: &str| -> Result<f32> { single.iter().map(|r| Ok(r.get_scalar(key)?)).sum() };
+
+        // A vectorized environment of the same seeds
+        let mut agent: Box<dyn Agent<CountVecEnv, ReplayBuffer>> = Box::new(VecTestAgent);
+        let vec_config = VecEnvConfig::new(config.clone(), 3);
+        let mut evaluator = DefaultEvaluator::<CountVecEnv>::new(&vec_config, 0, 3)?
+            .max_steps_per_episode(10)
+            .early_termination(Box::new(|_obs, _info, n_steps, _ix| n_steps >= 3))
+            .success(Box::new(|info, ix| !info[ix].is_life_lost))
+            .reward_hacking_checks(checks());
+        let result = evaluator.evaluate_result(&mut agent)?;
+        let record = &result.record;
+
+        let mut returns = result.episode_scalars("Episode return");
+        returns.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(returns, vec![2.0, 3.0, 3.0]);
+        for key in [
+            "Evaluated episodes",
+            "Truncated episodes",
+            "Natural terminations",
+            "Early terminations",
+            "reward_hacking/Rewarded failures",
+            "reward_hacking/Out-of-bounds returns",
+        ] {
+            assert_eq!(record.get_scalar(key)?, sum(key)?, "{}", key);
+        }
+        assert_eq!(record.get_scalar("Early terminations")?, 1.0);
+        assert_eq!(record.get_scalar("Success rate")?, 1.0);
+        assert_eq!(
+            record.get_scalar("Episode return")?,
+            sum("Episode return")? / 3.0
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,188 @@
+//! Statistics of episodes in evaluations.
+use crate::record::{Record, RecordValue};
+
+/// Statistics of the episodes of an evaluation, returned by [`EvalResult::report()`].
+///
+/// It is built from the records of individual episodes in [`EvalResult`], with
+/// `Episode return`, `Episode length` and, if the success of episodes is given from the info
+/// of the environment, `Success` (1 or 0). [`EvalReport::record()`] summarizes the statistics,
+/// which are recorded by [`Trainer`] at each evaluation.
+///
+/// [`EvalResult`]: super::EvalResult
+/// [`EvalResult::report()`]: super::EvalResult::report
+/// [`Trainer`]: crate::Trainer
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EvalReport {
+    /// Returns of episodes.
+    pub episode_returns: Vec<f32>,
+
+    /// Lengths of episodes.
+    pub episode_lengths: Vec<usize>,
+
+    /// Success flags of episodes, empty if the success of episodes is not evaluated.
+    pub successes: Vec<bool>,
+}
+
+impl EvalReport {
+    /// Builds the report from the records of individual episodes.
+    ///
+    /// Episodes without the corresponding scalar are skipped in each statistic.
+    pub fn from_episodes(episodes: &[Record]) -> Self {
+        let scalars = |key| {
+            episodes
+                .iter()
+                .filter_map(|r| r.get_scalar(key).ok())
+                .collect::<Vec<_>>()
+        };
+        Self {
+            episode_returns: scalars("Episode return"),
+            episode_lengths: scalars("Episode length")
+                .into_iter()
+                .map(|v| v as usize)
+                .collect(),
+            successes: scalars("Success").into_iter().map(|v| v > 0.0).collect(),
+        }
+    }
+
+    /// Returns the number of episodes.
+    pub fn n_episodes(&self) -> usize {
+        self.episode_returns.len()
+    }
+
+    /// Returns the mean of episode returns.
+    pub fn mean_return(&self) -> f32 {
+        mean(&self.episode_returns)
+    }
+
+    /// Returns the standard deviation of episode returns.
+    pub fn std_return(&self) -> f32 {
+        let m = self.mean_return();
+        mean(
+            &self
+                .episode_returns
+                .iter()
+                .map(|r| (r - m).powi(2))
+                .collect::<Vec<_>>(),
+        )
+        .sqrt()
+    }
+
+    /// Returns the minimum of episode returns, or `NaN` if there are no episodes.
+    pub fn min_return(&self) -> f32 {
+        self.episode_returns
+            .iter()
+            .copied()
+            .reduce(f32::min)
+            .unwrap_or(f32::NAN)
+    }
+
+    /// Returns the maximum of episode returns, or `NaN` if there are no episodes.
+    pub fn max_return(&self) -> f32 {
+        self.episode_returns
+            .iter()
+            .copied()
+            .reduce(f32::max)
+            .unwrap_or(f32::NAN)
+    }
+
+    /// Returns the mean of episode lengths.
+    pub fn mean_length(&self) -> f32 {
+        mean(
+            &self
+                .episode_lengths
+                .iter()
+                .map(|&l| l as f32)
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Returns the ratio of successful episodes, or `None` if the success is not evaluated.
+    pub fn success_rate(&self) -> Option<f32> {
+        match self.successes.is_empty() {
+            true => None,
+            false => Some(
+                self.successes.iter().filter(|&&s| s).count() as f32 / self.successes.len() as f32,
+            ),
+        }
+    }
+
+    /// Returns the record of the statistics.
+    ///
+    /// The record contains:
+    /// - `Episode return` - The mean of episode returns
+    /// - `Episode return std`, `Episode return min`, `Episode return max` - The standard
+    ///   deviation, the minimum and the maximum of episode returns
+    /// - `Episode returns` - Episode returns as [`RecordValue::Histogram`]
+    /// - `Episode length` - The mean of episode lengths
+    /// - `Success rate` - The ratio of successful episodes, if evaluated
+    ///
+    /// It is empty if there are no episodes.
+    pub fn record(&self) -> Record {
+        if self.episode_returns.is_empty() {
+            return Record::empty();
+        }
+
+        let mut record = Record::from_slice(&[
+            ("Episode return", RecordValue::Scalar(self.mean_return())),
+            ("Episode return std", RecordValue::Scalar(self.std_return())),
+            ("Episode return min", RecordValue::Scalar(self.min_return())),
+            ("Episode return max", RecordValue::Scalar(self.max_return())),
+            (
+                "Episode returns",
+                RecordValue::Histogram(self.episode_returns.clone()),
+            ),
+        ]);
+        if !self.episode_lengths.is_empty() {
+            record.insert("Episode length", RecordValue::Scalar(self.mean_length()));
+        }
+        if let Some(success_rate) = self.success_rate() {
+            record.insert("Success rate", RecordValue::Scalar(success_rate));
+        }
+        record
+    }
+}
+
+/// Returns the mean of values, or `NaN` if empty.
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval_report() {
+        let episode = |r: f32, l: f32, s: f32| {
+            Record::from_slice(&[
+                ("Episode return", RecordValue::Scalar(r)),
+                ("Episode length", RecordValue::Scalar(l)),
+                ("Success", RecordValue::Scalar(s)),
+            ])
+        };
+        let report = EvalReport::from_episodes(&[
+            episode(1.0, 10.0, 0.0),
+            episode(3.0, 20.0, 1.0),
+            episode(5.0, 30.0, 1.0),
+            episode(7.0, 40.0, 1.0),
+        ]);
+        let record = report.record();
+
+        assert_eq!(record.get_scalar("Episode return").unwrap(), 4.0);
+        assert_eq!(
+            record.get_scalar("Episode return std").unwrap(),
+            5f32.sqrt()
+        );
+        assert_eq!(record.get_scalar("Episode return min").unwrap(), 1.0);
+        assert_eq!(record.get_scalar("Episode return max").unwrap(), 7.0);
+        assert_eq!(record.get_histogram("Episode returns").unwrap().len(), 4);
+        assert_eq!(record.get_scalar("Episode length").unwrap(), 25.0);
+        assert_eq!(record.get_scalar("Success rate").unwrap(), 0.75);
+
+        // No success flags
+        let report = EvalReport::from_episodes(&[Record::from_scalar("Episode return", 1.0)]);
+        assert_eq!(report.success_rate(), None);
+        assert!(report.record().get_scalar("Success rate").is_err());
+        assert!(EvalReport::default().record().is_empty());
+    }
+}
@@ -0,0 +1,81 @@
+//! Structured result of evaluations.
+use super::EvalReport;
+use crate::record::Record;
+use std::path::PathBuf;
+
+/// Result of an evaluation returned by [`Evaluator::evaluate_result()`].
+///
+/// It is consumed by [`Trainer`] to choose the best model and to stop training early with
+/// [`EvalResult::score`], and to record [`EvalResult::record`] with the metrics of the
+/// optimization step. The records of individual episodes and the files produced by the
+/// evaluation are kept for further analysis, and the last result is available with
+/// [`Trainer::last_eval_result()`].
+///
+/// [`Evaluator::evaluate_result()`]: super::Evaluator::evaluate_result
+/// [`Trainer`]: crate::Trainer
+/// [`Trainer::last_eval_result()`]: crate::Trainer::last_eval_result
+#[derive(Clone, Debug)]
+pub struct EvalResult {
+    /// Performance metric, where a higher value is better.
+    pub score: f32,
+
+    /// Aggregate statistics over episodes, e.g., `Episode return`.
+    pub record: Record,
+
+    /// Records of individual episodes, e.g., the return and the length of each episode.
+    pub episodes: Vec<Record>,
+
+    /// Files produced by the evaluation, e.g., videos of episodes.
+    pub artifacts: Vec<PathBuf>,
+}
+
+impl EvalResult {
+    /// Creates a result with the performance metric and the aggregate statistics.
+    pub fn new(score: f32, record: Record) -> Self {
+        Self {
+            score,
+            record,
+            episodes: vec![],
+            artifacts: vec![],
+        }
+    }
+
+    /// Sets the records of individual episodes.
+    pub fn episodes(mut self, v: Vec<Record>) -> Self {
+        self.episodes = v;
+        self
+    }
+
+    /// Sets the files produced by the evaluation.
+    pub fn artifacts(mut self, v: Vec<PathBuf>) -> Self {
+        self.artifacts = v;
+        self
+    }
+
+    /// Returns the values of a scalar in the records of individual episodes.
+    ///
+    /// Episodes without the scalar are skipped.
+    pub fn episode_scalars(&self, key: &str) -> Vec<f32> {
+        self.episodes
+            .iter()
+            .filter_map(|r| r.get_scalar(key).ok())
+            .collect()
+    }
+
+    /// Returns the statistics of the records of individual episodes.
+    pub fn report(&self) -> EvalReport {
+        EvalReport::from_episodes(&self.episodes)
+    }
+}
+
+impl From<(f32, Record)> for EvalResult {
+    fn from((score, record): (f32, Record)) -> Self {
+        Self::new(score, record)
+    }
+}
+
+impl From<EvalResult> for (f32, Record) {
+    fn from(result: EvalResult) -> Self {
+        (result.score, result.record)
+    }
+}
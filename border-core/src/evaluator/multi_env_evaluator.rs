@@ -0,0 +1,191 @@
+//! Evaluation against multiple variants of an environment.
+//!
+//! This module provides an evaluator that runs an agent in several named variants of
+//! an environment, e.g., with the default physics, harder physics, or observation noise.
+//! It is useful for monitoring the generalization of the agent during training.
+
+use super::{DefaultEvaluator, EvalResult, Evaluator};
+use crate::{
+    record::{Record, RecordValue},
+    Agent, Env, ReplayBufferBase,
+};
+use anyhow::Result;
+
+/// An evaluator running an agent in multiple named variants of an environment.
+///
+/// Each variant is evaluated with a [`DefaultEvaluator`], and its records are reported
+/// with the keys prefixed by the name of the variant, like `harder_physics/Episode return`.
+///
+/// The performance metric, used by [`Trainer`] to choose the best model, is the score of
+/// the first variant, which is typically the training distribution. The records of individual
+/// episodes of all variants are returned with `Variant`, the name of the variant, so that
+/// [`EvalResult::report()`] gives the statistics over all variants.
+///
+/// # Examples
+///
+/// ```ignore
+/// let variants = vec![
+///     ("default", EnvConfig::default()),
+///     ("heavy_pole", EnvConfig::default().pole_mass(0.5)),
+/// ];
+/// let mut evaluator = MultiEnvEvaluator::new(&variants, 42, 5)?;
+/// trainer.train(env, step_proc, &mut agent, &mut buffer, &mut recorder, &mut evaluator)?;
+/// ```
+///
+/// [`Trainer`]: crate::Trainer
+pub struct MultiEnvEvaluator<E: Env> {
+    /// Names of the variants and their evaluators.
+    variants: Vec<(String, DefaultEvaluator<E>)>,
+}
+
+impl<E: Env> Evaluator<E> for MultiEnvEvaluator<E> {
+    /// Evaluates the agent in all variants.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the evaluation fails in any of the variants.
+    fn evaluate_result<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        let mut results = vec![];
+        for (_, evaluator) in self.variants.iter_mut() {
+            results.push(evaluator.evaluate_result(agent)?);
+        }
+        Ok(self.merge(results))
+    }
+
+    /// Evaluates the agent in all variants with an episode for each of the given indices.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the evaluation fails in any of the variants.
+    fn evaluate_with_indices<R>(
+        &mut self,
+        agent: &mut Box<dyn Agent<E, R>>,
+        ixs: &[usize],
+    ) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        let mut results = vec![];
+        for (_, evaluator) in self.variants.iter_mut() {
+            results.push(evaluator.evaluate_with_indices(agent, ixs)?);
+        }
+        Ok(self.merge(results))
+    }
+
+    fn close(&mut self) -> Result<()> {
+        for (_, evaluator) in self.variants.iter_mut() {
+            evaluator.close()?;
+        }
+        Ok(())
+    }
+}
+
+impl<E: Env> MultiEnvEvaluator<E> {
+    /// Constructs a new [`MultiEnvEvaluator`].
+    ///
+    /// # Arguments
+    ///
+    /// * `variants` - Names and configurations of the variants of the environment
+    /// * `seed` - Random seed for environment initialization, shared by all variants
+    /// * `n_episodes` - Number of episodes to run in each variant
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no variant is given or building any of the environments fails.
+    pub fn new(
+        variants: &[(impl Into<String> + Clone, E::Config)],
+        seed: i64,
+        n_episodes: usize,
+    ) -> Result<Self> {
+        if variants.is_empty() {
+            anyhow::bail!("No variant of the environment is given");
+        }
+        let variants = variants
+            .iter()
+            .map(|(name, config)| {
+                let evaluator = DefaultEvaluator::new(config, seed, n_episodes)?;
+                Ok((name.clone().into(), evaluator))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { variants })
+    }
+
+    /// Merges the results of the variants, in the same order as the variants.
+    ///
+    /// The score is that of the first variant, and the keys of the records are prefixed by
+    /// the names of the variants.
+    fn merge(&self, results: Vec<EvalResult>) -> EvalResult {
+        let score = results[0].score;
+        let mut record = Record::empty();
+        let mut episodes = vec![];
+        let mut artifacts = vec![];
+
+        for ((name, _), result) in self.variants.iter().zip(results) {
+            for (k, v) in result.record.into_iter_in_record() {
+                record.insert(format!("{}/{}", name, k), v);
+            }
+            episodes.extend(result.episodes.into_iter().map(|mut episode| {
+                episode.insert("Variant", RecordValue::String(name.clone()));
+                episode
+            }));
+            artifacts.extend(result.artifacts);
+        }
+
+        EvalResult::new(score, record)
+            .episodes(episodes)
+            .artifacts(artifacts)
+    }
+
+    /// Returns the names of the variants.
+    pub fn names(&self) -> Vec<&str> {
+        self.variants
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic_replay_buffer::SimpleReplayBuffer,
+        test::{TestActBatch, TestAgent, TestEnv, TestObsBatch},
+    };
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+
+    #[test]
+    fn test_multi_env_evaluator() -> Result<()> {
+        let variants: Vec<(&str, usize)> = vec![];
+        assert!(MultiEnvEvaluator::<TestEnv>::new(&variants, 0, 3).is_err());
+
+        // TestEnv never terminates episodes by itself
+        let variant = |name: &str| -> Result<_> {
+            let evaluator = DefaultEvaluator::new(&0, 0, 3)?.max_steps_per_episode(4);
+            Ok((name.to_string(), evaluator))
+        };
+        let mut evaluator = MultiEnvEvaluator::<TestEnv> {
+            variants: vec![variant("a")?, variant("b")?],
+        };
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+
+        // Episodes of all variants are returned with the names of the variants
+        let result = evaluator.evaluate_result(&mut agent)?;
+        assert_eq!(result.episodes.len(), 6);
+        assert_eq!(result.report().n_episodes(), 6);
+        assert!(result.record.get_scalar("b/Episode return").is_ok());
+        assert_eq!(result.episodes[3].get_string("Variant")?, "b".to_string());
+
+        // Episodes start with the given indices in all variants
+        let result = evaluator.evaluate_with_indices(&mut agent, &[3, 5])?;
+        assert_eq!(
+            result.episode_scalars("Episode index"),
+            vec![3.0, 5.0, 3.0, 5.0]
+        );
+        Ok(())
+    }
+}
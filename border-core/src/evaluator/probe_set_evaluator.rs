@@ -0,0 +1,221 @@
+//! Evaluation with a fixed set of probe observations.
+//!
+//! This module provides an evaluator that runs a fixed set of observations through the agent
+//! every evaluation and logs the outputs of its networks, e.g., action values of DQN.
+//! Plotting the average maximum action value on a held-out set of states is the standard
+//! technique for visualizing value drift on Atari (Mnih et al., 2013).
+
+use super::{EvalResult, Evaluator};
+use crate::{
+    record::{Record, RecordValue},
+    Agent, Env, Policy, ReplayBufferBase,
+};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// An evaluator logging the outputs of an agent for a fixed set of observations.
+///
+/// The inner evaluator is run first, and its performance metric and records are returned
+/// as they are. Then, [`Agent::probe()`] is called for each observation of the probe set,
+/// and the following values are added to the record:
+/// - `probe/<i>/<key>/<j>` - The `j`-th element of an [`RecordValue::Array1`] output for
+///   the `i`-th observation, e.g., the action value of the `j`-th action
+/// - `probe/<i>/<key>` - A [`RecordValue::Scalar`] output for the `i`-th observation
+/// - `probe/Average max <key>` - The maximum element of [`RecordValue::Array1`] outputs,
+///   averaged over the probe set
+/// - `probe/Average <key>` - [`RecordValue::Scalar`] outputs averaged over the probe set
+///
+/// Array outputs are flattened into scalars, as some recorders, like the MLflow recorder,
+/// do not support arrays.
+///
+/// # Examples
+///
+/// ```ignore
+/// // Observations visited by a random policy before training
+/// let probe_set = ProbeSetEvaluator::<Env, _>::collect(&env_config, 0, &mut random_policy, 32, 100)?;
+/// let mut evaluator = ProbeSetEvaluator::new(DefaultEvaluator::new(&env_config, 0, 5)?, probe_set);
+/// trainer.train(env, step_proc, &mut agent, &mut buffer, &mut recorder, &mut evaluator)?;
+/// ```
+pub struct ProbeSetEvaluator<E: Env, V: Evaluator<E>> {
+    /// Evaluator providing the performance metric.
+    evaluator: V,
+
+    /// Observations given to the agent.
+    probe_set: Vec<E::Obs>,
+}
+
+impl<E: Env, V: Evaluator<E>> Evaluator<E> for ProbeSetEvaluator<E, V> {
+    /// Evaluates the agent with the inner evaluator and the probe set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inner evaluator fails.
+    fn evaluate_result<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        let mut result = self.evaluator.evaluate_result(agent)?;
+        self.probe(agent, &mut result.record);
+        Ok(result)
+    }
+
+    /// Evaluates the agent with the inner evaluator given episode indices and the probe set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inner evaluator fails.
+    fn evaluate_with_indices<R>(
+        &mut self,
+        agent: &mut Box<dyn Agent<E, R>>,
+        ixs: &[usize],
+    ) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        let mut result = self.evaluator.evaluate_with_indices(agent, ixs)?;
+        self.probe(agent, &mut result.record);
+        Ok(result)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.evaluator.close()
+    }
+}
+
+impl<E: Env, V: Evaluator<E>> ProbeSetEvaluator<E, V> {
+    /// Adds the outputs of the agent for the probe set to the record.
+    fn probe<R>(&self, agent: &mut Box<dyn Agent<E, R>>, record: &mut Record)
+    where
+        R: ReplayBufferBase,
+    {
+        let mut sums = BTreeMap::<String, (f32, usize)>::new();
+        let mut accumulate = |key: String, v: f32| {
+            let sum = sums.entry(key).or_insert((0.0, 0));
+            sum.0 += v;
+            sum.1 += 1;
+        };
+
+        for (i, obs) in self.probe_set.iter().enumerate() {
+            for (key, value) in agent.probe(obs).into_iter_in_record() {
+                match value {
+                    RecordValue::Array1(v) => {
+                        let max = v.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+                        accumulate(format!("probe/Average max {}", key), max);
+                        for (j, x) in v.into_iter().enumerate() {
+                            record.insert(
+                                format!("probe/{}/{}/{}", i, key, j),
+                                RecordValue::Scalar(x),
+                            );
+                        }
+                    }
+                    RecordValue::Scalar(x) => {
+                        accumulate(format!("probe/Average {}", key), x);
+                        record.insert(format!("probe/{}/{}", i, key), RecordValue::Scalar(x));
+                    }
+                    value => record.insert(format!("probe/{}/{}", i, key), value),
+                }
+            }
+        }
+
+        for (key, (sum, n)) in sums.into_iter() {
+            record.insert(key, RecordValue::Scalar(sum / n as f32));
+        }
+    }
+
+    /// Constructs a new [`ProbeSetEvaluator`].
+    ///
+    /// # Arguments
+    ///
+    /// * `evaluator` - Evaluator providing the performance metric
+    /// * `probe_set` - Observations given to the agent in every evaluation
+    pub fn new(evaluator: V, probe_set: Vec<E::Obs>) -> Self {
+        Self {
+            evaluator,
+            probe_set,
+        }
+    }
+
+    /// Returns the probe set.
+    pub fn probe_set(&self) -> &[E::Obs] {
+        &self.probe_set
+    }
+
+    /// Collects a probe set by running a policy in the environment.
+    ///
+    /// Every `interval`-th observation is kept until `n_probes` observations are collected.
+    /// The environment is reset when an episode ends.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration of the environment
+    /// * `seed` - Random seed for environment initialization
+    /// * `policy` - Policy to run, typically a random policy
+    /// * `n_probes` - The number of observations to be collected
+    /// * `interval` - The number of steps between collected observations
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if building or resetting the environment fails.
+    pub fn collect<P: Policy<E>>(
+        config: &E::Config,
+        seed: i64,
+        policy: &mut P,
+        n_probes: usize,
+        interval: usize,
+    ) -> Result<Vec<E::Obs>> {
+        let interval = interval.max(1);
+        let mut env = E::build(config, seed)?;
+        let mut obs = env.reset(None)?;
+        let mut probe_set = Vec::with_capacity(n_probes);
+        let mut n_steps = 0;
+
+        while probe_set.len() < n_probes {
+            if n_steps % interval == 0 {
+                probe_set.push(obs.clone());
+            }
+            let act = policy.sample(&obs);
+            let (step, _) = env.step(&act);
+            n_steps += 1;
+            obs = match step.is_done() {
+                true => env.reset(None)?,
+                false => step.obs,
+            };
+        }
+
+        Ok(probe_set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        evaluator::DefaultEvaluator,
+        generic_replay_buffer::SimpleReplayBuffer,
+        test::{TestActBatch, TestAgent, TestEnv, TestObsBatch},
+    };
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+
+    #[test]
+    fn test_probe_set_evaluator() -> Result<()> {
+        let probe_set = ProbeSetEvaluator::<TestEnv, DefaultEvaluator<TestEnv>>::collect(
+            &0,
+            0,
+            &mut TestAgent {},
+            3,
+            2,
+        )?;
+        assert_eq!(probe_set.len(), 3);
+
+        let evaluator = DefaultEvaluator::<TestEnv>::new(&0, 0, 1)?.max_steps_per_episode(4);
+        let mut evaluator = ProbeSetEvaluator::new(evaluator, probe_set);
+        let mut agent: Box<dyn Agent<TestEnv, ReplayBuffer>> = Box::new(TestAgent {});
+        let (_, record) = evaluator.evaluate(&mut agent)?;
+
+        assert_eq!(record.get_scalar("probe/2/Q-value/1")?, 4.0);
+        assert_eq!(record.get_scalar("probe/Average max Q-value")?, 2.0);
+        assert_eq!(record.get_scalar("probe/Average obs")?, 2.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+//! Heuristics flagging suspicious patterns of rewards and actions in evaluation.
+use super::SuccessFn;
+use crate::{
+    record::{Record, RecordValue},
+    Env,
+};
+
+/// Predicate of actions at the limits of the action space, given the index of the environment.
+///
+/// The index is 0 except in vectorized environments, where actions are batched.
+type SaturationFn<E> = Box<dyn Fn(&<E as Env>::Act, usize) -> bool>;
+
+/// Heuristics for detecting reward hacking in evaluation episodes.
+///
+/// Agents exploiting bugs of reward shaping often show patterns that can be detected from
+/// evaluation episodes without inspecting them one by one. Each check is enabled by giving
+/// the information it requires:
+///
+/// * [`RewardHackingChecks::success()`] - Flags episodes obtaining positive return while the
+///   success flag extracted from the info never becomes `true`.
+/// * [`RewardHackingChecks::return_bounds()`] - Flags episode returns out of the bounds of
+///   the environment, e.g., those documented for the task.
+/// * [`RewardHackingChecks::saturation()`] - Flags evaluations where actions saturate at the
+///   limits of the action space in more than a ratio of steps, 0.95 by default.
+///
+/// Flagged checks are logged as warnings and recorded in the record of the evaluation.
+/// In vectorized environments, the checks are applied to the episodes of each environment.
+pub struct RewardHackingChecks<E: Env> {
+    /// Returns `true` if the info indicates the success of the task.
+    is_success: Option<SuccessFn<E>>,
+
+    /// Lower and upper bounds of episode returns.
+    return_bounds: Option<(f32, f32)>,
+
+    /// Returns `true` if the action is at the limits of the action space.
+    is_saturated: Option<SaturationFn<E>>,
+
+    /// Ratio of steps with saturated actions over which the evaluation is flagged.
+    saturation_threshold: f32,
+}
+
+impl<E: Env> Default for RewardHackingChecks<E> {
+    fn default() -> Self {
+        Self {
+            is_success: None,
+            return_bounds: None,
+            is_saturated: None,
+            saturation_threshold: 0.95,
+        }
+    }
+}
+
+impl<E: Env> RewardHackingChecks<E> {
+    /// Enables the check of rewards obtained without success, given the success flag of the info.
+    pub fn success(mut self, f: SuccessFn<E>) -> Self {
+        self.is_success = Some(f);
+        self
+    }
+
+    /// Enables the check of episode returns out of `[low, high]`.
+    pub fn return_bounds(mut self, low: f32, high: f32) -> Self {
+        self.return_bounds = Some((low, high));
+        self
+    }
+
+    /// Enables the check of saturated actions, given a predicate of actions at the limits.
+    pub fn saturation(mut self, f: SaturationFn<E>) -> Self {
+        self.is_saturated = Some(f);
+        self
+    }
+
+    /// Sets the ratio of steps with saturated actions over which the evaluation is flagged.
+    pub fn saturation_threshold(mut self, v: f32) -> Self {
+        self.saturation_threshold = v;
+        self
+    }
+
+    /// Returns a tracker of an evaluation in `n_envs` environments.
+    pub(super) fn tracker(&self, n_envs: usize) -> RewardHackingTracker<'_, E> {
+        RewardHackingTracker {
+            checks: self,
+            episode_return: vec![0.0; n_envs],
+            episode_success: vec![false; n_envs],
+            n_episodes: 0,
+            n_rewarded_failures: 0,
+            n_out_of_bounds: 0,
+            n_steps: 0,
+            n_saturated_steps: 0,
+        }
+    }
+}
+
+/// Accumulates statistics of the checks over the episodes of an evaluation.
+pub(super) struct RewardHackingTracker<'a, E: Env> {
+    checks: &'a RewardHackingChecks<E>,
+    episode_return: Vec<f32>,
+    episode_success: Vec<bool>,
+    n_episodes: usize,
+    n_rewarded_failures: usize,
+    n_out_of_bounds: usize,
+    n_steps: usize,
+    n_saturated_steps: usize,
+}
+
+impl<'a, E: Env> RewardHackingTracker<'a, E> {
+    /// Accumulates a step of the current episode in the `i`-th environment.
+    pub fn step(&mut self, i: usize, act: &E::Act, reward: f32, info: &E::Info) {
+        self.episode_return[i] += reward;
+        self.n_steps += 1;
+        if let Some(f) = self.checks.is_success.as_ref() {
+            self.episode_success[i] |= f(info, i);
+        }
+        if let Some(f) = self.checks.is_saturated.as_ref() {
+            self.n_saturated_steps += f(act, i) as usize;
+        }
+    }
+
+    /// Finishes the current episode in the `i`-th environment.
+    pub fn end_episode(&mut self, i: usize) {
+        let episode_return = std::mem::take(&mut self.episode_return[i]);
+        let episode_success = std::mem::take(&mut self.episode_success[i]);
+        self.n_episodes += 1;
+        if self.checks.is_success.is_some() && !episode_success && episode_return > 0.0 {
+            self.n_rewarded_failures += 1;
+        }
+        if let Some((low, high)) = self.checks.return_bounds {
+            if episode_return < low || episode_return > high {
+                self.n_out_of_bounds += 1;
+            }
+        }
+    }
+
+    /// Returns the record of the checks, logging flagged ones as warnings.
+    ///
+    /// The record has the statistics of the enabled checks and `reward_hacking/Warnings`,
+    /// the number of flagged checks.
+    pub fn record(&self) -> Record {
+        let mut record = Record::empty();
+        let mut n_warnings = 0;
+
+        if self.checks.is_success.is_some() {
+            record.insert(
+                "reward_hacking/Rewarded failures",
+                RecordValue::Scalar(self.n_rewarded_failures as f32),
+            );
+            if self.n_rewarded_failures > 0 {
+                n_warnings += 1;
+                log::warn!(
+                    "{} of {} episodes obtained positive return without success",
+                    self.n_rewarded_failures,
+                    self.n_episodes
+                );
+            }
+        }
+
+        if let Some((low, high)) = self.checks.return_bounds {
+            record.insert(
+                "reward_hacking/Out-of-bounds returns",
+                RecordValue::Scalar(self.n_out_of_bounds as f32),
+            );
+            if self.n_out_of_bounds > 0 {
+                n_warnings += 1;
+                log::warn!(
+                    "{} of {} episodes have returns out of [{}, {}]",
+                    self.n_out_of_bounds,
+                    self.n_episodes,
+                    low,
+                    high
+                );
+            }
+        }
+
+        if self.checks.is_saturated.is_some() {
+            let rate = self.n_saturated_steps as f32 / self.n_steps.max(1) as f32;
+            record.insert(
+                "reward_hacking/Saturated action rate",
+                RecordValue::Scalar(rate),
+            );
+            if rate > self.checks.saturation_threshold {
+                n_warnings += 1;
+                log::warn!(
+                    "Actions saturate at the limits in {:.1}% of steps",
+                    100.0 * rate
+                );
+            }
+        }
+
+        record.insert(
+            "reward_hacking/Warnings",
+            RecordValue::Scalar(n_warnings as f32),
+        );
+        record
+    }
+}
@@ -0,0 +1,191 @@
+//! Evaluation of the robustness of policies against perturbations.
+//!
+//! [`RobustnessSweep`] evaluates a trained policy, typically of continuous control like SAC,
+//! under a grid of action noise levels and observation noise levels. The resulting report
+//! quantifies how brittle the policy is before it is deployed.
+
+use super::{EvalResult, Evaluator};
+use crate::{
+    record::{Record, RecordValue},
+    Agent, Env, ReplayBufferBase,
+};
+use anyhow::Result;
+use rand::{rngs::SmallRng, Rng, RngCore, SeedableRng};
+
+/// Observations and actions that can be perturbed with Gaussian noise.
+pub trait AddNoise {
+    /// Returns a copy perturbed with zero-mean Gaussian noise of standard deviation `std`.
+    ///
+    /// Implementations for discrete values, like discrete actions, may return a copy as it is.
+    fn add_noise(&self, std: f32, rng: &mut dyn RngCore) -> Self;
+}
+
+/// Samples a value from the standard normal distribution with the Box-Muller transform.
+pub fn sample_standard_normal(rng: &mut dyn RngCore) -> f32 {
+    let u1 = 1.0 - rng.gen::<f32>(); // in (0, 1]
+    let u2 = rng.gen::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
+/// Evaluates a policy under a grid of action and observation noise levels.
+///
+/// For each pair of noise levels, the policy is run for a fixed number of episodes.
+/// Observations are perturbed before they are given to the policy, and actions are
+/// perturbed before they are applied to the environment.
+///
+/// The report returned by [`RobustnessSweep::run()`] contains:
+/// - `act_noise=<a>/obs_noise=<o>/Episode return` - The average return for each pair of noise levels
+/// - `robustness/Episode return` - The same values as a 2D array, whose rows and columns correspond
+///   to the action and observation noise levels, respectively
+/// - `robustness/Worst return` - The smallest average return over the grid
+///
+/// It also implements [`Evaluator`], where the performance metric is the average return
+/// over the grid, so that it can be used to choose the model that is robust to perturbations.
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut sweep = RobustnessSweep::<Env>::new(&env_config, 42, 5)?
+///     .act_noise(vec![0.0, 0.1, 0.3])
+///     .obs_noise(vec![0.0, 0.05]);
+/// agent.eval();
+/// let report = sweep.run(&mut agent)?;
+/// ```
+pub struct RobustnessSweep<E: Env> {
+    /// The number of episodes for each pair of noise levels.
+    n_episodes: usize,
+
+    /// Standard deviations of action noise.
+    act_noise: Vec<f32>,
+
+    /// Standard deviations of observation noise.
+    obs_noise: Vec<f32>,
+
+    /// The environment instance used for evaluation.
+    env: E,
+
+    /// Random number generator for noise.
+    rng: SmallRng,
+}
+
+impl<E: Env> RobustnessSweep<E>
+where
+    E::Obs: AddNoise,
+    E::Act: AddNoise,
+{
+    /// Constructs a new [`RobustnessSweep`] without noise.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - Configuration for the environment
+    /// * `seed` - Random seed for environment initialization and noise
+    /// * `n_episodes` - Number of episodes for each pair of noise levels
+    pub fn new(config: &E::Config, seed: i64, n_episodes: usize) -> Result<Self> {
+        Ok(Self {
+            n_episodes,
+            act_noise: vec![0.0],
+            obs_noise: vec![0.0],
+            env: E::build(config, seed)?,
+            rng: SmallRng::seed_from_u64(seed as _),
+        })
+    }
+
+    /// Sets standard deviations of action noise.
+    pub fn act_noise(mut self, v: Vec<f32>) -> Self {
+        self.act_noise = v;
+        self
+    }
+
+    /// Sets standard deviations of observation noise.
+    pub fn obs_noise(mut self, v: Vec<f32>) -> Self {
+        self.obs_noise = v;
+        self
+    }
+
+    /// Returns the average return over episodes with the given noise levels.
+    fn run_episodes<R>(
+        &mut self,
+        agent: &mut Box<dyn Agent<E, R>>,
+        act_noise: f32,
+        obs_noise: f32,
+    ) -> Result<f32>
+    where
+        R: ReplayBufferBase,
+    {
+        let mut r_total = 0f32;
+
+        for ix in 0..self.n_episodes {
+            let mut prev_obs = self.env.reset_with_index(ix)?;
+
+            loop {
+                let obs = match obs_noise > 0.0 {
+                    true => prev_obs.add_noise(obs_noise, &mut self.rng),
+                    false => prev_obs,
+                };
+                let act = agent.sample(&obs);
+                let act = match act_noise > 0.0 {
+                    true => act.add_noise(act_noise, &mut self.rng),
+                    false => act,
+                };
+                let (step, _) = self.env.step(&act);
+                r_total += step.reward[0];
+                if step.is_done() {
+                    break;
+                }
+                prev_obs = step.obs;
+            }
+        }
+
+        Ok(r_total / self.n_episodes as f32)
+    }
+
+    /// Runs the sweep and returns the report.
+    ///
+    /// The caller is responsible for switching the agent to evaluation mode.
+    pub fn run<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<Record>
+    where
+        R: ReplayBufferBase,
+    {
+        let (n_act, n_obs) = (self.act_noise.len(), self.obs_noise.len());
+        let mut record = Record::empty();
+        let mut returns = Vec::with_capacity(n_act * n_obs);
+
+        for a in self.act_noise.clone() {
+            for o in self.obs_noise.clone() {
+                let r = self.run_episodes(agent, a, o)?;
+                let key = format!("act_noise={}/obs_noise={}/Episode return", a, o);
+                record.insert(key, RecordValue::Scalar(r));
+                returns.push(r);
+            }
+        }
+
+        let worst = returns.iter().cloned().fold(f32::MAX, f32::min);
+        record.insert("robustness/Worst return", RecordValue::Scalar(worst));
+        record.insert(
+            "robustness/Episode return",
+            RecordValue::Array2(returns, [n_act, n_obs]),
+        );
+
+        Ok(record)
+    }
+}
+
+impl<E: Env> Evaluator<E> for RobustnessSweep<E>
+where
+    E::Obs: AddNoise,
+    E::Act: AddNoise,
+{
+    fn evaluate_result<R>(&mut self, agent: &mut Box<dyn Agent<E, R>>) -> Result<EvalResult>
+    where
+        R: ReplayBufferBase,
+    {
+        let record = self.run(agent)?;
+        let (returns, _) = record.get_array2("robustness/Episode return")?;
+        let performance = returns.iter().sum::<f32>() / returns.len() as f32;
+        Ok(EvalResult::new(performance, record))
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.env.close()
+    }
+}
@@ -0,0 +1,478 @@
+//! Exploration strategies of agents with discrete actions.
+//!
+//! An explorer takes actions given action values, e.g., outputs of the Q-network of DQN.
+//! Built-in explorers are selected with [`DiscreteExplorer`] in configurations of agents,
+//! and any type implementing [`Explorer`] can be given to agents after they are built.
+//!
+//! Explorers are independent of backends. A backend implements [`ExplorerBackend`] for
+//! a marker type, which converts action values and actions from and into its tensors,
+//! and agents of the backend hold explorers as `Box<dyn Explorer<Backend>>`.
+use crate::Schedule;
+use rand::{distributions::WeightedIndex, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+
+/// Conversion between tensors of a backend and values used by explorers.
+pub trait ExplorerBackend: 'static {
+    /// Tensor of action values, `[batch_size, n_actions]`, and of actions.
+    type Tensor;
+
+    /// Returns action values, `[batch_size][n_actions]`.
+    fn values(a: &Self::Tensor) -> Vec<Vec<f32>>;
+
+    /// Creates a tensor of actions, an action for each row of action values `a`, on the device
+    /// of `a`.
+    fn actions(a: &Self::Tensor, acts: Vec<i64>) -> Self::Tensor;
+}
+
+/// Exploration strategy taking actions given action values.
+pub trait Explorer<B: ExplorerBackend>: Send {
+    /// Takes actions given action values, `[batch_size, n_actions]`.
+    fn action(&mut self, action_value: &B::Tensor, rng: &mut dyn RngCore) -> B::Tensor;
+
+    /// Takes actions given action values, and returns `true` if they are the greedy actions.
+    ///
+    /// The default implementation always returns `false` for the flag.
+    fn action_with_best(
+        &mut self,
+        action_value: &B::Tensor,
+        rng: &mut dyn RngCore,
+    ) -> (B::Tensor, bool) {
+        (self.action(action_value, rng), false)
+    }
+
+    /// Sets a hyperparameter of the explorer, returns `true` if it is supported.
+    fn set_hyperparam(&mut self, _name: &str, _value: f64) -> bool {
+        false
+    }
+}
+
+/// Returns the greedy actions.
+fn greedy(values: &[Vec<f32>]) -> Vec<i64> {
+    values
+        .iter()
+        .map(|v| {
+            let mut best = 0;
+            for (i, x) in v.iter().enumerate() {
+                if *x > v[best] {
+                    best = i;
+                }
+            }
+            best as i64
+        })
+        .collect()
+}
+
+/// Built-in explorers for agents with discrete actions.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub enum DiscreteExplorer {
+    /// Softmax (Boltzmann) action selection.
+    Softmax(Softmax),
+
+    /// Epsilon-greedy action selection.
+    EpsilonGreedy(EpsilonGreedy),
+
+    /// Greedy action selection with UCB-style count bonus.
+    Ucb(Ucb),
+}
+
+impl<B: ExplorerBackend> Explorer<B> for DiscreteExplorer {
+    fn action(&mut self, a: &B::Tensor, rng: &mut dyn RngCore) -> B::Tensor {
+        match self {
+            Self::Softmax(softmax) => Explorer::<B>::action(softmax, a, rng),
+            Self::EpsilonGreedy(egreedy) => Explorer::<B>::action(egreedy, a, rng),
+            Self::Ucb(ucb) => Explorer::<B>::action(ucb, a, rng),
+        }
+    }
+
+    fn action_with_best(&mut self, a: &B::Tensor, rng: &mut dyn RngCore) -> (B::Tensor, bool) {
+        match self {
+            Self::Softmax(softmax) => Explorer::<B>::action_with_best(softmax, a, rng),
+            Self::EpsilonGreedy(egreedy) => Explorer::<B>::action_with_best(egreedy, a, rng),
+            Self::Ucb(ucb) => Explorer::<B>::action_with_best(ucb, a, rng),
+        }
+    }
+
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool {
+        match self {
+            Self::Softmax(softmax) => Explorer::<B>::set_hyperparam(softmax, name, value),
+            Self::EpsilonGreedy(egreedy) => Explorer::<B>::set_hyperparam(egreedy, name, value),
+            Self::Ucb(ucb) => Explorer::<B>::set_hyperparam(ucb, name, value),
+        }
+    }
+}
+
+/// Softmax explorer.
+///
+/// Actions are sampled with probabilities `softmax(a / temperature)`, where `a` is action values.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Softmax {
+    /// Temperature of the softmax, 1 by default.
+    #[serde(default = "default_temperature")]
+    pub temperature: f64,
+
+    /// Schedule of the temperature over steps, replacing the constant `temperature`.
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+
+    /// The number of steps.
+    #[serde(default)]
+    pub n_steps: usize,
+}
+
+fn default_temperature() -> f64 {
+    1.0
+}
+
+#[allow(clippy::new_without_default)]
+impl Softmax {
+    /// Constructs softmax explorer.
+    pub fn new() -> Self {
+        Self {
+            temperature: default_temperature(),
+            schedule: None,
+            n_steps: 0,
+        }
+    }
+
+    /// Sets the temperature.
+    pub fn temperature(mut self, v: f64) -> Self {
+        self.temperature = v;
+        self
+    }
+
+    /// Sets the schedule of the temperature.
+    pub fn schedule(mut self, v: Schedule) -> Self {
+        self.schedule = Some(v);
+        self
+    }
+
+    /// Returns the current temperature, and advances the schedule.
+    fn next_temperature(&mut self) -> f64 {
+        let t = match &self.schedule {
+            Some(schedule) => schedule.value(self.n_steps),
+            None => self.temperature,
+        };
+        self.n_steps += 1;
+        t
+    }
+}
+
+impl<B: ExplorerBackend> Explorer<B> for Softmax {
+    fn action(&mut self, a: &B::Tensor, rng: &mut dyn RngCore) -> B::Tensor {
+        let t = self.next_temperature();
+        let acts = B::values(a)
+            .into_iter()
+            .map(|v| {
+                let max = v.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+                let w = v
+                    .iter()
+                    .map(|x| (((x - max) as f64) / t).exp())
+                    .collect::<Vec<_>>();
+                rng.sample(WeightedIndex::new(&w).unwrap()) as i64
+            })
+            .collect();
+        B::actions(a, acts)
+    }
+
+    /// Supports `temperature`, which is kept constant after it is set.
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool {
+        match name {
+            "temperature" => {
+                self.temperature = value;
+                self.schedule = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Epsilon-greedy explorer.
+///
+/// The probability of taking a random action decreases linearly from `eps_start` to
+/// `eps_final` over `final_step` steps, unless `schedule` is given.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct EpsilonGreedy {
+    /// Number of optimization steps taken so far.
+    pub n_opts: usize,
+
+    /// Probability of a random action at the start.
+    pub eps_start: f64,
+
+    /// Probability of a random action after `final_step`.
+    pub eps_final: f64,
+
+    /// Optimization step at which the linear decay reaches `eps_final`.
+    pub final_step: usize,
+
+    /// Schedule of the probability of a random action, replacing the linear one.
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+}
+
+impl Default for EpsilonGreedy {
+    fn default() -> Self {
+        Self {
+            n_opts: 0,
+            eps_start: 1.0,
+            eps_final: 0.02,
+            final_step: 100_000,
+            schedule: None,
+        }
+    }
+}
+
+impl EpsilonGreedy {
+    /// Constructs epsilon-greedy explorer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Constructs epsilon-greedy explorer.
+    ///
+    /// TODO: improve interface.
+    pub fn with_final_step(final_step: usize) -> DiscreteExplorer {
+        DiscreteExplorer::EpsilonGreedy(Self {
+            final_step,
+            ..Self::default()
+        })
+    }
+
+    /// Constructs epsilon-greedy explorer.
+    pub fn with_params(eps_start: f64, eps_final: f64, final_step: usize) -> DiscreteExplorer {
+        DiscreteExplorer::EpsilonGreedy(Self {
+            n_opts: 0,
+            eps_start,
+            eps_final,
+            final_step,
+            schedule: None,
+        })
+    }
+
+    /// Returns the current probability of a random action.
+    pub fn eps(&self) -> f64 {
+        match &self.schedule {
+            Some(schedule) => schedule.value(self.n_opts),
+            None => {
+                let d = (self.eps_start - self.eps_final) / (self.final_step as f64);
+                (self.eps_start - d * self.n_opts as f64).max(self.eps_final)
+            }
+        }
+    }
+
+    /// Returns `true` with the current probability of a random action, and advances the schedule.
+    fn is_random(&mut self, rng: &mut dyn RngCore) -> bool {
+        let eps = self.eps();
+        let r = rng.gen::<f32>();
+        self.n_opts += 1;
+        r < eps as f32
+    }
+
+    fn random_action(values: &[Vec<f32>], rng: &mut dyn RngCore) -> Vec<i64> {
+        values
+            .iter()
+            .map(|v| rng.gen_range(0..v.len()) as i64)
+            .collect()
+    }
+
+    /// Set the epsilon value at the final step.
+    pub fn eps_final(self, v: f64) -> Self {
+        let mut s = self;
+        s.eps_final = v;
+        s
+    }
+
+    /// Set the epsilon value at the start.
+    pub fn eps_start(self, v: f64) -> Self {
+        let mut s = self;
+        s.eps_start = v;
+        s
+    }
+
+    /// Set the schedule of the epsilon value, replacing the linear one.
+    pub fn schedule(mut self, v: Schedule) -> Self {
+        self.schedule = Some(v);
+        self
+    }
+}
+
+impl<B: ExplorerBackend> Explorer<B> for EpsilonGreedy {
+    fn action(&mut self, a: &B::Tensor, rng: &mut dyn RngCore) -> B::Tensor {
+        Explorer::<B>::action_with_best(self, a, rng).0
+    }
+
+    fn action_with_best(&mut self, a: &B::Tensor, rng: &mut dyn RngCore) -> (B::Tensor, bool) {
+        let is_random = self.is_random(rng);
+        let values = B::values(a);
+        let best = greedy(&values);
+
+        if is_random {
+            let act = Self::random_action(&values, rng);
+            let is_best = act == best;
+            (B::actions(a, act), is_best)
+        } else {
+            (B::actions(a, best), true)
+        }
+    }
+
+    /// Supports `eps`, the probability of a random action, which is kept constant after it is set.
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool {
+        match name {
+            "eps" => {
+                self.eps_start = value;
+                self.eps_final = value;
+                self.schedule = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Explorer with UCB-style count bonus.
+///
+/// The greedy action is taken with respect to `a + c * sqrt(ln(t) / (n + 1))`, where `a` is
+/// action values, `t` is the number of steps and `n` is the number of times each action has
+/// been taken. The counts are shared over observations.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct Ucb {
+    /// Scale of the bonus.
+    pub c: f64,
+
+    /// The number of times each action has been taken.
+    #[serde(default)]
+    pub counts: Vec<usize>,
+
+    /// The number of steps.
+    #[serde(default)]
+    pub n_steps: usize,
+}
+
+impl Ucb {
+    /// Constructs UCB explorer with the given scale of the bonus.
+    pub fn new(c: f64) -> Self {
+        Self {
+            c,
+            counts: vec![],
+            n_steps: 0,
+        }
+    }
+
+    /// Returns the bonus of actions, `[n_actions]`.
+    fn bonus(&self) -> Vec<f32> {
+        let ln_t = ((self.n_steps + 1) as f64).ln();
+        self.counts
+            .iter()
+            .map(|n| (self.c * (ln_t / (*n + 1) as f64).sqrt()) as f32)
+            .collect()
+    }
+}
+
+impl<B: ExplorerBackend> Explorer<B> for Ucb {
+    fn action(&mut self, a: &B::Tensor, rng: &mut dyn RngCore) -> B::Tensor {
+        Explorer::<B>::action_with_best(self, a, rng).0
+    }
+
+    fn action_with_best(&mut self, a: &B::Tensor, _rng: &mut dyn RngCore) -> (B::Tensor, bool) {
+        let values = B::values(a);
+        let n_actions = values.first().map_or(0, |v| v.len());
+        if self.counts.len() != n_actions {
+            self.counts = vec![0; n_actions];
+        }
+        let bonus = self.bonus();
+        let best = greedy(&values);
+        let act = greedy(
+            &values
+                .iter()
+                .map(|v| v.iter().zip(bonus.iter()).map(|(x, b)| x + b).collect())
+                .collect::<Vec<_>>(),
+        );
+        for &ix in act.iter() {
+            self.counts[ix as usize] += 1;
+            self.n_steps += 1;
+        }
+        let is_best = act == best;
+        (B::actions(a, act), is_best)
+    }
+
+    /// Supports `c`, the scale of the bonus.
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool {
+        match name {
+            "c" => {
+                self.c = value;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::SmallRng, SeedableRng};
+
+    /// Backend with action values as nested vectors.
+    struct VecBackend;
+
+    impl ExplorerBackend for VecBackend {
+        type Tensor = Vec<Vec<f32>>;
+
+        fn values(a: &Self::Tensor) -> Vec<Vec<f32>> {
+            a.clone()
+        }
+
+        fn actions(_a: &Self::Tensor, acts: Vec<i64>) -> Self::Tensor {
+            vec![acts.into_iter().map(|a| a as f32).collect()]
+        }
+    }
+
+    #[test]
+    fn test_ucb() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut ucb = Ucb::new(1.0);
+        let a = vec![vec![0.1f32, 0.0, 0.0]];
+        let mut act = |ucb: &mut Ucb| Explorer::<VecBackend>::action(ucb, &a, &mut rng)[0][0];
+
+        // The bonus of actions not taken makes every action tried
+        let mut acts = (0..3).map(|_| act(&mut ucb) as i64).collect::<Vec<_>>();
+        acts.sort();
+        assert_eq!(acts, vec![0, 1, 2]);
+        assert_eq!(ucb.counts, vec![1, 1, 1]);
+        assert_eq!(ucb.n_steps, 3);
+
+        // Greedy action without the bonus
+        assert!(Explorer::<VecBackend>::set_hyperparam(&mut ucb, "c", 0.0));
+        assert_eq!(act(&mut ucb), 0.0);
+    }
+
+    #[test]
+    fn test_epsilon_greedy() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut explorer = EpsilonGreedy::new().schedule(Schedule::Linear {
+            start: 1.0,
+            end: 0.0,
+            n_steps: 10,
+        });
+        let a = vec![vec![0.0f32, 1.0, 0.0]; 4];
+        for _ in 0..10 {
+            Explorer::<VecBackend>::action(&mut explorer, &a, &mut rng);
+        }
+        assert_eq!(explorer.eps(), 0.0);
+
+        // Greedy actions after the schedule
+        let (act, is_best) = Explorer::<VecBackend>::action_with_best(&mut explorer, &a, &mut rng);
+        assert_eq!(act, vec![vec![1.0; 4]]);
+        assert!(is_best);
+    }
+
+    #[test]
+    fn test_softmax() {
+        let mut rng = SmallRng::seed_from_u64(42);
+        let mut explorer = Softmax::new().temperature(0.01);
+        let a = vec![vec![0.0f32, 1.0, 0.0]; 4];
+        let act = Explorer::<VecBackend>::action(&mut explorer, &a, &mut rng);
+        assert_eq!(act, vec![vec![1.0; 4]]);
+        assert_eq!(explorer.n_steps, 1);
+    }
+}
@@ -0,0 +1,236 @@
+//! Stacking the most recent observations of environments.
+//!
+//! Environments with image observations often provide a single frame at each step,
+//! while agents require several recent frames to infer motion. This module provides
+//! [`FrameStackEnv`], an environment wrapper stacking the most recent frames independently
+//! of environments, and [`FrameStack`], the buffer of recent frames used in the wrapper.
+//!
+//! Since the wrapper changes the observations of the environment, policies and replay buffers
+//! receive the same stacked observations. Thus, transitions can be produced with
+//! [`SimpleStepProcessor`].
+//!
+//! [`SimpleStepProcessor`]: crate::generic_replay_buffer::SimpleStepProcessor
+
+use crate::{normalize_obs::convert_step, record::Record, Env, Obs, Step};
+use anyhow::{bail, Result};
+use std::collections::VecDeque;
+
+/// Observations that can be concatenated along an axis.
+///
+/// This trait is required for the observation of environments wrapped with [`FrameStackEnv`].
+pub trait StackFrames: Obs {
+    /// Concatenates frames along `axis`, where the first frame is the oldest one.
+    fn concat_frames(frames: &[Self], axis: usize) -> Self
+    where
+        Self: Sized;
+}
+
+/// A buffer of the most recent observations of an environment.
+pub struct FrameStack<O> {
+    n_frames: usize,
+    axis: usize,
+    frames: VecDeque<O>,
+}
+
+impl<O: StackFrames> FrameStack<O> {
+    /// Constructs a buffer stacking `n_frames` observations along `axis`.
+    pub fn new(n_frames: usize, axis: usize) -> Self {
+        assert!(n_frames > 0, "n_frames must be positive");
+        Self {
+            n_frames,
+            axis,
+            frames: VecDeque::with_capacity(n_frames),
+        }
+    }
+
+    /// Fills the buffer with the initial observation of an episode and returns the stacked one.
+    pub fn reset(&mut self, init_obs: O) -> O {
+        self.frames.clear();
+        self.frames.resize(self.n_frames, init_obs);
+        self.stacked()
+    }
+
+    /// Pushes an observation, discarding the oldest one, and returns the stacked observation.
+    pub fn push(&mut self, obs: O) -> O {
+        if self.frames.len() == self.n_frames {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(obs);
+        self.stacked()
+    }
+
+    fn stacked(&mut self) -> O {
+        O::concat_frames(self.frames.make_contiguous(), self.axis)
+    }
+}
+
+/// Configuration of [`FrameStackEnv`].
+pub struct FrameStackEnvConfig<E: Env> {
+    /// Configuration of the wrapped environment.
+    pub env_config: E::Config,
+
+    /// The number of stacked frames.
+    pub n_frames: usize,
+
+    /// The axis along which frames are concatenated.
+    ///
+    /// It is the axis of the observation including the first axis for environments.
+    /// For example, 1 for observations of shape `[1, channels, height, width]`.
+    pub axis: usize,
+}
+
+impl<E: Env> Clone for FrameStackEnvConfig<E> {
+    fn clone(&self) -> Self {
+        Self {
+            env_config: self.env_config.clone(),
+            n_frames: self.n_frames,
+            axis: self.axis,
+        }
+    }
+}
+
+impl<E: Env> FrameStackEnvConfig<E> {
+    /// Constructs a configuration stacking 4 frames along axis 1.
+    pub fn new(env_config: E::Config) -> Self {
+        Self {
+            env_config,
+            n_frames: 4,
+            axis: 1,
+        }
+    }
+
+    /// Sets the number of stacked frames.
+    pub fn n_frames(mut self, v: usize) -> Self {
+        self.n_frames = v;
+        self
+    }
+
+    /// Sets the axis along which frames are concatenated.
+    pub fn axis(mut self, v: usize) -> Self {
+        self.axis = v;
+        self
+    }
+}
+
+/// An environment wrapper stacking the most recent observations.
+///
+/// The `n_frames` most recent observations are concatenated along `axis`, including `init_obs`
+/// of [`Step`]. At the beginning of an episode, the buffer is filled with the initial
+/// observation. Vectorized environments are not supported.
+pub struct FrameStackEnv<E: Env> {
+    env: E,
+    frame_stack: FrameStack<E::Obs>,
+}
+
+impl<E: Env> FrameStackEnv<E>
+where
+    E::Obs: StackFrames,
+{
+    fn stack_step(&mut self, step: Step<Self>) -> Step<Self> {
+        let obs = self.frame_stack.push(step.obs);
+        let init_obs = step.init_obs.map(|o| self.frame_stack.reset(o));
+        Step {
+            obs,
+            init_obs,
+            ..step
+        }
+    }
+
+    /// Returns the wrapped environment.
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+}
+
+impl<E: Env> Env for FrameStackEnv<E>
+where
+    E::Obs: StackFrames,
+{
+    type Config = FrameStackEnvConfig<E>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    /// Returns an error if `n_frames` is zero or the wrapped environment is vectorized.
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        if config.n_frames == 0 {
+            bail!("n_frames must be positive");
+        }
+        let env = E::build(&config.env_config, seed)?;
+        if env.n_envs() != 1 {
+            bail!("FrameStackEnv does not support vectorized environments");
+        }
+        Ok(Self {
+            env,
+            frame_stack: FrameStack::new(config.n_frames, config.axis),
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (step, record) = self.env.step(a);
+        (self.stack_step(convert_step(step)), record)
+    }
+
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (step, record) = self.env.step_with_reset(a);
+        (self.stack_step(convert_step(step)), record)
+    }
+
+    fn try_step(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        let (step, record) = self.env.try_step(a)?;
+        Ok((self.stack_step(convert_step(step)), record))
+    }
+
+    fn try_step_with_reset(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        let (step, record) = self.env.try_step_with_reset(a)?;
+        Ok((self.stack_step(convert_step(step)), record))
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        let obs = self.env.reset(is_done)?;
+        Ok(self.frame_stack.reset(obs))
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        let obs = self.env.reset_with_index(ix)?;
+        Ok(self.frame_stack.reset(obs))
+    }
+
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        self.env.probe_actions(n_random)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.env.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{CountAct, CountEnv, CountEnvConfig};
+    use anyhow::Result;
+
+    #[test]
+    fn test_frame_stack_env() -> Result<()> {
+        // Episodes are terminated at the third step
+        let env_config = CountEnvConfig::default().length(3);
+        let config = FrameStackEnvConfig::<CountEnv>::new(env_config).n_frames(3);
+        let mut env = FrameStackEnv::build(&config, 0)?;
+        assert_eq!(env.reset(None)?.0[0], vec![0.0, 0.0, 0.0]);
+
+        let expected = vec![
+            (vec![0.0, 0.0, 1.0], None),
+            (vec![0.0, 1.0, 2.0], None),
+            (vec![1.0, 2.0, 3.0], Some(vec![0.0, 0.0, 0.0])),
+            // New episode
+            (vec![0.0, 0.0, 1.0], None),
+        ];
+        for (obs, init_obs) in expected.into_iter() {
+            let (step, _) = env.step_with_reset(&CountAct(vec![0]));
+            assert_eq!(step.obs.0[0], obs);
+            assert_eq!(step.init_obs.map(|o| o.0[0].clone()), init_obs);
+        }
+        Ok(())
+    }
+}
@@ -10,6 +10,11 @@
 //! - [`GenericTransitionBatch`]: A generic batch structure for transitions
 //! - [`SimpleStepProcessor`]: A processor for converting environment steps to transitions
 //! - [`PerConfig`]: Configuration for prioritized experience replay
+//! - [`ChunkWriter`]: A writer of checksummed chunk files for persisting replay buffers
+//! - [`EpisodeWriter`]: A writer of episodes into chunk files, read with [`EpisodeReader`]
+//!   to reconstruct replay buffers for offline training
+//! - [`ReplayBufferSchema`]: A schema of persisted replay buffers with migration of old chunks
+//! - [`SelectedTransitions`]: Transitions with the largest TD errors or custom scores for inspection
 //!
 //! # Features
 //!
@@ -18,12 +23,31 @@
 //! - Prioritized experience replay with importance sampling
 //! - Configurable weight normalization
 //! - Step processing for non-vectorized environments
+//! - Corruption detection and repair of persisted chunks ([`verify_chunks()`], [`repair_chunks()`])
 
 mod base;
 mod batch;
+mod chunk;
 mod config;
+mod episode;
+mod her;
+mod inspect;
+mod schema;
 mod step_proc;
 pub use base::{IwScheduler, SimpleReplayBuffer, WeightNormalizer};
 pub use batch::{BatchBase, GenericTransitionBatch};
-pub use config::{PerConfig, SimpleReplayBufferConfig};
-pub use step_proc::{SimpleStepProcessor, SimpleStepProcessorConfig};
+pub use chunk::{
+    read_chunks, repair_chunks, scan_chunks, verify_chunks, ChunkCorruption, ChunkReport,
+    ChunkWriter,
+};
+pub use config::{
+    BootstrapConfig, MemoryLimitConfig, MemoryLimitPolicy, PerConfig, SimpleReplayBufferConfig,
+};
+pub use episode::{Episode, EpisodeReader, EpisodeWriter};
+pub use her::{GoalBatch, GoalRewardFn, HerReplayBuffer, HerReplayBufferConfig, HerStrategy};
+pub use inspect::{DumpBatch, DumpedTransition, SelectedTransitions};
+pub use schema::{
+    read_chunks_with_schema, scan_chunks_with_schema, schema_path, ReplayBufferSchema,
+    SchemaMigration,
+};
+pub use step_proc::{ActDistribution, SimpleStepProcessor, SimpleStepProcessorConfig};
@@ -8,11 +8,17 @@
 
 mod iw_scheduler;
 mod sum_tree;
-use super::{config::PerConfig, BatchBase, GenericTransitionBatch, SimpleReplayBufferConfig};
-use crate::{ExperienceBufferBase, ReplayBufferBase, TransitionBatch};
+use super::{
+    config::{MemoryLimitConfig, MemoryLimitPolicy, PerConfig},
+    BatchBase, GenericTransitionBatch, SelectedTransitions, SimpleReplayBufferConfig,
+};
+use crate::{
+    record::{Record, RecordValue},
+    ExperienceBufferBase, ReplayBufferBase, TransitionBatch,
+};
 use anyhow::Result;
 pub use iw_scheduler::IwScheduler;
-use rand::{rngs::StdRng, RngCore, SeedableRng};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
 use sum_tree::SumTree;
 pub use sum_tree::WeightNormalizer;
 
@@ -39,12 +45,65 @@ impl PerState {
     fn new(capacity: usize, per_config: &PerConfig) -> Self {
         Self {
             sum_tree: SumTree::new(capacity, per_config.alpha, per_config.normalize),
-            iw_scheduler: IwScheduler::new(
-                per_config.beta_0,
-                per_config.beta_final,
-                per_config.n_opts_final,
-            ),
+            iw_scheduler: match &per_config.beta_schedule {
+                Some(schedule) => IwScheduler::with_schedule(schedule.clone()),
+                None => IwScheduler::new(
+                    per_config.beta_0,
+                    per_config.beta_final,
+                    per_config.n_opts_final,
+                ),
+            },
+        }
+    }
+}
+
+/// Statistics of batches sampled from [`SimpleReplayBuffer`] since they were reported.
+#[derive(Default)]
+struct SampleStats {
+    n_batches: usize,
+    n_samples: usize,
+    sum_age: f64,
+    sum_sq_age: f64,
+    max_age: u64,
+    sum_reward: f64,
+    n_terminated: usize,
+}
+
+impl SampleStats {
+    /// Returns the record of the statistics, empty if no batch has been sampled.
+    fn record(&self) -> Record {
+        if self.n_samples == 0 {
+            return Record::empty();
         }
+        let n = self.n_samples as f64;
+        let mean_age = self.sum_age / n;
+        let std_age = (self.sum_sq_age / n - mean_age * mean_age).max(0.0).sqrt();
+        Record::from_slice(&[
+            (
+                "replay_buffer_sample_age_mean",
+                RecordValue::Scalar(mean_age as f32),
+            ),
+            (
+                "replay_buffer_sample_age_std",
+                RecordValue::Scalar(std_age as f32),
+            ),
+            (
+                "replay_buffer_sample_age_max",
+                RecordValue::Scalar(self.max_age as f32),
+            ),
+            (
+                "replay_buffer_sample_reward_mean",
+                RecordValue::Scalar((self.sum_reward / n) as f32),
+            ),
+            (
+                "replay_buffer_sample_terminal_fraction",
+                RecordValue::Scalar(self.n_terminated as f32 / self.n_samples as f32),
+            ),
+            (
+                "replay_buffer_sample_batches",
+                RecordValue::Scalar(self.n_batches as f32),
+            ),
+        ])
     }
 }
 
@@ -71,8 +130,12 @@ impl PerState {
 ///         beta_0: 0.4,
 ///         beta_final: 1.0,
 ///         n_opts_final: 100000,
+///         beta_schedule: None,
 ///         normalize: true,
 ///     }),
+///     mc_gamma: None,
+///     bootstrap: None,
+///     memory_limit: None,
 /// };
 ///
 /// let mut buffer = SimpleReplayBuffer::<Tensor, Tensor>::build(&config);
@@ -120,6 +183,43 @@ where
 
     /// State for prioritized experience replay, if enabled.
     per_state: Option<PerState>,
+
+    /// Discount factor of Monte Carlo returns, if enabled.
+    mc_gamma: Option<f32>,
+
+    /// Number of the most recent transitions of the ongoing episode,
+    /// used only when Monte Carlo returns are enabled.
+    n_pending: usize,
+
+    /// Number of heads and the probability of bootstrap masks, if enabled.
+    bootstrap: Option<(usize, f32)>,
+
+    /// Storage for bootstrap masks, flattened in the shape of `[capacity, n_heads]`.
+    mask: Vec<f32>,
+
+    /// Storage for Monte Carlo returns, used only when they are enabled.
+    mc_return: Vec<f32>,
+
+    /// Configuration of prioritized experience replay, used when the buffer is resized.
+    per_config: Option<PerConfig>,
+
+    /// Configuration of the memory limit, if enabled.
+    memory_limit: Option<MemoryLimitConfig>,
+
+    /// Estimated number of bytes per transition, if known.
+    bytes_per_transition: Option<usize>,
+
+    /// Number of transitions rejected due to the memory limit.
+    n_rejected: usize,
+
+    /// Number of transitions pushed before each stored transition, used to compute its age.
+    pushed_at: Vec<u64>,
+
+    /// Total number of transitions pushed into the buffer.
+    n_pushed: u64,
+
+    /// Statistics of sampled batches.
+    sample_stats: SampleStats,
 }
 
 impl<O, A> SimpleReplayBuffer<O, A>
@@ -234,6 +334,162 @@ where
         }
     }
 
+    /// Records the number of transitions pushed before `len` transitions from the specified index.
+    fn push_pushed_at(&mut self, i: usize, len: usize) {
+        for j in 0..len {
+            self.pushed_at[(i + j) % self.capacity] = self.n_pushed + j as u64;
+        }
+        self.n_pushed += len as u64;
+    }
+
+    /// Accumulates statistics of a sampled batch.
+    fn update_sample_stats(&mut self, ixs: &[usize]) {
+        let stats = &mut self.sample_stats;
+        stats.n_batches += 1;
+        for &ix in ixs.iter() {
+            // Number of transitions pushed after the sampled one
+            let age = self.n_pushed - self.pushed_at[ix] - 1;
+            stats.n_samples += 1;
+            stats.sum_age += age as f64;
+            stats.sum_sq_age += (age as f64).powi(2);
+            stats.max_age = stats.max_age.max(age);
+            stats.sum_reward += self.reward[ix] as f64;
+            stats.n_terminated += self.is_terminated[ix] as usize;
+        }
+    }
+
+    /// Draws bootstrap masks for `len` transitions from the specified index.
+    fn push_mask(&mut self, i: usize, len: usize) {
+        let (n_heads, p) = self.bootstrap.unwrap();
+        for j in 0..len {
+            let ix = (i + j) % self.capacity;
+            for k in 0..n_heads {
+                self.mask[ix * n_heads + k] = (self.rng.gen::<f32>() < p) as i32 as f32;
+            }
+        }
+    }
+
+    /// Samples bootstrap masks for the given indices, if enabled.
+    fn sample_mask(&self, ixs: &Vec<usize>) -> Option<Vec<f32>> {
+        let (n_heads, _) = self.bootstrap?;
+        Some(
+            ixs.iter()
+                .flat_map(|ix| self.mask[ix * n_heads..(ix + 1) * n_heads].iter().cloned())
+                .collect(),
+        )
+    }
+
+    /// Computes Monte Carlo returns of the pending transitions ending at `end`.
+    ///
+    /// # Arguments
+    ///
+    /// * `end` - Index of the last transition of the episode
+    /// * `n` - Number of transitions of the episode in the buffer
+    fn close_episode(&mut self, end: usize, n: usize) {
+        let gamma = self.mc_gamma.unwrap();
+        let mut g = 0f32;
+        for k in 0..n {
+            let ix = (end + self.capacity - k) % self.capacity;
+            g = self.reward[ix] + gamma * g;
+            self.mc_return[ix] = g;
+        }
+    }
+
+    /// Updates the pending transitions after `len` transitions are pushed at `i`.
+    fn update_pending(&mut self, i: usize, len: usize) {
+        for t in 0..len {
+            let ix = (i + t) % self.capacity;
+            self.n_pending = (self.n_pending + 1).min(self.capacity);
+            if self.is_terminated[ix] == 1 || self.is_truncated[ix] == 1 {
+                self.close_episode(ix, self.n_pending);
+                self.n_pending = 0;
+            }
+        }
+    }
+
+    /// Estimates the number of bytes per transition from pushed data.
+    fn estimate_bytes_per_transition(
+        &self,
+        obs: &O,
+        act: &A,
+        next_obs: &O,
+        len: usize,
+    ) -> Option<usize> {
+        let bytes = obs.size_in_bytes()? + act.size_in_bytes()? + next_obs.size_in_bytes()?;
+        let n_heads = self.bootstrap.map_or(0, |(n_heads, _)| n_heads);
+        let scalars = std::mem::size_of::<f32>() * (1 + n_heads)
+            + 2 * std::mem::size_of::<i8>()
+            + std::mem::size_of::<u64>();
+        Some(bytes / len.max(1) + scalars)
+    }
+
+    /// Returns the maximum number of transitions under the memory limit, if known.
+    fn max_transitions(&self) -> Option<usize> {
+        let max_bytes = self.memory_limit.as_ref()?.max_bytes;
+        let bytes_per_transition = self.bytes_per_transition?;
+        Some((max_bytes / bytes_per_transition.max(1)).max(1))
+    }
+
+    /// Reallocates the storage of the empty buffer with the given capacity.
+    fn resize(&mut self, capacity: usize) {
+        debug_assert_eq!(self.size, 0);
+        let n_heads = self.bootstrap.map_or(0, |(n_heads, _)| n_heads);
+        self.capacity = capacity;
+        self.obs = O::new(capacity);
+        self.act = A::new(capacity);
+        self.next_obs = O::new(capacity);
+        self.reward = vec![0.; capacity];
+        self.is_terminated = vec![0; capacity];
+        self.is_truncated = vec![0; capacity];
+        self.pushed_at = vec![0; capacity];
+        self.per_state = self
+            .per_config
+            .as_ref()
+            .map(|per_config| PerState::new(capacity, per_config));
+        self.mask = vec![0.; capacity * n_heads];
+        self.mc_return = vec![0.; self.mc_gamma.map_or(0, |_| capacity)];
+    }
+
+    /// Applies the memory limit, reducing the capacity if the buffer is empty.
+    fn apply_memory_limit(&mut self) {
+        let policy = self.memory_limit.as_ref().map(|config| config.policy);
+        match (policy, self.max_transitions()) {
+            (None, _) => {}
+            (Some(_), None) => log::warn!(
+                "The memory limit of the replay buffer is not enforced because the size of transitions is unknown"
+            ),
+            (Some(MemoryLimitPolicy::Evict), Some(max)) if max < self.capacity => {
+                log::warn!(
+                    "The capacity of the replay buffer is reduced from {} to {} due to the memory limit",
+                    self.capacity,
+                    max
+                );
+                self.resize(max);
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if `len` transitions can be pushed under the memory limit.
+    fn admit(&self, len: usize) -> bool {
+        match (self.memory_limit.as_ref(), self.max_transitions()) {
+            (Some(config), Some(max)) if config.policy == MemoryLimitPolicy::Backpressure => {
+                (self.size + len).min(self.capacity) <= max
+            }
+            _ => true,
+        }
+    }
+
+    /// Returns the estimated number of bytes of transitions in the buffer, if known.
+    pub fn memory_usage(&self) -> Option<usize> {
+        self.bytes_per_transition.map(|b| b * self.size)
+    }
+
+    /// Returns the number of transitions rejected due to the memory limit.
+    pub fn num_rejected(&self) -> usize {
+        self.n_rejected
+    }
+
     /// Returns a batch containing all actions in the buffer.
     ///
     /// # Warning
@@ -265,6 +521,83 @@ where
     pub fn sum_rewards(&self) -> f32 {
         self.reward.iter().sum()
     }
+
+    /// Returns the transitions at the given indices.
+    fn transitions(
+        &self,
+        ixs: Vec<usize>,
+        weight: Option<Vec<f32>>,
+    ) -> GenericTransitionBatch<O, A> {
+        GenericTransitionBatch {
+            obs: self.obs.sample(&ixs),
+            act: self.act.sample(&ixs),
+            next_obs: self.next_obs.sample(&ixs),
+            reward: self.sample_reward(&ixs),
+            is_terminated: self.sample_is_terminated(&ixs),
+            is_truncated: self.sample_is_truncated(&ixs),
+            mask: self.sample_mask(&ixs),
+            mc_return: self
+                .mc_gamma
+                .map(|_| ixs.iter().map(|&ix| self.mc_return[ix]).collect()),
+            ix_sample: Some(ixs),
+            weight,
+        }
+    }
+
+    /// Returns the `k` transitions with the largest scores in descending order.
+    fn select_top_k(&self, mut scores: Vec<(usize, f32)>, k: usize) -> SelectedTransitions<O, A> {
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scores.truncate(k);
+        let (ixs, scores) = scores.into_iter().unzip();
+        SelectedTransitions {
+            scores,
+            batch: self.transitions(ixs, None),
+        }
+    }
+
+    /// Returns the `k` transitions with the largest priorities, i.e., the absolute TD errors
+    /// reported with [`ReplayBufferBase::update_priority()`].
+    ///
+    /// Transitions whose TD errors have not been reported yet have the maximum priority
+    /// at the time they were pushed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if prioritized experience replay is not enabled.
+    pub fn top_k_by_priority(&self, k: usize) -> Result<SelectedTransitions<O, A>> {
+        let sum_tree = match &self.per_state {
+            Some(per_state) => &per_state.sum_tree,
+            None => anyhow::bail!("Prioritized experience replay is not enabled"),
+        };
+        let scores = (0..self.size)
+            .map(|ix| (ix, sum_tree.priority(ix)))
+            .collect();
+        Ok(self.select_top_k(scores, k))
+    }
+
+    /// Returns the `k` transitions with the largest scores computed by `score`.
+    ///
+    /// `score` is called with chunks of at most `chunk_size` transitions in the buffer,
+    /// and returns a score for each transition, e.g., the absolute TD errors computed
+    /// by an agent.
+    pub fn top_k_by_score(
+        &self,
+        k: usize,
+        chunk_size: usize,
+        mut score: impl FnMut(&GenericTransitionBatch<O, A>) -> Result<Vec<f32>>,
+    ) -> Result<SelectedTransitions<O, A>> {
+        let mut scores = vec![];
+        for start in (0..self.size).step_by(chunk_size.max(1)) {
+            let ixs = (start..(start + chunk_size).min(self.size)).collect::<Vec<_>>();
+            let chunk = self.transitions(ixs.clone(), None);
+            let s = score(&chunk)?;
+            if s.len() != ixs.len() {
+                anyhow::bail!("{} scores are given for {} transitions", s.len(), ixs.len());
+            }
+            scores.extend(ixs.into_iter().zip(s));
+        }
+        Ok(self.select_top_k(scores, k))
+    }
 }
 
 impl<O, A> ExperienceBufferBase for SimpleReplayBuffer<O, A>
@@ -279,15 +612,44 @@ where
         self.size
     }
 
+    /// Returns the fill level of the buffer, i.e., the ratio of stored transitions to the
+    /// capacity, and the statistics of the batches sampled since the last call: the mean,
+    /// standard deviation and maximum of the ages of sampled transitions, i.e., the numbers of
+    /// transitions pushed after them, the mean of sampled rewards and the fraction of terminal
+    /// transitions.
+    ///
+    /// With the memory limit, it also returns the estimated memory usage in bytes and the
+    /// number of transitions rejected due to the limit.
+    fn record(&mut self) -> Record {
+        let mut record = std::mem::take(&mut self.sample_stats).record();
+        record.insert(
+            "replay_buffer_fill",
+            RecordValue::Scalar(self.size as f32 / self.capacity as f32),
+        );
+        if let Some(bytes) = self.memory_usage() {
+            record.insert("replay_buffer_bytes", RecordValue::Scalar(bytes as f32));
+            record.insert(
+                "replay_buffer_rejected",
+                RecordValue::Scalar(self.n_rejected as f32),
+            );
+        }
+        record
+    }
+
     /// Adds a new transition to the buffer.
     ///
+    /// With [`MemoryLimitPolicy::Backpressure`], the transition is not added if the memory
+    /// limit is reached. A warning is logged at the first rejection, and the number of
+    /// rejected transitions is returned by [`SimpleReplayBuffer::num_rejected()`] and
+    /// recorded as `replay_buffer_rejected`.
+    ///
     /// # Arguments
     ///
     /// * `tr` - The transition to add
     ///
     /// # Returns
     ///
-    /// `Ok(())` if the transition was added successfully
+    /// `Ok(())` if the transition was added or rejected due to the memory limit
     ///
     /// # Errors
     ///
@@ -295,6 +657,25 @@ where
     fn push(&mut self, tr: Self::Item) -> Result<()> {
         let len = tr.len(); // batch size
         let (obs, act, next_obs, reward, is_terminated, is_truncated, _, _) = tr.unpack();
+
+        // Memory accounting with the first transitions
+        if self.size == 0 && self.bytes_per_transition.is_none() {
+            self.bytes_per_transition =
+                self.estimate_bytes_per_transition(&obs, &act, &next_obs, len);
+            self.apply_memory_limit();
+        }
+        if !self.admit(len) {
+            if self.n_rejected == 0 {
+                log::warn!(
+                    "The memory limit of the replay buffer is reached with {} transitions. \
+                     New transitions are rejected and counted in replay_buffer_rejected",
+                    self.size
+                );
+            }
+            self.n_rejected += len;
+            return Ok(());
+        }
+
         self.obs.push(self.i, obs);
         self.act.push(self.i, act);
         self.next_obs.push(self.i, next_obs);
@@ -306,6 +687,16 @@ where
             self.set_priority(len)
         };
 
+        if self.mc_gamma.is_some() {
+            self.update_pending(self.i, len);
+        }
+
+        if self.bootstrap.is_some() {
+            self.push_mask(self.i, len);
+        }
+
+        self.push_pushed_at(self.i, len);
+
         self.i = (self.i + len) % self.capacity;
         self.size += len;
         if self.size >= self.capacity {
@@ -314,6 +705,25 @@ where
 
         Ok(())
     }
+
+    /// Adds a new transition to the buffer with the priorities of the transitions.
+    ///
+    /// With prioritized experience replay, the transitions get the given priorities instead
+    /// of the maximum priority in the buffer. Otherwise, the priorities are ignored.
+    fn push_with_priorities(&mut self, tr: Self::Item, priorities: &[f32]) -> Result<()> {
+        let (i, n_rejected) = (self.i, self.n_rejected);
+        self.push(tr)?;
+
+        if let Some(per_state) = &mut self.per_state {
+            if self.n_rejected == n_rejected {
+                for (j, &p) in priorities.iter().enumerate() {
+                    per_state.sum_tree.update((i + j) % self.capacity, p);
+                }
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<O, A> ReplayBufferBase for SimpleReplayBuffer<O, A>
@@ -335,12 +745,22 @@ where
     /// A new instance of the replay buffer
     fn build(config: &Self::Config) -> Self {
         let capacity = config.capacity;
-        let per_state = match &config.per_config {
-            Some(per_config) => Some(PerState::new(capacity, per_config)),
-            None => None,
-        };
+        let per_state = config
+            .per_config
+            .as_ref()
+            .map(|per_config| PerState::new(capacity, per_config));
+        assert!(
+            per_state.is_none() || config.mc_gamma.is_none(),
+            "Monte Carlo returns are not supported with prioritized experience replay"
+        );
+        let bootstrap = config.bootstrap.as_ref().map(|b| (b.n_heads, b.p));
+        let n_heads = bootstrap.map_or(0, |(n_heads, _)| n_heads);
+        let bytes_per_transition = config
+            .memory_limit
+            .as_ref()
+            .and_then(|m| m.bytes_per_transition);
 
-        Self {
+        let mut buffer = Self {
             capacity,
             i: 0,
             size: 0,
@@ -352,7 +772,23 @@ where
             is_truncated: vec![0; capacity],
             rng: StdRng::seed_from_u64(config.seed as _),
             per_state,
+            mc_gamma: config.mc_gamma,
+            n_pending: 0,
+            bootstrap,
+            mask: vec![0.; capacity * n_heads],
+            mc_return: vec![0.; config.mc_gamma.map_or(0, |_| capacity)],
+            per_config: config.per_config.clone(),
+            memory_limit: config.memory_limit.clone(),
+            bytes_per_transition,
+            n_rejected: 0,
+            pushed_at: vec![0; capacity],
+            n_pushed: 0,
+            sample_stats: SampleStats::default(),
+        };
+        if bytes_per_transition.is_some() {
+            buffer.apply_memory_limit();
         }
+        buffer
     }
 
     /// Samples a batch of transitions from the buffer.
@@ -372,6 +808,7 @@ where
     ///
     /// Returns an error if:
     /// - The buffer is empty
+    /// - Monte Carlo returns are enabled and no episode has finished
     /// - The requested batch size is larger than the buffer size
     fn batch(&mut self, size: usize) -> Result<Self::Batch> {
         let (ixs, weight) = if let Some(per_state) = &self.per_state {
@@ -380,6 +817,17 @@ where
             let (ixs, weight) = sum_tree.sample(size, beta);
             let ixs = ixs.iter().map(|&ix| ix as usize).collect();
             (ixs, Some(weight))
+        } else if self.mc_gamma.is_some() {
+            // Sample from transitions of finished episodes, which precede the pending ones
+            let n_ready = self.size - self.n_pending;
+            if n_ready == 0 {
+                anyhow::bail!("No finished episode in the replay buffer");
+            }
+            let last = self.i + 2 * self.capacity - self.n_pending - 1;
+            let ixs = (0..size)
+                .map(|_| (last - (self.rng.next_u32() as usize) % n_ready) % self.capacity)
+                .collect::<Vec<_>>();
+            (ixs, None)
         } else {
             let ixs = (0..size)
                 // .map(|_| self.rng.usize(..self.size))
@@ -389,16 +837,9 @@ where
             (ixs, weight)
         };
 
-        Ok(Self::Batch {
-            obs: self.obs.sample(&ixs),
-            act: self.act.sample(&ixs),
-            next_obs: self.next_obs.sample(&ixs),
-            reward: self.sample_reward(&ixs),
-            is_terminated: self.sample_is_terminated(&ixs),
-            is_truncated: self.sample_is_truncated(&ixs),
-            ix_sample: Some(ixs),
-            weight,
-        })
+        self.update_sample_stats(&ixs);
+
+        Ok(self.transitions(ixs, weight))
     }
 
     /// Updates the priorities of transitions in the buffer.
@@ -425,3 +866,253 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic_replay_buffer::{BatchBase, BootstrapConfig, PerConfig, WeightNormalizer},
+        test::{TestActBatch, TestObsBatch},
+    };
+
+    fn transition(
+        reward: f32,
+        is_done: bool,
+    ) -> GenericTransitionBatch<TestObsBatch, TestActBatch> {
+        GenericTransitionBatch {
+            obs: TestObsBatch::new(1),
+            act: TestActBatch::new(1),
+            next_obs: TestObsBatch::new(1),
+            reward: vec![reward],
+            is_terminated: vec![is_done as i8],
+            is_truncated: vec![0],
+            weight: None,
+            ix_sample: None,
+            mask: None,
+            mc_return: None,
+        }
+    }
+
+    #[test]
+    fn test_mc_returns() -> Result<()> {
+        let config = SimpleReplayBufferConfig::default()
+            .capacity(8)
+            .mc_gamma(Some(0.5));
+        let mut buffer = SimpleReplayBuffer::<TestObsBatch, TestActBatch>::build(&config);
+
+        buffer.push(transition(1.0, false))?;
+        buffer.push(transition(2.0, false))?;
+        assert!(buffer.batch(1).is_err());
+
+        buffer.push(transition(4.0, true))?;
+        buffer.push(transition(8.0, false))?;
+        let mut tr = transition(16.0, false);
+        tr.is_truncated = vec![1];
+        buffer.push(tr)?;
+        buffer.push(transition(32.0, false))?;
+
+        // Rewards and flags are kept, returns are stored separately
+        assert_eq!(&buffer.reward[..6], &[1.0, 2.0, 4.0, 8.0, 16.0, 32.0]);
+        assert_eq!(&buffer.is_terminated[..6], &[0, 0, 1, 0, 0, 0]);
+        assert_eq!(&buffer.is_truncated[..6], &[0, 0, 0, 0, 1, 0]);
+        assert_eq!(&buffer.mc_return[..5], &[3.0, 4.0, 4.0, 16.0, 16.0]);
+
+        // The transition of the ongoing episode is never sampled
+        let batch = buffer.batch(100)?;
+        let mc_return = batch.mc_return().unwrap().clone();
+        let ixs = batch.ix_sample.unwrap();
+        assert!(ixs.iter().all(|&ix| ix < 5));
+        for (i, &ix) in ixs.iter().enumerate() {
+            assert_eq!(mc_return[i], buffer.mc_return[ix]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bootstrap_mask() -> Result<()> {
+        let config = SimpleReplayBufferConfig::default()
+            .capacity(4)
+            .bootstrap(Some(BootstrapConfig::default().n_heads(3).p(0.5)));
+        let mut buffer = SimpleReplayBuffer::<TestObsBatch, TestActBatch>::build(&config);
+        for _ in 0..6 {
+            buffer.push(transition(1.0, false))?;
+        }
+
+        // Masks are stored with transitions and returned for the sampled indices
+        let batch = buffer.batch(5)?;
+        let mask = batch.bootstrap_mask().unwrap().clone();
+        let ixs = batch.ix_sample.unwrap();
+        assert_eq!(mask.len(), 5 * 3);
+        for (i, ix) in ixs.iter().enumerate() {
+            assert_eq!(
+                &mask[i * 3..(i + 1) * 3],
+                &buffer.mask[ix * 3..(ix + 1) * 3]
+            );
+        }
+        assert!(mask.iter().all(|&m| m == 0.0 || m == 1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_per() -> Result<()> {
+        let per_config = PerConfig::default()
+            .alpha(1.0)
+            .beta_0(0.4)
+            .n_opts_final(2)
+            .normalize(WeightNormalizer::All);
+        let config = SimpleReplayBufferConfig::default()
+            .capacity(4)
+            .per_config(Some(per_config));
+        let mut buffer = SimpleReplayBuffer::<TestObsBatch, TestActBatch>::build(&config);
+        for _ in 0..4 {
+            buffer.push(transition(1.0, false))?;
+        }
+
+        // Transitions with larger TD errors are sampled more frequently
+        buffer.update_priority(&Some(vec![0, 1, 2, 3]), &Some(vec![10.0, 0.1, 0.1, 0.1]));
+        let batch = buffer.batch(1000)?;
+        let n = batch
+            .ix_sample
+            .unwrap()
+            .iter()
+            .filter(|&&ix| ix == 0)
+            .count();
+        assert!(n > 900);
+
+        // Importance weights are normalized by the maximum weight
+        let ws = batch.weight.unwrap();
+        assert!(ws.iter().all(|&w| w > 0.0 && w <= 1.0 + 1e-6));
+
+        // Beta is annealed with priority updates
+        buffer.update_priority(&Some(vec![0]), &Some(vec![10.0]));
+        let per_state = buffer.per_state.as_ref().unwrap();
+        assert_eq!(per_state.iw_scheduler.beta(), 1.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_push_with_priorities() -> Result<()> {
+        let per_config = PerConfig::default().alpha(1.0);
+        let config = SimpleReplayBufferConfig::default()
+            .capacity(4)
+            .per_config(Some(per_config));
+        let mut buffer = SimpleReplayBuffer::<TestObsBatch, TestActBatch>::build(&config);
+        buffer.push_with_priorities(transition(1.0, false), &[10.0])?;
+        for _ in 0..3 {
+            buffer.push_with_priorities(transition(1.0, false), &[0.1])?;
+        }
+
+        // The transitions have the given priorities instead of the maximum priority
+        let batch = buffer.batch(1000)?;
+        let n = batch
+            .ix_sample
+            .unwrap()
+            .iter()
+            .filter(|&&ix| ix == 0)
+            .count();
+        assert!(n > 900);
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_k() -> Result<()> {
+        let config = SimpleReplayBufferConfig::default()
+            .capacity(4)
+            .per_config(Some(PerConfig::default().alpha(0.5)));
+        let mut buffer = SimpleReplayBuffer::<TestObsBatch, TestActBatch>::build(&config);
+        for r in 0..4 {
+            buffer.push(transition(r as f32, false))?;
+        }
+
+        buffer.update_priority(&Some(vec![0, 1, 2, 3]), &Some(vec![0.1, 4.0, 0.5, 2.0]));
+        let selected = buffer.top_k_by_priority(2)?;
+        assert_eq!(selected.batch.ix_sample, Some(vec![1, 3]));
+        assert!((selected.scores[0] - 4.0).abs() < 1e-4);
+
+        // Scores computed in chunks
+        let selected = buffer.top_k_by_score(3, 3, |batch| Ok(batch.reward.clone()))?;
+        assert_eq!(selected.scores, vec![3.0, 2.0, 1.0]);
+        assert_eq!(selected.batch.reward, vec![3.0, 2.0, 1.0]);
+        Ok(())
+    }
+
+    fn assert_close(actual: f32, expected: f32) {
+        assert!(
+            (actual - expected).abs() < 1e-5,
+            "{} is not close to {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_sample_stats() -> Result<()> {
+        let config = SimpleReplayBufferConfig::default().capacity(4);
+        let mut buffer = SimpleReplayBuffer::<TestObsBatch, TestActBatch>::build(&config);
+        buffer.push(transition(1.0, false))?;
+        buffer.push(transition(2.0, true))?;
+        assert_close(buffer.record().get_scalar("replay_buffer_fill")?, 0.5);
+
+        // Ages are 1 and 0 for the first and second transitions
+        let batch = buffer.batch(100)?;
+        let n_last = batch
+            .ix_sample
+            .unwrap()
+            .iter()
+            .filter(|&&ix| ix == 1)
+            .count() as f32;
+        let stats = buffer.record();
+        assert_close(
+            stats.get_scalar("replay_buffer_sample_age_mean")?,
+            1.0 - n_last / 100.0,
+        );
+        assert_close(stats.get_scalar("replay_buffer_sample_age_max")?, 1.0);
+        assert_close(
+            stats.get_scalar("replay_buffer_sample_terminal_fraction")?,
+            n_last / 100.0,
+        );
+        assert_close(
+            stats.get_scalar("replay_buffer_sample_reward_mean")?,
+            1.0 + n_last / 100.0,
+        );
+
+        // Statistics of batches are reset
+        assert!(buffer
+            .record()
+            .get("replay_buffer_sample_age_mean")
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_limit() -> Result<()> {
+        let memory_limit = MemoryLimitConfig::default()
+            .max_bytes(40)
+            .bytes_per_transition(Some(10));
+
+        // The capacity is reduced and old transitions are evicted
+        let config = SimpleReplayBufferConfig::default()
+            .capacity(100)
+            .memory_limit(Some(memory_limit.clone()));
+        let mut buffer = SimpleReplayBuffer::<TestObsBatch, TestActBatch>::build(&config);
+        for _ in 0..6 {
+            buffer.push(transition(1.0, false))?;
+        }
+        assert_eq!(buffer.len(), 4);
+        assert_eq!(buffer.memory_usage(), Some(40));
+        assert_eq!(buffer.num_rejected(), 0);
+
+        // New transitions are rejected
+        let config =
+            config.memory_limit(Some(memory_limit.policy(MemoryLimitPolicy::Backpressure)));
+        let mut buffer = SimpleReplayBuffer::<TestObsBatch, TestActBatch>::build(&config);
+        for _ in 0..6 {
+            buffer.push(transition(1.0, false))?;
+        }
+        assert_eq!(buffer.len(), 4);
+        assert_eq!(buffer.num_rejected(), 2);
+        assert_eq!(buffer.record().get_scalar("replay_buffer_rejected")?, 2.0);
+
+        Ok(())
+    }
+}
@@ -1,42 +1,39 @@
 //! Scheduling the exponent of importance weight for PER.
+use crate::Schedule;
 use serde::{Deserialize, Serialize};
 
 /// Scheduler of the exponent of importance weight for PER.
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct IwScheduler {
-    /// Initial value of $\beta$.
-    pub beta_0: f32,
-
-    /// Final value of $\beta$.
-    pub beta_final: f32,
-
-    /// Optimization steps when beta reaches its final value.
-    pub n_opts_final: usize,
+    /// Schedule of $\beta$.
+    pub schedule: Schedule,
 
     /// Current optimizatioin steps.
     pub n_opts: usize,
 }
 
 impl IwScheduler {
-    /// Creates a scheduler.
+    /// Creates a scheduler increasing $\beta$ linearly from `beta_0` to `beta_final`
+    /// in `n_opts_final` optimization steps.
     pub fn new(beta_0: f32, beta_final: f32, n_opts_final: usize) -> Self {
+        Self::with_schedule(Schedule::Linear {
+            start: beta_0 as f64,
+            end: beta_final as f64,
+            n_steps: n_opts_final,
+        })
+    }
+
+    /// Creates a scheduler with the given schedule of $\beta$.
+    pub fn with_schedule(schedule: Schedule) -> Self {
         Self {
-            beta_0,
-            beta_final,
-            n_opts_final,
+            schedule,
             n_opts: 0,
         }
     }
 
     /// Gets the exponents of importance sampling weight.
     pub fn beta(&self) -> f32 {
-        let n_opts = self.n_opts;
-        if n_opts >= self.n_opts_final {
-            self.beta_final
-        } else {
-            let d = self.beta_final - self.beta_0;
-            self.beta_0 + d * (n_opts as f32 / self.n_opts_final as f32)
-        }
+        self.schedule.value(self.n_opts) as f32
     }
 
     /// Add optimization steps for scheduling beta through training.
@@ -106,6 +106,11 @@ impl SumTree {
         self.propagate(ix, change);
     }
 
+    /// Returns the priority value at `ix`-th element, undoing the alpha-th power.
+    pub fn priority(&self, ix: usize) -> f32 {
+        self.tree[ix + self.capacity - 1].powf(1.0 / self.alpha)
+    }
+
     /// Get the maximal index of the sum tree where the sum of priority values is less than `s`.
     pub fn get(&self, s: f32) -> usize {
         let ix = self.retrieve(0, s);
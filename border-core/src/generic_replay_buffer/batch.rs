@@ -7,6 +7,7 @@
 //! - Transition sampling and management
 
 use crate::TransitionBatch;
+use serde::{Deserialize, Serialize};
 
 /// A trait defining basic batch operations.
 ///
@@ -68,6 +69,14 @@ pub trait BatchBase {
     ///
     /// A new batch containing the sampled data
     fn sample(&self, ixs: &Vec<usize>) -> Self;
+
+    /// Returns the estimated number of bytes of data in the batch.
+    ///
+    /// It is used for the memory accounting of replay buffers.
+    /// The default implementation returns `None`, meaning that the size is unknown.
+    fn size_in_bytes(&self) -> Option<usize> {
+        None
+    }
 }
 
 /// A generic structure representing transitions in reinforcement learning.
@@ -86,6 +95,10 @@ pub trait BatchBase {
 /// ```ignore
 /// let batch = GenericTransitionBatch::<Tensor, Tensor>::with_capacity(32);
 /// ```
+///
+/// It implements [`Serialize`] and [`Deserialize`] if `O` and `A` do, so that batches can be
+/// sent over the network.
+#[derive(Clone, Serialize, Deserialize)]
 pub struct GenericTransitionBatch<O, A>
 where
     O: BatchBase,
@@ -114,6 +127,13 @@ where
 
     /// Indices of sampled transitions
     pub ix_sample: Option<Vec<usize>>,
+
+    /// Bootstrap masks of sampled transitions, flattened in the shape of `[batch_size, n_heads]`
+    pub mask: Option<Vec<f32>>,
+
+    /// Monte Carlo returns of sampled transitions
+    #[serde(default)]
+    pub mc_return: Option<Vec<f32>>,
 }
 
 impl<O, A> TransitionBatch for GenericTransitionBatch<O, A>
@@ -175,6 +195,16 @@ where
     fn act(&self) -> &Self::ActBatch {
         &self.act
     }
+
+    /// Returns the bootstrap masks of the batch.
+    fn bootstrap_mask(&self) -> Option<&Vec<f32>> {
+        self.mask.as_ref()
+    }
+
+    /// Returns the Monte Carlo returns of the batch.
+    fn mc_return(&self) -> Option<&Vec<f32>> {
+        self.mc_return.as_ref()
+    }
 }
 
 impl<O, A> GenericTransitionBatch<O, A>
@@ -201,6 +231,99 @@ where
             is_truncated: Vec::with_capacity(capacity),
             weight: None,
             ix_sample: None,
+            mask: None,
+            mc_return: None,
         }
     }
 }
+
+/// Allows concatenating batches of transitions, e.g., samples collected by an actor
+/// in asynchronous training, in order to process them at once.
+///
+/// The batch created with [`BatchBase::new()`] holds `capacity` transitions from the beginning.
+/// Weights, sample indices, bootstrap masks and Monte Carlo returns are not kept.
+impl<O, A> BatchBase for GenericTransitionBatch<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    fn new(capacity: usize) -> Self {
+        Self {
+            obs: O::new(capacity),
+            act: A::new(capacity),
+            next_obs: O::new(capacity),
+            reward: vec![0.0; capacity],
+            is_terminated: vec![0; capacity],
+            is_truncated: vec![0; capacity],
+            weight: None,
+            ix_sample: None,
+            mask: None,
+            mc_return: None,
+        }
+    }
+
+    fn push(&mut self, ix: usize, data: Self) {
+        let capacity = self.reward.len();
+        let flags = data.is_terminated.iter().zip(data.is_truncated.iter());
+        for (j, (r, (t1, t2))) in data.reward.iter().zip(flags).enumerate() {
+            let k = (ix + j) % capacity;
+            self.reward[k] = *r;
+            self.is_terminated[k] = *t1;
+            self.is_truncated[k] = *t2;
+        }
+        self.obs.push(ix, data.obs);
+        self.act.push(ix, data.act);
+        self.next_obs.push(ix, data.next_obs);
+    }
+
+    fn sample(&self, ixs: &Vec<usize>) -> Self {
+        Self {
+            obs: self.obs.sample(ixs),
+            act: self.act.sample(ixs),
+            next_obs: self.next_obs.sample(ixs),
+            reward: ixs.iter().map(|&ix| self.reward[ix]).collect(),
+            is_terminated: ixs.iter().map(|&ix| self.is_terminated[ix]).collect(),
+            is_truncated: ixs.iter().map(|&ix| self.is_truncated[ix]).collect(),
+            weight: None,
+            ix_sample: None,
+            mask: None,
+            mc_return: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::test::{TestActBatch, TestObsBatch};
+
+    type Batch = GenericTransitionBatch<TestObsBatch, TestActBatch>;
+
+    fn batch(i: usize) -> Batch {
+        Batch {
+            obs: TestObsBatch::new(1),
+            act: TestActBatch::new(1),
+            next_obs: TestObsBatch::new(1),
+            reward: vec![i as f32],
+            is_terminated: vec![(i % 2) as i8],
+            is_truncated: vec![0],
+            weight: None,
+            ix_sample: None,
+            mask: None,
+            mc_return: None,
+        }
+    }
+
+    #[test]
+    fn test_concat() {
+        let mut b = Batch::new(3);
+        (0..3).for_each(|i| b.push(i, batch(i)));
+        assert_eq!(b.len(), 3);
+        assert_eq!(b.reward, vec![0.0, 1.0, 2.0]);
+        assert_eq!(b.is_terminated, vec![0, 1, 0]);
+
+        let b = b.sample(&vec![2, 0]);
+        assert_eq!(b.reward, vec![2.0, 0.0]);
+        assert_eq!(b.is_terminated, vec![0, 0]);
+    }
+}
@@ -0,0 +1,416 @@
+//! Checksummed chunk files for persisting replay buffers.
+//!
+//! A chunk file starts with a magic header followed by a sequence of chunks.
+//! Each chunk consists of:
+//! - The length of the payload in bytes (`u64`, little endian)
+//! - The XXH3 checksum of the payload (`u64`, little endian)
+//! - The payload
+//!
+//! The payload is compressed with the codec of the [`ReplayBufferSchema`] of the file, if the file
+//! is created with [`ChunkWriter::create_with_schema()`], and decompressed by
+//! [`read_chunks_with_schema()`]. [`read_chunks()`] returns payloads as they are stored.
+//!
+//! After an unclean shutdown, the last chunk may be truncated, or a chunk may be
+//! corrupted on disk. [`verify_chunks()`] detects such chunks and [`repair_chunks()`]
+//! truncates the file to its longest valid prefix, so that a replay buffer never
+//! samples transitions decoded from broken bytes.
+//!
+//! [`ReplayBufferSchema`]: super::ReplayBufferSchema
+//! [`read_chunks_with_schema()`]: super::read_chunks_with_schema
+use super::{schema_path, ReplayBufferSchema};
+use crate::codec::{Codec, NoCodec};
+use anyhow::Result;
+use std::{
+    convert::TryFrom,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Magic bytes at the beginning of a chunk file.
+const MAGIC: &[u8; 8] = b"BRDRCHK1";
+
+/// Size of the header of a chunk in bytes.
+const CHUNK_HEADER_LEN: u64 = 16;
+
+/// Writes checksummed chunks into a file.
+pub struct ChunkWriter {
+    writer: BufWriter<File>,
+    pub(super) codec: Box<dyn Codec>,
+    n_chunks: usize,
+}
+
+impl ChunkWriter {
+    /// Creates a new chunk file, truncating an existing one.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(MAGIC)?;
+        Ok(Self {
+            writer,
+            codec: Box::new(NoCodec),
+            n_chunks: 0,
+        })
+    }
+
+    /// Opens an existing chunk file for appending chunks.
+    ///
+    /// The file is repaired with [`repair_chunks()`] before appending. If the file has a schema,
+    /// chunks are compressed with its codec.
+    pub fn append(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let codec = match schema_path(path).exists() {
+            true => ReplayBufferSchema::load(schema_path(path))?.codec.build()?,
+            false => Box::new(NoCodec),
+        };
+        let report = repair_chunks(path)?;
+        let file = OpenOptions::new().append(true).open(path)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            codec,
+            n_chunks: report.n_valid_chunks,
+        })
+    }
+
+    /// Writes a chunk, compressing it with the codec of the file.
+    pub fn write_chunk(&mut self, data: &[u8]) -> Result<()> {
+        let data = self.codec.compress(data)?;
+        self.writer.write_all(&(data.len() as u64).to_le_bytes())?;
+        self.writer.write_all(&xxh3_64(&data).to_le_bytes())?;
+        self.writer.write_all(&data)?;
+        self.n_chunks += 1;
+        Ok(())
+    }
+
+    /// Returns the number of chunks in the file.
+    pub fn n_chunks(&self) -> usize {
+        self.n_chunks
+    }
+
+    /// Flushes buffered chunks into the file and syncs it to the disk.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        Ok(())
+    }
+}
+
+/// Kind of corruption found in a chunk file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChunkCorruption {
+    /// The file does not start with the magic bytes.
+    InvalidHeader,
+
+    /// The file ends in the middle of a chunk.
+    Truncated {
+        /// Index of the truncated chunk.
+        chunk: usize,
+
+        /// Byte offset of the truncated chunk.
+        offset: u64,
+    },
+
+    /// The length of the payload stored in the header overflows the offset in the file.
+    InvalidLength {
+        /// Index of the corrupted chunk.
+        chunk: usize,
+
+        /// Byte offset of the corrupted chunk.
+        offset: u64,
+
+        /// The stored length of the payload.
+        len: u64,
+    },
+
+    /// The checksum of the payload does not match the stored one.
+    ChecksumMismatch {
+        /// Index of the corrupted chunk.
+        chunk: usize,
+
+        /// Byte offset of the corrupted chunk.
+        offset: u64,
+    },
+}
+
+/// Result of the verification of a chunk file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChunkReport {
+    /// Number of valid chunks from the beginning of the file.
+    pub n_valid_chunks: usize,
+
+    /// Length of the valid prefix of the file in bytes.
+    pub valid_len: u64,
+
+    /// The first corruption found in the file, `None` if the file is intact.
+    pub corruption: Option<ChunkCorruption>,
+}
+
+impl ChunkReport {
+    /// Returns `true` if no corruption was found.
+    pub fn is_intact(&self) -> bool {
+        self.corruption.is_none()
+    }
+}
+
+/// Reads `buf.len()` bytes, returning `false` if the reader reaches EOF before that.
+fn read_full(rdr: &mut impl Read, buf: &mut [u8]) -> Result<bool> {
+    match rdr.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Scans a chunk file, calling `f` with the payload of each valid chunk.
+///
+/// Chunks are read one by one, so the file is not loaded into memory at once.
+/// Scanning stops at the first corrupted chunk, or when `f` returns an error.
+pub fn scan_chunks(
+    path: impl AsRef<Path>,
+    mut f: impl FnMut(Vec<u8>) -> Result<()>,
+) -> Result<ChunkReport> {
+    let path = path.as_ref();
+    let mut rdr = BufReader::new(File::open(path)?);
+    let mut magic = [0u8; 8];
+    if !read_full(&mut rdr, &mut magic)? || &magic != MAGIC {
+        return Ok(ChunkReport {
+            n_valid_chunks: 0,
+            valid_len: 0,
+            corruption: Some(ChunkCorruption::InvalidHeader),
+        });
+    }
+
+    let file_len = std::fs::metadata(path)?.len();
+    let mut offset = MAGIC.len() as u64;
+    let mut chunk = 0;
+    let mut header = [0u8; CHUNK_HEADER_LEN as usize];
+
+    loop {
+        let truncated = Some(ChunkCorruption::Truncated { chunk, offset });
+        if offset == file_len {
+            break;
+        } else if !read_full(&mut rdr, &mut header)? {
+            return Ok(ChunkReport {
+                n_valid_chunks: chunk,
+                valid_len: offset,
+                corruption: truncated,
+            });
+        }
+
+        let mut len = [0u8; 8];
+        let mut checksum = [0u8; 8];
+        len.copy_from_slice(&header[..8]);
+        checksum.copy_from_slice(&header[8..]);
+        let len = u64::from_le_bytes(len);
+        let checksum = u64::from_le_bytes(checksum);
+
+        // Check the length before allocating, as it may be garbage
+        let end = offset
+            .checked_add(CHUNK_HEADER_LEN)
+            .and_then(|v| v.checked_add(len));
+        let (end, data_len) = match (end, usize::try_from(len)) {
+            (Some(end), Ok(data_len)) => (end, data_len),
+            _ => {
+                return Ok(ChunkReport {
+                    n_valid_chunks: chunk,
+                    valid_len: offset,
+                    corruption: Some(ChunkCorruption::InvalidLength { chunk, offset, len }),
+                });
+            }
+        };
+        if end > file_len {
+            return Ok(ChunkReport {
+                n_valid_chunks: chunk,
+                valid_len: offset,
+                corruption: truncated,
+            });
+        }
+
+        let mut data = vec![0u8; data_len];
+        if !read_full(&mut rdr, &mut data)? {
+            return Ok(ChunkReport {
+                n_valid_chunks: chunk,
+                valid_len: offset,
+                corruption: truncated,
+            });
+        }
+        if xxh3_64(&data) != checksum {
+            return Ok(ChunkReport {
+                n_valid_chunks: chunk,
+                valid_len: offset,
+                corruption: Some(ChunkCorruption::ChecksumMismatch { chunk, offset }),
+            });
+        }
+
+        f(data)?;
+        offset = end;
+        chunk += 1;
+    }
+
+    Ok(ChunkReport {
+        n_valid_chunks: chunk,
+        valid_len: offset,
+        corruption: None,
+    })
+}
+
+/// Verifies the checksums of all chunks in a file.
+pub fn verify_chunks(path: impl AsRef<Path>) -> Result<ChunkReport> {
+    scan_chunks(path, |_| Ok(()))
+}
+
+/// Reads the valid chunks of a file.
+///
+/// Chunks after the first corruption are not returned. The caller is expected to
+/// inspect the report and decide whether a partially loaded buffer is acceptable.
+pub fn read_chunks(path: impl AsRef<Path>) -> Result<(Vec<Vec<u8>>, ChunkReport)> {
+    let mut chunks = vec![];
+    let report = scan_chunks(path, |data| {
+        chunks.push(data);
+        Ok(())
+    })?;
+    Ok((chunks, report))
+}
+
+/// Truncates a chunk file to its longest valid prefix.
+///
+/// If the header of the file is invalid, an error is returned and the file is left untouched,
+/// as it may not be a chunk file, e.g., when a wrong path is given.
+pub fn repair_chunks(path: impl AsRef<Path>) -> Result<ChunkReport> {
+    let path = path.as_ref();
+    let report = verify_chunks(path)?;
+
+    match report.corruption {
+        None => {}
+        Some(ChunkCorruption::InvalidHeader) => {
+            anyhow::bail!(
+                "Invalid header in {:?}, which may not be a chunk file",
+                path
+            );
+        }
+        Some(ref corruption) => {
+            log::warn!(
+                "{:?} in {:?}, truncated the file to {} chunks",
+                corruption,
+                path,
+                report.n_valid_chunks
+            );
+            let file = OpenOptions::new().write(true).open(path)?;
+            file.set_len(report.valid_len)?;
+            file.sync_data()?;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn write_test_file(path: &Path) -> Result<()> {
+        let mut writer = ChunkWriter::create(path)?;
+        writer.write_chunk(&[1, 2, 3])?;
+        writer.write_chunk(&[4, 5, 6, 7])?;
+        writer.write_chunk(&[8])?;
+        writer.flush()
+    }
+
+    #[test]
+    fn test_truncated_chunk_file() -> Result<()> {
+        let dir = TempDir::new("chunk")?;
+        let path = dir.path().join("buffer.chunks");
+        write_test_file(&path)?;
+        assert!(verify_chunks(&path)?.is_intact());
+
+        // Cut the last chunk in the middle
+        let len = std::fs::metadata(&path)?.len();
+        OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .set_len(len - 1)?;
+
+        let (chunks, report) = read_chunks(&path)?;
+        assert_eq!(chunks, vec![vec![1, 2, 3], vec![4, 5, 6, 7]]);
+        assert!(matches!(
+            report.corruption,
+            Some(ChunkCorruption::Truncated { chunk: 2, .. })
+        ));
+
+        repair_chunks(&path)?;
+        let mut writer = ChunkWriter::append(&path)?;
+        assert_eq!(writer.n_chunks(), 2);
+        writer.write_chunk(&[9])?;
+        writer.flush()?;
+        let (chunks, report) = read_chunks(&path)?;
+        assert!(report.is_intact());
+        assert_eq!(chunks.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_chunk_length() -> Result<()> {
+        let dir = TempDir::new("chunk")?;
+        let path = dir.path().join("buffer.chunks");
+        write_test_file(&path)?;
+
+        // Overwrite the length of the second chunk with garbage
+        let mut bytes = std::fs::read(&path)?;
+        let ix = MAGIC.len() + CHUNK_HEADER_LEN as usize + 3;
+        for len in [u64::MAX, u64::MAX - 8].iter() {
+            bytes[ix..ix + 8].copy_from_slice(&len.to_le_bytes());
+            std::fs::write(&path, &bytes)?;
+
+            let (chunks, report) = read_chunks(&path)?;
+            assert_eq!(chunks, vec![vec![1, 2, 3]]);
+            assert_eq!(
+                report.corruption,
+                Some(ChunkCorruption::InvalidLength {
+                    chunk: 1,
+                    offset: ix as u64,
+                    len: *len
+                })
+            );
+        }
+
+        repair_chunks(&path)?;
+        assert!(verify_chunks(&path)?.is_intact());
+        assert_eq!(read_chunks(&path)?.0.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupted_chunk_file() -> Result<()> {
+        let dir = TempDir::new("chunk")?;
+        let path = dir.path().join("buffer.chunks");
+        write_test_file(&path)?;
+
+        // Flip a byte in the payload of the second chunk
+        let mut bytes = std::fs::read(&path)?;
+        let ix = MAGIC.len() + CHUNK_HEADER_LEN as usize * 2 + 3 + 1;
+        bytes[ix] ^= 0xff;
+        std::fs::write(&path, bytes)?;
+
+        let report = verify_chunks(&path)?;
+        assert_eq!(report.n_valid_chunks, 1);
+        assert!(matches!(
+            report.corruption,
+            Some(ChunkCorruption::ChecksumMismatch { chunk: 1, .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_header() -> Result<()> {
+        let dir = TempDir::new("chunk")?;
+        let path = dir.path().join("buffer.chunks");
+        std::fs::write(&path, b"not a chunk file")?;
+
+        // The file is not reinitialized
+        assert!(repair_chunks(&path).is_err());
+        assert!(ChunkWriter::append(&path).is_err());
+        assert_eq!(std::fs::read(&path)?, b"not a chunk file");
+        Ok(())
+    }
+}
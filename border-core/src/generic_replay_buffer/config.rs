@@ -6,6 +6,7 @@
 //! - Serialization and deserialization support
 
 use super::{WeightNormalizer, WeightNormalizer::All};
+use crate::Schedule;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -27,6 +28,7 @@ use std::{
 /// * `beta_0` - Initial value for importance sampling weights
 /// * `beta_final` - Final value for importance sampling weights
 /// * `n_opts_final` - Number of optimization steps to reach `beta_final`
+/// * `beta_schedule` - Schedule of `beta` replacing the linear one given by the above
 /// * `normalize` - Method for normalizing importance weights
 ///
 /// # Examples
@@ -59,6 +61,11 @@ pub struct PerConfig {
     /// This allows for a gradual increase in the impact of importance sampling.
     pub n_opts_final: usize,
 
+    /// Schedule of the importance sampling exponent over optimization steps.
+    /// If given, `beta_0`, `beta_final` and `n_opts_final` are ignored.
+    #[serde(default)]
+    pub beta_schedule: Option<Schedule>,
+
     /// Method for normalizing importance sampling weights. Controls how the
     /// weights are scaled to prevent numerical instability.
     pub normalize: WeightNormalizer,
@@ -77,6 +84,7 @@ impl Default for PerConfig {
             beta_0: 0.4,
             beta_final: 1.0,
             n_opts_final: 500_000,
+            beta_schedule: None,
             normalize: All,
         }
     }
@@ -139,6 +147,12 @@ impl PerConfig {
         self
     }
 
+    /// Sets the schedule of the importance sampling exponent, replacing the linear one.
+    pub fn beta_schedule(mut self, beta_schedule: Schedule) -> Self {
+        self.beta_schedule = Some(beta_schedule);
+        self
+    }
+
     /// Sets the method for normalizing importance weights.
     ///
     /// # Arguments
@@ -154,6 +168,122 @@ impl PerConfig {
     }
 }
 
+/// Configuration of bootstrap masks for ensemble agents like bootstrapped DQN.
+///
+/// When a transition is pushed into the replay buffer, a mask is drawn for each head
+/// of the ensemble from the Bernoulli distribution with probability `p`. The masks are
+/// stored with the transition and returned with sampled batches, so that each head is
+/// trained on its own bootstrap sample of the data.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct BootstrapConfig {
+    /// Number of heads of the ensemble.
+    pub n_heads: usize,
+
+    /// Probability that a transition is used to train a head.
+    pub p: f32,
+}
+
+impl Default for BootstrapConfig {
+    /// Creates a default configuration with `n_heads = 10` and `p = 0.5`.
+    fn default() -> Self {
+        Self {
+            n_heads: 10,
+            p: 0.5,
+        }
+    }
+}
+
+impl BootstrapConfig {
+    /// Sets the number of heads.
+    pub fn n_heads(mut self, n_heads: usize) -> Self {
+        self.n_heads = n_heads;
+        self
+    }
+
+    /// Sets the probability that a transition is used to train a head.
+    pub fn p(mut self, p: f32) -> Self {
+        self.p = p;
+        self
+    }
+}
+
+/// Action taken when a replay buffer reaches its memory limit.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum MemoryLimitPolicy {
+    /// Reduces the capacity of the buffer so that it fits in the memory limit.
+    /// The oldest transitions are evicted as in a full buffer.
+    Evict,
+
+    /// Keeps the capacity, but rejects new transitions once the memory limit is reached.
+    /// Training proceeds on the stored transitions, and the number of rejected transitions
+    /// is recorded.
+    Backpressure,
+}
+
+/// Default maximum number of bytes of transitions, 8GiB.
+#[cfg(target_pointer_width = "64")]
+const DEFAULT_MAX_BYTES: usize = 8usize << 30;
+
+/// Default maximum number of bytes of transitions, the whole address space of 32-bit targets.
+#[cfg(not(target_pointer_width = "64"))]
+const DEFAULT_MAX_BYTES: usize = usize::MAX;
+
+/// Configuration of the memory limit of a replay buffer.
+///
+/// The memory usage is estimated from the number of bytes per transition, which is computed
+/// from the first pushed transitions with [`BatchBase::size_in_bytes()`] or given explicitly
+/// with `bytes_per_transition`. If neither is available, the limit is not enforced.
+///
+/// Note that batch types allocating storage for the whole capacity in advance, like
+/// `TensorBatch` of `border-candle-agent`, do not save memory with
+/// [`MemoryLimitPolicy::Backpressure`]; use [`MemoryLimitPolicy::Evict`] for them.
+///
+/// [`BatchBase::size_in_bytes()`]: super::BatchBase::size_in_bytes
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct MemoryLimitConfig {
+    /// Maximum number of bytes of transitions in the buffer.
+    pub max_bytes: usize,
+
+    /// Number of bytes per transition. If `None`, it is estimated from pushed transitions.
+    #[serde(default)]
+    pub bytes_per_transition: Option<usize>,
+
+    /// Action taken when the memory limit is reached.
+    pub policy: MemoryLimitPolicy,
+}
+
+impl Default for MemoryLimitConfig {
+    /// Creates a default configuration with `max_bytes = 8GiB`, or `usize::MAX` on 32-bit
+    /// targets, and the eviction policy.
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+            bytes_per_transition: None,
+            policy: MemoryLimitPolicy::Evict,
+        }
+    }
+}
+
+impl MemoryLimitConfig {
+    /// Sets the maximum number of bytes.
+    pub fn max_bytes(mut self, v: usize) -> Self {
+        self.max_bytes = v;
+        self
+    }
+
+    /// Sets the number of bytes per transition.
+    pub fn bytes_per_transition(mut self, v: Option<usize>) -> Self {
+        self.bytes_per_transition = v;
+        self
+    }
+
+    /// Sets the action taken when the memory limit is reached.
+    pub fn policy(mut self, v: MemoryLimitPolicy) -> Self {
+        self.policy = v;
+        self
+    }
+}
+
 /// Configuration for the replay buffer.
 ///
 /// This structure defines the basic parameters for the replay buffer,
@@ -194,6 +324,25 @@ pub struct SimpleReplayBufferConfig {
     /// Optional configuration for prioritized experience replay. If `None`,
     /// transitions are sampled uniformly at random.
     pub per_config: Option<PerConfig>,
+
+    /// Discount factor of full-episode Monte Carlo returns. If `Some(gamma)`,
+    /// the discounted return from each transition to the end of the episode is
+    /// computed when the episode ends and returned in the `mc_return` field of
+    /// sampled batches. Rewards and termination flags are kept as they are.
+    /// Transitions of the ongoing episode are not sampled until the episode ends.
+    /// Cannot be combined with `per_config`.
+    #[serde(default)]
+    pub mc_gamma: Option<f32>,
+
+    /// Optional configuration of bootstrap masks stored with each transition.
+    /// If `None`, sampled batches have no masks.
+    #[serde(default)]
+    pub bootstrap: Option<BootstrapConfig>,
+
+    /// Optional memory limit of the buffer. If `None`, the memory usage is bounded
+    /// only by the capacity.
+    #[serde(default)]
+    pub memory_limit: Option<MemoryLimitConfig>,
 }
 
 impl Default for SimpleReplayBufferConfig {
@@ -201,11 +350,17 @@ impl Default for SimpleReplayBufferConfig {
     /// - `capacity = 10000` (moderate buffer size)
     /// - `seed = 42` (fixed random seed)
     /// - `per_config = None` (uniform sampling)
+    /// - `mc_gamma = None` (no Monte Carlo returns)
+    /// - `bootstrap = None` (no bootstrap masks)
+    /// - `memory_limit = None` (no memory limit)
     fn default() -> Self {
         Self {
             capacity: 10000,
             seed: 42,
             per_config: None,
+            mc_gamma: None,
+            bootstrap: None,
+            memory_limit: None,
         }
     }
 }
@@ -253,6 +408,48 @@ impl SimpleReplayBufferConfig {
         self
     }
 
+    /// Sets the discount factor of full-episode Monte Carlo returns.
+    ///
+    /// # Arguments
+    ///
+    /// * `mc_gamma` - The discount factor, or `None` to disable Monte Carlo returns
+    ///
+    /// # Returns
+    ///
+    /// The modified configuration
+    pub fn mc_gamma(mut self, mc_gamma: Option<f32>) -> Self {
+        self.mc_gamma = mc_gamma;
+        self
+    }
+
+    /// Sets the configuration of bootstrap masks.
+    ///
+    /// # Arguments
+    ///
+    /// * `bootstrap` - The configuration, or `None` to disable bootstrap masks
+    ///
+    /// # Returns
+    ///
+    /// The modified configuration
+    pub fn bootstrap(mut self, bootstrap: Option<BootstrapConfig>) -> Self {
+        self.bootstrap = bootstrap;
+        self
+    }
+
+    /// Sets the memory limit of the buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `memory_limit` - The configuration, or `None` to disable the memory limit
+    ///
+    /// # Returns
+    ///
+    /// The modified configuration
+    pub fn memory_limit(mut self, memory_limit: Option<MemoryLimitConfig>) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
     /// Loads the configuration from a YAML file.
     ///
     /// # Arguments
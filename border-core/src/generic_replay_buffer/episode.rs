@@ -0,0 +1,443 @@
+//! Recording of episodes into chunk files and their replay into replay buffers.
+//!
+//! [`EpisodeWriter`] serializes each episode collected in an environment, e.g., during
+//! training, into a chunk of a file written with [`ChunkWriter`]. [`EpisodeReader`] reads the
+//! episodes and reconstructs a [`SimpleReplayBuffer`] from them, so that agents can be trained
+//! offline on self-collected data without converting it into other dataset formats.
+use super::{
+    scan_chunks, scan_chunks_with_schema, BatchBase, ChunkReport, ChunkWriter,
+    GenericTransitionBatch, ReplayBufferSchema, SchemaMigration, SimpleReplayBuffer,
+    SimpleReplayBufferConfig,
+};
+use crate::{Env, ExperienceBufferBase, ReplayBufferBase, Step};
+use anyhow::Result;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{marker::PhantomData, path::Path};
+
+/// An episode recorded with [`EpisodeWriter`].
+///
+/// `obs[t]` is the observation at which `act[t]` was taken, and the last element of `obs` is
+/// the observation after the last action, i.e., `obs.len() == act.len() + 1`.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct Episode<O, A> {
+    /// Observations.
+    pub obs: Vec<O>,
+
+    /// Actions.
+    pub act: Vec<A>,
+
+    /// Rewards.
+    pub reward: Vec<f32>,
+
+    /// Termination flags.
+    pub is_terminated: Vec<i8>,
+
+    /// Truncation flags.
+    pub is_truncated: Vec<i8>,
+}
+
+impl<O, A> Episode<O, A> {
+    fn new(init_obs: O) -> Self {
+        Self {
+            obs: vec![init_obs],
+            act: vec![],
+            reward: vec![],
+            is_terminated: vec![],
+            is_truncated: vec![],
+        }
+    }
+
+    /// Returns the number of steps in the episode.
+    pub fn len(&self) -> usize {
+        self.act.len()
+    }
+
+    /// Returns `true` if the episode has no steps.
+    pub fn is_empty(&self) -> bool {
+        self.act.is_empty()
+    }
+
+    /// Returns the sum of the rewards.
+    pub fn episode_return(&self) -> f32 {
+        self.reward.iter().sum()
+    }
+}
+
+/// Reconstructs episodes from records read from a file.
+struct EpisodeBuilder<O, A> {
+    episodes: Vec<Episode<O, A>>,
+    episode: Option<Episode<O, A>>,
+}
+
+impl<O, A> Default for EpisodeBuilder<O, A> {
+    fn default() -> Self {
+        Self {
+            episodes: vec![],
+            episode: None,
+        }
+    }
+}
+
+impl<O, A> EpisodeBuilder<O, A>
+where
+    O: DeserializeOwned,
+    A: DeserializeOwned,
+{
+    fn push(&mut self, chunk: &[u8]) -> Result<()> {
+        match bincode::deserialize(chunk)? {
+            EpisodeRecord::Start(init_obs) => {
+                if self.episode.is_some() {
+                    log::warn!("Discarded an unfinished episode");
+                }
+                self.episode = Some(Episode::new(init_obs));
+            }
+            EpisodeRecord::Step {
+                obs,
+                act,
+                reward,
+                is_terminated,
+                is_truncated,
+            } => {
+                let episode = self
+                    .episode
+                    .as_mut()
+                    .ok_or_else(|| anyhow::anyhow!("A step is recorded out of an episode"))?;
+                let is_done = is_terminated
+                    .iter()
+                    .chain(is_truncated.iter())
+                    .any(|&f| f == 1);
+                episode.obs.push(obs);
+                episode.act.push(act);
+                episode.reward.extend(reward);
+                episode.is_terminated.extend(is_terminated);
+                episode.is_truncated.extend(is_truncated);
+                if is_done {
+                    self.episodes.push(self.episode.take().unwrap());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, path: &Path, report: ChunkReport) -> EpisodeReader<O, A> {
+        if !report.is_intact() {
+            log::warn!(
+                "{:?} in {:?}, read {} episodes",
+                report.corruption,
+                path,
+                self.episodes.len()
+            );
+        }
+        EpisodeReader {
+            episodes: self.episodes,
+            report,
+        }
+    }
+}
+
+/// A record written as a chunk by [`EpisodeWriter`].
+#[derive(Deserialize, Serialize)]
+enum EpisodeRecord<O, A> {
+    /// Start of an episode with the initial observation.
+    Start(O),
+
+    /// A step of the current episode.
+    Step {
+        obs: O,
+        act: A,
+        reward: Vec<f32>,
+        is_terminated: Vec<i8>,
+        is_truncated: Vec<i8>,
+    },
+}
+
+/// Writes episodes of an environment into a chunk file.
+///
+/// The start of an episode and each step given with [`EpisodeWriter::push()`] are written as
+/// chunks as they come, so an episode is not kept in memory. When an episode ends, the next
+/// episode starts from [`Step::init_obs`]. Observations and actions are serialized with
+/// [`bincode`], so they are required to implement [`Serialize`].
+/// Environments are assumed not to be vectorized.
+///
+/// The file can be tagged with a [`ReplayBufferSchema`] with [`EpisodeWriter::create_with_schema()`],
+/// which is checked by [`EpisodeReader::open_with_schema()`].
+///
+/// ```ignore
+/// let mut writer = EpisodeWriter::<Env>::create("episodes.chunks")?;
+/// writer.reset(&env.reset(None)?)?;
+/// loop {
+///     let (step, _) = env.step_with_reset(&agent.sample(&obs));
+///     writer.push(&step)?;
+///     // ...
+/// }
+/// ```
+pub struct EpisodeWriter<E: Env> {
+    writer: ChunkWriter,
+    in_episode: bool,
+    n_episodes: usize,
+    phantom: PhantomData<E>,
+}
+
+impl<E> EpisodeWriter<E>
+where
+    E: Env,
+    E::Obs: Serialize,
+    E::Act: Serialize,
+{
+    fn new(writer: ChunkWriter) -> Self {
+        Self {
+            writer,
+            in_episode: false,
+            n_episodes: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new file of episodes, truncating an existing one.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(ChunkWriter::create(path)?))
+    }
+
+    /// Creates a new file of episodes tagged with a schema, truncating an existing one.
+    pub fn create_with_schema(path: impl AsRef<Path>, schema: &ReplayBufferSchema) -> Result<Self> {
+        Ok(Self::new(ChunkWriter::create_with_schema(path, schema)?))
+    }
+
+    /// Opens an existing file of episodes for appending episodes.
+    ///
+    /// An episode left unfinished in the file is discarded when it is read.
+    pub fn append(path: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::new(ChunkWriter::append(path)?))
+    }
+
+    fn write(&mut self, record: &EpisodeRecord<&E::Obs, &E::Act>) -> Result<()> {
+        self.writer.write_chunk(&bincode::serialize(record)?)
+    }
+
+    /// Starts an episode with the initial observation.
+    ///
+    /// Steps of the unfinished episode, if any, are discarded when the file is read.
+    pub fn reset(&mut self, init_obs: &E::Obs) -> Result<()> {
+        self.write(&EpisodeRecord::Start(init_obs))?;
+        self.in_episode = true;
+        Ok(())
+    }
+
+    /// Writes a step of the current episode.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if [`EpisodeWriter::reset()`] has not been called,
+    /// or the step fails to be written.
+    pub fn push(&mut self, step: &Step<E>) -> Result<()> {
+        if !self.in_episode {
+            anyhow::bail!("EpisodeWriter::reset() must be called first");
+        }
+        self.write(&EpisodeRecord::Step {
+            obs: &step.obs,
+            act: &step.act,
+            reward: step.reward.clone(),
+            is_terminated: step.is_terminated.clone(),
+            is_truncated: step.is_truncated.clone(),
+        })?;
+
+        if step.is_done() {
+            self.n_episodes += 1;
+            self.in_episode = false;
+            if let Some(init_obs) = step.init_obs.as_ref() {
+                self.reset(init_obs)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of episodes finished with this writer.
+    pub fn n_episodes(&self) -> usize {
+        self.n_episodes
+    }
+
+    /// Flushes written records into the file.
+    pub fn flush(&mut self) -> Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Reads episodes written with [`EpisodeWriter`].
+pub struct EpisodeReader<O, A> {
+    episodes: Vec<Episode<O, A>>,
+    report: ChunkReport,
+}
+
+impl<O, A> EpisodeReader<O, A>
+where
+    O: DeserializeOwned + Clone,
+    A: DeserializeOwned,
+{
+    /// Reads the episodes in a file.
+    ///
+    /// Episodes unfinished in the file, e.g., an episode being written at an unclean shutdown,
+    /// are not read. Records after the first corrupted chunk are not read either, which is
+    /// reported by [`EpisodeReader::report()`].
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let mut builder = EpisodeBuilder::default();
+        let report = scan_chunks(path.as_ref(), |chunk| builder.push(&chunk))?;
+        Ok(builder.finish(path.as_ref(), report))
+    }
+
+    /// Reads the episodes in a file, checking its schema against `schema`.
+    ///
+    /// Chunks are converted with `migration` if the schemas differ,
+    /// see [`scan_chunks_with_schema()`].
+    pub fn open_with_schema(
+        path: impl AsRef<Path>,
+        schema: &ReplayBufferSchema,
+        migration: Option<&dyn SchemaMigration>,
+    ) -> Result<Self> {
+        let mut builder = EpisodeBuilder::default();
+        let report = scan_chunks_with_schema(path.as_ref(), schema, migration, |chunk| {
+            builder.push(&chunk)
+        })?;
+        Ok(builder.finish(path.as_ref(), report))
+    }
+
+    /// Returns the episodes.
+    pub fn episodes(&self) -> &[Episode<O, A>] {
+        &self.episodes
+    }
+
+    /// Returns the report of the verification of the file.
+    pub fn report(&self) -> &ChunkReport {
+        &self.report
+    }
+
+    /// Returns the number of transitions in the episodes.
+    pub fn n_transitions(&self) -> usize {
+        self.episodes.iter().map(|e| e.len()).sum()
+    }
+
+    /// Pushes the transitions of the episodes into a replay buffer.
+    pub fn push_into<OB, AB>(&self, buffer: &mut SimpleReplayBuffer<OB, AB>) -> Result<()>
+    where
+        OB: BatchBase + From<O>,
+        AB: BatchBase + From<A>,
+        A: Clone,
+    {
+        for episode in self.episodes.iter() {
+            for t in 0..episode.len() {
+                buffer.push(GenericTransitionBatch {
+                    obs: episode.obs[t].clone().into(),
+                    act: episode.act[t].clone().into(),
+                    next_obs: episode.obs[t + 1].clone().into(),
+                    reward: vec![episode.reward[t]],
+                    is_terminated: vec![episode.is_terminated[t]],
+                    is_truncated: vec![episode.is_truncated[t]],
+                    ix_sample: None,
+                    weight: None,
+                    mask: None,
+                    mc_return: None,
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds a replay buffer with the transitions of the episodes.
+    ///
+    /// Transitions of old episodes are overwritten if the number of transitions
+    /// exceeds the capacity of the replay buffer.
+    pub fn to_replay_buffer<OB, AB>(
+        &self,
+        config: &SimpleReplayBufferConfig,
+    ) -> Result<SimpleReplayBuffer<OB, AB>>
+    where
+        OB: BatchBase + From<O>,
+        AB: BatchBase + From<A>,
+        A: Clone,
+    {
+        let mut buffer = SimpleReplayBuffer::build(config);
+        self.push_into(&mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+#[cfg(all(test, feature = "agent-dev"))]
+mod tests {
+    use super::*;
+    use crate::agent_dev::{
+        Corridor, CorridorAct, CorridorActBatch, CorridorConfig, CorridorObs, CorridorObsBatch,
+    };
+    use tempdir::TempDir;
+
+    /// Moves right in a corridor of length 4, where an episode ends in 3 steps.
+    fn write_episodes(writer: &mut EpisodeWriter<Corridor>, n_steps: usize) -> Result<()> {
+        let mut env = Corridor::build(&CorridorConfig::default().length(4), 0)?;
+        writer.reset(&env.reset(None)?)?;
+        for _ in 0..n_steps {
+            let (step, _) = env.step_with_reset(&CorridorAct(1));
+            writer.push(&step)?;
+        }
+        writer.flush()
+    }
+
+    #[test]
+    fn test_episode_writer_reader() -> Result<()> {
+        let dir = TempDir::new("episode")?;
+        let path = dir.path().join("episodes.chunks");
+        let mut writer = EpisodeWriter::<Corridor>::create(&path)?;
+        write_episodes(&mut writer, 7)?;
+        // The third episode is not finished
+        assert_eq!(writer.n_episodes(), 2);
+
+        let reader = EpisodeReader::<CorridorObs, CorridorAct>::open(&path)?;
+        assert!(reader.report().is_intact());
+        assert_eq!(reader.episodes().len(), 2);
+        let episode = &reader.episodes()[1];
+        assert_eq!(episode.obs, (0..4).map(CorridorObs).collect::<Vec<_>>());
+        assert_eq!(episode.is_terminated, vec![0, 0, 1]);
+        assert_eq!(episode.episode_return(), 1.0);
+
+        let config = SimpleReplayBufferConfig::default().capacity(10);
+        let buffer = reader.to_replay_buffer::<CorridorObsBatch, CorridorActBatch>(&config)?;
+        assert_eq!(buffer.len(), reader.n_transitions());
+        assert_eq!(buffer.len(), 6);
+        assert_eq!(buffer.num_terminated_flags(), 2);
+        assert_eq!(buffer.sum_rewards(), 2.0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_append_after_unfinished_episode() -> Result<()> {
+        let dir = TempDir::new("episode")?;
+        let path = dir.path().join("episodes.chunks");
+        write_episodes(&mut EpisodeWriter::create(&path)?, 5)?;
+
+        // The unfinished episode is discarded
+        let mut writer = EpisodeWriter::<Corridor>::append(&path)?;
+        write_episodes(&mut writer, 3)?;
+        assert_eq!(writer.n_episodes(), 1);
+
+        let reader = EpisodeReader::<CorridorObs, CorridorAct>::open(&path)?;
+        assert_eq!(reader.episodes().len(), 2);
+        assert!(reader.episodes().iter().all(|e| e.len() == 3));
+        Ok(())
+    }
+
+    #[test]
+    fn test_episodes_with_schema() -> Result<()> {
+        let dir = TempDir::new("episode")?;
+        let path = dir.path().join("episodes.chunks");
+        let schema = ReplayBufferSchema::new(vec![1], vec![1], "corridor-v1");
+        write_episodes(&mut EpisodeWriter::create_with_schema(&path, &schema)?, 3)?;
+
+        let reader =
+            EpisodeReader::<CorridorObs, CorridorAct>::open_with_schema(&path, &schema, None)?;
+        assert_eq!(reader.n_transitions(), 3);
+
+        let new = ReplayBufferSchema::new(vec![1], vec![1], "corridor-v2");
+        assert!(
+            EpisodeReader::<CorridorObs, CorridorAct>::open_with_schema(&path, &new, None).is_err()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,549 @@
+//! Hindsight Experience Replay (HER) for goal-conditioned tasks.
+//!
+//! [`HerReplayBuffer`] stores transitions of goal-conditioned environments, like FetchReach and
+//! PointMaze, and relabels desired goals of sampled transitions with goals achieved later in the
+//! same episode ([Andrychowicz et al., 2017](https://arxiv.org/abs/1707.01495)). Rewards of
+//! relabeled transitions are recomputed, so that agents learn from failed episodes in tasks
+//! with sparse rewards.
+//!
+//! Observations are assumed to be flat vectors containing the desired goal and the achieved goal,
+//! e.g., those created by concatenating `observation`, `desired_goal` and `achieved_goal` of
+//! dictionary observations. The positions of the goals are given in [`HerReplayBufferConfig`].
+use super::{BatchBase, GenericTransitionBatch};
+use crate::{ExperienceBufferBase, ReplayBufferBase, TransitionBatch};
+use anyhow::Result;
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Batch of observations whose goals can be read and replaced.
+pub trait GoalBatch: BatchBase {
+    /// Returns `dim` values from `offset` of the `ix`-th observation in the batch.
+    fn get_goal(&self, ix: usize, offset: usize, dim: usize) -> Vec<f32>;
+
+    /// Replaces values from `offset` of the `ix`-th observation in the batch with `goal`.
+    fn set_goal(&mut self, ix: usize, offset: usize, goal: &[f32]);
+}
+
+/// Strategy for choosing goals for relabeling.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq)]
+pub enum HerStrategy {
+    /// A goal achieved at a random step after the transition in the same episode.
+    Future,
+
+    /// The goal achieved at the last step of the episode.
+    Final,
+
+    /// A goal achieved at a random step of the same episode.
+    Episode,
+}
+
+/// Configuration of [`HerReplayBuffer`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct HerReplayBufferConfig {
+    /// Maximum number of transitions stored in the buffer.
+    pub capacity: usize,
+
+    /// Random seed for sampling.
+    pub seed: u64,
+
+    /// Strategy for choosing goals.
+    pub strategy: HerStrategy,
+
+    /// The number of relabeled transitions per original transition.
+    /// A sampled transition is relabeled with probability `k / (k + 1)`.
+    pub k: usize,
+
+    /// Offset of the desired goal in observations.
+    pub desired_goal_offset: usize,
+
+    /// Offset of the achieved goal in observations.
+    pub achieved_goal_offset: usize,
+
+    /// Dimension of goals.
+    pub goal_dim: usize,
+
+    /// A goal is regarded as achieved if the Euclidean distance to the desired goal
+    /// is less than this threshold. Relabeled rewards are `0` if the goal is achieved
+    /// and `-1` otherwise, as in Gymnasium-Robotics.
+    pub distance_threshold: f32,
+}
+
+impl Default for HerReplayBufferConfig {
+    /// Creates a default configuration with the `future` strategy and `k = 4`.
+    ///
+    /// The offsets and dimension of goals must be set for each environment.
+    fn default() -> Self {
+        Self {
+            capacity: 10000,
+            seed: 42,
+            strategy: HerStrategy::Future,
+            k: 4,
+            desired_goal_offset: 0,
+            achieved_goal_offset: 0,
+            goal_dim: 0,
+            distance_threshold: 0.05,
+        }
+    }
+}
+
+impl HerReplayBufferConfig {
+    /// Sets the capacity of the replay buffer.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets the random seed for sampling.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets the strategy for choosing goals.
+    pub fn strategy(mut self, strategy: HerStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// Sets the number of relabeled transitions per original transition.
+    pub fn k(mut self, k: usize) -> Self {
+        self.k = k;
+        self
+    }
+
+    /// Sets the positions of goals in observations.
+    ///
+    /// # Arguments
+    ///
+    /// * `desired_goal_offset` - Offset of the desired goal
+    /// * `achieved_goal_offset` - Offset of the achieved goal
+    /// * `goal_dim` - Dimension of goals
+    pub fn goal_layout(
+        mut self,
+        desired_goal_offset: usize,
+        achieved_goal_offset: usize,
+        goal_dim: usize,
+    ) -> Self {
+        self.desired_goal_offset = desired_goal_offset;
+        self.achieved_goal_offset = achieved_goal_offset;
+        self.goal_dim = goal_dim;
+        self
+    }
+
+    /// Sets the distance threshold for regarding goals as achieved.
+    pub fn distance_threshold(mut self, v: f32) -> Self {
+        self.distance_threshold = v;
+        self
+    }
+
+    /// Loads the configuration from a YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves the configuration to a YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Function computing the reward from an achieved goal and a desired goal.
+pub type GoalRewardFn = Box<dyn Fn(&[f32], &[f32]) -> f32 + Send>;
+
+/// Replay buffer with Hindsight Experience Replay.
+///
+/// Transitions are pushed as in [`SimpleReplayBuffer`], and episode boundaries are tracked
+/// with termination and truncation flags. When a batch is sampled, each transition is relabeled
+/// with probability `k / (k + 1)`: the desired goal in its observation and next observation is
+/// replaced with a goal achieved in the same episode, chosen with [`HerStrategy`], and the reward
+/// is recomputed from the achieved goal of the next observation. Transitions of the ongoing
+/// episode are relabeled with goals achieved so far.
+///
+/// The default reward is the sparse reward of Gymnasium-Robotics; it can be replaced with
+/// [`HerReplayBuffer::reward_fn()`].
+///
+/// # Examples
+///
+/// ```ignore
+/// // FetchReach: observation (10), desired_goal (3), achieved_goal (3)
+/// let config = HerReplayBufferConfig::default()
+///     .capacity(1_000_000)
+///     .goal_layout(10, 13, 3);
+/// let buffer = HerReplayBuffer::<ObsBatch, ActBatch>::build(&config);
+/// ```
+///
+/// [`SimpleReplayBuffer`]: super::SimpleReplayBuffer
+pub struct HerReplayBuffer<O, A>
+where
+    O: GoalBatch,
+    A: BatchBase,
+{
+    config: HerReplayBufferConfig,
+    i: usize,
+    size: usize,
+    obs: O,
+    act: A,
+    next_obs: O,
+    reward: Vec<f32>,
+    is_terminated: Vec<i8>,
+    is_truncated: Vec<i8>,
+
+    /// Episode ID of each transition.
+    episode: Vec<u64>,
+
+    /// Step of each transition in its episode.
+    t: Vec<usize>,
+
+    /// Length of the episode of each transition, `0` for the ongoing episode.
+    episode_len: Vec<usize>,
+
+    /// ID of the ongoing episode.
+    current_episode: u64,
+
+    /// The number of steps of the ongoing episode.
+    current_len: usize,
+
+    reward_fn: GoalRewardFn,
+    rng: StdRng,
+}
+
+impl<O, A> HerReplayBuffer<O, A>
+where
+    O: GoalBatch,
+    A: BatchBase,
+{
+    /// Sets the function computing rewards of relabeled transitions.
+    ///
+    /// The function takes an achieved goal and a desired goal.
+    pub fn reward_fn(mut self, f: GoalRewardFn) -> Self {
+        self.reward_fn = f;
+        self
+    }
+
+    /// Returns the length of the episode of the `ix`-th transition,
+    /// or the number of steps so far for the ongoing episode.
+    fn len_of_episode(&self, ix: usize) -> usize {
+        match self.episode_len[ix] {
+            0 => self.current_len,
+            n => n,
+        }
+    }
+
+    /// Returns the index in the buffer of step `t` in the episode of the `ix`-th transition,
+    /// or `None` if it has been overwritten.
+    fn index_in_episode(&self, ix: usize, t: usize) -> Option<usize> {
+        let capacity = self.config.capacity;
+        let j = match t.checked_sub(self.t[ix]) {
+            Some(d) if d < capacity => (ix + d) % capacity,
+            None if self.t[ix] - t < capacity => (ix + capacity - (self.t[ix] - t)) % capacity,
+            // Steps apart by the capacity or more are not in the buffer at the same time
+            _ => return None,
+        };
+        match self.episode[j] == self.episode[ix] && self.t[j] == t {
+            true => Some(j),
+            false => None,
+        }
+    }
+
+    /// Chooses the index of the transition whose achieved goal is used for relabeling.
+    fn goal_index(&mut self, ix: usize) -> usize {
+        let len = self.len_of_episode(ix);
+        let t = self.t[ix];
+        let future = |rng: &mut StdRng| rng.gen_range(t..len);
+        let t_goal = match self.config.strategy {
+            HerStrategy::Final => len - 1,
+            HerStrategy::Future => future(&mut self.rng),
+            HerStrategy::Episode => self.rng.gen_range(0..len),
+        };
+        match self.index_in_episode(ix, t_goal) {
+            Some(j) => j,
+            // Earlier steps of the episode have been overwritten
+            None => {
+                let t_goal = future(&mut self.rng);
+                self.index_in_episode(ix, t_goal).unwrap_or(ix)
+            }
+        }
+    }
+
+    /// Updates the episode information of the `ix`-th transition.
+    fn push_episode(&mut self, ix: usize, is_done: bool) {
+        self.episode[ix] = self.current_episode;
+        self.t[ix] = self.current_len;
+        self.episode_len[ix] = 0;
+        self.current_len += 1;
+
+        if is_done {
+            let len = self.current_len;
+            for t in 0..len.min(self.config.capacity) {
+                let j = (ix + self.config.capacity - t) % self.config.capacity;
+                if self.episode[j] != self.current_episode {
+                    break;
+                }
+                self.episode_len[j] = len;
+            }
+            self.current_episode += 1;
+            self.current_len = 0;
+        }
+    }
+}
+
+impl<O, A> ExperienceBufferBase for HerReplayBuffer<O, A>
+where
+    O: GoalBatch,
+    A: BatchBase,
+{
+    type Item = GenericTransitionBatch<O, A>;
+
+    fn len(&self) -> usize {
+        self.size
+    }
+
+    fn push(&mut self, tr: Self::Item) -> Result<()> {
+        let len = tr.len();
+        let (obs, act, next_obs, reward, is_terminated, is_truncated, _, _) = tr.unpack();
+        self.obs.push(self.i, obs);
+        self.act.push(self.i, act);
+        self.next_obs.push(self.i, next_obs);
+
+        for j in 0..len {
+            let ix = (self.i + j) % self.config.capacity;
+            self.reward[ix] = reward[j];
+            self.is_terminated[ix] = is_terminated[j];
+            self.is_truncated[ix] = is_truncated[j];
+            self.push_episode(ix, is_terminated[j] == 1 || is_truncated[j] == 1);
+        }
+
+        self.i = (self.i + len) % self.config.capacity;
+        self.size = (self.size + len).min(self.config.capacity);
+
+        Ok(())
+    }
+}
+
+impl<O, A> ReplayBufferBase for HerReplayBuffer<O, A>
+where
+    O: GoalBatch,
+    A: BatchBase,
+{
+    type Config = HerReplayBufferConfig;
+    type Batch = GenericTransitionBatch<O, A>;
+
+    fn build(config: &Self::Config) -> Self {
+        let capacity = config.capacity;
+        let threshold = config.distance_threshold;
+        let reward_fn: GoalRewardFn = Box::new(move |achieved, desired| {
+            let d2: f32 = achieved
+                .iter()
+                .zip(desired.iter())
+                .map(|(a, d)| (a - d) * (a - d))
+                .sum();
+            -((d2.sqrt() >= threshold) as i32 as f32)
+        });
+
+        Self {
+            config: config.clone(),
+            i: 0,
+            size: 0,
+            obs: O::new(capacity),
+            act: A::new(capacity),
+            next_obs: O::new(capacity),
+            reward: vec![0.; capacity],
+            is_terminated: vec![0; capacity],
+            is_truncated: vec![0; capacity],
+            episode: vec![u64::MAX; capacity],
+            t: vec![0; capacity],
+            episode_len: vec![0; capacity],
+            current_episode: 0,
+            current_len: 0,
+            reward_fn,
+            rng: StdRng::seed_from_u64(config.seed),
+        }
+    }
+
+    /// Samples a batch of transitions, relabeling goals of some of them.
+    fn batch(&mut self, size: usize) -> Result<Self::Batch> {
+        if self.size == 0 {
+            anyhow::bail!("The replay buffer is empty");
+        }
+        let ixs = (0..size)
+            .map(|_| (self.rng.next_u32() as usize) % self.size)
+            .collect::<Vec<_>>();
+        let mut obs = self.obs.sample(&ixs);
+        let mut next_obs = self.next_obs.sample(&ixs);
+        let mut reward = ixs.iter().map(|&ix| self.reward[ix]).collect::<Vec<_>>();
+
+        let p = self.config.k as f64 / (self.config.k + 1) as f64;
+        let (dg, ag, dim) = (
+            self.config.desired_goal_offset,
+            self.config.achieved_goal_offset,
+            self.config.goal_dim,
+        );
+        for (b, &ix) in ixs.iter().enumerate() {
+            if !self.rng.gen_bool(p) {
+                continue;
+            }
+            let j = self.goal_index(ix);
+            let goal = self.next_obs.get_goal(j, ag, dim);
+            obs.set_goal(b, dg, &goal);
+            next_obs.set_goal(b, dg, &goal);
+            reward[b] = (self.reward_fn)(&next_obs.get_goal(b, ag, dim), &goal);
+        }
+
+        Ok(Self::Batch {
+            obs,
+            act: self.act.sample(&ixs),
+            next_obs,
+            reward,
+            is_terminated: ixs.iter().map(|&ix| self.is_terminated[ix]).collect(),
+            is_truncated: ixs.iter().map(|&ix| self.is_truncated[ix]).collect(),
+            weight: None,
+            mask: None,
+            mc_return: None,
+            ix_sample: Some(ixs),
+        })
+    }
+
+    fn update_priority(&mut self, _ixs: &Option<Vec<usize>>, _td_err: &Option<Vec<f32>>) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Observations of 2 dimensions: desired goal and achieved goal.
+    struct GoalObsBatch(Vec<[f32; 2]>);
+
+    impl BatchBase for GoalObsBatch {
+        fn new(capacity: usize) -> Self {
+            Self(vec![[0.0; 2]; capacity])
+        }
+
+        fn push(&mut self, ix: usize, data: Self) {
+            let capacity = self.0.len();
+            for (j, v) in data.0.into_iter().enumerate() {
+                self.0[(ix + j) % capacity] = v;
+            }
+        }
+
+        fn sample(&self, ixs: &Vec<usize>) -> Self {
+            Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+        }
+    }
+
+    impl GoalBatch for GoalObsBatch {
+        fn get_goal(&self, ix: usize, offset: usize, dim: usize) -> Vec<f32> {
+            self.0[ix][offset..offset + dim].to_vec()
+        }
+
+        fn set_goal(&mut self, ix: usize, offset: usize, goal: &[f32]) {
+            self.0[ix][offset..offset + goal.len()].copy_from_slice(goal);
+        }
+    }
+
+    struct ActBatch;
+
+    impl BatchBase for ActBatch {
+        fn new(_capacity: usize) -> Self {
+            Self
+        }
+
+        fn push(&mut self, _ix: usize, _data: Self) {}
+
+        fn sample(&self, _ixs: &Vec<usize>) -> Self {
+            Self
+        }
+    }
+
+    /// Pushes an episode where the achieved goal moves 0, 1, 2, ... and the desired goal is 100.
+    fn push_episode(buffer: &mut HerReplayBuffer<GoalObsBatch, ActBatch>, len: usize) {
+        for t in 0..len {
+            buffer
+                .push(GenericTransitionBatch {
+                    obs: GoalObsBatch(vec![[100.0, t as f32]]),
+                    act: ActBatch,
+                    next_obs: GoalObsBatch(vec![[100.0, t as f32 + 1.0]]),
+                    reward: vec![-1.0],
+                    is_terminated: vec![0],
+                    is_truncated: vec![(t == len - 1) as i8],
+                    weight: None,
+                    ix_sample: None,
+                    mask: None,
+                    mc_return: None,
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_her_future() -> Result<()> {
+        let config = HerReplayBufferConfig::default()
+            .capacity(8)
+            .k(1_000_000)
+            .goal_layout(0, 1, 1)
+            .distance_threshold(0.5);
+        let mut buffer = HerReplayBuffer::<GoalObsBatch, ActBatch>::build(&config);
+        push_episode(&mut buffer, 5);
+        push_episode(&mut buffer, 5); // overwrites the first episode partially
+
+        let batch = buffer.batch(100)?;
+        for (b, &ix) in batch.ix_sample.as_ref().unwrap().iter().enumerate() {
+            // Goals are achieved in the future of the same episode
+            let goal = batch.obs.0[b][0];
+            let achieved = batch.next_obs.0[b][1];
+            assert_eq!(goal, batch.next_obs.0[b][0]);
+            assert!(goal >= achieved && goal <= 5.0);
+            assert!(goal > buffer.t[ix] as f32);
+            let reward = if goal == achieved { 0.0 } else { -1.0 };
+            assert_eq!(batch.reward[b], reward);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_her_episode_longer_than_capacity() -> Result<()> {
+        let config = HerReplayBufferConfig::default()
+            .capacity(8)
+            .strategy(HerStrategy::Episode)
+            .k(1_000_000)
+            .goal_layout(0, 1, 1);
+        let mut buffer = HerReplayBuffer::<GoalObsBatch, ActBatch>::build(&config);
+        push_episode(&mut buffer, 20);
+
+        // Goals are achieved in the last 8 steps remaining in the buffer
+        let batch = buffer.batch(100)?;
+        for b in 0..100 {
+            let goal = batch.obs.0[b][0];
+            assert!((13.0..=20.0).contains(&goal));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_her_final() -> Result<()> {
+        let config = HerReplayBufferConfig::default()
+            .capacity(16)
+            .strategy(HerStrategy::Final)
+            .k(1_000_000)
+            .goal_layout(0, 1, 1);
+        let mut buffer = HerReplayBuffer::<GoalObsBatch, ActBatch>::build(&config);
+        push_episode(&mut buffer, 4);
+        push_episode(&mut buffer, 3);
+
+        let batch = buffer.batch(50)?;
+        for (b, &ix) in batch.ix_sample.as_ref().unwrap().iter().enumerate() {
+            let final_goal = if ix < 4 { 4.0 } else { 3.0 };
+            assert_eq!(batch.obs.0[b][0], final_goal);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Extraction of transitions from replay buffers for inspection.
+use super::{BatchBase, GenericTransitionBatch};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Batches whose elements can be written to files for inspection.
+///
+/// Implementations decode the elements into a format readable with common tools,
+/// e.g., `.npy` arrays or `.png` images of Atari frames.
+pub trait DumpBatch: BatchBase {
+    /// Writes the `i`-th element of the batch to a file.
+    ///
+    /// `path` does not have an extension, which is appended by the implementation.
+    /// Returns the path of the written file.
+    fn dump(&self, i: usize, path: &Path) -> Result<PathBuf>;
+}
+
+/// Transitions selected from a replay buffer by their scores, e.g., TD errors.
+///
+/// They are obtained with [`SimpleReplayBuffer::top_k_by_priority()`] or
+/// [`SimpleReplayBuffer::top_k_by_score()`], sorted in descending order of the scores.
+///
+/// [`SimpleReplayBuffer::top_k_by_priority()`]: super::SimpleReplayBuffer::top_k_by_priority
+/// [`SimpleReplayBuffer::top_k_by_score()`]: super::SimpleReplayBuffer::top_k_by_score
+pub struct SelectedTransitions<O, A>
+where
+    O: BatchBase,
+    A: BatchBase,
+{
+    /// Scores of the transitions.
+    pub scores: Vec<f32>,
+
+    /// The transitions, whose `ix_sample` has their indices in the replay buffer.
+    pub batch: GenericTransitionBatch<O, A>,
+}
+
+/// An entry of the index file written by [`SelectedTransitions::dump()`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DumpedTransition {
+    /// Index of the transition in the replay buffer.
+    pub ix: usize,
+
+    /// Score of the transition.
+    pub score: f32,
+
+    /// Reward of the transition.
+    pub reward: f32,
+
+    /// Termination flag of the transition.
+    pub is_terminated: i8,
+
+    /// Truncation flag of the transition.
+    pub is_truncated: i8,
+
+    /// File of the observation.
+    pub obs: PathBuf,
+
+    /// File of the action.
+    pub act: PathBuf,
+
+    /// File of the next observation.
+    pub next_obs: PathBuf,
+}
+
+impl<O, A> SelectedTransitions<O, A>
+where
+    O: DumpBatch,
+    A: DumpBatch,
+{
+    /// Writes the transitions into directory `dir`.
+    ///
+    /// The observations and actions of the `r`-th transition are written in files named
+    /// `{r:04}_obs`, `{r:04}_act` and `{r:04}_next_obs` with [`DumpBatch::dump()`].
+    /// `transitions.yaml` lists the transitions with their scores, rewards, flags and files.
+    pub fn dump(&self, dir: impl AsRef<Path>) -> Result<Vec<DumpedTransition>> {
+        let dir = dir.as_ref();
+        std::fs::create_dir_all(dir)?;
+
+        let batch = &self.batch;
+        let ixs = batch.ix_sample.clone().unwrap_or_default();
+        let mut entries = vec![];
+        for (r, score) in self.scores.iter().enumerate() {
+            entries.push(DumpedTransition {
+                ix: ixs.get(r).copied().unwrap_or(r),
+                score: *score,
+                reward: batch.reward[r],
+                is_terminated: batch.is_terminated[r],
+                is_truncated: batch.is_truncated[r],
+                obs: batch.obs.dump(r, &dir.join(format!("{:04}_obs", r)))?,
+                act: batch.act.dump(r, &dir.join(format!("{:04}_act", r)))?,
+                next_obs: batch
+                    .next_obs
+                    .dump(r, &dir.join(format!("{:04}_next_obs", r)))?,
+            });
+        }
+
+        let mut file = File::create(dir.join("transitions.yaml"))?;
+        file.write_all(serde_yaml::to_string(&entries)?.as_bytes())?;
+        Ok(entries)
+    }
+}
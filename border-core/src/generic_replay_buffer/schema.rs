@@ -0,0 +1,288 @@
+//! Schema tagging of persisted replay buffers.
+//!
+//! A chunk file written by [`ChunkWriter`] holds encoded transitions as opaque bytes.
+//! When an observation converter changes, e.g., a goal is added to observations,
+//! chunks written with the old converter are no longer compatible with the new one.
+//! [`ReplayBufferSchema`] describes the layout of transitions and is stored in a YAML
+//! file next to the chunk file, so that [`read_chunks_with_schema()`] detects the
+//! mismatch and adapts the chunks with a [`SchemaMigration`] instead of silently
+//! decoding them with wrong shapes. The schema also specifies the [`Codec`] compressing
+//! the payloads of chunks.
+//!
+//! [`Codec`]: crate::codec::Codec
+use super::{scan_chunks, ChunkReport, ChunkWriter};
+use crate::codec::CodecConfig;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+/// Layout of transitions in a persisted replay buffer.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReplayBufferSchema {
+    /// Shape of an observation.
+    pub obs_shape: Vec<usize>,
+
+    /// Shape of an action.
+    pub act_shape: Vec<usize>,
+
+    /// Name of the converter of observations and actions, including its version if any.
+    pub converter: String,
+
+    /// Mean of observations used for normalization, if observations are normalized.
+    #[serde(default)]
+    pub obs_mean: Option<Vec<f32>>,
+
+    /// Standard deviation of observations used for normalization,
+    /// if observations are normalized.
+    #[serde(default)]
+    pub obs_std: Option<Vec<f32>>,
+
+    /// Codec compressing the payloads of chunks.
+    #[serde(default)]
+    pub codec: CodecConfig,
+}
+
+impl ReplayBufferSchema {
+    /// Constructs a schema.
+    pub fn new(obs_shape: Vec<usize>, act_shape: Vec<usize>, converter: impl Into<String>) -> Self {
+        Self {
+            obs_shape,
+            act_shape,
+            converter: converter.into(),
+            obs_mean: None,
+            obs_std: None,
+            codec: CodecConfig::None,
+        }
+    }
+
+    /// Sets the codec compressing the payloads of chunks.
+    pub fn codec(mut self, v: CodecConfig) -> Self {
+        self.codec = v;
+        self
+    }
+
+    /// Sets the statistics used for the normalization of observations.
+    pub fn normalization(mut self, mean: Vec<f32>, std: Vec<f32>) -> Self {
+        self.obs_mean = Some(mean);
+        self.obs_std = Some(std);
+        self
+    }
+
+    /// Returns the names of the fields different from those of `other`.
+    ///
+    /// The codec is not compared, as chunks are decompressed before migration.
+    pub fn diff(&self, other: &Self) -> Vec<&'static str> {
+        let mut fields = vec![];
+        if self.obs_shape != other.obs_shape {
+            fields.push("obs_shape");
+        }
+        if self.act_shape != other.act_shape {
+            fields.push("act_shape");
+        }
+        if self.converter != other.converter {
+            fields.push("converter");
+        }
+        if self.obs_mean != other.obs_mean || self.obs_std != other.obs_std {
+            fields.push("normalization");
+        }
+        fields
+    }
+
+    /// Loads a schema from a YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let rdr = BufReader::new(File::open(path)?);
+        Ok(serde_yaml::from_reader(rdr)?)
+    }
+
+    /// Saves the schema as a YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let wtr = BufWriter::new(File::create(path)?);
+        serde_yaml::to_writer(wtr, self)?;
+        Ok(())
+    }
+}
+
+/// Adapts chunks written with an old schema to a new schema.
+///
+/// It is implemented for closures taking the old schema, the new schema and the payload of a chunk.
+pub trait SchemaMigration {
+    /// Converts the payload of a chunk written with schema `from` into one of schema `to`.
+    fn migrate(
+        &self,
+        from: &ReplayBufferSchema,
+        to: &ReplayBufferSchema,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>>;
+}
+
+impl<F> SchemaMigration for F
+where
+    F: Fn(&ReplayBufferSchema, &ReplayBufferSchema, Vec<u8>) -> Result<Vec<u8>>,
+{
+    fn migrate(
+        &self,
+        from: &ReplayBufferSchema,
+        to: &ReplayBufferSchema,
+        chunk: Vec<u8>,
+    ) -> Result<Vec<u8>> {
+        self(from, to, chunk)
+    }
+}
+
+/// Returns the path of the schema file of a chunk file, `<path>.schema.yaml`.
+pub fn schema_path(path: impl AsRef<Path>) -> PathBuf {
+    let mut path = path.as_ref().as_os_str().to_owned();
+    path.push(".schema.yaml");
+    path.into()
+}
+
+impl ChunkWriter {
+    /// Creates a new chunk file tagged with a schema, truncating an existing one.
+    ///
+    /// The schema is saved in [`schema_path()`] of the chunk file.
+    /// Chunks are compressed with the codec of the schema.
+    pub fn create_with_schema(path: impl AsRef<Path>, schema: &ReplayBufferSchema) -> Result<Self> {
+        let codec = schema.codec.build()?;
+        schema.save(schema_path(path.as_ref()))?;
+        let mut writer = Self::create(path)?;
+        writer.codec = codec;
+        Ok(writer)
+    }
+}
+
+/// Scans a chunk file with [`scan_chunks()`], checking its schema against `schema`.
+///
+/// * If the file has no schema, the chunks are given to `f` as they are with a warning.
+/// * If the schema of the file is the same as `schema`, the chunks are given as they are.
+/// * Otherwise, each chunk is converted with `migration` before given to `f`.
+///
+/// Chunks of a file with a schema are decompressed with its codec before the migration.
+///
+/// # Errors
+///
+/// Returns an error if the schemas differ and `migration` is `None`, or the migration fails.
+pub fn scan_chunks_with_schema(
+    path: impl AsRef<Path>,
+    schema: &ReplayBufferSchema,
+    migration: Option<&dyn SchemaMigration>,
+    mut f: impl FnMut(Vec<u8>) -> Result<()>,
+) -> Result<ChunkReport> {
+    let path = path.as_ref();
+    let schema_file = schema_path(path);
+
+    if !schema_file.exists() {
+        log::warn!(
+            "{:?} has no schema, chunks are loaded without checking their layout",
+            path
+        );
+        return scan_chunks(path, f);
+    }
+
+    let from = ReplayBufferSchema::load(&schema_file)?;
+    let codec = from.codec.build()?;
+    let diff = from.diff(schema);
+    let migration = match (diff.is_empty(), migration) {
+        (true, _) => None,
+        (false, None) => anyhow::bail!(
+            "Schema of {:?} differs in {:?}: {:?} != {:?}",
+            path,
+            diff,
+            from,
+            schema
+        ),
+        (false, Some(migration)) => {
+            log::info!("Migrating {:?}, which differs in {:?}", path, diff);
+            Some(migration)
+        }
+    };
+
+    scan_chunks(path, |chunk| {
+        let chunk = codec.decompress(&chunk)?;
+        match migration {
+            None => f(chunk),
+            Some(migration) => f(migration.migrate(&from, schema, chunk)?),
+        }
+    })
+}
+
+/// Reads the valid chunks of a file, checking its schema against `schema`.
+///
+/// See [`scan_chunks_with_schema()`] for how the schema is checked.
+pub fn read_chunks_with_schema(
+    path: impl AsRef<Path>,
+    schema: &ReplayBufferSchema,
+    migration: Option<&dyn SchemaMigration>,
+) -> Result<(Vec<Vec<u8>>, ChunkReport)> {
+    let mut chunks = vec![];
+    let report = scan_chunks_with_schema(path, schema, migration, |chunk| {
+        chunks.push(chunk);
+        Ok(())
+    })?;
+    Ok((chunks, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_read_chunks_with_schema() -> Result<()> {
+        let dir = TempDir::new("schema")?;
+        let path = dir.path().join("buffer.chunks");
+        let old = ReplayBufferSchema::new(vec![2], vec![1], "converter-v1");
+        let mut writer = ChunkWriter::create_with_schema(&path, &old)?;
+        writer.write_chunk(&[1, 2])?;
+        writer.write_chunk(&[3, 4])?;
+        writer.flush()?;
+
+        // Same schema
+        let (chunks, _) = read_chunks_with_schema(&path, &old, None)?;
+        assert_eq!(chunks, vec![vec![1, 2], vec![3, 4]]);
+
+        // A goal is added to observations
+        let new = ReplayBufferSchema::new(vec![3], vec![1], "converter-v2");
+        assert_eq!(old.diff(&new), vec!["obs_shape", "converter"]);
+        assert!(read_chunks_with_schema(&path, &new, None).is_err());
+
+        let add_goal = |_: &ReplayBufferSchema,
+                        _: &ReplayBufferSchema,
+                        mut chunk: Vec<u8>|
+         -> Result<Vec<u8>> {
+            chunk.push(0);
+            Ok(chunk)
+        };
+        let (chunks, _) = read_chunks_with_schema(&path, &new, Some(&add_goal))?;
+        assert_eq!(chunks, vec![vec![1, 2, 0], vec![3, 4, 0]]);
+        Ok(())
+    }
+
+    #[cfg(feature = "lz4")]
+    #[test]
+    fn test_compressed_chunks() -> Result<()> {
+        let dir = TempDir::new("schema")?;
+        let path = dir.path().join("buffer.chunks");
+        let schema =
+            ReplayBufferSchema::new(vec![256], vec![1], "converter").codec(CodecConfig::Lz4);
+        let payload = vec![7u8; 256];
+        let mut writer = ChunkWriter::create_with_schema(&path, &schema)?;
+        writer.write_chunk(&payload)?;
+        writer.flush()?;
+
+        // Appended chunks are compressed with the codec in the schema
+        let mut writer = ChunkWriter::append(&path)?;
+        writer.write_chunk(&payload)?;
+        writer.flush()?;
+
+        let (raw, _) = crate::generic_replay_buffer::read_chunks(&path)?;
+        assert!(raw.iter().all(|chunk| chunk.len() < payload.len()));
+        let (chunks, report) = read_chunks_with_schema(&path, &schema, None)?;
+        assert!(report.is_intact());
+        assert_eq!(chunks, vec![payload.clone(), payload]);
+        Ok(())
+    }
+}
@@ -6,19 +6,152 @@
 //! - 1-step TD backup for non-vectorized environments
 //! - Generic observation and action types
 //! - Efficient batch processing
+//! - Treating the loss of a life as termination for bootstrapping
+//! - Bootstrapping on or terminating at truncated transitions, e.g., time limits
+//! - Logging the empirical distribution of executed actions
 
 use super::{BatchBase, GenericTransitionBatch};
-use crate::{Env, Obs, StepProcessor};
+use crate::{
+    record::{Record, RecordValue},
+    Act, ActValues, Env, Info, Obs, StepProcessor,
+};
 use std::{default::Default, marker::PhantomData};
 
 /// Configuration for the simple step processor.
 #[derive(Clone, Debug)]
-pub struct SimpleStepProcessorConfig {}
+pub struct SimpleStepProcessorConfig {
+    /// If `true`, transitions where a life has been lost, given by [`Info::is_life_lost()`],
+    /// are stored as terminated while the episode continues.
+    pub terminate_on_life_loss: bool,
+
+    /// If `true`, transitions truncated but not terminated, e.g., at time limits, are stored
+    /// as they are, so that agents bootstrap from the next observation in TD targets.
+    /// If `false`, they are stored as terminated.
+    pub bootstrap_on_truncation: bool,
+
+    /// If `true`, the empirical distribution of executed actions is recorded,
+    /// see [`ActDistribution`].
+    pub act_distribution: bool,
+}
 
 impl Default for SimpleStepProcessorConfig {
     /// Creates a new default configuration.
     fn default() -> Self {
-        Self {}
+        Self {
+            terminate_on_life_loss: false,
+            bootstrap_on_truncation: true,
+            act_distribution: false,
+        }
+    }
+}
+
+impl SimpleStepProcessorConfig {
+    /// Sets `true` to store transitions where a life has been lost as terminated.
+    ///
+    /// This matches the training setup of DQN on Atari games, where the target value is not
+    /// bootstrapped at the loss of a life while the episode continues until the game is over.
+    pub fn terminate_on_life_loss(mut self, v: bool) -> Self {
+        self.terminate_on_life_loss = v;
+        self
+    }
+
+    /// Sets `false` to store truncated transitions as terminated.
+    ///
+    /// Agents compute TD targets with `is_terminated` of transitions, while `is_truncated` is
+    /// stored separately. By default, the target value is bootstrapped at time limits, which
+    /// do not mean the end of the task, e.g., `TimeLimit` of Mujoco environments. Setting
+    /// `false` reproduces implementations treating any end of episodes as termination.
+    pub fn bootstrap_on_truncation(mut self, v: bool) -> Self {
+        self.bootstrap_on_truncation = v;
+        self
+    }
+
+    /// Sets `true` to record the empirical distribution of executed actions.
+    ///
+    /// It helps to diagnose premature collapse of policies, which is hard to notice
+    /// until returns flatline. Actions should implement [`Act::values()`].
+    pub fn act_distribution(mut self, v: bool) -> Self {
+        self.act_distribution = v;
+        self
+    }
+}
+
+/// Empirical distribution of executed actions.
+///
+/// For discrete actions, the record has the frequency of each action as `act_freq/{i}`
+/// and the entropy of the frequencies as `act_entropy`. For continuous actions, it has
+/// the mean and standard deviation of each dimension as `act_mean/{d}` and `act_std/{d}`.
+#[derive(Clone, Debug, Default)]
+pub struct ActDistribution {
+    /// Counts of discrete actions.
+    counts: Vec<usize>,
+    /// Sums of continuous actions.
+    sum: Vec<f64>,
+    /// Sums of squares of continuous actions.
+    sum_sq: Vec<f64>,
+    /// The number of continuous actions.
+    n: usize,
+}
+
+impl ActDistribution {
+    /// Accumulates actions.
+    pub fn push(&mut self, values: &ActValues) {
+        match values {
+            ActValues::Discrete(ixs) => {
+                for ix in ixs.iter() {
+                    if *ix >= self.counts.len() {
+                        self.counts.resize(ix + 1, 0);
+                    }
+                    self.counts[*ix] += 1;
+                }
+            }
+            ActValues::Continuous(acts) => {
+                for act in acts.iter() {
+                    if self.sum.len() != act.len() {
+                        self.sum = vec![0.0; act.len()];
+                        self.sum_sq = vec![0.0; act.len()];
+                        self.n = 0;
+                    }
+                    for (d, v) in act.iter().enumerate() {
+                        self.sum[d] += *v as f64;
+                        self.sum_sq[d] += (*v as f64).powi(2);
+                    }
+                    self.n += 1;
+                }
+            }
+        }
+    }
+
+    /// Returns the record of the distribution of the actions accumulated so far,
+    /// then clears them.
+    pub fn take_record(&mut self) -> Record {
+        let mut record = Record::empty();
+
+        let total = self.counts.iter().sum::<usize>();
+        if total > 0 {
+            let mut entropy = 0.0;
+            for (i, c) in self.counts.iter().enumerate() {
+                let p = *c as f32 / total as f32;
+                if p > 0.0 {
+                    entropy -= p * p.ln();
+                }
+                record.insert(format!("act_freq/{}", i), RecordValue::Scalar(p));
+            }
+            record.insert("act_entropy", RecordValue::Scalar(entropy));
+        }
+
+        if self.n > 0 {
+            let n = self.n as f64;
+            for (d, (s, s2)) in self.sum.iter().zip(self.sum_sq.iter()).enumerate() {
+                let mean = s / n;
+                let std = (s2 / n - mean * mean).max(0.0).sqrt();
+                record.insert(format!("act_mean/{}", d), RecordValue::Scalar(mean as f32));
+                record.insert(format!("act_std/{}", d), RecordValue::Scalar(std as f32));
+            }
+        }
+
+        *self = Self::default();
+        record
     }
 }
 
@@ -37,6 +170,12 @@ impl Default for SimpleStepProcessorConfig {
 pub struct SimpleStepProcessor<E, O, A> {
     /// The previous observation, used to construct transitions.
     prev_obs: Option<O>,
+    /// If `true`, the loss of a life is stored as termination.
+    terminate_on_life_loss: bool,
+    /// If `false`, truncation is stored as termination.
+    bootstrap_on_truncation: bool,
+    /// Distribution of executed actions, if recorded.
+    act_distribution: Option<ActDistribution>,
     /// Phantom data to hold the generic type parameters.
     phantom: PhantomData<(E, A)>,
 }
@@ -54,14 +193,20 @@ where
     ///
     /// # Arguments
     ///
-    /// * `_config` - The configuration for the processor
+    /// * `config` - The configuration for the processor
     ///
     /// # Returns
     ///
     /// A new instance of the step processor
-    fn build(_config: &Self::Config) -> Self {
+    fn build(config: &Self::Config) -> Self {
         Self {
             prev_obs: None,
+            terminate_on_life_loss: config.terminate_on_life_loss,
+            bootstrap_on_truncation: config.bootstrap_on_truncation,
+            act_distribution: match config.act_distribution {
+                true => Some(ActDistribution::default()),
+                false => None,
+            },
             phantom: PhantomData,
         }
     }
@@ -85,6 +230,7 @@ where
     /// - Converting observations and actions to the appropriate batch types
     /// - Managing the previous observation for constructing transitions
     /// - Handling episode termination and truncation
+    /// - Storing the loss of a life and truncation as termination if configured
     ///
     /// # Arguments
     ///
@@ -97,11 +243,11 @@ where
     /// # Panics
     ///
     /// This method will panic if:
-    /// - The step contains more than one observation
+    /// - The number of observations differs from the number of rewards
     /// - `reset()` has not been called before processing steps
     /// - The step is terminal but does not contain an initial observation
     fn process(&mut self, step: crate::Step<E>) -> Self::Output {
-        assert_eq!(step.obs.len(), 1);
+        assert_eq!(step.obs.len(), step.reward.len());
 
         let batch = if self.prev_obs.is_none() {
             panic!("prev_obs is not set. Forgot to call reset()?");
@@ -109,10 +255,29 @@ where
             let is_done = step.is_done();
             let next_obs = step.obs.clone().into();
             let obs = self.prev_obs.replace(step.obs.into()).unwrap();
+            if let Some(dist) = self.act_distribution.as_mut() {
+                if let Some(values) = step.act.values() {
+                    dist.push(&values);
+                }
+            }
             let act = step.act.into();
             let reward = step.reward;
-            let is_terminated = step.is_terminated;
+            let mut is_terminated = step.is_terminated;
+            if self.terminate_on_life_loss {
+                if let Some(is_life_lost) = step.info.is_life_lost() {
+                    is_terminated
+                        .iter_mut()
+                        .zip(is_life_lost.iter())
+                        .for_each(|(t, l)| *t |= *l);
+                }
+            }
             let is_truncated = step.is_truncated;
+            if !self.bootstrap_on_truncation {
+                is_terminated
+                    .iter_mut()
+                    .zip(is_truncated.iter())
+                    .for_each(|(t, tr)| *t |= *tr);
+            }
             let ix_sample = None;
             let weight = None;
 
@@ -130,9 +295,116 @@ where
                 is_truncated,
                 ix_sample,
                 weight,
+                mask: None,
+                mc_return: None,
             }
         };
 
         batch
     }
+
+    /// Returns the record of the distribution of actions executed since the last call
+    /// if [`SimpleStepProcessorConfig::act_distribution`] is `true`.
+    fn record(&mut self) -> Record {
+        match self.act_distribution.as_mut() {
+            Some(dist) => dist.take_record(),
+            None => Record::empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{CountAct, CountEnv, CountEnvConfig, CountObs};
+    use anyhow::Result;
+
+    #[derive(Debug, PartialEq)]
+    struct Batch(Vec<f32>);
+
+    impl BatchBase for Batch {
+        fn new(capacity: usize) -> Self {
+            Self(vec![0.0; capacity])
+        }
+
+        fn push(&mut self, ix: usize, data: Self) {
+            self.0[ix] = data.0[0];
+        }
+
+        fn sample(&self, ixs: &Vec<usize>) -> Self {
+            Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+        }
+    }
+
+    impl From<CountObs> for Batch {
+        fn from(obs: CountObs) -> Self {
+            Self(obs.counts())
+        }
+    }
+
+    impl From<CountAct> for Batch {
+        fn from(act: CountAct) -> Self {
+            Self(act.0.iter().map(|&a| a as f32).collect())
+        }
+    }
+
+    #[test]
+    fn test_terminate_on_life_loss() -> Result<()> {
+        // A life is lost at every step
+        let mut env = CountEnv::build(&CountEnvConfig::default().lose_life(true), 0)?;
+
+        for (v, expected) in [(false, 0), (true, 1)].iter() {
+            let config = SimpleStepProcessorConfig::default().terminate_on_life_loss(*v);
+            let mut step_proc = SimpleStepProcessor::<CountEnv, Batch, Batch>::build(&config);
+            step_proc.reset(env.reset(None)?);
+            let (step, _) = env.step_with_reset(&CountAct(vec![0]));
+            let batch = step_proc.process(step);
+            assert_eq!(batch.is_terminated, vec![*expected]);
+            assert_eq!(batch.obs, Batch(vec![0.0]));
+            assert_eq!(batch.next_obs, Batch(vec![1.0]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_bootstrap_on_truncation() -> Result<()> {
+        // Episodes are truncated at the first step
+        let mut env = CountEnv::build(&CountEnvConfig::default().max_steps(1), 0)?;
+
+        for (v, expected) in [(true, 0), (false, 1)].iter() {
+            let config = SimpleStepProcessorConfig::default().bootstrap_on_truncation(*v);
+            let mut step_proc = SimpleStepProcessor::<CountEnv, Batch, Batch>::build(&config);
+            step_proc.reset(env.reset(None)?);
+            let (step, _) = env.step_with_reset(&CountAct(vec![0]));
+            let batch = step_proc.process(step);
+            assert_eq!(batch.is_terminated, vec![*expected]);
+            assert_eq!(batch.is_truncated, vec![1]);
+            assert_eq!(batch.next_obs, Batch(vec![1.0]));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_act_distribution() -> Result<()> {
+        let mut dist = ActDistribution::default();
+        // Negative indices are not valid actions
+        assert_eq!(ActValues::from_indices([0, -1]), None);
+
+        dist.push(&ActValues::from_indices([0, 2]).unwrap());
+        dist.push(&ActValues::Discrete(vec![2, 2]));
+        let record = dist.take_record();
+        assert_eq!(record.get_scalar("act_freq/0")?, 0.25);
+        assert_eq!(record.get_scalar("act_freq/1")?, 0.0);
+        assert_eq!(record.get_scalar("act_freq/2")?, 0.75);
+        let entropy = -(0.25f32 * 0.25f32.ln() + 0.75 * 0.75f32.ln());
+        assert!((record.get_scalar("act_entropy")? - entropy).abs() < 1e-6);
+        assert!(dist.take_record().is_empty());
+
+        dist.push(&ActValues::Continuous(vec![vec![1.0, 0.0], vec![3.0, 0.0]]));
+        let record = dist.take_record();
+        assert_eq!(record.get_scalar("act_mean/0")?, 2.0);
+        assert_eq!(record.get_scalar("act_std/0")?, 1.0);
+        assert_eq!(record.get_scalar("act_std/1")?, 0.0);
+        Ok(())
+    }
 }
@@ -0,0 +1,234 @@
+//! Golden trajectories for testing the dynamics of environments.
+use crate::Env;
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Environment variable which makes [`assert_golden()`] overwrite fixtures if set.
+pub const UPDATE_GOLDEN_VAR: &str = "BORDER_UPDATE_GOLDEN";
+
+/// Rewards and terminations observed when a fixed sequence of actions is applied to an
+/// environment.
+///
+/// Stored as a fixture, it detects changes of environments and their wrappers, like the
+/// preprocessing of rewards and the conditions of terminations, which silently alter the
+/// dynamics seen by agents. The environment is reset automatically at the end of episodes,
+/// so a trajectory can span multiple episodes.
+///
+/// `T` is a serializable representation of actions, converted into actions of the
+/// environment with [`From`], e.g., `u8` for [`BorderAtariAct`] and `i64` for
+/// `CartPoleAct` of the `agent-dev` feature.
+///
+/// [`BorderAtariAct`]: https://docs.rs/border-atari-env/latest/border_atari_env/struct.BorderAtariAct.html
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct GoldenTrajectory<T> {
+    /// Random seed of the environment.
+    pub seed: i64,
+
+    /// Actions applied to the environment.
+    pub actions: Vec<T>,
+
+    /// Rewards of the steps.
+    pub rewards: Vec<f32>,
+
+    /// Termination flags of the steps.
+    pub is_terminated: Vec<i8>,
+
+    /// Truncation flags of the steps.
+    pub is_truncated: Vec<i8>,
+}
+
+impl<T> GoldenTrajectory<T>
+where
+    T: Clone + Serialize + DeserializeOwned,
+{
+    /// Records the trajectory by applying `actions` to the environment built with `seed`.
+    pub fn record<E>(config: &E::Config, seed: i64, actions: Vec<T>) -> Result<Self>
+    where
+        E: Env,
+        E::Act: From<T>,
+    {
+        let mut env = E::build(config, seed)?;
+        env.reset(None)?;
+
+        let (mut rewards, mut is_terminated, mut is_truncated) = (vec![], vec![], vec![]);
+        for a in actions.iter() {
+            let (step, _) = env.step_with_reset(&a.clone().into());
+            rewards.push(step.reward[0]);
+            is_terminated.push(step.is_terminated[0]);
+            is_truncated.push(step.is_truncated[0]);
+        }
+        env.close()?;
+
+        Ok(Self {
+            seed,
+            actions,
+            rewards,
+            is_terminated,
+            is_truncated,
+        })
+    }
+
+    /// Loads a trajectory from a YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("Failed to open {:?}", path.as_ref()))?;
+        Ok(serde_yaml::from_reader(BufReader::new(file))?)
+    }
+
+    /// Saves the trajectory as a YAML file, creating parent directories if needed.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(path.as_ref())?;
+        file.write_all(serde_yaml::to_string(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Replays the actions of the trajectory and checks that the environment reproduces
+    /// the rewards and terminations.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first step where the replayed trajectory differs.
+    /// Rewards are compared with an absolute tolerance of `1e-6`.
+    pub fn check<E>(&self, config: &E::Config) -> Result<()>
+    where
+        E: Env,
+        E::Act: From<T>,
+    {
+        let n = self.actions.len();
+        if self.rewards.len() != n || self.is_terminated.len() != n || self.is_truncated.len() != n
+        {
+            anyhow::bail!("Lengths of actions, rewards and flags differ");
+        }
+
+        let actual = Self::record::<E>(config, self.seed, self.actions.clone())?;
+        for t in 0..self.actions.len() {
+            if (actual.rewards[t] - self.rewards[t]).abs() > 1e-6 {
+                anyhow::bail!(
+                    "Reward at step {} is {}, expected {}",
+                    t,
+                    actual.rewards[t],
+                    self.rewards[t]
+                );
+            }
+            if actual.is_terminated[t] != self.is_terminated[t] {
+                anyhow::bail!(
+                    "Termination flag at step {} is {}, expected {}",
+                    t,
+                    actual.is_terminated[t],
+                    self.is_terminated[t]
+                );
+            }
+            if actual.is_truncated[t] != self.is_truncated[t] {
+                anyhow::bail!(
+                    "Truncation flag at step {} is {}, expected {}",
+                    t,
+                    actual.is_truncated[t],
+                    self.is_truncated[t]
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Asserts that the environment reproduces the golden trajectory stored in `path`.
+///
+/// If the environment variable `BORDER_UPDATE_GOLDEN` is set, the trajectory is recorded
+/// with `seed` and `actions` and saved to `path` instead. The recorded fixture should be
+/// reviewed and committed along with the change of the environment which requires it.
+///
+/// # Panics
+///
+/// Panics if the fixture does not exist, if it was recorded with a seed or actions other
+/// than `seed` and `actions`, or if the replayed trajectory differs from the fixture.
+///
+/// ```ignore
+/// #[test]
+/// fn test_golden_pong() -> anyhow::Result<()> {
+///     let config = env_config("pong");
+///     let actions = (0..500).map(|t| (t % 6) as u8).collect::<Vec<_>>();
+///     assert_golden::<Env, _>("tests/golden/pong.yaml", &config, 42, actions)
+/// }
+/// ```
+pub fn assert_golden<E, T>(
+    path: impl AsRef<Path>,
+    config: &E::Config,
+    seed: i64,
+    actions: Vec<T>,
+) -> Result<()>
+where
+    E: Env,
+    E::Act: From<T>,
+    T: Clone + Serialize + DeserializeOwned + PartialEq,
+{
+    let path = path.as_ref();
+    if std::env::var_os(UPDATE_GOLDEN_VAR).is_some() {
+        log::info!("Record golden trajectory in {:?}", path);
+        return GoldenTrajectory::record::<E>(config, seed, actions)?.save(path);
+    }
+    if !path.exists() {
+        panic!(
+            "Golden trajectory {:?} does not exist. Set {} to record it.",
+            path, UPDATE_GOLDEN_VAR
+        );
+    }
+
+    let golden = GoldenTrajectory::<T>::load(path)?;
+    if golden.seed != seed || golden.actions != actions {
+        panic!(
+            "Golden trajectory {:?} was recorded with another seed or actions. Set {} to update it.",
+            path, UPDATE_GOLDEN_VAR
+        );
+    }
+    if let Err(e) = golden.check::<E>(config) {
+        panic!(
+            "Golden trajectory {:?} is not reproduced: {}. Set {} to update it.",
+            path, e, UPDATE_GOLDEN_VAR
+        );
+    }
+    Ok(())
+}
+
+#[cfg(all(test, feature = "agent-dev"))]
+mod tests {
+    use super::*;
+    use crate::agent_dev::{CartPole, CartPoleConfig};
+    use tempdir::TempDir;
+
+    fn actions() -> Vec<i64> {
+        (0..30).map(|t| ((t / 3) % 2) as i64).collect()
+    }
+
+    #[test]
+    #[should_panic(expected = "does not exist")]
+    fn test_missing_golden() {
+        let dir = TempDir::new("golden").unwrap();
+        let path = dir.path().join("cartpole.yaml");
+        let _ = assert_golden::<CartPole, _>(&path, &CartPoleConfig::default(), 42, actions());
+    }
+
+    #[test]
+    #[should_panic(expected = "another seed or actions")]
+    fn test_golden_with_other_actions() {
+        let dir = TempDir::new("golden").unwrap();
+        let path = dir.path().join("cartpole.yaml");
+        let config = CartPoleConfig::default();
+        GoldenTrajectory::record::<CartPole>(&config, 42, actions())
+            .unwrap()
+            .save(&path)
+            .unwrap();
+        assert_golden::<CartPole, _>(&path, &config, 42, actions()).unwrap();
+
+        let mut other = actions();
+        other[0] = 1;
+        let _ = assert_golden::<CartPole, _>(&path, &config, 42, other);
+    }
+}
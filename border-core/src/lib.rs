@@ -15,6 +15,12 @@
 //! which may be empty (implemented as a zero-sized struct). The `Config` type represents environment configurations
 //! and is used during environment construction.
 //!
+//! [`VecEnv<E: Env>`] runs copies of an environment on worker threads as a single vectorized environment,
+//! where observations and actions implement [`StackObs`] and [`SplitAct`], respectively.
+//! [`NormalizeObs<E: Env>`] normalizes observations with their running mean and variance, and
+//! [`NormalizeReward<E: Env>`] normalizes rewards with the running variance of discounted returns.
+//! [`RateLimit<E: Env>`] steps an environment at a target rate, e.g., real hardware.
+//!
 //! # Policy
 //!
 //! The [`Policy<E: Env>`] trait represents a decision-making policy. The [`Policy::sample()`] method takes an
@@ -32,6 +38,7 @@
 //! obtained from the [`ReplayBufferBase`].
 //!
 //! This trait also provides methods for saving and loading trained policy parameters to and from a directory.
+//! Agents in the backend crates save parameters in the safetensors format, along with a [`ModelManifest`].
 //!
 //! # Batch
 //!
@@ -93,6 +100,33 @@
 //! [`DefaultEvaluator<E, P>`] serves as the default implementation of [`Evaluator<E, P>`]. This evaluator
 //! runs the policy in the environment for a specified number of episodes. At the start of each episode,
 //! the environment is reset using [`Env::reset_with_index()`] to control specific evaluation conditions.
+//! [`MultiEnvEvaluator<E>`] evaluates the policy in multiple named variants of the environment,
+//! reporting the score of each variant under its name. [`RobustnessSweep<E>`] evaluates the policy
+//! under a grid of action and observation noise levels.
+//!
+//! # Multi-Agent Environments
+//!
+//! [`MultiAgentEnv`] is an environment in which multiple agents, identified by their IDs, act
+//! simultaneously. [`MultiAgentTrainer`] trains an agent with its own replay buffer for each ID,
+//! and records the metrics of the agents namespaced by the IDs, like `agent_0/loss`.
+//! [`SelfPlayTrainer`] trains an agent in a two-player environment against a pool of its past
+//! policies, recording the win rate and the Elo rating of the learner.
+//!
+//! # Developing Agents in External Crates
+//!
+//! With the `agent-dev` feature, the `agent_dev` module exports a stable subset of this crate
+//! for implementing agents outside of this workspace, along with a native CartPole environment
+//! and a harness for testing agents on it without Python or deep learning backends.
+//! The module also provides a corridor gridworld, with which the training loop is tested
+//! in its documentation.
+//!
+//! # Tracing
+//!
+//! With the `tracing` feature, the training loop is instrumented with spans of the
+//! [`tracing`](https://docs.rs/tracing) crate: `train`, `sample` (including `policy_sample` and
+//! `env_step`), `opt_step`, `evaluate`, `save_model` and `recorder_flush`. Registering a
+//! subscriber, such as `console-subscriber` or `tracing-chrome` for Perfetto, shows where a
+//! training run spends its time.
 //!
 //! [`SimpleReplayBuffer`]: generic_replay_buffer::SimpleReplayBuffer
 //! [`SimpleReplayBuffer<O, A>`]: generic_replay_buffer::SimpleReplayBuffer
@@ -100,21 +134,74 @@
 //! [`GenericTransitionBatch`]: generic_replay_buffer::GenericTransitionBatch
 //! [`SimpleStepProcessor`]: generic_replay_buffer::SimpleStepProcessor
 //! [`SimpleStepProcessor<E, O, A>`]: generic_replay_buffer::SimpleStepProcessor
+#[macro_use]
+mod trace;
+
+#[cfg(feature = "agent-dev")]
+pub mod agent_dev;
+pub mod codec;
 pub mod dummy;
 pub mod error;
 mod evaluator;
+pub mod explorer;
 pub mod generic_replay_buffer;
 pub mod record;
 
 mod base;
 pub use base::{
-    Act, Agent, Configurable, Env, ExperienceBufferBase, Info, NullReplayBuffer, Obs, Policy,
-    ReplayBufferBase, Step, StepProcessor, TransitionBatch,
+    Act, ActValues, Agent, Configurable, Env, ExperienceBufferBase, Info, NullReplayBuffer, Obs,
+    Policy, ReplayBufferBase, Step, StepProcessor, TransitionBatch,
 };
 
 mod trainer;
-pub use evaluator::{DefaultEvaluator, Evaluator};
-pub use trainer::{Sampler, Trainer, TrainerConfig};
+pub use evaluator::{
+    sample_standard_normal, AddNoise, DefaultEvaluator, EarlyTermination, EvalReport, EvalResult,
+    Evaluator, MultiEnvEvaluator, ProbeSetEvaluator, RewardHackingChecks, RobustnessSweep,
+    SuccessFn,
+};
+pub use trainer::{
+    BestModelConfig, BestModelCriterion, HotReloadConfig, ModelMetadata, Sampler, StoppingRule,
+    Trainer, TrainerConfig, HOT_RELOADABLE,
+};
+
+mod multi_agent;
+pub use multi_agent::{
+    DefaultMultiAgentEvaluator, MultiAgentAct, MultiAgentEnv, MultiAgentEvaluator, MultiAgentObs,
+    MultiAgentStep, MultiAgentTrainer, OpponentSampling, PoolEntry, SelfPlayConfig,
+    SelfPlayTrainer,
+};
+
+mod vec_env;
+pub use vec_env::{SplitAct, StackObs, VecEnv, VecEnvConfig};
+
+mod normalize_obs;
+pub use normalize_obs::{NormalizableObs, NormalizeObs, NormalizeObsConfig, RunningMeanStd};
+
+mod frame_stack;
+pub use frame_stack::{FrameStack, FrameStackEnv, FrameStackEnvConfig, StackFrames};
+
+mod golden;
+pub use golden::{assert_golden, GoldenTrajectory, UPDATE_GOLDEN_VAR};
+
+mod schedule;
+pub use schedule::{LrDecay, LrScheduler, Schedule, SchedulerConfig};
+
+mod probe;
+pub use probe::{probe_env, ProbeConfig, ProbeReport};
+
+mod rate_limit;
+pub use rate_limit::{Clock, RateLimit, RateLimitConfig, SystemClock};
+
+mod manifest;
+pub use manifest::{find_params, params_path, ModelManifest};
+
+mod normalize_reward;
+pub use normalize_reward::{
+    NormalizeReward, NormalizeRewardConfig, ReturnStats, RewardNormalizer, RewardNormalizerConfig,
+};
+
+#[cfg(test)]
+mod test_env;
 
 // TODO: Consider to compile this module only for tests.
 /// Agent and Env for testing.
@@ -134,6 +221,7 @@ pub mod test {
     }
 
     /// Batch of obs for testing.
+    #[derive(Clone)]
     pub struct TestObsBatch {
         obs: Vec<usize>,
     }
@@ -170,6 +258,7 @@ pub mod test {
     impl crate::Act for TestAct {}
 
     /// Batch of act for testing.
+    #[derive(Clone)]
     pub struct TestActBatch {
         act: Vec<usize>,
     }
@@ -296,6 +385,16 @@ pub mod test {
             crate::record::Record::empty()
         }
 
+        fn probe(&mut self, obs: &TestObs) -> crate::record::Record {
+            crate::record::Record::from_slice(&[
+                (
+                    "Q-value",
+                    crate::record::RecordValue::Array1(vec![0.0, obs.obs as f32]),
+                ),
+                ("obs", crate::record::RecordValue::Scalar(obs.obs as f32)),
+            ])
+        }
+
         fn save_params(&self, _path: &std::path::Path) -> anyhow::Result<Vec<std::path::PathBuf>> {
             Ok(vec![])
         }
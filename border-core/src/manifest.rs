@@ -0,0 +1,233 @@
+//! Manifest of model parameters saved by agents.
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+use xxhash_rust::xxh3::xxh3_64;
+
+/// Manifest of model parameters saved in a directory by [`Agent::save_params()`].
+///
+/// Agents in `border-candle-agent` and `border-tch-agent` save parameters in the
+/// [safetensors](https://github.com/huggingface/safetensors) format, along with this
+/// manifest as `manifest.yaml`, so that a policy trained with either backend can be
+/// inspected or loaded by tooling without knowing the backend:
+///
+/// ```yaml
+/// format: safetensors
+/// backend: candle
+/// model_type: Dqn
+/// obs_shape:
+/// - 4
+/// act_dim: 2
+/// config_hash: 5f3c9a0b1e2d4c6f
+/// files:
+/// - qnet.safetensors
+/// - qnet_tgt.safetensors
+/// border_version: 0.0.8
+/// ```
+///
+/// `config_hash` is the hash of the configuration of the policy network, with which
+/// tooling can check if parameters are compatible with a network before loading them.
+///
+/// [`Agent::save_params()`]: crate::Agent::save_params
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ModelManifest {
+    /// Format of the files of parameters.
+    pub format: String,
+
+    /// Backend with which the parameters are saved, e.g., `candle` or `tch`.
+    pub backend: String,
+
+    /// Type of the model, e.g., the name of the agent.
+    pub model_type: String,
+
+    /// Shape of an observation, if known.
+    pub obs_shape: Option<Vec<i64>>,
+
+    /// Dimension of actions, i.e., the output of the policy network.
+    pub act_dim: i64,
+
+    /// Hash of the configuration of the policy network.
+    pub config_hash: String,
+
+    /// Names of the files of parameters in the directory.
+    pub files: Vec<String>,
+
+    /// Version of border with which the parameters are saved.
+    pub border_version: String,
+}
+
+impl ModelManifest {
+    /// Name of the file of the manifest in a model directory.
+    pub const FILE: &'static str = "manifest.yaml";
+
+    /// Format of the files of parameters.
+    pub const FORMAT: &'static str = "safetensors";
+
+    /// Constructs a manifest of a model saved with the given backend.
+    pub fn new(backend: impl Into<String>, model_type: impl Into<String>) -> Self {
+        Self {
+            format: Self::FORMAT.to_string(),
+            backend: backend.into(),
+            model_type: model_type.into(),
+            obs_shape: None,
+            act_dim: 0,
+            config_hash: String::new(),
+            files: vec![],
+            border_version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    /// Sets the type of the model.
+    pub fn model_type(mut self, v: impl Into<String>) -> Self {
+        self.model_type = v.into();
+        self
+    }
+
+    /// Sets the shape of an observation.
+    pub fn obs_shape(mut self, v: Option<Vec<i64>>) -> Self {
+        self.obs_shape = v;
+        self
+    }
+
+    /// Sets the dimension of actions.
+    pub fn act_dim(mut self, v: i64) -> Self {
+        self.act_dim = v;
+        self
+    }
+
+    /// Sets the hash of the configuration of the policy network.
+    pub fn config<T: Serialize>(mut self, config: &T) -> Result<Self> {
+        self.config_hash = Self::config_hash(config)?;
+        Ok(self)
+    }
+
+    /// Sets the files of parameters, taking their names from the paths.
+    pub fn files(mut self, paths: &[PathBuf]) -> Self {
+        self.files = paths
+            .iter()
+            .filter_map(|p| p.file_name())
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        self
+    }
+
+    /// Returns the hash of a configuration, computed from its YAML representation.
+    pub fn config_hash<T: Serialize>(config: &T) -> Result<String> {
+        let yaml = serde_yaml::to_string(config)?;
+        Ok(format!("{:016x}", xxh3_64(yaml.as_bytes())))
+    }
+
+    /// Loads a manifest from a YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let rdr = BufReader::new(File::open(path)?);
+        Ok(serde_yaml::from_reader(rdr)?)
+    }
+
+    /// Saves the manifest as a YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let wtr = BufWriter::new(File::create(path)?);
+        serde_yaml::to_writer(wtr, self)?;
+        Ok(())
+    }
+
+    /// Saves the manifest of the files of parameters `paths` in directory `dir`.
+    ///
+    /// The path of the manifest is appended to `paths`.
+    pub fn save_in(self, dir: &Path, paths: &mut Vec<PathBuf>) -> Result<()> {
+        let path = dir.join(Self::FILE);
+        self.files(paths).save(&path)?;
+        paths.push(path);
+        Ok(())
+    }
+}
+
+fn append_ext(prefix: impl AsRef<Path>, ext: &str) -> PathBuf {
+    let mut path = prefix.as_ref().as_os_str().to_owned();
+    path.push(".");
+    path.push(ext);
+    PathBuf::from(path)
+}
+
+/// Returns the path of a file of parameters, prefix + `.safetensors`.
+pub fn params_path(prefix: impl AsRef<Path>) -> PathBuf {
+    append_ext(prefix, ModelManifest::FORMAT)
+}
+
+/// Returns the path of a file of parameters to be loaded.
+///
+/// If prefix + `.safetensors` does not exist, prefix + `.` + `legacy_ext`, saved by older
+/// versions of an agent crate, is returned if it exists.
+pub fn find_params(prefix: impl AsRef<Path>, legacy_ext: &str) -> PathBuf {
+    let path = params_path(&prefix);
+    let legacy = append_ext(&prefix, legacy_ext);
+    match !path.exists() && legacy.exists() {
+        true => legacy,
+        false => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_model_manifest() -> Result<()> {
+        let dir = TempDir::new("manifest")?;
+        let manifest = ModelManifest::new("candle", "Dqn")
+            .obs_shape(Some(vec![4]))
+            .act_dim(2)
+            .config(&vec![4, 64, 2])?
+            .files(&[dir.path().join("qnet.safetensors")]);
+        assert_eq!(manifest.files, vec!["qnet.safetensors".to_string()]);
+        assert_eq!(manifest.config_hash.len(), 16);
+        assert_eq!(
+            manifest.config_hash,
+            ModelManifest::config_hash(&vec![4, 64, 2])?
+        );
+        assert_ne!(
+            manifest.config_hash,
+            ModelManifest::config_hash(&vec![4, 32, 2])?
+        );
+
+        let path = dir.path().join(ModelManifest::FILE);
+        manifest.save(&path)?;
+        assert_eq!(ModelManifest::load(&path)?, manifest);
+
+        let mut paths = vec![dir.path().join("pi.safetensors")];
+        manifest
+            .clone()
+            .model_type("Sac")
+            .save_in(dir.path(), &mut paths)?;
+        assert_eq!(paths[1], path);
+        let loaded = ModelManifest::load(&path)?;
+        assert_eq!(loaded.model_type, "Sac");
+        assert_eq!(loaded.files, vec!["pi.safetensors".to_string()]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_params() -> Result<()> {
+        let dir = TempDir::new("manifest")?;
+        let prefix = dir.path().join("qnet");
+        assert_eq!(params_path(&prefix), dir.path().join("qnet.safetensors"));
+
+        // Fall back to parameters saved by older versions
+        assert_eq!(
+            find_params(&prefix, "pt"),
+            dir.path().join("qnet.safetensors")
+        );
+        std::fs::write(dir.path().join("qnet.pt"), [])?;
+        assert_eq!(find_params(&prefix, "pt"), dir.path().join("qnet.pt"));
+        std::fs::write(dir.path().join("qnet.safetensors"), [])?;
+        assert_eq!(
+            find_params(&prefix, "pt"),
+            dir.path().join("qnet.safetensors")
+        );
+        Ok(())
+    }
+}
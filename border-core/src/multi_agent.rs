@@ -0,0 +1,28 @@
+//! Independent training of multiple agents in a shared environment.
+//!
+//! This module provides [`MultiAgentEnv`], an environment in which agents identified by
+//! names act simultaneously, and [`MultiAgentTrainer`], which trains an [`Agent`] for each of
+//! the agents independently, as in independent Q-learning (IQL) and independent PPO (IPPO).
+//! Each agent has its own replay buffer and step processor, and treats the other agents
+//! as a part of the environment.
+//!
+//! [`Agent`]: crate::Agent
+mod env;
+mod evaluator;
+mod self_play;
+mod trainer;
+pub use env::{MultiAgentAct, MultiAgentEnv, MultiAgentObs, MultiAgentStep};
+pub use evaluator::{DefaultMultiAgentEvaluator, MultiAgentEvaluator};
+pub use self_play::{OpponentSampling, PoolEntry, SelfPlayConfig, SelfPlayTrainer};
+pub use trainer::MultiAgentTrainer;
+
+use crate::record::Record;
+
+/// Returns the record with the keys prefixed by the ID of an agent, like `agent_0/loss`.
+pub(crate) fn namespaced(id: &str, record: Record) -> Record {
+    let mut namespaced = Record::empty();
+    for (k, v) in record.into_iter_in_record() {
+        namespaced.insert(format!("{}/{}", id, k), v);
+    }
+    namespaced
+}
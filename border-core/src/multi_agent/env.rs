@@ -0,0 +1,84 @@
+use crate::{record::Record, Env, Step};
+use anyhow::Result;
+use std::collections::BTreeMap;
+
+/// Observations of the agents of a [`MultiAgentEnv`], keyed by the IDs of the agents.
+pub type MultiAgentObs<M> = BTreeMap<String, <<M as MultiAgentEnv>::AgentEnv as Env>::Obs>;
+
+/// Actions of the agents of a [`MultiAgentEnv`], keyed by the IDs of the agents.
+pub type MultiAgentAct<M> = BTreeMap<String, <<M as MultiAgentEnv>::AgentEnv as Env>::Act>;
+
+/// Steps of the agents of a [`MultiAgentEnv`], keyed by the IDs of the agents.
+pub type MultiAgentStep<M> = BTreeMap<String, Step<<M as MultiAgentEnv>::AgentEnv>>;
+
+/// Environment in which multiple agents act simultaneously.
+///
+/// Agents are identified by their IDs, e.g., `agent_0`, and share the types of observations,
+/// actions and information, given by [`MultiAgentEnv::AgentEnv`]. Each agent receives its own
+/// [`Step`], so that agents, step processors and replay buffers for single-agent environments
+/// can be used for each agent.
+///
+/// An episode ends for all agents at the same time. If the episode of any agent has ended,
+/// [`MultiAgentEnv::step_with_reset()`] resets the environment, and the steps of the other
+/// agents are marked as truncated.
+pub trait MultiAgentEnv {
+    /// Configuration parameters for the environment.
+    type Config: Clone;
+
+    /// Environment seen by each agent, giving the types of observations, actions and
+    /// information of agents.
+    ///
+    /// It is used only as a type parameter of agents, step processors and recorders,
+    /// and is not built by [`MultiAgentTrainer`](super::MultiAgentTrainer).
+    type AgentEnv: Env;
+
+    /// Builds a new instance of the environment with the given configuration and random seed.
+    fn build(config: &Self::Config, seed: i64) -> Result<Self>
+    where
+        Self: Sized;
+
+    /// Returns the IDs of the agents.
+    fn agent_ids(&self) -> Vec<String>;
+
+    /// Resets the environment and returns the observations of all agents.
+    fn reset(&mut self) -> Result<MultiAgentObs<Self>>
+    where
+        Self: Sized;
+
+    /// Resets the environment with an index, e.g., used as a seed for evaluation.
+    fn reset_with_index(&mut self, ix: usize) -> Result<MultiAgentObs<Self>>
+    where
+        Self: Sized;
+
+    /// Applies the actions of all agents and returns the steps of all agents.
+    fn step(&mut self, acts: &MultiAgentAct<Self>) -> (MultiAgentStep<Self>, Record)
+    where
+        Self: Sized;
+
+    /// Applies the actions and resets the environment if the episode has ended.
+    ///
+    /// If the episode of any agent has ended, the observations after the reset are set to
+    /// `init_obs` of the steps of all agents, and the steps not terminated are truncated.
+    fn step_with_reset(&mut self, acts: &MultiAgentAct<Self>) -> (MultiAgentStep<Self>, Record)
+    where
+        Self: Sized,
+    {
+        let (mut steps, record) = self.step(acts);
+        if steps.values().any(|step| step.is_done()) {
+            let mut init_obs = self.reset().unwrap();
+            for (id, step) in steps.iter_mut() {
+                if !step.is_done() {
+                    step.is_truncated = vec![1; step.is_truncated.len()];
+                }
+                step.init_obs = init_obs.remove(id);
+            }
+        }
+
+        (steps, record)
+    }
+
+    /// Releases resources of the environment. The default implementation does nothing.
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use super::{MultiAgentAct, MultiAgentEnv};
+use crate::{
+    record::{Record, RecordValue},
+    Agent, ReplayBufferBase,
+};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+/// Interface for evaluating agents in a [`MultiAgentEnv`].
+pub trait MultiAgentEvaluator<M: MultiAgentEnv> {
+    /// Evaluates the agents, keyed by the IDs of the agents.
+    ///
+    /// Returns a tuple of the performance metric, with which
+    /// [`MultiAgentTrainer`](super::MultiAgentTrainer) chooses the best models,
+    /// and [`Record`] containing the evaluation results. Metrics of each agent in the record
+    /// should be namespaced by the ID of the agent, like `agent_0/Episode return`.
+    fn evaluate<R>(
+        &mut self,
+        agents: &mut BTreeMap<String, Box<dyn Agent<M::AgentEnv, R>>>,
+    ) -> Result<(f32, Record)>
+    where
+        R: ReplayBufferBase;
+
+    /// Evaluates the agents with an episode for each of the given indices.
+    ///
+    /// Each episode should start with [`MultiAgentEnv::reset_with_index()`] given the index.
+    /// [`MultiAgentTrainer`](super::MultiAgentTrainer) calls this method instead of
+    /// [`MultiAgentEvaluator::evaluate()`] if
+    /// [`TrainerConfig::eval_seeds`](crate::TrainerConfig::eval_seeds) is set.
+    /// The default implementation returns an error.
+    fn evaluate_with_indices<R>(
+        &mut self,
+        _agents: &mut BTreeMap<String, Box<dyn Agent<M::AgentEnv, R>>>,
+        _ixs: &[usize],
+    ) -> Result<(f32, Record)>
+    where
+        R: ReplayBufferBase,
+    {
+        anyhow::bail!("The evaluator does not support evaluation with episode indices")
+    }
+
+    /// Closes the environment used for evaluation with [`MultiAgentEnv::close()`].
+    ///
+    /// The default implementation does nothing.
+    fn close(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Default evaluator for [`MultiAgentEnv`].
+///
+/// It runs `n_episodes` episodes, the `i`-th of which starts with
+/// [`MultiAgentEnv::reset_with_index()`] given `i`. The average return of each agent is recorded
+/// as `<id>/Episode return`, and the performance metric is the average of them over agents.
+pub struct DefaultMultiAgentEvaluator<M: MultiAgentEnv> {
+    n_episodes: usize,
+    env: M,
+}
+
+impl<M: MultiAgentEnv> DefaultMultiAgentEvaluator<M> {
+    /// Constructs the evaluator with the configuration of the environment.
+    pub fn new(config: &M::Config, seed: i64, n_episodes: usize) -> Result<Self> {
+        Ok(Self {
+            n_episodes,
+            env: M::build(config, seed)?,
+        })
+    }
+}
+
+impl<M: MultiAgentEnv> MultiAgentEvaluator<M> for DefaultMultiAgentEvaluator<M> {
+    fn evaluate<R>(
+        &mut self,
+        agents: &mut BTreeMap<String, Box<dyn Agent<M::AgentEnv, R>>>,
+    ) -> Result<(f32, Record)>
+    where
+        R: ReplayBufferBase,
+    {
+        let ixs: Vec<usize> = (0..self.n_episodes).collect();
+        self.evaluate_with_indices(agents, &ixs)
+    }
+
+    fn evaluate_with_indices<R>(
+        &mut self,
+        agents: &mut BTreeMap<String, Box<dyn Agent<M::AgentEnv, R>>>,
+        ixs: &[usize],
+    ) -> Result<(f32, Record)>
+    where
+        R: ReplayBufferBase,
+    {
+        if ixs.is_empty() {
+            anyhow::bail!("No episode indices are given for evaluation");
+        }
+        let ids = self.env.agent_ids();
+        let mut returns: BTreeMap<&str, f32> = ids.iter().map(|id| (id.as_str(), 0.0)).collect();
+
+        for &ix in ixs.iter() {
+            let mut obs = self.env.reset_with_index(ix)?;
+            loop {
+                let mut acts: MultiAgentAct<M> = BTreeMap::new();
+                for id in ids.iter() {
+                    let agent = agents
+                        .get_mut(id)
+                        .with_context(|| format!("No agent for {}", id))?;
+                    let obs = obs
+                        .get(id)
+                        .with_context(|| format!("No observation for {}", id))?;
+                    acts.insert(id.clone(), agent.sample(obs));
+                }
+
+                let (steps, _) = self.env.step(&acts);
+                let is_done = steps.values().any(|step| step.is_done());
+                obs = steps
+                    .into_iter()
+                    .map(|(id, step)| {
+                        if let Some(r) = returns.get_mut(id.as_str()) {
+                            *r += step.reward[0];
+                        }
+                        (id, step.obs)
+                    })
+                    .collect();
+                if is_done {
+                    break;
+                }
+            }
+        }
+
+        let mut record = Record::empty();
+        for (id, r) in returns.iter() {
+            let r = r / ixs.len() as f32;
+            record.insert(format!("{}/Episode return", id), RecordValue::Scalar(r));
+        }
+        let score = returns.values().sum::<f32>() / (ixs.len() * returns.len()) as f32;
+
+        Ok((score, record))
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.env.close()
+    }
+}
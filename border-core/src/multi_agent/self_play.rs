@@ -0,0 +1,612 @@
+use super::{MultiAgentAct, MultiAgentEnv, MultiAgentObs};
+use crate::{
+    record::{Record, RecordValue::Scalar, Recorder},
+    Agent, Configurable, Evaluator, ExperienceBufferBase, ReplayBufferBase, StepProcessor,
+    StoppingRule, Trainer, TrainerConfig,
+};
+use anyhow::{Context, Result};
+use log::{info, warn};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::PathBuf};
+
+/// How opponents are sampled from the pool of [`SelfPlayTrainer`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy)]
+pub enum OpponentSampling {
+    /// Samples opponents uniformly.
+    Uniform,
+
+    /// Samples opponents with probabilities proportional to the expected scores of
+    /// the opponents against the learner by the Elo ratings, i.e., stronger opponents
+    /// are sampled more often.
+    Elo,
+}
+
+/// Configuration of [`SelfPlayTrainer`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct SelfPlayConfig {
+    /// ID of the learner in the environment. If `None`, the first ID is the learner.
+    pub learner_id: Option<String>,
+
+    /// Interval of snapshots of the learner added to the pool, in optimization steps.
+    pub snapshot_interval: usize,
+
+    /// Maximum number of policies in the pool, at least 2.
+    ///
+    /// When exceeded, the oldest policy other than the current opponent is removed.
+    pub pool_size: usize,
+
+    /// How opponents are sampled from the pool.
+    pub sampling: OpponentSampling,
+
+    /// Initial Elo rating of the learner.
+    pub initial_elo: f32,
+
+    /// Factor of updates of Elo ratings.
+    pub elo_k: f32,
+
+    /// Directory where the parameters of the policies in the pool are saved.
+    pub pool_dir: PathBuf,
+
+    /// Random seed for sampling opponents.
+    pub seed: u64,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        Self {
+            learner_id: None,
+            snapshot_interval: 10000,
+            pool_size: 10,
+            sampling: OpponentSampling::Uniform,
+            initial_elo: 1200.0,
+            elo_k: 32.0,
+            pool_dir: PathBuf::from("self_play_pool"),
+            seed: 42,
+        }
+    }
+}
+
+impl SelfPlayConfig {
+    /// Sets the ID of the learner.
+    pub fn learner_id(mut self, v: impl Into<String>) -> Self {
+        self.learner_id = Some(v.into());
+        self
+    }
+
+    /// Sets the interval of snapshots of the learner in optimization steps.
+    pub fn snapshot_interval(mut self, v: usize) -> Self {
+        self.snapshot_interval = v;
+        self
+    }
+
+    /// Sets the maximum number of policies in the pool.
+    pub fn pool_size(mut self, v: usize) -> Self {
+        self.pool_size = v;
+        self
+    }
+
+    /// Sets how opponents are sampled from the pool.
+    pub fn sampling(mut self, v: OpponentSampling) -> Self {
+        self.sampling = v;
+        self
+    }
+
+    /// Sets the initial Elo rating of the learner.
+    pub fn initial_elo(mut self, v: f32) -> Self {
+        self.initial_elo = v;
+        self
+    }
+
+    /// Sets the factor of updates of Elo ratings.
+    pub fn elo_k(mut self, v: f32) -> Self {
+        self.elo_k = v;
+        self
+    }
+
+    /// Sets the directory of the parameters of the policies in the pool.
+    pub fn pool_dir(mut self, v: impl Into<PathBuf>) -> Self {
+        self.pool_dir = v.into();
+        self
+    }
+
+    /// Sets the random seed for sampling opponents.
+    pub fn seed(mut self, v: u64) -> Self {
+        self.seed = v;
+        self
+    }
+}
+
+/// A frozen past policy in the pool of [`SelfPlayTrainer`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolEntry {
+    /// Optimization step at which the learner was snapshotted.
+    pub opt_steps: usize,
+
+    /// Elo rating of the policy.
+    pub elo: f32,
+
+    /// Number of episodes played against the learner.
+    pub n_games: usize,
+
+    /// Number of episodes won by the learner.
+    pub n_learner_wins: usize,
+}
+
+/// Returns the expected score of a player with rating `r` against a player with rating `r_other`.
+fn expected_score(r: f32, r_other: f32) -> f32 {
+    1.0 / (1.0 + 10f32.powf((r_other - r) / 400.0))
+}
+
+/// Trains an agent by playing against its past policies in a two-player [`MultiAgentEnv`].
+///
+/// The trainer maintains a pool of frozen past policies of the learner. At the start of each
+/// episode, an opponent is sampled from the pool as specified by [`OpponentSampling`], and
+/// only the transitions of the learner are pushed into the replay buffer. Every
+/// `snapshot_interval` optimization steps, the parameters of the learner are saved in
+/// `pool_dir` with [`Agent::save_params()`] and loaded into a new agent built with
+/// [`Configurable::build()`], which is added to the pool. The initial learner is the first
+/// policy of the pool.
+///
+/// The winner of an episode is the player with the larger return, and Elo ratings of
+/// the learner and the opponent are updated after every episode.
+///
+/// # Records
+///
+/// In addition to the records of the learner, `self_play/win_rate`, `self_play/draw_rate`,
+/// `self_play/learner_elo` and `self_play/pool_size` are recorded at the end of each episode,
+/// which are averaged over the episodes in a flush interval by the recorder.
+///
+/// # Evaluation
+///
+/// The learner is evaluated by an [`Evaluator`] of the environment seen by the learner, e.g.,
+/// against a fixed opponent, as in [`Trainer`], with which the steps after each optimization
+/// step are shared: hot-reload of hyperparameters, evaluation with `eval_seeds`, the selection
+/// of the best models, early stopping and saving the learner as `aborted` when an optimization
+/// step fails. The configuration is given by [`TrainerConfig`].
+pub struct SelfPlayTrainer {
+    /// Trainer counting steps and handling evaluations of the learner.
+    trainer: Trainer,
+
+    /// Configuration of self-play.
+    config: SelfPlayConfig,
+
+    /// Elo rating of the learner.
+    learner_elo: f32,
+
+    /// Policies in the pool.
+    pool: Vec<PoolEntry>,
+
+    /// Random number generator for sampling opponents.
+    rng: SmallRng,
+}
+
+impl SelfPlayTrainer {
+    /// Creates a new trainer with the specified configurations.
+    pub fn build(config: TrainerConfig, self_play: SelfPlayConfig) -> Self {
+        Self {
+            trainer: Trainer::build(config),
+            learner_elo: self_play.initial_elo,
+            pool: vec![],
+            rng: SmallRng::seed_from_u64(self_play.seed),
+            config: self_play,
+        }
+    }
+
+    /// Sets a custom rule for stopping training, called after each evaluation.
+    ///
+    /// See [`Trainer::stopping_rule()`].
+    pub fn stopping_rule(mut self, f: StoppingRule) -> Self {
+        self.trainer = self.trainer.stopping_rule(f);
+        self
+    }
+
+    /// Returns the number of optimization steps done so far.
+    pub fn opt_steps(&self) -> usize {
+        self.trainer.opt_steps()
+    }
+
+    /// Returns the reason of stopping training before `max_opts`, if stopped.
+    pub fn stop_reason(&self) -> Option<&str> {
+        self.trainer.stop_reason()
+    }
+
+    /// Returns the Elo rating of the learner.
+    pub fn learner_elo(&self) -> f32 {
+        self.learner_elo
+    }
+
+    /// Returns the policies in the pool, from the oldest one.
+    pub fn pool(&self) -> &[PoolEntry] {
+        &self.pool
+    }
+
+    /// Adds a snapshot of the learner to the pool.
+    ///
+    /// If the pool exceeds its size, the oldest policy other than the current opponent `ix`
+    /// is removed, and the index of the current opponent after the removal is returned.
+    fn snapshot<E, R, A>(
+        &mut self,
+        learner: &dyn Agent<E, R>,
+        agent_config: &A::Config,
+        opponents: &mut Vec<A>,
+        ix: usize,
+    ) -> Result<usize>
+    where
+        E: crate::Env,
+        R: ReplayBufferBase,
+        A: Agent<E, R> + Configurable,
+    {
+        let opt_steps = self.trainer.opt_steps();
+        let path = self.config.pool_dir.join(format!("{}", opt_steps));
+        std::fs::create_dir_all(&path)?;
+        learner.save_params(&path)?;
+        let mut opponent = A::build(agent_config.clone());
+        opponent
+            .load_params(&path)
+            .with_context(|| format!("Failed to load a snapshot from {:?}", path))?;
+        opponent.eval();
+        opponents.push(opponent);
+        self.pool.push(PoolEntry {
+            opt_steps,
+            elo: self.learner_elo,
+            n_games: 0,
+            n_learner_wins: 0,
+        });
+        info!("Added the snapshot at {} to the pool", opt_steps);
+
+        if self.pool.len() > self.config.pool_size.max(2) {
+            let removed = (ix == 0) as usize;
+            let entry = self.pool.remove(removed);
+            opponents.remove(removed);
+            let path = self.config.pool_dir.join(format!("{}", entry.opt_steps));
+            if let Err(e) = std::fs::remove_dir_all(&path) {
+                warn!("Failed to remove {:?}: {}", path, e);
+            }
+            if ix > removed {
+                return Ok(ix - 1);
+            }
+        }
+
+        Ok(ix)
+    }
+
+    /// Samples the index of an opponent in the pool.
+    fn sample_opponent(&mut self) -> usize {
+        let weights: Vec<f32> = match self.config.sampling {
+            OpponentSampling::Uniform => vec![1.0; self.pool.len()],
+            OpponentSampling::Elo => self
+                .pool
+                .iter()
+                .map(|entry| expected_score(entry.elo, self.learner_elo))
+                .collect(),
+        };
+        let mut u = self.rng.gen::<f32>() * weights.iter().sum::<f32>();
+        for (ix, w) in weights.iter().enumerate() {
+            if u < *w {
+                return ix;
+            }
+            u -= w;
+        }
+        weights.len() - 1
+    }
+
+    /// Updates the Elo ratings with the result of an episode and returns its record.
+    fn update_elo(&mut self, ix: usize, learner_return: f32, opponent_return: f32) -> Record {
+        let score = match learner_return.partial_cmp(&opponent_return) {
+            Some(std::cmp::Ordering::Greater) => 1.0,
+            Some(std::cmp::Ordering::Less) => 0.0,
+            _ => 0.5,
+        };
+        let entry = &mut self.pool[ix];
+        let delta = self.config.elo_k * (score - expected_score(self.learner_elo, entry.elo));
+        self.learner_elo += delta;
+        entry.elo -= delta;
+        entry.n_games += 1;
+        entry.n_learner_wins += (score == 1.0) as usize;
+
+        Record::from_slice(&[
+            ("self_play/win_rate", Scalar((score == 1.0) as i32 as f32)),
+            ("self_play/draw_rate", Scalar((score == 0.5) as i32 as f32)),
+            ("self_play/learner_elo", Scalar(self.learner_elo)),
+            ("self_play/pool_size", Scalar(self.pool.len() as f32)),
+        ])
+    }
+
+    /// Trains the learner online against the policies in the pool.
+    ///
+    /// The environment must have exactly two agents. `agent_config` is used to build
+    /// the frozen policies of the pool, which must have the same type as the learner.
+    #[allow(clippy::too_many_arguments)]
+    pub fn train<M, A, P, R, D>(
+        &mut self,
+        env: M,
+        agent_config: &A::Config,
+        step_proc_config: &P::Config,
+        learner: &mut Box<dyn Agent<M::AgentEnv, R>>,
+        buffer: &mut R,
+        recorder: &mut Box<dyn Recorder<M::AgentEnv, R>>,
+        evaluator: &mut D,
+    ) -> Result<()>
+    where
+        M: MultiAgentEnv,
+        A: Agent<M::AgentEnv, R> + Configurable,
+        P: StepProcessor<M::AgentEnv>,
+        R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+        D: Evaluator<M::AgentEnv>,
+    {
+        let mut env = env;
+        let ids = env.agent_ids();
+        if ids.len() != 2 {
+            anyhow::bail!("Self-play requires 2 agents, got {:?}", ids);
+        }
+        let learner_id = self.config.learner_id.clone().unwrap_or(ids[0].clone());
+        let opponent_id = match ids.iter().find(|id| **id != learner_id) {
+            Some(id) if ids.contains(&learner_id) => id.clone(),
+            _ => anyhow::bail!("{} is not an agent of the environment", learner_id),
+        };
+
+        let mut step_proc = P::build(step_proc_config);
+        let mut opponents: Vec<A> = vec![];
+        self.snapshot(learner.as_ref(), agent_config, &mut opponents, 0)?;
+        let mut ix = self.sample_opponent();
+        let mut returns = (0f32, 0f32);
+        let mut prev_obs: Option<MultiAgentObs<M>> = None;
+        learner.train();
+
+        loop {
+            // Reset the environment at the start of training
+            if prev_obs.is_none() {
+                let obs = env.reset()?;
+                step_proc.reset(obs[&learner_id].clone());
+                prev_obs = Some(obs);
+            }
+
+            // Sample actions of the learner and the opponent
+            let acts: MultiAgentAct<M> = {
+                let obs = prev_obs.as_ref().unwrap();
+                let mut acts = BTreeMap::new();
+                acts.insert(learner_id.clone(), learner.sample(&obs[&learner_id]));
+                acts.insert(
+                    opponent_id.clone(),
+                    opponents[ix].sample(&obs[&opponent_id]),
+                );
+                acts
+            };
+            let (mut steps, mut record) = env.step_with_reset(&acts);
+            let is_done = steps.values().any(|step| step.is_done());
+            self.trainer.env_step();
+
+            // Push the transition of the learner to the replay buffer
+            let opponent_step = steps.remove(&opponent_id).unwrap();
+            let step = steps.remove(&learner_id).unwrap();
+            returns.0 += step.reward[0];
+            returns.1 += opponent_step.reward[0];
+            let next_obs = match is_done {
+                true => (step.init_obs.clone(), opponent_step.init_obs),
+                false => (Some(step.obs.clone()), Some(opponent_step.obs)),
+            };
+            buffer.push(step_proc.process(step))?;
+            let mut obs = BTreeMap::new();
+            obs.insert(
+                learner_id.clone(),
+                next_obs.0.expect("Failed to unwrap init_obs"),
+            );
+            obs.insert(
+                opponent_id.clone(),
+                next_obs.1.expect("Failed to unwrap init_obs"),
+            );
+
+            // Update Elo ratings and sample the next opponent at the end of the episode
+            if is_done {
+                record.merge_inplace(self.update_elo(ix, returns.0, returns.1));
+                returns = (0.0, 0.0);
+                step_proc.reset(obs[&learner_id].clone());
+                learner.on_episode_end();
+                ix = self.sample_opponent();
+            }
+            prev_obs = Some(obs);
+
+            // Optimize the learner
+            let (r, is_opt) = self.trainer.train_step(learner, buffer).map_err(|err| {
+                Trainer::close_after_failure([env.close(), evaluator.close()]);
+                self.trainer.abort(learner, recorder, err)
+            })?;
+            record.merge_inplace(r);
+            if is_opt {
+                if self.trainer.opt_steps() % self.config.snapshot_interval == 0 {
+                    ix = self.snapshot(learner.as_ref(), agent_config, &mut opponents, ix)?;
+                }
+                self.trainer
+                    .post_process(learner, evaluator, recorder, &mut record)?;
+            }
+
+            if !record.is_empty() {
+                recorder.store(record);
+            }
+
+            // Flush records, including the statistics of steps of the learner
+            if is_opt && self.trainer.is_flush_step() {
+                let record = step_proc.record();
+                if !record.is_empty() {
+                    recorder.store(record);
+                }
+                recorder.flush(self.trainer.opt_steps() as _);
+            }
+
+            // Finish training
+            if self.trainer.is_finished() {
+                break;
+            }
+        }
+
+        env.close()?;
+        evaluator.close()
+    }
+}
+
+#[cfg(all(test, feature = "agent-dev"))]
+mod tests {
+    use super::*;
+    use crate::{
+        agent_dev::{
+            Corridor, CorridorAct, CorridorBuffer, CorridorConfig, CorridorObs,
+            CorridorStepProcessor,
+        },
+        generic_replay_buffer::{SimpleReplayBufferConfig, SimpleStepProcessorConfig},
+        multi_agent::MultiAgentStep,
+        record::NullRecorder,
+        DefaultEvaluator, Env, Policy,
+    };
+    use std::path::Path;
+    use tempdir::TempDir;
+
+    /// Race of two agents to the ends of their corridors.
+    struct Race(BTreeMap<String, Corridor>);
+
+    impl MultiAgentEnv for Race {
+        type Config = ();
+        type AgentEnv = Corridor;
+
+        fn build(_config: &Self::Config, seed: i64) -> Result<Self> {
+            let config = CorridorConfig::default().length(3);
+            let mut corridors = BTreeMap::new();
+            for id in ["black", "white"].iter() {
+                corridors.insert(id.to_string(), Corridor::build(&config, seed)?);
+            }
+            Ok(Self(corridors))
+        }
+
+        fn agent_ids(&self) -> Vec<String> {
+            self.0.keys().cloned().collect()
+        }
+
+        fn reset(&mut self) -> Result<MultiAgentObs<Self>> {
+            self.0
+                .iter_mut()
+                .map(|(id, env)| Ok((id.clone(), env.reset(None)?)))
+                .collect()
+        }
+
+        fn reset_with_index(&mut self, _ix: usize) -> Result<MultiAgentObs<Self>> {
+            self.reset()
+        }
+
+        fn step(&mut self, acts: &MultiAgentAct<Self>) -> (MultiAgentStep<Self>, Record) {
+            let steps = self
+                .0
+                .iter_mut()
+                .map(|(id, env)| (id.clone(), env.step(&acts[id]).0))
+                .collect();
+            (steps, Record::empty())
+        }
+    }
+
+    /// Agent walking to the right after the first optimization step.
+    struct LateAgent(usize);
+
+    impl Policy<Corridor> for LateAgent {
+        fn sample(&mut self, _obs: &CorridorObs) -> CorridorAct {
+            CorridorAct((self.0 > 0) as usize)
+        }
+    }
+
+    impl Configurable for LateAgent {
+        type Config = ();
+
+        fn build(_config: ()) -> Self {
+            Self(0)
+        }
+    }
+
+    impl Agent<Corridor, CorridorBuffer> for LateAgent {
+        fn train(&mut self) {}
+
+        fn eval(&mut self) {}
+
+        fn opt_with_record(&mut self, _buffer: &mut CorridorBuffer) -> Record {
+            self.0 += 1;
+            Record::empty()
+        }
+
+        fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+            let path = path.join("n_opts");
+            std::fs::write(&path, self.0.to_string())?;
+            Ok(vec![path])
+        }
+
+        fn load_params(&mut self, path: &Path) -> Result<()> {
+            self.0 = std::fs::read_to_string(path.join("n_opts"))?.parse()?;
+            Ok(())
+        }
+
+        fn as_any_ref(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+
+    /// Trains [`LateAgent`] against its snapshots saved in `dir`, evaluated in a corridor.
+    fn train(config: TrainerConfig, dir: &Path) -> Result<(SelfPlayTrainer, CorridorBuffer)> {
+        let mut learner: Box<dyn Agent<Corridor, CorridorBuffer>> = Box::new(LateAgent(0));
+        let mut buffer = CorridorBuffer::build(&SimpleReplayBufferConfig::default());
+        let mut recorder: Box<dyn Recorder<Corridor, CorridorBuffer>> =
+            Box::new(NullRecorder::new());
+        let mut evaluator = DefaultEvaluator::<Corridor>::new(&CorridorConfig::default(), 0, 1)?;
+        let mut trainer = SelfPlayTrainer::build(
+            config,
+            SelfPlayConfig::default()
+                .snapshot_interval(2)
+                .pool_size(2)
+                .pool_dir(dir),
+        );
+        trainer.train::<_, LateAgent, CorridorStepProcessor, _, _>(
+            Race::build(&(), 0)?,
+            &(),
+            &SimpleStepProcessorConfig::default(),
+            &mut learner,
+            &mut buffer,
+            &mut recorder,
+            &mut evaluator,
+        )?;
+        Ok((trainer, buffer))
+    }
+
+    #[test]
+    fn test_self_play_trainer() -> Result<()> {
+        let dir = TempDir::new("self_play")?;
+        let config = TrainerConfig::default()
+            .max_opts(4)
+            .warmup_period(1)
+            .eval_interval(2);
+        let (trainer, buffer) = train(config, dir.path())?;
+        assert_eq!(buffer.len(), 4);
+
+        // The learner beat the initial policy, which never moves to the right
+        assert_eq!(trainer.learner_elo(), 1216.0);
+
+        // Snapshots at 0, 2 and 4, of which one is removed
+        assert_eq!(trainer.pool().len(), 2);
+        assert_eq!(trainer.pool()[1].opt_steps, 4);
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_self_play_early_stopping() -> Result<()> {
+        // The learner reaches the goal in evaluation after the first optimization step
+        let dir = TempDir::new("self_play")?;
+        let config = TrainerConfig::default()
+            .max_opts(4)
+            .warmup_period(1)
+            .eval_interval(1)
+            .eval_threshold(0.5);
+        let (trainer, _) = train(config, dir.path())?;
+        assert_eq!(trainer.opt_steps(), 1);
+        assert!(trainer.stop_reason().is_some());
+        Ok(())
+    }
+}
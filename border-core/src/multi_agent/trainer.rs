@@ -0,0 +1,448 @@
+use super::{namespaced, MultiAgentAct, MultiAgentEnv, MultiAgentEvaluator, MultiAgentObs};
+use crate::{
+    record::{Record, Recorder},
+    Agent, EvalResult, ExperienceBufferBase, ReplayBufferBase, StepProcessor, StoppingRule,
+    Trainer, TrainerConfig,
+};
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+
+/// Trains agents in a [`MultiAgentEnv`] independently.
+///
+/// Each agent has its own replay buffer and step processor, keyed by the ID of the agent.
+/// At every environment step, the trainer samples actions of all agents, and the transition of
+/// each agent is pushed into the replay buffer of the agent. At every optimization step, all agents
+/// are optimized with their own replay buffers.
+///
+/// # Records
+///
+/// Records of agents and step processors are namespaced by the IDs of the agents,
+/// like `agent_0/loss`, so that the metrics of agents are not mixed in a single recorder.
+/// Records of the environment and the evaluator are stored as they are.
+///
+/// # Configuration
+///
+/// The configuration is given by [`TrainerConfig`], and the steps after each optimization step
+/// are shared with [`Trainer`]: hot-reload of hyperparameters, which are set to all agents,
+/// evaluation with `eval_seeds` through [`MultiAgentEvaluator::evaluate_with_indices()`],
+/// the selection of the best models, early stopping and saving the models as `aborted` when
+/// an optimization step fails. The models of agents are saved under the IDs of the agents,
+/// like `<id>/best` and `<id>/<opt_steps>`.
+pub struct MultiAgentTrainer {
+    /// Trainer counting steps and handling evaluations, shared with single agents.
+    trainer: Trainer,
+}
+
+impl MultiAgentTrainer {
+    /// Creates a new trainer with the specified configuration.
+    pub fn build(config: TrainerConfig) -> Self {
+        Self {
+            trainer: Trainer::build(config),
+        }
+    }
+
+    /// Sets a custom rule for stopping training, called after each evaluation.
+    ///
+    /// See [`Trainer::stopping_rule()`].
+    pub fn stopping_rule(mut self, f: StoppingRule) -> Self {
+        self.trainer = self.trainer.stopping_rule(f);
+        self
+    }
+
+    /// Returns the number of optimization steps done so far.
+    pub fn opt_steps(&self) -> usize {
+        self.trainer.opt_steps()
+    }
+
+    /// Returns the reason of stopping training before `max_opts`, if stopped.
+    pub fn stop_reason(&self) -> Option<&str> {
+        self.trainer.stop_reason()
+    }
+
+    /// Optimizes all agents with their own replay buffers.
+    fn opt_step<M, R>(
+        agents: &mut BTreeMap<String, Box<dyn Agent<M::AgentEnv, R>>>,
+        buffers: &mut BTreeMap<String, R>,
+        with_record: bool,
+    ) -> Result<Record>
+    where
+        M: MultiAgentEnv,
+        R: ReplayBufferBase,
+    {
+        let mut record = Record::empty();
+        for (id, agent) in agents.iter_mut() {
+            let buffer = buffers
+                .get_mut(id)
+                .with_context(|| format!("No replay buffer for {}", id))?;
+            match with_record {
+                true => record.merge_inplace(namespaced(id, agent.try_opt_with_record(buffer)?)),
+                false => agent.try_opt(buffer)?,
+            }
+        }
+        Ok(record)
+    }
+
+    /// Trains the agents online.
+    ///
+    /// `agents` and `buffers` must have entries for all IDs returned by
+    /// [`MultiAgentEnv::agent_ids()`]. A step processor is built for each agent with
+    /// `step_proc_config`.
+    pub fn train<M, P, R, D>(
+        &mut self,
+        env: M,
+        step_proc_config: &P::Config,
+        agents: &mut BTreeMap<String, Box<dyn Agent<M::AgentEnv, R>>>,
+        buffers: &mut BTreeMap<String, R>,
+        recorder: &mut Box<dyn Recorder<M::AgentEnv, R>>,
+        evaluator: &mut D,
+    ) -> Result<()>
+    where
+        M: MultiAgentEnv,
+        P: StepProcessor<M::AgentEnv>,
+        R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
+        D: MultiAgentEvaluator<M>,
+    {
+        let mut env = env;
+        let ids = env.agent_ids();
+        for id in ids.iter() {
+            if !agents.contains_key(id) || !buffers.contains_key(id) {
+                anyhow::bail!("No agent or replay buffer for {}", id);
+            }
+        }
+        let mut step_procs: BTreeMap<String, P> = ids
+            .iter()
+            .map(|id| (id.clone(), P::build(step_proc_config)))
+            .collect();
+        let mut prev_obs: Option<MultiAgentObs<M>> = None;
+        agents.values_mut().for_each(|agent| agent.train());
+
+        loop {
+            // Reset the environment at the start of training
+            if prev_obs.is_none() {
+                let obs = env.reset()?;
+                for (id, step_proc) in step_procs.iter_mut() {
+                    step_proc.reset(obs[id].clone());
+                }
+                prev_obs = Some(obs);
+            }
+
+            // Sample actions of all agents and apply them to the environment
+            let acts: MultiAgentAct<M> = {
+                let obs = prev_obs.as_ref().unwrap();
+                ids.iter()
+                    .map(|id| (id.clone(), agents.get_mut(id).unwrap().sample(&obs[id])))
+                    .collect()
+            };
+            let (steps, mut record) = env.step_with_reset(&acts);
+            let is_done = steps.values().any(|step| step.is_done());
+            self.trainer.env_step();
+
+            // Push the transition of each agent to its replay buffer
+            let mut obs = BTreeMap::new();
+            for (id, step) in steps.into_iter() {
+                let next_obs = match is_done {
+                    true => step.init_obs.clone().expect("Failed to unwrap init_obs"),
+                    false => step.obs.clone(),
+                };
+                let step_proc = step_procs.get_mut(&id).unwrap();
+                buffers
+                    .get_mut(&id)
+                    .unwrap()
+                    .push(step_proc.process(step))?;
+                if is_done {
+                    step_proc.reset(next_obs.clone());
+                    agents.get_mut(&id).unwrap().on_episode_end();
+                }
+                obs.insert(id, next_obs);
+            }
+            prev_obs = Some(obs);
+
+            // Optimize all agents
+            let opt = |with_record| Self::opt_step::<M, R>(agents, buffers, with_record);
+            let is_opt = match self.trainer.opt_step(opt) {
+                Ok((r, is_opt)) => {
+                    record.merge_inplace(r);
+                    is_opt
+                }
+                Err(err) => {
+                    Trainer::close_after_failure([env.close(), evaluator.close()]);
+                    return Err(self.trainer.abort(agents, recorder, err));
+                }
+            };
+            if is_opt {
+                self.trainer.post_process_with(
+                    agents,
+                    recorder,
+                    &mut record,
+                    |agents, seeds| {
+                        let result = match seeds {
+                            Some(seeds) => evaluator.evaluate_with_indices(agents, seeds)?,
+                            None => evaluator.evaluate(agents)?,
+                        };
+                        Ok(EvalResult::from(result))
+                    },
+                )?;
+            }
+
+            if !record.is_empty() {
+                recorder.store(record);
+            }
+
+            // Flush records, including the statistics of steps of each agent
+            if is_opt && self.trainer.is_flush_step() {
+                for (id, step_proc) in step_procs.iter_mut() {
+                    let record = step_proc.record();
+                    if !record.is_empty() {
+                        recorder.store(namespaced(id, record));
+                    }
+                }
+                recorder.flush(self.trainer.opt_steps() as _);
+            }
+
+            // Finish training
+            if self.trainer.is_finished() {
+                env.close()?;
+                evaluator.close()?;
+                return Ok(());
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "agent-dev"))]
+mod tests {
+    use super::*;
+    use crate::{
+        agent_dev::{
+            Corridor, CorridorAct, CorridorBuffer, CorridorConfig, CorridorObs,
+            CorridorStepProcessor,
+        },
+        generic_replay_buffer::{SimpleReplayBufferConfig, SimpleStepProcessorConfig},
+        multi_agent::{DefaultMultiAgentEvaluator, MultiAgentStep},
+        Env, Policy, ReplayBufferBase,
+    };
+    use std::{
+        cell::RefCell,
+        path::{Path, PathBuf},
+        rc::Rc,
+    };
+
+    /// Two agents in their own corridors of different lengths.
+    struct TwoCorridors(BTreeMap<String, Corridor>);
+
+    impl MultiAgentEnv for TwoCorridors {
+        type Config = ();
+        type AgentEnv = Corridor;
+
+        fn build(_config: &Self::Config, seed: i64) -> Result<Self> {
+            let corridors = [("agent_0", 3), ("agent_1", 4)]
+                .iter()
+                .map(|&(id, length)| {
+                    let config = CorridorConfig::default().length(length);
+                    Ok((id.to_string(), Corridor::build(&config, seed)?))
+                })
+                .collect::<Result<_>>()?;
+            Ok(Self(corridors))
+        }
+
+        fn agent_ids(&self) -> Vec<String> {
+            self.0.keys().cloned().collect()
+        }
+
+        fn reset(&mut self) -> Result<MultiAgentObs<Self>> {
+            self.0
+                .iter_mut()
+                .map(|(id, env)| Ok((id.clone(), env.reset(None)?)))
+                .collect()
+        }
+
+        fn reset_with_index(&mut self, _ix: usize) -> Result<MultiAgentObs<Self>> {
+            self.reset()
+        }
+
+        fn step(&mut self, acts: &MultiAgentAct<Self>) -> (MultiAgentStep<Self>, Record) {
+            let steps = self
+                .0
+                .iter_mut()
+                .map(|(id, env)| (id.clone(), env.step(&acts[id]).0))
+                .collect();
+            (steps, Record::empty())
+        }
+    }
+
+    /// Agent walking to the right, counting optimization steps.
+    ///
+    /// Its optimization step fails after the given number of steps, if any.
+    struct RightAgent(usize, Option<usize>);
+
+    impl Policy<Corridor> for RightAgent {
+        fn sample(&mut self, _obs: &CorridorObs) -> CorridorAct {
+            CorridorAct(1)
+        }
+    }
+
+    impl Agent<Corridor, CorridorBuffer> for RightAgent {
+        fn train(&mut self) {}
+
+        fn eval(&mut self) {}
+
+        fn opt_with_record(&mut self, _buffer: &mut CorridorBuffer) -> Record {
+            self.0 += 1;
+            Record::from_scalar("n_opts", self.0 as f32)
+        }
+
+        fn try_opt_with_record(&mut self, buffer: &mut CorridorBuffer) -> Result<Record> {
+            if Some(self.0) == self.1 {
+                anyhow::bail!("Out of memory");
+            }
+            Ok(self.opt_with_record(buffer))
+        }
+
+        fn save_params(&self, _path: &Path) -> Result<Vec<PathBuf>> {
+            Ok(vec![])
+        }
+
+        fn load_params(&mut self, _path: &Path) -> Result<()> {
+            Ok(())
+        }
+
+        fn as_any_ref(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+            self
+        }
+    }
+
+    /// Recorder keeping stored records and the directories of saved models.
+    struct StoreRecorder(Rc<RefCell<Vec<Record>>>, Rc<RefCell<Vec<PathBuf>>>);
+
+    impl Recorder<Corridor, CorridorBuffer> for StoreRecorder {
+        fn write(&mut self, record: Record) {
+            self.0.borrow_mut().push(record);
+        }
+
+        fn store(&mut self, record: Record) {
+            self.0.borrow_mut().push(record);
+        }
+
+        fn flush(&mut self, _step: i64) {}
+
+        fn save_model(
+            &self,
+            base: &Path,
+            _agent: &Box<dyn Agent<Corridor, CorridorBuffer>>,
+        ) -> Result<()> {
+            self.1.borrow_mut().push(base.to_path_buf());
+            Ok(())
+        }
+    }
+
+    type Buffers = BTreeMap<String, CorridorBuffer>;
+
+    type TrainResult = (Result<()>, Buffers, Vec<Record>, Vec<PathBuf>);
+
+    /// Trains agents failing after `fail_at` optimization steps, if given, returning
+    /// the result of training, the replay buffers, the records and the directories of
+    /// saved models.
+    fn train(trainer: &mut MultiAgentTrainer, fail_at: Option<usize>) -> Result<TrainResult> {
+        let env = TwoCorridors::build(&(), 0)?;
+        let ids = env.agent_ids();
+        let mut agents: BTreeMap<String, Box<dyn Agent<Corridor, CorridorBuffer>>> = ids
+            .iter()
+            .map(|id| (id.clone(), Box::new(RightAgent(0, fail_at)) as _))
+            .collect();
+        let buffer_config = SimpleReplayBufferConfig::default().capacity(100);
+        let mut buffers: Buffers = ids
+            .iter()
+            .map(|id| (id.clone(), CorridorBuffer::build(&buffer_config)))
+            .collect();
+        let (records, saved) = (Rc::new(RefCell::new(vec![])), Rc::new(RefCell::new(vec![])));
+        let mut recorder: Box<dyn Recorder<Corridor, CorridorBuffer>> =
+            Box::new(StoreRecorder(records.clone(), saved.clone()));
+        let mut evaluator = DefaultMultiAgentEvaluator::<TwoCorridors>::new(&(), 0, 2)?;
+        let result = trainer.train::<_, CorridorStepProcessor, _, _>(
+            env,
+            &SimpleStepProcessorConfig::default(),
+            &mut agents,
+            &mut buffers,
+            &mut recorder,
+            &mut evaluator,
+        );
+        let records = records.borrow().clone();
+        let saved = saved.borrow().clone();
+        Ok((result, buffers, records, saved))
+    }
+
+    #[test]
+    fn test_multi_agent_trainer() -> Result<()> {
+        let mut trainer = MultiAgentTrainer::build(
+            TrainerConfig::default()
+                .max_opts(5)
+                .warmup_period(2)
+                .eval_interval(5)
+                .record_agent_info_interval(1),
+        );
+        let (result, buffers, records, saved) = train(&mut trainer, None)?;
+        result?;
+        assert_eq!(trainer.opt_steps(), 5);
+
+        // Each agent has its own transitions
+        assert_eq!(buffers["agent_0"].len(), 6);
+        assert_eq!(buffers["agent_1"].len(), 6);
+
+        // Records of agents are namespaced by the IDs
+        let last = |k: &str| records.iter().rev().find_map(|r| r.get_scalar(k).ok());
+        assert_eq!(last("agent_0/n_opts"), Some(5.0));
+        assert_eq!(last("agent_1/n_opts"), Some(5.0));
+        assert!(last("n_opts").is_none());
+
+        // The episode ends when the agent in the shorter corridor reaches the goal
+        assert_eq!(last("agent_0/Episode return"), Some(1.0));
+        assert_eq!(last("agent_1/Episode return"), Some(0.0));
+
+        // The best models are saved under the IDs
+        assert!(saved.contains(&PathBuf::from("agent_0/best")));
+        assert!(saved.contains(&PathBuf::from("agent_1/best")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_agent_early_stopping() -> Result<()> {
+        // The average return of the agents is 0.5 in every evaluation
+        let mut trainer = MultiAgentTrainer::build(
+            TrainerConfig::default()
+                .max_opts(10)
+                .warmup_period(2)
+                .eval_interval(1)
+                .eval_threshold(0.4)
+                .eval_threshold_patience(2)
+                .eval_seeds(Some(vec![0, 1, 2])),
+        );
+        let (result, _, records, _) = train(&mut trainer, None)?;
+        result?;
+        assert_eq!(trainer.opt_steps(), 2);
+        assert!(trainer.stop_reason().is_some());
+        let last = |k: &str| records.iter().rev().find_map(|r| r.get_scalar(k).ok());
+        assert_eq!(last("agent_0/Episode return"), Some(1.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_agent_abort() -> Result<()> {
+        let mut trainer = MultiAgentTrainer::build(
+            TrainerConfig::default()
+                .max_opts(10)
+                .warmup_period(2)
+                .record_agent_info_interval(1),
+        );
+        let (result, _, _, saved) = train(&mut trainer, Some(3))?;
+        let err = result.unwrap_err();
+        assert!(format!("{:#}", err).contains("optimization step 4"));
+        assert_eq!(trainer.opt_steps(), 3);
+        assert!(saved.contains(&PathBuf::from("agent_0/aborted")));
+        assert!(saved.contains(&PathBuf::from("agent_1/aborted")));
+        Ok(())
+    }
+}
@@ -0,0 +1,381 @@
+//! Normalization of observations with running statistics.
+use crate::{record::Record, Env, Obs, Step};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+/// Name of the file of the statistics saved in a model directory.
+const STATS_FILE: &str = "obs_stats.yaml";
+
+/// Observations whose elements can be normalized.
+///
+/// This trait is required for the observation of environments wrapped with [`NormalizeObs`].
+pub trait NormalizableObs: Obs {
+    /// Returns the elements of the observation.
+    ///
+    /// The elements are those of [`Obs::len()`] observations of the same dimension,
+    /// concatenated in the order of the first axis.
+    fn values(&self) -> Vec<f32>;
+
+    /// Returns an observation of the same shape with the given elements.
+    fn with_values(&self, values: Vec<f32>) -> Self;
+}
+
+/// Running mean and variance of vectors.
+///
+/// The statistics are updated with batches of vectors using the parallel variant of
+/// Welford's algorithm. The dimension is set at the first update.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct RunningMeanStd {
+    /// Mean of each element.
+    pub mean: Vec<f64>,
+
+    /// Variance of each element.
+    pub var: Vec<f64>,
+
+    /// The number of vectors.
+    pub count: f64,
+}
+
+impl RunningMeanStd {
+    /// Updates the statistics with `values`, the elements of vectors concatenated.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `values` is not a multiple of `n`, or the dimension differs from
+    /// that of previous updates.
+    pub fn update(&mut self, values: &[f32], n: usize) {
+        if n == 0 {
+            return;
+        }
+        assert_eq!(values.len() % n, 0);
+        let dim = values.len() / n;
+        if self.count == 0.0 {
+            self.mean = vec![0.0; dim];
+            self.var = vec![1.0; dim];
+        }
+        assert_eq!(self.mean.len(), dim, "Dimension of values changed");
+
+        let b_count = n as f64;
+        let tot = self.count + b_count;
+        for j in 0..dim {
+            let xs = values.iter().skip(j).step_by(dim).map(|v| *v as f64);
+            let b_mean = xs.clone().sum::<f64>() / b_count;
+            let b_var = xs.map(|x| (x - b_mean).powi(2)).sum::<f64>() / b_count;
+
+            let delta = b_mean - self.mean[j];
+            let m2 = self.var[j] * self.count
+                + b_var * b_count
+                + delta.powi(2) * self.count * b_count / tot;
+            self.mean[j] += delta * b_count / tot;
+            self.var[j] = m2 / tot;
+        }
+        self.count = tot;
+    }
+
+    /// Normalizes `values`, the elements of vectors concatenated.
+    ///
+    /// Values are returned as they are if the statistics have not been updated.
+    pub fn normalize(&self, values: &[f32], epsilon: f64, clip: Option<f32>) -> Vec<f32> {
+        if self.count == 0.0 {
+            return values.to_vec();
+        }
+        let dim = self.mean.len();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                let j = i % dim;
+                let v = ((*v as f64 - self.mean[j]) / (self.var[j] + epsilon).sqrt()) as f32;
+                match clip {
+                    Some(c) => v.clamp(-c, c),
+                    None => v,
+                }
+            })
+            .collect()
+    }
+
+    /// Loads statistics from a YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let rdr = BufReader::new(File::open(path)?);
+        Ok(serde_yaml::from_reader(rdr)?)
+    }
+
+    /// Saves statistics as a YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let wtr = BufWriter::new(File::create(path)?);
+        serde_yaml::to_writer(wtr, self)?;
+        Ok(())
+    }
+}
+
+/// Configuration of [`NormalizeObs`].
+///
+/// The statistics are shared by the environments built with clones of the configuration.
+/// Thus, an environment for evaluation built with [`NormalizeObsConfig::update()`] set to `false`
+/// normalizes observations with the statistics updated by the environment for training.
+pub struct NormalizeObsConfig<E: Env> {
+    /// Configuration of the wrapped environment.
+    pub env_config: E::Config,
+
+    /// If `true`, the statistics are updated with observations.
+    pub update: bool,
+
+    /// Normalized values are clipped to `[-clip, clip]` if given.
+    pub clip: Option<f32>,
+
+    /// A small value added to the variance for numerical stability.
+    pub epsilon: f64,
+
+    /// Statistics shared by environments.
+    stats: Arc<RwLock<RunningMeanStd>>,
+}
+
+impl<E: Env> Clone for NormalizeObsConfig<E> {
+    fn clone(&self) -> Self {
+        Self {
+            env_config: self.env_config.clone(),
+            update: self.update,
+            clip: self.clip,
+            epsilon: self.epsilon,
+            stats: self.stats.clone(),
+        }
+    }
+}
+
+impl<E: Env> NormalizeObsConfig<E> {
+    /// Constructs a configuration, where the statistics are updated and values are clipped to `[-10, 10]`.
+    pub fn new(env_config: E::Config) -> Self {
+        Self {
+            env_config,
+            update: true,
+            clip: Some(10.0),
+            epsilon: 1e-8,
+            stats: Arc::new(RwLock::new(RunningMeanStd::default())),
+        }
+    }
+
+    /// Sets `false` to normalize observations without updating the statistics, e.g., in evaluation.
+    pub fn update(mut self, v: bool) -> Self {
+        self.update = v;
+        self
+    }
+
+    /// Sets the range of clipping normalized values, `None` for no clipping.
+    pub fn clip(mut self, v: Option<f32>) -> Self {
+        self.clip = v;
+        self
+    }
+
+    /// Sets the value added to the variance.
+    pub fn epsilon(mut self, v: f64) -> Self {
+        self.epsilon = v;
+        self
+    }
+
+    /// Returns the current statistics.
+    pub fn stats(&self) -> RunningMeanStd {
+        self.stats.read().unwrap().clone()
+    }
+
+    /// Saves the statistics in `obs_stats.yaml` in a model directory.
+    pub fn save_stats(&self, model_dir: impl AsRef<Path>) -> Result<()> {
+        self.stats().save(model_dir.as_ref().join(STATS_FILE))
+    }
+
+    /// Loads the statistics from `obs_stats.yaml` in a model directory.
+    ///
+    /// The loaded statistics are shared by environments built with clones of the configuration.
+    pub fn load_stats(&self, model_dir: impl AsRef<Path>) -> Result<()> {
+        let stats = RunningMeanStd::load(model_dir.as_ref().join(STATS_FILE))?;
+        *self.stats.write().unwrap() = stats;
+        Ok(())
+    }
+}
+
+/// An environment wrapper normalizing observations with running mean and variance.
+///
+/// Observations, including `init_obs` of [`Step`], are normalized element-wise as
+/// `(o - mean) / sqrt(var + epsilon)` and clipped. If [`NormalizeObsConfig::update`] is `true`,
+/// the statistics are updated with observations before the normalization.
+///
+/// The statistics are not part of agents. They should be saved with
+/// [`NormalizeObsConfig::save_stats()`] in the directory of the model and loaded with
+/// [`NormalizeObsConfig::load_stats()`], so that evaluation uses the same normalization.
+pub struct NormalizeObs<E: Env> {
+    env: E,
+    update: bool,
+    clip: Option<f32>,
+    epsilon: f64,
+    stats: Arc<RwLock<RunningMeanStd>>,
+}
+
+impl<E: Env> NormalizeObs<E>
+where
+    E::Obs: NormalizableObs,
+{
+    fn normalize(&self, obs: E::Obs) -> E::Obs {
+        let values = obs.values();
+        if self.update {
+            self.stats.write().unwrap().update(&values, obs.len());
+        }
+        let values = self
+            .stats
+            .read()
+            .unwrap()
+            .normalize(&values, self.epsilon, self.clip);
+        obs.with_values(values)
+    }
+
+    fn normalize_step(&self, step: Step<Self>) -> Step<Self> {
+        Step {
+            obs: self.normalize(step.obs),
+            init_obs: step.init_obs.map(|o| self.normalize(o)),
+            ..step
+        }
+    }
+
+    /// Returns the wrapped environment.
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+
+    /// Returns the current statistics.
+    pub fn stats(&self) -> RunningMeanStd {
+        self.stats.read().unwrap().clone()
+    }
+}
+
+impl<E: Env> Env for NormalizeObs<E>
+where
+    E::Obs: NormalizableObs,
+{
+    type Config = NormalizeObsConfig<E>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        Ok(Self {
+            env: E::build(&config.env_config, seed)?,
+            update: config.update,
+            clip: config.clip,
+            epsilon: config.epsilon,
+            stats: config.stats.clone(),
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (step, record) = self.env.step(a);
+        (self.normalize_step(convert_step(step)), record)
+    }
+
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (step, record) = self.env.step_with_reset(a);
+        (self.normalize_step(convert_step(step)), record)
+    }
+
+    fn try_step(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        let (step, record) = self.env.try_step(a)?;
+        Ok((self.normalize_step(convert_step(step)), record))
+    }
+
+    fn try_step_with_reset(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        let (step, record) = self.env.try_step_with_reset(a)?;
+        Ok((self.normalize_step(convert_step(step)), record))
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        let obs = self.env.reset(is_done)?;
+        Ok(self.normalize(obs))
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        let obs = self.env.reset_with_index(ix)?;
+        Ok(self.normalize(obs))
+    }
+
+    fn n_envs(&self) -> usize {
+        self.env.n_envs()
+    }
+
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        self.env.probe_actions(n_random)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.env.close()
+    }
+}
+
+/// Converts a step of the wrapped environment into that of the wrapper.
+pub(crate) fn convert_step<E: Env, W>(step: Step<E>) -> Step<W>
+where
+    W: Env<Obs = E::Obs, Act = E::Act, Info = E::Info>,
+{
+    Step {
+        act: step.act,
+        obs: step.obs,
+        reward: step.reward,
+        is_terminated: step.is_terminated,
+        is_truncated: step.is_truncated,
+        info: step.info,
+        init_obs: step.init_obs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{CountAct, CountEnv, CountEnvConfig};
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_running_mean_std() {
+        let mut stats = RunningMeanStd::default();
+        stats.update(&[1.0, 10.0, 2.0, 20.0], 2);
+        stats.update(&[3.0, 30.0], 1);
+        stats.update(&[4.0, 40.0, 5.0, 50.0], 2);
+        assert_eq!(stats.count, 5.0);
+        assert!((stats.mean[0] - 3.0).abs() < 1e-9);
+        assert!((stats.mean[1] - 30.0).abs() < 1e-9);
+        assert!((stats.var[0] - 2.0).abs() < 1e-9);
+        assert!((stats.var[1] - 200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_normalize_obs() -> Result<()> {
+        // Observations are `[t, 100]` at step `t`
+        let env_config = CountEnvConfig::default().constant(100.0);
+        let config = NormalizeObsConfig::<CountEnv>::new(env_config.clone()).clip(Some(5.0));
+        let mut env = NormalizeObs::build(&config, 0)?;
+        let mut env_eval = NormalizeObs::build(&config.clone().update(false), 0)?;
+
+        env.reset(None)?;
+        for _ in 0..99 {
+            env.step(&CountAct(vec![0]));
+        }
+        let stats = config.stats();
+        assert_eq!(stats.count, 100.0);
+        assert!((stats.mean[0] - 49.5).abs() < 1e-9);
+
+        // The environment for evaluation shares the statistics without updating them
+        let obs = env_eval.reset(None)?;
+        assert!((obs.0[0][0] + 49.5 / stats.var[0].sqrt() as f32).abs() < 1e-4);
+        assert_eq!(obs.0[0][1], 0.0);
+        assert_eq!(config.stats().count, 100.0);
+
+        // Save and load
+        let dir = TempDir::new("normalize_obs")?;
+        config.save_stats(dir.path())?;
+        let config = NormalizeObsConfig::<CountEnv>::new(env_config);
+        config.load_stats(dir.path())?;
+        assert_eq!(config.stats(), stats);
+        Ok(())
+    }
+}
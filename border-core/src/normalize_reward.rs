@@ -0,0 +1,473 @@
+//! Normalization, scaling and clipping of rewards.
+use crate::{
+    normalize_obs::convert_step,
+    record::{Record, RecordValue},
+    Env, RunningMeanStd, Step,
+};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+/// Name of the file of the statistics saved in a model directory.
+const STATS_FILE: &str = "return_stats.yaml";
+
+/// Configuration of [`RewardNormalizer`].
+///
+/// It is serializable, so that it can be saved with the configurations of agents and
+/// experiments to reproduce them.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct RewardNormalizerConfig {
+    /// If `true`, rewards are divided by the running standard deviation of discounted returns.
+    pub normalize: bool,
+
+    /// Discount factor of returns.
+    pub gamma: f64,
+
+    /// A small value added to the variance for numerical stability.
+    pub epsilon: f64,
+
+    /// Rewards are multiplied by this value after the normalization.
+    pub scale: f32,
+
+    /// Rewards are clipped to `[-clip, clip]` after the scaling if given.
+    pub clip: Option<f32>,
+}
+
+impl Default for RewardNormalizerConfig {
+    fn default() -> Self {
+        Self {
+            normalize: true,
+            gamma: 0.99,
+            epsilon: 1e-8,
+            scale: 1.0,
+            clip: Some(10.0),
+        }
+    }
+}
+
+impl RewardNormalizerConfig {
+    /// Sets `false` to scale and clip rewards without the normalization.
+    pub fn normalize(mut self, v: bool) -> Self {
+        self.normalize = v;
+        self
+    }
+
+    /// Sets the discount factor of returns.
+    pub fn gamma(mut self, v: f64) -> Self {
+        self.gamma = v;
+        self
+    }
+
+    /// Sets the value added to the variance.
+    pub fn epsilon(mut self, v: f64) -> Self {
+        self.epsilon = v;
+        self
+    }
+
+    /// Sets the scale of rewards.
+    pub fn scale(mut self, v: f32) -> Self {
+        self.scale = v;
+        self
+    }
+
+    /// Sets the range of clipping rewards, `None` for no clipping.
+    pub fn clip(mut self, v: Option<f32>) -> Self {
+        self.clip = v;
+        self
+    }
+}
+
+/// Statistics of discounted returns shared by reward normalizers and evaluators.
+///
+/// It is a handle of the statistics updated by [`RewardNormalizer`] in training, along with
+/// the configuration of the normalization. Given to an evaluator running episodes with
+/// the original rewards, e.g., with [`DefaultEvaluator::return_stats()`], it reports
+/// the returns of the rewards normalized as in training besides the original returns.
+///
+/// [`DefaultEvaluator::return_stats()`]: crate::DefaultEvaluator::return_stats
+#[derive(Clone, Debug)]
+pub struct ReturnStats {
+    config: RewardNormalizerConfig,
+    returns: Arc<RwLock<RunningMeanStd>>,
+}
+
+impl ReturnStats {
+    /// Constructs statistics without returns.
+    pub fn new(config: RewardNormalizerConfig) -> Self {
+        Self {
+            config,
+            returns: Arc::new(RwLock::new(RunningMeanStd::default())),
+        }
+    }
+
+    /// Returns the configuration of the normalization.
+    pub fn config(&self) -> &RewardNormalizerConfig {
+        &self.config
+    }
+
+    /// Returns the running statistics of discounted returns.
+    pub fn returns(&self) -> RunningMeanStd {
+        self.returns.read().unwrap().clone()
+    }
+
+    /// Returns the standard deviation which rewards are divided by, 1 before the statistics
+    /// are computed from two or more returns or if the normalization is disabled.
+    pub fn std(&self) -> f64 {
+        let returns = self.returns.read().unwrap();
+        match self.config.normalize && returns.count > 1.0 {
+            true => (returns.var[0] + self.config.epsilon).sqrt(),
+            false => 1.0,
+        }
+    }
+
+    /// Returns normalized, scaled and clipped rewards without updating the statistics.
+    pub fn normalize(&self, reward: &[f32]) -> Vec<f32> {
+        let std = self.std();
+        reward
+            .iter()
+            .map(|r| {
+                let r = (*r as f64 / std) as f32 * self.config.scale;
+                match self.config.clip {
+                    Some(c) => r.clamp(-c, c),
+                    None => r,
+                }
+            })
+            .collect()
+    }
+
+    /// Saves the statistics of returns in `return_stats.yaml` in a model directory.
+    pub fn save(&self, model_dir: impl AsRef<Path>) -> Result<()> {
+        self.returns().save(model_dir.as_ref().join(STATS_FILE))
+    }
+
+    /// Loads the statistics of returns from `return_stats.yaml` in a model directory.
+    ///
+    /// The loaded statistics are shared by the clones of this object.
+    pub fn load(&self, model_dir: impl AsRef<Path>) -> Result<()> {
+        let returns = RunningMeanStd::load(model_dir.as_ref().join(STATS_FILE))?;
+        *self.returns.write().unwrap() = returns;
+        Ok(())
+    }
+}
+
+/// Normalizes rewards with the running standard deviation of discounted returns.
+///
+/// As in common implementations of PPO, the discounted return of each environment is
+/// accumulated as `ret = gamma * ret + reward`, and rewards are divided by the standard
+/// deviation of the returns without subtracting the mean, so that the sign of rewards is kept.
+/// The return is reset to zero at the end of an episode. Rewards are not normalized until
+/// the statistics are computed from two or more returns.
+///
+/// The statistics are kept in [`ReturnStats`], which can be shared with evaluators.
+/// The undiscounted returns of episodes are also accumulated with both the original and
+/// the normalized rewards, see [`RewardNormalizer::take_record()`].
+pub struct RewardNormalizer {
+    returns: Vec<f64>,
+    stats: ReturnStats,
+    episode_returns: Vec<(f32, f32)>,
+    finished: Vec<(f32, f32)>,
+}
+
+impl RewardNormalizer {
+    /// Constructs a reward normalizer.
+    pub fn new(config: RewardNormalizerConfig) -> Self {
+        Self::with_stats(ReturnStats::new(config))
+    }
+
+    /// Constructs a reward normalizer updating the given statistics.
+    pub fn with_stats(stats: ReturnStats) -> Self {
+        Self {
+            returns: vec![],
+            stats,
+            episode_returns: vec![],
+            finished: vec![],
+        }
+    }
+
+    /// Returns normalized, scaled and clipped rewards of a step of environments.
+    ///
+    /// `is_done` is the flags of the end of episodes in the step.
+    pub fn normalize(&mut self, reward: &[f32], is_done: &[i8]) -> Vec<f32> {
+        if self.returns.len() != reward.len() {
+            self.returns = vec![0.0; reward.len()];
+            self.episode_returns = vec![(0.0, 0.0); reward.len()];
+        }
+
+        let config = self.stats.config();
+        if config.normalize {
+            for (ret, r) in self.returns.iter_mut().zip(reward.iter()) {
+                *ret = config.gamma * *ret + *r as f64;
+            }
+            let returns = self.returns.iter().map(|r| *r as f32).collect::<Vec<_>>();
+            self.stats
+                .returns
+                .write()
+                .unwrap()
+                .update(&returns, returns.len());
+        }
+        let normalized = self.stats.normalize(reward);
+
+        for (i, d) in is_done.iter().enumerate() {
+            let ret = &mut self.episode_returns[i];
+            ret.0 += reward[i];
+            ret.1 += normalized[i];
+            if *d == 1 {
+                self.finished.push(*ret);
+                *ret = (0.0, 0.0);
+                self.returns[i] = 0.0;
+            }
+        }
+
+        normalized
+    }
+
+    /// Resets the returns of the environments where `is_done[i] == 1`, or all environments
+    /// if `is_done` is `None`.
+    pub fn reset(&mut self, is_done: Option<&Vec<i8>>) {
+        match is_done {
+            None => {
+                self.returns.iter_mut().for_each(|r| *r = 0.0);
+                self.episode_returns
+                    .iter_mut()
+                    .for_each(|r| *r = (0.0, 0.0));
+            }
+            Some(is_done) => {
+                for (i, d) in is_done.iter().enumerate() {
+                    if *d == 1 && i < self.returns.len() {
+                        self.returns[i] = 0.0;
+                        self.episode_returns[i] = (0.0, 0.0);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the record of the mean returns of the episodes ended since the last call,
+    /// `episode_return_raw` with the original rewards and `episode_return_normalized` with
+    /// the normalized rewards. It is empty if no episode has ended.
+    pub fn take_record(&mut self) -> Record {
+        if self.finished.is_empty() {
+            return Record::empty();
+        }
+        let n = self.finished.len() as f32;
+        let (raw, normalized) = self
+            .finished
+            .drain(..)
+            .fold((0.0, 0.0), |(a, b), (r, s)| (a + r, b + s));
+        Record::from_slice(&[
+            ("episode_return_raw", RecordValue::Scalar(raw / n)),
+            (
+                "episode_return_normalized",
+                RecordValue::Scalar(normalized / n),
+            ),
+        ])
+    }
+
+    /// Returns the running statistics of discounted returns.
+    pub fn stats(&self) -> RunningMeanStd {
+        self.stats.returns()
+    }
+
+    /// Returns the statistics of returns, which can be shared with evaluators.
+    pub fn return_stats(&self) -> &ReturnStats {
+        &self.stats
+    }
+}
+
+/// Configuration of [`NormalizeReward`].
+///
+/// The statistics of returns are shared by the environments built with clones of
+/// the configuration and by [`ReturnStats`] obtained with
+/// [`NormalizeRewardConfig::return_stats()`].
+pub struct NormalizeRewardConfig<E: Env> {
+    /// Configuration of the wrapped environment.
+    pub env_config: E::Config,
+
+    /// Configuration of the reward normalizer.
+    pub normalizer: RewardNormalizerConfig,
+
+    /// Statistics shared by environments.
+    returns: Arc<RwLock<RunningMeanStd>>,
+}
+
+impl<E: Env> Clone for NormalizeRewardConfig<E> {
+    fn clone(&self) -> Self {
+        Self {
+            env_config: self.env_config.clone(),
+            normalizer: self.normalizer.clone(),
+            returns: self.returns.clone(),
+        }
+    }
+}
+
+impl<E: Env> NormalizeRewardConfig<E> {
+    /// Constructs a configuration with the default [`RewardNormalizerConfig`].
+    pub fn new(env_config: E::Config) -> Self {
+        Self {
+            env_config,
+            normalizer: RewardNormalizerConfig::default(),
+            returns: Arc::new(RwLock::new(RunningMeanStd::default())),
+        }
+    }
+
+    /// Sets the configuration of the reward normalizer.
+    pub fn normalizer(mut self, v: RewardNormalizerConfig) -> Self {
+        self.normalizer = v;
+        self
+    }
+
+    /// Returns the statistics of returns shared with the environments built with
+    /// the configuration, e.g., to be given to an evaluator.
+    pub fn return_stats(&self) -> ReturnStats {
+        ReturnStats {
+            config: self.normalizer.clone(),
+            returns: self.returns.clone(),
+        }
+    }
+}
+
+/// An environment wrapper normalizing, scaling and clipping rewards with [`RewardNormalizer`].
+///
+/// It is intended for training. Evaluators should be given the wrapped environment
+/// to report returns of the original rewards, along with [`NormalizeRewardConfig::return_stats()`]
+/// to report those of the normalized rewards. The record of a step contains
+/// `episode_return_raw` and `episode_return_normalized` when episodes end,
+/// see [`RewardNormalizer::take_record()`].
+pub struct NormalizeReward<E: Env> {
+    env: E,
+    normalizer: RewardNormalizer,
+}
+
+impl<E: Env> NormalizeReward<E> {
+    fn normalize_step(&mut self, step: Step<E>, record: Record) -> (Step<Self>, Record) {
+        let reward = self.normalizer.normalize(&step.reward, &step.is_done_vec());
+        let step = Step {
+            reward,
+            ..convert_step(step)
+        };
+        (step, record.merge(self.normalizer.take_record()))
+    }
+
+    /// Returns the wrapped environment.
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+
+    /// Returns the reward normalizer.
+    pub fn normalizer(&self) -> &RewardNormalizer {
+        &self.normalizer
+    }
+}
+
+impl<E: Env> Env for NormalizeReward<E> {
+    type Config = NormalizeRewardConfig<E>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        Ok(Self {
+            env: E::build(&config.env_config, seed)?,
+            normalizer: RewardNormalizer::with_stats(config.return_stats()),
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (step, record) = self.env.step(a);
+        self.normalize_step(step, record)
+    }
+
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (step, record) = self.env.step_with_reset(a);
+        self.normalize_step(step, record)
+    }
+
+    fn try_step(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        let (step, record) = self.env.try_step(a)?;
+        Ok(self.normalize_step(step, record))
+    }
+
+    fn try_step_with_reset(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        let (step, record) = self.env.try_step_with_reset(a)?;
+        Ok(self.normalize_step(step, record))
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        self.normalizer.reset(is_done);
+        self.env.reset(is_done)
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.normalizer.reset(None);
+        self.env.reset_with_index(ix)
+    }
+
+    fn n_envs(&self) -> usize {
+        self.env.n_envs()
+    }
+
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        self.env.probe_actions(n_random)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.env.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reward_normalizer() {
+        // Scaling and clipping only
+        let config = RewardNormalizerConfig::default()
+            .normalize(false)
+            .scale(0.5)
+            .clip(Some(1.0));
+        let mut normalizer = RewardNormalizer::new(config);
+        assert_eq!(normalizer.normalize(&[1.0, 4.0], &[0, 0]), vec![0.5, 1.0]);
+
+        // Returns of constant rewards are 1, 1.5, 1.75, ...
+        let config = RewardNormalizerConfig::default().gamma(0.5).clip(None);
+        let mut normalizer = RewardNormalizer::new(config);
+        assert_eq!(normalizer.normalize(&[1.0], &[0]), vec![1.0]);
+        let mut std = 0.0;
+        for t in 1..4 {
+            let r = normalizer.normalize(&[1.0], &[(t == 1) as i8]);
+            std = normalizer.stats().var[0].sqrt();
+            assert!((r[0] as f64 - 1.0 / std).abs() < 1e-3);
+        }
+        // Returns are 1, 1.5, 1 and 1.5 as the episode ends at the second step
+        assert!((std - 0.25).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_return_stats() {
+        let config = RewardNormalizerConfig::default().gamma(0.5).clip(None);
+        let stats = ReturnStats::new(config);
+        let mut normalizer = RewardNormalizer::with_stats(stats.clone());
+        assert_eq!(stats.normalize(&[2.0]), vec![2.0]);
+
+        let mut r_normalized = 0.0;
+        for t in 0..4 {
+            r_normalized += normalizer.normalize(&[1.0], &[(t == 3) as i8])[0];
+        }
+
+        // The statistics updated by the normalizer are shared
+        assert_eq!(stats.returns(), normalizer.stats());
+        let std = stats.std() as f32;
+        assert!((stats.normalize(&[2.0])[0] - 2.0 / std).abs() < 1e-6);
+
+        let record = normalizer.take_record();
+        assert_eq!(record.get_scalar("episode_return_raw").unwrap(), 4.0);
+        assert!(
+            (record.get_scalar("episode_return_normalized").unwrap() - r_normalized).abs() < 1e-6
+        );
+        assert!(normalizer.take_record().is_empty());
+    }
+}
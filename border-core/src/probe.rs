@@ -0,0 +1,131 @@
+//! Sanity probes of environments before training.
+use crate::{Env, Obs};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Configuration of the probe of an environment at the start of training.
+///
+/// The probe applies the actions returned by [`Env::probe_actions()`], which should cover
+/// the boundaries of the action space and include random actions, and checks that rewards
+/// and observations are finite and that observations have the configured shape.
+/// It detects mis-specified action filters and environments before a long training run.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ProbeConfig {
+    /// The number of random actions in addition to the boundary actions.
+    #[serde(default = "default_n_random")]
+    pub n_random: usize,
+
+    /// Expected shape of observations excluding the batch dimension, checked if given.
+    #[serde(default)]
+    pub obs_shape: Option<Vec<usize>>,
+}
+
+fn default_n_random() -> usize {
+    10
+}
+
+impl Default for ProbeConfig {
+    fn default() -> Self {
+        Self {
+            n_random: default_n_random(),
+            obs_shape: None,
+        }
+    }
+}
+
+impl ProbeConfig {
+    /// Sets the number of random actions.
+    pub fn n_random(mut self, v: usize) -> Self {
+        self.n_random = v;
+        self
+    }
+
+    /// Sets the expected shape of observations.
+    pub fn obs_shape(mut self, v: Vec<usize>) -> Self {
+        self.obs_shape = Some(v);
+        self
+    }
+}
+
+/// Result of [`probe_env()`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ProbeReport {
+    /// The number of steps taken in the environment.
+    pub n_steps: usize,
+
+    /// Descriptions of the detected problems.
+    pub issues: Vec<String>,
+}
+
+impl ProbeReport {
+    /// Returns `true` if no problem is detected.
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ProbeReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Probe of the environment found {} issue(s) in {} steps",
+            self.issues.len(),
+            self.n_steps
+        )?;
+        for issue in self.issues.iter() {
+            write!(f, "\n  - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+fn check_obs<O: Obs>(obs: &O, config: &ProbeConfig, context: &str, issues: &mut Vec<String>) {
+    if !obs.is_finite() {
+        issues.push(format!("Observation {} is not finite: {:?}", context, obs));
+    }
+    if let (Some(expected), Some(shape)) = (&config.obs_shape, obs.shape()) {
+        if expected != &shape {
+            issues.push(format!(
+                "Observation {} has shape {:?}, expected {:?}",
+                context, shape, expected
+            ));
+        }
+    }
+}
+
+/// Probes the environment with the actions returned by [`Env::probe_actions()`].
+///
+/// The environment is reset before the probe and whenever an episode ends.
+/// Panics of [`Env::step()`], e.g., due to actions out of the action space, are not caught.
+///
+/// # Errors
+///
+/// Returns an error if the environment fails to reset. Problems of rewards and observations
+/// are reported in [`ProbeReport`].
+pub fn probe_env<E: Env>(env: &mut E, config: &ProbeConfig) -> Result<ProbeReport> {
+    let mut report = ProbeReport::default();
+    let obs = env.reset(None)?;
+    check_obs(&obs, config, "after reset", &mut report.issues);
+
+    let acts = env.probe_actions(config.n_random);
+    if acts.is_empty() {
+        log::warn!("The environment provides no actions to probe");
+    }
+    for act in acts.iter() {
+        let (step, _) = env.step(act);
+        report.n_steps += 1;
+        let context = format!("after action {:?}", act);
+        if let Some(r) = step.reward.iter().find(|r| !r.is_finite()) {
+            report
+                .issues
+                .push(format!("Reward {} is {}, not finite", context, r));
+        }
+        check_obs(&step.obs, config, &context, &mut report.issues);
+        if step.is_done() {
+            env.reset(None)?;
+        }
+    }
+
+    Ok(report)
+}
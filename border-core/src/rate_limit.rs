@@ -0,0 +1,310 @@
+//! Limiting the rate of interaction steps with environments.
+use crate::{
+    normalize_obs::convert_step,
+    record::{Record, RecordValue},
+    Env, Step,
+};
+use anyhow::Result;
+use std::time::{Duration, Instant};
+
+/// Smoothing factor of the moving average of step intervals.
+const EMA_ALPHA: f64 = 0.1;
+
+/// Source of time of [`RateLimit`].
+///
+/// [`SystemClock`] is used by default. Another clock, e.g., that of a simulator, can be given
+/// with [`RateLimit::with_clock()`].
+pub trait Clock: Send {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Blocks the current thread for the given duration.
+    fn sleep(&self, duration: Duration);
+}
+
+/// [`Clock`] of the system.
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Configuration of [`RateLimit`].
+pub struct RateLimitConfig<E: Env> {
+    /// Configuration of the wrapped environment.
+    pub env_config: E::Config,
+
+    /// Target rate of steps in Hz.
+    pub hz: f64,
+
+    /// Maximum lag behind the schedule of steps to be caught up with.
+    ///
+    /// If a step is delayed more than this, the schedule restarts from the step instead of
+    /// running the following steps without waiting. It defaults to the period of steps.
+    pub max_lag: Option<Duration>,
+}
+
+impl<E: Env> Clone for RateLimitConfig<E> {
+    fn clone(&self) -> Self {
+        Self {
+            env_config: self.env_config.clone(),
+            hz: self.hz,
+            max_lag: self.max_lag,
+        }
+    }
+}
+
+impl<E: Env> RateLimitConfig<E> {
+    /// Constructs a configuration with the target rate of steps in Hz.
+    pub fn new(env_config: E::Config, hz: f64) -> Self {
+        Self {
+            env_config,
+            hz,
+            max_lag: None,
+        }
+    }
+
+    /// Sets the maximum lag behind the schedule to be caught up with.
+    pub fn max_lag(mut self, v: Duration) -> Self {
+        self.max_lag = Some(v);
+        self
+    }
+}
+
+/// An environment wrapper stepping the wrapped environment at a target rate.
+///
+/// It is used when the environment is real hardware or a real-time simulator, where actions
+/// should be applied at a fixed control frequency. Before each step, the wrapper waits until
+/// the scheduled time of the step. The schedule advances by the period from the scheduled time
+/// of the previous step, not from the time when the previous step ended, so that the time spent
+/// in the policy and the environment does not accumulate as a drift. Delayed steps up to
+/// [`RateLimitConfig::max_lag`] are caught up with by the following steps.
+///
+/// The schedule restarts at each reset, as resetting hardware takes an arbitrary time.
+///
+/// The record of each step contains:
+/// - `step_rate` - The measured rate of steps in Hz, with the moving average of step intervals
+/// - `step_overruns` - The number of steps started later than scheduled so far
+pub struct RateLimit<E: Env> {
+    env: E,
+    clock: Box<dyn Clock>,
+    period: Duration,
+    max_lag: Duration,
+    deadline: Option<Instant>,
+    last_step: Option<Instant>,
+    interval: Option<f64>,
+    n_overruns: usize,
+}
+
+impl<E: Env> RateLimit<E> {
+    /// Waits until the scheduled time of the next step, and updates the schedule.
+    fn wait(&mut self) {
+        let now = self.clock.now();
+        self.deadline = Some(match self.deadline {
+            None => now + self.period,
+            Some(deadline) if now < deadline => {
+                self.clock.sleep(deadline - now);
+                deadline + self.period
+            }
+            Some(deadline) => {
+                self.n_overruns += (now > deadline) as usize;
+                match now - deadline > self.max_lag {
+                    true => now + self.period,
+                    false => deadline + self.period,
+                }
+            }
+        });
+
+        let now = self.clock.now();
+        if let Some(last_step) = self.last_step {
+            let dt = (now - last_step).as_secs_f64();
+            self.interval = Some(match self.interval {
+                None => dt,
+                Some(v) => EMA_ALPHA * dt + (1.0 - EMA_ALPHA) * v,
+            });
+        }
+        self.last_step = Some(now);
+    }
+
+    /// Restarts the schedule of steps.
+    fn restart(&mut self) {
+        self.deadline = None;
+        self.last_step = None;
+    }
+
+    fn record(&self) -> Record {
+        let mut record = Record::from_scalar("step_overruns", self.n_overruns as f32);
+        if let Some(rate) = self.step_rate() {
+            record.insert("step_rate", RecordValue::Scalar(rate as f32));
+        }
+        record
+    }
+
+    /// Returns the measured rate of steps in Hz, or `None` before the second step.
+    pub fn step_rate(&self) -> Option<f64> {
+        self.interval.map(|v| 1.0 / v)
+    }
+
+    /// Returns the wrapped environment.
+    pub fn inner(&self) -> &E {
+        &self.env
+    }
+
+    /// Sets the clock by which steps are scheduled.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+}
+
+impl<E: Env> Env for RateLimit<E> {
+    type Config = RateLimitConfig<E>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = E::Info;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        if !(config.hz > 0.0 && config.hz.is_finite()) {
+            anyhow::bail!("Target rate of steps must be positive, got {}", config.hz);
+        }
+        let period = Duration::from_secs_f64(1.0 / config.hz);
+        Ok(Self {
+            env: E::build(&config.env_config, seed)?,
+            clock: Box::new(SystemClock),
+            period,
+            max_lag: config.max_lag.unwrap_or(period),
+            deadline: None,
+            last_step: None,
+            interval: None,
+            n_overruns: 0,
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.wait();
+        let (step, record) = self.env.step(a);
+        (convert_step(step), record.merge(self.record()))
+    }
+
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.wait();
+        let (step, record) = self.env.step_with_reset(a);
+        if step.init_obs.is_some() {
+            self.restart();
+        }
+        (convert_step(step), record.merge(self.record()))
+    }
+
+    fn try_step(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        self.wait();
+        let (step, record) = self.env.try_step(a)?;
+        Ok((convert_step(step), record.merge(self.record())))
+    }
+
+    fn try_step_with_reset(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        self.wait();
+        let (step, record) = self.env.try_step_with_reset(a)?;
+        if step.init_obs.is_some() {
+            self.restart();
+        }
+        Ok((convert_step(step), record.merge(self.record())))
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        let obs = self.env.reset(is_done)?;
+        self.restart();
+        Ok(obs)
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        let obs = self.env.reset_with_index(ix)?;
+        self.restart();
+        Ok(obs)
+    }
+
+    fn n_envs(&self) -> usize {
+        self.env.n_envs()
+    }
+
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        self.env.probe_actions(n_random)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        self.env.close()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        test::{TestAgent, TestEnv},
+        Policy,
+    };
+    use std::sync::{Arc, Mutex};
+
+    /// Clock advanced only by sleeping or manually.
+    #[derive(Clone)]
+    struct ManualClock(Arc<Mutex<Instant>>);
+
+    impl ManualClock {
+        fn advance(&self, duration: Duration) {
+            *self.0.lock().unwrap() += duration;
+        }
+    }
+
+    impl Clock for ManualClock {
+        fn now(&self) -> Instant {
+            *self.0.lock().unwrap()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.advance(duration);
+        }
+    }
+
+    #[test]
+    fn test_rate_limit() -> Result<()> {
+        let start = Instant::now();
+        let clock = ManualClock(Arc::new(Mutex::new(start)));
+        let config = RateLimitConfig::<TestEnv>::new(0, 200.0);
+        let mut env = RateLimit::build(&config, 0)?.with_clock(clock.clone());
+        let obs = env.reset(None)?;
+        let act = TestAgent {}.sample(&obs);
+
+        // The first step runs immediately, and the following 10 steps at 200 Hz,
+        // where each step takes 1 ms
+        let mut record = Record::empty();
+        for _ in 0..11 {
+            record = env.step(&act).1;
+            clock.advance(Duration::from_millis(1));
+        }
+        assert_eq!(clock.now() - start, Duration::from_millis(51));
+        assert!((record.get_scalar("step_rate")? - 200.0).abs() < 1e-3);
+        assert_eq!(record.get_scalar("step_overruns")?, 0.0);
+
+        // A step delayed more than the period restarts the schedule
+        clock.advance(Duration::from_millis(20));
+        let record = env.step(&act).1;
+        assert_eq!(record.get_scalar("step_overruns")?, 1.0);
+        let now = clock.now();
+        env.step(&act);
+        assert_eq!(clock.now() - now, Duration::from_millis(5));
+
+        assert!(RateLimit::build(&RateLimitConfig::<TestEnv>::new(0, 0.0), 0).is_err());
+
+        // Fallible steps are also limited
+        let now = clock.now();
+        let record = env.try_step_with_reset(&act)?.1;
+        assert_eq!(clock.now() - now, Duration::from_millis(5));
+        assert!(record.get_scalar("step_rate").is_ok());
+        Ok(())
+    }
+}
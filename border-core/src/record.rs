@@ -13,6 +13,7 @@
 //! * [`RecordStorage`] - A storage system with aggregation capabilities
 //! * [`BufferedRecorder`] - A recorder that temporarily stores records in memory
 //! * [`NullRecorder`] - A recorder that discards all records (useful for testing)
+//! * [`KeyStabilityRecorder`] - A wrapper of recorders warning when record keys drift
 //!
 //! # Basic Usage
 //!
@@ -46,16 +47,20 @@
 //! * `Array2(Vec<f32>, [usize; 2])` - 2-dimensional arrays with shape
 //! * `Array3(Vec<f32>, [usize; 3])` - 3-dimensional arrays with shape
 //! * `String(String)` - Text values
+//! * `Histogram(Vec<f32>)` - Samples of distributions, e.g., Q-values
+//! * `Image(Vec<u8>, [usize; 3])` - Images, e.g., observation frames
 //!
 //! [`HashMap`]: std::collections::HashMap
 mod base;
 mod buffered_recorder;
+mod key_stability_recorder;
 mod null_recorder;
 mod recorder;
 mod storage;
 
 pub use base::{Record, RecordValue};
 pub use buffered_recorder::BufferedRecorder;
+pub use key_stability_recorder::KeyStabilityRecorder;
 pub use null_recorder::NullRecorder;
 pub use recorder::Recorder;
 pub use storage::RecordStorage;
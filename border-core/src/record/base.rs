@@ -29,6 +29,8 @@ use std::{
 /// * `Array2(Vec<f32>, [usize; 2])` - A 2-dimensional array with shape information
 /// * `Array3(Vec<f32>, [usize; 3])` - A 3-dimensional array with shape information
 /// * `String(String)` - A text value
+/// * `Histogram(Vec<f32>)` - Samples of a distribution
+/// * `Image(Vec<u8>, [usize; 3])` - An image with shape information
 #[derive(Debug, Clone)]
 pub enum RecordValue {
     /// A single floating-point value, typically used for metrics like loss or accuracy.
@@ -48,6 +50,17 @@ pub enum RecordValue {
 
     /// A text value, useful for storing labels or descriptions.
     String(String),
+
+    /// Samples of a distribution, e.g., Q-values in a batch, summarized as a histogram.
+    ///
+    /// When records are aggregated in [`RecordStorage`], the samples are concatenated.
+    ///
+    /// [`RecordStorage`]: crate::record::RecordStorage
+    Histogram(Vec<f32>),
+
+    /// An image, e.g., an observation frame, with pixel values in the row-major order of
+    /// the shape `[height, width, channels]`, where `channels` is 1 (grayscale) or 3 (RGB).
+    Image(Vec<u8>, [usize; 3]),
 }
 
 /// A container for storing key-value pairs of various data types.
@@ -55,7 +68,7 @@ pub enum RecordValue {
 /// This structure provides a flexible way to store and retrieve different types
 /// of data using string keys. It supports merging records and provides type-safe
 /// access to stored values.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Record(HashMap<String, RecordValue>);
 
 impl Record {
@@ -167,6 +180,29 @@ impl Record {
         Record(self.0.into_iter().chain(record.0).collect())
     }
 
+    /// Merges records, averaging scalars over the records containing them.
+    ///
+    /// For other values, the value in the last record with the key is kept as in [`Record::merge`].
+    /// This is used to merge records of environments running in parallel.
+    pub fn merge_mean(records: impl IntoIterator<Item = Record>) -> Self {
+        let mut sums = HashMap::<String, (f32, usize)>::new();
+        let mut merged = Record::empty();
+        for record in records.into_iter() {
+            for (k, v) in record.iter() {
+                if let RecordValue::Scalar(v) = v {
+                    let e = sums.entry(k.clone()).or_insert((0.0, 0));
+                    e.0 += v;
+                    e.1 += 1;
+                }
+            }
+            merged = merged.merge(record);
+        }
+        for (k, (sum, n)) in sums.into_iter() {
+            merged.insert(k, RecordValue::Scalar(sum / n as f32));
+        }
+        merged
+    }
+
     /// Merges another record into this one in place.
     ///
     /// If both records contain the same key, the value from the second record
@@ -311,6 +347,42 @@ impl Record {
         }
     }
 
+    /// Gets the samples of a histogram from the record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The key does not exist
+    /// - The value is not a histogram
+    pub fn get_histogram(&self, k: &str) -> Result<Vec<f32>, LrrError> {
+        if let Some(v) = self.0.get(k) {
+            match v {
+                RecordValue::Histogram(v) => Ok(v.clone()),
+                _ => Err(LrrError::RecordValueTypeError("Histogram".to_string())),
+            }
+        } else {
+            Err(LrrError::RecordKeyError(k.to_string()))
+        }
+    }
+
+    /// Gets an image and its shape `[height, width, channels]` from the record.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - The key does not exist
+    /// - The value is not an image
+    pub fn get_image(&self, k: &str) -> Result<(Vec<u8>, [usize; 3]), LrrError> {
+        if let Some(v) = self.0.get(k) {
+            match v {
+                RecordValue::Image(v, s) => Ok((v.clone(), *s)),
+                _ => Err(LrrError::RecordValueTypeError("Image".to_string())),
+            }
+        } else {
+            Err(LrrError::RecordKeyError(k.to_string()))
+        }
+    }
+
     /// Checks if the record is empty.
     ///
     /// # Returns
@@ -0,0 +1,200 @@
+//! Recorder wrapper checking the stability of record keys.
+//!
+//! Dashboards built on recorded metrics silently break when metric names drift,
+//! which is typically a symptom of conditional logging bugs. This module provides
+//! a wrapper of [`Recorder`] that detects such drifts at run time.
+
+use super::{Record, Recorder};
+use crate::{Agent, Env, ModelMetadata, ReplayBufferBase};
+use anyhow::Result;
+use log::warn;
+use std::{collections::BTreeSet, path::Path};
+
+/// A recorder that warns when the set of record keys changes between flushes.
+///
+/// The wrapper collects the keys of records given to [`Recorder::write()`] and
+/// [`Recorder::store()`] between two flushes. On each flush, the set of keys is compared
+/// with that of the previous flush, and keys that appeared or disappeared are reported
+/// with [`log::warn!`], or cause a panic in strict mode. Records are forwarded to the
+/// inner recorder as they are.
+///
+/// Keys recorded at a different cadence from flushes, like evaluation results,
+/// can be excluded from the check with [`KeyStabilityRecorder::ignore_prefix()`].
+///
+/// # Examples
+///
+/// ```ignore
+/// let recorder = TensorboardRecorder::new(log_dir, model_dir, false);
+/// let mut recorder: Box<dyn Recorder<_, _>> = Box::new(
+///     KeyStabilityRecorder::new(Box::new(recorder)).ignore_prefix("Episode return"),
+/// );
+/// ```
+pub struct KeyStabilityRecorder<E, R>
+where
+    E: Env,
+    R: ReplayBufferBase,
+{
+    /// The wrapped recorder.
+    inner: Box<dyn Recorder<E, R>>,
+
+    /// Keys given since the last flush.
+    current: BTreeSet<String>,
+
+    /// Keys given in the interval before the last flush.
+    prev: Option<BTreeSet<String>>,
+
+    /// Prefixes of keys excluded from the check.
+    ignored_prefixes: Vec<String>,
+
+    /// If `true`, panics when the keys change.
+    strict: bool,
+
+    /// The number of flushes at which the keys changed.
+    n_violations: usize,
+}
+
+impl<E, R> KeyStabilityRecorder<E, R>
+where
+    E: Env,
+    R: ReplayBufferBase,
+{
+    /// Wraps a recorder.
+    pub fn new(inner: Box<dyn Recorder<E, R>>) -> Self {
+        Self {
+            inner,
+            current: BTreeSet::new(),
+            prev: None,
+            ignored_prefixes: vec![],
+            strict: false,
+            n_violations: 0,
+        }
+    }
+
+    /// Excludes keys starting with `prefix` from the check.
+    pub fn ignore_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.ignored_prefixes.push(prefix.into());
+        self
+    }
+
+    /// If `true`, panics instead of warning when the keys change.
+    pub fn strict(mut self, v: bool) -> Self {
+        self.strict = v;
+        self
+    }
+
+    /// Returns the number of flushes at which the keys changed.
+    pub fn n_violations(&self) -> usize {
+        self.n_violations
+    }
+
+    /// Collects the keys of a record.
+    fn track(&mut self, record: &Record) {
+        for k in record.keys() {
+            if !self.ignored_prefixes.iter().any(|p| k.starts_with(p)) {
+                self.current.insert(k.clone());
+            }
+        }
+    }
+
+    /// Compares the keys of the current interval with those of the previous one.
+    fn check(&mut self, step: i64) {
+        let current = std::mem::take(&mut self.current);
+
+        // Intervals without records are not compared
+        if current.is_empty() {
+            return;
+        }
+
+        if let Some(prev) = self.prev.as_ref() {
+            let appeared = current.difference(prev).collect::<Vec<_>>();
+            let disappeared = prev.difference(&current).collect::<Vec<_>>();
+            if !appeared.is_empty() || !disappeared.is_empty() {
+                self.n_violations += 1;
+                let msg = format!(
+                    "Record keys changed at step {}: appeared {:?}, disappeared {:?}",
+                    step, appeared, disappeared
+                );
+                if self.strict {
+                    panic!("{}", msg);
+                }
+                warn!("{}", msg);
+            }
+        }
+
+        self.prev = Some(current);
+    }
+}
+
+impl<E, R> Recorder<E, R> for KeyStabilityRecorder<E, R>
+where
+    E: Env,
+    R: ReplayBufferBase,
+{
+    fn write(&mut self, record: Record) {
+        self.track(&record);
+        self.inner.write(record);
+    }
+
+    fn store(&mut self, record: Record) {
+        self.track(&record);
+        self.inner.store(record);
+    }
+
+    /// Checks the keys given since the last flush, then flushes the inner recorder.
+    fn flush(&mut self, step: i64) {
+        self.check(step);
+        self.inner.flush(step);
+    }
+
+    fn save_model(&self, base: &Path, agent: &Box<dyn Agent<E, R>>) -> Result<()> {
+        self.inner.save_model(base, agent)
+    }
+
+    fn load_model(&self, base: &Path, agent: &mut Box<dyn Agent<E, R>>) -> Result<()> {
+        self.inner.load_model(base, agent)
+    }
+
+    fn save_model_metadata(&self, base: &Path, metadata: &ModelMetadata) -> Result<()> {
+        self.inner.save_model_metadata(base, metadata)
+    }
+
+    fn remove_model(&self, base: &Path) -> Result<()> {
+        self.inner.remove_model(base)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        generic_replay_buffer::SimpleReplayBuffer,
+        record::{NullRecorder, RecordValue::Scalar},
+        test::{TestActBatch, TestEnv, TestObsBatch},
+    };
+
+    type ReplayBuffer = SimpleReplayBuffer<TestObsBatch, TestActBatch>;
+
+    #[test]
+    fn test_key_stability() {
+        let inner = Box::new(NullRecorder::<TestEnv, ReplayBuffer>::new());
+        let mut recorder = KeyStabilityRecorder::new(inner).ignore_prefix("eval/");
+
+        recorder.store(Record::from_slice(&[
+            ("loss", Scalar(1.0)),
+            ("q", Scalar(0.0)),
+        ]));
+        recorder.flush(1);
+        recorder.store(Record::from_slice(&[
+            ("loss", Scalar(1.0)),
+            ("q", Scalar(0.0)),
+        ]));
+        recorder.store(Record::from_scalar("eval/return", 1.0));
+        recorder.flush(2);
+        assert_eq!(recorder.n_violations(), 0);
+
+        // "q" disappeared
+        recorder.store(Record::from_scalar("loss", 1.0));
+        recorder.flush(3);
+        assert_eq!(recorder.n_violations(), 1);
+    }
+}
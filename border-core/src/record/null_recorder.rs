@@ -4,10 +4,11 @@
 //! It is particularly useful for debugging, testing, or when record storage is not needed
 //! but the recorder interface must be maintained.
 
-use std::marker::PhantomData;
+use std::{marker::PhantomData, path::Path};
 
 use super::{Record, Recorder};
-use crate::{Env, ReplayBufferBase};
+use crate::{Agent, Env, ReplayBufferBase};
+use anyhow::Result;
 
 /// A recorder that discards all records without storing them.
 ///
@@ -80,4 +81,17 @@ where
     ///
     /// * `_step` - The step at which to flush (ignored)
     fn flush(&mut self, _step: i64) {}
+
+    /// Discards the model without saving it.
+    ///
+    /// This allows [`Trainer`](crate::Trainer) to evaluate agents and choose the best model
+    /// without a place to save models, e.g., in tests.
+    ///
+    /// # Arguments
+    ///
+    /// * `_base` - The base path of the model (ignored)
+    /// * `_agent` - The agent whose model would be saved (ignored)
+    fn save_model(&self, _base: &Path, _agent: &Box<dyn Agent<E, R>>) -> Result<()> {
+        Ok(())
+    }
 }
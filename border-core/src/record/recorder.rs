@@ -6,7 +6,7 @@
 //! recording strategies.
 
 use super::Record;
-use crate::{Agent, Env, ReplayBufferBase};
+use crate::{Agent, Env, ModelMetadata, ReplayBufferBase};
 use anyhow::Result;
 use std::path::Path;
 
@@ -38,6 +38,16 @@ where
     /// * `record` - The record to be written
     fn write(&mut self, record: Record);
 
+    /// Writes a record, returning an error instead of panicking, e.g., on I/O errors.
+    ///
+    /// The default implementation calls [`write`].
+    ///
+    /// [`write`]: Recorder::write
+    fn try_write(&mut self, record: Record) -> Result<()> {
+        self.write(record);
+        Ok(())
+    }
+
     /// Stores a record for later processing or aggregation.
     ///
     /// This method is used to collect records that will be processed together,
@@ -105,4 +115,27 @@ where
     fn load_model(&self, base: &Path, agent: &mut Box<dyn Agent<E, R>>) -> Result<()> {
         unimplemented!();
     }
+
+    /// Saves the metadata of a model saved with [`Recorder::save_model()`].
+    ///
+    /// [`Trainer`] calls this method for the best models, e.g., to keep the metric triggering
+    /// the save. Implementations typically save it as [`ModelMetadata::FILE`] in the directory
+    /// of the model. The default implementation does nothing.
+    ///
+    /// [`Trainer`]: crate::Trainer
+    #[allow(unused_variables)]
+    fn save_model_metadata(&self, base: &Path, metadata: &ModelMetadata) -> Result<()> {
+        Ok(())
+    }
+
+    /// Removes a model saved with [`Recorder::save_model()`].
+    ///
+    /// [`Trainer`] calls this method for models falling out of the best models kept.
+    /// The default implementation does nothing.
+    ///
+    /// [`Trainer`]: crate::Trainer
+    #[allow(unused_variables)]
+    fn remove_model(&self, base: &Path) -> Result<()> {
+        Ok(())
+    }
 }
@@ -263,6 +263,46 @@ impl RecordStorage {
         panic!("Unexpected");
     }
 
+    /// Gets the most recent image for a given key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if:
+    /// - The key is not found
+    /// - The value is not an image
+    fn image(&self, key: &String) -> Record {
+        for record in self.data.iter().rev() {
+            if let Some(value) = record.get(key) {
+                match value {
+                    RecordValue::Image(..) => {
+                        return Record::from_slice(&[(key, value.clone())]);
+                    }
+                    _ => panic!("Expect RecordValue::Image for {}", key),
+                }
+            }
+        }
+        panic!("Unexpected");
+    }
+
+    /// Concatenates samples of histograms for a given key.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the values are not histograms.
+    fn histogram(&self, key: &String) -> Record {
+        let vs: Vec<f32> = self
+            .data
+            .iter()
+            .filter_map(|record| match record.get(key) {
+                Some(RecordValue::Histogram(v)) => Some(v.iter().copied()),
+                Some(_) => panic!("Expect RecordValue::Histogram for {}", key),
+                None => None,
+            })
+            .flatten()
+            .collect();
+        Record::from_slice(&[(key, RecordValue::Histogram(vs))])
+    }
+
     /// Aggregates scalar values with statistical measures.
     ///
     /// For a single value, returns it directly. For multiple values,
@@ -347,6 +387,8 @@ impl RecordStorage {
                 RecordValue::Array3(..) => self.array3(key),
                 RecordValue::String(..) => self.string(key),
                 RecordValue::Scalar(..) => self.scalar(key),
+                RecordValue::Histogram(..) => self.histogram(key),
+                RecordValue::Image(..) => self.image(key),
             };
             record = record.merge(r);
         }
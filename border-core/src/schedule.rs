@@ -0,0 +1,321 @@
+//! Schedules of hyperparameters over steps.
+use anyhow::Result;
+use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
+
+/// Value of a hyperparameter as a function of steps.
+///
+/// It is used for hyperparameters annealed through training, like the probability of random
+/// actions of explorers, the entropy coefficient of SAC and the exponent of importance weights
+/// of prioritized experience replay. As a part of configurations, it is serialized and
+/// recorded along with the other hyperparameters.
+///
+/// ```
+/// use border_core::Schedule;
+///
+/// let eps = Schedule::Linear { start: 1.0, end: 0.5, n_steps: 100 };
+/// assert_eq!(eps.value(50), 0.75);
+/// assert_eq!(eps.value(200), 0.5);
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum Schedule {
+    /// Keeps the value.
+    Constant(f64),
+
+    /// Changes linearly from `start` to `end` in `n_steps`, then keeps `end`.
+    Linear {
+        /// Value at step 0.
+        start: f64,
+        /// Value after `n_steps`.
+        end: f64,
+        /// Number of steps to reach `end`.
+        n_steps: usize,
+    },
+
+    /// Approaches `end` from `start` exponentially, as `end + (start - end) * rate^step`.
+    Exponential {
+        /// Value at step 0.
+        start: f64,
+        /// Asymptotic value.
+        end: f64,
+        /// Ratio of the distance to `end` between consecutive steps.
+        rate: f64,
+    },
+
+    /// Interpolates linearly between `(step, value)` points sorted by steps.
+    ///
+    /// The value of the first (last) point is kept before (after) it. It should be created with
+    /// [`Schedule::piecewise()`], which checks the points as in deserialization.
+    Piecewise(#[serde(deserialize_with = "deserialize_points")] Vec<(usize, f64)>),
+}
+
+/// Returns an error if there are no points or the steps of the points are not increasing.
+fn check_points(points: &[(usize, f64)]) -> Result<()> {
+    if points.is_empty() {
+        anyhow::bail!("Piecewise schedule must have at least one point");
+    }
+    if points.windows(2).any(|w| w[0].0 >= w[1].0) {
+        anyhow::bail!(
+            "Steps of piecewise schedule must be increasing: {:?}",
+            points
+        );
+    }
+    Ok(())
+}
+
+fn deserialize_points<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(usize, f64)>, D::Error> {
+    let points = Vec::<(usize, f64)>::deserialize(deserializer)?;
+    check_points(&points).map_err(D::Error::custom)?;
+    Ok(points)
+}
+
+impl Schedule {
+    /// Creates [`Schedule::Piecewise`] with `(step, value)` points.
+    ///
+    /// Returns an error if there are no points or the steps are not increasing.
+    pub fn piecewise(points: Vec<(usize, f64)>) -> Result<Self> {
+        check_points(&points)?;
+        Ok(Self::Piecewise(points))
+    }
+
+    /// Returns the value at the given step.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`Schedule::Piecewise`] has no points, which is not the case for schedules
+    /// created with [`Schedule::piecewise()`] or deserialized.
+    pub fn value(&self, step: usize) -> f64 {
+        match self {
+            Self::Constant(v) => *v,
+            Self::Linear {
+                start,
+                end,
+                n_steps,
+            } => {
+                let r = (step as f64 / (*n_steps).max(1) as f64).min(1.0);
+                start + (end - start) * r
+            }
+            Self::Exponential { start, end, rate } => end + (start - end) * rate.powf(step as f64),
+            Self::Piecewise(points) => {
+                let ix = points.partition_point(|(s, _)| *s <= step);
+                if ix == 0 {
+                    points[0].1
+                } else if ix == points.len() {
+                    points[ix - 1].1
+                } else {
+                    let ((s0, v0), (s1, v1)) = (points[ix - 1], points[ix]);
+                    v0 + (v1 - v0) * (step - s0) as f64 / (s1 - s0) as f64
+                }
+            }
+        }
+    }
+}
+
+/// Decay of the learning rate after warmup in [`SchedulerConfig`].
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub enum LrDecay {
+    /// Keeps the base learning rate.
+    #[default]
+    Constant,
+
+    /// Decays the learning rate linearly to `final_lr` in `n_steps`.
+    Linear {
+        /// Number of steps to reach `final_lr`.
+        n_steps: usize,
+        /// Learning rate after `n_steps`.
+        final_lr: f64,
+    },
+
+    /// Decays the learning rate to `final_lr` in `n_steps` following a half cosine curve.
+    Cosine {
+        /// Number of steps to reach `final_lr`.
+        n_steps: usize,
+        /// Learning rate after `n_steps`.
+        final_lr: f64,
+    },
+
+    /// Multiplies the learning rate by `gamma` every `step_size` steps.
+    Step {
+        /// Number of steps between decays.
+        step_size: usize,
+        /// Factor of each decay.
+        gamma: f64,
+    },
+}
+
+/// Configuration of the schedule of the learning rate.
+///
+/// The learning rate increases linearly from `base_lr / warmup_steps` to the base learning rate
+/// in the first `warmup_steps` steps, then decays as specified by `decay`, where steps are
+/// counted from the end of the warmup. Optimizers of the backends apply the learning rate
+/// tracked by [`LrScheduler`].
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct SchedulerConfig {
+    /// Number of steps of the linear warmup.
+    #[serde(default)]
+    pub warmup_steps: usize,
+
+    /// Decay of the learning rate after the warmup.
+    #[serde(default)]
+    pub decay: LrDecay,
+}
+
+impl SchedulerConfig {
+    /// Linear decay to `final_lr` in `n_steps`.
+    pub fn linear(n_steps: usize, final_lr: f64) -> Self {
+        Self {
+            warmup_steps: 0,
+            decay: LrDecay::Linear { n_steps, final_lr },
+        }
+    }
+
+    /// Cosine decay to `final_lr` in `n_steps`.
+    pub fn cosine(n_steps: usize, final_lr: f64) -> Self {
+        Self {
+            warmup_steps: 0,
+            decay: LrDecay::Cosine { n_steps, final_lr },
+        }
+    }
+
+    /// Decay by `gamma` every `step_size` steps.
+    pub fn step(step_size: usize, gamma: f64) -> Self {
+        Self {
+            warmup_steps: 0,
+            decay: LrDecay::Step { step_size, gamma },
+        }
+    }
+
+    /// Sets the number of warmup steps.
+    pub fn warmup(mut self, v: usize) -> Self {
+        self.warmup_steps = v;
+        self
+    }
+
+    /// Returns the learning rate at the given optimization step.
+    pub fn lr(&self, base_lr: f64, step: usize) -> f64 {
+        if step < self.warmup_steps {
+            return base_lr * (step + 1) as f64 / self.warmup_steps as f64;
+        }
+        let t = step - self.warmup_steps;
+        match self.decay {
+            LrDecay::Constant => base_lr,
+            LrDecay::Linear { n_steps, final_lr } => {
+                let r = (t as f64 / n_steps.max(1) as f64).min(1.0);
+                base_lr + (final_lr - base_lr) * r
+            }
+            LrDecay::Cosine { n_steps, final_lr } => {
+                let r = (t as f64 / n_steps.max(1) as f64).min(1.0);
+                final_lr + 0.5 * (base_lr - final_lr) * (1.0 + (std::f64::consts::PI * r).cos())
+            }
+            LrDecay::Step { step_size, gamma } => {
+                base_lr * gamma.powi((t / step_size.max(1)) as i32)
+            }
+        }
+    }
+}
+
+/// State of the schedule of the learning rate.
+///
+/// It counts optimization steps and returns the learning rate to be set to the optimizer.
+#[derive(Debug, Clone)]
+pub struct LrScheduler {
+    config: SchedulerConfig,
+    base_lr: f64,
+    n_steps: usize,
+}
+
+impl LrScheduler {
+    /// Creates a schedule starting from step 0.
+    pub fn new(config: SchedulerConfig, base_lr: f64) -> Self {
+        Self {
+            config,
+            base_lr,
+            n_steps: 0,
+        }
+    }
+
+    /// Returns the learning rate at the current step.
+    pub fn lr(&self) -> f64 {
+        self.config.lr(self.base_lr, self.n_steps)
+    }
+
+    /// Advances the schedule by an optimization step and returns the new learning rate.
+    pub fn step(&mut self) -> f64 {
+        self.n_steps += 1;
+        self.lr()
+    }
+
+    /// Sets the base learning rate and returns the learning rate at the current step.
+    pub fn set_base_lr(&mut self, base_lr: f64) -> f64 {
+        self.base_lr = base_lr;
+        self.lr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schedule() {
+        let s = Schedule::Exponential {
+            start: 1.0,
+            end: 0.0,
+            rate: 0.5,
+        };
+        assert_eq!(s.value(0), 1.0);
+        assert_eq!(s.value(2), 0.25);
+
+        let s = Schedule::piecewise(vec![(10, 1.0), (20, 0.0), (40, 0.5)]).unwrap();
+        assert_eq!(s.value(0), 1.0);
+        assert_eq!(s.value(15), 0.5);
+        assert_eq!(s.value(30), 0.25);
+        assert_eq!(s.value(100), 0.5);
+    }
+
+    #[test]
+    fn test_scheduler_lr() {
+        let assert_close = |actual: f64, expected: f64| {
+            assert!(
+                (actual - expected).abs() < 1e-12,
+                "{} is not close to {}",
+                actual,
+                expected
+            )
+        };
+
+        let config = SchedulerConfig::linear(10, 0.0).warmup(5);
+        assert_close(config.lr(1.0, 0), 0.2);
+        assert_close(config.lr(1.0, 4), 1.0);
+        assert_close(config.lr(1.0, 10), 0.5);
+        assert_close(config.lr(1.0, 100), 0.0);
+
+        let config = SchedulerConfig::cosine(10, 0.0);
+        assert_close(config.lr(1.0, 5), 0.5);
+        assert_close(config.lr(1.0, 10), 0.0);
+
+        let config = SchedulerConfig::step(10, 0.5);
+        assert_close(config.lr(1.0, 9), 1.0);
+        assert_close(config.lr(1.0, 25), 0.25);
+
+        let mut scheduler = LrScheduler::new(SchedulerConfig::step(2, 0.5), 1.0);
+        assert_close(scheduler.lr(), 1.0);
+        scheduler.step();
+        assert_close(scheduler.step(), 0.5);
+        assert_close(scheduler.set_base_lr(0.1), 0.05);
+    }
+
+    #[test]
+    fn test_invalid_piecewise() {
+        assert!(Schedule::piecewise(vec![]).is_err());
+        assert!(Schedule::piecewise(vec![(10, 1.0), (10, 0.0)]).is_err());
+        assert!(Schedule::piecewise(vec![(20, 1.0), (10, 0.0)]).is_err());
+        assert!(serde_yaml::from_str::<Schedule>("Piecewise: []").is_err());
+        assert!(serde_yaml::from_str::<Schedule>("Piecewise: [[10, 1.0], [5, 0.0]]").is_err());
+        assert_eq!(
+            serde_yaml::from_str::<Schedule>("Piecewise: [[10, 1.0]]").unwrap(),
+            Schedule::Piecewise(vec![(10, 1.0)])
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! Environment shared by tests of wrappers of environments.
+use crate::{
+    record::Record, Act, Env, Info, NormalizableObs, Obs, SplitAct, StackFrames, StackObs, Step,
+};
+use anyhow::Result;
+
+/// Observations of [`CountEnv`], a row for each environment.
+///
+/// A row consists of the step count, followed by the constant of the configuration if any.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountObs(pub Vec<Vec<f32>>);
+
+impl CountObs {
+    /// Returns the first element of each row, i.e., the step counts of environments.
+    pub fn counts(&self) -> Vec<f32> {
+        self.0.iter().map(|row| row[0]).collect()
+    }
+}
+
+impl Obs for CountObs {
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl StackObs for CountObs {
+    fn stack(obs: Vec<Self>) -> Self {
+        Self(obs.into_iter().flat_map(|o| o.0).collect())
+    }
+}
+
+impl StackFrames for CountObs {
+    /// Concatenates the rows of frames regardless of the axis.
+    fn concat_frames(frames: &[Self], _axis: usize) -> Self {
+        let rows = (0..frames[0].0.len())
+            .map(|i| frames.iter().flat_map(|f| f.0[i].clone()).collect())
+            .collect();
+        Self(rows)
+    }
+}
+
+impl NormalizableObs for CountObs {
+    fn values(&self) -> Vec<f32> {
+        self.0.concat()
+    }
+
+    fn with_values(&self, values: Vec<f32>) -> Self {
+        let dim = values.len() / self.0.len();
+        Self(values.chunks(dim).map(|row| row.to_vec()).collect())
+    }
+}
+
+/// Actions of [`CountEnv`], a value for each environment, which is given as reward.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CountAct(pub Vec<usize>);
+
+impl Act for CountAct {}
+
+impl SplitAct for CountAct {
+    fn split(&self, n: usize) -> Vec<Self> {
+        assert_eq!(self.0.len(), n);
+        self.0.iter().map(|a| Self(vec![*a])).collect()
+    }
+
+    fn stack(acts: Vec<Self>) -> Self {
+        Self(acts.into_iter().flat_map(|a| a.0).collect())
+    }
+}
+
+/// Info of [`CountEnv`].
+pub struct CountInfo {
+    /// Whether a life is lost at the step.
+    pub is_life_lost: bool,
+}
+
+impl Info for CountInfo {
+    fn is_life_lost(&self) -> Option<Vec<i8>> {
+        Some(vec![self.is_life_lost as i8])
+    }
+}
+
+/// Configuration of [`CountEnv`].
+#[derive(Clone, Debug, Default)]
+pub struct CountEnvConfig {
+    /// Episodes are terminated after `length + seed` steps, never if `None`.
+    pub length: Option<usize>,
+
+    /// Constant appended to observations.
+    pub constant: Option<f32>,
+
+    /// Episodes are truncated after `max_steps` steps, never if `None`.
+    pub max_steps: Option<usize>,
+
+    /// If `true`, a life is lost at every step.
+    pub lose_life: bool,
+}
+
+impl CountEnvConfig {
+    /// Sets the length of episodes.
+    pub fn length(mut self, v: usize) -> Self {
+        self.length = Some(v);
+        self
+    }
+
+    /// Sets the constant appended to observations.
+    pub fn constant(mut self, v: f32) -> Self {
+        self.constant = Some(v);
+        self
+    }
+
+    /// Sets the maximum number of steps in an episode.
+    pub fn max_steps(mut self, v: usize) -> Self {
+        self.max_steps = Some(v);
+        self
+    }
+
+    /// Sets whether a life is lost at every step.
+    pub fn lose_life(mut self, v: bool) -> Self {
+        self.lose_life = v;
+        self
+    }
+}
+
+/// Counts steps, returning the count as observation and the action as reward.
+///
+/// The record of a step has the count as `count`. Termination and truncation depend on
+/// the number of steps from the last reset, not on the count.
+pub struct CountEnv {
+    config: CountEnvConfig,
+    count: usize,
+    n_steps: usize,
+    length: Option<usize>,
+}
+
+impl CountEnv {
+    fn obs(&self) -> CountObs {
+        let row = std::iter::once(self.count as f32)
+            .chain(self.config.constant)
+            .collect();
+        CountObs(vec![row])
+    }
+}
+
+impl Env for CountEnv {
+    type Config = CountEnvConfig;
+    type Obs = CountObs;
+    type Act = CountAct;
+    type Info = CountInfo;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        Ok(Self {
+            config: config.clone(),
+            count: 0,
+            n_steps: 0,
+            length: config.length.map(|l| l + seed as usize),
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.count += 1;
+        self.n_steps += 1;
+        let is_terminated = Some(self.n_steps) == self.length;
+        let is_truncated = !is_terminated && Some(self.n_steps) == self.config.max_steps;
+        let info = CountInfo {
+            is_life_lost: self.config.lose_life,
+        };
+        let step = Step::new(
+            self.obs(),
+            a.clone(),
+            vec![a.0[0] as f32],
+            vec![is_terminated as i8],
+            vec![is_truncated as i8],
+            info,
+            None,
+        );
+        (step, Record::from_scalar("count", self.count as f32))
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        self.count = 0;
+        self.n_steps = 0;
+        Ok(self.obs())
+    }
+
+    /// Returns actions of values from 0 to `n_random`.
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        (0..=n_random).map(|a| CountAct(vec![a])).collect()
+    }
+
+    /// Starts the episode from count `ix`.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.count = ix;
+        self.n_steps = 0;
+        Ok(self.obs())
+    }
+}
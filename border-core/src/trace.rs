@@ -0,0 +1,35 @@
+//! Macros for instrumentation with the `tracing` crate.
+//!
+//! With the `tracing` feature, `trace_span!` enters a span of the `tracing` crate until the
+//! returned guard is dropped and `trace_event!` emits an event. Without the feature, they
+//! expand to nothing, so the instrumentation costs nothing.
+
+/// Enters an info-level span, returning a guard that exits the span when dropped.
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        tracing::info_span!($($args)*).entered()
+    };
+}
+
+/// Enters an info-level span, returning a guard that exits the span when dropped.
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($($args:tt)*) => {
+        ()
+    };
+}
+
+/// Emits an info-level event.
+#[cfg(feature = "tracing")]
+macro_rules! trace_event {
+    ($($args:tt)*) => {
+        tracing::info!($($args)*)
+    };
+}
+
+/// Emits an info-level event.
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_event {
+    ($($args:tt)*) => {};
+}
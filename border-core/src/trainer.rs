@@ -4,19 +4,105 @@
 //! learning agents. It handles environment interactions, experience collection,
 //! optimization steps, and evaluation.
 
+mod best_model;
 mod config;
+mod hot_reload;
 mod sampler;
-use std::time::{Duration, SystemTime};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
 
 use crate::{
-    record::{Record, RecordValue::Scalar, Recorder},
-    Agent, Env, Evaluator, ExperienceBufferBase, ReplayBufferBase, StepProcessor,
+    probe_env,
+    record::{
+        Record,
+        RecordValue::{self, Scalar},
+        Recorder,
+    },
+    Agent, Env, EvalResult, Evaluator, ExperienceBufferBase, ProbeConfig, ReplayBufferBase,
+    StepProcessor,
 };
 use anyhow::Result;
+use best_model::BestModelSelector;
+pub use best_model::{BestModelConfig, BestModelCriterion, ModelMetadata};
 pub use config::TrainerConfig;
-use log::info;
+use hot_reload::HotReloader;
+pub use hot_reload::{HotReloadConfig, HOT_RELOADABLE};
+use log::{info, warn};
 pub use sampler::Sampler;
 
+/// A custom rule for stopping training, called after each evaluation.
+///
+/// It takes the number of optimization steps, the performance returned by the evaluator and
+/// the record of the optimization step including the evaluation, and returns the reason to stop
+/// training, or `None` to continue.
+pub type StoppingRule = Box<dyn FnMut(usize, f32, &Record) -> Option<String>>;
+
+type AgentBox<E, R> = Box<dyn Agent<E, R>>;
+
+/// Agents trained by a trainer, a single agent or agents keyed by their IDs.
+///
+/// It lets trainers of multiple agents share the evaluation, the model selection, early
+/// stopping and the handling of failures with [`Trainer`]. Agents keyed by IDs are saved
+/// under `<id>/<base>`.
+pub(crate) trait TrainedAgents<E: Env, R: ReplayBufferBase> {
+    /// Returns the directories under `base` and the agents saved in them.
+    fn with_bases(&self, base: &Path) -> Vec<(PathBuf, &AgentBox<E, R>)>;
+
+    /// Sets a hyperparameter of all agents, returning `false` if any of them does not have it.
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool;
+
+    /// Sets all agents to training mode.
+    fn train(&mut self);
+
+    /// Sets all agents to evaluation mode.
+    fn eval(&mut self);
+}
+
+impl<E: Env, R: ReplayBufferBase> TrainedAgents<E, R> for Box<dyn Agent<E, R>> {
+    fn with_bases(&self, base: &Path) -> Vec<(PathBuf, &AgentBox<E, R>)> {
+        vec![(base.to_path_buf(), self)]
+    }
+
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool {
+        self.as_mut().set_hyperparam(name, value)
+    }
+
+    fn train(&mut self) {
+        self.as_mut().train()
+    }
+
+    fn eval(&mut self) {
+        self.as_mut().eval()
+    }
+}
+
+impl<E: Env, R: ReplayBufferBase> TrainedAgents<E, R> for BTreeMap<String, Box<dyn Agent<E, R>>> {
+    fn with_bases(&self, base: &Path) -> Vec<(PathBuf, &AgentBox<E, R>)> {
+        self.iter()
+            .map(|(id, agent)| (Path::new(id).join(base), agent))
+            .collect()
+    }
+
+    fn set_hyperparam(&mut self, name: &str, value: f64) -> bool {
+        let mut applied = true;
+        for agent in self.values_mut() {
+            applied &= agent.set_hyperparam(name, value);
+        }
+        applied
+    }
+
+    fn train(&mut self) {
+        self.values_mut().for_each(|agent| agent.train());
+    }
+
+    fn eval(&mut self) {
+        self.values_mut().for_each(|agent| agent.eval());
+    }
+}
+
 /// Manages the training loop and coordinates interactions between components.
 ///
 /// The `Trainer` orchestrates the training process by managing:
@@ -53,16 +139,27 @@ pub use sampler::Sampler;
 ///    * Save model checkpoints
 ///    * Monitor optimization speed
 ///
+/// 5. Shutdown:
+///    * At the end of training, the environments of the sampler and the evaluator are
+///      closed with [`Env::close()`] and [`Evaluator::close()`]
+///
 /// # Model Selection
 ///
-/// During training, the best performing model is automatically saved based on evaluation rewards:
+/// During training, the best performing model is automatically saved based on evaluations:
 ///
 /// * At each evaluation interval (`eval_interval`), the agent's performance is evaluated
-/// * The evaluation reward is obtained from `Record::get_scalar_without_key()`
-/// * If the current evaluation reward exceeds the previous maximum reward:
+/// * The evaluation reward is the performance metric returned by the evaluator
+/// * The statistics of evaluation episodes, such as the standard deviation of returns and
+///   the success rate, are recorded with those in the record of the evaluator
+///   (see [`EvalReport`](crate::EvalReport))
+/// * The metric of [`BestModelCriterion`], the evaluation reward by default, is computed
+///   from the evaluation. Other criteria are the success rate, a scalar of the record
+///   such as the loss, and the moving average of evaluation rewards
+/// * If the metric is better than the previous best one:
 ///   * The model is saved as the "best" model
-///   * The maximum reward is updated
-/// * This ensures that the saved "best" model represents the agent's peak performance
+///   * [`ModelMetadata`] with the metric is saved along with the model
+/// * If `keep_top_k` of [`BestModelConfig`] is more than 1, the top-k models are also kept
+///   as "best_<opt_steps>", and the models falling out of them are removed
 ///
 /// # Configuration
 ///
@@ -73,6 +170,39 @@ pub use sampler::Sampler;
 /// * `save_interval`: Steps between model checkpoints
 /// * `warmup_period`: Initial steps before optimization begins
 /// * `max_opts`: Maximum number of optimization steps
+///
+/// # Hot-Reload of Hyperparameters
+///
+/// If `hot_reload` is set in the configuration, the trainer watches a control file and applies
+/// changes of some hyperparameters, like the learning rate, without restarting training.
+/// See [`HotReloadConfig`] for details.
+///
+/// # Early Stopping
+///
+/// If `eval_threshold` is set in the configuration, training stops when the performance returned
+/// by the evaluator exceeds the threshold in `eval_threshold_patience` consecutive evaluations.
+/// Custom rules can be given with [`Trainer::stopping_rule()`]. The reason of stopping is
+/// recorded as `Stopping reason` and records are flushed before training ends.
+///
+/// # Failure of Optimization Steps
+///
+/// Optimization steps are run with [`Agent::try_opt()`] and [`Agent::try_opt_with_record()`].
+/// If a step fails, e.g., by out of memory of a GPU, the trainer saves the model as `aborted`,
+/// flushes records and returns the error with the optimization step.
+///
+/// # Evaluation with Fixed Seeds
+///
+/// If `eval_seeds` is set in the configuration, every evaluation runs an episode for each of the
+/// given indices with [`Evaluator::evaluate_with_indices()`], which resets the environment
+/// deterministically through [`Env::reset_with_index()`]. The return of each index is recorded
+/// as `Episode return (seed i)`, along with the spread of returns like `Episode return std`.
+///
+/// # Probe of the Environment
+///
+/// If `probe` is set in the configuration, [`Trainer::train()`] first applies the actions
+/// returned by [`Env::probe_actions()`] to the environment, and fails with a report of the
+/// problems, like non-finite rewards and observations of unexpected shapes, before training.
+/// See [`ProbeConfig`] for details.
 pub struct Trainer {
     /// Interval between optimization steps in environment steps.
     /// Ignored for offline training.
@@ -112,14 +242,44 @@ pub struct Trainer {
     /// Timer for optimization steps.
     timer_for_opt_steps: Duration,
 
-    /// Maximum evaluation reward achieved.
-    max_eval_reward: f32,
+    /// Selector of the best models.
+    best_model: BestModelSelector,
 
     /// Current environment step count.
     env_steps: usize,
 
     /// Current optimization step count.
     opt_steps: usize,
+
+    /// Configuration of hot-reload, from which the watcher is created.
+    hot_reload: Option<HotReloadConfig>,
+
+    /// Watcher of the control file for hot-reload of hyperparameters.
+    hot_reloader: Option<HotReloader>,
+
+    /// Threshold of the evaluation performance for early stopping.
+    eval_threshold: Option<f32>,
+
+    /// Number of consecutive evaluations exceeding the threshold to stop training.
+    eval_threshold_patience: usize,
+
+    /// Number of consecutive evaluations exceeding the threshold so far.
+    n_evals_above_threshold: usize,
+
+    /// Custom rule for stopping training.
+    stopping_rule: Option<StoppingRule>,
+
+    /// Reason of stopping training before `max_opts`.
+    stop_reason: Option<String>,
+
+    /// Configuration of the probe of the environment.
+    probe: Option<ProbeConfig>,
+
+    /// Result of the last evaluation.
+    last_eval_result: Option<EvalResult>,
+
+    /// Indices of episodes run in every evaluation.
+    eval_seeds: Option<Vec<usize>>,
 }
 
 impl Trainer {
@@ -146,10 +306,137 @@ impl Trainer {
             timer_for_samples: Duration::new(0, 0),
             opt_steps_counter: 0,
             timer_for_opt_steps: Duration::new(0, 0),
-            max_eval_reward: f32::MIN,
+            best_model: BestModelSelector::new(config.best_model),
             env_steps: 0,
             opt_steps: 0,
+            hot_reload: config.hot_reload,
+            hot_reloader: None,
+            eval_threshold: config.eval_threshold,
+            eval_threshold_patience: config.eval_threshold_patience,
+            n_evals_above_threshold: 0,
+            stopping_rule: None,
+            stop_reason: None,
+            probe: config.probe,
+            last_eval_result: None,
+            eval_seeds: config.eval_seeds,
+        }
+    }
+
+    /// Sets a custom rule for stopping training, called after each evaluation.
+    ///
+    /// # Examples
+    ///
+    /// ```ignore
+    /// // Stop when the performance does not reach 100 by 10000 optimization steps
+    /// let trainer = Trainer::build(config).stopping_rule(Box::new(|opt_steps, score, _record| {
+    ///     (opt_steps >= 10000 && score < 100.0).then(|| "Too slow progress".to_string())
+    /// }));
+    /// ```
+    pub fn stopping_rule(mut self, f: StoppingRule) -> Self {
+        self.stopping_rule = Some(f);
+        self
+    }
+
+    /// Returns the result of the last evaluation, including the records of individual episodes.
+    pub fn last_eval_result(&self) -> Option<&EvalResult> {
+        self.last_eval_result.as_ref()
+    }
+
+    /// Returns the reason of stopping training before `max_opts`, if stopped.
+    pub fn stop_reason(&self) -> Option<&str> {
+        self.stop_reason.as_deref()
+    }
+
+    /// Returns the record of the return of each episode index in an evaluation.
+    fn seed_record(result: &EvalResult) -> Record {
+        let mut record = Record::empty();
+        for episode in result.episodes.iter() {
+            if let (Ok(ix), Ok(r)) = (
+                episode.get_scalar("Episode index"),
+                episode.get_scalar("Episode return"),
+            ) {
+                record.insert(
+                    format!("Episode return (seed {})", ix as usize),
+                    RecordValue::Scalar(r),
+                );
+            }
+        }
+        record
+    }
+
+    /// Returns the number of optimization steps done so far.
+    pub(crate) fn opt_steps(&self) -> usize {
+        self.opt_steps
+    }
+
+    /// Counts an environment step.
+    pub(crate) fn env_step(&mut self) {
+        self.env_steps += 1;
+    }
+
+    /// Returns `true` if records should be flushed after an optimization step.
+    pub(crate) fn is_flush_step(&self) -> bool {
+        (self.opt_steps - 1) % self.flush_records_interval == 0 || self.stop_reason.is_some()
+    }
+
+    /// Returns `true` if training reached `max_opts` or is stopped early.
+    pub(crate) fn is_finished(&self) -> bool {
+        self.opt_steps == self.max_opts || self.stop_reason.is_some()
+    }
+
+    /// Closes environments after a failed optimization step, logging errors of closing as
+    /// warnings, so that the error of the step is returned by [`Trainer::abort()`].
+    pub(crate) fn close_after_failure(results: impl IntoIterator<Item = Result<()>>) {
+        for result in results {
+            if let Err(e) = result {
+                warn!("Failed to close the environment after the failure: {:#}", e);
+            }
+        }
+    }
+
+    /// Saves the models as `aborted` and flushes records after a failed optimization step,
+    /// returning the error with the step.
+    pub(crate) fn abort<E, R, A>(
+        &self,
+        agents: &A,
+        recorder: &mut Box<dyn Recorder<E, R>>,
+        err: anyhow::Error,
+    ) -> anyhow::Error
+    where
+        E: Env,
+        R: ReplayBufferBase,
+        A: TrainedAgents<E, R>,
+    {
+        let step = self.opt_steps + 1;
+        log::error!("Optimization step {} failed: {:#}", step, err);
+        for (base, agent) in agents.with_bases("aborted".as_ref()) {
+            if let Err(e) = recorder.save_model(&base, agent) {
+                warn!("Failed to save the model after the failure: {}", e);
+            }
+        }
+        recorder.flush(self.opt_steps as _);
+        err.context(format!("Training aborted at optimization step {}", step))
+    }
+
+    /// Returns the reason to stop training after an evaluation, if any.
+    fn stopping_reason(&mut self, score: f32, record: &Record) -> Option<String> {
+        if let Some(threshold) = self.eval_threshold {
+            match score > threshold {
+                true => self.n_evals_above_threshold += 1,
+                false => self.n_evals_above_threshold = 0,
+            }
+            if self.n_evals_above_threshold >= self.eval_threshold_patience {
+                return Some(format!(
+                    "Evaluation performance exceeded {} in {} consecutive evaluations",
+                    threshold, self.n_evals_above_threshold
+                ));
+            }
         }
+
+        let opt_steps = self.opt_steps;
+        self.stopping_rule
+            .as_mut()
+            .and_then(|f| f(opt_steps, score, record))
     }
 
     /// Resets the counters.
@@ -203,32 +490,74 @@ impl Trainer {
         E: Env,
         R: ReplayBufferBase,
     {
-        if self.env_steps < self.warmup_period {
-            Ok((Record::empty(), false))
-        } else if self.env_steps % self.opt_interval != 0 {
+        self.opt_step(|with_record| match with_record {
+            true => agent.try_opt_with_record(buffer),
+            false => agent.try_opt(buffer).map(|_| Record::empty()),
+        })
+    }
+
+    /// Performs an optimization step with `opt` if it is due at the current environment step.
+    ///
+    /// `opt` takes whether the record of agents is requested in the step.
+    pub(crate) fn opt_step(
+        &mut self,
+        opt: impl FnOnce(bool) -> Result<Record>,
+    ) -> Result<(Record, bool)> {
+        if self.env_steps < self.warmup_period || self.env_steps % self.opt_interval != 0 {
             // skip optimization step
-            Ok((Record::empty(), false))
-        } else if (self.opt_steps + 1) % self.record_agent_info_interval == 0 {
-            // Do optimization step with record
-            let timer = SystemTime::now();
-            let record_agent = agent.opt_with_record(buffer);
-            self.opt_steps += 1;
-            self.timer_for_opt_steps += timer.elapsed()?;
-            self.opt_steps_counter += 1;
-            Ok((record_agent, true))
-        } else {
-            // Do optimization step without record
-            let timer = SystemTime::now();
-            agent.opt(buffer);
-            self.opt_steps += 1;
-            self.timer_for_opt_steps += timer.elapsed()?;
-            self.opt_steps_counter += 1;
-            Ok((Record::empty(), true))
+            return Ok((Record::empty(), false));
         }
+
+        let with_record = (self.opt_steps + 1) % self.record_agent_info_interval == 0;
+        let _span = trace_span!(
+            "opt_step",
+            opt_steps = self.opt_steps + 1,
+            record = with_record
+        );
+        let timer = SystemTime::now();
+        let record = opt(with_record)?;
+        self.opt_steps += 1;
+        self.timer_for_opt_steps += timer.elapsed()?;
+        self.opt_steps_counter += 1;
+        Ok((record, true))
+    }
+
+    /// Applies hyperparameters changed in the control file.
+    fn hot_reload<E, R, A>(&mut self, agents: &mut A, record: &mut Record) -> Result<()>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+        A: TrainedAgents<E, R>,
+    {
+        // The control file is watched from the first optimization step
+        if let Some(config) = self.hot_reload.take() {
+            self.hot_reloader = Some(HotReloader::new(config)?);
+        }
+        let changes = match self.hot_reloader.as_mut() {
+            Some(hot_reloader) => hot_reloader.poll(self.opt_steps)?,
+            None => return Ok(()),
+        };
+
+        for (name, value) in changes.into_iter() {
+            let applied = match name.as_str() {
+                "eval_interval" if value >= 1.0 => {
+                    self.eval_interval = value as usize;
+                    true
+                }
+                "eval_interval" => false,
+                _ => agents.set_hyperparam(&name, value),
+            };
+            match applied {
+                true => record.insert(format!("hyperparams/{}", name), Scalar(value as f32)),
+                false => warn!("Failed to set {} = {}", name, value),
+            }
+        }
+
+        Ok(())
     }
 
     /// Evaluates the agent and saves the best model.
-    fn post_process<E, R, D>(
+    pub(crate) fn post_process<E, R, D>(
         &mut self,
         agent: &mut Box<dyn Agent<E, R>>,
         evaluator: &mut D,
@@ -240,24 +569,90 @@ impl Trainer {
         R: ReplayBufferBase,
         D: Evaluator<E>,
     {
+        self.post_process_with(agent, recorder, record, |agent, seeds| match seeds {
+            Some(seeds) => evaluator.evaluate_with_indices(agent, seeds),
+            None => evaluator.evaluate_result(agent),
+        })
+    }
+
+    /// Applies hot-reload, evaluates the agents with `evaluate` and saves the models after
+    /// an optimization step.
+    ///
+    /// `evaluate` takes the indices of episodes if `eval_seeds` is set. The best models are
+    /// chosen and early stopping is decided with the performance it returns.
+    pub(crate) fn post_process_with<E, R, A>(
+        &mut self,
+        agents: &mut A,
+        recorder: &mut Box<dyn Recorder<E, R>>,
+        record: &mut Record,
+        evaluate: impl FnOnce(&mut A, Option<&[usize]>) -> Result<EvalResult>,
+    ) -> Result<()>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+        A: TrainedAgents<E, R>,
+    {
+        // Hot-reload of hyperparameters
+        self.hot_reload(agents, record)?;
+
         // Evaluation
-        if self.opt_steps % self.eval_interval == 0 {
+        if (self.eval_interval > 0) && (self.opt_steps % self.eval_interval == 0) {
             info!("Starts evaluation of the trained model");
-            agent.eval();
-            let (score, record_eval) = evaluator.evaluate(agent)?;
-            agent.train();
-            record.merge_inplace(record_eval);
-
-            // Save the best model up to the current iteration
-            if score > self.max_eval_reward {
-                self.max_eval_reward = score;
-                recorder.save_model("best".as_ref(), agent)?;
+            let result = {
+                let _span = trace_span!("evaluate", opt_steps = self.opt_steps);
+                agents.eval();
+                let result = evaluate(agents, self.eval_seeds.as_deref())?;
+                agents.train();
+                result
+            };
+            let score = result.score;
+            trace_event!(opt_steps = self.opt_steps, score, "evaluation");
+            record.merge_inplace(result.report().record());
+            record.merge_inplace(result.record.clone());
+            if self.eval_seeds.is_some() {
+                record.merge_inplace(Self::seed_record(&result));
+            }
+            self.last_eval_result = Some(result);
+
+            // Save the best models up to the current iteration
+            if let Some(selection) = self.best_model.update(self.opt_steps, score, record) {
+                let _span = trace_span!("save_model", opt_steps = self.opt_steps, best = true);
+                let mut bases = vec![];
+                if selection.is_best {
+                    bases.push("best".to_string());
+                }
+                if selection.is_top_k {
+                    bases.push(format!("best_{}", self.opt_steps));
+                }
+                for base in bases.iter() {
+                    for (base, agent) in agents.with_bases(base.as_ref()) {
+                        recorder.save_model(&base, agent)?;
+                        recorder.save_model_metadata(&base, &selection.metadata)?;
+                    }
+                }
+                for opt_steps in selection.removed {
+                    let base = format!("best_{}", opt_steps);
+                    for (base, _) in agents.with_bases(base.as_ref()) {
+                        recorder.remove_model(&base)?;
+                    }
+                }
+            }
+
+            // Early stopping
+            if let Some(reason) = self.stopping_reason(score, record) {
+                info!("Stops training: {}", reason);
+                record.insert("Stopping reason", RecordValue::String(reason.clone()));
+                self.stop_reason = Some(reason);
             }
         };
 
         // Save the current model
         if (self.save_interval > 0) && (self.opt_steps % self.save_interval == 0) {
-            recorder.save_model(format!("{}", self.opt_steps).as_ref(), agent)?;
+            let _span = trace_span!("save_model", opt_steps = self.opt_steps, best = false);
+            let base = format!("{}", self.opt_steps);
+            for (base, agent) in agents.with_bases(base.as_ref()) {
+                recorder.save_model(&base, agent)?;
+            }
         }
 
         Ok(())
@@ -279,6 +674,15 @@ impl Trainer {
         R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
         D: Evaluator<E>,
     {
+        let _span = trace_span!("train", max_opts = self.max_opts);
+        let mut env = env;
+        if let Some(config) = &self.probe {
+            let report = probe_env(&mut env, config)?;
+            if !report.is_ok() {
+                anyhow::bail!("{}", report);
+            }
+            info!("Probed the environment in {} steps", report.n_steps);
+        }
         let mut sampler = Sampler::new(env, step_proc);
         agent.train();
 
@@ -291,9 +695,12 @@ impl Trainer {
             self.env_steps += 1;
 
             // Performe optimization step(s)
-            let (mut record, is_opt) = {
-                let (r, is_opt) = self.train_step(agent, buffer)?;
-                (record.merge(r), is_opt)
+            let (mut record, is_opt) = match self.train_step(agent, buffer) {
+                Ok((r, is_opt)) => (record.merge(r), is_opt),
+                Err(err) => {
+                    Self::close_after_failure([sampler.close(), evaluator.close()]);
+                    return Err(self.abort(agent, recorder, err));
+                }
             };
 
             // Postprocessing after each training step
@@ -309,18 +716,30 @@ impl Trainer {
                 self.reset_counters();
             }
 
+            // Record the state of the replay buffer, like its memory usage and sampled batches
+            if is_opt && self.opt_steps % self.record_compute_cost_interval == 0 {
+                record = record.merge(buffer.record());
+            }
+
             // Store record to the recorder
             if !record.is_empty() {
                 recorder.store(record);
             }
 
-            // Flush records
-            if is_opt && ((self.opt_steps - 1) % self.flush_records_interval == 0) {
+            // Flush records, including the statistics of steps in the interval
+            if is_opt && self.is_flush_step() {
+                let _span = trace_span!("recorder_flush", opt_steps = self.opt_steps);
+                let record = sampler.step_processor_record();
+                if !record.is_empty() {
+                    recorder.store(record);
+                }
                 recorder.flush(self.opt_steps as _);
             }
 
             // Finish training
-            if self.opt_steps == self.max_opts {
+            if self.is_finished() {
+                sampler.close()?;
+                evaluator.close()?;
                 return Ok(());
             }
         }
@@ -336,12 +755,13 @@ impl Trainer {
     ) -> Result<()>
     where
         E: Env,
-        R: ReplayBufferBase,
+        R: ExperienceBufferBase + ReplayBufferBase,
         D: Evaluator<E>,
     {
         // Return empty record
         self.warmup_period = 0;
         self.opt_interval = 1;
+        let _span = trace_span!("train", max_opts = self.max_opts, offline = true);
         agent.train();
 
         loop {
@@ -349,9 +769,12 @@ impl Trainer {
             self.env_steps += 1;
 
             // Performe optimization step(s)
-            let (mut record, is_opt) = {
-                let (r, is_opt) = self.train_step(agent, buffer)?;
-                (record.merge(r), is_opt)
+            let (mut record, is_opt) = match self.train_step(agent, buffer) {
+                Ok((r, is_opt)) => (record.merge(r), is_opt),
+                Err(err) => {
+                    Self::close_after_failure([evaluator.close()]);
+                    return Err(self.abort(agent, recorder, err));
+                }
             };
 
             // Postprocessing after each training step
@@ -364,6 +787,7 @@ impl Trainer {
                 let (avr_opt_time, _) = self.average_time();
                 record.insert("average_opt_time", Scalar(avr_opt_time));
                 self.reset_counters();
+                record = record.merge(buffer.record());
             }
 
             // Store record to the recorder
@@ -372,14 +796,43 @@ impl Trainer {
             }
 
             // Flush records
-            if is_opt && ((self.opt_steps - 1) % self.flush_records_interval == 0) {
+            if is_opt && self.is_flush_step() {
+                let _span = trace_span!("recorder_flush", opt_steps = self.opt_steps);
                 recorder.flush(self.opt_steps as _);
             }
 
             // Finish training
-            if self.opt_steps == self.max_opts {
+            if self.is_finished() {
+                evaluator.close()?;
                 return Ok(());
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopping_reason() {
+        let config = TrainerConfig::default()
+            .eval_threshold(1.0)
+            .eval_threshold_patience(2);
+        let mut trainer = Trainer::build(config);
+        let record = Record::empty();
+        assert!(trainer.stopping_reason(2.0, &record).is_none());
+        assert!(trainer.stopping_reason(0.0, &record).is_none());
+        assert!(trainer.stopping_reason(2.0, &record).is_none());
+        assert!(trainer.stopping_reason(2.0, &record).is_some());
+
+        let mut trainer = Trainer::build(TrainerConfig::default()).stopping_rule(Box::new(
+            |_opt_steps, score, _record| (score < 0.0).then(|| "Negative score".to_string()),
+        ));
+        assert!(trainer.stopping_reason(1.0, &record).is_none());
+        assert_eq!(
+            trainer.stopping_reason(-1.0, &record).as_deref(),
+            Some("Negative score")
+        );
+    }
+}
@@ -0,0 +1,280 @@
+//! Selection of the best models during training.
+use crate::record::Record;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+};
+
+/// Criterion of the best model chosen by [`Trainer`] at each evaluation.
+///
+/// [`Trainer`]: crate::Trainer
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq, Clone)]
+pub enum BestModelCriterion {
+    /// Maximum of the performance returned by the evaluator, e.g., the mean return of episodes.
+    #[default]
+    MaxScore,
+
+    /// Maximum of `Success rate` in the record of the evaluation.
+    ///
+    /// The success of episodes should be given to the evaluator, e.g., with
+    /// [`DefaultEvaluator::success()`].
+    ///
+    /// [`DefaultEvaluator::success()`]: crate::DefaultEvaluator::success
+    MaxSuccessRate,
+
+    /// Maximum of a scalar in the record of the optimization step with the evaluation.
+    MaxScalar(String),
+
+    /// Minimum of a scalar in the record of the optimization step with the evaluation,
+    /// e.g., `loss` of the agent.
+    ///
+    /// The record of the agent is included in the optimization steps that are multiples of
+    /// `record_agent_info_interval`, which should divide `eval_interval`.
+    MinScalar(String),
+
+    /// Maximum of the moving average of the performance over the given number of
+    /// the last evaluations, which is robust to the noise of evaluations.
+    MovingAverage(usize),
+}
+
+impl BestModelCriterion {
+    /// Returns the metric of the criterion, where a larger value is better.
+    fn key(&self, metric: f32) -> f32 {
+        match self {
+            Self::MinScalar(_) => -metric,
+            _ => metric,
+        }
+    }
+}
+
+/// Configuration of the selection of the best models in [`Trainer`].
+///
+/// The best model is saved as `best` with [`Recorder::save_model()`], along with
+/// [`ModelMetadata`] saved with [`Recorder::save_model_metadata()`]. If `keep_top_k` is
+/// more than 1, the top-k models are also kept as `best_<opt_steps>`, and the models
+/// falling out of the top-k are removed with [`Recorder::remove_model()`].
+///
+/// ```yaml
+/// best_model:
+///   criterion: !MovingAverage 5
+///   keep_top_k: 3
+/// ```
+///
+/// [`Trainer`]: crate::Trainer
+/// [`Recorder::save_model()`]: crate::record::Recorder::save_model
+/// [`Recorder::save_model_metadata()`]: crate::record::Recorder::save_model_metadata
+/// [`Recorder::remove_model()`]: crate::record::Recorder::remove_model
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct BestModelConfig {
+    /// Criterion of the best model.
+    #[serde(default)]
+    pub criterion: BestModelCriterion,
+
+    /// The number of the best models kept.
+    #[serde(default = "default_keep_top_k")]
+    pub keep_top_k: usize,
+}
+
+fn default_keep_top_k() -> usize {
+    1
+}
+
+impl Default for BestModelConfig {
+    fn default() -> Self {
+        Self {
+            criterion: BestModelCriterion::default(),
+            keep_top_k: default_keep_top_k(),
+        }
+    }
+}
+
+impl BestModelConfig {
+    /// Sets the criterion of the best model.
+    pub fn criterion(mut self, v: BestModelCriterion) -> Self {
+        self.criterion = v;
+        self
+    }
+
+    /// Sets the number of the best models kept.
+    pub fn keep_top_k(mut self, v: usize) -> Self {
+        self.keep_top_k = v;
+        self
+    }
+}
+
+/// Metadata of a model saved as one of the best models.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct ModelMetadata {
+    /// The optimization step at which the model is saved.
+    pub opt_steps: usize,
+
+    /// Criterion of the best model.
+    pub criterion: BestModelCriterion,
+
+    /// Value of the metric of the criterion triggering the save.
+    pub metric: f32,
+
+    /// Performance returned by the evaluator.
+    pub score: f32,
+}
+
+impl ModelMetadata {
+    /// Name of the file of the metadata in a model directory.
+    pub const FILE: &'static str = "metadata.yaml";
+
+    /// Loads metadata from a YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let rdr = BufReader::new(File::open(path)?);
+        Ok(serde_yaml::from_reader(rdr)?)
+    }
+
+    /// Saves metadata as a YAML file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let wtr = BufWriter::new(File::create(path)?);
+        serde_yaml::to_writer(wtr, self)?;
+        Ok(())
+    }
+}
+
+/// Models to be saved and removed after an evaluation.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Selection {
+    /// Metadata of the model.
+    pub metadata: ModelMetadata,
+
+    /// If `true`, the model is saved as `best`.
+    pub is_best: bool,
+
+    /// If `true`, the model is kept as one of the top-k models.
+    pub is_top_k: bool,
+
+    /// Optimization steps of the top-k models to be removed.
+    pub removed: Vec<usize>,
+}
+
+/// Chooses the best models with the criterion.
+pub(crate) struct BestModelSelector {
+    config: BestModelConfig,
+    scores: VecDeque<f32>,
+    best: Option<f32>,
+    top_k: Vec<(f32, usize)>,
+}
+
+impl BestModelSelector {
+    pub fn new(config: BestModelConfig) -> Self {
+        Self {
+            config,
+            scores: VecDeque::new(),
+            best: None,
+            top_k: vec![],
+        }
+    }
+
+    /// Returns the metric of the criterion, or `None` if it is not available.
+    fn metric(&mut self, score: f32, record: &Record) -> Option<f32> {
+        match &self.config.criterion {
+            BestModelCriterion::MaxScore => Some(score),
+            BestModelCriterion::MaxSuccessRate => record.get_scalar("Success rate").ok(),
+            BestModelCriterion::MaxScalar(key) | BestModelCriterion::MinScalar(key) => {
+                record.get_scalar(key).ok()
+            }
+            BestModelCriterion::MovingAverage(n) => {
+                self.scores.push_back(score);
+                while self.scores.len() > (*n).max(1) {
+                    self.scores.pop_front();
+                }
+                Some(self.scores.iter().sum::<f32>() / self.scores.len() as f32)
+            }
+        }
+    }
+
+    /// Updates the best models with an evaluation.
+    ///
+    /// Returns `None` if the metric is not available or the model is not saved.
+    pub fn update(&mut self, opt_steps: usize, score: f32, record: &Record) -> Option<Selection> {
+        let metric = match self.metric(score, record) {
+            Some(metric) if !metric.is_nan() => metric,
+            _ => {
+                log::debug!("Metric of {:?} is not available", self.config.criterion);
+                return None;
+            }
+        };
+        let key = self.config.criterion.key(metric);
+
+        let is_best = match self.best {
+            Some(best) => key > best,
+            None => true,
+        };
+        if is_best {
+            self.best = Some(key);
+        }
+
+        let mut is_top_k = false;
+        let mut removed = vec![];
+        if self.config.keep_top_k > 1 {
+            let n = self.top_k.len();
+            if n < self.config.keep_top_k || key > self.top_k[n - 1].0 {
+                is_top_k = true;
+                let ix = self.top_k.partition_point(|(k, _)| *k >= key);
+                self.top_k.insert(ix, (key, opt_steps));
+                while self.top_k.len() > self.config.keep_top_k {
+                    removed.push(self.top_k.pop().unwrap().1);
+                }
+            }
+        }
+
+        match is_best || is_top_k {
+            true => Some(Selection {
+                metadata: ModelMetadata {
+                    opt_steps,
+                    criterion: self.config.criterion.clone(),
+                    metric,
+                    score,
+                },
+                is_best,
+                is_top_k,
+                removed,
+            }),
+            false => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_best_model_selector() {
+        let record = |loss: f32| Record::from_scalar("loss", loss);
+
+        // The top-2 models with the minimum loss
+        let config = BestModelConfig::default()
+            .criterion(BestModelCriterion::MinScalar("loss".to_string()))
+            .keep_top_k(2);
+        let mut selector = BestModelSelector::new(config);
+        let s = selector.update(1, 0.0, &record(3.0)).unwrap();
+        assert!(s.is_best && s.is_top_k);
+        let s = selector.update(2, 0.0, &record(1.0)).unwrap();
+        assert!(s.is_best && s.removed.is_empty());
+        let s = selector.update(3, 0.0, &record(2.0)).unwrap();
+        assert!(!s.is_best && s.is_top_k);
+        assert_eq!(s.removed, vec![1]);
+        assert_eq!(s.metadata.metric, 2.0);
+        assert!(selector.update(4, 0.0, &record(5.0)).is_none());
+        assert!(selector.update(5, 0.0, &Record::empty()).is_none());
+
+        // Moving average of the last 2 scores
+        let config = BestModelConfig::default().criterion(BestModelCriterion::MovingAverage(2));
+        let mut selector = BestModelSelector::new(config);
+        assert!(selector.update(1, 10.0, &Record::empty()).is_some());
+        assert!(selector.update(2, 0.0, &Record::empty()).is_none());
+        let s = selector.update(3, 30.0, &Record::empty()).unwrap();
+        assert_eq!(s.metadata.metric, 15.0);
+        assert_eq!(s.metadata.score, 30.0);
+    }
+}
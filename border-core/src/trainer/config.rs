@@ -13,6 +13,8 @@
 //! * Evaluation frequency and model selection
 //! * Performance monitoring and metrics recording
 //! * Model checkpointing and warmup periods
+use super::{BestModelConfig, HotReloadConfig};
+use crate::ProbeConfig;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::{
@@ -59,6 +61,38 @@ pub struct TrainerConfig {
     /// Number of optimization steps between saving model checkpoints.
     /// These checkpoints can be used for resuming training or analysis.
     pub save_interval: usize,
+
+    /// Optional configuration of hot-reload of hyperparameters from a control file.
+    #[serde(default)]
+    pub hot_reload: Option<HotReloadConfig>,
+
+    /// Optional threshold of the evaluation performance for early stopping.
+    /// Training stops when the performance exceeds this value in
+    /// `eval_threshold_patience` consecutive evaluations.
+    #[serde(default)]
+    pub eval_threshold: Option<f32>,
+
+    /// Number of consecutive evaluations exceeding `eval_threshold` to stop training.
+    #[serde(default = "default_eval_threshold_patience")]
+    pub eval_threshold_patience: usize,
+
+    /// Configuration of the probe of the environment at the start of online training.
+    #[serde(default)]
+    pub probe: Option<ProbeConfig>,
+
+    /// Optional indices of episodes, e.g., seeds, run in every evaluation.
+    /// The evaluation environment is reset with each of them through `Env::reset_with_index()`,
+    /// and the return of each index is recorded.
+    #[serde(default)]
+    pub eval_seeds: Option<Vec<usize>>,
+
+    /// Selection of the best models saved at evaluations.
+    #[serde(default)]
+    pub best_model: BestModelConfig,
+}
+
+fn default_eval_threshold_patience() -> usize {
+    1
 }
 
 impl Default for TrainerConfig {
@@ -73,6 +107,12 @@ impl Default for TrainerConfig {
     /// * `record_agent_info_interval`: usize::MAX (never record)
     /// * `warmup_period`: 0 (no warmup)
     /// * `save_interval`: usize::MAX (never save)
+    /// * `hot_reload`: None (no hot-reload)
+    /// * `eval_threshold`: None (no early stopping)
+    /// * `eval_threshold_patience`: 1
+    /// * `probe`: None (no probe)
+    /// * `eval_seeds`: None (episodes chosen by the evaluator)
+    /// * `best_model`: The model with the maximum score kept as `best`
     fn default() -> Self {
         Self {
             max_opts: 0,
@@ -83,6 +123,12 @@ impl Default for TrainerConfig {
             record_agent_info_interval: usize::MAX,
             warmup_period: 0,
             save_interval: usize::MAX,
+            hot_reload: None,
+            eval_threshold: None,
+            eval_threshold_patience: default_eval_threshold_patience(),
+            probe: None,
+            eval_seeds: None,
+            best_model: BestModelConfig::default(),
         }
     }
 }
@@ -116,13 +162,32 @@ impl TrainerConfig {
         self
     }
 
-    /// (Deprecated) Sets the evaluation threshold.
+    /// Sets the evaluation threshold for early stopping.
     ///
-    /// This method is currently unimplemented and may be removed in future versions.
-    pub fn eval_threshold(/*mut */ self, _v: f32) -> Self {
-        unimplemented!();
-        // self.eval_threshold = Some(v);
-        // self
+    /// # Arguments
+    ///
+    /// * `v` - Threshold of the performance returned by the evaluator
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn eval_threshold(mut self, v: f32) -> Self {
+        self.eval_threshold = Some(v);
+        self
+    }
+
+    /// Sets the number of consecutive evaluations exceeding the threshold to stop training.
+    ///
+    /// # Arguments
+    ///
+    /// * `v` - Number of consecutive evaluations
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn eval_threshold_patience(mut self, v: usize) -> Self {
+        self.eval_threshold_patience = v;
+        self
     }
 
     /// Sets the interval between optimization updates.
@@ -209,6 +274,63 @@ impl TrainerConfig {
         self
     }
 
+    /// Sets the configuration of hot-reload of hyperparameters.
+    ///
+    /// # Arguments
+    ///
+    /// * `hot_reload` - Configuration of the control file, or `None` to disable hot-reload
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn hot_reload(mut self, hot_reload: Option<HotReloadConfig>) -> Self {
+        self.hot_reload = hot_reload;
+        self
+    }
+
+    /// Sets the configuration of the probe of the environment at the start of training.
+    ///
+    /// # Arguments
+    ///
+    /// * `probe` - Configuration of the probe, or `None` to disable it
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn probe(mut self, probe: Option<ProbeConfig>) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    /// Sets the indices of episodes run in every evaluation.
+    ///
+    /// # Arguments
+    ///
+    /// * `eval_seeds` - Indices of episodes, e.g., `(0..10).collect()`, or `None` to use
+    ///   the episodes chosen by the evaluator
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn eval_seeds(mut self, eval_seeds: Option<Vec<usize>>) -> Self {
+        self.eval_seeds = eval_seeds;
+        self
+    }
+
+    /// Sets the selection of the best models.
+    ///
+    /// # Arguments
+    ///
+    /// * `best_model` - Criterion of the best model and the number of the best models kept
+    ///
+    /// # Returns
+    ///
+    /// Self with the updated configuration
+    pub fn best_model(mut self, best_model: BestModelConfig) -> Self {
+        self.best_model = best_model;
+        self
+    }
+
     /// Loads configuration from a YAML file.
     ///
     /// # Arguments
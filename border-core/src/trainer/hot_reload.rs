@@ -0,0 +1,155 @@
+//! Hot-reload of hyperparameters during training.
+use anyhow::Result;
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::BufReader,
+    path::PathBuf,
+    time::SystemTime,
+};
+
+/// Names of hyperparameters that can be adjusted during training.
+pub const HOT_RELOADABLE: [&str; 3] = ["lr", "eps", "eval_interval"];
+
+/// Configuration of hot-reload of hyperparameters.
+///
+/// [`Trainer`] watches a YAML file, called a control file, and applies changes of a whitelisted
+/// set of hyperparameters to the running training loop. The control file is a flat map from
+/// the names of hyperparameters to their values:
+///
+/// ```yaml
+/// lr: 0.0001
+/// eps: 0.05
+/// eval_interval: 5000
+/// ```
+///
+/// The following hyperparameters can be adjusted (see [`HOT_RELOADABLE`]):
+/// - `lr` - Learning rate, applied with [`Agent::set_hyperparam()`]
+/// - `eps` - Exploration rate, applied with [`Agent::set_hyperparam()`]
+/// - `eval_interval` - Evaluation interval of the trainer in optimization steps
+///
+/// Other names are ignored with a warning. Every applied change is logged and stored
+/// in the record of the optimization step with the key `hyperparams/<name>`.
+///
+/// [`Trainer`]: crate::Trainer
+/// [`Agent::set_hyperparam()`]: crate::Agent::set_hyperparam
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct HotReloadConfig {
+    /// Path to the control file.
+    pub path: PathBuf,
+
+    /// Interval of checking the control file in optimization steps, which must be positive.
+    pub interval: usize,
+}
+
+impl HotReloadConfig {
+    /// Creates a configuration watching the given control file every 100 optimization steps.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            interval: 100,
+        }
+    }
+
+    /// Sets the interval of checking the control file in optimization steps.
+    pub fn interval(mut self, v: usize) -> Self {
+        self.interval = v;
+        self
+    }
+}
+
+/// Watches the control file and returns changed hyperparameters.
+pub(crate) struct HotReloader {
+    config: HotReloadConfig,
+    modified: Option<SystemTime>,
+    values: HashMap<String, f64>,
+}
+
+impl HotReloader {
+    /// Returns an error if the interval of checking the control file is zero.
+    pub fn new(config: HotReloadConfig) -> Result<Self> {
+        if config.interval == 0 {
+            anyhow::bail!("The interval of hot-reload must be positive");
+        }
+        Ok(Self {
+            config,
+            modified: None,
+            values: HashMap::new(),
+        })
+    }
+
+    /// Returns hyperparameters changed since the last call.
+    ///
+    /// The control file is read only when it has been modified. Missing files are ignored,
+    /// so that the control file can be created after training starts.
+    pub fn poll(&mut self, opt_steps: usize) -> Result<Vec<(String, f64)>> {
+        if opt_steps % self.config.interval != 0 {
+            return Ok(vec![]);
+        }
+        let modified = match std::fs::metadata(&self.config.path) {
+            Ok(metadata) => metadata.modified()?,
+            Err(_) => return Ok(vec![]),
+        };
+        if self.modified == Some(modified) {
+            return Ok(vec![]);
+        }
+        self.modified = Some(modified);
+
+        let rdr = BufReader::new(File::open(&self.config.path)?);
+        let values: BTreeMap<String, f64> = match serde_yaml::from_reader(rdr) {
+            Ok(values) => values,
+            Err(e) => {
+                warn!("Failed to parse control file {:?}: {}", self.config.path, e);
+                return Ok(vec![]);
+            }
+        };
+
+        let mut changes = vec![];
+        for (name, value) in values.into_iter() {
+            if !HOT_RELOADABLE.contains(&name.as_str()) {
+                warn!("{} cannot be changed during training, ignored", name);
+                continue;
+            }
+            if self.values.get(&name) != Some(&value) {
+                info!("Hot-reload at opt step {}: {} = {}", opt_steps, name, value);
+                self.values.insert(name.clone(), value);
+                changes.push((name, value));
+            }
+        }
+
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_hot_reloader() -> Result<()> {
+        let dir = TempDir::new("hot_reload")?;
+        let path = dir.path().join("control.yaml");
+        let mut reloader = HotReloader::new(HotReloadConfig::new(&path).interval(2))?;
+
+        // The control file does not exist yet
+        assert!(reloader.poll(0)?.is_empty());
+
+        std::fs::write(&path, "lr: 0.001\nbatch_size: 64\n")?;
+        assert!(reloader.poll(1)?.is_empty());
+        assert_eq!(reloader.poll(2)?, vec![("lr".to_string(), 0.001)]);
+
+        // Only changed values are returned
+        std::fs::write(&path, "lr: 0.001\neps: 0.1\n")?;
+        reloader.modified = None;
+        assert_eq!(reloader.poll(4)?, vec![("eps".to_string(), 0.1)]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_zero_interval() {
+        assert!(HotReloader::new(HotReloadConfig::new("control.yaml").interval(0)).is_err());
+    }
+}
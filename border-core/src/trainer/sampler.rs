@@ -81,6 +81,10 @@ where
     /// 4. Processes the resulting step
     /// 5. Stores the experience in the replay buffer
     ///
+    /// For vectorized environments, a step is taken in all environments, and the experiences
+    /// of all environments are stored as a batch. The environments where the episode has ended
+    /// are reset in [`Env::try_step_with_reset()`].
+    ///
     /// # Arguments
     ///
     /// * `agent` - The agent to sample actions from
@@ -105,6 +109,8 @@ where
         R: ExperienceBufferBase<Item = P::Output> + ReplayBufferBase,
         R_: ExperienceBufferBase<Item = R::Item>,
     {
+        let _span = trace_span!("sample");
+
         // Reset environment(s) if required
         if self.prev_obs.is_none() {
             // For a vectorized environments, reset all environments in `env`
@@ -116,9 +122,15 @@ where
 
         // Sample an action and apply it to the environment
         let (step, record, is_done) = {
-            let act = agent.sample(self.prev_obs.as_ref().unwrap());
-            let (step, record) = self.env.step_with_reset(&act);
-            let is_done = step.is_done(); // not support vectorized env
+            let act = {
+                let _span = trace_span!("policy_sample");
+                agent.sample(self.prev_obs.as_ref().unwrap())
+            };
+            let (step, record) = {
+                let _span = trace_span!("env_step");
+                self.env.try_step_with_reset(&act)?
+            };
+            let is_done = step.is_done();
             (step, record, is_done)
         };
 
@@ -134,12 +146,25 @@ where
         // Push transition
         buffer.push(transition)?;
 
-        // Reset step processor
+        // Reset step processor and notify the agent of the end of the episode
         if is_done {
             self.step_processor
                 .reset(self.prev_obs.as_ref().unwrap().clone());
+            agent.on_episode_end();
+            trace_event!("episode_end");
         }
 
         Ok(record)
     }
+
+    /// Returns the record of statistics of the steps processed since the last call,
+    /// given by [`StepProcessor::record()`].
+    pub fn step_processor_record(&mut self) -> Record {
+        self.step_processor.record()
+    }
+
+    /// Closes the environment with [`Env::close()`].
+    pub fn close(&mut self) -> Result<()> {
+        self.env.close()
+    }
 }
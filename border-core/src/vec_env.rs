@@ -0,0 +1,403 @@
+//! Vectorized environment running copies of an environment on worker threads.
+use crate::{record::Record, Act, Env, Obs, Step};
+use anyhow::{Context, Result};
+use std::{
+    sync::mpsc::{channel, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+/// Observations that can be stacked along the first axis.
+///
+/// This trait is required for the observation of environments run in [`VecEnv`].
+pub trait StackObs: Obs {
+    /// Stacks observations of environments into a single observation,
+    /// where the `i`-th element corresponds to `obs[i]`.
+    fn stack(obs: Vec<Self>) -> Self
+    where
+        Self: Sized;
+}
+
+/// Actions that can be split along the first axis.
+///
+/// This trait is required for the action of environments run in [`VecEnv`].
+pub trait SplitAct: Act {
+    /// Splits an action of `n` environments into actions of each environment.
+    fn split(&self, n: usize) -> Vec<Self>
+    where
+        Self: Sized;
+
+    /// Stacks actions of environments into a single action, the inverse of [`SplitAct::split()`].
+    fn stack(acts: Vec<Self>) -> Self
+    where
+        Self: Sized;
+}
+
+/// Configuration of [`VecEnv`].
+pub struct VecEnvConfig<E: Env> {
+    /// Configuration of each environment.
+    pub env_config: E::Config,
+
+    /// The number of environments.
+    pub n_envs: usize,
+}
+
+impl<E: Env> Clone for VecEnvConfig<E> {
+    fn clone(&self) -> Self {
+        Self {
+            env_config: self.env_config.clone(),
+            n_envs: self.n_envs,
+        }
+    }
+}
+
+impl<E: Env> VecEnvConfig<E> {
+    /// Constructs the configuration of `n_envs` environments.
+    pub fn new(env_config: E::Config, n_envs: usize) -> Self {
+        Self { env_config, n_envs }
+    }
+}
+
+/// Commands sent to worker threads.
+enum Command<A> {
+    /// Runs a step, resetting the environment if the episode ends when the flag is `true`.
+    Step(A, bool),
+
+    /// Resets the environment if the flag is `true`, otherwise returns the current observation.
+    Reset(bool),
+
+    /// Resets the environment with the index.
+    ResetWithIndex(usize),
+
+    /// Returns actions to probe the environment.
+    ProbeActions(usize),
+}
+
+/// Responses of worker threads.
+enum Response<E: Env> {
+    Step(Step<E>, Record),
+    Obs(Result<E::Obs>),
+    Acts(Vec<E::Act>),
+}
+
+/// Runs an environment until the channel of commands is closed.
+fn worker<E: Env>(mut env: E, rx: Receiver<Command<E::Act>>, tx: Sender<Response<E>>) {
+    // The current observation, returned when the environment is not reset
+    let mut obs = None;
+
+    for command in rx.iter() {
+        let response = match command {
+            Command::Step(act, with_reset) => {
+                let (step, record) = match with_reset {
+                    true => env.step_with_reset(&act),
+                    false => env.step(&act),
+                };
+                obs = Some(step.init_obs.as_ref().unwrap_or(&step.obs).clone());
+                Response::Step(step, record)
+            }
+            Command::Reset(true) => {
+                let o = env.reset(None);
+                obs = o.as_ref().ok().cloned();
+                Response::Obs(o)
+            }
+            Command::Reset(false) => Response::Obs(
+                obs.clone()
+                    .context("Environment must be reset before the first step"),
+            ),
+            Command::ResetWithIndex(ix) => {
+                let o = env.reset_with_index(ix);
+                obs = o.as_ref().ok().cloned();
+                Response::Obs(o)
+            }
+            Command::ProbeActions(n_random) => Response::Acts(env.probe_actions(n_random)),
+        };
+        if tx.send(response).is_err() {
+            break;
+        }
+    }
+}
+
+/// Runs copies of an environment on worker threads.
+///
+/// Each environment is built and run on its own thread, so `E` itself is not required to be
+/// [`Send`]. The `i`-th environment is built with seed `seed + i`.
+///
+/// Observations of the environments are stacked with [`StackObs`] and the action is split
+/// with [`SplitAct`], where the first axis is the index of environments. Rewards and flags
+/// of [`Step`] have an element for each environment, and the info is a vector of the info
+/// of the environments. Scalars in the records of the environments are averaged.
+///
+/// [`Env::reset()`] with `is_done` resets the environments where `is_done[i] == 1` and
+/// [`Env::step_with_reset()`] resets the environments where the episode has ended,
+/// so that [`Trainer`] and [`DefaultEvaluator`] can run the environments.
+///
+/// [`Trainer`]: crate::Trainer
+/// [`DefaultEvaluator`]: crate::DefaultEvaluator
+pub struct VecEnv<E: Env> {
+    txs: Vec<Sender<Command<E::Act>>>,
+    rxs: Vec<Receiver<Response<E>>>,
+    handles: Vec<JoinHandle<()>>,
+}
+
+impl<E: Env> VecEnv<E> {
+    fn recv(&self, i: usize) -> Response<E> {
+        self.rxs[i]
+            .recv()
+            .expect("Worker thread of VecEnv terminated")
+    }
+
+    fn send(&self, i: usize, command: Command<E::Act>) {
+        self.txs[i]
+            .send(command)
+            .expect("Worker thread of VecEnv terminated");
+    }
+
+    /// Sends commands to all environments and stacks the returned observations.
+    fn reset_envs(&mut self, commands: Vec<Command<E::Act>>) -> Result<E::Obs>
+    where
+        E::Obs: StackObs,
+    {
+        for (i, command) in commands.into_iter().enumerate() {
+            self.send(i, command);
+        }
+        let obs = (0..self.txs.len())
+            .map(|i| match self.recv(i) {
+                Response::Obs(obs) => obs,
+                _ => unreachable!(),
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(E::Obs::stack(obs))
+    }
+
+    /// Runs a step of all environments.
+    fn step_envs(&mut self, a: &E::Act, with_reset: bool) -> (Step<Self>, Record)
+    where
+        E::Obs: StackObs + Send + 'static,
+        E::Act: SplitAct + Send + 'static,
+        E::Info: Send + 'static,
+        E::Config: Send + 'static,
+        E: 'static,
+    {
+        let n_envs = self.txs.len();
+        let acts = a.split(n_envs);
+        assert_eq!(acts.len(), n_envs);
+        for (i, act) in acts.into_iter().enumerate() {
+            self.send(i, Command::Step(act, with_reset));
+        }
+
+        let mut obs = Vec::with_capacity(n_envs);
+        let mut init_obs = Vec::with_capacity(n_envs);
+        let mut reward = Vec::with_capacity(n_envs);
+        let mut is_terminated = Vec::with_capacity(n_envs);
+        let mut is_truncated = Vec::with_capacity(n_envs);
+        let mut info = Vec::with_capacity(n_envs);
+        let mut records = Vec::with_capacity(n_envs);
+        for i in 0..n_envs {
+            let (step, record) = match self.recv(i) {
+                Response::Step(step, record) => (step, record),
+                _ => unreachable!(),
+            };
+            reward.extend(step.reward);
+            is_terminated.extend(step.is_terminated);
+            is_truncated.extend(step.is_truncated);
+            info.push(step.info);
+            init_obs.push(step.init_obs);
+            obs.push(step.obs);
+            records.push(record);
+        }
+
+        // Observations of all environments after resetting those where the episode has ended
+        let init_obs = match init_obs.iter().any(|o| o.is_some()) {
+            true => Some(E::Obs::stack(
+                init_obs
+                    .into_iter()
+                    .zip(obs.iter())
+                    .map(|(init_obs, obs)| init_obs.unwrap_or_else(|| obs.clone()))
+                    .collect(),
+            )),
+            false => None,
+        };
+        let step = Step::new(
+            E::Obs::stack(obs),
+            a.clone(),
+            reward,
+            is_terminated,
+            is_truncated,
+            info,
+            init_obs,
+        );
+
+        (step, Record::merge_mean(records))
+    }
+}
+
+impl<E> Env for VecEnv<E>
+where
+    E: Env + 'static,
+    E::Obs: StackObs + Send + 'static,
+    E::Act: SplitAct + Send + 'static,
+    E::Info: Send + 'static,
+    E::Config: Send + 'static,
+{
+    type Config = VecEnvConfig<E>;
+    type Obs = E::Obs;
+    type Act = E::Act;
+    type Info = Vec<E::Info>;
+
+    /// Builds environments on worker threads.
+    ///
+    /// Returns an error if any of the environments fails to be built.
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let (mut txs, mut rxs, mut handles) = (vec![], vec![], vec![]);
+        let (tx_built, rx_built) = channel();
+
+        for i in 0..config.n_envs {
+            let (tx_command, rx_command) = channel();
+            let (tx_response, rx_response) = channel();
+            let env_config = config.env_config.clone();
+            let tx_built = tx_built.clone();
+            let handle = thread::Builder::new()
+                .name(format!("vec-env-{}", i))
+                .spawn(move || {
+                    let env = match E::build(&env_config, seed + i as i64) {
+                        Ok(env) => {
+                            let _ = tx_built.send(Ok(()));
+                            env
+                        }
+                        Err(e) => {
+                            let _ = tx_built.send(Err(e));
+                            return;
+                        }
+                    };
+                    drop(tx_built);
+                    worker(env, rx_command, tx_response);
+                })?;
+            txs.push(tx_command);
+            rxs.push(rx_response);
+            handles.push(handle);
+        }
+        drop(tx_built);
+
+        let env = Self { txs, rxs, handles };
+        for _ in 0..config.n_envs {
+            rx_built
+                .recv()
+                .context("Worker thread of VecEnv terminated")??;
+        }
+
+        Ok(env)
+    }
+
+    /// Runs a step of the environments.
+    ///
+    /// Environments where the episode has ended are not reset in this method.
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.step_envs(a, false)
+    }
+
+    /// Runs a step and resets the environments where the episode has ended.
+    ///
+    /// If any episode has ended, `init_obs` of the returned [`Step`] holds the observations
+    /// of all environments after the reset.
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.step_envs(a, true)
+    }
+
+    /// Resets the environments where `is_done[i] == 1`, or all environments if `is_done` is `None`.
+    ///
+    /// It returns the observations of all environments.
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        let commands = (0..self.txs.len())
+            .map(|i| Command::Reset(is_done.is_none_or(|is_done| is_done[i] == 1)))
+            .collect();
+        self.reset_envs(commands)
+    }
+
+    /// Resets all environments, where the `i`-th environment is reset with index `ix + i`.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        let commands = (0..self.txs.len())
+            .map(|i| Command::ResetWithIndex(ix + i))
+            .collect();
+        self.reset_envs(commands)
+    }
+
+    fn n_envs(&self) -> usize {
+        self.txs.len()
+    }
+
+    /// Returns actions stacking the probe actions of the environments with [`SplitAct::stack()`].
+    ///
+    /// The `k`-th action consists of the `k`-th actions of the environments. If the environments
+    /// return different numbers of actions, the extra actions are discarded.
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        let n_envs = self.txs.len();
+        for i in 0..n_envs {
+            self.send(i, Command::ProbeActions(n_random));
+        }
+        let mut acts = (0..n_envs)
+            .map(|i| match self.recv(i) {
+                Response::Acts(acts) => acts.into_iter(),
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+
+        let n = acts.iter().map(|acts| acts.len()).min().unwrap_or(0);
+        (0..n)
+            .map(|_| E::Act::stack(acts.iter_mut().map(|acts| acts.next().unwrap()).collect()))
+            .collect()
+    }
+}
+
+impl<E: Env> Drop for VecEnv<E> {
+    fn drop(&mut self) {
+        // Closing the channels stops the worker threads
+        self.txs.clear();
+        for handle in self.handles.drain(..) {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_env::{CountAct, CountEnv, CountEnvConfig};
+
+    #[test]
+    fn test_vec_env() -> Result<()> {
+        // The episode of the `i`-th environment is terminated after `i + 2` steps
+        let config = VecEnvConfig::<CountEnv>::new(CountEnvConfig::default().length(2), 3);
+        let mut env = VecEnv::build(&config, 0)?;
+        assert_eq!(env.n_envs(), 3);
+        assert_eq!(env.reset(None)?.counts(), vec![0.0, 0.0, 0.0]);
+
+        let act = CountAct(vec![1, 2, 3]);
+        let (step, record) = env.step_with_reset(&act);
+        assert_eq!(step.obs.counts(), vec![1.0, 1.0, 1.0]);
+        assert_eq!(step.reward, vec![1.0, 2.0, 3.0]);
+        assert_eq!(step.info.len(), 3);
+        assert!(step.init_obs.is_none());
+        assert_eq!(record.get_scalar("count")?, 1.0);
+
+        // The first environment is reset
+        let (step, _) = env.step_with_reset(&act);
+        assert_eq!(step.obs.counts(), vec![2.0, 2.0, 2.0]);
+        assert_eq!(step.is_done_vec(), vec![1, 0, 0]);
+        assert_eq!(step.init_obs.map(|o| o.counts()), Some(vec![0.0, 2.0, 2.0]));
+
+        // Only the second environment is reset
+        let (step, _) = env.step(&act);
+        assert_eq!(step.obs.counts(), vec![1.0, 3.0, 3.0]);
+        assert_eq!(step.is_done_vec(), vec![0, 1, 0]);
+        let obs = env.reset(Some(&step.is_done_vec()))?;
+        assert_eq!(obs.counts(), vec![1.0, 0.0, 3.0]);
+
+        let obs = env.reset_with_index(10)?;
+        assert_eq!(obs.counts(), vec![10.0, 11.0, 12.0]);
+
+        let acts = env.probe_actions(2);
+        assert_eq!(acts.len(), 3);
+        assert_eq!(acts[2], CountAct(vec![2, 2, 2]));
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+/* C API for trained policies of Border.
+ *
+ * Build the library with `cargo build --release -p border-ffi --features capi`.
+ */
+#ifndef BORDER_H
+#define BORDER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* Opaque handle of a policy. */
+typedef struct BorderPolicy BorderPolicy;
+
+/* Loads a policy from a bundle file.
+ *
+ * Returns NULL on failure; the reason is available with border_last_error().
+ * The returned handle must be released with border_policy_free().
+ */
+BorderPolicy *border_policy_load(const char *path);
+
+/* Returns the dimension of observations, or 0 if policy is NULL. */
+size_t border_policy_obs_dim(const BorderPolicy *policy);
+
+/* Returns the dimension of actions, or 0 if policy is NULL. */
+size_t border_policy_act_dim(const BorderPolicy *policy);
+
+/* Computes an action for an observation.
+ *
+ * obs_len and act_len must be equal to the dimensions of observations and actions.
+ * Returns 0 on success and a negative value on failure.
+ */
+int32_t border_policy_infer(const BorderPolicy *policy, const float *obs, size_t obs_len,
+                            float *act, size_t act_len);
+
+/* Releases a policy. Passing NULL is a no-op. */
+void border_policy_free(BorderPolicy *policy);
+
+/* Returns the message of the last error on the calling thread, or NULL if no error occurred.
+ *
+ * The string is valid until the next call of a function of this API on the same thread.
+ */
+const char *border_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* BORDER_H */
@@ -0,0 +1,139 @@
+//! Functions exported with the C ABI.
+use crate::PolicyBundle;
+use anyhow::{anyhow, Result};
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr, slice,
+};
+
+/// Opaque handle of a policy.
+pub struct BorderPolicy(PolicyBundle);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(msg: String) {
+    let msg = CString::new(msg.replace('\0', " ")).unwrap();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(msg));
+}
+
+/// Runs `f`, converting errors and panics into the last error.
+fn guard<T>(f: impl FnOnce() -> Result<T>) -> Option<T> {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(v)) => Some(v),
+        Ok(Err(e)) => {
+            set_last_error(format!("{:#}", e));
+            None
+        }
+        Err(_) => {
+            set_last_error("Panic in border-ffi".to_string());
+            None
+        }
+    }
+}
+
+/// Loads a policy from a bundle file.
+///
+/// Returns a null pointer on failure.
+///
+/// # Safety
+///
+/// `path` must be a valid pointer to a null-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn border_policy_load(path: *const c_char) -> *mut BorderPolicy {
+    guard(|| {
+        if path.is_null() {
+            return Err(anyhow!("path is null"));
+        }
+        let path = CStr::from_ptr(path).to_str()?;
+        let policy = PolicyBundle::load(path)?;
+        Ok(Box::into_raw(Box::new(BorderPolicy(policy))))
+    })
+    .unwrap_or(ptr::null_mut())
+}
+
+/// Returns the dimension of observations, or 0 if `policy` is null.
+///
+/// # Safety
+///
+/// `policy` must be null or a pointer returned by [`border_policy_load()`].
+#[no_mangle]
+pub unsafe extern "C" fn border_policy_obs_dim(policy: *const BorderPolicy) -> usize {
+    match policy.as_ref() {
+        Some(policy) => policy.0.obs_dim(),
+        None => 0,
+    }
+}
+
+/// Returns the dimension of actions, or 0 if `policy` is null.
+///
+/// # Safety
+///
+/// `policy` must be null or a pointer returned by [`border_policy_load()`].
+#[no_mangle]
+pub unsafe extern "C" fn border_policy_act_dim(policy: *const BorderPolicy) -> usize {
+    match policy.as_ref() {
+        Some(policy) => policy.0.act_dim(),
+        None => 0,
+    }
+}
+
+/// Computes an action for an observation.
+///
+/// Returns 0 on success and -1 on failure.
+///
+/// # Safety
+///
+/// `policy` must be a pointer returned by [`border_policy_load()`], `obs` must point to
+/// `obs_len` floats and `act` must point to `act_len` writable floats.
+#[no_mangle]
+pub unsafe extern "C" fn border_policy_infer(
+    policy: *const BorderPolicy,
+    obs: *const f32,
+    obs_len: usize,
+    act: *mut f32,
+    act_len: usize,
+) -> i32 {
+    let ret = guard(|| {
+        let policy = policy.as_ref().ok_or_else(|| anyhow!("policy is null"))?;
+        if obs.is_null() || act.is_null() {
+            return Err(anyhow!("obs or act is null"));
+        }
+        let obs = slice::from_raw_parts(obs, obs_len);
+        let act = slice::from_raw_parts_mut(act, act_len);
+        policy.0.infer(obs, act)
+    });
+    match ret {
+        Some(()) => 0,
+        None => -1,
+    }
+}
+
+/// Releases a policy.
+///
+/// # Safety
+///
+/// `policy` must be null or a pointer returned by [`border_policy_load()`],
+/// and must not be used after this call.
+#[no_mangle]
+pub unsafe extern "C" fn border_policy_free(policy: *mut BorderPolicy) {
+    if !policy.is_null() {
+        drop(Box::from_raw(policy));
+    }
+}
+
+/// Returns the message of the last error on the calling thread, or null.
+///
+/// The returned string is valid until the next call of a function of this API on the same thread.
+#[no_mangle]
+pub extern "C" fn border_last_error() -> *const c_char {
+    LAST_ERROR.with(|e| match e.borrow().as_ref() {
+        Some(msg) => msg.as_ptr(),
+        None => ptr::null(),
+    })
+}
@@ -0,0 +1,84 @@
+//! C API for embedding trained policies of Border.
+//!
+//! [`PolicyBundle`] loads an [`Mlp`] serialized with bincode, like `mlp.bincode` produced by
+//! the `convert_policy` example, and computes actions from observations given as `f32` slices.
+//!
+//! With the `capi` feature, this crate exports the following functions with the C ABI.
+//! Their declarations are in `include/border.h`.
+//!
+//! * `border_policy_load` - Creates a policy from a bundle file
+//! * `border_policy_obs_dim`, `border_policy_act_dim` - Dimensions of observations and actions
+//! * `border_policy_infer` - Computes an action from an observation
+//! * `border_policy_free` - Destroys a policy
+//! * `border_last_error` - Message of the last error on the calling thread
+//!
+//! The functions never unwind across the FFI boundary: panics are caught and
+//! reported as errors.
+#[cfg(feature = "capi")]
+mod capi;
+#[cfg(feature = "capi")]
+pub use capi::*;
+
+use anyhow::{ensure, Context, Result};
+use bincode::Options;
+use border_policy_no_backend::{Mat, Mlp};
+use std::{fs::File, io::BufReader, path::Path};
+
+/// A trained policy loaded from a bundle file.
+pub struct PolicyBundle {
+    mlp: Mlp,
+}
+
+impl PolicyBundle {
+    /// Loads a policy from a bundle file.
+    ///
+    /// Returns an error if the shapes of the layers are inconsistent, see [`Mlp::check()`].
+    /// The size of data read is limited by the size of the file, so a corrupted length prefix
+    /// is reported as an error before a buffer is allocated for it.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        let mlp: Mlp = bincode::options()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(len)
+            .deserialize_from(BufReader::new(file))
+            .context("Failed to read policy bundle")?;
+        mlp.check().context("Invalid policy bundle")?;
+        Ok(Self { mlp })
+    }
+
+    /// Creates a policy from an [`Mlp`].
+    pub fn from_mlp(mlp: Mlp) -> Self {
+        Self { mlp }
+    }
+
+    /// Returns the dimension of observations.
+    pub fn obs_dim(&self) -> usize {
+        self.mlp.in_dim()
+    }
+
+    /// Returns the dimension of actions.
+    pub fn act_dim(&self) -> usize {
+        self.mlp.out_dim()
+    }
+
+    /// Computes an action for an observation and writes it into `act`.
+    pub fn infer(&self, obs: &[f32], act: &mut [f32]) -> Result<()> {
+        ensure!(
+            obs.len() == self.obs_dim(),
+            "Expected an observation of length {}, got {}",
+            self.obs_dim(),
+            obs.len()
+        );
+        ensure!(
+            act.len() == self.act_dim(),
+            "Expected an action buffer of length {}, got {}",
+            self.act_dim(),
+            act.len()
+        );
+        let y = self.mlp.forward(&Mat::from(obs.to_vec()));
+        act.copy_from_slice(&y.data);
+        Ok(())
+    }
+}
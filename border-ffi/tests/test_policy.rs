@@ -0,0 +1,181 @@
+use anyhow::Result;
+use border_ffi::PolicyBundle;
+use border_policy_no_backend::Mat;
+use serde::Serialize;
+use std::path::Path;
+use tempdir::TempDir;
+
+/// Has the same layout as `Mlp` in bincode.
+#[derive(Serialize)]
+struct MlpData {
+    ws: Vec<Mat>,
+    bs: Vec<Mat>,
+}
+
+fn write_mlp(path: &Path, ws: Vec<Mat>, bs: Vec<Mat>) -> Result<()> {
+    std::fs::write(path, bincode::serialize(&MlpData { ws, bs })?)?;
+    Ok(())
+}
+
+fn mat(shape: [i32; 2]) -> Mat {
+    Mat {
+        data: vec![0.; (shape[0] * shape[1]) as usize],
+        shape: shape.to_vec(),
+    }
+}
+
+/// Writes an MLP of a single layer computing `tanh([x0 + x1, x0 - x1, 0])`.
+fn write_bundle(path: &Path) -> Result<()> {
+    let w = Mat {
+        data: vec![1., 1., 1., -1., 0., 0.],
+        shape: vec![3, 2],
+    };
+    let b = Mat {
+        data: vec![0., 0., 0.],
+        shape: vec![3, 1],
+    };
+    let mlp = MlpData {
+        ws: vec![w],
+        bs: vec![b],
+    };
+    std::fs::write(path, bincode::serialize(&mlp)?)?;
+    Ok(())
+}
+
+#[test]
+fn test_policy_bundle() -> Result<()> {
+    let dir = TempDir::new("border_ffi")?;
+    let path = dir.path().join("mlp.bincode");
+    write_bundle(&path)?;
+
+    let policy = PolicyBundle::load(&path)?;
+    assert_eq!(policy.obs_dim(), 2);
+    assert_eq!(policy.act_dim(), 3);
+
+    let mut act = [0f32; 3];
+    policy.infer(&[0.5, 0.25], &mut act)?;
+    assert_eq!(act, [0.75f32.tanh(), 0.25f32.tanh(), 0.]);
+    assert!(policy.infer(&[0.5], &mut act).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_invalid_bundle() -> Result<()> {
+    let dir = TempDir::new("border_ffi")?;
+    let path = dir.path().join("mlp.bincode");
+
+    // Two layers, 2 -> 4 -> 3
+    write_mlp(
+        &path,
+        vec![mat([4, 2]), mat([3, 4])],
+        vec![mat([4, 1]), mat([3, 1])],
+    )?;
+    assert!(PolicyBundle::load(&path).is_ok());
+
+    // Input of the second layer does not match the output of the first layer
+    write_mlp(
+        &path,
+        vec![mat([4, 2]), mat([3, 5])],
+        vec![mat([4, 1]), mat([3, 1])],
+    )?;
+    assert!(PolicyBundle::load(&path).is_err());
+
+    // Bias does not match the weight
+    write_mlp(&path, vec![mat([3, 2])], vec![mat([2, 1])])?;
+    assert!(PolicyBundle::load(&path).is_err());
+
+    // Values do not match the shape
+    let mut w = mat([3, 2]);
+    w.data.pop();
+    write_mlp(&path, vec![w], vec![mat([3, 1])])?;
+    assert!(PolicyBundle::load(&path).is_err());
+
+    // No layers
+    write_mlp(&path, vec![], vec![])?;
+    assert!(PolicyBundle::load(&path).is_err());
+    Ok(())
+}
+
+#[test]
+fn test_corrupted_bundle() -> Result<()> {
+    let dir = TempDir::new("border_ffi")?;
+    let path = dir.path().join("mlp.bincode");
+
+    // Truncated file
+    write_bundle(&path)?;
+    let bytes = std::fs::read(&path)?;
+    std::fs::write(&path, &bytes[..bytes.len() - 4])?;
+    assert!(PolicyBundle::load(&path).is_err());
+
+    // Huge length prefix of the weights
+    std::fs::write(&path, u64::MAX.to_le_bytes())?;
+    assert!(PolicyBundle::load(&path).is_err());
+    let mut bytes = 1u64.to_le_bytes().to_vec();
+    bytes.extend_from_slice(&(1u64 << 40).to_le_bytes());
+    std::fs::write(&path, bytes)?;
+    assert!(PolicyBundle::load(&path).is_err());
+    Ok(())
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn test_capi_corrupted_bundle() -> Result<()> {
+    use border_ffi::*;
+    use std::ffi::{CStr, CString};
+
+    let dir = TempDir::new("border_ffi")?;
+    let truncated = dir.path().join("truncated.bincode");
+    write_bundle(&truncated)?;
+    let bytes = std::fs::read(&truncated)?;
+    std::fs::write(&truncated, &bytes[..bytes.len() / 2])?;
+    let huge = dir.path().join("huge.bincode");
+    std::fs::write(&huge, u64::MAX.to_le_bytes())?;
+
+    for path in [truncated, huge] {
+        let path = CString::new(path.to_str().unwrap())?;
+        unsafe {
+            assert!(border_policy_load(path.as_ptr()).is_null());
+            let msg = CStr::from_ptr(border_last_error()).to_str()?;
+            assert!(msg.contains("Failed to read policy bundle"), "{}", msg);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "capi")]
+#[test]
+fn test_capi() -> Result<()> {
+    use border_ffi::*;
+    use std::ffi::{CStr, CString};
+
+    let dir = TempDir::new("border_ffi")?;
+    let path = dir.path().join("mlp.bincode");
+    write_bundle(&path)?;
+
+    unsafe {
+        let missing = CString::new(dir.path().join("missing").to_str().unwrap())?;
+        assert!(border_policy_load(missing.as_ptr()).is_null());
+        assert!(!border_last_error().is_null());
+
+        let path = CString::new(path.to_str().unwrap())?;
+        let policy = border_policy_load(path.as_ptr());
+        assert!(!policy.is_null());
+        assert_eq!(border_policy_obs_dim(policy), 2);
+        assert_eq!(border_policy_act_dim(policy), 3);
+
+        let obs = [0.5f32, 0.25];
+        let mut act = [0f32; 3];
+        let ret = border_policy_infer(policy, obs.as_ptr(), 2, act.as_mut_ptr(), 3);
+        assert_eq!(ret, 0);
+        assert!(border_last_error().is_null());
+        assert_eq!(act, [0.75f32.tanh(), 0.25f32.tanh(), 0.]);
+
+        let ret = border_policy_infer(policy, obs.as_ptr(), 2, act.as_mut_ptr(), 2);
+        assert_eq!(ret, -1);
+        let msg = CStr::from_ptr(border_last_error()).to_str()?;
+        assert!(msg.contains("action buffer"));
+
+        border_policy_free(policy);
+    }
+    Ok(())
+}
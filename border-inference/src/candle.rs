@@ -0,0 +1,153 @@
+//! Policies of agents in `border-candle-agent`.
+use crate::{PolicyModel, PolicyRunner};
+use anyhow::{Context, Result};
+use border_candle_agent::{
+    bc::{BcModel, BcModelConfig},
+    dqn::{DqnModel, DqnModelConfig},
+    model::SubModel1,
+    ppo::{CategoricalActor, CategoricalActorConfig},
+    util::{
+        actor::{GaussianActor, GaussianActorConfig},
+        find_params, OutDim,
+    },
+    Device,
+};
+use border_core::ModelManifest;
+use candle_core::Tensor;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::Path;
+
+/// Policy network taking a batch of observations.
+struct CandlePolicy<F> {
+    forward: F,
+    obs_shape: Option<Vec<usize>>,
+    device: candle_core::Device,
+}
+
+impl<F> CandlePolicy<F>
+where
+    F: FnMut(&Tensor) -> Result<Tensor> + Send + 'static,
+{
+    fn boxed(manifest: &ModelManifest, device: Device, forward: F) -> Box<dyn PolicyModel> {
+        let obs_shape = manifest
+            .obs_shape
+            .as_ref()
+            .map(|shape| shape.iter().map(|&d| d as usize).collect());
+        Box::new(Self {
+            forward,
+            obs_shape,
+            device: device.into(),
+        })
+    }
+}
+
+impl<F> PolicyModel for CandlePolicy<F>
+where
+    F: FnMut(&Tensor) -> Result<Tensor> + Send,
+{
+    fn forward(&mut self, obs: &[f32]) -> Result<Vec<f32>> {
+        Ok(self.forward_batch(&[obs.to_vec()])?.remove(0))
+    }
+
+    fn forward_batch(&mut self, obs: &[Vec<f32>]) -> Result<Vec<Vec<f32>>> {
+        let mut shape = vec![obs.len()];
+        match &self.obs_shape {
+            Some(obs_shape) => shape.extend(obs_shape),
+            None => shape.push(obs.first().map_or(0, |o| o.len())),
+        }
+        let obs = Tensor::from_slice(&obs.concat(), shape, &self.device)?;
+        let out = (self.forward)(&obs)?;
+        Ok(out.flatten_from(1)?.to_vec2::<f32>()?)
+    }
+}
+
+impl PolicyRunner {
+    /// Loads the action-value function of `Dqn` or `BootstrappedDqn` of `border-candle-agent`.
+    ///
+    /// The output of the policy is action values.
+    pub fn load_candle_dqn<Q>(
+        dir: impl AsRef<Path>,
+        q_config: Q::Config,
+        device: Device,
+    ) -> Result<Self>
+    where
+        Q: SubModel1<Input = Tensor, Output = Tensor> + Send + 'static,
+        Q::Config: DeserializeOwned + Serialize + OutDim + Clone + Send,
+    {
+        let dir = dir.as_ref();
+        let manifest = Self::load_manifest(dir, "candle", &["Dqn", "BootstrappedDqn"], &q_config)?;
+        let config = DqnModelConfig::default().q_config(q_config);
+        let mut qnet = DqnModel::<Q>::build(config, device.into())?;
+        qnet.load(find_params(dir.join("qnet")))?;
+        let model = CandlePolicy::boxed(&manifest, device, move |obs| Ok(qnet.forward(obs)));
+        Ok(Self::new(manifest, model))
+    }
+
+    /// Loads the policy model of `Bc` of `border-candle-agent`.
+    ///
+    /// The output of the policy is that of the policy model.
+    pub fn load_candle_bc<P>(
+        dir: impl AsRef<Path>,
+        policy_model_config: P::Config,
+        device: Device,
+    ) -> Result<Self>
+    where
+        P: SubModel1<Input = Tensor, Output = Tensor> + Send + 'static,
+        P::Config: DeserializeOwned + Serialize + OutDim + Clone + Send,
+    {
+        let dir = dir.as_ref();
+        let manifest = Self::load_manifest(dir, "candle", &["Bc"], &policy_model_config)?;
+        let config = BcModelConfig::default().policy_model_config(policy_model_config);
+        let mut policy = BcModel::<P>::build(config, device.into())?;
+        policy.load(find_params(dir.join("policy_model")))?;
+        let model = CandlePolicy::boxed(&manifest, device, move |obs| Ok(policy.forward(obs)));
+        Ok(Self::new(manifest, model))
+    }
+
+    /// Loads the actor of `Ppo` of `border-candle-agent`.
+    ///
+    /// The output of the policy is logits of actions.
+    pub fn load_candle_ppo<P>(
+        dir: impl AsRef<Path>,
+        policy_config: P::Config,
+        device: Device,
+    ) -> Result<Self>
+    where
+        P: SubModel1<Input = Tensor, Output = Tensor> + Send + 'static,
+        P::Config: DeserializeOwned + Serialize + OutDim + Clone + Send,
+    {
+        let dir = dir.as_ref();
+        let manifest = Self::load_manifest(dir, "candle", &["Ppo"], &policy_config)?;
+        let config = CategoricalActorConfig::default().policy_config(policy_config);
+        let mut actor = CategoricalActor::<P>::build(config, device.into())?;
+        actor.load(dir.join("actor"))?;
+        let model = CandlePolicy::boxed(&manifest, device, move |obs| Ok(actor.forward(obs)));
+        Ok(Self::new(manifest, model))
+    }
+
+    /// Loads the actor of `Sac`, `Cql`, `Iql` or `Awac` of `border-candle-agent`.
+    ///
+    /// The output of the policy is the mean of the Gaussian distribution limited with
+    /// the action limit in `actor_config`.
+    pub fn load_candle_gaussian<P>(
+        dir: impl AsRef<Path>,
+        actor_config: GaussianActorConfig<P::Config>,
+        device: Device,
+    ) -> Result<Self>
+    where
+        P: SubModel1<Input = Tensor, Output = (Tensor, Tensor)> + Send + 'static,
+        P::Config: DeserializeOwned + Serialize + OutDim + Clone + Send,
+    {
+        let dir = dir.as_ref();
+        let policy_config = actor_config
+            .policy_config
+            .as_ref()
+            .context("policy_config is not set.")?;
+        let model_types = ["Sac", "Cql", "Iql", "Awac"];
+        let manifest = Self::load_manifest(dir, "candle", &model_types, policy_config)?;
+        let mut actor = GaussianActor::<P>::build(actor_config, device.into())?;
+        actor.load(dir.join("actor"))?;
+        let model = CandlePolicy::boxed(&manifest, device, move |obs| actor.sample(obs, false));
+        Ok(Self::new(manifest, model))
+    }
+}
@@ -0,0 +1,45 @@
+//! Policy-only inference of agents trained with Border.
+//!
+//! [`PolicyRunner`] loads the policy of an agent saved with [`Agent::save_params()`] and
+//! computes actions for observations given as `f32` slices. It does not require types of
+//! environments, replay buffers or [`Trainer`], so that trained policies can be embedded
+//! in robotics or production binaries.
+//!
+//! The directory of the saved agent should include [`ModelManifest`], with which the type
+//! of the agent and the configuration of the policy network are checked. The policy network
+//! is loaded with one of the following methods, enabled with the feature of the backend:
+//!
+//! * `candle` - [`PolicyRunner::load_candle_dqn()`], [`PolicyRunner::load_candle_bc()`],
+//!   [`PolicyRunner::load_candle_ppo()`] and [`PolicyRunner::load_candle_gaussian()`]
+//! * `tch` - [`PolicyRunner::load_tch_dqn()`] and [`PolicyRunner::load_tch_sac()`]
+//!
+//! ```no_run
+//! # #[cfg(feature = "candle")]
+//! # fn main() -> anyhow::Result<()> {
+//! use border_candle_agent::{
+//!     mlp::{Mlp, MlpConfig},
+//!     Activation, Device,
+//! };
+//! use border_inference::PolicyRunner;
+//!
+//! let q_config = MlpConfig::new(4, vec![64, 64], 2, Activation::None);
+//! let mut runner =
+//!     PolicyRunner::load_candle_dqn::<Mlp>("model/dqn_cartpole/best", q_config, Device::Cpu)?;
+//! let act = runner.act_discrete(&[0.0, 0.1, 0.0, -0.1]);
+//! # Ok(())
+//! # }
+//! # #[cfg(not(feature = "candle"))]
+//! # fn main() {}
+//! ```
+//!
+//! [`Agent::save_params()`]: border_core::Agent::save_params
+//! [`Trainer`]: border_core::Trainer
+//! [`ModelManifest`]: border_core::ModelManifest
+mod runner;
+pub use runner::{argmax, PolicyModel, PolicyRunner};
+
+#[cfg(feature = "candle")]
+mod candle;
+
+#[cfg(feature = "tch")]
+mod tch;
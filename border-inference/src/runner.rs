@@ -0,0 +1,146 @@
+use anyhow::{ensure, Context, Result};
+use border_core::ModelManifest;
+use serde::Serialize;
+use std::path::Path;
+
+/// Network of a policy run by [`PolicyRunner`].
+pub trait PolicyModel: Send {
+    /// Returns the output of the policy for an observation, e.g., action values or an action.
+    fn forward(&mut self, obs: &[f32]) -> Result<Vec<f32>>;
+
+    /// Returns the outputs of the policy for a batch of observations.
+    ///
+    /// The default implementation calls [`PolicyModel::forward()`] for each observation.
+    fn forward_batch(&mut self, obs: &[Vec<f32>]) -> Result<Vec<Vec<f32>>> {
+        obs.iter().map(|obs| self.forward(obs)).collect()
+    }
+}
+
+/// Returns the index of the maximum value, e.g., a discrete action from action values.
+///
+/// Returns an error if `xs` is empty.
+pub fn argmax(xs: &[f32]) -> Result<usize> {
+    xs.iter()
+        .enumerate()
+        .max_by(|(_, x), (_, y)| x.total_cmp(y))
+        .map(|(i, _)| i)
+        .context("Output of the policy is empty")
+}
+
+/// Runs the policy of a trained agent.
+///
+/// The output of the policy depends on the agent: action values for DQN, logits for PPO,
+/// and actions for agents with continuous actions. For discrete actions,
+/// [`PolicyRunner::act_discrete()`] returns the index of the maximum output.
+pub struct PolicyRunner {
+    manifest: ModelManifest,
+    model: Box<dyn PolicyModel>,
+}
+
+impl PolicyRunner {
+    /// Creates a runner of a policy network.
+    pub fn new(manifest: ModelManifest, model: Box<dyn PolicyModel>) -> Self {
+        Self { manifest, model }
+    }
+
+    /// Loads [`ModelManifest`] in the directory of a saved agent.
+    ///
+    /// It returns an error if the backend or the type of the model do not match,
+    /// or the hash of the given configuration of the policy network differs from
+    /// that in the manifest.
+    pub fn load_manifest<C: Serialize>(
+        dir: impl AsRef<Path>,
+        backend: &str,
+        model_types: &[&str],
+        config: &C,
+    ) -> Result<ModelManifest> {
+        let path = dir.as_ref().join(ModelManifest::FILE);
+        let manifest = ModelManifest::load(&path)
+            .with_context(|| format!("Failed to load manifest {:?}", path))?;
+        ensure!(
+            manifest.backend == backend,
+            "Model is saved with {}, not {}",
+            manifest.backend,
+            backend
+        );
+        ensure!(
+            model_types.contains(&manifest.model_type.as_str()),
+            "Model type {} is not one of {:?}",
+            manifest.model_type,
+            model_types
+        );
+        ensure!(
+            manifest.config_hash == ModelManifest::config_hash(config)?,
+            "Configuration of the policy network does not match that of the saved model"
+        );
+        Ok(manifest)
+    }
+
+    /// Returns the manifest of the saved agent.
+    pub fn manifest(&self) -> &ModelManifest {
+        &self.manifest
+    }
+
+    /// Returns the dimension of observations, if known.
+    pub fn obs_dim(&self) -> Option<usize> {
+        self.manifest
+            .obs_shape
+            .as_ref()
+            .map(|shape| shape.iter().product::<i64>() as usize)
+    }
+
+    /// Returns the dimension of the output of the policy.
+    pub fn act_dim(&self) -> usize {
+        self.manifest.act_dim as _
+    }
+
+    /// Returns the output of the policy for an observation.
+    pub fn try_act(&mut self, obs: &[f32]) -> Result<Vec<f32>> {
+        if let Some(obs_dim) = self.obs_dim() {
+            ensure!(
+                obs.len() == obs_dim,
+                "Expected an observation of length {}, got {}",
+                obs_dim,
+                obs.len()
+            );
+        }
+        self.model.forward(obs)
+    }
+
+    /// Returns the outputs of the policy for a batch of observations.
+    ///
+    /// The observations are processed at once by the policy network, e.g., on GPU.
+    pub fn try_act_batch(&mut self, obs: &[Vec<f32>]) -> Result<Vec<Vec<f32>>> {
+        if let Some(obs_dim) = self.obs_dim() {
+            for o in obs.iter() {
+                ensure!(
+                    o.len() == obs_dim,
+                    "Expected an observation of length {}, got {}",
+                    obs_dim,
+                    o.len()
+                );
+            }
+        }
+        self.model.forward_batch(obs)
+    }
+
+    /// Returns the index of the maximum output of the policy for an observation.
+    pub fn try_act_discrete(&mut self, obs: &[f32]) -> Result<usize> {
+        argmax(&self.try_act(obs)?)
+    }
+
+    /// Returns the output of the policy for an observation.
+    ///
+    /// See [`PolicyRunner::try_act()`] for handling errors.
+    pub fn act(&mut self, obs: &[f32]) -> Vec<f32> {
+        self.try_act(obs).expect("Failed in PolicyRunner::act()")
+    }
+
+    /// Returns the index of the maximum output of the policy for an observation.
+    ///
+    /// See [`PolicyRunner::try_act_discrete()`] for handling errors.
+    pub fn act_discrete(&mut self, obs: &[f32]) -> usize {
+        self.try_act_discrete(obs)
+            .expect("Failed in PolicyRunner::act_discrete()")
+    }
+}
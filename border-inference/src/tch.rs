@@ -0,0 +1,101 @@
+//! Policies of agents in `border-tch-agent`.
+use crate::{PolicyModel, PolicyRunner};
+use anyhow::Result;
+use border_core::ModelManifest;
+use border_tch_agent::{
+    dqn::{DqnModel, DqnModelConfig},
+    model::{ModelBase, SubModel},
+    sac::{Actor, ActorConfig},
+    util::{find_params, OutDim},
+    Device,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{convert::TryFrom, path::Path};
+use tch::{no_grad, Tensor};
+
+/// Policy network taking a batch of observations.
+struct TchPolicy<F> {
+    forward: F,
+    obs_shape: Option<Vec<i64>>,
+    device: tch::Device,
+}
+
+impl<F> TchPolicy<F>
+where
+    F: FnMut(&Tensor) -> Tensor + Send + 'static,
+{
+    fn boxed(manifest: &ModelManifest, device: Device, forward: F) -> Box<dyn PolicyModel> {
+        Box::new(Self {
+            forward,
+            obs_shape: manifest.obs_shape.clone(),
+            device: device.into(),
+        })
+    }
+}
+
+impl<F> PolicyModel for TchPolicy<F>
+where
+    F: FnMut(&Tensor) -> Tensor + Send,
+{
+    fn forward(&mut self, obs: &[f32]) -> Result<Vec<f32>> {
+        Ok(self.forward_batch(&[obs.to_vec()])?.remove(0))
+    }
+
+    fn forward_batch(&mut self, obs: &[Vec<f32>]) -> Result<Vec<Vec<f32>>> {
+        let mut shape = vec![obs.len() as i64];
+        match &self.obs_shape {
+            Some(obs_shape) => shape.extend(obs_shape),
+            None => shape.push(obs.first().map_or(0, |o| o.len()) as i64),
+        }
+        let obs = Tensor::from_slice(&obs.concat())
+            .reshape(&shape)
+            .to(self.device);
+        let out = no_grad(|| (self.forward)(&obs)).flatten(1, -1);
+        Ok(Vec::<Vec<f32>>::try_from(&out.to(tch::Device::Cpu))?)
+    }
+}
+
+impl PolicyRunner {
+    /// Loads the action-value function of `Dqn` of `border-tch-agent`.
+    ///
+    /// The output of the policy is action values.
+    pub fn load_tch_dqn<Q>(
+        dir: impl AsRef<Path>,
+        q_config: Q::Config,
+        device: Device,
+    ) -> Result<Self>
+    where
+        Q: SubModel<Input = Tensor, Output = Tensor> + Send + 'static,
+        Q::Config: DeserializeOwned + Serialize + OutDim + Send,
+    {
+        let dir = dir.as_ref();
+        let manifest = Self::load_manifest(dir, "tch", &["Dqn"], &q_config)?;
+        let config = DqnModelConfig::default().q_config(q_config);
+        let mut qnet = DqnModel::<Q>::build(config, device.into());
+        qnet.load(find_params(dir.join("qnet")))?;
+        let model = TchPolicy::boxed(&manifest, device, move |obs| qnet.forward(obs));
+        Ok(Self::new(manifest, model))
+    }
+
+    /// Loads the actor of `Sac` of `border-tch-agent`.
+    ///
+    /// The output of the policy is the mean of the Gaussian distribution squashed with `tanh`,
+    /// as in the evaluation mode of `Sac`.
+    pub fn load_tch_sac<P>(
+        dir: impl AsRef<Path>,
+        pi_config: P::Config,
+        device: Device,
+    ) -> Result<Self>
+    where
+        P: SubModel<Input = Tensor, Output = (Tensor, Tensor)> + Send + 'static,
+        P::Config: DeserializeOwned + Serialize + OutDim + Send,
+    {
+        let dir = dir.as_ref();
+        let manifest = Self::load_manifest(dir, "tch", &["Sac"], &pi_config)?;
+        let config = ActorConfig::default().pi_config(pi_config);
+        let mut pi = Actor::<P>::build(config, device.into())?;
+        pi.load(find_params(dir.join("pi")))?;
+        let model = TchPolicy::boxed(&manifest, device, move |obs| pi.forward(obs).0.tanh());
+        Ok(Self::new(manifest, model))
+    }
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "candle")]
+use anyhow::Result;
+use border_candle_agent::{
+    dqn::{DqnModel, DqnModelConfig},
+    mlp::{Mlp, MlpConfig},
+    util::params_path,
+    Activation, Device,
+};
+use border_core::ModelManifest;
+use border_inference::PolicyRunner;
+use candle_core::Tensor;
+use tempdir::TempDir;
+
+#[test]
+fn test_load_candle_dqn() -> Result<()> {
+    let dir = TempDir::new("policy_runner")?;
+    let q_config = MlpConfig::new(4, vec![16], 3, Activation::None);
+
+    // Save the action-value function as `Dqn` does
+    let config = DqnModelConfig::default().q_config(q_config.clone());
+    let qnet = DqnModel::<Mlp>::build(config, candle_core::Device::Cpu)?;
+    let path = params_path(dir.path().join("qnet"));
+    qnet.save(&path)?;
+    ModelManifest::new("candle", "Dqn")
+        .obs_shape(Some(vec![4]))
+        .act_dim(3)
+        .config(&q_config)?
+        .files(&[path])
+        .save(dir.path().join(ModelManifest::FILE))?;
+
+    let mut runner =
+        PolicyRunner::load_candle_dqn::<Mlp>(dir.path(), q_config.clone(), Device::Cpu)?;
+    assert_eq!(runner.obs_dim(), Some(4));
+    assert_eq!(runner.act_dim(), 3);
+
+    let obs = [0.1f32, -0.2, 0.3, 0.4];
+    let q = qnet
+        .forward(&Tensor::from_slice(
+            &obs,
+            (1, 4),
+            &candle_core::Device::Cpu,
+        )?)
+        .flatten_all()?
+        .to_vec1::<f32>()?;
+    assert_eq!(runner.act(&obs), q);
+    let best = (0..3).max_by(|&i, &j| q[i].total_cmp(&q[j])).unwrap();
+    assert_eq!(runner.act_discrete(&obs), best);
+    assert!(runner.try_act(&[0.0; 3]).is_err());
+
+    let obs2 = [0.5f32, 0.0, -0.1, 0.2];
+    let q2 = runner.act(&obs2);
+    let qs = runner.try_act_batch(&[obs.to_vec(), obs2.to_vec()])?;
+    for (x, y) in qs.concat().iter().zip([q, q2].concat()) {
+        assert!((x - y).abs() < 1e-5);
+    }
+
+    // Another configuration of the network is rejected
+    let q_config = MlpConfig::new(4, vec![32], 3, Activation::None);
+    assert!(PolicyRunner::load_candle_dqn::<Mlp>(dir.path(), q_config, Device::Cpu).is_err());
+
+    Ok(())
+}
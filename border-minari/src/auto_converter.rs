@@ -0,0 +1,378 @@
+//! Converter built from the observation and action spaces of Minari datasets.
+use crate::{
+    util::{
+        candle::{NdarrayAct, NdarrayObs, TensorBatch},
+        ndarray::arrayd_to_pyobj,
+    },
+    EpisodeArray, MinariConverter, MinariDataset,
+};
+use anyhow::Result;
+use candle_core::{Device::Cpu, Tensor};
+use ndarray::{concatenate, Array2, ArrayD, Axis, Slice};
+use pyo3::{
+    types::{PyDict, PyIterator},
+    PyAny, PyObject, Python, ToPyObject,
+};
+
+/// Configuration of [`AutoConverter`].
+#[derive(Clone, Debug, Default)]
+pub struct AutoConverterConfig {
+    /// Keys of `Dict` observations concatenated into flattened observations, in this order,
+    /// e.g., `["observation", "desired_goal"]`. If `None`, all keys are used in the order of
+    /// the observation space.
+    pub obs_keys: Option<Vec<String>>,
+
+    /// If `true`, observations are normalized with the mean and standard deviation of
+    /// the observations in the dataset.
+    pub normalize_obs: bool,
+}
+
+impl AutoConverterConfig {
+    /// Sets the keys of `Dict` observations.
+    pub fn obs_keys(mut self, v: Option<Vec<String>>) -> Self {
+        self.obs_keys = v;
+        self
+    }
+
+    /// Sets the flag to normalize observations.
+    pub fn normalize_obs(mut self, v: bool) -> Self {
+        self.normalize_obs = v;
+        self
+    }
+}
+
+/// An element of flattened observations, given by the path of keys in `Dict` spaces.
+#[derive(Clone, Debug)]
+struct Field {
+    keys: Vec<String>,
+    dim: usize,
+}
+
+/// Parses a space into elements of flattened vectors.
+fn parse_space(space: &PyAny, keys: Vec<String>, fields: &mut Vec<Field>) -> Result<()> {
+    let name: String = space.getattr("__class__")?.getattr("__name__")?.extract()?;
+    match name.as_str() {
+        "Box" => {
+            let shape: Vec<usize> = space.getattr("shape")?.extract()?;
+            fields.push(Field {
+                keys,
+                dim: shape.iter().product(),
+            });
+        }
+        "Discrete" => fields.push(Field { keys, dim: 1 }),
+        "Dict" => {
+            let spaces: &PyDict = space.getattr("spaces")?.extract()?;
+            for (k, v) in spaces.iter() {
+                let mut keys = keys.clone();
+                keys.push(k.extract()?);
+                parse_space(v, keys, fields)?;
+            }
+        }
+        _ => return Err(anyhow::anyhow!("Unsupported space: {}", name)),
+    }
+    Ok(())
+}
+
+/// Converts `n` elements of a field into an array of shape `[n, dim]`.
+fn field_to_array2(arr: &EpisodeArray, field: &Field) -> Result<Array2<f32>> {
+    let arr = arr.get(&field.keys)?;
+    let n = arr.len() / field.dim.max(1);
+    Ok(arr.clone().into_shape((n, field.dim))?)
+}
+
+/// Returns the mean and the standard deviation of observations of shape `[1, obs_dim]`,
+/// given arrays of shape `[n, obs_dim]` of episodes.
+///
+/// The standard deviation of a constant element is set to 1.
+fn obs_stats(obs: &[ArrayD<f32>]) -> Result<(ArrayD<f32>, ArrayD<f32>)> {
+    let views = obs.iter().map(|o| o.view()).collect::<Vec<_>>();
+    if views.is_empty() {
+        return Err(anyhow::anyhow!("No observations in the dataset"));
+    }
+    let all_obs = concatenate(Axis(0), &views)?;
+    let mean = all_obs
+        .mean_axis(Axis(0))
+        .ok_or_else(|| anyhow::anyhow!("No observations in the dataset"))?
+        .insert_axis(Axis(0));
+    let std = all_obs
+        .std_axis(Axis(0), 1.0)
+        .mapv(|v| if v > 1e-6 { v } else { 1.0 })
+        .insert_axis(Axis(0));
+    Ok((mean, std))
+}
+
+/// Converter for Minari datasets, built from their observation and action spaces.
+///
+/// Unlike the converters in [`d4rl`](crate::d4rl) written for each task, this converter
+/// inspects the observation and action spaces of a dataset. Observations of `Box` and `Dict`
+/// spaces are flattened into vectors, where the subspaces of `Dict` spaces, including nested
+/// ones, are concatenated in order. Actions of `Box` spaces are flattened continuous actions,
+/// and those of `Discrete` spaces are discrete actions.
+///
+/// ```no_run
+/// # use anyhow::Result;
+/// # use border_minari::{AutoConverter, AutoConverterConfig, MinariDataset};
+/// # fn main() -> Result<()> {
+/// let dataset = MinariDataset::load_dataset("D4RL/pointmaze/medium-v2", true)?;
+/// let config = AutoConverterConfig::default()
+///     .obs_keys(Some(vec!["observation".into(), "desired_goal".into()]))
+///     .normalize_obs(true);
+/// let mut converter = AutoConverter::new(config, &dataset)?;
+/// let (obs_dim, act_dim) = (converter.obs_dim(), converter.act_dim());
+/// let replay_buffer = dataset.create_replay_buffer(&mut converter, None)?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AutoConverter {
+    obs_fields: Vec<Field>,
+    act_dim: usize,
+    discrete_act: bool,
+    mean: Option<ArrayD<f32>>, // for normalizing observation
+    std: Option<ArrayD<f32>>,  // for normalizing observation
+}
+
+impl AutoConverter {
+    /// Creates a converter from the spaces of a dataset.
+    ///
+    /// If [`AutoConverterConfig::normalize_obs`] is `true`, all episodes of the dataset
+    /// are read to compute the statistics of observations.
+    pub fn new(config: AutoConverterConfig, dataset: &MinariDataset) -> Result<Self> {
+        Python::with_gil(|py| {
+            let obs_space = dataset.dataset.getattr(py, "observation_space")?;
+            let act_space = dataset.dataset.getattr(py, "action_space")?;
+
+            let mut obs_fields = vec![];
+            parse_space(obs_space.as_ref(py), vec![], &mut obs_fields)?;
+            if let Some(keys) = config.obs_keys.as_ref() {
+                obs_fields = keys
+                    .iter()
+                    .map(|key| {
+                        let fields = obs_fields
+                            .iter()
+                            .filter(|f| f.keys.first() == Some(key))
+                            .cloned()
+                            .collect::<Vec<_>>();
+                        match fields.is_empty() {
+                            true => Err(anyhow::anyhow!("Key {} is not in observations", key)),
+                            false => Ok(fields),
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()?
+                    .concat();
+            }
+
+            let act_space = act_space.as_ref(py);
+            let name: String = act_space
+                .getattr("__class__")?
+                .getattr("__name__")?
+                .extract()?;
+            let (act_dim, discrete_act) = match name.as_str() {
+                "Box" => {
+                    let shape: Vec<usize> = act_space.getattr("shape")?.extract()?;
+                    (shape.iter().product(), false)
+                }
+                "Discrete" => (act_space.getattr("n")?.extract()?, true),
+                _ => return Err(anyhow::anyhow!("Unsupported action space: {}", name)),
+            };
+
+            let mut converter = Self {
+                obs_fields,
+                act_dim,
+                discrete_act,
+                mean: None,
+                std: None,
+            };
+
+            if config.normalize_obs {
+                let episodes =
+                    dataset
+                        .dataset
+                        .call_method1(py, "iterate_episodes", (None::<i32>,))?;
+                let all_obs = PyIterator::from_object(py, &episodes)?
+                    .map(|ep| {
+                        let obs = EpisodeArray::from_py(ep?.getattr("observations")?)?;
+                        converter.flatten(&obs)
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let (mean, std) = obs_stats(&all_obs)?;
+                converter.mean = Some(mean);
+                converter.std = Some(std);
+            }
+
+            Ok(converter)
+        })
+    }
+
+    /// Returns the dimension of flattened observations.
+    pub fn obs_dim(&self) -> usize {
+        self.obs_fields.iter().map(|f| f.dim).sum()
+    }
+
+    /// Returns the dimension of continuous actions, or the number of discrete actions.
+    pub fn act_dim(&self) -> usize {
+        self.act_dim
+    }
+
+    /// Flattens and normalizes observations into an array of shape `[n, obs_dim]`.
+    fn flatten(&self, obs: &EpisodeArray) -> Result<ArrayD<f32>> {
+        let arrs = self
+            .obs_fields
+            .iter()
+            .map(|f| field_to_array2(obs, f))
+            .collect::<Result<Vec<_>>>()?;
+        let views = arrs.iter().map(|a| a.view()).collect::<Vec<_>>();
+        let obs = concatenate(Axis(1), &views)?.into_dyn();
+
+        match (self.mean.as_ref(), self.std.as_ref()) {
+            (Some(mean), Some(std)) => Ok((&obs - mean) / std),
+            _ => Ok(obs),
+        }
+    }
+
+    /// Converts observations of an episode into a batch, dropping the first or last one.
+    fn observation_batch(&self, obs: &ArrayD<f32>, slice: Slice) -> Result<TensorBatch> {
+        let obs = obs.slice_axis(Axis(0), slice).to_owned();
+        let shape = obs.shape().to_vec();
+        let t = Tensor::from_vec(obs.into_raw_vec(), shape.as_slice(), &Cpu)?;
+        Ok(TensorBatch::from(t))
+    }
+
+    /// Converts actions of an episode into a batch.
+    fn action_batch(&self, act: &EpisodeArray) -> Result<TensorBatch> {
+        let act = act.get(&[])?;
+        let t = match self.discrete_act {
+            false => {
+                let v = act.iter().cloned().collect::<Vec<_>>();
+                let n = v.len() / self.act_dim.max(1);
+                Tensor::from_vec(v, &[n, self.act_dim], &Cpu)?
+            }
+            true => {
+                let v = act.iter().map(|&a| a as i64).collect::<Vec<_>>();
+                let n = v.len();
+                Tensor::from_vec(v, &[n, 1], &Cpu)?
+            }
+        };
+        Ok(TensorBatch::from(t))
+    }
+}
+
+impl MinariConverter for AutoConverter {
+    type Obs = NdarrayObs;
+    type Act = NdarrayAct;
+    type ObsBatch = TensorBatch;
+    type ActBatch = TensorBatch;
+
+    fn convert_observation(&self, obj: &PyAny) -> Result<Self::Obs> {
+        Ok(NdarrayObs(self.flatten(&EpisodeArray::from_py(obj)?)?))
+    }
+
+    fn convert_action(&self, act: Self::Act) -> Result<PyObject> {
+        match act {
+            NdarrayAct::Continuous(act) => Ok(arrayd_to_pyobj(act)),
+            NdarrayAct::Discrete(act) => Python::with_gil(|py| {
+                let act = act
+                    .iter()
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Empty action"))?;
+                Ok(act.to_object(py))
+            }),
+        }
+    }
+
+    fn convert_observation_batch(&self, obj: &PyAny) -> Result<Self::ObsBatch> {
+        let obs = self.flatten(&EpisodeArray::from_py(obj)?)?;
+        self.observation_batch(&obs, Slice::from(..-1))
+    }
+
+    fn convert_observation_batch_next(&self, obj: &PyAny) -> Result<Self::ObsBatch> {
+        let obs = self.flatten(&EpisodeArray::from_py(obj)?)?;
+        self.observation_batch(&obs, Slice::from(1..))
+    }
+
+    fn convert_action_batch(&self, obj: &PyAny) -> Result<Self::ActBatch> {
+        self.action_batch(&EpisodeArray::from_py(obj)?)
+    }
+
+    fn converts_episode_arrays(&self) -> bool {
+        true
+    }
+
+    fn convert_episode_arrays(
+        &self,
+        obs: &EpisodeArray,
+        act: &EpisodeArray,
+    ) -> Result<(Self::ObsBatch, Self::ObsBatch, Self::ActBatch)> {
+        let obs = self.flatten(obs)?;
+        Ok((
+            self.observation_batch(&obs, Slice::from(..-1))?,
+            self.observation_batch(&obs, Slice::from(1..))?,
+            self.action_batch(act)?,
+        ))
+    }
+
+    fn env_params(&self, _py: Python<'_>) -> Vec<(&str, PyObject)> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::{array, Array1};
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_obs_stats() -> Result<()> {
+        let obs = vec![
+            array![[0.0f32, 1.0], [2.0, 1.0]].into_dyn(),
+            array![[4.0f32, 1.0]].into_dyn(),
+        ];
+        let (mean, std) = obs_stats(&obs)?;
+        assert_eq!(mean.shape(), &[1, 2]);
+        assert!((mean[[0, 0]] - 2.0).abs() < 1e-6);
+        assert!((mean[[0, 1]] - 1.0).abs() < 1e-6);
+        assert!((std[[0, 0]] - 2.0).abs() < 1e-6);
+        assert_eq!(std[[0, 1]], 1.0);
+
+        assert!(obs_stats(&[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_episode_arrays() -> Result<()> {
+        let converter = AutoConverter {
+            obs_fields: vec![
+                Field {
+                    keys: vec!["observation".into()],
+                    dim: 2,
+                },
+                Field {
+                    keys: vec!["desired_goal".into()],
+                    dim: 1,
+                },
+            ],
+            act_dim: 1,
+            discrete_act: false,
+            mean: None,
+            std: None,
+        };
+        let mut obs = BTreeMap::new();
+        let observation = Array1::range(0.0, 6.0, 1.0).into_shape((3, 2))?.into_dyn();
+        obs.insert("observation".into(), EpisodeArray::Array(observation));
+        let goal = array![[10.0f32], [11.0], [12.0]].into_dyn();
+        obs.insert("desired_goal".into(), EpisodeArray::Array(goal));
+        let act = EpisodeArray::Array(array![[0.5f32], [-0.5]].into_dyn());
+
+        let (obs, next_obs, act) =
+            converter.convert_episode_arrays(&EpisodeArray::Dict(obs), &act)?;
+        let obs = Tensor::from(obs).to_vec2::<f32>()?;
+        let next_obs = Tensor::from(next_obs).to_vec2::<f32>()?;
+        assert_eq!(obs, vec![vec![0.0, 1.0, 10.0], vec![2.0, 3.0, 11.0]]);
+        assert_eq!(next_obs, vec![vec![2.0, 3.0, 11.0], vec![4.0, 5.0, 12.0]]);
+        assert_eq!(
+            Tensor::from(act).to_vec2::<f32>()?,
+            vec![vec![0.5], vec![-0.5]]
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,55 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use border_core::{generic_replay_buffer::BatchBase, Act, Obs};
-use pyo3::{PyAny, PyObject, Python};
+use ndarray::ArrayD;
+use numpy::PyArrayDyn;
+use pyo3::{types::PyDict, PyAny, PyObject, Python};
+use std::collections::BTreeMap;
+
+/// Values of observations or actions in an episode, copied from numpy arrays.
+///
+/// It is read from a dataset while holding the GIL, and converted into batches without the GIL
+/// with [`MinariConverter::convert_episode_arrays()`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum EpisodeArray {
+    /// Values of a `Box` or `Discrete` space as `f32`, where the first axis is time steps.
+    Array(ArrayD<f32>),
+
+    /// Values of the subspaces of a `Dict` space.
+    Dict(BTreeMap<String, EpisodeArray>),
+}
+
+impl EpisodeArray {
+    /// Copies a numpy array, or a dict of numpy arrays, into Rust.
+    pub fn from_py(obj: &PyAny) -> Result<Self> {
+        if let Ok(dict) = obj.extract::<&PyDict>() {
+            let arrays = dict
+                .iter()
+                .map(|(k, v)| Ok((k.extract()?, Self::from_py(v)?)))
+                .collect::<Result<_>>()?;
+            return Ok(Self::Dict(arrays));
+        }
+        let arr = obj
+            .py()
+            .import("numpy")?
+            .call_method1("asarray", (obj, "float32"))?;
+        let arr: &PyArrayDyn<f32> = arr.extract()?;
+        Ok(Self::Array(arr.to_owned_array()))
+    }
+
+    /// Returns the array at the path of keys in `Dict` spaces.
+    ///
+    /// The path is empty for the array of a space other than `Dict`.
+    pub fn get(&self, keys: &[String]) -> Result<&ArrayD<f32>> {
+        match (self, keys.split_first()) {
+            (Self::Array(arr), None) => Ok(arr),
+            (Self::Dict(arrays), Some((key, keys))) => arrays
+                .get(key)
+                .ok_or_else(|| anyhow!("Key {} is not in the episode", key))?
+                .get(keys),
+            _ => Err(anyhow!("Keys {:?} do not match the episode", keys)),
+        }
+    }
+}
 
 /// Conversion trait for observation and action.
 pub trait MinariConverter {
@@ -41,6 +90,28 @@ pub trait MinariConverter {
     /// [`PyObject`]: pyo3::PyObject
     fn convert_action_batch(&self, obj: &PyAny) -> Result<Self::ActBatch>;
 
+    /// Returns `true` if the converter implements [`MinariConverter::convert_episode_arrays()`].
+    ///
+    /// It is `false` by default, in which case episodes are converted with the GIL held in
+    /// [`MinariDataset::create_replay_buffer_parallel()`].
+    ///
+    /// [`MinariDataset::create_replay_buffer_parallel()`]: crate::MinariDataset::create_replay_buffer_parallel
+    fn converts_episode_arrays(&self) -> bool {
+        false
+    }
+
+    /// Converts observations and actions of an episode, copied from numpy arrays, into
+    /// [`Self::ObsBatch`] for `obs` and `next_obs`, and [`Self::ActBatch`].
+    ///
+    /// It is called without the GIL on worker threads.
+    fn convert_episode_arrays(
+        &self,
+        _obs: &EpisodeArray,
+        _act: &EpisodeArray,
+    ) -> Result<(Self::ObsBatch, Self::ObsBatch, Self::ActBatch)> {
+        Err(anyhow!("Conversion of episode arrays is not implemented"))
+    }
+
     /// Returns optional parameters when recovering the environment for evaluation from the dataset.
     fn env_params(&self, py: Python<'_>) -> Vec<(&str, PyObject)>;
 }
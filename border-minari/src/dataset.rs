@@ -1,17 +1,102 @@
-use crate::{util, MinariConverter, MinariEnv};
+use crate::{
+    util, EpisodeArray, EpisodeFilter, EpisodeInfo, MinariConverter, MinariEnv, RewardTransform,
+};
 use anyhow::Result;
 use border_core::{
-    generic_replay_buffer::{GenericTransitionBatch, SimpleReplayBuffer, SimpleReplayBufferConfig},
+    generic_replay_buffer::{
+        BatchBase, GenericTransitionBatch, SimpleReplayBuffer, SimpleReplayBufferConfig,
+    },
     ExperienceBufferBase, ReplayBufferBase,
 };
 use pyo3::{
     types::{IntoPyDict, PyIterator},
     PyAny, PyObject, Python, ToPyObject,
 };
+use rayon::prelude::*;
+use std::sync::mpsc;
+
+/// Configuration of the parallel ingestion of episodes in
+/// [`MinariDataset::create_replay_buffer_parallel()`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct IngestConfig {
+    /// The number of worker threads converting episodes.
+    pub n_workers: usize,
+
+    /// The number of episodes read from the dataset while holding the GIL.
+    pub chunk_size: usize,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            n_workers: std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+            chunk_size: 16,
+        }
+    }
+}
+
+impl IngestConfig {
+    /// Sets the number of worker threads.
+    pub fn n_workers(mut self, v: usize) -> Self {
+        self.n_workers = v;
+        self
+    }
+
+    /// Sets the number of episodes in a chunk.
+    pub fn chunk_size(mut self, v: usize) -> Self {
+        self.chunk_size = v;
+        self
+    }
+}
+
+/// Transitions of an episode copied from numpy arrays while holding the GIL.
+struct EpisodeArrays {
+    obs: EpisodeArray,
+    act: EpisodeArray,
+    reward: Vec<f32>,
+    is_terminated: Vec<i8>,
+    is_truncated: Vec<i8>,
+}
+
+/// An episode read from the dataset while holding the GIL.
+enum Episode<T: MinariConverter> {
+    /// Arrays converted without the GIL.
+    Arrays(EpisodeArrays),
+
+    /// Transitions converted with the GIL held,
+    /// see [`MinariConverter::converts_episode_arrays()`].
+    Batch(GenericTransitionBatch<T::ObsBatch, T::ActBatch>),
+}
+
+impl<T: MinariConverter> Episode<T> {
+    fn into_batch(self, converter: &T) -> Result<GenericTransitionBatch<T::ObsBatch, T::ActBatch>> {
+        match self {
+            Self::Arrays(ep) => {
+                let (obs, next_obs, act) = converter.convert_episode_arrays(&ep.obs, &ep.act)?;
+                Ok(GenericTransitionBatch {
+                    obs,
+                    act,
+                    next_obs,
+                    reward: ep.reward,
+                    is_terminated: ep.is_terminated,
+                    is_truncated: ep.is_truncated,
+                    weight: None,
+                    ix_sample: None,
+                    mask: None,
+                    mc_return: None,
+                })
+            }
+            Self::Batch(batch) => Ok(batch),
+        }
+    }
+}
 
 /// Common interface for Minari datasets.
 pub struct MinariDataset {
     pub(crate) dataset: PyObject,
+    pub(crate) reward_transform: Option<RewardTransform>,
 }
 
 impl MinariDataset {
@@ -26,10 +111,24 @@ impl MinariDataset {
                 .getattr("load_dataset")?
                 .call1((dataset_id.as_ref(), download))?
                 .to_object(py);
-            Ok(Self { dataset })
+            Ok(Self {
+                dataset,
+                reward_transform: None,
+            })
         })
     }
 
+    /// Sets the transformation of rewards applied while creating replay buffers.
+    ///
+    /// Summaries of episodes given to [`EpisodeFilter`] are computed with the original rewards.
+    pub fn reward_transform(mut self, v: Option<RewardTransform>) -> Self {
+        if let Some(transform) = v.as_ref() {
+            log::info!("Rewards are relabeled with {:?}", transform);
+        }
+        self.reward_transform = v;
+        self
+    }
+
     /// Gets the number of transitions over all episodes.
     pub fn get_num_transitions(&self, episode_indices: Option<Vec<usize>>) -> Result<usize> {
         Python::with_gil(|py| {
@@ -51,11 +150,73 @@ impl MinariDataset {
         })
     }
 
+    /// Gets the summaries of all episodes in the dataset.
+    pub fn episode_infos(&self) -> Result<Vec<EpisodeInfo>> {
+        Python::with_gil(|py| {
+            let episodes =
+                self.dataset
+                    .call_method1(py, "iterate_episodes", (None::<Vec<usize>>,))?;
+            let mut infos = vec![];
+
+            for ep in PyIterator::from_object(py, &episodes)? {
+                let ep = ep?;
+                let reward = util::vec::pyany_to_f32vec(py, ep.getattr("rewards")?)?;
+                let is_terminated = util::vec::pyany_to_vec::<i8>(py, ep.getattr("terminations")?)?;
+                let is_truncated = util::vec::pyany_to_vec::<i8>(py, ep.getattr("truncations")?)?;
+                let is_terminated = is_terminated.last().map_or(false, |&v| v != 0);
+                let success = ep.getattr("infos")?.call_method1("get", ("success",))?;
+                let is_success = match success.is_none() {
+                    true => is_terminated,
+                    false => util::vec::pyany_to_vec::<i8>(py, success)?
+                        .last()
+                        .map_or(false, |&v| v != 0),
+                };
+                infos.push(EpisodeInfo {
+                    id: ep.getattr("id")?.extract()?,
+                    n_steps: reward.len(),
+                    episode_return: reward.iter().sum(),
+                    is_terminated,
+                    is_truncated: is_truncated.last().map_or(false, |&v| v != 0),
+                    is_success,
+                });
+            }
+            Ok(infos)
+        })
+    }
+
+    /// Selects episodes with filters applied in order and returns their indices.
+    ///
+    /// The indices are given to [`MinariDataset::create_replay_buffer()`] to create a replay
+    /// buffer from a subset of the dataset, e.g., the episodes with the top 10% returns:
+    ///
+    /// ```no_run
+    /// # use anyhow::Result;
+    /// # use border_minari::{d4rl::kitchen::ndarray::KitchenConverter, EpisodeFilter, MinariDataset};
+    /// # fn main() -> Result<()> {
+    /// let dataset = MinariDataset::load_dataset("D4RL/kitchen/complete-v1", true)?;
+    /// let ixs = dataset.select_episodes(&[EpisodeFilter::TopReturn(0.1)])?;
+    /// let replay_buffer = dataset.create_replay_buffer(&mut KitchenConverter {}, Some(ixs))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn select_episodes(&self, filters: &[EpisodeFilter]) -> Result<Vec<usize>> {
+        let mut episodes = self.episode_infos()?;
+        let n_episodes = episodes.len();
+        for filter in filters.iter() {
+            episodes = filter.apply(episodes);
+        }
+        log::info!("{} of {} episodes are selected", episodes.len(), n_episodes);
+        Ok(episodes.into_iter().map(|ep| ep.id).collect())
+    }
+
     /// Creates replay buffer from the dataset.
     ///
     /// The order of transitions in the original dataset is preserved,
     /// but the boundary between episodes is discarded.
     ///
+    /// `terminations` and `truncations` of episodes are stored as separate flags, so that agents
+    /// bootstrap from the next observation at timeouts, which are common in D4RL datasets.
+    ///
     /// * `converter`: converter for observation and action.
     /// * `episode_indices`: indices of episodes to be included in the replay buffer.
     ///   If `None`, all episodes are included.
@@ -75,11 +236,7 @@ impl MinariDataset {
             let num_transitions = self.get_num_transitions(episode_indices.clone())?;
 
             // Prepare replay buffer
-            let mut replay_buffer = SimpleReplayBuffer::build(&SimpleReplayBufferConfig {
-                capacity: num_transitions,
-                seed: 0,
-                per_config: None,
-            });
+            let mut replay_buffer = Self::build_replay_buffer(num_transitions);
 
             let episodes = self
                 .dataset
@@ -91,24 +248,229 @@ impl MinariDataset {
                 let ep = ep?;
 
                 // Extract transitions in the episode as a batch
-                let batch = Self::extract_transitions_in_episode(py, &ep, converter)?;
+                let batch = self.extract_transitions_in_episode(py, &ep, converter)?;
 
                 // Push the batch to the replay buffer
                 replay_buffer.push(batch)?;
             }
 
-            // Stats in the replay buffer
-            log::info!("In replay buffer:");
-            log::info!("{} transitions", num_transitions);
-            log::info!("{} terminated flags", replay_buffer.num_terminated_flags());
-            log::info!("{} truncated flags", replay_buffer.num_truncated_flags());
-            log::info!("{} reward sum", replay_buffer.sum_rewards());
+            Self::log_stats(&replay_buffer, num_transitions);
 
             Ok(replay_buffer)
         })
     }
 
+    /// Creates replay buffer from the episodes selected with filters applied in order.
+    ///
+    /// This is a shorthand of [`MinariDataset::select_episodes()`] followed by
+    /// [`MinariDataset::create_replay_buffer()`]. For example, the following replay buffer
+    /// contains the successful episodes with returns in the top 10%, e.g., for %BC:
+    ///
+    /// ```no_run
+    /// # use anyhow::Result;
+    /// # use border_minari::{d4rl::kitchen::ndarray::KitchenConverter, EpisodeFilter, MinariDataset};
+    /// # fn main() -> Result<()> {
+    /// let dataset = MinariDataset::load_dataset("D4RL/kitchen/complete-v1", true)?;
+    /// let filters = [EpisodeFilter::Success, EpisodeFilter::ReturnPercentile(90.0)];
+    /// let replay_buffer =
+    ///     dataset.create_replay_buffer_with_filters(&mut KitchenConverter {}, &filters)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// An error is returned if no episodes are selected.
+    pub fn create_replay_buffer_with_filters<T: MinariConverter>(
+        &self,
+        converter: &mut T,
+        filters: &[EpisodeFilter],
+    ) -> Result<SimpleReplayBuffer<T::ObsBatch, T::ActBatch>>
+    where
+        T::ObsBatch: std::fmt::Debug,
+        T::ActBatch: std::fmt::Debug,
+    {
+        let episode_indices = self.select_episodes(filters)?;
+        if episode_indices.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No episodes are selected with {:?}",
+                filters
+            ));
+        }
+        self.create_replay_buffer(converter, Some(episode_indices))
+    }
+
+    /// Creates replay buffer from the dataset, converting episodes on worker threads.
+    ///
+    /// Episodes are split into chunks of [`IngestConfig::chunk_size`] episodes. A reader thread
+    /// copies the numpy arrays of the episodes in a chunk while holding the GIL, and
+    /// [`IngestConfig::n_workers`] threads convert them with
+    /// [`MinariConverter::convert_episode_arrays()`] without the GIL, while the next chunk
+    /// is read. Converters not implementing it, see
+    /// [`MinariConverter::converts_episode_arrays()`], convert episodes on the reader thread.
+    /// The order of transitions is the same as [`MinariDataset::create_replay_buffer()`].
+    ///
+    /// * `converter`: converter for observation and action.
+    /// * `episode_indices`: indices of episodes to be included in the replay buffer.
+    ///   If `None`, all episodes are included.
+    /// * `config`: configuration of the workers.
+    /// * `progress`: called with the number of ingested episodes and the total number of
+    ///   episodes, whenever a chunk is pushed into the replay buffer.
+    ///
+    /// This method must not be called while the GIL is held by the calling thread.
+    pub fn create_replay_buffer_parallel<T>(
+        &self,
+        converter: &T,
+        episode_indices: Option<Vec<usize>>,
+        config: &IngestConfig,
+        mut progress: impl FnMut(usize, usize),
+    ) -> Result<SimpleReplayBuffer<T::ObsBatch, T::ActBatch>>
+    where
+        T: MinariConverter + Sync,
+        T::ObsBatch: std::fmt::Debug + Send,
+        T::ActBatch: std::fmt::Debug + Send,
+    {
+        let episode_indices = match episode_indices {
+            Some(ixs) => ixs,
+            None => {
+                let n = Python::with_gil(|py| -> Result<usize> {
+                    Ok(self.dataset.getattr(py, "total_episodes")?.extract(py)?)
+                })?;
+                (0..n).collect()
+            }
+        };
+        let num_transitions = self.get_num_transitions(Some(episode_indices.clone()))?;
+        let mut replay_buffer = Self::build_replay_buffer(num_transitions);
+
+        let n_episodes = episode_indices.len();
+        let chunks = episode_indices
+            .chunks(config.chunk_size.max(1))
+            .collect::<Vec<_>>();
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(config.n_workers.max(1))
+            .build()?;
+        let (sender, receiver) = mpsc::sync_channel::<Result<Vec<Episode<T>>>>(1);
+
+        std::thread::scope(|s| -> Result<()> {
+            let chunks = &chunks;
+            s.spawn(move || {
+                for chunk in chunks.iter() {
+                    let episodes = self.read_episodes(chunk, converter);
+                    let failed = episodes.is_err();
+                    if sender.send(episodes).is_err() || failed {
+                        break;
+                    }
+                }
+            });
+
+            // Chunks are received in the order of episodes
+            let mut n_ingested = 0;
+            for (chunk, episodes) in chunks.iter().zip(receiver) {
+                let episodes = episodes?;
+                let batches = pool.install(|| {
+                    episodes
+                        .into_par_iter()
+                        .map(|ep| ep.into_batch(converter))
+                        .collect::<Result<Vec<_>>>()
+                })?;
+                for batch in batches {
+                    replay_buffer.push(batch)?;
+                }
+                n_ingested += chunk.len();
+                progress(n_ingested, n_episodes);
+            }
+            Ok(())
+        })?;
+
+        Self::log_stats(&replay_buffer, num_transitions);
+
+        Ok(replay_buffer)
+    }
+
+    /// Reads episodes with the given indices, holding the GIL.
+    fn read_episodes<T: MinariConverter>(
+        &self,
+        episode_indices: &[usize],
+        converter: &T,
+    ) -> Result<Vec<Episode<T>>>
+    where
+        T::ObsBatch: std::fmt::Debug,
+        T::ActBatch: std::fmt::Debug,
+    {
+        Python::with_gil(|py| {
+            let episodes =
+                self.dataset
+                    .call_method1(py, "iterate_episodes", (episode_indices.to_vec(),))?;
+            PyIterator::from_object(py, &episodes)?
+                .map(|ep| {
+                    let ep = ep?;
+                    match converter.converts_episode_arrays() {
+                        true => Ok(Episode::Arrays(self.extract_arrays_in_episode(py, ep)?)),
+                        false => Ok(Episode::Batch(
+                            self.extract_transitions_in_episode(py, ep, converter)?,
+                        )),
+                    }
+                })
+                .collect()
+        })
+    }
+
+    /// Copies the numpy arrays of an episode, with rewards relabeled.
+    fn extract_arrays_in_episode(&self, py: Python, ep: &PyAny) -> Result<EpisodeArrays> {
+        let (reward, is_terminated, is_truncated) = self.extract_rewards_and_flags(py, ep)?;
+        Ok(EpisodeArrays {
+            obs: EpisodeArray::from_py(ep.getattr("observations")?)?,
+            act: EpisodeArray::from_py(ep.getattr("actions")?)?,
+            reward,
+            is_terminated,
+            is_truncated,
+        })
+    }
+
+    /// Returns the rewards, relabeled with the reward transform, and the flags of an episode.
+    fn extract_rewards_and_flags(
+        &self,
+        py: Python,
+        ep: &PyAny,
+    ) -> Result<(Vec<f32>, Vec<i8>, Vec<i8>)> {
+        let reward = util::vec::pyany_to_f32vec(py, ep.getattr("rewards")?)?;
+        let reward = match self.reward_transform.as_ref() {
+            Some(transform) => transform.apply(py, ep, reward)?,
+            None => reward,
+        };
+        let is_terminated = util::vec::pyany_to_vec::<i8>(py, ep.getattr("terminations")?)?;
+        let is_truncated = util::vec::pyany_to_vec::<i8>(py, ep.getattr("truncations")?)?;
+        Ok((reward, is_terminated, is_truncated))
+    }
+
+    fn build_replay_buffer<O, A>(capacity: usize) -> SimpleReplayBuffer<O, A>
+    where
+        O: BatchBase,
+        A: BatchBase,
+    {
+        SimpleReplayBuffer::build(&SimpleReplayBufferConfig {
+            capacity,
+            seed: 0,
+            per_config: None,
+            mc_gamma: None,
+            bootstrap: None,
+            memory_limit: None,
+        })
+    }
+
+    fn log_stats<O, A>(replay_buffer: &SimpleReplayBuffer<O, A>, num_transitions: usize)
+    where
+        O: BatchBase,
+        A: BatchBase,
+    {
+        // Stats in the replay buffer
+        log::info!("In replay buffer:");
+        log::info!("{} transitions", num_transitions);
+        log::info!("{} terminated flags", replay_buffer.num_terminated_flags());
+        log::info!("{} truncated flags", replay_buffer.num_truncated_flags());
+        log::info!("{} reward sum", replay_buffer.sum_rewards());
+    }
+
     fn extract_transitions_in_episode<T: MinariConverter>(
+        &self,
         py: Python,
         ep: &PyAny,
         converter: &T,
@@ -120,17 +482,12 @@ impl MinariDataset {
         // Extract episode as batch
         let obs = ep.getattr("observations")?;
         let act = ep.getattr("actions")?;
-        let rew = ep.getattr("rewards")?;
-        let trm = ep.getattr("terminations")?;
-        let trn = ep.getattr("truncations")?;
 
         // Creates batch
         let next_obs = converter.convert_observation_batch_next(&obs)?;
         let obs = converter.convert_observation_batch(&obs)?;
         let act = converter.convert_action_batch(&act)?;
-        let reward = util::vec::pyany_to_f32vec(py, &rew)?;
-        let is_terminated = util::vec::pyany_to_vec::<i8>(py, &trm)?;
-        let is_truncated = util::vec::pyany_to_vec::<i8>(py, &trn)?;
+        let (reward, is_terminated, is_truncated) = self.extract_rewards_and_flags(py, ep)?;
 
         Ok(GenericTransitionBatch {
             obs,
@@ -141,6 +498,8 @@ impl MinariDataset {
             is_truncated,
             weight: None,
             ix_sample: None,
+            mask: None,
+            mc_return: None,
         })
     }
 
@@ -211,7 +570,98 @@ impl MinariDataset {
             count_steps: 0,
             max_steps: None,
             ref_score_minmax,
+            last_obs: None,
+            last_info: None,
             // dataset: self.dataset.clone(),
         })
     }
 }
+
+#[cfg(all(test, feature = "candle"))]
+mod tests {
+    use super::*;
+    use crate::{util::candle::TensorBatch, AutoConverter, AutoConverterConfig};
+    use candle_core::Tensor;
+    use pyo3::types::PyModule;
+
+    /// A dataset with the same interface as `minari.MinariDataset`.
+    const DATASET: &str = r#"
+import numpy as np
+
+class Box:
+    def __init__(self, shape):
+        self.shape = shape
+
+class Dict:
+    def __init__(self, spaces):
+        self.spaces = spaces
+
+class EpisodeData:
+    def __init__(self, id):
+        n = 3 + id
+        rng = np.random.default_rng(id)
+        self.id = id
+        self.observations = {
+            "observation": rng.normal(size=(n + 1, 4)),
+            "desired_goal": rng.normal(size=(n + 1, 2)),
+        }
+        self.actions = rng.normal(size=(n, 2))
+        self.rewards = rng.normal(size=n)
+        self.terminations = np.zeros(n, dtype=np.int8)
+        self.terminations[-1] = id % 2
+        self.truncations = np.zeros(n, dtype=np.int8)
+        self.truncations[-1] = 1 - id % 2
+        self.infos = {}
+
+class Dataset:
+    total_episodes = 10
+    observation_space = Dict({"observation": Box((4,)), "desired_goal": Box((2,))})
+    action_space = Box((2,))
+
+    def iterate_episodes(self, episode_indices=None):
+        if episode_indices is None:
+            episode_indices = range(self.total_episodes)
+        return (EpisodeData(i) for i in episode_indices)
+"#;
+
+    fn dataset() -> Result<MinariDataset> {
+        Python::with_gil(|py| {
+            let module = PyModule::from_code(py, DATASET, "dataset.py", "dataset")?;
+            Ok(MinariDataset {
+                dataset: module.getattr("Dataset")?.call0()?.to_object(py),
+                reward_transform: None,
+            })
+        })
+    }
+
+    #[test]
+    fn test_create_replay_buffer_parallel() -> Result<()> {
+        let dataset = dataset()?.reward_transform(Some(RewardTransform::Scale {
+            scale: 2.0,
+            shift: -1.0,
+        }));
+        let config = AutoConverterConfig::default().normalize_obs(true);
+        let mut converter = AutoConverter::new(config, &dataset)?;
+        let mut buffer1 = dataset.create_replay_buffer(&mut converter, None)?;
+        let config = IngestConfig::default().n_workers(3).chunk_size(2);
+        let mut n_progress = vec![];
+        let mut buffer2 =
+            dataset.create_replay_buffer_parallel(&converter, None, &config, |n, _| {
+                n_progress.push(n)
+            })?;
+        assert_eq!(n_progress, vec![2, 4, 6, 8, 10]);
+
+        // Both buffers are sampled at the same indices with the same seed
+        let n = buffer1.len();
+        assert_eq!(n, buffer2.len());
+        let (batch1, batch2) = (buffer1.batch(n)?, buffer2.batch(n)?);
+        let to_vec2 = |t: TensorBatch| Tensor::from(t).to_vec2::<f32>();
+        assert_eq!(to_vec2(batch1.obs)?, to_vec2(batch2.obs)?);
+        assert_eq!(to_vec2(batch1.next_obs)?, to_vec2(batch2.next_obs)?);
+        assert_eq!(to_vec2(batch1.act)?, to_vec2(batch2.act)?);
+        assert_eq!(batch1.reward, batch2.reward);
+        assert_eq!(batch1.is_terminated, batch2.is_terminated);
+        assert_eq!(batch1.is_truncated, batch2.is_truncated);
+        Ok(())
+    }
+}
@@ -13,7 +13,7 @@ use border_core::{
 };
 use pyo3::{
     types::{IntoPyDict, PyTuple},
-    PyObject, Python,
+    PyObject, Python, ToPyObject,
 };
 
 /// Environment interface for Minari datasets.
@@ -24,6 +24,8 @@ pub struct MinariEnv<T: MinariConverter> {
     pub(crate) count_steps: usize,
     pub(crate) max_steps: Option<usize>,
     pub(crate) ref_score_minmax: Option<(f32, f32)>,
+    pub(crate) last_obs: Option<PyObject>, // Python observation of the last step
+    pub(crate) last_info: Option<PyObject>, // `info` of the last step
 }
 
 impl<T: MinariConverter> Env for MinariEnv<T> {
@@ -34,6 +36,8 @@ impl<T: MinariConverter> Env for MinariEnv<T> {
 
     fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
         assert_eq!(is_done, None);
+        self.last_obs = None;
+        self.last_info = None;
 
         pyo3::Python::with_gil(|py| {
             let ret_values = if let Some(seed) = self.initial_seed {
@@ -87,6 +91,8 @@ impl<T: MinariConverter> Env for MinariEnv<T> {
                 let (is_terminated, is_truncated) = is_done(step);
                 let is_terminated = vec![is_terminated];
                 let is_truncated = vec![is_truncated];
+                self.last_obs = Some(step.get_item(0).to_object(py));
+                self.last_info = Some(step.get_item(4).to_object(py));
                 let info = ();
                 let init_obs = None;
                 let act = act.clone();
@@ -1,7 +1,82 @@
 //! Evaluator for Minari environments.
 use crate::{MinariConverter, MinariEnv};
 use anyhow::Result;
-use border_core::{record::Record, Agent, Env, Evaluator, ReplayBufferBase};
+use border_core::{
+    record::{Record, RecordValue::Scalar},
+    Agent, Env, EvalResult, Evaluator, ReplayBufferBase, Step,
+};
+use numpy::PyArrayDyn;
+use pyo3::Python;
+
+/// Criterion of the success of episodes in [`MinariEvaluator`].
+///
+/// An episode is successful if the criterion holds at any step of the episode.
+pub enum SuccessCriterion<T: MinariConverter> {
+    /// `info[key]` returned by the environment is true, e.g., `success` in Point Maze and AntMaze.
+    InfoKey(String),
+
+    /// The episode is terminated, e.g., all tasks are completed in Kitchen.
+    Terminated,
+
+    /// The distance between the achieved and desired goals, given by the keys of `Dict`
+    /// observations, is less than or equal to the threshold.
+    GoalDistance {
+        /// Key of the achieved goal in observations.
+        achieved_goal: String,
+        /// Key of the desired goal in observations.
+        desired_goal: String,
+        /// Threshold of the distance.
+        threshold: f32,
+    },
+
+    /// The predicate on the converted observation returns `true`.
+    Predicate(Box<dyn Fn(&T::Obs) -> bool + Send + Sync>),
+}
+
+impl<T: MinariConverter> SuccessCriterion<T> {
+    /// Creates a criterion with a predicate on observations.
+    pub fn predicate(f: impl Fn(&T::Obs) -> bool + Send + Sync + 'static) -> Self {
+        Self::Predicate(Box::new(f))
+    }
+
+    fn is_success(&self, env: &MinariEnv<T>, step: &Step<MinariEnv<T>>) -> Result<bool> {
+        match self {
+            Self::InfoKey(key) => Python::with_gil(|py| match env.last_info.as_ref() {
+                Some(info) => {
+                    let v = info.as_ref(py).call_method1("get", (key.as_str(),))?;
+                    Ok(!v.is_none() && v.is_true()?)
+                }
+                None => Ok(false),
+            }),
+            Self::Terminated => Ok(step.is_terminated[0] == 1),
+            Self::GoalDistance {
+                achieved_goal,
+                desired_goal,
+                threshold,
+            } => Python::with_gil(|py| match env.last_obs.as_ref() {
+                Some(obs) => {
+                    let obs = obs.as_ref(py);
+                    let to_vec = |key: &str| -> Result<Vec<f32>> {
+                        let arr = py
+                            .import("numpy")?
+                            .call_method1("asarray", (obs.get_item(key)?, "float32"))?;
+                        let arr: &PyArrayDyn<f32> = arr.extract()?;
+                        Ok(arr.to_owned_array().into_raw_vec())
+                    };
+                    let dist = to_vec(achieved_goal)?
+                        .iter()
+                        .zip(to_vec(desired_goal)?.iter())
+                        .map(|(a, d)| (a - d).powi(2))
+                        .sum::<f32>()
+                        .sqrt();
+                    Ok(dist <= *threshold)
+                }
+                None => Ok(false),
+            }),
+            Self::Predicate(f) => Ok(f(&step.obs)),
+        }
+    }
+}
 
 /// An evaluator for Minari environments.
 ///
@@ -10,9 +85,14 @@ use border_core::{record::Record, Agent, Env, Evaluator, ReplayBufferBase};
 /// The average return over episodes is returned.
 /// If the environment has ref_min_score and ref_max_score, the normalized score is also returned
 /// in the record.
+///
+/// If [`SuccessCriterion`] is given with [`MinariEvaluator::success_criterion()`], the ratio of
+/// successful episodes is also returned in the record as `Success rate`, which is the standard
+/// metric for goal-reaching tasks like AntMaze and Kitchen.
 pub struct MinariEvaluator<T: MinariConverter> {
     n_episodes: usize,
     env: MinariEnv<T>,
+    success_criterion: Option<SuccessCriterion<T>>,
 }
 
 impl<T: MinariConverter> Evaluator<MinariEnv<T>> for MinariEvaluator<T> {
@@ -22,28 +102,34 @@ impl<T: MinariConverter> Evaluator<MinariEnv<T>> for MinariEvaluator<T> {
     /// The average return over episodes is returned.
     /// If the environment has ref_min_score and ref_max_score, the normalized score is also returned
     /// in the record.
-    fn evaluate<R: ReplayBufferBase>(
+    fn evaluate_result<R: ReplayBufferBase>(
         &mut self,
         policy: &mut Box<dyn Agent<MinariEnv<T>, R>>,
-    ) -> Result<(f32, Record)> {
+    ) -> Result<EvalResult> {
         log::debug!("Evaluation");
         let mut r_total = 0f32;
+        let mut n_success = 0;
 
         // Episode loop
         for ix in 0..self.n_episodes {
             log::trace!("Episode: {:?}", ix);
             let mut prev_obs = self.env.reset_with_index(ix)?;
+            let mut is_success = false;
 
             // Environment loop
             loop {
                 let act = policy.sample(&prev_obs);
                 let (step, _) = self.env.step(&act);
                 r_total += step.reward[0];
+                if let Some(criterion) = self.success_criterion.as_ref() {
+                    is_success = is_success || criterion.is_success(&self.env, &step)?;
+                }
                 if step.is_done() {
                     break;
                 }
                 prev_obs = step.obs;
             }
+            n_success += is_success as usize;
         }
 
         // Average return
@@ -58,7 +144,13 @@ impl<T: MinariConverter> Evaluator<MinariEnv<T>> for MinariEvaluator<T> {
             record = Record::from_scalar(name, score);
         }
 
-        Ok((score, record))
+        // Success rate
+        if self.success_criterion.is_some() {
+            let rate = n_success as f32 / self.n_episodes as f32;
+            record.insert("Success rate", Scalar(rate));
+        }
+
+        Ok(EvalResult::new(score, record))
     }
 }
 
@@ -68,6 +160,149 @@ impl<T: MinariConverter> MinariEvaluator<T> {
     /// `env` - Instance of the environment.
     /// `n_episodes` - The number of episodes for evaluation.
     pub fn new(env: MinariEnv<T>, n_episodes: usize) -> Result<Self> {
-        Ok(Self { n_episodes, env })
+        Ok(Self {
+            n_episodes,
+            env,
+            success_criterion: None,
+        })
+    }
+
+    /// Sets the criterion of the success of episodes.
+    pub fn success_criterion(mut self, v: Option<SuccessCriterion<T>>) -> Self {
+        self.success_criterion = v;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use border_core::agent_dev::{CorridorAct, CorridorActBatch, CorridorObs, CorridorObsBatch};
+    use pyo3::{types::PyModule, PyAny, PyObject, ToPyObject};
+
+    /// An environment moving the agent towards the goal at position 3.
+    const ENV: &str = r#"
+class Env:
+    def __init__(self):
+        self.pos = 0
+
+    def reset(self, seed=None):
+        self.pos = 0
+        return self.obs(), {}
+
+    def step(self, act):
+        self.pos += 1 if act == 1 else -1
+        terminated = self.pos >= 3
+        return self.obs(), 0.0, terminated, False, {"success": terminated}
+
+    def obs(self):
+        return {
+            "position": self.pos,
+            "achieved_goal": [float(self.pos)],
+            "desired_goal": [3.0],
+        }
+
+    def close(self):
+        pass
+"#;
+
+    struct CorridorConverter;
+
+    impl MinariConverter for CorridorConverter {
+        type Obs = CorridorObs;
+        type Act = CorridorAct;
+        type ObsBatch = CorridorObsBatch;
+        type ActBatch = CorridorActBatch;
+
+        fn convert_observation(&self, obj: &PyAny) -> Result<Self::Obs> {
+            Ok(CorridorObs(obj.get_item("position")?.extract()?))
+        }
+
+        fn convert_action(&self, act: Self::Act) -> Result<PyObject> {
+            Ok(Python::with_gil(|py| act.0.to_object(py)))
+        }
+
+        fn convert_observation_batch(&self, _obj: &PyAny) -> Result<Self::ObsBatch> {
+            unimplemented!();
+        }
+
+        fn convert_observation_batch_next(&self, _obj: &PyAny) -> Result<Self::ObsBatch> {
+            unimplemented!();
+        }
+
+        fn convert_action_batch(&self, _obj: &PyAny) -> Result<Self::ActBatch> {
+            unimplemented!();
+        }
+
+        fn env_params(&self, _py: Python<'_>) -> Vec<(&str, PyObject)> {
+            vec![]
+        }
+    }
+
+    fn env() -> Result<MinariEnv<CorridorConverter>> {
+        let env = Python::with_gil(|py| -> Result<PyObject> {
+            let module = PyModule::from_code(py, ENV, "env.py", "env")?;
+            Ok(module.getattr("Env")?.call0()?.to_object(py))
+        })?;
+        Ok(MinariEnv {
+            converter: CorridorConverter,
+            env,
+            initial_seed: None,
+            count_steps: 0,
+            max_steps: None,
+            ref_score_minmax: None,
+            last_obs: None,
+            last_info: None,
+        })
+    }
+
+    fn criteria() -> Vec<SuccessCriterion<CorridorConverter>> {
+        vec![
+            SuccessCriterion::InfoKey("success".into()),
+            SuccessCriterion::Terminated,
+            SuccessCriterion::GoalDistance {
+                achieved_goal: "achieved_goal".into(),
+                desired_goal: "desired_goal".into(),
+                threshold: 0.5,
+            },
+            SuccessCriterion::predicate(|obs: &CorridorObs| obs.0 >= 3),
+        ]
+    }
+
+    #[test]
+    fn test_success_criteria() -> Result<()> {
+        let mut env = env()?;
+        env.reset(None)?;
+        for t in 1..=3 {
+            let (step, _) = env.step(&CorridorAct(1));
+            for criterion in criteria().iter() {
+                assert_eq!(criterion.is_success(&env, &step)?, t == 3);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_reset_clears_last_step() -> Result<()> {
+        let mut env = env()?;
+        env.reset(None)?;
+        for _ in 0..3 {
+            env.step(&CorridorAct(1));
+        }
+        assert!(env.last_obs.is_some() && env.last_info.is_some());
+
+        env.reset(None)?;
+        assert!(env.last_obs.is_none() && env.last_info.is_none());
+        let (step, _) = env.step(&CorridorAct(0));
+        for criterion in criteria().iter() {
+            assert!(!criterion.is_success(&env, &step)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_criterion_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SuccessCriterion<CorridorConverter>>();
     }
 }
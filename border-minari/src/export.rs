@@ -0,0 +1,372 @@
+//! Export of episodes collected in Border environments as Minari datasets.
+use crate::MinariDataset;
+use anyhow::Result;
+use border_core::{generic_replay_buffer::Episode, Env, Policy, Step};
+use numpy::PyArray1;
+use pyo3::{
+    types::{IntoPyDict, PyDict, PyList},
+    PyObject, Python, ToPyObject,
+};
+
+/// Conversion of observations and actions of an environment into Python objects
+/// for [`MinariDatasetBuilder`].
+pub trait MinariExportConverter<E: Env> {
+    /// Converts the observations of an episode, including the one after the last action,
+    /// into a Python object of stacked observations, e.g., a `numpy.ndarray` whose first
+    /// axis is time, or a `dict` of them for `Dict` observation spaces.
+    fn convert_observations(&self, py: Python<'_>, obs: &[E::Obs]) -> Result<PyObject>;
+
+    /// Converts the actions of an episode into a Python object of stacked actions.
+    fn convert_actions(&self, py: Python<'_>, act: &[E::Act]) -> Result<PyObject>;
+
+    /// Returns the observation space, a `gymnasium.spaces.Space`.
+    ///
+    /// It can be `None` if it is given by the environment set with
+    /// [`MinariDatasetBuilder::env_id()`].
+    fn observation_space(&self, _py: Python<'_>) -> Result<Option<PyObject>> {
+        Ok(None)
+    }
+
+    /// Returns the action space, a `gymnasium.spaces.Space`.
+    ///
+    /// It can be `None` if it is given by the environment set with
+    /// [`MinariDatasetBuilder::env_id()`].
+    fn action_space(&self, _py: Python<'_>) -> Result<Option<PyObject>> {
+        Ok(None)
+    }
+}
+
+/// Builds a Minari dataset from episodes collected in a Border environment.
+///
+/// Steps are given with [`MinariDatasetBuilder::push()`] after
+/// [`MinariDatasetBuilder::reset()`], or episodes are collected with a policy with
+/// [`MinariDatasetBuilder::collect()`]. Episodes recorded with
+/// [`EpisodeWriter`](border_core::generic_replay_buffer::EpisodeWriter) can also be added with
+/// [`MinariDatasetBuilder::add_episode()`]. Each episode is converted into Python objects with
+/// [`MinariExportConverter`] when it ends.
+///
+/// [`MinariDatasetBuilder::create_dataset()`] writes the dataset with
+/// [`minari.create_dataset_from_buffers()`](https://minari.farama.org/api/minari_functions/#minari.create_dataset_from_buffers),
+/// so that it can be loaded with `minari.load_dataset()` in Python, or with
+/// [`MinariDataset::load_dataset()`]:
+///
+/// ```no_run
+/// # use anyhow::Result;
+/// # use border_core::Env;
+/// # use border_minari::{MinariDatasetBuilder, MinariExportConverter};
+/// # fn export<E: Env, C: MinariExportConverter<E>>(env: &mut E, policy: &mut impl border_core::Policy<E>, converter: C) -> Result<()> {
+/// let mut builder = MinariDatasetBuilder::new(converter)
+///     .env_id("CartPole-v1")
+///     .algorithm_name("dqn");
+/// builder.collect(env, policy, 100)?;
+/// let dataset = builder.create_dataset("cartpole/dqn-v0")?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Environments are assumed not to be vectorized.
+pub struct MinariDatasetBuilder<E: Env, C: MinariExportConverter<E>> {
+    converter: C,
+    buffer: Vec<PyObject>,
+    episode: Option<Episode<E::Obs, E::Act>>,
+    env_id: Option<String>,
+    algorithm_name: Option<String>,
+    author: Option<String>,
+    code_permalink: Option<String>,
+    description: Option<String>,
+}
+
+impl<E: Env, C: MinariExportConverter<E>> MinariDatasetBuilder<E, C> {
+    /// Constructs a builder with a converter of observations and actions.
+    pub fn new(converter: C) -> Self {
+        Self {
+            converter,
+            buffer: vec![],
+            episode: None,
+            env_id: None,
+            algorithm_name: None,
+            author: None,
+            code_permalink: None,
+            description: None,
+        }
+    }
+
+    /// Sets the ID of the Gymnasium environment in which the episodes are collected,
+    /// with which the environment is recovered from the dataset.
+    pub fn env_id(mut self, v: impl Into<String>) -> Self {
+        self.env_id = Some(v.into());
+        self
+    }
+
+    /// Sets the name of the algorithm with which the episodes are collected.
+    pub fn algorithm_name(mut self, v: impl Into<String>) -> Self {
+        self.algorithm_name = Some(v.into());
+        self
+    }
+
+    /// Sets the author of the dataset.
+    pub fn author(mut self, v: impl Into<String>) -> Self {
+        self.author = Some(v.into());
+        self
+    }
+
+    /// Sets the link to the code with which the episodes are collected.
+    pub fn code_permalink(mut self, v: impl Into<String>) -> Self {
+        self.code_permalink = Some(v.into());
+        self
+    }
+
+    /// Sets the description of the dataset.
+    pub fn description(mut self, v: impl Into<String>) -> Self {
+        self.description = Some(v.into());
+        self
+    }
+
+    /// Returns the number of finished episodes.
+    pub fn n_episodes(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Starts an episode with the initial observation.
+    ///
+    /// Steps of the unfinished episode, if any, are discarded.
+    pub fn reset(&mut self, init_obs: E::Obs) {
+        self.episode = Some(Episode {
+            obs: vec![init_obs],
+            act: vec![],
+            reward: vec![],
+            is_terminated: vec![],
+            is_truncated: vec![],
+        });
+    }
+
+    /// Adds a step to the current episode, converting the episode if it ends.
+    ///
+    /// The next episode starts from [`Step::init_obs`] if it is given.
+    pub fn push(&mut self, step: &Step<E>) -> Result<()> {
+        let episode = self
+            .episode
+            .as_mut()
+            .ok_or_else(|| anyhow::anyhow!("reset() must be called first"))?;
+        episode.obs.push(step.obs.clone());
+        episode.act.push(step.act.clone());
+        episode.reward.extend(step.reward.iter());
+        episode.is_terminated.extend(step.is_terminated.iter());
+        episode.is_truncated.extend(step.is_truncated.iter());
+
+        if step.is_done() {
+            let episode = self.episode.take().unwrap();
+            self.add_episode(&episode)?;
+            if let Some(init_obs) = step.init_obs.clone() {
+                self.reset(init_obs);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds an episode to the dataset.
+    ///
+    /// An error is returned if the episode is empty or the lengths of its fields are
+    /// inconsistent, i.e., `obs.len() != act.len() + 1` or the numbers of rewards and flags
+    /// differ from the number of actions.
+    pub fn add_episode(&mut self, episode: &Episode<E::Obs, E::Act>) -> Result<()> {
+        let n_steps = episode.act.len();
+        if n_steps == 0 {
+            return Err(anyhow::anyhow!("Episode has no steps"));
+        }
+        if episode.obs.len() != n_steps + 1
+            || episode.reward.len() != n_steps
+            || episode.is_terminated.len() != n_steps
+            || episode.is_truncated.len() != n_steps
+        {
+            return Err(anyhow::anyhow!(
+                "Inconsistent lengths in episode: {} observations, {} actions, {} rewards, {} terminations and {} truncations",
+                episode.obs.len(),
+                n_steps,
+                episode.reward.len(),
+                episode.is_terminated.len(),
+                episode.is_truncated.len()
+            ));
+        }
+
+        let converter = &self.converter;
+        let ep = Python::with_gil(|py| -> Result<PyObject> {
+            let to_bool = |v: &[i8]| v.iter().map(|&v| v != 0).collect::<Vec<_>>();
+            let ep = PyDict::new(py);
+            ep.set_item(
+                "observations",
+                converter.convert_observations(py, &episode.obs)?,
+            )?;
+            ep.set_item("actions", converter.convert_actions(py, &episode.act)?)?;
+            ep.set_item("rewards", PyArray1::from_slice(py, &episode.reward))?;
+            ep.set_item(
+                "terminations",
+                PyArray1::from_vec(py, to_bool(&episode.is_terminated)),
+            )?;
+            ep.set_item(
+                "truncations",
+                PyArray1::from_vec(py, to_bool(&episode.is_truncated)),
+            )?;
+            Ok(ep.to_object(py))
+        })?;
+        self.buffer.push(ep);
+        Ok(())
+    }
+
+    /// Collects episodes with a policy.
+    pub fn collect<P: Policy<E>>(
+        &mut self,
+        env: &mut E,
+        policy: &mut P,
+        n_episodes: usize,
+    ) -> Result<()> {
+        for _ in 0..n_episodes {
+            let mut obs = env.reset(None)?;
+            self.reset(obs.clone());
+            loop {
+                let act = policy.sample(&obs);
+                let (step, _) = env.step(&act);
+                let is_done = step.is_done();
+                obs = step.obs.clone();
+                self.push(&step)?;
+                if is_done {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the episodes as a Minari dataset with the given ID, e.g., `cartpole/dqn-v0`.
+    ///
+    /// The dataset is saved in the directory of local datasets of Minari.
+    pub fn create_dataset(self, dataset_id: impl AsRef<str>) -> Result<MinariDataset> {
+        if self.buffer.is_empty() {
+            return Err(anyhow::anyhow!("No episodes to be written"));
+        }
+        log::info!(
+            "Create Minari dataset {} with {} episodes",
+            dataset_id.as_ref(),
+            self.buffer.len()
+        );
+
+        Python::with_gil(|py| {
+            let minari = py.import("minari")?;
+            let kwargs: Vec<(&str, PyObject)> = vec![
+                ("env", self.env_id.to_object(py)),
+                ("algorithm_name", self.algorithm_name.to_object(py)),
+                ("author", self.author.to_object(py)),
+                ("code_permalink", self.code_permalink.to_object(py)),
+                ("description", self.description.to_object(py)),
+                (
+                    "observation_space",
+                    self.converter.observation_space(py)?.to_object(py),
+                ),
+                (
+                    "action_space",
+                    self.converter.action_space(py)?.to_object(py),
+                ),
+            ];
+            let buffer = PyList::new(py, self.buffer.iter());
+            let dataset = minari
+                .getattr("create_dataset_from_buffers")?
+                .call((dataset_id.as_ref(), buffer), Some(kwargs.into_py_dict(py)))?;
+            Ok(MinariDataset {
+                dataset: dataset.to_object(py),
+                reward_transform: None,
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{util, EpisodeArray};
+    use border_core::agent_dev::{Corridor, CorridorAct, CorridorObs};
+    use ndarray::array;
+
+    struct CorridorConverter;
+
+    impl MinariExportConverter<Corridor> for CorridorConverter {
+        fn convert_observations(&self, py: Python<'_>, obs: &[CorridorObs]) -> Result<PyObject> {
+            let obs = obs.iter().map(|o| o.0 as i64).collect::<Vec<_>>();
+            Ok(PyArray1::from_vec(py, obs).to_object(py))
+        }
+
+        fn convert_actions(&self, py: Python<'_>, act: &[CorridorAct]) -> Result<PyObject> {
+            let act = act.iter().map(|a| a.0 as i64).collect::<Vec<_>>();
+            Ok(PyArray1::from_vec(py, act).to_object(py))
+        }
+    }
+
+    fn episode() -> Episode<CorridorObs, CorridorAct> {
+        Episode {
+            obs: vec![CorridorObs(0), CorridorObs(1), CorridorObs(2)],
+            act: vec![CorridorAct(1), CorridorAct(1)],
+            reward: vec![0.0, 1.0],
+            is_terminated: vec![0, 1],
+            is_truncated: vec![0, 0],
+        }
+    }
+
+    #[test]
+    fn test_add_episode() -> Result<()> {
+        let mut builder = MinariDatasetBuilder::<Corridor, _>::new(CorridorConverter);
+        builder.add_episode(&episode())?;
+        assert_eq!(builder.n_episodes(), 1);
+
+        Python::with_gil(|py| -> Result<()> {
+            let ep = builder.buffer[0].as_ref(py);
+            assert_eq!(
+                EpisodeArray::from_py(ep.get_item("observations")?)?,
+                EpisodeArray::Array(array![0.0f32, 1.0, 2.0].into_dyn())
+            );
+            assert_eq!(
+                EpisodeArray::from_py(ep.get_item("actions")?)?,
+                EpisodeArray::Array(array![1.0f32, 1.0].into_dyn())
+            );
+            assert_eq!(
+                util::vec::pyany_to_f32vec(py, ep.get_item("rewards")?)?,
+                vec![0.0, 1.0]
+            );
+            assert_eq!(
+                EpisodeArray::from_py(ep.get_item("terminations")?)?,
+                EpisodeArray::Array(array![0.0f32, 1.0].into_dyn())
+            );
+            assert_eq!(
+                EpisodeArray::from_py(ep.get_item("truncations")?)?,
+                EpisodeArray::Array(array![0.0f32, 0.0].into_dyn())
+            );
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_add_inconsistent_episode() {
+        let mut builder = MinariDatasetBuilder::<Corridor, _>::new(CorridorConverter);
+
+        let mut ep = episode();
+        ep.obs.pop();
+        assert!(builder.add_episode(&ep).is_err());
+
+        let mut ep = episode();
+        ep.reward.push(0.0);
+        assert!(builder.add_episode(&ep).is_err());
+
+        let mut ep = episode();
+        ep.is_truncated.clear();
+        assert!(builder.add_episode(&ep).is_err());
+
+        let ep = Episode {
+            obs: vec![CorridorObs(0)],
+            act: vec![],
+            reward: vec![],
+            is_terminated: vec![],
+            is_truncated: vec![],
+        };
+        assert!(builder.add_episode(&ep).is_err());
+        assert_eq!(builder.n_episodes(), 0);
+    }
+}
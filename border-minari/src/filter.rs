@@ -0,0 +1,260 @@
+//! Selection of episodes in Minari datasets.
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use std::{collections::HashSet, fmt};
+
+/// Summary of an episode in a Minari dataset, given to [`EpisodeFilter`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct EpisodeInfo {
+    /// Index of the episode in the dataset.
+    pub id: usize,
+
+    /// The number of transitions in the episode.
+    pub n_steps: usize,
+
+    /// Sum of rewards in the episode.
+    pub episode_return: f32,
+
+    /// If `true`, the episode ends with termination.
+    pub is_terminated: bool,
+
+    /// If `true`, the episode ends with truncation.
+    pub is_truncated: bool,
+
+    /// If `true`, the task is achieved at the end of the episode.
+    ///
+    /// It is the last value of `success` in the infos of the episode, e.g., in D4RL Point Maze
+    /// and Adroit datasets. If the infos have no `success`, it is the same as
+    /// [`EpisodeInfo::is_terminated`].
+    pub is_success: bool,
+}
+
+/// Filter of episodes used to create replay buffers from a subset of a Minari dataset.
+///
+/// Filters are applied in order with [`MinariDataset::select_episodes()`], which returns
+/// the indices of the selected episodes given to [`MinariDataset::create_replay_buffer()`].
+/// For example, `[TopReturn(0.1), RandomFraction { fraction: 0.5, seed: 42 }]` selects
+/// a half of the episodes with the top 10% returns.
+///
+/// [`MinariDataset::select_episodes()`]: crate::MinariDataset::select_episodes
+/// [`MinariDataset::create_replay_buffer()`]: crate::MinariDataset::create_replay_buffer
+pub enum EpisodeFilter {
+    /// Keeps episodes with the given indices.
+    Indices(Vec<usize>),
+
+    /// Keeps the given fraction of episodes with the highest returns, e.g., expert-only data.
+    ///
+    /// At least one episode is kept.
+    TopReturn(f32),
+
+    /// Keeps episodes with returns at or above the given percentile of returns, in `[0, 100]`.
+    ///
+    /// For example, `ReturnPercentile(90.0)` keeps episodes with returns in the top 10%,
+    /// including all episodes tied with the threshold.
+    ReturnPercentile(f32),
+
+    /// Keeps successful episodes, see [`EpisodeInfo::is_success`].
+    Success,
+
+    /// Keeps the given fraction of episodes selected randomly, e.g., for data-scaling ablations.
+    RandomFraction {
+        /// Fraction of episodes to be kept.
+        fraction: f32,
+        /// Seed of the random number generator.
+        seed: u64,
+    },
+
+    /// Keeps episodes for which the predicate returns `true`.
+    Predicate(Box<dyn Fn(&EpisodeInfo) -> bool + Send + Sync>),
+}
+
+impl fmt::Debug for EpisodeFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Indices(ixs) => f.debug_tuple("Indices").field(ixs).finish(),
+            Self::TopReturn(fraction) => f.debug_tuple("TopReturn").field(fraction).finish(),
+            Self::ReturnPercentile(q) => f.debug_tuple("ReturnPercentile").field(q).finish(),
+            Self::Success => f.write_str("Success"),
+            Self::RandomFraction { fraction, seed } => f
+                .debug_struct("RandomFraction")
+                .field("fraction", fraction)
+                .field("seed", seed)
+                .finish(),
+            Self::Predicate(_) => f.write_str("Predicate(..)"),
+        }
+    }
+}
+
+impl EpisodeFilter {
+    /// Creates a filter with a predicate on [`EpisodeInfo`].
+    pub fn predicate(f: impl Fn(&EpisodeInfo) -> bool + Send + Sync + 'static) -> Self {
+        Self::Predicate(Box::new(f))
+    }
+
+    /// Applies the filter to episodes, preserving their order.
+    pub fn apply(&self, episodes: Vec<EpisodeInfo>) -> Vec<EpisodeInfo> {
+        let n_keep = |fraction: f32| {
+            ((episodes.len() as f32 * fraction).round() as usize).min(episodes.len())
+        };
+
+        match self {
+            Self::Indices(ixs) => {
+                let ids = ixs.iter().collect::<HashSet<_>>();
+                episodes
+                    .into_iter()
+                    .filter(|ep| ids.contains(&ep.id))
+                    .collect()
+            }
+            Self::TopReturn(fraction) => {
+                let n = n_keep(*fraction).max(1);
+                let mut ranked = episodes.iter().collect::<Vec<_>>();
+                ranked.sort_by(|a, b| b.episode_return.total_cmp(&a.episode_return));
+                let ids = ranked[..n.min(ranked.len())]
+                    .iter()
+                    .map(|ep| ep.id)
+                    .collect::<HashSet<_>>();
+                episodes
+                    .into_iter()
+                    .filter(|ep| ids.contains(&ep.id))
+                    .collect()
+            }
+            Self::ReturnPercentile(q) => {
+                let threshold = match return_percentile(&episodes, *q) {
+                    Some(threshold) => threshold,
+                    None => return episodes,
+                };
+                episodes
+                    .into_iter()
+                    .filter(|ep| ep.episode_return >= threshold)
+                    .collect()
+            }
+            Self::Success => episodes.into_iter().filter(|ep| ep.is_success).collect(),
+            Self::RandomFraction { fraction, seed } => {
+                let n = n_keep(*fraction);
+                let mut rng = StdRng::seed_from_u64(*seed);
+                let ids = episodes
+                    .choose_multiple(&mut rng, n)
+                    .map(|ep| ep.id)
+                    .collect::<HashSet<_>>();
+                episodes
+                    .into_iter()
+                    .filter(|ep| ids.contains(&ep.id))
+                    .collect()
+            }
+            Self::Predicate(f) => episodes.into_iter().filter(|ep| f(ep)).collect(),
+        }
+    }
+}
+
+/// Returns the percentile of episode returns with linear interpolation, as `numpy.percentile()`.
+fn return_percentile(episodes: &[EpisodeInfo], q: f32) -> Option<f32> {
+    let mut returns = episodes
+        .iter()
+        .map(|ep| ep.episode_return)
+        .collect::<Vec<_>>();
+    if returns.is_empty() {
+        return None;
+    }
+    returns.sort_by(|a, b| a.total_cmp(b));
+    let pos = (q.clamp(0.0, 100.0) / 100.0) * (returns.len() - 1) as f32;
+    let (lo, hi) = (pos.floor() as usize, pos.ceil() as usize);
+    Some(returns[lo] + (returns[hi] - returns[lo]) * (pos - lo as f32))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn episodes(returns: &[f32]) -> Vec<EpisodeInfo> {
+        returns
+            .iter()
+            .enumerate()
+            .map(|(id, &episode_return)| EpisodeInfo {
+                id,
+                n_steps: 10,
+                episode_return,
+                is_terminated: id % 2 == 0,
+                is_truncated: id % 2 == 1,
+                is_success: id % 2 == 0,
+            })
+            .collect()
+    }
+
+    fn ids(episodes: &[EpisodeInfo]) -> Vec<usize> {
+        episodes.iter().map(|ep| ep.id).collect()
+    }
+
+    #[test]
+    fn test_indices() {
+        let filter = EpisodeFilter::Indices(vec![3, 1, 10]);
+        assert_eq!(ids(&filter.apply(episodes(&[0.0; 5]))), vec![1, 3]);
+    }
+
+    #[test]
+    fn test_top_return() {
+        let eps = episodes(&[1.0, 5.0, 3.0, 4.0, 2.0]);
+        let filter = EpisodeFilter::TopReturn(0.4);
+        assert_eq!(ids(&filter.apply(eps.clone())), vec![1, 3]);
+
+        // At least one episode is kept
+        let filter = EpisodeFilter::TopReturn(0.0);
+        assert_eq!(ids(&filter.apply(eps)), vec![1]);
+    }
+
+    #[test]
+    fn test_random_fraction() {
+        let eps = episodes(&[0.0; 10]);
+        let filter = EpisodeFilter::RandomFraction {
+            fraction: 0.3,
+            seed: 42,
+        };
+        let selected = ids(&filter.apply(eps.clone()));
+        assert_eq!(selected.len(), 3);
+        assert!(selected.windows(2).all(|w| w[0] < w[1]));
+
+        // The same episodes are selected with the same seed
+        assert_eq!(ids(&filter.apply(eps)), selected);
+    }
+
+    #[test]
+    fn test_predicate() {
+        let filter = EpisodeFilter::predicate(|ep| ep.episode_return > 2.0);
+        let eps = episodes(&[1.0, 5.0, 3.0, 4.0, 2.0]);
+        assert_eq!(ids(&filter.apply(eps)), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_return_percentile() {
+        let eps = episodes(&[1.0, 5.0, 3.0, 4.0, 2.0]);
+        assert!((return_percentile(&eps, 50.0).unwrap() - 3.0).abs() < 1e-6);
+        assert!((return_percentile(&eps, 90.0).unwrap() - 4.6).abs() < 1e-6);
+        assert!((return_percentile(&eps, 150.0).unwrap() - 5.0).abs() < 1e-6);
+        assert!(return_percentile(&[], 50.0).is_none());
+
+        let filter = EpisodeFilter::ReturnPercentile(50.0);
+        assert_eq!(ids(&filter.apply(eps.clone())), vec![1, 2, 3]);
+        let filter = EpisodeFilter::ReturnPercentile(0.0);
+        assert_eq!(ids(&filter.apply(eps)), vec![0, 1, 2, 3, 4]);
+
+        // Episodes tied with the threshold are kept
+        let eps = episodes(&[1.0, 2.0, 2.0, 2.0]);
+        let filter = EpisodeFilter::ReturnPercentile(90.0);
+        assert_eq!(ids(&filter.apply(eps)), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_success() {
+        let eps = episodes(&[1.0, 5.0, 3.0, 4.0, 2.0]);
+        assert_eq!(ids(&EpisodeFilter::Success.apply(eps)), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_filters_in_order() {
+        let eps = episodes(&[1.0, 5.0, 3.0, 4.0, 2.0]);
+        let filters = [
+            EpisodeFilter::TopReturn(0.6),
+            EpisodeFilter::Indices(vec![0, 1, 2]),
+        ];
+        let eps = filters.iter().fold(eps, |eps, filter| filter.apply(eps));
+        assert_eq!(ids(&eps), vec![1, 2]);
+    }
+}
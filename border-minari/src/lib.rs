@@ -8,6 +8,19 @@
 //! - **Dataset Loading**: Load Minari datasets from disk or from the Minari registry.
 //! - **Environment Interaction**: Interact with the loaded datasets using the Border environment interface.
 //! - **Data Access**: Access observations, actions, rewards, and other data from the datasets.
+//! - **Episode Selection**: Create replay buffers from a subset of episodes, e.g., those with
+//!   the top returns or successful ones, with [`MinariDataset::create_replay_buffer_with_filters()`]
+//!   and [`EpisodeFilter`].
+//! - **Reward Relabeling**: Transform rewards, e.g., scaling or goal-distance shaping, while creating
+//!   replay buffers with [`MinariDataset::reward_transform()`] and [`RewardTransform`].
+//! - **Parallel Ingestion**: Convert episodes of large datasets into a replay buffer on worker threads
+//!   with [`MinariDataset::create_replay_buffer_parallel()`].
+//! - **Success Rate**: Evaluate policies with the ratio of successful episodes, e.g., in AntMaze,
+//!   with [`MinariEvaluator::success_criterion()`] and [`SuccessCriterion`].
+//! - **Dataset Export**: Write episodes collected in Border environments as Minari datasets
+//!   with [`MinariDatasetBuilder`].
+//! - **Automatic Conversion**: Build flattened observations and actions from the spaces of datasets
+//!   with `AutoConverter`, available with the `candle` feature.
 //!
 //! # Example
 //!
@@ -75,13 +88,23 @@
 //!
 //! [`Env`]: border_core::Env
 
+#[cfg(feature = "candle")]
+mod auto_converter;
 mod converter;
 pub mod d4rl;
 mod dataset;
 pub mod env;
 pub mod evaluator;
+mod export;
+mod filter;
+mod reward;
 pub mod util;
-pub use converter::MinariConverter;
-pub use dataset::MinariDataset;
+#[cfg(feature = "candle")]
+pub use auto_converter::{AutoConverter, AutoConverterConfig};
+pub use converter::{EpisodeArray, MinariConverter};
+pub use dataset::{IngestConfig, MinariDataset};
 pub use env::MinariEnv;
-pub use evaluator::MinariEvaluator;
+pub use evaluator::{MinariEvaluator, SuccessCriterion};
+pub use export::{MinariDatasetBuilder, MinariExportConverter};
+pub use filter::{EpisodeFilter, EpisodeInfo};
+pub use reward::{RewardFn, RewardTransform};
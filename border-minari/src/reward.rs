@@ -0,0 +1,223 @@
+//! Relabeling of rewards in Minari datasets.
+use anyhow::Result;
+use ndarray::{Array2, Axis};
+use numpy::PyArrayDyn;
+use pyo3::{PyAny, Python};
+use serde::{Deserialize, Serialize};
+use std::{fmt, sync::Arc};
+
+/// Function relabeling the rewards of an episode, see [`RewardTransform::custom()`].
+pub type RewardFn = Arc<dyn Fn(&PyAny, Vec<f32>) -> Result<Vec<f32>> + Send + Sync>;
+
+/// Transformation of rewards applied while creating replay buffers from a Minari dataset.
+///
+/// It is set to a dataset with [`MinariDataset::reward_transform()`]. Except for
+/// [`RewardTransform::Custom`], transformations can be serialized, so that they are recorded
+/// in the configuration of a run, e.g., the parameters of an MLflow run.
+///
+/// Goal distances are the Euclidean distances between the achieved and desired goals,
+/// given by the keys of `Dict` observations, e.g., `achieved_goal` and `desired_goal`
+/// in D4RL Point Maze and AntMaze datasets.
+///
+/// [`MinariDataset::reward_transform()`]: crate::MinariDataset::reward_transform
+#[derive(Clone, Deserialize, Serialize)]
+pub enum RewardTransform {
+    /// `scale * r + shift`, e.g., `scale = 1.0` and `shift = -1.0` for AntMaze datasets in IQL.
+    Scale {
+        /// Scale of rewards.
+        scale: f32,
+        /// Shift of rewards.
+        shift: f32,
+    },
+
+    /// Clipping of rewards into `[min, max]`.
+    Clip {
+        /// Lower bound of rewards.
+        min: f32,
+        /// Upper bound of rewards.
+        max: f32,
+    },
+
+    /// Negative goal distance after each transition, replacing the original rewards.
+    GoalDistance {
+        /// Key of the achieved goal in observations.
+        achieved_goal: String,
+        /// Key of the desired goal in observations.
+        desired_goal: String,
+    },
+
+    /// Potential-based shaping of sparse rewards, `r + gamma * phi(s') - phi(s)`,
+    /// where the potential `phi` is the negative goal distance.
+    GoalDistanceShaping {
+        /// Key of the achieved goal in observations.
+        achieved_goal: String,
+        /// Key of the desired goal in observations.
+        desired_goal: String,
+        /// Discount factor.
+        gamma: f32,
+    },
+
+    /// Transformations applied in order.
+    Chain(Vec<RewardTransform>),
+
+    /// Relabeling with a function, which cannot be serialized.
+    #[serde(skip)]
+    Custom(RewardFn),
+}
+
+impl fmt::Debug for RewardTransform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Scale { scale, shift } => f
+                .debug_struct("Scale")
+                .field("scale", scale)
+                .field("shift", shift)
+                .finish(),
+            Self::Clip { min, max } => f
+                .debug_struct("Clip")
+                .field("min", min)
+                .field("max", max)
+                .finish(),
+            Self::GoalDistance {
+                achieved_goal,
+                desired_goal,
+            } => f
+                .debug_struct("GoalDistance")
+                .field("achieved_goal", achieved_goal)
+                .field("desired_goal", desired_goal)
+                .finish(),
+            Self::GoalDistanceShaping {
+                achieved_goal,
+                desired_goal,
+                gamma,
+            } => f
+                .debug_struct("GoalDistanceShaping")
+                .field("achieved_goal", achieved_goal)
+                .field("desired_goal", desired_goal)
+                .field("gamma", gamma)
+                .finish(),
+            Self::Chain(transforms) => f.debug_tuple("Chain").field(transforms).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+impl RewardTransform {
+    /// Creates a transformation with a function.
+    ///
+    /// The function takes an episode, `minari.dataset.episode_data.EpisodeData`,
+    /// and its rewards, and returns the relabeled rewards.
+    pub fn custom(
+        f: impl Fn(&PyAny, Vec<f32>) -> Result<Vec<f32>> + Send + Sync + 'static,
+    ) -> Self {
+        Self::Custom(Arc::new(f))
+    }
+
+    /// Relabels the rewards of an episode.
+    pub(crate) fn apply(&self, py: Python<'_>, ep: &PyAny, reward: Vec<f32>) -> Result<Vec<f32>> {
+        let reward = match self {
+            Self::Scale { scale, shift } => reward.iter().map(|r| scale * r + shift).collect(),
+            Self::Clip { min, max } => reward.iter().map(|r| r.max(*min).min(*max)).collect(),
+            Self::GoalDistance {
+                achieved_goal,
+                desired_goal,
+            } => {
+                let dist = goal_distances(py, ep, achieved_goal, desired_goal)?;
+                dist[1..].iter().map(|d| -d).collect()
+            }
+            Self::GoalDistanceShaping {
+                achieved_goal,
+                desired_goal,
+                gamma,
+            } => {
+                let dist = goal_distances(py, ep, achieved_goal, desired_goal)?;
+                reward
+                    .iter()
+                    .enumerate()
+                    .map(|(t, r)| r - gamma * dist[t + 1] + dist[t])
+                    .collect()
+            }
+            Self::Chain(transforms) => {
+                let mut reward = reward;
+                for transform in transforms.iter() {
+                    reward = transform.apply(py, ep, reward)?;
+                }
+                reward
+            }
+            Self::Custom(f) => f(ep, reward)?,
+        };
+        Ok(reward)
+    }
+}
+
+/// Returns the goal distances at all observations in an episode, including the last one.
+fn goal_distances(
+    py: Python<'_>,
+    ep: &PyAny,
+    achieved_goal: &str,
+    desired_goal: &str,
+) -> Result<Vec<f32>> {
+    let obs = ep.getattr("observations")?;
+    let to_array2 = |key: &str| -> Result<Array2<f32>> {
+        let arr = py
+            .import("numpy")?
+            .call_method1("asarray", (obs.get_item(key)?, "float32"))?;
+        let arr: &PyArrayDyn<f32> = arr.extract()?;
+        let arr = arr.to_owned_array();
+        let (n, len) = (arr.shape()[0], arr.len());
+        Ok(arr.into_shape((n, len / n.max(1)))?)
+    };
+    let diff = to_array2(achieved_goal)? - to_array2(desired_goal)?;
+    Ok(diff.axis_iter(Axis(0)).map(|d| d.dot(&d).sqrt()).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(transform: &RewardTransform, reward: Vec<f32>) -> Result<Vec<f32>> {
+        Python::with_gil(|py| transform.apply(py, py.None().as_ref(py), reward))
+    }
+
+    #[test]
+    fn test_scale() -> Result<()> {
+        let transform = RewardTransform::Scale {
+            scale: 2.0,
+            shift: -1.0,
+        };
+        assert_eq!(
+            apply(&transform, vec![0.0, 0.5, 1.0])?,
+            vec![-1.0, 0.0, 1.0]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_clip() -> Result<()> {
+        let transform = RewardTransform::Clip {
+            min: -1.0,
+            max: 1.0,
+        };
+        assert_eq!(
+            apply(&transform, vec![-3.0, 0.5, 2.0])?,
+            vec![-1.0, 0.5, 1.0]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_chain() -> Result<()> {
+        let transform = RewardTransform::Chain(vec![
+            RewardTransform::Scale {
+                scale: 4.0,
+                shift: 0.0,
+            },
+            RewardTransform::Clip {
+                min: -1.0,
+                max: 1.0,
+            },
+        ]);
+        assert_eq!(apply(&transform, vec![-0.5, 0.125])?, vec![-1.0, 0.5]);
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 mod ndarray_act;
 mod ndarray_obs;
 mod tensor_batch;
+mod tensor_data;
 pub use ndarray_act::NdarrayAct;
 pub use ndarray_obs::NdarrayObs;
 pub use tensor_batch::TensorBatch;
@@ -1,5 +1,7 @@
+use super::tensor_data::TensorData;
 use border_core::generic_replay_buffer::BatchBase;
 use candle_core::{/*error::Result, DType,*/ Device, Tensor};
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 
 // /// Adds capability of constructing [`Tensor`] with a static method.
 // ///
@@ -90,6 +92,34 @@ impl BatchBase for TensorBatch {
     }
 }
 
+/// Serializes the shapes, the data types and the values of the samples.
+///
+/// It is used to send samples over the network, e.g., in the networked mode of
+/// `border-async-trainer`.
+impl Serialize for TensorBatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let buf = self
+            .buf
+            .iter()
+            .map(TensorData::from_tensor)
+            .collect::<candle_core::Result<Vec<_>>>()
+            .map_err(S::Error::custom)?;
+        (self.capacity, buf).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TensorBatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let (capacity, buf) = <(usize, Vec<(Vec<usize>, TensorData)>)>::deserialize(deserializer)?;
+        let buf = buf
+            .into_iter()
+            .map(|(shape, data)| data.into_tensor(shape))
+            .collect::<candle_core::Result<Vec<_>>>()
+            .map_err(D::Error::custom)?;
+        Ok(Self { buf, capacity })
+    }
+}
+
 impl From<TensorBatch> for Tensor {
     fn from(b: TensorBatch) -> Self {
         Tensor::cat(&b.buf[..], 0).unwrap()
@@ -0,0 +1,44 @@
+//! Serialization of tensors.
+use candle_core::{bail, DType, Device, Result, Tensor};
+use serde::{Deserialize, Serialize};
+
+/// Values of a tensor, keeping its data type in serialization.
+#[derive(Serialize, Deserialize)]
+pub(crate) enum TensorData {
+    U8(Vec<u8>),
+    U32(Vec<u32>),
+    I64(Vec<i64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+impl TensorData {
+    /// Copies the shape and the values of a tensor.
+    pub fn from_tensor(t: &Tensor) -> Result<(Vec<usize>, Self)> {
+        let shape = t.dims().to_vec();
+        let t = t.flatten_all()?;
+        let data = match t.dtype() {
+            DType::U8 => Self::U8(t.to_vec1()?),
+            DType::U32 => Self::U32(t.to_vec1()?),
+            DType::I64 => Self::I64(t.to_vec1()?),
+            DType::F32 => Self::F32(t.to_vec1()?),
+            DType::F64 => Self::F64(t.to_vec1()?),
+            dtype => bail!("Serialization of tensors of {:?} is not supported", dtype),
+        };
+        Ok((shape, data))
+    }
+
+    /// Creates a tensor on CPU.
+    ///
+    /// Returns an error if the number of values does not match `shape`.
+    pub fn into_tensor(self, shape: Vec<usize>) -> Result<Tensor> {
+        let device = &Device::Cpu;
+        match self {
+            Self::U8(v) => Tensor::from_vec(v, shape, device),
+            Self::U32(v) => Tensor::from_vec(v, shape, device),
+            Self::I64(v) => Tensor::from_vec(v, shape, device),
+            Self::F32(v) => Tensor::from_vec(v, shape, device),
+            Self::F64(v) => Tensor::from_vec(v, shape, device),
+        }
+    }
+}
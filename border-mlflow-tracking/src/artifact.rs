@@ -0,0 +1,79 @@
+use crate::Run;
+use anyhow::Result;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Storage of artifacts of runs, like model parameters saved during training.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ArtifactStore {
+    /// Artifacts are copied under the directory given by the `MLFLOW_DEFAULT_ARTIFACT_ROOT`
+    /// environment variable, assuming that the tracking server runs on the same host.
+    #[default]
+    Env,
+
+    /// Artifacts are copied under the given directory, which is the artifact root of
+    /// the tracking server, e.g., `mlruns`, mounted on the host.
+    Path(PathBuf),
+
+    /// Artifacts are uploaded to the tracking server via the REST API of artifacts.
+    ///
+    /// The tracking server should be started with `--serve-artifacts`, which is the default
+    /// of MLflow 2. Artifacts are also written in a local staging directory under the temporary
+    /// directory of the system.
+    Http,
+}
+
+impl ArtifactStore {
+    /// Returns the local directory in which artifacts of the run are written.
+    pub(crate) fn artifact_base(&self, run: &Run) -> Result<PathBuf> {
+        match self {
+            Self::Env => crate::get_artifact_base(run.clone()),
+            Self::Path(root) => Ok(root.join(artifact_path(run)?)),
+            Self::Http => Ok(std::env::temp_dir()
+                .join("border-mlflow-artifacts")
+                .join(&run.info.run_id)),
+        }
+    }
+}
+
+/// Returns the path of the artifacts of the run relative to the artifact root.
+pub(crate) fn artifact_path(run: &Run) -> Result<PathBuf> {
+    let artifact_uri: PathBuf = run
+        .info
+        .artifact_uri
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Failed to get artifact_uri"))?
+        .into();
+    Ok(artifact_uri
+        .strip_prefix("mlflow-artifacts:/")?
+        .to_path_buf())
+}
+
+#[derive(Debug, Deserialize)]
+/// A file in the response of List Artifacts of the artifact proxy.
+pub(crate) struct FileInfo {
+    pub(crate) path: String,
+    #[serde(default)]
+    pub(crate) is_dir: bool,
+}
+
+#[derive(Debug, Deserialize)]
+/// Response of List Artifacts of the artifact proxy.
+pub(crate) struct ListArtifactsResponse {
+    #[serde(default)]
+    pub(crate) files: Vec<FileInfo>,
+}
+
+/// Returns the URL of an artifact in the artifact proxy of the tracking server.
+pub(crate) fn artifact_url(base_url: &str, run: &Run, path: &Path) -> Result<String> {
+    let path = artifact_path(run)?.join(path);
+    let path = path
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Ok(format!(
+        "{}/api/2.0/mlflow-artifacts/artifacts/{}",
+        base_url, path
+    ))
+}
@@ -1,9 +1,11 @@
-use crate::{system_time_as_millis, Experiment, MlflowTrackingRecorder, Run};
+use crate::{
+    http::HttpClient, system_time_as_millis, ArtifactStore, Auth, Experiment,
+    MlflowTrackingRecorder, Run, StepResumption, TlsConfig,
+};
 use anyhow::Result;
 use border_core::{Env, ReplayBufferBase};
 use log::info;
-use reqwest::blocking::Client;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::error::Error;
 use std::fmt::Display;
 
@@ -38,6 +40,33 @@ struct CreateRunParams {
     run_name: String,
 }
 
+#[derive(Debug, Serialize)]
+/// Request body of [Log Metric](https://mlflow.org/docs/2.11.3/rest-api.html#log-metric).
+struct LogMetricParams<'a> {
+    run_id: &'a str,
+    key: &'a str,
+    value: f64,
+    timestamp: i64,
+    step: i64,
+}
+
+#[derive(Debug, Serialize)]
+/// Request body of [Set Tag](https://mlflow.org/docs/2.11.3/rest-api.html#set-tag).
+struct SetTagParams<'a> {
+    run_id: &'a str,
+    key: &'a str,
+    value: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+/// Request body of [Update Run](https://mlflow.org/docs/2.11.3/rest-api.html#update-run).
+struct UpdateRunParams<'a> {
+    run_id: &'a str,
+    status: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_time: Option<i64>,
+}
+
 #[derive(Debug, Serialize)]
 /// Request body of [Create Experiment](https://mlflow.org/docs/2.11.3/rest-api.html#id67).
 struct CreateExperimentParams {
@@ -58,12 +87,33 @@ pub struct SearchRunsResponse {
     next_page_token: Option<String>,
 }
 
+#[derive(Clone, Debug, Deserialize)]
+/// A value of a metric in the response of [Get Metric History](https://mlflow.org/docs/2.11.3/rest-api.html).
+struct MetricValue {
+    value: f64,
+    step: Option<i64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+/// Response of [Get Metric History](https://mlflow.org/docs/2.11.3/rest-api.html).
+struct GetMetricHistoryResponse {
+    metrics: Option<Vec<MetricValue>>,
+    next_page_token: Option<String>,
+}
+
 #[derive(Debug)]
 /// Provides access to a MLflow tracking server via REST API.
 ///
 /// Support Mlflow API version 2.0.
+///
+/// Managed tracking servers, like Databricks or a server behind an authentication proxy,
+/// can be accessed with authentication ([`MlflowTrackingClient::auth()`]), custom headers
+/// ([`MlflowTrackingClient::header()`]) and the TLS configuration of https endpoints
+/// ([`MlflowTrackingClient::tls()`]). These settings are shared with the recorders
+/// created by the client.
 pub struct MlflowTrackingClient {
-    client: Client,
+    /// HTTP client with authentication, headers and TLS configuration.
+    http: HttpClient,
 
     /// Base URL.
     base_url: String,
@@ -71,33 +121,66 @@ pub struct MlflowTrackingClient {
     /// Current experiment ID.
     experiment_id: Option<String>,
 
-    /// User name of the tracking server.
-    user_name: String,
-
-    /// Password.
-    password: String,
+    /// Storage of artifacts of runs.
+    artifact_store: ArtifactStore,
 }
 
 impl MlflowTrackingClient {
     pub fn new(base_url: impl AsRef<str>) -> Self {
         Self {
-            client: Client::new(),
+            http: HttpClient::default(),
             base_url: base_url.as_ref().to_string(),
             experiment_id: None,
-            user_name: "".to_string(),
-            password: "".to_string(),
+            artifact_store: ArtifactStore::default(),
         }
     }
 
     /// Sets user name and password for basic authentication of the tracking server.
     pub fn basic_auth(self, user_name: impl AsRef<str>, password: impl AsRef<str>) -> Self {
-        Self {
-            client: self.client,
-            base_url: self.base_url,
-            experiment_id: self.experiment_id,
+        self.auth(Auth::Basic {
             user_name: user_name.as_ref().to_string(),
             password: password.as_ref().to_string(),
-        }
+        })
+    }
+
+    /// Sets a bearer token for authentication of the tracking server.
+    pub fn bearer_token(self, token: impl AsRef<str>) -> Self {
+        self.auth(Auth::Bearer(token.as_ref().to_string()))
+    }
+
+    /// Sets authentication of the tracking server.
+    ///
+    /// Use [`Auth::from_env()`] to read a token or credentials from environment variables
+    /// instead of writing them in code.
+    pub fn auth(mut self, v: Auth) -> Self {
+        self.http.set_auth(v);
+        self
+    }
+
+    /// Adds a header sent with all requests to the tracking server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `name` or `value` is not a valid header name or value.
+    pub fn header(mut self, name: impl AsRef<str>, value: impl AsRef<str>) -> Result<Self> {
+        self.http.insert_header(name.as_ref(), value.as_ref())?;
+        Ok(self)
+    }
+
+    /// Sets the TLS configuration of https endpoints of the tracking server.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the certificate file cannot be read or parsed.
+    pub fn tls(mut self, v: TlsConfig) -> Result<Self> {
+        self.http.set_tls(v)?;
+        Ok(self)
+    }
+
+    /// Sets the storage of artifacts of runs, like model parameters saved during training.
+    pub fn artifact_store(mut self, v: ArtifactStore) -> Self {
+        self.artifact_store = v;
+        self
     }
 
     /// Sets an experiment with the given name to this struct.
@@ -115,11 +198,10 @@ impl MlflowTrackingClient {
         );
 
         Ok(Self {
-            client: self.client,
+            http: self.http,
             base_url: self.base_url,
             experiment_id: Some(experiment_id),
-            user_name: self.user_name,
-            password: self.password,
+            artifact_store: self.artifact_store,
         })
     }
 
@@ -172,11 +254,7 @@ impl MlflowTrackingClient {
         url: String,
         query: &impl Serialize,
     ) -> reqwest::Result<reqwest::blocking::Response> {
-        self.client
-            .get(url)
-            .basic_auth(&self.user_name, Some(&self.password))
-            .query(query)
-            .send()
+        self.http.get(url).query(query).send()
     }
 
     fn post(
@@ -184,9 +262,8 @@ impl MlflowTrackingClient {
         url: String,
         params: &impl Serialize,
     ) -> reqwest::Result<reqwest::blocking::Response> {
-        self.client
+        self.http
             .post(url)
-            .basic_auth(&self.user_name, Some(&self.password))
             .json(&params) // auto serialize
             .send()
     }
@@ -202,10 +279,11 @@ impl MlflowTrackingClient {
     /// You need to set an experiment using [`MlflowTrackingClient::set_experiment()`]
     /// before calling this method.
     ///
-    /// This method uses `MLFLOW_DEFAULT_ARTIFACT_ROOT` environment variable as the directory
-    /// where artifacts, like model parameters, will be saved. It is recommended to set this
-    /// environment variable `mlruns` directory to which the tracking server persists experiment
-    /// and run data.
+    /// By default, this method uses `MLFLOW_DEFAULT_ARTIFACT_ROOT` environment variable as
+    /// the directory where artifacts, like model parameters, will be saved. It is recommended
+    /// to set this environment variable `mlruns` directory to which the tracking server persists
+    /// experiment and run data. The storage can be changed with
+    /// [`MlflowTrackingClient::artifact_store()`].
     pub fn create_recorder<E, R>(
         &self,
         run_name: impl AsRef<str>,
@@ -233,11 +311,65 @@ impl MlflowTrackingClient {
         }
 
         // Get the directory to which artifacts will be saved
-        let artifact_base = crate::get_artifact_base(run.clone())?;
+        let artifact_base = self.artifact_store.artifact_base(&run)?;
 
         // Return a recorder
         let experiment_id = self.experiment_id.as_ref().expect("Needs experiment_id");
-        MlflowTrackingRecorder::new(&self.base_url, &experiment_id, run, artifact_base)
+        Ok(MlflowTrackingRecorder::with_http(
+            self.http.clone(),
+            &self.base_url,
+            &experiment_id,
+            run,
+            artifact_base,
+        )?
+        .with_artifact_store(self.artifact_store.clone()))
+    }
+
+    /// Creates [`MlflowTrackingRecorder`] attached to an existing run with the given ID
+    /// to resume it, e.g., after a crash.
+    ///
+    /// The status of the run is set to "RUNNING". Metrics are logged with steps continuing
+    /// from those logged before as specified by `resumption`, and the last step logged in the run
+    /// is given by [`MlflowTrackingRecorder::last_step()`], from which training can be resumed.
+    /// Unlike [`MlflowTrackingClient::create_recorder()`], the experiment does not need to be set.
+    ///
+    /// [`MlflowTrackingRecorder::last_step()`]: crate::MlflowTrackingRecorder::last_step
+    pub fn resume_recorder<E, R>(
+        &self,
+        run_id: impl AsRef<str>,
+        resumption: StepResumption,
+    ) -> Result<MlflowTrackingRecorder<E, R>>
+    where
+        E: Env,
+        R: ReplayBufferBase,
+    {
+        let run = self.get_run(run_id.as_ref())?;
+        let params = UpdateRunParams {
+            run_id: run_id.as_ref(),
+            status: "RUNNING",
+            end_time: None,
+        };
+        let resp = self.post(self.url("runs/update"), &params)?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to resume run {}: {}", run_id.as_ref(), resp.text()?);
+        }
+        info!(
+            "Resume run '{}' (id={})",
+            run.info.run_name,
+            run_id.as_ref()
+        );
+
+        let artifact_base = self.artifact_store.artifact_base(&run)?;
+        let experiment_id = run.info.experiment_id.clone();
+        Ok(MlflowTrackingRecorder::with_http(
+            self.http.clone(),
+            &self.base_url,
+            &experiment_id,
+            run,
+            artifact_base,
+        )?
+        .with_artifact_store(self.artifact_store.clone())
+        .step_resumption(resumption))
     }
 
     /// Get Run info.
@@ -263,6 +395,147 @@ impl MlflowTrackingClient {
         Ok(run)
     }
 
+    /// Creates a new run named `run_name` without a recorder.
+    ///
+    /// It is used to log metrics computed outside of training, such as aggregates of runs.
+    /// Metrics and tags are logged with [`MlflowTrackingClient::log_metric()`] and
+    /// [`MlflowTrackingClient::set_tag()`], and the run is finished with
+    /// [`MlflowTrackingClient::finish_run()`].
+    pub fn create_run(&self, run_name: impl AsRef<str>) -> Result<Run> {
+        self.get_run_info(run_name)
+    }
+
+    /// Logs a metric of a run at a step.
+    pub fn log_metric(
+        &self,
+        run_id: impl AsRef<str>,
+        key: impl AsRef<str>,
+        value: f32,
+        step: i64,
+    ) -> Result<()> {
+        let params = LogMetricParams {
+            run_id: run_id.as_ref(),
+            key: key.as_ref(),
+            value: value as f64,
+            timestamp: system_time_as_millis() as i64,
+            step,
+        };
+        let resp = self.post(self.url("runs/log-metric"), &params)?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to log {}: {}", key.as_ref(), resp.text()?);
+        }
+        Ok(())
+    }
+
+    /// Sets a tag of a run, overwriting the existing one.
+    pub fn set_tag(
+        &self,
+        run_id: impl AsRef<str>,
+        key: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) -> Result<()> {
+        let params = SetTagParams {
+            run_id: run_id.as_ref(),
+            key: key.as_ref(),
+            value: value.as_ref(),
+        };
+        let resp = self.post(self.url("runs/set-tag"), &params)?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to set tag {}: {}", key.as_ref(), resp.text()?);
+        }
+        Ok(())
+    }
+
+    /// Updates the status of a run to "FINISHED".
+    pub fn finish_run(&self, run_id: impl AsRef<str>) -> Result<()> {
+        let params = UpdateRunParams {
+            run_id: run_id.as_ref(),
+            status: "FINISHED",
+            end_time: Some(system_time_as_millis() as i64),
+        };
+        let resp = self.post(self.url("runs/update"), &params)?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to finish run {}: {}", run_id.as_ref(), resp.text()?);
+        }
+        Ok(())
+    }
+
+    /// Gets [`Run`] by ID from the tracking server.
+    pub fn get_run(&self, run_id: impl AsRef<str>) -> Result<Run> {
+        let resp = self.get(self.url("runs/get"), &[("run_id", run_id.as_ref())])?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to get run {}: {}", run_id.as_ref(), resp.text()?);
+        }
+        let run: Run_ = serde_json::from_str(&resp.text()?)?;
+        Ok(run.run)
+    }
+
+    /// Loads the configuration logged to a run with [`MlflowTrackingRecorder::log_params()`].
+    ///
+    /// The configuration is deserialized from the artifact `params.json` of the run if it exists
+    /// in the directory given by `MLFLOW_DEFAULT_ARTIFACT_ROOT`. Otherwise, it is reconstructed
+    /// from the flattened parameters of the run. Note that the reconstruction is not exact
+    /// for arrays of structs and for values truncated by the tracking server.
+    pub fn load_run_config<T: DeserializeOwned>(&self, run_id: impl AsRef<str>) -> Result<T> {
+        let run = self.get_run(run_id.as_ref())?;
+
+        if std::env::var("MLFLOW_DEFAULT_ARTIFACT_ROOT").is_ok() {
+            let path = crate::get_artifact_base(run.clone())?.join(crate::PARAMS_ARTIFACT);
+            if path.exists() {
+                info!("Load config of run {} from {:?}", run_id.as_ref(), path);
+                let file = std::fs::File::open(path)?;
+                return Ok(serde_json::from_reader(std::io::BufReader::new(file))?);
+            }
+        }
+
+        info!("Load config of run {} from parameters", run_id.as_ref());
+        let params = crate::unflatten_params(&run.params())?;
+        Ok(serde_json::from_value(params)?)
+    }
+
+    /// Gets the history of a metric logged to a run as pairs of steps and values.
+    ///
+    /// The values are sorted by steps.
+    pub fn get_metric_history(
+        &self,
+        run_id: impl AsRef<str>,
+        key: impl AsRef<str>,
+    ) -> Result<Vec<(i64, f32)>> {
+        let mut history = vec![];
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut query = vec![
+                ("run_id", run_id.as_ref().to_string()),
+                ("metric_key", key.as_ref().to_string()),
+                ("max_results", "25000".to_string()),
+            ];
+            if let Some(token) = page_token.take() {
+                query.push(("page_token", token));
+            }
+            let resp = self.get(self.url("metrics/get-history"), &query)?;
+            if !resp.status().is_success() {
+                anyhow::bail!(
+                    "Failed to get history of {} in run {}: {}",
+                    key.as_ref(),
+                    run_id.as_ref(),
+                    resp.text()?
+                );
+            }
+            let resp: GetMetricHistoryResponse = serde_json::from_str(&resp.text()?)?;
+            for m in resp.metrics.unwrap_or_default() {
+                history.push((m.step.unwrap_or(0), m.value as f32));
+            }
+            match resp.next_page_token {
+                Some(token) if !token.is_empty() => page_token = Some(token),
+                _ => break,
+            }
+        }
+
+        history.sort_by_key(|(step, _)| *step);
+        Ok(history)
+    }
+
     /// Get runs by names.
     ///
     /// This method queries the tracking server and returns [`Run`]s.
@@ -0,0 +1,164 @@
+use anyhow::Result;
+use reqwest::{
+    blocking::{Client, RequestBuilder},
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Certificate, IntoUrl, Method,
+};
+use std::path::PathBuf;
+
+/// Authentication to the tracking server.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum Auth {
+    /// No authentication.
+    #[default]
+    None,
+
+    /// Basic authentication with a user name and a password.
+    Basic {
+        /// User name.
+        user_name: String,
+        /// Password.
+        password: String,
+    },
+
+    /// Bearer token, e.g., a personal access token of Databricks.
+    Bearer(String),
+}
+
+impl Auth {
+    /// Reads authentication from the environment variables used by the MLflow client of Python.
+    ///
+    /// `MLFLOW_TRACKING_TOKEN` is used as a bearer token if set. Otherwise,
+    /// `MLFLOW_TRACKING_USERNAME` and `MLFLOW_TRACKING_PASSWORD` are used for basic
+    /// authentication if set. Returns [`Auth::None`] if none of them is set.
+    pub fn from_env() -> Self {
+        let var = |key| std::env::var(key).ok().filter(|v: &String| !v.is_empty());
+        if let Some(token) = var("MLFLOW_TRACKING_TOKEN") {
+            Self::Bearer(token)
+        } else if let Some(user_name) = var("MLFLOW_TRACKING_USERNAME") {
+            Self::Basic {
+                user_name,
+                password: var("MLFLOW_TRACKING_PASSWORD").unwrap_or_default(),
+            }
+        } else {
+            Self::None
+        }
+    }
+}
+
+/// TLS configuration of https endpoints of the tracking server.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsConfig {
+    /// PEM file of a certificate of the server or its CA, added to the trusted root certificates.
+    pub ca_cert: Option<PathBuf>,
+
+    /// If `true`, certificates of the server are not verified.
+    ///
+    /// It should be used only for testing with self-signed certificates.
+    pub accept_invalid_certs: bool,
+}
+
+impl TlsConfig {
+    /// Sets the PEM file of a certificate to be trusted.
+    pub fn ca_cert(mut self, v: impl Into<PathBuf>) -> Self {
+        self.ca_cert = Some(v.into());
+        self
+    }
+
+    /// Sets `true` not to verify certificates of the server.
+    pub fn accept_invalid_certs(mut self, v: bool) -> Self {
+        self.accept_invalid_certs = v;
+        self
+    }
+}
+
+/// HTTP client shared by [`MlflowTrackingClient`] and the recorders created by it.
+///
+/// Custom headers are sent with all requests, and authentication is applied to each request.
+///
+/// [`MlflowTrackingClient`]: crate::MlflowTrackingClient
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HttpClient {
+    client: Client,
+    auth: Auth,
+    headers: HeaderMap,
+    tls: TlsConfig,
+}
+
+impl HttpClient {
+    pub(crate) fn set_auth(&mut self, auth: Auth) {
+        self.auth = auth;
+    }
+
+    pub(crate) fn insert_header(&mut self, name: &str, value: &str) -> Result<()> {
+        self.headers.insert(
+            HeaderName::from_bytes(name.as_bytes())?,
+            HeaderValue::from_str(value)?,
+        );
+        self.rebuild()
+    }
+
+    pub(crate) fn set_tls(&mut self, tls: TlsConfig) -> Result<()> {
+        self.tls = tls;
+        self.rebuild()
+    }
+
+    /// Rebuilds the client with the headers and the TLS configuration.
+    fn rebuild(&mut self) -> Result<()> {
+        let mut builder = Client::builder()
+            .default_headers(self.headers.clone())
+            .danger_accept_invalid_certs(self.tls.accept_invalid_certs);
+        if let Some(path) = &self.tls.ca_cert {
+            builder = builder.add_root_certificate(Certificate::from_pem(&std::fs::read(path)?)?);
+        }
+        self.client = builder.build()?;
+        Ok(())
+    }
+
+    pub(crate) fn request(&self, method: Method, url: impl IntoUrl) -> RequestBuilder {
+        let req = self.client.request(method, url);
+        match &self.auth {
+            Auth::None => req,
+            Auth::Basic {
+                user_name,
+                password,
+            } => req.basic_auth(user_name, Some(password)),
+            Auth::Bearer(token) => req.bearer_auth(token),
+        }
+    }
+
+    pub(crate) fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::GET, url)
+    }
+
+    pub(crate) fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::POST, url)
+    }
+
+    pub(crate) fn put(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::PUT, url)
+    }
+
+    pub(crate) fn delete(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.request(Method::DELETE, url)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_client() -> Result<()> {
+        let mut http = HttpClient::default();
+        http.set_auth(Auth::Bearer("token".to_string()));
+        http.insert_header("X-Team", "rl")?;
+        let req = http.get("https://localhost:8080").build()?;
+        assert_eq!(req.headers()["authorization"], "Bearer token");
+
+        // Default headers are added when the request is sent
+        assert_eq!(http.headers["x-team"], "rl");
+        assert!(http.insert_header("X Team", "rl").is_err());
+        Ok(())
+    }
+}
@@ -114,6 +114,10 @@
 //! }
 //! ```
 //!
+//! Parameters can be logged with [`MlflowTrackingRecorder::log_params_with_diff()`] instead,
+//! which also records the parameters different from the default values of the configuration
+//! type as tags and an artifact.
+//!
 //! ## Save model parameters during training
 //!
 //! [`MlflowTrackingClient`] relies on the `MLFLOW_DEFAULT_ARTIFACT_ROOT` environment variable
@@ -121,18 +125,92 @@
 //! should be set for the program using this crate, not for the tracking server program.
 //! Currently, only saving to the local file system is supported.
 //!
+//! Model parameters can be stored in a given directory or uploaded to the tracking server
+//! via the REST API of artifacts instead, with [`MlflowTrackingClient::artifact_store()`].
+//! Then, the environment variable is not required:
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! # use border_mlflow_tracking::{ArtifactStore, MlflowTrackingClient};
+//! # fn main() -> Result<()> {
+//! let client = MlflowTrackingClient::new("http://localhost:8080")
+//!     .artifact_store(ArtifactStore::Http)
+//!     .set_experiment("Default")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Managed tracking servers
+//!
+//! Tracking servers requiring authentication, like Databricks or a server behind an
+//! authentication proxy, can be accessed via https with [`Auth`], custom headers and [`TlsConfig`]:
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! # use border_mlflow_tracking::{ArtifactStore, Auth, MlflowTrackingClient, TlsConfig};
+//! # fn main() -> Result<()> {
+//! // Reads MLFLOW_TRACKING_TOKEN, or MLFLOW_TRACKING_USERNAME and MLFLOW_TRACKING_PASSWORD
+//! let client = MlflowTrackingClient::new("https://mlflow.example.com")
+//!     .auth(Auth::from_env())
+//!     .header("X-Team", "rl")?
+//!     .tls(TlsConfig::default().ca_cert("ca.pem"))?
+//!     .artifact_store(ArtifactStore::Http)
+//!     .set_experiment("Default")?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Rebuild configurations of runs
+//!
+//! Parameters logged with [`MlflowTrackingRecorder::log_params()`] can be loaded as the original
+//! configuration struct with [`MlflowTrackingClient::load_run_config()`], so that an evaluation job
+//! can rebuild the agent of a run without local configuration files:
+//!
+//! ```no_run
+//! # use anyhow::Result;
+//! # use border_mlflow_tracking::MlflowTrackingClient;
+//! # use serde::Deserialize;
+//! #[derive(Debug, Deserialize)]
+//! struct Config {
+//!     env_params: EnvParams,
+//!     agent_params: AgentParams,
+//! }
+//! # #[derive(Debug, Deserialize)]
+//! # struct EnvParams {}
+//! # #[derive(Debug, Deserialize)]
+//! # struct AgentParams {}
+//!
+//! fn main() -> Result<()> {
+//!     let client = MlflowTrackingClient::new("http://localhost:8080");
+//!     let config: Config = client.load_run_config("<run_id>")?;
+//!     Ok(())
+//! }
+//! ```
+//!
+mod artifact;
 mod client;
 mod experiment;
+mod http;
 mod recorder;
 mod run;
 use anyhow::Result;
+pub use artifact::ArtifactStore;
 pub use client::{GetExperimentIdError, MlflowTrackingClient};
 use experiment::Experiment;
-pub use recorder::MlflowTrackingRecorder;
+pub use http::{Auth, TlsConfig};
+pub use recorder::{MlflowTrackingRecorder, StepResumption};
 pub use run::Run;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Name of the artifact in which parameters of a run are saved.
+const PARAMS_ARTIFACT: &str = "params.json";
+
+/// Name of the artifact in which parameters different from their default values are saved.
+const PARAMS_DIFF_ARTIFACT: &str = "params_diff.json";
+
 /// Code adapted from <https://stackoverflow.com/questions/26593387>.
 fn system_time_as_millis() -> u128 {
     let time = SystemTime::now();
@@ -143,19 +221,113 @@ fn system_time_as_millis() -> u128 {
 
 /// Get the directory to which artifacts will be saved.
 pub(crate) fn get_artifact_base(run: Run) -> Result<PathBuf> {
-    let artifact_uri: PathBuf = run
-        .clone()
-        .info
-        .artifact_uri
-        .expect("Failed to get artifact_uri")
-        .into();
-    let artifact_uri = artifact_uri.strip_prefix("mlflow-artifacts:/")?;
+    let artifact_uri = artifact::artifact_path(&run)?;
     let path: PathBuf = std::env::var("MLFLOW_DEFAULT_ARTIFACT_ROOT")
         .expect("MLFLOW_DEFAULT_ARTIFACT_ROOT must be set")
         .into();
     Ok(path.join(artifact_uri))
 }
 
+/// Reconstructs nested parameters from those flattened by [`MlflowTrackingRecorder::log_params()`].
+///
+/// Keys are split at `.` into nested objects. Values are parsed as JSON, and those failing to be
+/// parsed are kept as strings.
+pub(crate) fn unflatten_params(params: &[(String, String)]) -> Result<Value> {
+    let mut root = Map::new();
+    for (key, value) in params.iter() {
+        let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.clone()));
+        let mut keys = key.split('.').peekable();
+        let mut map = &mut root;
+        while let Some(k) = keys.next() {
+            if keys.peek().is_none() {
+                map.insert(k.to_string(), value);
+                break;
+            }
+            map = match map
+                .entry(k.to_string())
+                .or_insert_with(|| Value::Object(Map::new()))
+            {
+                Value::Object(map) => map,
+                _ => anyhow::bail!("Conflicting parameter key: {}", key),
+            };
+        }
+    }
+    Ok(Value::Object(root))
+}
+
+/// Returns flattened parameters different from their default values.
+///
+/// Values are pairs of the given value and the default value, which is `null` for parameters
+/// missing in the default.
+pub(crate) fn diff_params(
+    params: &Map<String, Value>,
+    default: &Map<String, Value>,
+) -> BTreeMap<String, (Value, Value)> {
+    params
+        .iter()
+        .filter(|(key, value)| default.get(*key) != Some(value))
+        .map(|(key, value)| {
+            let default = default.get(key).cloned().unwrap_or(Value::Null);
+            (key.clone(), (value.clone(), default))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Config {
+        name: String,
+        agent: AgentConfig,
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct AgentConfig {
+        lr: f32,
+        hidden: Vec<usize>,
+        seed: Option<u64>,
+    }
+
+    #[test]
+    fn test_diff_params() {
+        let params = serde_json::json!({"agent.lr": 0.01, "agent.seed": 42, "name": "dqn"});
+        let default = serde_json::json!({"agent.lr": 0.001, "agent.seed": 42});
+        let diff = diff_params(params.as_object().unwrap(), default.as_object().unwrap());
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff["agent.lr"], (0.01.into(), 0.001.into()));
+        assert_eq!(diff["name"], ("dqn".into(), Value::Null));
+    }
+
+    #[test]
+    fn test_unflatten_params() -> Result<()> {
+        let params = [
+            ("name", "\"dqn\""),
+            ("agent.lr", "0.001"),
+            ("agent.hidden", "[64,64]"),
+            ("agent.seed", "null"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<Vec<_>>();
+        let config: Config = serde_json::from_value(unflatten_params(&params)?)?;
+        assert_eq!(
+            config,
+            Config {
+                name: "dqn".to_string(),
+                agent: AgentConfig {
+                    lr: 0.001,
+                    hidden: vec![64, 64],
+                    seed: None
+                }
+            }
+        );
+        Ok(())
+    }
+}
+
 // /// https://stackoverflow.com/questions/26958489/how-to-copy-a-folder-recursively-in-rust
 // fn copy_dir_all(src: impl AsRef<Path>, dst: impl AsRef<Path>) -> Result<()> {
 //     fs::create_dir_all(&dst)?;
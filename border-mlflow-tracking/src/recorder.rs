@@ -1,13 +1,18 @@
-use crate::{system_time_as_millis, Run};
+use crate::{
+    artifact::{artifact_url, ListArtifactsResponse},
+    http::HttpClient,
+    system_time_as_millis, ArtifactStore, Run,
+};
 use anyhow::Result;
 use border_core::{
     record::{RecordStorage, RecordValue, Recorder},
-    Agent, Env, ReplayBufferBase,
+    Agent, Env, ModelMetadata, ReplayBufferBase,
 };
 use chrono::{DateTime, Duration, Local, SecondsFormat};
-use reqwest::blocking::Client;
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs::File;
 use std::marker::PhantomData;
 use std::path::{Path, PathBuf};
 use tempdir::TempDir;
@@ -43,6 +48,54 @@ struct SetTagParams<'a> {
     value: &'a String,
 }
 
+/// How metrics are logged to a resumed run, which has metrics logged before.
+///
+/// Without it, metrics at the steps re-run after a crash would be logged again,
+/// resulting in duplicated values and zigzags in the charts of MLflow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StepResumption {
+    /// Skips values of a metric at steps up to the last step logged for the metric.
+    ///
+    /// It is suitable when training is resumed from a checkpoint, as the steps continue
+    /// from that of the checkpoint.
+    #[default]
+    Skip,
+
+    /// Adds the last step logged in the run, the maximum over metrics, to steps.
+    ///
+    /// It is suitable when training restarts from step 0. Values at steps up to the last step
+    /// of a metric after the offset are still skipped.
+    Offset,
+}
+
+/// Last steps of metrics, used to keep the steps of each metric monotone.
+struct MetricSteps {
+    last_steps: HashMap<String, i64>,
+    offset: i64,
+}
+
+impl MetricSteps {
+    fn new(last_steps: HashMap<String, i64>, resumption: StepResumption) -> Self {
+        let offset = match resumption {
+            StepResumption::Skip => 0,
+            StepResumption::Offset => last_steps.values().copied().max().unwrap_or(0),
+        };
+        Self { last_steps, offset }
+    }
+
+    /// Returns the step at which a value of the metric is logged, or `None` if it is skipped.
+    fn step(&mut self, key: &str, step: i64) -> Option<i64> {
+        let step = step + self.offset;
+        match self.last_steps.get(key) {
+            Some(last) if step <= *last => None,
+            _ => {
+                self.last_steps.insert(key.to_string(), step);
+                Some(step)
+            }
+        }
+    }
+}
+
 #[allow(dead_code)]
 /// Record metrics to the MLflow tracking server during training.
 ///
@@ -54,27 +107,41 @@ struct SetTagParams<'a> {
 /// as metrics. As an exception, `opt_steps` is treated as the `step` field of Mlflow's metric data
 /// (<https://mlflow.org/docs/latest/rest-api.html#metric>).
 ///
-/// Other types of values like [`RecordValue::Array1`] will be ignored.
+/// [`RecordValue::Image`] and [`RecordValue::Histogram`] values are stored as artifacts
+/// `images/{key}/{step}.png` and `histograms/{key}/{step}.json`, the latter of which is
+/// a JSON array of the samples. Other types of values like [`RecordValue::Array1`] will be ignored.
+///
+/// When the recorder is attached to an existing run, e.g., to resume training after a crash,
+/// the last step of each metric in the run is queried, and values at the steps not after it
+/// are skipped so that the history of each metric is monotone in steps. Steps can be offset
+/// instead with [`MlflowTrackingRecorder::step_resumption()`].
+///
+/// Model parameters saved with [`Recorder::save_model()`], e.g., `best` and checkpoints at
+/// optimization steps, and parameters saved with [`MlflowTrackingRecorder::log_params()`]
+/// are stored as artifacts of the run in the [`ArtifactStore`] of the client,
+/// so that models can be retrieved from the MLflow UI.
 ///
 /// When dropped, this struct updates run's status to "FINISHED"
 /// (<https://mlflow.org/docs/latest/rest-api.html#mlflowrunstatus>).
 ///
 /// [`RecordValue::Scalar`]: border_core::record::RecordValue::Scalar
 /// [`RecordValue::Array1`]: border_core::record::RecordValue::Array1
+/// [`RecordValue::Image`]: border_core::record::RecordValue::Image
+/// [`RecordValue::Histogram`]: border_core::record::RecordValue::Histogram
 pub struct MlflowTrackingRecorder<E, R>
 where
     E: Env,
     R: ReplayBufferBase,
 {
-    client: Client,
+    http: HttpClient,
     base_url: String,
     experiment_id: String,
     run: Run,
-    user_name: String,
     storage: RecordStorage,
-    password: String,
     start_time: DateTime<Local>,
     artifact_base: PathBuf,
+    artifact_store: ArtifactStore,
+    steps: MetricSteps,
     phantom: PhantomData<(E, R)>,
 }
 
@@ -98,18 +165,44 @@ where
         run: Run,
         artifact_base: PathBuf,
     ) -> Result<Self> {
-        let client = Client::new();
+        Self::with_http(
+            HttpClient::default(),
+            base_url,
+            experiment_id,
+            run,
+            artifact_base,
+        )
+    }
+
+    /// Creates a recorder sending requests with the HTTP client of [`MlflowTrackingClient`].
+    ///
+    /// [`MlflowTrackingClient`]: crate::MlflowTrackingClient
+    pub(crate) fn with_http(
+        http: HttpClient,
+        base_url: &String,
+        experiment_id: &String,
+        run: Run,
+        artifact_base: PathBuf,
+    ) -> Result<Self> {
         let start_time = Local::now();
+        let last_steps = run.last_steps();
+        if !last_steps.is_empty() {
+            log::info!(
+                "Run {} has metrics logged up to step {}",
+                run.info.run_id,
+                last_steps.values().max().unwrap()
+            );
+        }
         let recorder = Self {
-            client,
+            http,
             base_url: base_url.clone(),
             experiment_id: experiment_id.to_string(),
             run,
-            user_name: "".to_string(),
-            password: "".to_string(),
             storage: RecordStorage::new(),
             start_time: start_time.clone(),
             artifact_base,
+            artifact_store: ArtifactStore::default(),
+            steps: MetricSteps::new(last_steps, StepResumption::default()),
             phantom: PhantomData,
         };
 
@@ -122,15 +215,123 @@ where
         Ok(recorder)
     }
 
+    /// Sets the storage of artifacts, whose local directory is given to [`Self::new()`].
+    pub(crate) fn with_artifact_store(mut self, v: ArtifactStore) -> Self {
+        self.artifact_store = v;
+        self
+    }
+
+    /// Uploads a file in the artifact directory to the tracking server
+    /// if artifacts are stored via HTTP.
+    fn upload_artifact(&self, path: &Path) -> Result<()> {
+        if self.artifact_store != ArtifactStore::Http {
+            return Ok(());
+        }
+        let url = artifact_url(&self.base_url, &self.run, path)?;
+        let resp = self
+            .http
+            .put(&url)
+            .body(std::fs::read(self.artifact_base.join(path))?)
+            .send()?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to upload artifact {:?}: {}", path, resp.text()?);
+        }
+        Ok(())
+    }
+
+    /// Downloads files under a directory of artifacts of the run from the tracking server
+    /// to the artifact directory.
+    fn download_artifacts(&self, dir: &Path) -> Result<()> {
+        let url = format!("{}/api/2.0/mlflow-artifacts/artifacts", self.base_url);
+        let path = crate::artifact::artifact_path(&self.run)?.join(dir);
+        let resp = self
+            .http
+            .get(&url)
+            .query(&[("path", path.to_string_lossy())])
+            .send()?;
+        if !resp.status().is_success() {
+            anyhow::bail!("Failed to list artifacts in {:?}: {}", dir, resp.text()?);
+        }
+        let resp: ListArtifactsResponse = serde_json::from_str(&resp.text()?)?;
+
+        std::fs::create_dir_all(self.artifact_base.join(dir))?;
+        for file in resp.files.iter() {
+            let path = dir.join(&file.path);
+            if file.is_dir {
+                self.download_artifacts(&path)?;
+                continue;
+            }
+            let resp = self
+                .http
+                .get(&artifact_url(&self.base_url, &self.run, &path)?)
+                .send()?;
+            if !resp.status().is_success() {
+                anyhow::bail!("Failed to download artifact {:?}: {}", path, resp.text()?);
+            }
+            std::fs::write(self.artifact_base.join(&path), resp.bytes()?)?;
+        }
+        Ok(())
+    }
+
+    /// Stores an image or a histogram in a record as an artifact.
+    fn log_value_artifact(&self, key: &str, value: &RecordValue, step: i64) -> Result<()> {
+        let file = match value {
+            RecordValue::Image(..) => Path::new("images").join(key).join(format!("{}.png", step)),
+            _ => Path::new("histograms")
+                .join(key)
+                .join(format!("{}.json", step)),
+        };
+        let dest = self.artifact_base.join(&file);
+        if let Some(dir) = dest.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        match value {
+            RecordValue::Image(data, [h, w, c]) => {
+                let color = match c {
+                    1 => image::ColorType::L8,
+                    3 => image::ColorType::Rgb8,
+                    _ => anyhow::bail!("Unsupported number of channels: {}", c),
+                };
+                image::save_buffer(&dest, data, *w as u32, *h as u32, color)?;
+            }
+            RecordValue::Histogram(values) => {
+                serde_json::to_writer(File::create(&dest)?, values)?;
+            }
+            _ => unreachable!(),
+        }
+        self.upload_artifact(&file)
+    }
+
+    /// Returns the last step of metrics logged in the run before this recorder was created,
+    /// or `None` if no metric has been logged.
+    ///
+    /// When training is resumed from a checkpoint, the trainer should continue from this step,
+    /// so that metrics are logged with steps continuing from the previous run.
+    pub fn last_step(&self) -> Option<i64> {
+        self.run.last_steps().values().copied().max()
+    }
+
+    /// Sets how metrics are logged to the run resumed with metrics logged before.
+    pub fn step_resumption(mut self, v: StepResumption) -> Self {
+        self.steps = MetricSteps::new(self.run.last_steps(), v);
+        self
+    }
+
+    /// Logs parameters of the run.
+    ///
+    /// Nested parameters are flattened with keys joined by `.`. The parameters are also saved
+    /// as an artifact `params.json`, which is used by [`MlflowTrackingClient::load_run_config()`]
+    /// to rebuild the original configuration.
+    ///
+    /// [`MlflowTrackingClient::load_run_config()`]: crate::MlflowTrackingClient::load_run_config
     pub fn log_params(&self, params: impl Serialize) -> Result<()> {
         let url = format!("{}/api/2.0/mlflow/runs/log-parameter", self.base_url);
-        let flatten_map = {
-            let map = match serde_json::to_value(params).unwrap() {
-                Value::Object(map) => map,
-                _ => panic!("Failed to parse object"),
-            };
-            flatten_serde_json::flatten(&map)
+        let map = match serde_json::to_value(params).unwrap() {
+            Value::Object(map) => map,
+            _ => panic!("Failed to parse object"),
         };
+        let flatten_map = flatten_serde_json::flatten(&map);
         for (key, value) in flatten_map.iter() {
             let params = LogParamParams {
                 run_id: &self.run.info.run_id,
@@ -138,16 +339,64 @@ where
                 value: value.to_string(),
             };
             let _resp = self
-                .client
+                .http
                 .post(&url)
-                .basic_auth(&self.user_name, Some(&self.password))
                 .json(&params) // auto serialize
                 .send()
                 .unwrap();
             // TODO: error handling caused by API call
         }
 
-        Ok(())
+        self.save_params_artifact(map)
+    }
+
+    /// Saves parameters in the artifact directory, merged with those logged before.
+    fn save_params_artifact(&self, map: Map<String, Value>) -> Result<()> {
+        let path = self.artifact_base.join(crate::PARAMS_ARTIFACT);
+        let mut params: Map<String, Value> = match path.exists() {
+            true => serde_json::from_reader(File::open(&path)?)?,
+            false => Map::new(),
+        };
+        params.extend(map);
+        std::fs::create_dir_all(&self.artifact_base)?;
+        serde_json::to_writer_pretty(File::create(&path)?, &params)?;
+        self.upload_artifact(crate::PARAMS_ARTIFACT.as_ref())
+    }
+
+    /// Logs parameters of the run with the differences from their default values.
+    ///
+    /// In addition to [`MlflowTrackingRecorder::log_params()`], parameters different from those
+    /// of `T::default()` are set as tags with prefix `params_diff.`, e.g., `params_diff.agent.lr`,
+    /// and saved as an artifact `params_diff.json` with their default values. This helps to see
+    /// at a glance what was changed in each experiment.
+    pub fn log_params_with_diff<T: Serialize + Default>(&self, params: &T) -> Result<()> {
+        let to_map = |params: &T| -> Result<Map<String, Value>> {
+            match serde_json::to_value(params)? {
+                Value::Object(map) => Ok(flatten_serde_json::flatten(&map)),
+                _ => Err(anyhow::anyhow!("Failed to parse object")),
+            }
+        };
+        let diff = crate::diff_params(&to_map(params)?, &to_map(&T::default())?);
+
+        self.log_params(params)?;
+        for (key, (value, _)) in diff.iter() {
+            self.set_tag(format!("params_diff.{}", key), value.to_string())?;
+        }
+
+        let diff = diff
+            .into_iter()
+            .map(|(key, (value, default))| {
+                let mut map = Map::new();
+                map.insert("value".to_string(), value);
+                map.insert("default".to_string(), default);
+                (key, Value::Object(map))
+            })
+            .collect::<Map<_, _>>();
+        std::fs::create_dir_all(&self.artifact_base)?;
+        let path = self.artifact_base.join(crate::PARAMS_DIFF_ARTIFACT);
+        serde_json::to_writer_pretty(File::create(&path)?, &diff)?;
+
+        self.upload_artifact(crate::PARAMS_DIFF_ARTIFACT.as_ref())
     }
 
     /// Set tag.
@@ -165,13 +414,7 @@ where
             key: &key.as_ref().to_string(),
             value: &value.as_ref().to_string(),
         };
-        let _resp = self
-            .client
-            .post(&url)
-            .basic_auth(&self.user_name, Some(&self.password))
-            .json(&params)
-            .send()
-            .unwrap();
+        let _resp = self.http.post(&url).json(&params).send().unwrap();
 
         Ok(())
     }
@@ -201,6 +444,13 @@ where
             if *key != "opt_steps" {
                 match value {
                     RecordValue::Scalar(v) => {
+                        let step = match self.steps.step(key, step) {
+                            Some(step) => step,
+                            None => {
+                                log::debug!("Skip {} at step {} logged before", key, step);
+                                continue;
+                            }
+                        };
                         let value = *v as f64;
                         let params = LogMetricParams {
                             run_id: &self.run.info.run_id,
@@ -210,14 +460,18 @@ where
                             step,
                         };
                         let _resp = self
-                            .client
+                            .http
                             .post(&url)
-                            .basic_auth(&self.user_name, Some(&self.password))
                             .json(&params) // auto serialize
                             .send()
                             .unwrap();
                         // TODO: error handling caused by API call
                     }
+                    RecordValue::Image(..) | RecordValue::Histogram(..) => {
+                        if let Err(e) = self.log_value_artifact(key, value, step) {
+                            log::warn!("Failed to store {} at step {}: {}", key, step, e);
+                        }
+                    }
                     _ => {} // ignore record value
                 }
             }
@@ -236,10 +490,13 @@ where
 
     /// Save model parameters as MLflow artifacts.
     ///
-    /// MLflow server is assumed to be running on the same host as the program using this struct.
-    /// Under this condition, this method saves model parameters under the `mlruns` directory managed by
-    /// the MLflow server. This method recognizes the environment variable `MLFLOW_DEFAULT_ARTIFACT_ROOT`
-    /// as the location of the `mlruns` directory.
+    /// By default, MLflow server is assumed to be running on the same host as the program using
+    /// this struct. Under this condition, this method saves model parameters under the `mlruns`
+    /// directory managed by the MLflow server. This method recognizes the environment variable
+    /// `MLFLOW_DEFAULT_ARTIFACT_ROOT` as the location of the `mlruns` directory.
+    ///
+    /// With [`ArtifactStore::Path`], model parameters are saved under the given directory instead.
+    /// With [`ArtifactStore::Http`], they are uploaded to the tracking server.
     fn save_model(&self, base: &Path, agent: &Box<dyn border_core::Agent<E, R>>) -> Result<()> {
         // Saves the artifacts in the temporary directory
         let tmp = TempDir::new("mlflow")?;
@@ -247,38 +504,64 @@ where
 
         // Copies the artifacts
         for src in srcs.iter() {
-            let dest = {
-                // Create subdirectory
-                let path = self.artifact_base.join(base);
-                if !path.exists() {
-                    let _ = std::fs::create_dir(&path)?;
-                }
-
-                // Create destination file path
-                let file = src.strip_prefix(tmp.path())?;
-                path.join(file)
-            };
+            let file = base.join(src.strip_prefix(tmp.path())?);
+            let dest = self.artifact_base.join(&file);
+            if let Some(dir) = dest.parent() {
+                std::fs::create_dir_all(dir)?;
+            }
             let bytes = std::fs::copy(src, &dest)?;
             log::info!("Save {:?}", &src);
             log::info!("Copy {:?}, {:.2}MB", &dest, bytes as f32 / (1024. * 1024.));
+            self.upload_artifact(&file)?;
         }
         Ok(())
     }
 
     /// Loads model parameters previously saved as MLflow artifacts.
     ///
-    /// This method uses `MLFLOW_DEFAULT_ARTIFACT_ROOT` environment variable as the directory
-    /// where artifacts, like model parameters, will be saved. It is recommended to set this
-    /// environment variable `mlruns` directory to which the tracking server persists experiment
-    /// and run data.
+    /// By default, this method uses `MLFLOW_DEFAULT_ARTIFACT_ROOT` environment variable as
+    /// the directory where artifacts, like model parameters, will be saved. It is recommended
+    /// to set this environment variable `mlruns` directory to which the tracking server persists
+    /// experiment and run data. With [`ArtifactStore::Http`], model parameters are downloaded
+    /// from the tracking server.
     fn load_model(&self, base: &Path, agent: &mut Box<dyn Agent<E, R>>) -> Result<()> {
-        // Get the directory to which artifacts will be saved
-        let artifact_base = crate::get_artifact_base(self.run.clone())?;
+        if self.artifact_store == ArtifactStore::Http {
+            self.download_artifacts(base)?;
+        }
 
         // Load model parameters from the artifact directory
-        let path = &artifact_base.join(base);
+        let path = &self.artifact_base.join(base);
         agent.load_params(path)
     }
+
+    /// Saves the metadata of a model as an MLflow artifact in the directory of the model.
+    fn save_model_metadata(&self, base: &Path, metadata: &ModelMetadata) -> Result<()> {
+        let file = base.join(ModelMetadata::FILE);
+        let dest = self.artifact_base.join(&file);
+        if let Some(dir) = dest.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        metadata.save(&dest)?;
+        self.upload_artifact(&file)
+    }
+
+    /// Removes the artifacts of a model.
+    ///
+    /// With [`ArtifactStore::Http`], the artifacts are also deleted from the tracking server.
+    fn remove_model(&self, base: &Path) -> Result<()> {
+        let path = self.artifact_base.join(base);
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        if self.artifact_store == ArtifactStore::Http {
+            let url = artifact_url(&self.base_url, &self.run, base)?;
+            let resp = self.http.delete(&url).send()?;
+            if !resp.status().is_success() {
+                anyhow::bail!("Failed to delete artifact {:?}: {}", base, resp.text()?);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<E, R> Drop for MlflowTrackingRecorder<E, R>
@@ -308,9 +591,8 @@ where
             run_name: &self.run.info.run_name,
         };
         let _resp = self
-            .client
+            .http
             .post(&url)
-            .basic_auth(&self.user_name, Some(&self.password))
             .json(&params) // auto serialize
             .send()
             .unwrap();
@@ -326,3 +608,30 @@ fn format_duration(dt: &Duration) -> String {
     minutes %= 60;
     format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metric_steps() {
+        let last_steps = || {
+            vec![("loss".to_string(), 100), ("return".to_string(), 80)]
+                .into_iter()
+                .collect::<HashMap<_, _>>()
+        };
+
+        let mut steps = MetricSteps::new(last_steps(), StepResumption::Skip);
+        assert_eq!(steps.step("loss", 90), None);
+        assert_eq!(steps.step("loss", 100), None);
+        assert_eq!(steps.step("loss", 110), Some(110));
+        assert_eq!(steps.step("return", 90), Some(90));
+        assert_eq!(steps.step("lr", 0), Some(0));
+        assert_eq!(steps.step("lr", 0), None);
+
+        let mut steps = MetricSteps::new(last_steps(), StepResumption::Offset);
+        assert_eq!(steps.step("loss", 0), None);
+        assert_eq!(steps.step("loss", 10), Some(110));
+        assert_eq!(steps.step("return", 0), Some(100));
+    }
+}
@@ -1,4 +1,5 @@
 use serde::Deserialize;
+use std::collections::HashMap;
 
 #[allow(dead_code)]
 #[derive(Clone, Debug, Deserialize)]
@@ -21,6 +22,36 @@ impl Run {
         }
         return false;
     }
+
+    /// Returns parameters logged to the run as pairs of keys and values.
+    pub fn params(&self) -> Vec<(String, String)> {
+        self.data
+            .as_ref()
+            .and_then(|data| data.params.as_ref())
+            .map(|params| {
+                params
+                    .iter()
+                    .map(|p| (p.key.clone(), p.value.clone()))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the last step of each metric logged to the run.
+    ///
+    /// It is taken from the latest values of metrics in the run data, so the run should be
+    /// obtained from the tracking server, e.g., with [`MlflowTrackingClient::get_run()`].
+    ///
+    /// [`MlflowTrackingClient::get_run()`]: crate::MlflowTrackingClient::get_run
+    pub fn last_steps(&self) -> HashMap<String, i64> {
+        let mut steps = HashMap::new();
+        let metrics = self.data.as_ref().and_then(|data| data.metrics.as_ref());
+        for m in metrics.into_iter().flatten() {
+            let step = steps.entry(m.key.clone()).or_insert(m.step);
+            *step = m.step.max(*step);
+        }
+        steps
+    }
 }
 
 #[allow(dead_code)]
@@ -1,4 +1,5 @@
 use crate::Mat;
+use anyhow::{ensure, Result};
 use serde::{Deserialize, Serialize};
 
 #[cfg(feature = "tch")]
@@ -27,6 +28,67 @@ impl Mlp {
         x.tanh()
     }
 
+    /// Returns the dimension of the input.
+    pub fn in_dim(&self) -> usize {
+        self.ws[0].shape[1] as _
+    }
+
+    /// Returns the dimension of the output.
+    pub fn out_dim(&self) -> usize {
+        self.ws[self.ws.len() - 1].shape[0] as _
+    }
+
+    /// Returns an error if the shapes of the layers are inconsistent.
+    ///
+    /// The weight of each layer is `[out_dim, in_dim]` and the bias is `[out_dim, 1]`, where
+    /// `in_dim` is `out_dim` of the previous layer. It should be called on MLPs deserialized
+    /// from untrusted files before [`Mlp::forward()`], which panics on inconsistent shapes.
+    pub fn check(&self) -> Result<()> {
+        ensure!(!self.ws.is_empty(), "MLP has no layers");
+        ensure!(
+            self.ws.len() == self.bs.len(),
+            "{} weights do not match {} biases",
+            self.ws.len(),
+            self.bs.len()
+        );
+        let mut in_dim = None;
+        for (i, (w, b)) in self.ws.iter().zip(self.bs.iter()).enumerate() {
+            for (name, x) in [("weight", w), ("bias", b)] {
+                ensure!(
+                    x.shape.len() == 2 && x.shape.iter().all(|&d| d > 0),
+                    "Invalid shape of the {} of layer {}: {:?}",
+                    name,
+                    i,
+                    x.shape
+                );
+                let len = x.shape[0] as usize * x.shape[1] as usize;
+                ensure!(
+                    x.data.len() == len,
+                    "{} values do not match the shape of the {} of layer {}: {:?}",
+                    x.data.len(),
+                    name,
+                    i,
+                    x.shape
+                );
+            }
+            ensure!(
+                in_dim.is_none_or(|d| d == w.shape[1]),
+                "Input dimension {} of layer {} does not match the output of the previous layer",
+                w.shape[1],
+                i
+            );
+            ensure!(
+                b.shape == [w.shape[0], 1],
+                "Shape of the bias {:?} of layer {} does not match the weight {:?}",
+                b.shape,
+                i,
+                w.shape
+            );
+            in_dim = Some(w.shape[0]);
+        }
+        Ok(())
+    }
+
     #[cfg(feature = "tch")]
     pub fn from_varstore(vs: &VarStore, w_names: &[&str], b_names: &[&str]) -> Self {
         let vars = vs.variables();
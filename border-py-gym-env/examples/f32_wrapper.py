@@ -54,10 +54,133 @@ class F32Wrapper(gym.Wrapper):
 
         return (obs, reward, terminated, truncated, info)
 
-def make_f32(env_name, render_mode=None):
+def make_f32(env_name, render_mode=None, **kwargs):
     if render_mode is not None:
-        env = gym.make(env_name, render_mode=render_mode)
+        env = gym.make(env_name, render_mode=render_mode, **kwargs)
     else:
-        env = gym.make(env_name)
+        env = gym.make(env_name, **kwargs)
 
     return F32Wrapper(env)
+
+
+def _stack(xs):
+    """Stacks observations of environments, supporting dict observations."""
+    if isinstance(xs[0], dict):
+        return {key: np.stack([x[key] for x in xs]) for key in xs[0].keys()}
+    else:
+        return np.stack(xs)
+
+
+class SyncVecEnv:
+    """Vectorized environment running environments sequentially in this process.
+
+    Unlike `gymnasium.vector`, environments are not reset automatically when episodes end.
+    They are reset with `reset(mask)`, where `mask[i]` is `True` for environments to be reset.
+    Rewards and flags are returned as lists.
+    """
+    def __init__(self, env_fns):
+        self.envs = [env_fn() for env_fn in env_fns]
+        self.n_envs = len(self.envs)
+        self.action_space = self.envs[0].action_space
+        self.observation_space = self.envs[0].observation_space
+        self._obs = [None] * self.n_envs
+
+    def reset(self, mask=None, seed=None):
+        for i, env in enumerate(self.envs):
+            if mask is None or mask[i]:
+                kwargs = {} if seed is None else {"seed": seed + i}
+                self._obs[i], _ = env.reset(**kwargs)
+        return (_stack(self._obs), {})
+
+    def step(self, acts):
+        rewards, terminated, truncated = [], [], []
+        for i, env in enumerate(self.envs):
+            (obs, reward, term, trunc, _) = env.step(acts[i])
+            self._obs[i] = obs
+            rewards.append(float(reward))
+            terminated.append(bool(term))
+            truncated.append(bool(trunc))
+        return (_stack(self._obs), rewards, terminated, truncated, {})
+
+    def close(self):
+        for env in self.envs:
+            env.close()
+
+
+def _worker(remote, parent_remote, env_fn):
+    parent_remote.close()
+    env = env_fn()
+    try:
+        while True:
+            (cmd, data) = remote.recv()
+            if cmd == "reset":
+                (obs, _) = env.reset(**data)
+                remote.send(obs)
+            elif cmd == "step":
+                (obs, reward, term, trunc, _) = env.step(data)
+                remote.send((obs, float(reward), bool(term), bool(trunc)))
+            elif cmd == "spaces":
+                remote.send((env.action_space, env.observation_space))
+            elif cmd == "close":
+                env.close()
+                break
+    finally:
+        remote.close()
+
+
+class SubprocVecEnv:
+    """Vectorized environment running each environment in a subprocess.
+
+    The interface is the same as `SyncVecEnv`. Subprocesses are started with `fork`,
+    as `spawn` does not work in the Python interpreter embedded in Rust programs.
+    """
+    def __init__(self, env_fns, context="fork"):
+        import multiprocessing as mp
+        ctx = mp.get_context(context)
+        self.n_envs = len(env_fns)
+        self.remotes, self.work_remotes = zip(*[ctx.Pipe() for _ in range(self.n_envs)])
+        self.processes = []
+        for (work_remote, remote, env_fn) in zip(self.work_remotes, self.remotes, env_fns):
+            process = ctx.Process(target=_worker, args=(work_remote, remote, env_fn), daemon=True)
+            process.start()
+            self.processes.append(process)
+            work_remote.close()
+        self.remotes[0].send(("spaces", None))
+        (self.action_space, self.observation_space) = self.remotes[0].recv()
+        self._obs = [None] * self.n_envs
+        self.closed = False
+
+    def reset(self, mask=None, seed=None):
+        ixs = [i for i in range(self.n_envs) if mask is None or mask[i]]
+        for i in ixs:
+            kwargs = {} if seed is None else {"seed": seed + i}
+            self.remotes[i].send(("reset", kwargs))
+        for i in ixs:
+            self._obs[i] = self.remotes[i].recv()
+        return (_stack(self._obs), {})
+
+    def step(self, acts):
+        for (i, remote) in enumerate(self.remotes):
+            remote.send(("step", acts[i]))
+        results = [remote.recv() for remote in self.remotes]
+        (obs, rewards, terminated, truncated) = zip(*results)
+        self._obs = list(obs)
+        return (_stack(self._obs), list(rewards), list(terminated), list(truncated), {})
+
+    def close(self):
+        if self.closed:
+            return
+        for remote in self.remotes:
+            remote.send(("close", None))
+        for process in self.processes:
+            process.join()
+        self.closed = True
+
+
+def make_f32_vec(env_name, n_envs, asynchronous=False, **kwargs):
+    """Creates a vectorized environment of `n_envs` environments made by `make_f32()`."""
+    env_fns = [lambda: make_f32(env_name, **kwargs) for _ in range(n_envs)]
+    if asynchronous:
+        return SubprocVecEnv(env_fns)
+    else:
+        return SyncVecEnv(env_fns)
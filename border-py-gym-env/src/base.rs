@@ -5,12 +5,18 @@ use border_core::{
     record::{Record, RecordValue::Scalar},
     Env, Info, Step,
 };
-use log::{info, trace};
+use log::{info, trace, warn};
 // use pyo3::IntoPy;
-use pyo3::types::{IntoPyDict, PyTuple};
+use crate::{
+    util::{arrayd_to_pyobj, json_to_pyobj},
+    FrameConfig, ProbeAct, SpaceInfo,
+};
+use numpy::PyArrayDyn;
+use pyo3::types::{IntoPyDict, PyDict, PyTuple};
 use pyo3::{types::PyModule, PyObject, Python, ToPyObject};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use std::{fmt::Debug, time::Duration};
+use serde_json::Value;
+use std::{collections::HashMap, fmt::Debug, time::Duration};
 
 /// Information given at every step of the interaction with the environment.
 ///
@@ -48,6 +54,104 @@ pub trait GymEnvConverter {
         let obs = self.filt_obs(obs)?;
         Ok(obs)
     }
+
+    /// Convert batched observations of [`PyVecGymEnv`] to [`Self::Obs`].
+    ///
+    /// The first axis of the observations is the index of environments.
+    /// The default implementation returns an error, as vectorized environments are not supported.
+    ///
+    /// [`PyVecGymEnv`]: crate::PyVecGymEnv
+    #[allow(unused_variables)]
+    fn filt_obs_vec(&mut self, obs: PyObject) -> Result<Self::Obs> {
+        Err(anyhow::anyhow!(
+            "The converter does not support vectorized environments"
+        ))
+    }
+
+    /// Convert batched actions of [`PyVecGymEnv`] to [`PyObject`].
+    ///
+    /// The first axis of the returned actions must be the index of environments.
+    /// The default implementation returns an error, as vectorized environments are not supported.
+    ///
+    /// [`PyVecGymEnv`]: crate::PyVecGymEnv
+    #[allow(unused_variables)]
+    fn filt_act_vec(&mut self, act: Self::Act) -> Result<PyObject> {
+        Err(anyhow::anyhow!(
+            "The converter does not support vectorized environments"
+        ))
+    }
+
+    /// Converts actions sampled from the action space to [`Self::Act`] to probe the environment.
+    ///
+    /// It is used by [`Env::probe_actions()`] of [`GymEnv`]. The actions have the shape of the
+    /// action space. The default implementation returns no action.
+    #[allow(unused_variables)]
+    fn probe_acts(&self, acts: Vec<ProbeAct>) -> Vec<Self::Act> {
+        vec![]
+    }
+
+    /// Converts batched actions sampled from the action space to [`Self::Act`] to probe
+    /// [`PyVecGymEnv`].
+    ///
+    /// The first axis of the actions is the index of environments.
+    /// The default implementation returns no action.
+    ///
+    /// [`PyVecGymEnv`]: crate::PyVecGymEnv
+    #[allow(unused_variables)]
+    fn probe_acts_vec(&self, acts: Vec<ProbeAct>) -> Vec<Self::Act> {
+        vec![]
+    }
+}
+
+/// Handling of continuous actions outside the bounds of the action space.
+///
+/// The bounds are taken from `low` and `high` of the `Box` action space of the environment.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum ActBounds {
+    /// Actions are given to the environment as they are.
+    #[default]
+    Ignore,
+
+    /// Actions are clipped to the bounds.
+    Clip,
+
+    /// Actions in `[-1, 1]` are linearly rescaled to the bounds, then clipped.
+    Rescale,
+}
+
+impl ActBounds {
+    /// Checks the bounds of the action space.
+    ///
+    /// The bounds must have the same non-zero length, without NaN, and `low <= high`.
+    /// [`ActBounds::Rescale`] additionally requires finite bounds.
+    fn check(&self, low: &[f32], high: &[f32]) -> Result<()> {
+        if low.is_empty() || low.len() != high.len() {
+            anyhow::bail!(
+                "Invalid bounds of the action space: {} lows and {} highs",
+                low.len(),
+                high.len()
+            );
+        }
+        for (i, (lo, hi)) in low.iter().zip(high.iter()).enumerate() {
+            if lo.is_nan() || hi.is_nan() || lo > hi {
+                anyhow::bail!(
+                    "Invalid bounds of the action space at {}: [{}, {}]",
+                    i,
+                    lo,
+                    hi
+                );
+            }
+            if *self == ActBounds::Rescale && !(lo.is_finite() && hi.is_finite()) {
+                anyhow::bail!(
+                    "Actions cannot be rescaled to infinite bounds at {}: [{}, {}]",
+                    i,
+                    lo,
+                    hi
+                );
+            }
+        }
+        Ok(())
+    }
 }
 
 /// Configuration of [`GymEnv`].
@@ -68,9 +172,24 @@ where
     /// Rendering mode, e.g., "human" or "rgb_array".
     pub render_mode: Option<String>,
 
+    /// Keyword arguments given to `gym.make()`, e.g., `continuous: true` for `CarRacing-v2`.
+    #[serde(default)]
+    pub env_kwargs: HashMap<String, Value>,
+
     /// Wait time at every interaction steps.
+    ///
+    /// It is a fixed sleep, e.g., for rendering. To step the environment at a target rate,
+    /// use [`RateLimit`](border_core::RateLimit).
     pub wait: Duration,
 
+    /// Handling of actions outside the bounds of the action space.
+    #[serde(default)]
+    pub act_bounds: ActBounds,
+
+    /// Preprocessing of pixel observations applied before the converter, if given.
+    #[serde(default)]
+    pub frame: Option<FrameConfig>,
+
     /// Converter of observation and action.
     pub converter_config: C::Config,
 }
@@ -85,7 +204,10 @@ where
             pybullet: false,
             name: "".to_string(),
             render_mode: None,
+            env_kwargs: HashMap::new(),
             wait: Duration::from_millis(0),
+            act_bounds: ActBounds::Ignore,
+            frame: None,
             converter_config: Default::default(),
         }
     }
@@ -112,18 +234,68 @@ where
         self
     }
 
+    /// Set keyword arguments given to `gym.make()`.
+    pub fn env_kwargs(mut self, env_kwargs: HashMap<String, Value>) -> Self {
+        self.env_kwargs = env_kwargs;
+        self
+    }
+
+    /// Add a keyword argument given to `gym.make()`.
+    pub fn env_kwarg(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.env_kwargs.insert(key.into(), value.into());
+        self
+    }
+
     /// Set wait time in milli seconds.
     pub fn set_wait_in_millis(mut self, millis: u64) -> Self {
         self.wait = Duration::from_millis(millis);
         self
     }
 
+    /// Set handling of actions outside the bounds of the action space.
+    pub fn act_bounds(mut self, v: ActBounds) -> Self {
+        self.act_bounds = v;
+        self
+    }
+
+    /// Set preprocessing of pixel observations.
+    pub fn frame(mut self, v: FrameConfig) -> Self {
+        self.frame = Some(v);
+        self
+    }
+
     pub fn converter_config(mut self, config: C::Config) -> Self {
         self.converter_config = config;
         self
     }
 }
 
+impl<C> GymEnvConfig<C>
+where
+    C: GymEnvConverter + Clone,
+{
+    /// Returns the observation and action spaces of the environment.
+    ///
+    /// The environment is created without rendering and closed, so that the dimensions of
+    /// models can be derived from the spaces before training.
+    /// See [`GymEnv::observation_space()`] and [`GymEnv::action_space()`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either of the spaces is not supported by [`SpaceInfo`].
+    pub fn spaces(&self) -> Result<(SpaceInfo, SpaceInfo)> {
+        let config = self.clone().render_mode(None);
+        let mut env = GymEnv::<C>::build(&config, 0)?;
+        env.close()?;
+        match (env.observation_space.take(), env.action_space.take()) {
+            (Some(obs_space), Some(act_space)) => Ok((obs_space, act_space)),
+            _ => Err(anyhow::anyhow!(
+                "Spaces of the environment are not supported"
+            )),
+        }
+    }
+}
+
 /// An wrapper of [Gymnasium](https://gymnasium.farama.org).
 #[derive(Debug)]
 pub struct GymEnv<C>
@@ -142,6 +314,18 @@ where
     ///
     /// This value will be used at the first call of the reset method.
     initial_seed: Option<i64>,
+    /// Handling of actions and the lower and upper bounds of the action space.
+    act_bounds: Option<(ActBounds, Vec<f32>, Vec<f32>)>,
+    /// The number of steps in the current episode where actions were clipped.
+    count_clipped_steps: usize,
+    /// `true` if the Python environment has been closed.
+    closed: bool,
+    /// Preprocessing of pixel observations.
+    frame: Option<FrameConfig>,
+    /// Observation space, after the preprocessing of pixel observations, if supported.
+    observation_space: Option<SpaceInfo>,
+    /// Action space, if supported.
+    action_space: Option<SpaceInfo>,
 }
 
 impl<C> GymEnv<C>
@@ -174,6 +358,41 @@ where
         self.wait = d;
     }
 
+    /// Returns the observation space of the environment.
+    ///
+    /// If [`GymEnvConfig::frame`] is given, the shape of frames is that after preprocessing.
+    /// It is `None` if the space is not supported by [`SpaceInfo`].
+    pub fn observation_space(&self) -> Option<&SpaceInfo> {
+        self.observation_space.as_ref()
+    }
+
+    /// Returns the action space of the environment.
+    ///
+    /// It is `None` if the space is not supported by [`SpaceInfo`].
+    pub fn action_space(&self) -> Option<&SpaceInfo> {
+        self.action_space.as_ref()
+    }
+
+    /// Preprocesses a pixel observation if configured.
+    fn filt_frame(&self, obs: PyObject) -> Result<PyObject> {
+        match &self.frame {
+            Some(frame) => frame.filt(obs, false),
+            None => Ok(obs),
+        }
+    }
+
+    /// Calls `close()` of the Python environment if it has not been closed.
+    fn close_py_env(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+        Python::with_gil(|py| {
+            self.env.call_method0(py, "close")?;
+            Ok(())
+        })
+    }
+
     // /// Get the number of available actions of atari environments
     // pub fn get_num_actions_atari(&self) -> i64 {
     //     pyo3::Python::with_gil(|py| {
@@ -196,6 +415,52 @@ where
 
         Ok((is_terminated, is_truncated))
     }
+
+    /// Returns the bounds of a `Box` action space, or `None` for other spaces.
+    fn act_space_bounds(action_space: &pyo3::PyAny) -> Result<Option<(Vec<f32>, Vec<f32>)>> {
+        if !action_space.hasattr("low")? || !action_space.hasattr("high")? {
+            return Ok(None);
+        }
+        let to_vec = |name: &str| -> Result<Vec<f32>> {
+            let v = action_space.getattr(name)?;
+            let v = v
+                .call_method1("astype", ("float32",))?
+                .call_method0("ravel")?;
+            Ok(v.call_method0("tolist")?.extract()?)
+        };
+        Ok(Some((to_vec("low")?, to_vec("high")?)))
+    }
+
+    /// Rescales and/or clips a continuous action to the bounds of the action space.
+    ///
+    /// Returns the action and `true` if any of its elements was clipped.
+    /// Actions other than `float32` arrays are returned as they are.
+    fn bound_act(&self, py: Python, act: PyObject) -> Result<(PyObject, bool)> {
+        let (mode, low, high) = match &self.act_bounds {
+            Some(bounds) => bounds,
+            None => return Ok((act, false)),
+        };
+        let mut a = match act.extract::<&PyArrayDyn<f32>>(py) {
+            Ok(a) => a.to_owned_array(),
+            Err(_) => return Ok((act, false)),
+        };
+
+        // Elements are matched with the bounds in the order of the flattened action space,
+        // which supports a leading batch dimension
+        let mut is_clipped = false;
+        for (i, v) in a.iter_mut().enumerate() {
+            let (lo, hi) = (low[i % low.len()], high[i % high.len()]);
+            if *mode == ActBounds::Rescale {
+                *v = lo + (*v + 1.0) * 0.5 * (hi - lo);
+            }
+            if *v < lo || *v > hi {
+                *v = v.clamp(lo, hi);
+                is_clipped = true;
+            }
+        }
+
+        Ok((arrayd_to_pyobj(a), is_clipped))
+    }
 }
 
 impl<C> Env for GymEnv<C>
@@ -238,6 +503,7 @@ where
                 floor.getattr("add_floor")?.call1((&self.env,)).unwrap();
             }
             // Reset the state
+            let obs = self.filt_frame(obs)?;
             Ok(self.converter.reset(obs)?)
         });
 
@@ -262,6 +528,38 @@ where
         self.reset(None)
     }
 
+    /// Returns actions sampled with [`SpaceInfo::probe_acts()`], converted with
+    /// [`GymEnvConverter::probe_acts()`].
+    ///
+    /// Continuous actions are scaled to `[-1, 1]` if they are rescaled with [`ActBounds::Rescale`].
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        let mut acts = match &self.action_space {
+            Some(space) => space.probe_acts(n_random),
+            None => return vec![],
+        };
+        if let Some((ActBounds::Rescale, low, high)) = &self.act_bounds {
+            for act in acts.iter_mut() {
+                if let ProbeAct::Continuous(a) = act {
+                    for (i, v) in a.iter_mut().enumerate() {
+                        let (lo, hi) = (low[i % low.len()], high[i % high.len()]);
+                        *v = match hi > lo && (hi - lo).is_finite() {
+                            true => 2.0 * (*v - lo) / (hi - lo) - 1.0,
+                            false => 0.0,
+                        };
+                    }
+                }
+            }
+        }
+        self.converter.probe_acts(acts)
+    }
+
+    /// Closes the Python environment, releasing render windows and subprocesses.
+    ///
+    /// It is also called when the environment is dropped.
+    fn close(&mut self) -> Result<()> {
+        self.close_py_env()
+    }
+
     /// Runs a step of the environment's dynamics.
     ///
     /// It returns [`Step`] and [`Record`] objects.
@@ -285,6 +583,10 @@ where
             // Run a step
             let step_py = {
                 let a_py = self.converter.filt_act(act.clone()).unwrap();
+                let (a_py, is_clipped) = self.bound_act(py, a_py).unwrap();
+                if is_clipped {
+                    self.count_clipped_steps += 1;
+                }
                 self.env.call_method(py, "step", (a_py,), None).unwrap()
             };
             let step: &PyTuple = step_py.extract(py).unwrap();
@@ -292,7 +594,10 @@ where
             // Observation at the next step
             let obs = {
                 let obs_py = step.get_item(0).to_owned();
-                self.converter.filt_obs(obs_py.to_object(py)).unwrap()
+                let obs_py = self
+                    .filt_frame(obs_py.to_object(py))
+                    .expect("Failed to preprocess a frame");
+                self.converter.filt_obs(obs_py).unwrap()
                 // self.converter.filt_obs(obs_py.into()).unwrap()
             };
 
@@ -321,7 +626,12 @@ where
 
             if (is_terminated[0] | is_truncated[0]) == 1 {
                 record.insert("episode_length", Scalar(self.count_steps as _));
+                if self.act_bounds.is_some() {
+                    let rate = self.count_clipped_steps as f32 / self.count_steps as f32;
+                    record.insert("act_clip_rate", Scalar(rate));
+                }
                 self.count_steps = 0;
+                self.count_clipped_steps = 0;
             }
 
             // Returned step object
@@ -344,6 +654,9 @@ where
     /// * `seed` - The seed value of the random number generator.
     ///   This value will be used at the first call of the reset method.
     fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        if let Some(frame) = config.frame.as_ref() {
+            frame.check()?;
+        }
         let gil = Python::acquire_gil();
         let py = gil.python();
 
@@ -366,23 +679,27 @@ where
         if py.import("IPython").is_ok() {}
 
         let name = config.name.as_str();
+        let env_kwargs = {
+            let kwargs = PyDict::new(py);
+            for (k, v) in config.env_kwargs.iter() {
+                kwargs.set_item(k, json_to_pyobj(py, v)?)?;
+            }
+            kwargs
+        };
         let (env, render) = if !config.pybullet {
             let gym = py.import("f32_wrapper")?;
             let render = config.render_mode.is_some();
             let env = {
-                let kwargs = if let Some(render_mode) = config.render_mode.clone() {
-                    Some(vec![("render_mode", render_mode)].into_py_dict(py))
-                } else {
-                    None
-                };
-                gym.getattr("make_f32")?.call((name,), kwargs)?
+                if let Some(render_mode) = config.render_mode.clone() {
+                    env_kwargs.set_item("render_mode", render_mode)?;
+                }
+                gym.getattr("make_f32")?.call((name,), Some(env_kwargs))?
             };
 
             (env, render)
         } else {
             let gym = py.import("f32_wrapper")?;
-            let kwargs = None;
-            let env = gym.getattr("make_f32")?.call((name,), kwargs)?;
+            let env = gym.getattr("make_f32")?.call((name,), Some(env_kwargs))?;
             if config.render_mode.is_some() {
                 env.call_method("render", ("human",), None).unwrap();
                 (env, true)
@@ -391,12 +708,26 @@ where
             }
         };
 
-        // TODO: consider removing action_space and observation_space.
-        // Act/obs types are specified by type parameters.
         let action_space = env.getattr("action_space")?;
         println!("Action space = {:?}", action_space);
         let observation_space = env.getattr("observation_space")?;
         println!("Observation space = {:?}", observation_space);
+        let act_space_info = SpaceInfo::try_from_py(action_space, None);
+        let obs_space_info = SpaceInfo::try_from_py(observation_space, config.frame.as_ref());
+
+        let act_bounds = match config.act_bounds {
+            ActBounds::Ignore => None,
+            ref mode => match Self::act_space_bounds(action_space)? {
+                Some((low, high)) => {
+                    mode.check(&low, &high)?;
+                    Some((mode.clone(), low, high))
+                }
+                None => {
+                    warn!("act_bounds is ignored as the action space has no bounds");
+                    None
+                }
+            },
+        };
 
         let pybullet_state = if !config.pybullet {
             None
@@ -469,6 +800,40 @@ def update_camera_pos(env):
             pybullet: config.pybullet,
             pybullet_state,
             initial_seed: Some(seed),
+            act_bounds,
+            count_clipped_steps: 0,
+            closed: false,
+            frame: config.frame.clone(),
+            observation_space: obs_space_info,
+            action_space: act_space_info,
         })
     }
 }
+
+impl<C> Drop for GymEnv<C>
+where
+    C: GymEnvConverter,
+{
+    fn drop(&mut self) {
+        if let Err(e) = self.close_py_env() {
+            warn!("Failed to close the environment: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_act_bounds() {
+        let inf = f32::INFINITY;
+        assert!(ActBounds::Clip.check(&[-1.0, 0.0], &[1.0, 0.0]).is_ok());
+        assert!(ActBounds::Clip.check(&[-inf], &[inf]).is_ok());
+        assert!(ActBounds::Rescale.check(&[-inf], &[inf]).is_err());
+        assert!(ActBounds::Clip.check(&[], &[]).is_err());
+        assert!(ActBounds::Clip.check(&[-1.0], &[1.0, 1.0]).is_err());
+        assert!(ActBounds::Clip.check(&[f32::NAN], &[1.0]).is_err());
+        assert!(ActBounds::Clip.check(&[1.0], &[-1.0]).is_err());
+    }
+}
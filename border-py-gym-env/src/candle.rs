@@ -9,6 +9,7 @@ mod ndarray_dict_obs_converter;
 mod ndarray_obs;
 pub mod tensor;
 mod tensor_batch;
+mod tensor_data;
 pub use ndarray_act::NdarrayAct;
 pub use ndarray_converter::{NdarrayConverter, NdarrayConverterConfig};
 pub use ndarray_dict_obs::NdarrayDictObs;
@@ -1,15 +1,48 @@
 use super::{arrayd_to_tensor, tensor_to_arrayd, TensorBatch};
+use crate::{
+    util::{add_gaussian_noise, continuous_act_values},
+    ProbeAct,
+};
+use border_core::{ActValues, AddNoise};
 use candle_core::{DType, Tensor, D};
 use ndarray::ArrayD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 /// Action.
 pub enum NdarrayAct {
     Continuous(ArrayD<f32>),
     Discrete(ArrayD<i64>),
 }
 
-impl border_core::Act for NdarrayAct {}
+impl border_core::Act for NdarrayAct {
+    fn values(&self) -> Option<ActValues> {
+        match self {
+            Self::Continuous(a) => Some(continuous_act_values(a)),
+            Self::Discrete(a) => ActValues::from_indices(a.iter().copied()),
+        }
+    }
+}
+
+impl AddNoise for NdarrayAct {
+    /// Perturbs continuous actions, discrete actions are returned as they are.
+    fn add_noise(&self, std: f32, rng: &mut dyn RngCore) -> Self {
+        match self {
+            Self::Continuous(a) => Self::Continuous(add_gaussian_noise(a, std, rng)),
+            Self::Discrete(_) => self.clone(),
+        }
+    }
+}
+
+impl From<ProbeAct> for NdarrayAct {
+    fn from(act: ProbeAct) -> Self {
+        match act {
+            ProbeAct::Continuous(a) => Self::Continuous(a),
+            ProbeAct::Discrete(a) => Self::Discrete(a),
+        }
+    }
+}
 
 impl Into<Tensor> for NdarrayAct {
     fn into(self) -> Tensor {
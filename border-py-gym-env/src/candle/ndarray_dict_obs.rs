@@ -1,8 +1,9 @@
 use super::{arrayd_to_tensor, TensorBatch};
 use candle_core::Tensor;
 use ndarray::ArrayD;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 /// Observation of dict of [`ArrayD`].
 ///
 /// Each array represents a vector and its type is `f32`.
@@ -1,6 +1,6 @@
 //! Converter for observation and action of [`NdarrayDictObs`] and [`NdarrayAct`].
 use super::{NdarrayAct, NdarrayDictObs};
-use crate::{util::pyobj_to_arrayd, GymEnvConverter};
+use crate::{util::pyobj_to_arrayd, GymEnvConverter, ProbeAct};
 use anyhow::Result;
 use numpy::PyArrayDyn;
 use pyo3::{IntoPy, PyObject};
@@ -74,4 +74,9 @@ impl GymEnvConverter for NdarrayDictObsConverter {
             }
         }
     }
+
+    /// Converts actions sampled from the action space to [`NdarrayAct`].
+    fn probe_acts(&self, acts: Vec<ProbeAct>) -> Vec<Self::Act> {
+        acts.into_iter().map(Into::into).collect()
+    }
 }
@@ -1,8 +1,12 @@
 use super::{arrayd_to_tensor, TensorBatch};
+use crate::util::{add_gaussian_noise, concat_arrayd};
+use border_core::{AddNoise, NormalizableObs, StackFrames};
 use candle_core::Tensor;
 use ndarray::ArrayD;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 /// Observation.
 pub struct NdarrayObs(pub ArrayD<f32>);
 
@@ -10,6 +14,37 @@ impl border_core::Obs for NdarrayObs {
     fn len(&self) -> usize {
         self.0.shape()[0]
     }
+
+    fn shape(&self) -> Option<Vec<usize>> {
+        Some(self.0.shape()[1..].to_vec())
+    }
+
+    fn is_finite(&self) -> bool {
+        self.0.iter().all(|v| v.is_finite())
+    }
+}
+
+impl AddNoise for NdarrayObs {
+    fn add_noise(&self, std: f32, rng: &mut dyn RngCore) -> Self {
+        Self(add_gaussian_noise(&self.0, std, rng))
+    }
+}
+
+impl StackFrames for NdarrayObs {
+    fn concat_frames(frames: &[Self], axis: usize) -> Self {
+        let xs = frames.iter().map(|f| &f.0).collect::<Vec<_>>();
+        Self(concat_arrayd(&xs, axis))
+    }
+}
+
+impl NormalizableObs for NdarrayObs {
+    fn values(&self) -> Vec<f32> {
+        self.0.iter().cloned().collect()
+    }
+
+    fn with_values(&self, values: Vec<f32>) -> Self {
+        Self(ArrayD::from_shape_vec(self.0.shape(), values).unwrap())
+    }
 }
 
 impl Into<Tensor> for NdarrayObs {
@@ -1,11 +1,12 @@
 //! Converter for Observation and action of [`candle_core::Tensor`].
 use super::{arrayd_to_tensor, tensor_to_arrayd, TensorBatch};
 use crate::{
-    util::{arrayd_to_pyobj, pyobj_to_arrayd, ActionType},
-    GymEnvConverter,
+    util::{arrayd_to_pyobj, pyobj_to_arrayd, pyobj_to_arrayd_vec, ActionType},
+    GymEnvConverter, ProbeAct,
 };
 use anyhow::Result;
-use candle_core::Tensor;
+use border_core::{NormalizableObs, StackFrames};
+use candle_core::{DType, Tensor};
 use numpy::PyArrayDyn;
 use pyo3::{IntoPy, PyObject};
 use serde::{Deserialize, Serialize};
@@ -23,7 +24,31 @@ mod obs {
 
     impl border_core::Obs for TensorObs {
         fn len(&self) -> usize {
-            1
+            self.0.dims()[0]
+        }
+    }
+
+    impl StackFrames for TensorObs {
+        fn concat_frames(frames: &[Self], axis: usize) -> Self {
+            let xs = frames.iter().map(|f| &f.0).collect::<Vec<_>>();
+            Self(Tensor::cat(&xs, axis).unwrap())
+        }
+    }
+
+    impl NormalizableObs for TensorObs {
+        fn values(&self) -> Vec<f32> {
+            self.0
+                .flatten_all()
+                .and_then(|t| t.to_dtype(DType::F32))
+                .and_then(|t| t.to_vec1())
+                .unwrap()
+        }
+
+        fn with_values(&self, values: Vec<f32>) -> Self {
+            let t = Tensor::from_vec(values, self.0.dims(), self.0.device())
+                .and_then(|t| t.to_dtype(self.0.dtype()))
+                .unwrap();
+            Self(t)
         }
     }
 
@@ -143,5 +168,55 @@ mod converter {
                 }
             }
         }
+
+        /// Convert batched observations of a vectorized environment.
+        fn filt_obs_vec(&mut self, obs: PyObject) -> Result<Self::Obs> {
+            let obs = pyobj_to_arrayd_vec::<f32, f32>(obs);
+            Ok(TensorObs(arrayd_to_tensor::<f32, f32>(obs, false)?))
+        }
+
+        /// Convert batched actions of a vectorized environment.
+        ///
+        /// Discrete actions are flattened into a vector, whose length is the number of environments.
+        fn filt_act_vec(&mut self, act: Self::Act) -> Result<PyObject> {
+            match self.action_type {
+                ActionType::Discrete => {
+                    let arrayd = tensor_to_arrayd::<i64>(act.0.flatten_all()?, false)?;
+                    let pyobj = pyo3::Python::with_gil(|py| {
+                        let act = PyArrayDyn::<i64>::from_array(py, &arrayd);
+                        act.into_py(py)
+                    });
+                    Ok(pyobj)
+                }
+                ActionType::Continuous => {
+                    let arrayd = tensor_to_arrayd::<f32>(act.0, false)?;
+                    Ok(arrayd_to_pyobj(arrayd))
+                }
+            }
+        }
+
+        /// Converts actions sampled from the action space to [`TensorAct`] with a batch axis.
+        fn probe_acts(&self, acts: Vec<ProbeAct>) -> Vec<Self::Act> {
+            Self::probe_acts_to_tensor(acts, true)
+        }
+
+        /// Converts batched actions sampled from the action space to [`TensorAct`].
+        fn probe_acts_vec(&self, acts: Vec<ProbeAct>) -> Vec<Self::Act> {
+            Self::probe_acts_to_tensor(acts, false)
+        }
+    }
+
+    impl TensorConverter {
+        fn probe_acts_to_tensor(acts: Vec<ProbeAct>, add_batch_dim: bool) -> Vec<TensorAct> {
+            acts.into_iter()
+                .map(|act| {
+                    let t = match act {
+                        ProbeAct::Continuous(a) => arrayd_to_tensor::<f32, f32>(a, add_batch_dim),
+                        ProbeAct::Discrete(a) => arrayd_to_tensor::<i64, i64>(a, add_batch_dim),
+                    };
+                    TensorAct(t.expect("Failed to convert a probe action to a tensor"))
+                })
+                .collect()
+        }
     }
 }
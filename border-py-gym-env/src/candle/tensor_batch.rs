@@ -1,5 +1,7 @@
-use border_core::generic_replay_buffer::BatchBase;
+use super::tensor_data::TensorData;
+use border_core::generic_replay_buffer::{BatchBase, GoalBatch};
 use candle_core::{error::Result, DType, Device, Tensor};
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 
 /// Adds capability of constructing [`Tensor`] with a static method.
 ///
@@ -88,6 +90,73 @@ impl BatchBase for TensorBatch {
             capacity: ixs.len(),
         }
     }
+
+    fn size_in_bytes(&self) -> Option<usize> {
+        Some(
+            self.buf
+                .iter()
+                .map(|t| t.elem_count() * t.dtype().size_in_bytes())
+                .sum(),
+        )
+    }
+}
+
+/// Used with [`HerReplayBuffer`] for observations of shape `[1, dim]`.
+///
+/// [`HerReplayBuffer`]: border_core::generic_replay_buffer::HerReplayBuffer
+impl GoalBatch for TensorBatch {
+    fn get_goal(&self, ix: usize, offset: usize, dim: usize) -> Vec<f32> {
+        self.buf[ix]
+            .narrow(1, offset, dim)
+            .unwrap()
+            .flatten_all()
+            .unwrap()
+            .to_dtype(DType::F32)
+            .unwrap()
+            .to_vec1()
+            .unwrap()
+    }
+
+    fn set_goal(&mut self, ix: usize, offset: usize, goal: &[f32]) {
+        let t = &self.buf[ix];
+        let dim = t.dims()[1];
+        let goal = Tensor::from_slice(goal, (1, goal.len()), t.device())
+            .unwrap()
+            .to_dtype(t.dtype())
+            .unwrap();
+        let head = t.narrow(1, 0, offset).unwrap();
+        let end = offset + goal.dims()[1];
+        let tail = t.narrow(1, end, dim - end).unwrap();
+        self.buf[ix] = Tensor::cat(&[head, goal, tail], 1).unwrap();
+    }
+}
+
+/// Serializes the shapes, the data types and the values of the samples.
+///
+/// It is used to send samples over the network, e.g., in the networked mode of
+/// `border-async-trainer`.
+impl Serialize for TensorBatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let buf = self
+            .buf
+            .iter()
+            .map(TensorData::from_tensor)
+            .collect::<Result<Vec<_>>>()
+            .map_err(S::Error::custom)?;
+        (self.capacity, buf).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TensorBatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let (capacity, buf) = <(usize, Vec<(Vec<usize>, TensorData)>)>::deserialize(deserializer)?;
+        let buf = buf
+            .into_iter()
+            .map(|(shape, data)| data.into_tensor(shape))
+            .collect::<Result<Vec<_>>>()
+            .map_err(D::Error::custom)?;
+        Ok(Self { buf, capacity })
+    }
 }
 
 impl From<TensorBatch> for Tensor {
@@ -0,0 +1,214 @@
+//! Preprocessing of pixel observations.
+use crate::util::arrayd_to_pyobj;
+use anyhow::Result;
+use ndarray::{stack, Array3, ArrayD, ArrayView3, Axis, Ix3, Ix4};
+use numpy::PyArrayDyn;
+use pyo3::{PyObject, Python};
+use serde::{Deserialize, Serialize};
+
+/// Configuration of the preprocessing of pixel observations in [`GymEnv`] and [`PyVecGymEnv`].
+///
+/// Frames of shape `(height, width, channels)`, e.g., those of `CarRacing-v2` or Atari games
+/// of Gymnasium, are resized, converted to grayscale and normalized on the Rust side,
+/// without Python wrappers. The grayscale conversion uses the same weights of RGB channels
+/// as `border-atari-env`. Processed frames are `float32` arrays given to the converter
+/// of observations, with the channel axis first if [`FrameConfig::channel_first`] is `true`.
+///
+/// [`GymEnv`]: crate::GymEnv
+/// [`PyVecGymEnv`]: crate::PyVecGymEnv
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct FrameConfig {
+    /// Height and width of resized frames. Frames are resized with bilinear interpolation.
+    #[serde(default)]
+    pub resize: Option<(usize, usize)>,
+
+    /// If `true`, RGB frames are converted to grayscale frames with a single channel.
+    #[serde(default)]
+    pub grayscale: bool,
+
+    /// If `true`, pixel values are divided by 255.
+    #[serde(default)]
+    pub normalize: bool,
+
+    /// If `true`, the channel axis of frames is moved to the first.
+    #[serde(default)]
+    pub channel_first: bool,
+}
+
+impl FrameConfig {
+    /// Sets the height and width of resized frames.
+    pub fn resize(mut self, height: usize, width: usize) -> Self {
+        self.resize = Some((height, width));
+        self
+    }
+
+    /// Sets `true` to convert frames to grayscale.
+    pub fn grayscale(mut self, v: bool) -> Self {
+        self.grayscale = v;
+        self
+    }
+
+    /// Sets `true` to divide pixel values by 255.
+    pub fn normalize(mut self, v: bool) -> Self {
+        self.normalize = v;
+        self
+    }
+
+    /// Sets `true` to move the channel axis to the first.
+    pub fn channel_first(mut self, v: bool) -> Self {
+        self.channel_first = v;
+        self
+    }
+
+    /// Checks the configuration, where the size of resized frames must not be zero.
+    pub fn check(&self) -> Result<()> {
+        match self.resize {
+            Some((h, w)) if h == 0 || w == 0 => {
+                anyhow::bail!("Invalid size of resized frames: ({}, {})", h, w)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Processes a frame of shape `(height, width, channels)`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an empty frame or a frame of zero size is resized,
+    /// or if the grayscale conversion is applied to a frame with channels
+    /// other than 1, 3 (RGB) or 4 (RGBA).
+    pub fn process(&self, frame: ArrayView3<f32>) -> Result<Array3<f32>> {
+        let mut frame = match self.resize {
+            Some((h, w)) => resize(frame, h, w)?,
+            None => frame.to_owned(),
+        };
+        if self.grayscale {
+            frame = match frame.dim().2 {
+                1 => frame,
+                3 | 4 => {
+                    let (h, w, _) = frame.dim();
+                    Array3::from_shape_fn((h, w, 1), |(i, j, _)| {
+                        0.299 * frame[[i, j, 0]]
+                            + 0.587 * frame[[i, j, 1]]
+                            + 0.114 * frame[[i, j, 2]]
+                    })
+                }
+                c => anyhow::bail!("Cannot convert a frame with {} channels to grayscale", c),
+            };
+        }
+        if self.normalize {
+            frame.mapv_inplace(|v| v / 255.0);
+        }
+        if self.channel_first {
+            frame = frame
+                .permuted_axes([2, 0, 1])
+                .as_standard_layout()
+                .to_owned();
+        }
+        Ok(frame)
+    }
+
+    /// Processes frames in an observation of the Python environment.
+    ///
+    /// The observation is a `uint8` or `float32` array of shape `(height, width[, channels])`,
+    /// with the leading axis of environments if `batched` is `true`.
+    pub(crate) fn filt(&self, obs: PyObject, batched: bool) -> Result<PyObject> {
+        let frames = Python::with_gil(|py| -> Result<ArrayD<f32>> {
+            if let Ok(a) = obs.extract::<&PyArrayDyn<u8>>(py) {
+                return Ok(a.to_owned_array().mapv(|v| v as f32));
+            }
+            match obs.extract::<&PyArrayDyn<f32>>(py) {
+                Ok(a) => Ok(a.to_owned_array()),
+                Err(_) => anyhow::bail!("Frames must be uint8 or float32 arrays"),
+            }
+        })?;
+
+        // Insert the channel axis of grayscale frames
+        let frames = match frames.ndim() == 2 + batched as usize {
+            true => frames.insert_axis(Axis(frames.ndim())),
+            false => frames,
+        };
+
+        let frames = match batched {
+            false => self
+                .process(frames.into_dimensionality::<Ix3>()?.view())?
+                .into_dyn(),
+            true => {
+                let frames = frames.into_dimensionality::<Ix4>()?;
+                let frames = frames
+                    .outer_iter()
+                    .map(|f| self.process(f))
+                    .collect::<Result<Vec<_>>>()?;
+                let views = frames.iter().map(|f| f.view()).collect::<Vec<_>>();
+                stack(Axis(0), &views)?.into_dyn()
+            }
+        };
+
+        Ok(arrayd_to_pyobj(frames))
+    }
+}
+
+/// Resizes a frame of shape `(height, width, channels)` with bilinear interpolation.
+///
+/// Pixel centers are aligned as in `cv2.resize()` with `INTER_LINEAR`.
+fn resize(frame: ArrayView3<f32>, height: usize, width: usize) -> Result<Array3<f32>> {
+    let (h0, w0, c) = frame.dim();
+    if h0 == 0 || w0 == 0 || height == 0 || width == 0 {
+        anyhow::bail!(
+            "Cannot resize a frame of ({}, {}) to ({}, {})",
+            h0,
+            w0,
+            height,
+            width
+        );
+    }
+    let coords = |n: usize, n0: usize| {
+        (0..n)
+            .map(|i| {
+                let x = ((i as f32 + 0.5) * n0 as f32 / n as f32 - 0.5).max(0.0);
+                let x0 = (x.floor() as usize).min(n0 - 1);
+                let x1 = (x0 + 1).min(n0 - 1);
+                (x0, x1, (x - x0 as f32).min(1.0))
+            })
+            .collect::<Vec<_>>()
+    };
+    let ys = coords(height, h0);
+    let xs = coords(width, w0);
+
+    Ok(Array3::from_shape_fn((height, width, c), |(i, j, k)| {
+        let (y0, y1, dy) = ys[i];
+        let (x0, x1, dx) = xs[j];
+        let top = frame[[y0, x0, k]] * (1.0 - dx) + frame[[y0, x1, k]] * dx;
+        let bottom = frame[[y1, x0, k]] * (1.0 - dx) + frame[[y1, x1, k]] * dx;
+        top * (1.0 - dy) + bottom * dy
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::Array;
+
+    #[test]
+    fn test_frame_config() -> Result<()> {
+        // 4x4 RGB frame, whose pixel values are 10 * row in all channels
+        let frame = Array::from_shape_fn((4, 4, 3), |(i, _, _)| 10.0 * i as f32);
+        let config = FrameConfig::default()
+            .resize(2, 2)
+            .grayscale(true)
+            .normalize(true)
+            .channel_first(true);
+        let frame = config.process(frame.view())?;
+
+        assert_eq!(frame.dim(), (1, 2, 2));
+        assert!((frame[[0, 0, 1]] - 5.0 / 255.0).abs() < 1e-6);
+        assert!((frame[[0, 1, 0]] - 25.0 / 255.0).abs() < 1e-6);
+
+        // Empty frames and zero sizes are rejected
+        let config = FrameConfig::default().resize(2, 2);
+        assert!(config.process(Array3::zeros((0, 4, 3)).view()).is_err());
+        assert!(FrameConfig::default().resize(0, 2).check().is_err());
+        assert!(config.check().is_ok());
+        Ok(())
+    }
+}
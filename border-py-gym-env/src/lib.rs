@@ -41,13 +41,41 @@
 //! * Discrete actions (e.g., CartPole)
 //! * Continuous actions (e.g., Pendulum)
 //!
+//! # Pixel Observations
+//!
+//! Frames of pixel observations can be resized, converted to grayscale and normalized
+//! on the Rust side with [`FrameConfig`], set with [`GymEnvConfig::frame()`], before they are
+//! given to the converter. It makes preprocessing consistent with `border-atari-env`
+//! without Python wrapper scripts.
+//!
+//! # Spaces
+//!
+//! Observation and action spaces of the environment are parsed into [`SpaceInfo`], which is
+//! returned by [`GymEnv::observation_space()`] and [`GymEnv::action_space()`]. The dimensions
+//! of models can be derived from the spaces with [`GymEnvConfig::spaces()`] and
+//! [`SpaceInfo::flat_dim()`], instead of hardcoding them. Actions sampled from the action space
+//! with [`SpaceInfo::probe_acts()`] are converted with [`GymEnvConverter::probe_acts()`] to
+//! probe the environment before training.
+//!
+//! # Vectorized Environments
+//!
+//! [`PyVecGymEnv`] runs multiple environments sequentially or in subprocesses,
+//! returning batched observations. It requires converters supporting batched observations
+//! and actions, i.e., [`ndarray::NdarrayConverter`] and [`candle::tensor::TensorConverter`].
+//!
 //! [`Policy`]: border_core::Policy
 //! [`ArrayD`]: https://docs.rs/ndarray/0.15.1/ndarray/type.ArrayD.html
 mod base;
 #[cfg(feature = "candle")]
 pub mod candle;
+mod frame;
 pub mod ndarray;
+mod space;
 #[cfg(feature = "tch")]
 pub mod tch;
 pub mod util;
-pub use base::{GymEnv, GymEnvConfig, GymEnvConverter, GymInfo};
+mod vec;
+pub use base::{ActBounds, GymEnv, GymEnvConfig, GymEnvConverter, GymInfo};
+pub use frame::FrameConfig;
+pub use space::{ProbeAct, SpaceInfo};
+pub use vec::{PyVecGymEnv, PyVecGymEnvConfig};
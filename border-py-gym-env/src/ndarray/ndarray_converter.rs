@@ -3,7 +3,10 @@
 //! [`NdarrayObs`]: super::NdarrayObs
 //! [`NdarrayAct`]: super::NdarrayAct
 use super::{NdarrayAct, NdarrayObs};
-use crate::{util::pyobj_to_arrayd, GymEnvConverter};
+use crate::{
+    util::{pyobj_to_arrayd, pyobj_to_arrayd_vec},
+    GymEnvConverter, ProbeAct,
+};
 use anyhow::Result;
 use numpy::PyArrayDyn;
 use pyo3::{IntoPy, PyObject};
@@ -73,4 +76,33 @@ impl GymEnvConverter for NdarrayConverter {
             }
         }
     }
+
+    /// Converts actions sampled from the action space to [`NdarrayAct`].
+    fn probe_acts(&self, acts: Vec<ProbeAct>) -> Vec<Self::Act> {
+        acts.into_iter().map(Into::into).collect()
+    }
+
+    /// Convert batched observations of a vectorized environment.
+    fn filt_obs_vec(&mut self, obs: PyObject) -> Result<Self::Obs> {
+        Ok(NdarrayObs(pyobj_to_arrayd_vec::<f32, f32>(obs)))
+    }
+
+    /// Convert batched actions of a vectorized environment.
+    ///
+    /// Discrete actions are flattened into a vector, whose length is the number of environments.
+    fn filt_act_vec(&mut self, act: Self::Act) -> Result<PyObject> {
+        match act {
+            NdarrayAct::Discrete(arrayd) => {
+                let n = arrayd.len();
+                let arrayd = arrayd.into_shape(ndarray::IxDyn(&[n]))?;
+                self.filt_act(NdarrayAct::Discrete(arrayd))
+            }
+            act => self.filt_act(act),
+        }
+    }
+
+    /// Converts batched actions sampled from the action space to [`NdarrayAct`].
+    fn probe_acts_vec(&self, acts: Vec<ProbeAct>) -> Vec<Self::Act> {
+        self.probe_acts(acts)
+    }
 }
@@ -0,0 +1,331 @@
+//! Spaces of observations and actions of gym environments.
+use crate::FrameConfig;
+use anyhow::Result;
+use ndarray::{ArrayD, Axis, IxDyn};
+use pyo3::{types::PyDict, PyAny};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Space of observations or actions of a Python environment.
+///
+/// It is parsed from `observation_space` and `action_space` of a
+/// [Gymnasium](https://gymnasium.farama.org/api/spaces/) environment, so that the
+/// dimensions of the inputs and outputs of models can be taken from the environment
+/// instead of being hardcoded:
+///
+/// ```ignore
+/// let (obs_space, act_space) = env_config.spaces()?;
+/// let mlp_config = MlpConfig::new(
+///     obs_space.flat_dim() as _,
+///     vec![256, 256],
+///     act_space.flat_dim() as _,
+///     Activation::None,
+/// );
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum SpaceInfo {
+    /// `Box` space, with the flattened lower and upper bounds.
+    Box {
+        shape: Vec<usize>,
+        low: Vec<f32>,
+        high: Vec<f32>,
+    },
+
+    /// `Discrete` space of `n` values starting from `start`.
+    Discrete { n: usize, start: i64 },
+
+    /// `MultiDiscrete` space with the number of values of each element.
+    MultiDiscrete { nvec: Vec<usize> },
+
+    /// `Dict` space, with its subspaces in the order of the keys.
+    Dict(Vec<(String, SpaceInfo)>),
+}
+
+/// Action sampled from a [`SpaceInfo`] to probe an environment.
+///
+/// See [`SpaceInfo::probe_acts()`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum ProbeAct {
+    /// Action of a `Box` space.
+    Continuous(ArrayD<f32>),
+
+    /// Action of a `Discrete` or `MultiDiscrete` space.
+    Discrete(ArrayD<i64>),
+}
+
+impl ProbeAct {
+    /// Repeats the action along a new first axis, the index of `n` environments.
+    pub(crate) fn repeat(&self, n: usize) -> Self {
+        fn repeat<T: Clone>(a: &ArrayD<T>, n: usize) -> ArrayD<T> {
+            let mut shape = vec![n];
+            shape.extend_from_slice(a.shape());
+            a.view()
+                .insert_axis(Axis(0))
+                .broadcast(shape)
+                .unwrap()
+                .to_owned()
+        }
+
+        match self {
+            Self::Continuous(a) => Self::Continuous(repeat(a, n)),
+            Self::Discrete(a) => Self::Discrete(repeat(a, n)),
+        }
+    }
+}
+
+impl SpaceInfo {
+    /// Parses a space of Gymnasium.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for spaces other than `Box`, `Discrete`, `MultiDiscrete` and `Dict`.
+    pub fn from_py(space: &PyAny) -> Result<Self> {
+        let to_vec = |name: &str| -> Result<Vec<f32>> {
+            let v = space
+                .getattr(name)?
+                .call_method1("astype", ("float32",))?
+                .call_method0("ravel")?;
+            Ok(v.call_method0("tolist")?.extract()?)
+        };
+        let name: String = space.getattr("__class__")?.getattr("__name__")?.extract()?;
+
+        match name.as_str() {
+            "Box" => Ok(Self::Box {
+                shape: space.getattr("shape")?.extract()?,
+                low: to_vec("low")?,
+                high: to_vec("high")?,
+            }),
+            "Discrete" => Ok(Self::Discrete {
+                n: space.getattr("n")?.extract()?,
+                start: space.getattr("start")?.extract()?,
+            }),
+            "MultiDiscrete" => {
+                let nvec = space.getattr("nvec")?.call_method0("ravel")?;
+                Ok(Self::MultiDiscrete {
+                    nvec: nvec.call_method0("tolist")?.extract()?,
+                })
+            }
+            "Dict" => {
+                let spaces: &PyDict = space.getattr("spaces")?.extract()?;
+                let spaces = spaces
+                    .iter()
+                    .map(|(k, v)| Ok((k.extract()?, Self::from_py(v)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                Ok(Self::Dict(spaces))
+            }
+            _ => Err(anyhow::anyhow!("Unsupported space: {}", name)),
+        }
+    }
+
+    /// Parses a space of Gymnasium, preprocessing frames if `frame` is given.
+    ///
+    /// Parsing is best-effort: it returns `None` with a warning for unsupported spaces,
+    /// e.g., `Tuple` or `Text`, so that environments of such spaces can still be used.
+    pub(crate) fn try_from_py(space: &PyAny, frame: Option<&FrameConfig>) -> Option<Self> {
+        match Self::from_py(space) {
+            Ok(space) => Some(match frame {
+                Some(frame) => space.with_frame(frame),
+                None => space,
+            }),
+            Err(e) => {
+                log::warn!("Failed to parse the space {:?}: {}", space, e);
+                None
+            }
+        }
+    }
+
+    /// Returns the dimension of the flattened space.
+    ///
+    /// As `gymnasium.spaces.flatdim()`, it is the number of elements for `Box`,
+    /// the number of values for `Discrete`, i.e., the dimension of one-hot vectors or
+    /// action values, the sum of the numbers of values for `MultiDiscrete`, and the sum of
+    /// the dimensions of subspaces for `Dict`.
+    pub fn flat_dim(&self) -> usize {
+        match self {
+            Self::Box { shape, .. } => shape.iter().product(),
+            Self::Discrete { n, .. } => *n,
+            Self::MultiDiscrete { nvec } => nvec.iter().sum(),
+            Self::Dict(spaces) => spaces.iter().map(|(_, s)| s.flat_dim()).sum(),
+        }
+    }
+
+    /// Returns actions covering the boundaries of the space, followed by `n_random` random actions.
+    ///
+    /// The boundaries are the lower and upper bounds of `Box` spaces, where infinite bounds are
+    /// replaced with -1 and 1, every value of `Discrete` spaces and the smallest and largest
+    /// values of `MultiDiscrete` spaces. Random actions are sampled with a generator of a fixed
+    /// seed. Actions have the shape of the space, and `Dict` spaces return no action.
+    pub fn probe_acts(&self, n_random: usize) -> Vec<ProbeAct> {
+        let mut rng = SmallRng::seed_from_u64(0);
+
+        match self {
+            Self::Box { shape, low, high } => {
+                let bounds = low
+                    .iter()
+                    .zip(high.iter())
+                    .map(|(lo, hi)| match (lo.is_finite(), hi.is_finite()) {
+                        (true, true) => (*lo, *hi),
+                        (true, false) => (*lo, lo.max(1.0)),
+                        (false, true) => (hi.min(-1.0), *hi),
+                        (false, false) => (-1.0, 1.0),
+                    })
+                    .collect::<Vec<_>>();
+                let act = |v: Vec<f32>| {
+                    ProbeAct::Continuous(ArrayD::from_shape_vec(IxDyn(shape), v).unwrap())
+                };
+                let mut acts = vec![
+                    act(bounds.iter().map(|(lo, _)| *lo).collect()),
+                    act(bounds.iter().map(|(_, hi)| *hi).collect()),
+                ];
+                for _ in 0..n_random {
+                    let v = bounds.iter().map(|(lo, hi)| rng.gen_range(*lo..=*hi));
+                    acts.push(act(v.collect()));
+                }
+                acts
+            }
+            Self::Discrete { n, start } => {
+                let n = *n as i64;
+                (0..n)
+                    .chain((0..n_random).map(|_| rng.gen_range(0..n)))
+                    .map(|v| ProbeAct::Discrete(ArrayD::from_elem(IxDyn(&[]), start + v)))
+                    .collect()
+            }
+            Self::MultiDiscrete { nvec } => {
+                let act = |v: Vec<i64>| {
+                    ProbeAct::Discrete(ArrayD::from_shape_vec(IxDyn(&[v.len()]), v).unwrap())
+                };
+                let mut acts = vec![
+                    act(vec![0; nvec.len()]),
+                    act(nvec.iter().map(|n| *n as i64 - 1).collect()),
+                ];
+                for _ in 0..n_random {
+                    let v = nvec.iter().map(|n| rng.gen_range(0..*n as i64));
+                    acts.push(act(v.collect()));
+                }
+                acts
+            }
+            Self::Dict(_) => vec![],
+        }
+    }
+
+    /// Returns the subspace of a `Dict` space with the given key.
+    pub fn get(&self, key: &str) -> Option<&SpaceInfo> {
+        match self {
+            Self::Dict(spaces) => spaces.iter().find(|(k, _)| k == key).map(|(_, s)| s),
+            _ => None,
+        }
+    }
+
+    /// Returns the space of frames preprocessed with [`FrameConfig`].
+    ///
+    /// `Box` spaces of frames of shape `(height, width[, channels])` are changed,
+    /// and other spaces are returned as they are.
+    pub(crate) fn with_frame(self, frame: &FrameConfig) -> Self {
+        let shape = match &self {
+            Self::Box { shape, .. } if shape.len() == 2 || shape.len() == 3 => shape,
+            _ => return self,
+        };
+        let (h, w) = frame.resize.unwrap_or((shape[0], shape[1]));
+        let c = match frame.grayscale {
+            true => 1,
+            false => shape.get(2).copied().unwrap_or(1),
+        };
+        let shape = match frame.channel_first {
+            true => vec![c, h, w],
+            false => vec![h, w, c],
+        };
+        let n = shape.iter().product();
+        let high = if frame.normalize { 1.0 } else { 255.0 };
+
+        Self::Box {
+            shape,
+            low: vec![0.0; n],
+            high: vec![high; n],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_info() {
+        let space = SpaceInfo::Dict(vec![
+            (
+                "observation".to_string(),
+                SpaceInfo::Box {
+                    shape: vec![2, 5],
+                    low: vec![-1.0; 10],
+                    high: vec![1.0; 10],
+                },
+            ),
+            (
+                "goal".to_string(),
+                SpaceInfo::MultiDiscrete { nvec: vec![3, 4] },
+            ),
+        ]);
+        assert_eq!(space.flat_dim(), 17);
+        assert_eq!(space.get("goal").unwrap().flat_dim(), 7);
+        assert!(space.get("achieved_goal").is_none());
+
+        let frame = FrameConfig::default()
+            .resize(84, 84)
+            .grayscale(true)
+            .channel_first(true);
+        let space = SpaceInfo::Box {
+            shape: vec![96, 96, 3],
+            low: vec![0.0; 96 * 96 * 3],
+            high: vec![255.0; 96 * 96 * 3],
+        };
+        match space.with_frame(&frame) {
+            SpaceInfo::Box { shape, high, .. } => {
+                assert_eq!(shape, vec![1, 84, 84]);
+                assert_eq!(high.len(), 84 * 84);
+            }
+            _ => panic!("Expected a Box space"),
+        }
+    }
+
+    #[test]
+    fn test_probe_acts() {
+        let space = SpaceInfo::Discrete { n: 3, start: 1 };
+        let acts = space.probe_acts(4);
+        assert_eq!(acts.len(), 7);
+        for (i, act) in acts.iter().enumerate() {
+            match act {
+                ProbeAct::Discrete(a) => {
+                    assert_eq!(a.ndim(), 0);
+                    let v = a[[]];
+                    assert!((1..4).contains(&v));
+                    if i < 3 {
+                        assert_eq!(v, i as i64 + 1);
+                    }
+                }
+                _ => panic!("Expected a discrete action"),
+            }
+        }
+
+        let space = SpaceInfo::Box {
+            shape: vec![2],
+            low: vec![-2.0, f32::NEG_INFINITY],
+            high: vec![2.0, f32::INFINITY],
+        };
+        let acts = space.probe_acts(3);
+        assert_eq!(acts.len(), 5);
+        assert_eq!(
+            acts[0],
+            ProbeAct::Continuous(ArrayD::from_shape_vec(IxDyn(&[2]), vec![-2.0, -1.0]).unwrap())
+        );
+        match acts[4].repeat(4) {
+            ProbeAct::Continuous(a) => {
+                assert_eq!(a.shape(), &[4, 2]);
+                assert!(a.iter().all(|v| (-2.0..=2.0).contains(v)));
+            }
+            _ => panic!("Expected a continuous action"),
+        }
+
+        let space = SpaceInfo::Dict(vec![("goal".to_string(), space)]);
+        assert!(space.probe_acts(3).is_empty());
+    }
+}
@@ -2,6 +2,7 @@ mod ndarray_act;
 mod ndarray_converter;
 mod ndarray_obs;
 mod tensor_batch;
+mod tensor_data;
 pub use ndarray_act::NdarrayAct;
 pub use ndarray_converter::{NdarrayConverter, NdarrayConverterConfig};
 pub use ndarray_obs::NdarrayObs;
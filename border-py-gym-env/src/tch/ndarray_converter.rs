@@ -3,7 +3,7 @@
 //! [`NdarrayObs`]: super::NdarrayObs
 //! [`NdarrayAct`]: super::NdarrayAct
 use super::{NdarrayAct, NdarrayObs};
-use crate::{util::pyobj_to_arrayd, GymEnvConverter};
+use crate::{util::pyobj_to_arrayd, GymEnvConverter, ProbeAct};
 use anyhow::Result;
 use numpy::PyArrayDyn;
 use pyo3::{IntoPy, PyObject};
@@ -73,4 +73,9 @@ impl GymEnvConverter for NdarrayConverter {
             }
         }
     }
+
+    /// Converts actions sampled from the action space to [`NdarrayAct`].
+    fn probe_acts(&self, acts: Vec<ProbeAct>) -> Vec<Self::Act> {
+        acts.into_iter().map(Into::into).collect()
+    }
 }
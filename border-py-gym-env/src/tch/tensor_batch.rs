@@ -1,4 +1,6 @@
+use super::tensor_data::TensorData;
 use border_core::generic_replay_buffer::BatchBase;
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
 use tch::{Device, Tensor};
 
 /// Adds capability of constructing [`Tensor`] with a static method.
@@ -123,6 +125,39 @@ impl BatchBase for TensorBatch {
             capacity: ixs.len() as i64,
         }
     }
+
+    fn size_in_bytes(&self) -> Option<usize> {
+        self.buf
+            .as_ref()
+            .map(|t| t.numel() * t.kind().elt_size_in_bytes())
+    }
+}
+
+/// Serializes the shape, the kind and the values of the buffer.
+///
+/// It is used to send samples over the network, e.g., in the networked mode of
+/// `border-async-trainer`.
+impl Serialize for TensorBatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let buf = self
+            .buf
+            .as_ref()
+            .map(TensorData::from_tensor)
+            .transpose()
+            .map_err(S::Error::custom)?;
+        (self.capacity, buf).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TensorBatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (capacity, buf) = <(i64, Option<(Vec<i64>, TensorData)>)>::deserialize(deserializer)?;
+        let buf = buf
+            .map(|(shape, data)| data.into_tensor(&shape))
+            .transpose()
+            .map_err(D::Error::custom)?;
+        Ok(Self { buf, capacity })
+    }
 }
 
 impl From<TensorBatch> for Tensor {
@@ -1,9 +1,15 @@
 //! Utility functions mainly for data conversion between Python and Rust.
+use border_core::{sample_standard_normal, ActValues};
 use ndarray::{concatenate, ArrayD, Axis};
 use num_traits::cast::AsPrimitive;
 use numpy::{Element, PyArrayDyn};
-use pyo3::{IntoPy, PyObject};
+use pyo3::{
+    types::{PyDict, PyList},
+    IntoPy, PyObject, PyResult, Python, ToPyObject,
+};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 /// Action type.
@@ -30,6 +36,35 @@ where
     })
 }
 
+/// Converts PyObject of batched observations of a vectorized environment to ArrayD.
+///
+/// Unlike [`pyobj_to_arrayd`], the sample dimension is not inserted, as the first axis
+/// is already the index of environments.
+pub fn pyobj_to_arrayd_vec<T1, T2>(obs: PyObject) -> ArrayD<T2>
+where
+    T1: Element + AsPrimitive<T2>,
+    T2: 'static + Copy,
+{
+    pyo3::Python::with_gil(|py| {
+        let obs: &PyArrayDyn<T1> = obs.extract(py).unwrap();
+        obs.to_owned_array().mapv(|elem| elem.as_())
+    })
+}
+
+/// Returns [`ActValues`] of continuous actions.
+///
+/// The first axis of actions with two or more dimensions is the index of environments.
+pub fn continuous_act_values(act: &ArrayD<f32>) -> ActValues {
+    let acts = match act.ndim() {
+        0 | 1 => vec![act.iter().copied().collect()],
+        _ => act
+            .outer_iter()
+            .map(|a| a.iter().copied().collect())
+            .collect(),
+    };
+    ActValues::Continuous(acts)
+}
+
 /// Converts [`ArrayD<f32>`] to [`PyObject`].
 ///
 /// This function does not support batch action.
@@ -41,6 +76,37 @@ pub fn arrayd_to_pyobj(act: ArrayD<f32>) -> PyObject {
     })
 }
 
+/// Converts a JSON value to [`PyObject`].
+///
+/// Integers are converted to `int`, other numbers to `float`, arrays to `list`
+/// and objects to `dict`.
+pub fn json_to_pyobj(py: Python, value: &Value) -> PyResult<PyObject> {
+    let obj = match value {
+        Value::Null => py.None(),
+        Value::Bool(v) => v.to_object(py),
+        Value::Number(v) => match v.as_i64() {
+            Some(v) => v.to_object(py),
+            None => v.as_f64().unwrap().to_object(py),
+        },
+        Value::String(v) => v.to_object(py),
+        Value::Array(vs) => {
+            let vs = vs
+                .iter()
+                .map(|v| json_to_pyobj(py, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, vs).to_object(py)
+        }
+        Value::Object(kvs) => {
+            let dict = PyDict::new(py);
+            for (k, v) in kvs.iter() {
+                dict.set_item(k, json_to_pyobj(py, v)?)?;
+            }
+            dict.to_object(py)
+        }
+    };
+    Ok(obj)
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum ArrayType {
     F32Array,
@@ -120,3 +186,14 @@ impl Array {
         }
     }
 }
+
+/// Adds zero-mean Gaussian noise of standard deviation `std` to an array.
+pub fn add_gaussian_noise(x: &ArrayD<f32>, std: f32, rng: &mut dyn RngCore) -> ArrayD<f32> {
+    x.mapv(|v| v + std * sample_standard_normal(rng))
+}
+
+/// Concatenates arrays along `axis`.
+pub fn concat_arrayd(xs: &[&ArrayD<f32>], axis: usize) -> ArrayD<f32> {
+    let views = xs.iter().map(|x| x.view()).collect::<Vec<_>>();
+    concatenate(Axis(axis), &views).unwrap()
+}
@@ -0,0 +1,346 @@
+//! Vectorized wrapper of gym environments implemented in Python.
+use crate::{
+    util::json_to_pyobj, ActBounds, FrameConfig, GymEnvConfig, GymEnvConverter, GymInfo, SpaceInfo,
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue::Scalar},
+    Env, Step,
+};
+use log::{info, trace, warn};
+use pyo3::types::{PyDict, PyTuple};
+use pyo3::{PyObject, Python, ToPyObject};
+use serde::{Deserialize, Serialize};
+
+/// Configuration of [`PyVecGymEnv`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(bound = "")]
+pub struct PyVecGymEnvConfig<C>
+where
+    C: GymEnvConverter,
+{
+    /// Configuration of each environment.
+    ///
+    /// `pybullet`, `render_mode`, `wait` and `act_bounds` are not supported.
+    pub env_config: GymEnvConfig<C>,
+
+    /// The number of environments.
+    pub n_envs: usize,
+
+    /// `true` to run each environment in a subprocess,
+    /// `false` to run the environments sequentially in the main process.
+    pub asynchronous: bool,
+}
+
+impl<C> PyVecGymEnvConfig<C>
+where
+    C: GymEnvConverter,
+{
+    /// Constructs the configuration of `n_envs` environments.
+    pub fn new(env_config: GymEnvConfig<C>, n_envs: usize) -> Self {
+        Self {
+            env_config,
+            n_envs,
+            asynchronous: false,
+        }
+    }
+
+    /// Set `true` to run each environment in a subprocess.
+    pub fn asynchronous(mut self, v: bool) -> Self {
+        self.asynchronous = v;
+        self
+    }
+}
+
+/// A vectorized wrapper of [Gymnasium](https://gymnasium.farama.org).
+///
+/// Environments are run sequentially in the main process, or in subprocesses when
+/// [`PyVecGymEnvConfig::asynchronous`] is `true`. They are created with `make_f32_vec()`
+/// in `f32_wrapper.py`, whose vectorized environments do not reset environments automatically.
+///
+/// Observations, rewards and flags of [`Step`] have an element for each environment.
+/// Observations and actions are converted with [`GymEnvConverter::filt_obs_vec()`] and
+/// [`GymEnvConverter::filt_act_vec()`], where the first axis is the index of environments.
+/// [`Env::reset()`] with `is_done` resets the environments where the episode has ended,
+/// and [`Env::step_with_reset()`] does it automatically, so that [`Trainer`] and
+/// [`DefaultEvaluator`] can run the environments.
+///
+/// [`Trainer`]: border_core::Trainer
+/// [`DefaultEvaluator`]: border_core::DefaultEvaluator
+#[derive(Debug)]
+pub struct PyVecGymEnv<C>
+where
+    C: GymEnvConverter,
+{
+    env: PyObject,
+    n_envs: usize,
+    converter: C,
+    max_steps: Option<usize>,
+    /// The number of steps in the current episode of each environment.
+    count_steps: Vec<usize>,
+    /// Initial seed.
+    ///
+    /// This value will be used at the next call of the reset method.
+    /// The seed of the `i`-th environment is `initial_seed + i`.
+    initial_seed: Option<i64>,
+    /// `true` if the Python environment has been closed.
+    closed: bool,
+    /// Preprocessing of pixel observations.
+    frame: Option<FrameConfig>,
+    /// Observation space of each environment, after the preprocessing of pixel observations,
+    /// if supported.
+    observation_space: Option<SpaceInfo>,
+    /// Action space of each environment, if supported.
+    action_space: Option<SpaceInfo>,
+}
+
+impl<C> PyVecGymEnv<C>
+where
+    C: GymEnvConverter,
+{
+    /// Returns the observation space of each environment, without the axis of environments.
+    ///
+    /// It is `None` if the space is not supported by [`SpaceInfo`].
+    pub fn observation_space(&self) -> Option<&SpaceInfo> {
+        self.observation_space.as_ref()
+    }
+
+    /// Returns the action space of each environment, without the axis of environments.
+    ///
+    /// It is `None` if the space is not supported by [`SpaceInfo`].
+    pub fn action_space(&self) -> Option<&SpaceInfo> {
+        self.action_space.as_ref()
+    }
+
+    /// Converts batched observations, preprocessing pixel observations if configured.
+    fn filt_obs_vec(&mut self, obs: PyObject) -> Result<C::Obs> {
+        let obs = match &self.frame {
+            Some(frame) => frame.filt(obs, true)?,
+            None => obs,
+        };
+        self.converter.filt_obs_vec(obs)
+    }
+
+    /// Resets the environments where `mask[i]` is `true`, or all environments if `mask` is `None`.
+    fn reset_envs(&mut self, mask: Option<Vec<bool>>) -> Result<C::Obs> {
+        Python::with_gil(|py| {
+            let kwargs = PyDict::new(py);
+            if let Some(seed) = self.initial_seed.take() {
+                kwargs.set_item("seed", seed)?;
+            }
+            if let Some(mask) = mask {
+                kwargs.set_item("mask", mask)?;
+            }
+            let ret_values = self.env.call_method(py, "reset", (), Some(kwargs))?;
+            let ret_values: &PyTuple = ret_values.extract(py)?;
+            let obs = ret_values.get_item(0).to_object(py);
+            self.filt_obs_vec(obs)
+        })
+    }
+
+    /// Calls `close()` of the Python environment if it has not been closed.
+    fn close_py_env(&mut self) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+        Python::with_gil(|py| {
+            self.env.call_method0(py, "close")?;
+            Ok(())
+        })
+    }
+}
+
+impl<C> Env for PyVecGymEnv<C>
+where
+    C: GymEnvConverter + Clone,
+{
+    type Obs = <C as GymEnvConverter>::Obs;
+    type Act = <C as GymEnvConverter>::Act;
+    type Info = GymInfo;
+    type Config = PyVecGymEnvConfig<C>;
+
+    /// Resets the environments where `is_done[i] == 1`, or all environments if `is_done` is `None`.
+    ///
+    /// It returns the observations of all environments.
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        trace!("PyVecGymEnv::reset()");
+
+        let mask = is_done.map(|is_done| is_done.iter().map(|d| *d == 1).collect::<Vec<_>>());
+        for (i, count) in self.count_steps.iter_mut().enumerate() {
+            if mask.as_ref().map_or(true, |mask| mask[i]) {
+                *count = 0;
+            }
+        }
+
+        self.reset_envs(mask)
+    }
+
+    /// Resets all environments with the given index.
+    ///
+    /// Specifically, `env.reset(seed=ix + i)` is called for the `i`-th environment.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.initial_seed = Some(ix as _);
+        self.reset(None)
+    }
+
+    /// Returns actions sampled with [`SpaceInfo::probe_acts()`], where all environments are
+    /// given the same action, converted with [`GymEnvConverter::probe_acts_vec()`].
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        let acts = match &self.action_space {
+            Some(space) => space.probe_acts(n_random),
+            None => return vec![],
+        };
+        let acts = acts.iter().map(|a| a.repeat(self.n_envs)).collect();
+        self.converter.probe_acts_vec(acts)
+    }
+
+    /// Runs a step of the environments' dynamics.
+    ///
+    /// Environments where the episode has ended are not reset in this method.
+    fn step(&mut self, act: &Self::Act) -> (Step<Self>, Record) {
+        trace!("PyVecGymEnv::step()");
+
+        Python::with_gil(|py| {
+            // Run a step
+            let step_py = {
+                let a_py = self.converter.filt_act_vec(act.clone()).unwrap();
+                self.env.call_method1(py, "step", (a_py,)).unwrap()
+            };
+            let step: &PyTuple = step_py.extract(py).unwrap();
+
+            // Observations at the next step
+            let obs = {
+                let obs_py = step.get_item(0).to_object(py);
+                self.filt_obs_vec(obs_py).unwrap()
+            };
+
+            // Rewards and terminated/truncated flags
+            let reward: Vec<f32> = step.get_item(1).extract().unwrap();
+            let to_flags = |v: Vec<bool>| v.into_iter().map(|b| b as i8).collect::<Vec<_>>();
+            let is_terminated = to_flags(step.get_item(2).extract().unwrap());
+            let mut is_truncated = to_flags(step.get_item(3).extract().unwrap());
+
+            // Episode lengths of the environments where the episode has ended
+            let mut lengths = vec![];
+            for i in 0..self.n_envs {
+                self.count_steps[i] += 1;
+                if let Some(max_steps) = self.max_steps {
+                    if self.count_steps[i] >= max_steps {
+                        is_truncated[i] = 1;
+                    }
+                }
+                if (is_terminated[i] | is_truncated[i]) == 1 {
+                    lengths.push(self.count_steps[i] as f32);
+                }
+            }
+
+            let mut record = Record::empty();
+            if !lengths.is_empty() {
+                let length = lengths.iter().sum::<f32>() / lengths.len() as f32;
+                record.insert("episode_length", Scalar(length));
+            }
+
+            let step = Step::new(
+                obs,
+                act.clone(),
+                reward,
+                is_terminated,
+                is_truncated,
+                GymInfo {},
+                None,
+            );
+
+            (step, record)
+        })
+    }
+
+    /// Runs a step and resets the environments where the episode has ended.
+    ///
+    /// If any episode has ended, `init_obs` of the returned [`Step`] holds the observations
+    /// of all environments after the reset.
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (mut step, record) = self.step(a);
+        if step.is_done() {
+            let init_obs = self.reset(Some(&step.is_done_vec())).unwrap();
+            step.init_obs = Some(init_obs);
+        }
+
+        (step, record)
+    }
+
+    fn n_envs(&self) -> usize {
+        self.n_envs
+    }
+
+    /// Closes the Python environment, releasing the subprocesses of asynchronous environments.
+    ///
+    /// It is also called when the environment is dropped.
+    fn close(&mut self) -> Result<()> {
+        self.close_py_env()
+    }
+
+    /// Creates [`PyVecGymEnv`].
+    ///
+    /// * `seed` - The seed value of the random number generator.
+    ///   This value will be used at the first call of the reset method.
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let env_config = &config.env_config;
+        if env_config.pybullet
+            || env_config.render_mode.is_some()
+            || !env_config.wait.is_zero()
+            || env_config.act_bounds != ActBounds::Ignore
+        {
+            warn!("pybullet, render_mode, wait and act_bounds are ignored in PyVecGymEnv");
+        }
+
+        let (env, observation_space, action_space) = Python::with_gil(|py| {
+            let env_kwargs = PyDict::new(py);
+            for (k, v) in env_config.env_kwargs.iter() {
+                env_kwargs.set_item(k, json_to_pyobj(py, v)?)?;
+            }
+            let gym = py.import("f32_wrapper")?;
+            let env = gym.getattr("make_f32_vec")?.call(
+                (env_config.name.as_str(), config.n_envs, config.asynchronous),
+                Some(env_kwargs),
+            )?;
+            info!("Initialize PyVecGymEnv with {} environments", config.n_envs);
+            info!("Action space = {:?}", env.getattr("action_space")?);
+            info!(
+                "Observation space = {:?}",
+                env.getattr("observation_space")?
+            );
+            let action_space = SpaceInfo::try_from_py(env.getattr("action_space")?, None);
+            let observation_space = SpaceInfo::try_from_py(
+                env.getattr("observation_space")?,
+                env_config.frame.as_ref(),
+            );
+            let env: PyObject = env.into();
+            Ok::<_, anyhow::Error>((env, observation_space, action_space))
+        })?;
+
+        Ok(Self {
+            env,
+            n_envs: config.n_envs,
+            converter: C::new(&env_config.converter_config)?,
+            max_steps: env_config.max_steps,
+            count_steps: vec![0; config.n_envs],
+            initial_seed: Some(seed),
+            closed: false,
+            frame: env_config.frame.clone(),
+            observation_space,
+            action_space,
+        })
+    }
+}
+
+impl<C> Drop for PyVecGymEnv<C>
+where
+    C: GymEnvConverter,
+{
+    fn drop(&mut self) {
+        if let Err(e) = self.close_py_env() {
+            warn!("Failed to close the environment: {}", e);
+        }
+    }
+}
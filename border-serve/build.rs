@@ -0,0 +1,37 @@
+//! Generates the gRPC services defined in `proto/border.proto`.
+//!
+//! The services are generated without `protoc`, using the message types in `src/proto.rs`.
+use tonic_build::manual::{Builder, Method, Service};
+
+fn method(name: &str, route_name: &str, input_type: &str, output_type: &str) -> Method {
+    Method::builder()
+        .name(name)
+        .route_name(route_name)
+        .input_type(format!("crate::proto::{}", input_type))
+        .output_type(format!("crate::proto::{}", output_type))
+        .codec_path("tonic::codec::ProstCodec")
+        .build()
+}
+
+fn main() {
+    let policy = Service::builder()
+        .name("Policy")
+        .package("border")
+        .method(method("act", "Act", "ActRequest", "ActResponse"))
+        .method(method("info", "Info", "InfoRequest", "InfoResponse"))
+        .build();
+    let env = Service::builder()
+        .name("Env")
+        .package("border")
+        .method(method("reset", "Reset", "ResetRequest", "Observation"))
+        .method(method("step", "Step", "StepRequest", "StepResponse"))
+        .method(method(
+            "render",
+            "Render",
+            "RenderRequest",
+            "RenderResponse",
+        ))
+        .build();
+    Builder::new().compile(&[policy, env]);
+    println!("cargo:rerun-if-changed=build.rs");
+}
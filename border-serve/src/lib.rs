@@ -0,0 +1,38 @@
+//! gRPC services of policies and environments for Border.
+//!
+//! [`PolicyService`] serves a policy loaded with [`PolicyRunner`] of `border-inference`,
+//! so that external simulators, e.g., Unity or custom C++ simulators, can query actions
+//! of trained agents. The service `border.Policy` is defined in `proto/border.proto`,
+//! from which clients in other languages can be generated. In Rust, [`PolicyClient`]
+//! can be used.
+//!
+//! Requests received concurrently are batched and processed at once by the policy,
+//! which can run on GPU depending on the device with which the policy is loaded.
+//! The backend of `border-inference` is selected with the features `candle` and `tch`.
+//! The feature `cuda` enables GPU inference with `candle`.
+//!
+//! ```no_run
+//! use border_inference::PolicyRunner;
+//! use border_serve::{PolicyService, PolicyServiceConfig};
+//!
+//! // `runner` is loaded, e.g., with `PolicyRunner::load_candle_dqn()`
+//! async fn serve(runner: PolicyRunner) -> anyhow::Result<()> {
+//!     let config = PolicyServiceConfig::default().max_batch_size(64);
+//!     PolicyService::new(runner, config)
+//!         .serve("0.0.0.0:50051".parse()?)
+//!         .await
+//! }
+//! ```
+//!
+//! Conversely, [`RemoteEnv`] implements [`Env`] for an environment running in another
+//! process or machine, e.g., a simulator serving the service `border.Env` defined in
+//! `proto/border.proto`, with which agents can be trained without PyO3.
+//!
+//! [`PolicyRunner`]: border_inference::PolicyRunner
+//! [`Env`]: border_core::Env
+pub mod proto;
+mod remote_env;
+mod service;
+pub use proto::policy_client::PolicyClient;
+pub use remote_env::{RemoteEnv, RemoteEnvConfig, RemoteInfo};
+pub use service::{PolicyService, PolicyServiceConfig};
@@ -0,0 +1,131 @@
+//! Messages and the gRPC services of policies and environments, defined in `proto/border.proto`.
+#![allow(missing_docs)]
+use std::collections::HashMap;
+
+/// Request of [`Act`](policy_server::Policy::act).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActRequest {
+    /// Observation flattened in row-major order.
+    #[prost(float, repeated, tag = "1")]
+    pub obs: Vec<f32>,
+}
+
+/// Response of [`Act`](policy_server::Policy::act).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ActResponse {
+    /// Output of the policy, e.g., action values or a continuous action.
+    #[prost(float, repeated, tag = "1")]
+    pub act: Vec<f32>,
+
+    /// Index of the maximum output, i.e., a discrete action.
+    #[prost(uint32, tag = "2")]
+    pub act_discrete: u32,
+}
+
+/// Request of [`Info`](policy_server::Policy::info).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InfoRequest {}
+
+/// Response of [`Info`](policy_server::Policy::info).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct InfoResponse {
+    /// Shape of an observation, empty if unknown.
+    #[prost(int64, repeated, tag = "1")]
+    pub obs_shape: Vec<i64>,
+
+    /// Dimension of the output of the policy.
+    #[prost(int64, tag = "2")]
+    pub act_dim: i64,
+
+    /// Type of the model, e.g., the name of the agent.
+    #[prost(string, tag = "3")]
+    pub model_type: String,
+
+    /// Backend with which the model is trained.
+    #[prost(string, tag = "4")]
+    pub backend: String,
+}
+
+/// Request of [`Reset`](env_server::Env::reset).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct ResetRequest {
+    /// Random seed, given at the first reset after the client is built.
+    #[prost(int64, optional, tag = "1")]
+    pub seed: Option<i64>,
+
+    /// Index of the episode for evaluation, e.g., used as a seed.
+    #[prost(uint64, optional, tag = "2")]
+    pub index: Option<u64>,
+}
+
+/// Observation of a remote environment.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Observation {
+    /// Observation flattened in row-major order.
+    #[prost(float, repeated, tag = "1")]
+    pub data: Vec<f32>,
+
+    /// Shape of the observation.
+    #[prost(int64, repeated, tag = "2")]
+    pub shape: Vec<i64>,
+}
+
+/// Action sent to a remote environment.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Action {
+    /// Continuous action.
+    #[prost(float, repeated, tag = "1")]
+    pub continuous: Vec<f32>,
+
+    /// Discrete action.
+    #[prost(int64, repeated, tag = "2")]
+    pub discrete: Vec<i64>,
+}
+
+/// Request of [`Step`](env_server::Env::step).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StepRequest {
+    #[prost(message, optional, tag = "1")]
+    pub act: Option<Action>,
+}
+
+/// Response of [`Step`](env_server::Env::step).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct StepResponse {
+    #[prost(message, optional, tag = "1")]
+    pub obs: Option<Observation>,
+
+    #[prost(float, tag = "2")]
+    pub reward: f32,
+
+    #[prost(bool, tag = "3")]
+    pub is_terminated: bool,
+
+    #[prost(bool, tag = "4")]
+    pub is_truncated: bool,
+
+    /// Additional information, e.g., `is_success`.
+    #[prost(map = "string, float", tag = "5")]
+    pub info: HashMap<String, f32>,
+}
+
+/// Request of [`Render`](env_server::Env::render).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RenderRequest {}
+
+/// Response of [`Render`](env_server::Env::render).
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RenderResponse {
+    /// RGB image of `u8` in the shape of `[height, width, 3]`.
+    #[prost(bytes = "vec", tag = "1")]
+    pub image: Vec<u8>,
+
+    #[prost(uint32, tag = "2")]
+    pub height: u32,
+
+    #[prost(uint32, tag = "3")]
+    pub width: u32,
+}
+
+include!(concat!(env!("OUT_DIR"), "/border.Policy.rs"));
+include!(concat!(env!("OUT_DIR"), "/border.Env.rs"));
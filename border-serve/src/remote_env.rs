@@ -0,0 +1,190 @@
+use crate::proto::{
+    env_client::EnvClient, Action, Observation, RenderRequest, RenderResponse, ResetRequest,
+    StepRequest,
+};
+use anyhow::{anyhow, Context, Result};
+use border_core::{record::Record, Act, Env, Info, Obs, Step};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, marker::PhantomData, time::Duration};
+use tokio::runtime::Runtime;
+use tonic::transport::{Channel, Endpoint};
+
+/// Configuration of [`RemoteEnv`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct RemoteEnvConfig {
+    /// Address of the environment server, e.g., `http://127.0.0.1:50052`.
+    pub addr: String,
+
+    /// Timeout of each request in milliseconds.
+    pub timeout_ms: Option<u64>,
+}
+
+impl Default for RemoteEnvConfig {
+    fn default() -> Self {
+        Self {
+            addr: "http://127.0.0.1:50052".to_string(),
+            timeout_ms: None,
+        }
+    }
+}
+
+impl RemoteEnvConfig {
+    /// Sets the address of the environment server.
+    pub fn addr(mut self, v: impl Into<String>) -> Self {
+        self.addr = v.into();
+        self
+    }
+
+    /// Sets the timeout of each request in milliseconds.
+    pub fn timeout_ms(mut self, v: Option<u64>) -> Self {
+        self.timeout_ms = v;
+        self
+    }
+}
+
+/// Information of a step of [`RemoteEnv`], given by the environment server.
+#[derive(Debug, Clone, Default)]
+pub struct RemoteInfo(pub HashMap<String, f32>);
+
+impl RemoteInfo {
+    /// Returns `true` if `is_success` of the information is positive.
+    pub fn is_success(&self) -> bool {
+        self.0.get("is_success").is_some_and(|v| *v > 0.0)
+    }
+}
+
+impl Info for RemoteInfo {}
+
+/// Environment running in an external process or machine, accessed over gRPC.
+///
+/// The environment server implements the service `border.Env` defined in
+/// `proto/border.proto`, i.e., `Reset`, `Step` and `Render`, so that agents can be trained
+/// with simulators written in other languages without PyO3. Observations and actions are
+/// converted from and into the messages of the protocol with `O: From<Observation>` and
+/// `A: Into<Action>`.
+///
+/// The seed given to [`Env::build()`] is sent with the first reset, and the index given to
+/// [`Env::reset_with_index()`] is sent as `index` of the reset request. Requests are
+/// blocking, which are processed with a runtime owned by the environment.
+pub struct RemoteEnv<O, A> {
+    runtime: Runtime,
+    client: EnvClient<Channel>,
+    seed: Option<i64>,
+    phantom: PhantomData<(O, A)>,
+}
+
+impl<O, A> RemoteEnv<O, A>
+where
+    O: Obs + From<Observation>,
+    A: Act + Into<Action>,
+{
+    fn reset_(&mut self, index: Option<u64>) -> Result<O> {
+        let request = ResetRequest {
+            seed: self.seed.take(),
+            index,
+        };
+        let obs = self
+            .runtime
+            .block_on(self.client.reset(request))
+            .context("Failed to reset the remote environment")?;
+        Ok(obs.into_inner().into())
+    }
+
+    /// Returns an image of the environment rendered by the server.
+    pub fn render(&mut self) -> Result<RenderResponse> {
+        let image = self
+            .runtime
+            .block_on(self.client.render(RenderRequest {}))
+            .context("Failed to render the remote environment")?;
+        Ok(image.into_inner())
+    }
+}
+
+impl<O, A> Env for RemoteEnv<O, A>
+where
+    O: Obs + From<Observation>,
+    A: Act + Into<Action>,
+{
+    type Config = RemoteEnvConfig;
+    type Obs = O;
+    type Act = A;
+    type Info = RemoteInfo;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        let mut endpoint = Endpoint::from_shared(config.addr.clone())?;
+        if let Some(timeout_ms) = config.timeout_ms {
+            endpoint = endpoint.timeout(Duration::from_millis(timeout_ms));
+        }
+        let client = runtime
+            .block_on(EnvClient::connect(endpoint))
+            .with_context(|| format!("Failed to connect to {}", config.addr))?;
+        log::info!("Connected to the remote environment at {}", config.addr);
+
+        Ok(Self {
+            runtime,
+            client,
+            seed: Some(seed),
+            phantom: PhantomData,
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.try_step(a)
+            .expect("Failed to step the remote environment")
+    }
+
+    /// Returns an error if the request fails or the response has no observation.
+    fn try_step(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        let request = StepRequest {
+            act: Some(a.clone().into()),
+        };
+        let res = self
+            .runtime
+            .block_on(self.client.step(request))
+            .context("Failed to step the remote environment")?
+            .into_inner();
+        let obs = res
+            .obs
+            .ok_or_else(|| anyhow!("No observation in the response of the remote environment"))?;
+        let step = Step::new(
+            obs.into(),
+            a.clone(),
+            vec![res.reward],
+            vec![res.is_terminated as i8],
+            vec![res.is_truncated as i8],
+            RemoteInfo(res.info),
+            None,
+        );
+
+        Ok((step, Record::empty()))
+    }
+
+    fn step_with_reset(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        self.try_step_with_reset(a)
+            .expect("Failed to step the remote environment")
+    }
+
+    /// Returns an error if the step or the reset at the end of the episode fails.
+    fn try_step_with_reset(&mut self, a: &Self::Act) -> Result<(Step<Self>, Record)> {
+        let (step, record) = self.try_step(a)?;
+        let step = match step.is_done() {
+            true => Step {
+                init_obs: Some(self.reset(None)?),
+                ..step
+            },
+            false => step,
+        };
+        Ok((step, record))
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        self.reset_(None)
+    }
+
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.reset_(Some(ix as _))
+    }
+}
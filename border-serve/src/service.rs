@@ -0,0 +1,186 @@
+use crate::proto::{
+    policy_server::{Policy, PolicyServer},
+    ActRequest, ActResponse, InfoRequest, InfoResponse,
+};
+use anyhow::Result;
+use border_inference::{argmax, PolicyRunner};
+use crossbeam_channel::{bounded, Receiver, Sender, TrySendError};
+use serde::{Deserialize, Serialize};
+use std::{
+    net::SocketAddr,
+    time::{Duration, Instant},
+};
+use tokio::sync::oneshot;
+use tonic::{transport::Server, Request, Response, Status};
+
+/// Configuration of [`PolicyService`].
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct PolicyServiceConfig {
+    /// The maximum number of observations processed at once by the policy.
+    pub max_batch_size: usize,
+
+    /// Time in microseconds to wait for other requests after the first request of a batch.
+    pub batch_timeout_us: u64,
+
+    /// The maximum number of requests waiting for a batch.
+    ///
+    /// Requests beyond this are rejected with [`Status::resource_exhausted`].
+    #[serde(default = "default_max_queue_size")]
+    pub max_queue_size: usize,
+}
+
+fn default_max_queue_size() -> usize {
+    1024
+}
+
+impl Default for PolicyServiceConfig {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 32,
+            batch_timeout_us: 1000,
+            max_queue_size: default_max_queue_size(),
+        }
+    }
+}
+
+impl PolicyServiceConfig {
+    /// Sets the maximum number of observations processed at once by the policy.
+    pub fn max_batch_size(mut self, v: usize) -> Self {
+        self.max_batch_size = v;
+        self
+    }
+
+    /// Sets time in microseconds to wait for other requests of a batch.
+    pub fn batch_timeout_us(mut self, v: u64) -> Self {
+        self.batch_timeout_us = v;
+        self
+    }
+
+    /// Sets the maximum number of requests waiting for a batch.
+    pub fn max_queue_size(mut self, v: usize) -> Self {
+        self.max_queue_size = v;
+        self
+    }
+}
+
+/// A request of an action waiting for the batch.
+struct Job {
+    obs: Vec<f32>,
+    tx: oneshot::Sender<Result<Vec<f32>, Status>>,
+}
+
+/// Computes actions of batches of requests until all senders are dropped.
+fn run_batches(mut runner: PolicyRunner, config: PolicyServiceConfig, rx: Receiver<Job>) {
+    let max_batch_size = config.max_batch_size.max(1);
+    let timeout = Duration::from_micros(config.batch_timeout_us);
+
+    while let Ok(job) = rx.recv() {
+        let mut jobs = vec![job];
+        let deadline = Instant::now() + timeout;
+        while jobs.len() < max_batch_size {
+            match rx.recv_deadline(deadline) {
+                Ok(job) => jobs.push(job),
+                Err(_) => break,
+            }
+        }
+
+        // Invalid observations are rejected before the batch is processed
+        let (jobs, invalid): (Vec<_>, Vec<_>) = jobs
+            .into_iter()
+            .partition(|job| runner.obs_dim().is_none_or(|d| d == job.obs.len()));
+        for job in invalid.into_iter() {
+            let msg = format!(
+                "Expected an observation of length {}, got {}",
+                runner.obs_dim().unwrap(),
+                job.obs.len()
+            );
+            let _ = job.tx.send(Err(Status::invalid_argument(msg)));
+        }
+        if jobs.is_empty() {
+            continue;
+        }
+
+        let (obs, txs): (Vec<_>, Vec<_>) = jobs.into_iter().map(|job| (job.obs, job.tx)).unzip();
+        log::trace!("Process a batch of {} observations", obs.len());
+        match runner.try_act_batch(&obs) {
+            Ok(acts) => {
+                for (tx, act) in txs.into_iter().zip(acts) {
+                    let _ = tx.send(Ok(act));
+                }
+            }
+            Err(e) => {
+                log::warn!("Failed to process a batch: {:?}", e);
+                for tx in txs.into_iter() {
+                    let _ = tx.send(Err(Status::internal(e.to_string())));
+                }
+            }
+        }
+    }
+}
+
+/// gRPC service of the policy of a trained agent.
+///
+/// Requests received concurrently are batched and processed at once by [`PolicyRunner`].
+/// The policy runs in a dedicated thread, where a batch is processed when it has
+/// `max_batch_size` observations or `batch_timeout_us` has passed since its first request.
+/// At most `max_queue_size` requests wait for a batch; further requests are rejected.
+/// Policies loaded on GPU, e.g., with `Device::Cuda(0)` of `border-candle-agent`,
+/// benefit from the batching.
+pub struct PolicyService {
+    info: InfoResponse,
+    tx: Sender<Job>,
+}
+
+impl PolicyService {
+    /// Creates a service running the policy in a dedicated thread.
+    ///
+    /// The thread exits when the service is dropped.
+    pub fn new(runner: PolicyRunner, config: PolicyServiceConfig) -> Self {
+        let manifest = runner.manifest();
+        let info = InfoResponse {
+            obs_shape: manifest.obs_shape.clone().unwrap_or_default(),
+            act_dim: manifest.act_dim,
+            model_type: manifest.model_type.clone(),
+            backend: manifest.backend.clone(),
+        };
+        let (tx, rx) = bounded(config.max_queue_size.max(1));
+        std::thread::spawn(move || run_batches(runner, config, rx));
+        Self { info, tx }
+    }
+
+    /// Returns the service to be added to a [`tonic`] server.
+    pub fn into_server(self) -> PolicyServer<Self> {
+        PolicyServer::new(self)
+    }
+
+    /// Serves the policy at the given address.
+    pub async fn serve(self, addr: SocketAddr) -> Result<()> {
+        log::info!("Serve the policy at {}", addr);
+        Server::builder()
+            .add_service(self.into_server())
+            .serve(addr)
+            .await?;
+        Ok(())
+    }
+}
+
+#[tonic::async_trait]
+impl Policy for PolicyService {
+    async fn act(&self, request: Request<ActRequest>) -> Result<Response<ActResponse>, Status> {
+        let (tx, rx) = oneshot::channel();
+        let obs = request.into_inner().obs;
+        self.tx.try_send(Job { obs, tx }).map_err(|e| match e {
+            TrySendError::Full(_) => Status::resource_exhausted("Too many pending requests"),
+            TrySendError::Disconnected(_) => Status::unavailable("Policy is not running"),
+        })?;
+        let act = rx
+            .await
+            .map_err(|_| Status::unavailable("Policy is not running"))??;
+        let act_discrete = argmax(&act).map_err(|e| Status::internal(e.to_string()))? as _;
+        Ok(Response::new(ActResponse { act, act_discrete }))
+    }
+
+    async fn info(&self, _request: Request<InfoRequest>) -> Result<Response<InfoResponse>, Status> {
+        Ok(Response::new(self.info.clone()))
+    }
+}
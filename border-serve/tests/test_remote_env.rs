@@ -0,0 +1,145 @@
+use anyhow::Result;
+use border_core::{Act, Env, Obs};
+use border_serve::{
+    proto::{
+        env_server::{self, EnvServer},
+        Action, Observation, RenderRequest, RenderResponse, ResetRequest, StepRequest,
+        StepResponse,
+    },
+    RemoteEnv, RemoteEnvConfig,
+};
+use std::{collections::HashMap, sync::Mutex};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{transport::Server, Request, Response, Status};
+
+/// Position on a line, moved by actions, where the episode ends at position 2.
+#[derive(Default)]
+struct LineEnv {
+    pos: Mutex<i64>,
+}
+
+fn observation(pos: i64) -> Observation {
+    Observation {
+        data: vec![pos as f32],
+        shape: vec![1],
+    }
+}
+
+#[tonic::async_trait]
+impl env_server::Env for LineEnv {
+    async fn reset(&self, request: Request<ResetRequest>) -> Result<Response<Observation>, Status> {
+        let request = request.into_inner();
+        let mut pos = self.pos.lock().unwrap();
+        *pos = request.index.map_or(0, |ix| -(ix as i64));
+        Ok(Response::new(observation(*pos)))
+    }
+
+    async fn step(&self, request: Request<StepRequest>) -> Result<Response<StepResponse>, Status> {
+        let act = request.into_inner().act.unwrap_or_default();
+        let mut pos = self.pos.lock().unwrap();
+        // A negative action is answered without an observation
+        if act.discrete[0] < 0 {
+            return Ok(Response::new(StepResponse::default()));
+        }
+        *pos += act.discrete[0] * 2 - 1;
+        let is_terminated = *pos == 2;
+        Ok(Response::new(StepResponse {
+            obs: Some(observation(*pos)),
+            reward: is_terminated as i32 as f32,
+            is_terminated,
+            is_truncated: false,
+            info: HashMap::from([("is_success".to_string(), is_terminated as i32 as f32)]),
+        }))
+    }
+
+    async fn render(
+        &self,
+        _request: Request<RenderRequest>,
+    ) -> Result<Response<RenderResponse>, Status> {
+        Ok(Response::new(RenderResponse {
+            image: vec![0; 2 * 3 * 3],
+            height: 2,
+            width: 3,
+        }))
+    }
+}
+
+#[derive(Clone, Debug)]
+struct LineObs(f32);
+
+impl Obs for LineObs {
+    fn len(&self) -> usize {
+        1
+    }
+}
+
+impl From<Observation> for LineObs {
+    fn from(obs: Observation) -> Self {
+        Self(obs.data[0])
+    }
+}
+
+#[derive(Clone, Debug)]
+struct LineAct(i64);
+
+impl Act for LineAct {}
+
+impl From<LineAct> for Action {
+    fn from(act: LineAct) -> Self {
+        Action {
+            continuous: vec![],
+            discrete: vec![act.0],
+        }
+    }
+}
+
+/// Runs the environment server in a thread and returns its address.
+fn serve() -> Result<String> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let listener = runtime.block_on(TcpListener::bind("127.0.0.1:0"))?;
+    let addr = listener.local_addr()?;
+    std::thread::spawn(move || {
+        runtime.block_on(
+            Server::builder()
+                .add_service(EnvServer::new(LineEnv::default()))
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        )
+    });
+    Ok(format!("http://{}", addr))
+}
+
+#[test]
+fn test_remote_env() -> Result<()> {
+    let config = RemoteEnvConfig::default()
+        .addr(serve()?)
+        .timeout_ms(Some(5000));
+    let mut env = RemoteEnv::<LineObs, LineAct>::build(&config, 42)?;
+
+    assert_eq!(env.reset(None)?.0, 0.0);
+    let (step, _) = env.step(&LineAct(1));
+    assert_eq!(step.obs.0, 1.0);
+    assert!(!step.is_done());
+    let (step, _) = env.step(&LineAct(1));
+    assert_eq!(step.reward, vec![1.0]);
+    assert_eq!(step.is_terminated, vec![1]);
+    assert!(step.info.is_success());
+
+    // A missing observation is an error
+    assert!(env.try_step(&LineAct(-1)).is_err());
+
+    // The environment is reset at the end of an episode, as in training
+    env.reset(None)?;
+    env.try_step_with_reset(&LineAct(1))?;
+    let (step, _) = env.try_step_with_reset(&LineAct(1))?;
+    assert!(step.is_done());
+    assert_eq!(step.init_obs.unwrap().0, 0.0);
+    assert!(env.try_step_with_reset(&LineAct(-1)).is_err());
+
+    // The index is sent to the server
+    assert_eq!(env.reset_with_index(3)?.0, -3.0);
+
+    let image = env.render()?;
+    assert_eq!(image.image.len(), 18);
+    Ok(())
+}
@@ -0,0 +1,142 @@
+use anyhow::Result;
+use border_core::ModelManifest;
+use border_inference::{PolicyModel, PolicyRunner};
+use border_serve::{
+    proto::{ActRequest, InfoRequest},
+    PolicyClient, PolicyService, PolicyServiceConfig,
+};
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::net::TcpListener;
+use tokio_stream::wrappers::TcpListenerStream;
+use tonic::{transport::Server, Code};
+
+/// Returns the sum of an observation and its negation, recording the sizes of batches.
+struct SumPolicy {
+    batch_sizes: Arc<Mutex<Vec<usize>>>,
+}
+
+impl PolicyModel for SumPolicy {
+    fn forward(&mut self, obs: &[f32]) -> Result<Vec<f32>> {
+        let sum = obs.iter().sum::<f32>();
+        Ok(vec![sum, -sum])
+    }
+
+    fn forward_batch(&mut self, obs: &[Vec<f32>]) -> Result<Vec<Vec<f32>>> {
+        self.batch_sizes.lock().unwrap().push(obs.len());
+        obs.iter().map(|obs| self.forward(obs)).collect()
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_policy_service() -> Result<()> {
+    let batch_sizes = Arc::new(Mutex::new(vec![]));
+    let manifest = ModelManifest::new("candle", "Dqn")
+        .obs_shape(Some(vec![3]))
+        .act_dim(2);
+    let model = SumPolicy {
+        batch_sizes: batch_sizes.clone(),
+    };
+    let runner = PolicyRunner::new(manifest, Box::new(model));
+    let config = PolicyServiceConfig::default()
+        .max_batch_size(4)
+        .batch_timeout_us(100_000);
+    let service = PolicyService::new(runner, config);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(
+        Server::builder()
+            .add_service(service.into_server())
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    let mut client = PolicyClient::connect(format!("http://{}", addr)).await?;
+
+    let info = client.info(InfoRequest {}).await?.into_inner();
+    assert_eq!(info.obs_shape, vec![3]);
+    assert_eq!(info.act_dim, 2);
+
+    // Concurrent requests are processed in a batch
+    let requests = (0..4).map(|i| {
+        let mut client = client.clone();
+        let obs = vec![i as f32 - 2.0, 0.5, 0.0];
+        tokio::spawn(async move { client.act(ActRequest { obs }).await })
+    });
+    for (i, request) in requests.collect::<Vec<_>>().into_iter().enumerate() {
+        let res = request.await??.into_inner();
+        let sum = i as f32 - 1.5;
+        assert_eq!(res.act, vec![sum, -sum]);
+        assert_eq!(res.act_discrete, if sum > 0.0 { 0 } else { 1 });
+    }
+    assert_eq!(*batch_sizes.lock().unwrap(), vec![4]);
+
+    // Observations of wrong length are rejected
+    let err = client
+        .act(ActRequest { obs: vec![0.0; 2] })
+        .await
+        .unwrap_err();
+    assert_eq!(err.code(), Code::InvalidArgument);
+    Ok(())
+}
+
+/// Blocks in the first batch until it is released.
+struct BlockingPolicy {
+    started: std::sync::mpsc::Sender<()>,
+    release: std::sync::mpsc::Receiver<()>,
+}
+
+impl PolicyModel for BlockingPolicy {
+    fn forward(&mut self, _obs: &[f32]) -> Result<Vec<f32>> {
+        let _ = self.started.send(());
+        let _ = self.release.recv();
+        Ok(vec![0.0])
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_queue_full() -> Result<()> {
+    let (started_tx, started_rx) = std::sync::mpsc::channel();
+    let (release_tx, release_rx) = std::sync::mpsc::channel();
+    let manifest = ModelManifest::new("candle", "Dqn").act_dim(1);
+    let model = BlockingPolicy {
+        started: started_tx,
+        release: release_rx,
+    };
+    let runner = PolicyRunner::new(manifest, Box::new(model));
+    let config = PolicyServiceConfig::default()
+        .max_batch_size(1)
+        .batch_timeout_us(0)
+        .max_queue_size(1);
+    let service = PolicyService::new(runner, config);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(
+        Server::builder()
+            .add_service(service.into_server())
+            .serve_with_incoming(TcpListenerStream::new(listener)),
+    );
+    let client = PolicyClient::connect(format!("http://{}", addr)).await?;
+    let spawn_act = || {
+        let mut client = client.clone();
+        tokio::spawn(async move { client.act(ActRequest { obs: vec![0.0] }).await })
+    };
+
+    // The first request blocks the policy and the second one waits in the queue
+    let first = spawn_act();
+    tokio::task::spawn_blocking(move || started_rx.recv()).await??;
+    let second = spawn_act();
+    tokio::task::spawn_blocking(|| std::thread::sleep(Duration::from_millis(100))).await?;
+
+    // Requests beyond the queue are rejected
+    let err = spawn_act().await?.unwrap_err();
+    assert_eq!(err.code(), Code::ResourceExhausted);
+
+    release_tx.send(())?;
+    release_tx.send(())?;
+    assert_eq!(first.await??.into_inner().act, vec![0.0]);
+    assert_eq!(second.await??.into_inner().act, vec![0.0]);
+    Ok(())
+}
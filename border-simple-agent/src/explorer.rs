@@ -0,0 +1,95 @@
+//! Exploration strategy of the agents.
+use border_core::Schedule;
+use ndarray::{Array1, ArrayView2, Axis};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Epsilon-greedy explorer.
+///
+/// The probability of taking a random action decreases linearly from `eps_start` to
+/// `eps_final` over `final_step` optimization steps, unless `schedule` is given.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct EpsilonGreedy {
+    /// Initial probability of a random action.
+    pub eps_start: f32,
+
+    /// Final probability of a random action.
+    pub eps_final: f32,
+
+    /// Number of optimization steps to reach `eps_final`.
+    pub final_step: usize,
+
+    /// Schedule of the probability of a random action over optimization steps,
+    /// replacing the linear one.
+    #[serde(default)]
+    pub schedule: Option<Schedule>,
+}
+
+impl Default for EpsilonGreedy {
+    fn default() -> Self {
+        Self {
+            eps_start: 1.0,
+            eps_final: 0.02,
+            final_step: 10_000,
+            schedule: None,
+        }
+    }
+}
+
+impl EpsilonGreedy {
+    /// Constructs epsilon-greedy explorer.
+    pub fn new(eps_start: f32, eps_final: f32, final_step: usize) -> Self {
+        Self {
+            eps_start,
+            eps_final,
+            final_step,
+            schedule: None,
+        }
+    }
+
+    /// Sets the schedule of the probability of a random action.
+    pub fn schedule(mut self, v: Schedule) -> Self {
+        self.schedule = Some(v);
+        self
+    }
+
+    /// Returns the probability of a random action after `n_opts` optimization steps.
+    pub fn eps(&self, n_opts: usize) -> f32 {
+        if let Some(schedule) = &self.schedule {
+            schedule.value(n_opts) as f32
+        } else if n_opts >= self.final_step {
+            self.eps_final
+        } else {
+            let d = (self.eps_start - self.eps_final) / self.final_step as f32;
+            self.eps_start - d * n_opts as f32
+        }
+    }
+
+    /// Takes actions based on action values, whose rows correspond to samples.
+    pub fn action(&self, q: ArrayView2<f32>, n_opts: usize, rng: &mut impl Rng) -> Array1<i64> {
+        let eps = self.eps(n_opts);
+        let n_actions = q.ncols();
+        q.axis_iter(Axis(0))
+            .map(|q| match rng.gen::<f32>() < eps {
+                true => rng.gen_range(0..n_actions) as i64,
+                false => argmax(q.iter()) as i64,
+            })
+            .collect()
+    }
+}
+
+/// Returns the index of the largest value, the first one for ties.
+pub(crate) fn argmax<'a>(xs: impl Iterator<Item = &'a f32>) -> usize {
+    let mut best = (0, f32::MIN);
+    for (i, &x) in xs.enumerate() {
+        if x > best.1 {
+            best = (i, x);
+        }
+    }
+    best.0
+}
+
+/// Returns the largest value.
+pub(crate) fn max<'a>(xs: impl Iterator<Item = &'a f32>) -> f32 {
+    xs.fold(f32::MIN, |m, &x| m.max(x))
+}
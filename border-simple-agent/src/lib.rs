@@ -0,0 +1,18 @@
+//! RL agents implemented with [ndarray](https://crates.io/crates/ndarray).
+//!
+//! The agents in this crate do not depend on any deep learning backend, such as tch or candle.
+//! They are useful for teaching, testing the core abstractions of Border, and validating
+//! new environments cheaply.
+//!
+//! * [`TabularQ`](tabular_q::TabularQ) learns a table of action values for discrete states.
+//!   Observations are converted into state indices as [`Array1<i64>`](ndarray::Array1).
+//! * [`LinearQ`](linear_q::LinearQ) learns action values linear in the features of observations.
+//!   Observations are converted into feature vectors as [`Array2<f32>`](ndarray::Array2),
+//!   whose rows correspond to samples.
+//!
+//! For both agents, actions are converted from and into action indices as
+//! [`Array1<i64>`](ndarray::Array1).
+mod explorer;
+pub mod linear_q;
+pub mod tabular_q;
+pub use explorer::EpsilonGreedy;
@@ -0,0 +1,5 @@
+//! Q-learning agent with linear function approximation.
+mod base;
+mod config;
+pub use base::LinearQ;
+pub use config::LinearQConfig;
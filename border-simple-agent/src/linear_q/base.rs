@@ -0,0 +1,243 @@
+//! Q-learning agent with linear function approximation.
+use super::LinearQConfig;
+use crate::{
+    explorer::{argmax, max},
+    EpsilonGreedy,
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+};
+use ndarray::{Array1, Array2, Axis};
+use rand::{rngs::SmallRng, SeedableRng};
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+/// Q-learning agent with linear function approximation.
+///
+/// Action values are computed as `Q(x, a) = w_a . x + b_a`, where `x` is the feature
+/// vector of an observation. The parameters are updated with the semi-gradient of
+/// the squared TD error averaged over a batch.
+pub struct LinearQ<E, R> {
+    w: Array2<f32>,
+    b: Array1<f32>,
+    learning_rate: f32,
+    discount_factor: f32,
+    batch_size: usize,
+    n_updates_per_opt: usize,
+    explorer: EpsilonGreedy,
+    train: bool,
+    n_opts: usize,
+    rng: SmallRng,
+    phantom: PhantomData<(E, R)>,
+}
+
+impl<E, R> LinearQ<E, R> {
+    fn from_config(config: LinearQConfig) -> Self {
+        Self {
+            w: Array2::zeros((config.n_actions, config.n_features)),
+            b: Array1::zeros(config.n_actions),
+            learning_rate: config.learning_rate,
+            discount_factor: config.discount_factor,
+            batch_size: config.batch_size,
+            n_updates_per_opt: config.n_updates_per_opt,
+            explorer: config.explorer,
+            train: config.train,
+            n_opts: 0,
+            rng: SmallRng::seed_from_u64(config.seed),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the weights and biases, whose rows correspond to actions.
+    pub fn params(&self) -> (&Array2<f32>, &Array1<f32>) {
+        (&self.w, &self.b)
+    }
+
+    /// Computes action values for feature vectors, whose rows correspond to samples.
+    pub fn q_values(&self, x: &Array2<f32>) -> Array2<f32> {
+        x.dot(&self.w.t()) + &self.b
+    }
+
+    /// Updates the parameters with transitions and returns the mean squared TD error.
+    fn update(
+        &mut self,
+        obs: &Array2<f32>,
+        act: &Array1<i64>,
+        next_obs: &Array2<f32>,
+        reward: &[f32],
+        is_terminated: &[i8],
+    ) -> f32 {
+        let n = obs.nrows();
+        let q = self.q_values(obs);
+        let q_next = self.q_values(next_obs);
+        let mut grad_w = Array2::<f32>::zeros(self.w.raw_dim());
+        let mut grad_b = Array1::<f32>::zeros(self.b.raw_dim());
+        let mut loss = 0f32;
+
+        for i in 0..n {
+            let a = act[i] as usize;
+            let q_next = match is_terminated[i] {
+                0 => max(q_next.row(i).iter()),
+                _ => 0.0,
+            };
+            let td_err = reward[i] + self.discount_factor * q_next - q[[i, a]];
+            grad_w.row_mut(a).scaled_add(td_err, &obs.row(i));
+            grad_b[a] += td_err;
+            loss += td_err * td_err;
+        }
+
+        let lr = self.learning_rate / n as f32;
+        self.w.scaled_add(lr, &grad_w);
+        self.b.scaled_add(lr, &grad_b);
+        loss / n as f32
+    }
+}
+
+impl<E, R> LinearQ<E, R>
+where
+    E: Env,
+    R: ReplayBufferBase,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Array2<f32>>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Array1<i64>>,
+{
+    fn opt_(&mut self, buffer: &mut R) -> Record {
+        let mut loss = 0f32;
+
+        for _ in 0..self.n_updates_per_opt {
+            let batch = buffer.batch(self.batch_size).unwrap();
+            let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
+            let (obs, act, next_obs) = (obs.into(), act.into(), next_obs.into());
+            loss += self.update(&obs, &act, &next_obs, &reward, &is_terminated);
+        }
+
+        self.n_opts += 1;
+
+        Record::from_slice(&[
+            (
+                "loss",
+                RecordValue::Scalar(loss / self.n_updates_per_opt as f32),
+            ),
+            ("eps", RecordValue::Scalar(self.explorer.eps(self.n_opts))),
+        ])
+    }
+}
+
+impl<E, R> Policy<E> for LinearQ<E, R>
+where
+    E: Env,
+    E::Obs: Into<Array2<f32>>,
+    E::Act: From<Array1<i64>>,
+{
+    /// Takes epsilon-greedy actions in training mode, greedy actions otherwise.
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        let q = self.q_values(&obs.clone().into());
+        let act = match self.train {
+            true => self.explorer.action(q.view(), self.n_opts, &mut self.rng),
+            false => q
+                .axis_iter(Axis(0))
+                .map(|q| argmax(q.iter()) as i64)
+                .collect(),
+        };
+        act.into()
+    }
+}
+
+impl<E, R> Configurable for LinearQ<E, R>
+where
+    E: Env,
+    E::Obs: Into<Array2<f32>>,
+    E::Act: From<Array1<i64>>,
+{
+    type Config = LinearQConfig;
+
+    /// Constructs Q-learning agent with linear function approximation.
+    fn build(config: Self::Config) -> Self {
+        Self::from_config(config)
+    }
+}
+
+impl<E, R> Agent<E, R> for LinearQ<E, R>
+where
+    E: Env + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<Array2<f32>>,
+    E::Act: From<Array1<i64>>,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Array2<f32>>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Array1<i64>>,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt(&mut self, buffer: &mut R) {
+        self.opt_(buffer);
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.opt_(buffer)
+    }
+
+    /// Save the weights and biases in the given directory as `linear_q.bincode`.
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+        let path = path.join("linear_q.bincode");
+        let wtr = BufWriter::new(File::create(&path)?);
+        bincode::serialize_into(wtr, &(&self.w, &self.b))?;
+        Ok(vec![path])
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        let rdr = BufReader::new(File::open(path.join("linear_q.bincode"))?);
+        let (w, b) = bincode::deserialize_from(rdr)?;
+        self.w = w;
+        self.b = b;
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        // One-step bandit with one-hot features: Q(x_0, 1) = 1 and Q(x_1, 0) = -1
+        let config = LinearQConfig::default()
+            .dims(2, 2)
+            .learning_rate(0.5)
+            .discount_factor(0.9);
+        let mut agent = LinearQ::<(), ()>::from_config(config);
+        let obs = Array2::from_shape_vec((2, 2), vec![1.0, 0.0, 0.0, 1.0]).unwrap();
+        let act = Array1::from(vec![1, 0]);
+        for _ in 0..200 {
+            agent.update(&obs, &act, &obs, &[1.0, -1.0], &[1, 1]);
+        }
+        let q = agent.q_values(&obs);
+        assert!((q[[0, 1]] - 1.0).abs() < 1e-3);
+        assert!((q[[1, 0]] + 1.0).abs() < 1e-3);
+    }
+}
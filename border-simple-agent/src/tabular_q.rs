@@ -0,0 +1,5 @@
+//! Tabular Q-learning agent.
+mod base;
+mod config;
+pub use base::TabularQ;
+pub use config::TabularQConfig;
@@ -0,0 +1,228 @@
+//! Tabular Q-learning agent.
+use super::TabularQConfig;
+use crate::{
+    explorer::{argmax, max},
+    EpsilonGreedy,
+};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
+};
+use ndarray::{Array1, Array2, Axis};
+use rand::{rngs::SmallRng, SeedableRng};
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+/// Tabular Q-learning agent.
+///
+/// The agent learns a table of action values, whose rows and columns correspond to
+/// states and actions, respectively. Each sampled transition updates the table as
+/// `Q(s, a) += lr * (r + gamma * max_a' Q(s', a') - Q(s, a))`.
+pub struct TabularQ<E, R> {
+    q: Array2<f32>,
+    learning_rate: f32,
+    discount_factor: f32,
+    batch_size: usize,
+    n_updates_per_opt: usize,
+    explorer: EpsilonGreedy,
+    train: bool,
+    n_opts: usize,
+    rng: SmallRng,
+    phantom: PhantomData<(E, R)>,
+}
+
+impl<E, R> TabularQ<E, R> {
+    fn from_config(config: TabularQConfig) -> Self {
+        Self {
+            q: Array2::from_elem((config.n_states, config.n_actions), config.initial_value),
+            learning_rate: config.learning_rate,
+            discount_factor: config.discount_factor,
+            batch_size: config.batch_size,
+            n_updates_per_opt: config.n_updates_per_opt,
+            explorer: config.explorer,
+            train: config.train,
+            n_opts: 0,
+            rng: SmallRng::seed_from_u64(config.seed),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the table of action values.
+    pub fn q_table(&self) -> &Array2<f32> {
+        &self.q
+    }
+
+    /// Updates the table with transitions and returns the mean squared TD error.
+    fn update(
+        &mut self,
+        obs: &Array1<i64>,
+        act: &Array1<i64>,
+        next_obs: &Array1<i64>,
+        reward: &[f32],
+        is_terminated: &[i8],
+    ) -> f32 {
+        let mut loss = 0f32;
+        for i in 0..obs.len() {
+            let (s, a, s_) = (obs[i] as usize, act[i] as usize, next_obs[i] as usize);
+            let q_next = match is_terminated[i] {
+                0 => max(self.q.row(s_).iter()),
+                _ => 0.0,
+            };
+            let td_err = reward[i] + self.discount_factor * q_next - self.q[[s, a]];
+            self.q[[s, a]] += self.learning_rate * td_err;
+            loss += td_err * td_err;
+        }
+        loss / obs.len() as f32
+    }
+}
+
+impl<E, R> TabularQ<E, R>
+where
+    E: Env,
+    R: ReplayBufferBase,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Array1<i64>>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Array1<i64>>,
+{
+    fn opt_(&mut self, buffer: &mut R) -> Record {
+        let mut loss = 0f32;
+
+        for _ in 0..self.n_updates_per_opt {
+            let batch = buffer.batch(self.batch_size).unwrap();
+            let (obs, act, next_obs, reward, is_terminated, _, _, _) = batch.unpack();
+            let (obs, act, next_obs) = (obs.into(), act.into(), next_obs.into());
+            loss += self.update(&obs, &act, &next_obs, &reward, &is_terminated);
+        }
+
+        self.n_opts += 1;
+
+        Record::from_slice(&[
+            (
+                "loss",
+                RecordValue::Scalar(loss / self.n_updates_per_opt as f32),
+            ),
+            ("eps", RecordValue::Scalar(self.explorer.eps(self.n_opts))),
+        ])
+    }
+}
+
+impl<E, R> Policy<E> for TabularQ<E, R>
+where
+    E: Env,
+    E::Obs: Into<Array1<i64>>,
+    E::Act: From<Array1<i64>>,
+{
+    /// Takes epsilon-greedy actions in training mode, greedy actions otherwise.
+    fn sample(&mut self, obs: &E::Obs) -> E::Act {
+        let states: Array1<i64> = obs.clone().into();
+        let q = self.q.select(
+            Axis(0),
+            &states.iter().map(|&s| s as usize).collect::<Vec<_>>(),
+        );
+        let act = match self.train {
+            true => self.explorer.action(q.view(), self.n_opts, &mut self.rng),
+            false => q
+                .axis_iter(Axis(0))
+                .map(|q| argmax(q.iter()) as i64)
+                .collect(),
+        };
+        act.into()
+    }
+}
+
+impl<E, R> Configurable for TabularQ<E, R>
+where
+    E: Env,
+    E::Obs: Into<Array1<i64>>,
+    E::Act: From<Array1<i64>>,
+{
+    type Config = TabularQConfig;
+
+    /// Constructs tabular Q-learning agent.
+    fn build(config: Self::Config) -> Self {
+        Self::from_config(config)
+    }
+}
+
+impl<E, R> Agent<E, R> for TabularQ<E, R>
+where
+    E: Env + 'static,
+    R: ReplayBufferBase + 'static,
+    E::Obs: Into<Array1<i64>>,
+    E::Act: From<Array1<i64>>,
+    R::Batch: TransitionBatch,
+    <R::Batch as TransitionBatch>::ObsBatch: Into<Array1<i64>>,
+    <R::Batch as TransitionBatch>::ActBatch: Into<Array1<i64>>,
+{
+    fn train(&mut self) {
+        self.train = true;
+    }
+
+    fn eval(&mut self) {
+        self.train = false;
+    }
+
+    fn is_train(&self) -> bool {
+        self.train
+    }
+
+    fn opt(&mut self, buffer: &mut R) {
+        self.opt_(buffer);
+    }
+
+    fn opt_with_record(&mut self, buffer: &mut R) -> Record {
+        self.opt_(buffer)
+    }
+
+    /// Save the table of action values in the given directory as `q_table.bincode`.
+    fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(path)?;
+        let path = path.join("q_table.bincode");
+        let wtr = BufWriter::new(File::create(&path)?);
+        bincode::serialize_into(wtr, &self.q)?;
+        Ok(vec![path])
+    }
+
+    fn load_params(&mut self, path: &Path) -> Result<()> {
+        let rdr = BufReader::new(File::open(path.join("q_table.bincode"))?);
+        self.q = bincode::deserialize_from(rdr)?;
+        Ok(())
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+
+    fn as_any_ref(&self) -> &dyn std::any::Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update() {
+        // Two-state chain: action 1 in state 0 moves to state 1, which terminates with reward 1
+        let config = TabularQConfig::default()
+            .dims(2, 2)
+            .learning_rate(0.5)
+            .discount_factor(0.9);
+        let mut agent = TabularQ::<(), ()>::from_config(config);
+        let obs = Array1::from(vec![0, 1]);
+        let act = Array1::from(vec![1, 0]);
+        let next_obs = Array1::from(vec![1, 1]);
+        for _ in 0..100 {
+            agent.update(&obs, &act, &next_obs, &[0.0, 1.0], &[0, 1]);
+        }
+        assert!((agent.q[[1, 0]] - 1.0).abs() < 1e-4);
+        assert!((agent.q[[0, 1]] - 0.9).abs() < 1e-4);
+        assert_eq!(agent.q[[0, 0]], 0.0);
+    }
+}
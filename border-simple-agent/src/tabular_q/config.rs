@@ -0,0 +1,126 @@
+//! Configuration of tabular Q-learning agent.
+use crate::EpsilonGreedy;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::Path,
+};
+
+/// Configuration of [`TabularQ`](super::TabularQ) agent.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct TabularQConfig {
+    /// Number of states.
+    pub n_states: usize,
+
+    /// Number of actions.
+    pub n_actions: usize,
+
+    /// Initial value of the table.
+    pub initial_value: f32,
+
+    /// Step size of the updates.
+    pub learning_rate: f32,
+
+    /// Discount factor.
+    pub discount_factor: f32,
+
+    /// Batch size.
+    pub batch_size: usize,
+
+    /// Number of updates per optimization step.
+    pub n_updates_per_opt: usize,
+
+    /// Explorer.
+    pub explorer: EpsilonGreedy,
+
+    /// Random seed for exploration.
+    pub seed: u64,
+
+    /// If `true`, the agent is in training mode.
+    pub train: bool,
+}
+
+impl Default for TabularQConfig {
+    fn default() -> Self {
+        Self {
+            n_states: 1,
+            n_actions: 1,
+            initial_value: 0.0,
+            learning_rate: 0.1,
+            discount_factor: 0.99,
+            batch_size: 1,
+            n_updates_per_opt: 1,
+            explorer: EpsilonGreedy::default(),
+            seed: 42,
+            train: false,
+        }
+    }
+}
+
+impl TabularQConfig {
+    /// Sets the numbers of states and actions.
+    pub fn dims(mut self, n_states: usize, n_actions: usize) -> Self {
+        self.n_states = n_states;
+        self.n_actions = n_actions;
+        self
+    }
+
+    /// Sets the initial value of the table.
+    pub fn initial_value(mut self, v: f32) -> Self {
+        self.initial_value = v;
+        self
+    }
+
+    /// Sets the step size of the updates.
+    pub fn learning_rate(mut self, v: f32) -> Self {
+        self.learning_rate = v;
+        self
+    }
+
+    /// Sets the discount factor.
+    pub fn discount_factor(mut self, v: f32) -> Self {
+        self.discount_factor = v;
+        self
+    }
+
+    /// Sets the batch size.
+    pub fn batch_size(mut self, v: usize) -> Self {
+        self.batch_size = v;
+        self
+    }
+
+    /// Sets the number of updates per optimization step.
+    pub fn n_updates_per_opt(mut self, v: usize) -> Self {
+        self.n_updates_per_opt = v;
+        self
+    }
+
+    /// Sets the explorer.
+    pub fn explorer(mut self, v: EpsilonGreedy) -> Self {
+        self.explorer = v;
+        self
+    }
+
+    /// Sets the random seed for exploration.
+    pub fn seed(mut self, v: u64) -> Self {
+        self.seed = v;
+        self
+    }
+
+    /// Constructs [`TabularQConfig`] from YAML file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves [`TabularQConfig`].
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
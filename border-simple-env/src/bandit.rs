@@ -0,0 +1,176 @@
+//! Multi-armed bandit.
+use crate::{DiscreteAct, StateObs};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    sample_standard_normal, Env, Info, Step,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Info of [`Bandit`].
+pub struct BanditInfo {
+    /// `true` if the arm with the largest mean reward has been pulled.
+    pub is_optimal: bool,
+}
+
+impl Info for BanditInfo {}
+
+/// Configuration of [`Bandit`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BanditConfig {
+    /// Mean rewards of the arms.
+    pub means: Vec<f32>,
+
+    /// Standard deviation of rewards.
+    pub std: f32,
+
+    /// The number of pulls in an episode, after which the episode terminates.
+    pub episode_length: usize,
+}
+
+impl Default for BanditConfig {
+    fn default() -> Self {
+        Self {
+            means: vec![0.1, 0.5, 0.9],
+            std: 1.0,
+            episode_length: 1,
+        }
+    }
+}
+
+impl BanditConfig {
+    /// Sets the mean rewards of the arms.
+    pub fn means(mut self, v: Vec<f32>) -> Self {
+        self.means = v;
+        self
+    }
+
+    /// Sets the standard deviation of rewards.
+    pub fn std(mut self, v: f32) -> Self {
+        self.std = v;
+        self
+    }
+
+    /// Sets the number of pulls in an episode.
+    pub fn episode_length(mut self, v: usize) -> Self {
+        self.episode_length = v;
+        self
+    }
+}
+
+/// Multi-armed bandit with Gaussian rewards.
+///
+/// An action is the index of the arm to pull, and the reward is sampled from the normal
+/// distribution with the mean of the arm and `std`. The observation is always state `0`.
+/// The regret of each pull, i.e., the difference between the largest mean reward and
+/// the mean reward of the pulled arm, is recorded as `regret`.
+pub struct Bandit {
+    means: Vec<f32>,
+    std: f32,
+    episode_length: usize,
+    n_steps: usize,
+    seed: i64,
+    rng: SmallRng,
+}
+
+impl Bandit {
+    /// Returns the number of arms.
+    pub fn n_arms(&self) -> usize {
+        self.means.len()
+    }
+
+    fn max_mean(&self) -> f32 {
+        self.means.iter().cloned().fold(f32::MIN, f32::max)
+    }
+}
+
+impl Env for Bandit {
+    type Config = BanditConfig;
+    type Obs = StateObs;
+    type Act = DiscreteAct;
+    type Info = BanditInfo;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        if config.means.is_empty() {
+            anyhow::bail!("The bandit must have at least one arm");
+        }
+        Ok(Self {
+            means: config.means.clone(),
+            std: config.std,
+            episode_length: config.episode_length,
+            n_steps: 0,
+            seed,
+            rng: SmallRng::seed_from_u64(seed as _),
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let mean = self.means[a.0 as usize];
+        let reward = mean + self.std * sample_standard_normal(&mut self.rng);
+        let regret = self.max_mean() - mean;
+        self.n_steps += 1;
+
+        let is_terminated = self.n_steps >= self.episode_length;
+        let step = Step::new(
+            StateObs(0),
+            a.clone(),
+            vec![reward],
+            vec![is_terminated as i8],
+            vec![0],
+            BanditInfo {
+                is_optimal: regret == 0.0,
+            },
+            None,
+        );
+        let record = Record::from_slice(&[("regret", RecordValue::Scalar(regret))]);
+
+        (step, record)
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        self.n_steps = 0;
+        Ok(StateObs(0))
+    }
+
+    /// Returns all arms followed by random ones, which are sampled with a generator
+    /// separate from that of the environment.
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        let mut rng = SmallRng::seed_from_u64(self.seed as _);
+        let n = self.n_arms() as i64;
+        (0..n)
+            .chain((0..n_random).map(|_| rng.gen_range(0..n)))
+            .map(DiscreteAct)
+            .collect()
+    }
+
+    /// Resets the environment with the random number generator seeded by `ix`,
+    /// so that rewards in evaluation episodes are reproducible.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.rng =
+            SmallRng::seed_from_u64(self.seed as u64 ^ (ix as u64).wrapping_mul(0x9e37_79b9));
+        self.reset(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bandit() -> Result<()> {
+        let mut env = Bandit::build(&BanditConfig::default().std(0.1), 0)?;
+        let rewards: Vec<f32> = (0..100)
+            .map(|_| {
+                env.reset(None).unwrap();
+                let (step, record) = env.step(&DiscreteAct(0));
+                assert!(step.is_done());
+                assert!((record.get_scalar("regret").unwrap() - 0.8).abs() < 1e-6);
+                step.reward[0]
+            })
+            .collect();
+        let mean = rewards.iter().sum::<f32>() / 100.0;
+        assert!((mean - 0.1).abs() < 0.05);
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//! Observations and actions shared by the environments.
+use border_core::{
+    generic_replay_buffer::{BatchBase, SimpleReplayBuffer, SimpleStepProcessor},
+    Act, ActValues, Obs,
+};
+use ndarray::{Array1, Array2};
+use serde::{Deserialize, Serialize};
+
+/// Observation of continuous features, used by [`CartPole`] and [`MountainCar`].
+///
+/// [`CartPole`]: crate::CartPole
+/// [`MountainCar`]: crate::MountainCar
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct FeatureObs(pub Vec<f32>);
+
+impl Obs for FeatureObs {
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn shape(&self) -> Option<Vec<usize>> {
+        Some(vec![self.0.len()])
+    }
+
+    fn is_finite(&self) -> bool {
+        self.0.iter().all(|v| v.is_finite())
+    }
+}
+
+/// Converts the observation into a feature matrix with a single row.
+impl From<FeatureObs> for Array2<f32> {
+    fn from(obs: FeatureObs) -> Self {
+        let dim = obs.0.len();
+        Array2::from_shape_vec((1, dim), obs.0).unwrap()
+    }
+}
+
+/// Observation of a discrete state, used by [`GridWorld`] and [`Bandit`].
+///
+/// [`GridWorld`]: crate::GridWorld
+/// [`Bandit`]: crate::Bandit
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct StateObs(pub i64);
+
+impl Obs for StateObs {
+    fn len(&self) -> usize {
+        1
+    }
+}
+
+impl From<StateObs> for Array1<i64> {
+    fn from(obs: StateObs) -> Self {
+        Array1::from(vec![obs.0])
+    }
+}
+
+/// Discrete action, the index of an action.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DiscreteAct(pub i64);
+
+impl Act for DiscreteAct {
+    fn len(&self) -> usize {
+        1
+    }
+
+    fn values(&self) -> Option<ActValues> {
+        ActValues::from_indices([self.0])
+    }
+}
+
+impl From<i64> for DiscreteAct {
+    fn from(a: i64) -> Self {
+        Self(a)
+    }
+}
+
+/// Takes the first element as the action.
+impl From<Array1<i64>> for DiscreteAct {
+    fn from(a: Array1<i64>) -> Self {
+        Self(a[0])
+    }
+}
+
+/// Batch of [`FeatureObs`].
+///
+/// The dimension of features is taken from the first pushed observations.
+#[derive(Clone, Debug)]
+pub struct FeatureObsBatch {
+    capacity: usize,
+    dim: usize,
+    data: Vec<f32>,
+}
+
+impl BatchBase for FeatureObsBatch {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            dim: 0,
+            data: vec![],
+        }
+    }
+
+    fn push(&mut self, ix: usize, data: Self) {
+        if self.data.is_empty() {
+            self.dim = data.dim;
+            self.data = vec![0.0; self.capacity * self.dim];
+        }
+        for (j, row) in data.data.chunks(self.dim).enumerate() {
+            let i = (ix + j) % self.capacity;
+            self.data[i * self.dim..(i + 1) * self.dim].copy_from_slice(row);
+        }
+    }
+
+    fn sample(&self, ixs: &Vec<usize>) -> Self {
+        let data = ixs
+            .iter()
+            .flat_map(|&i| self.data[i * self.dim..(i + 1) * self.dim].iter().copied())
+            .collect();
+        Self {
+            capacity: ixs.len(),
+            dim: self.dim,
+            data,
+        }
+    }
+}
+
+impl From<FeatureObs> for FeatureObsBatch {
+    fn from(obs: FeatureObs) -> Self {
+        Self {
+            capacity: 1,
+            dim: obs.0.len(),
+            data: obs.0,
+        }
+    }
+}
+
+impl From<FeatureObsBatch> for Array2<f32> {
+    fn from(batch: FeatureObsBatch) -> Self {
+        let n = batch.data.len() / batch.dim.max(1);
+        Array2::from_shape_vec((n, batch.dim), batch.data).unwrap()
+    }
+}
+
+/// Batch of [`StateObs`].
+#[derive(Clone, Debug)]
+pub struct StateObsBatch(pub Vec<i64>);
+
+impl BatchBase for StateObsBatch {
+    fn new(capacity: usize) -> Self {
+        Self(vec![0; capacity])
+    }
+
+    fn push(&mut self, ix: usize, data: Self) {
+        let capacity = self.0.len();
+        for (j, v) in data.0.into_iter().enumerate() {
+            self.0[(ix + j) % capacity] = v;
+        }
+    }
+
+    fn sample(&self, ixs: &Vec<usize>) -> Self {
+        Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+    }
+}
+
+impl From<StateObs> for StateObsBatch {
+    fn from(obs: StateObs) -> Self {
+        Self(vec![obs.0])
+    }
+}
+
+impl From<StateObsBatch> for Array1<i64> {
+    fn from(batch: StateObsBatch) -> Self {
+        Array1::from(batch.0)
+    }
+}
+
+/// Batch of [`DiscreteAct`].
+#[derive(Clone, Debug)]
+pub struct DiscreteActBatch(pub Vec<i64>);
+
+impl BatchBase for DiscreteActBatch {
+    fn new(capacity: usize) -> Self {
+        Self(vec![0; capacity])
+    }
+
+    fn push(&mut self, ix: usize, data: Self) {
+        let capacity = self.0.len();
+        for (j, v) in data.0.into_iter().enumerate() {
+            self.0[(ix + j) % capacity] = v;
+        }
+    }
+
+    fn sample(&self, ixs: &Vec<usize>) -> Self {
+        Self(ixs.iter().map(|&ix| self.0[ix]).collect())
+    }
+}
+
+impl From<DiscreteAct> for DiscreteActBatch {
+    fn from(act: DiscreteAct) -> Self {
+        Self(vec![act.0])
+    }
+}
+
+impl From<DiscreteActBatch> for Array1<i64> {
+    fn from(batch: DiscreteActBatch) -> Self {
+        Array1::from(batch.0)
+    }
+}
+
+/// Step processor for environments with [`FeatureObs`].
+pub type FeatureStepProcessor<E> = SimpleStepProcessor<E, FeatureObsBatch, DiscreteActBatch>;
+
+/// Replay buffer for environments with [`FeatureObs`].
+pub type FeatureReplayBuffer = SimpleReplayBuffer<FeatureObsBatch, DiscreteActBatch>;
+
+/// Step processor for environments with [`StateObs`].
+pub type StateStepProcessor<E> = SimpleStepProcessor<E, StateObsBatch, DiscreteActBatch>;
+
+/// Replay buffer for environments with [`StateObs`].
+pub type StateReplayBuffer = SimpleReplayBuffer<StateObsBatch, DiscreteActBatch>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_obs_batch() {
+        let mut batch = FeatureObsBatch::new(3);
+        for i in 0..4 {
+            batch.push(i, FeatureObs(vec![i as f32, -(i as f32)]).into());
+        }
+        let batch: Array2<f32> = batch.sample(&vec![0, 2]).into();
+        assert_eq!(batch, ndarray::arr2(&[[3.0, -3.0], [2.0, -2.0]]));
+    }
+}
@@ -0,0 +1,118 @@
+//! CartPole environment.
+use crate::{DiscreteAct, FeatureObs};
+use anyhow::Result;
+use border_core::{agent_dev, record::Record, Env, Step};
+use serde::{Deserialize, Serialize};
+
+pub use agent_dev::CartPoleInfo;
+
+/// Configuration of [`CartPole`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct CartPoleConfig {
+    /// The maximum number of steps in an episode, after which the episode is truncated.
+    pub max_steps: usize,
+}
+
+impl Default for CartPoleConfig {
+    fn default() -> Self {
+        Self { max_steps: 500 }
+    }
+}
+
+impl CartPoleConfig {
+    /// Sets the maximum number of steps in an episode.
+    pub fn max_steps(mut self, v: usize) -> Self {
+        self.max_steps = v;
+        self
+    }
+}
+
+/// CartPole environment.
+///
+/// The dynamics, the reward and the termination condition follow `CartPole-v1` of
+/// [Gymnasium](https://gymnasium.farama.org/environments/classic_control/cart_pole/).
+/// The observation consists of the cart position, the cart velocity, the pole angle and
+/// the pole angular velocity. Action `0` pushes the cart to the left and `1` to the right.
+/// The agent receives reward 1 for every step, including the terminal step.
+///
+/// The environment wraps [`agent_dev::CartPole`] of `border-core`, converting its observations
+/// and actions into [`FeatureObs`] and [`DiscreteAct`].
+pub struct CartPole(agent_dev::CartPole);
+
+impl CartPole {
+    /// Dimension of observations.
+    pub const DIM_OBS: usize = 4;
+
+    /// Number of actions.
+    pub const N_ACTIONS: usize = 2;
+}
+
+impl Env for CartPole {
+    type Config = CartPoleConfig;
+    type Obs = FeatureObs;
+    type Act = DiscreteAct;
+    type Info = CartPoleInfo;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let config = agent_dev::CartPoleConfig {
+            max_steps: config.max_steps,
+        };
+        Ok(Self(agent_dev::CartPole::build(&config, seed)?))
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let (step, record) = self.0.step(&agent_dev::CartPoleAct(a.0));
+        let step = Step::new(
+            FeatureObs(step.obs.0.to_vec()),
+            a.clone(),
+            step.reward,
+            step.is_terminated,
+            step.is_truncated,
+            step.info,
+            None,
+        );
+        (step, record)
+    }
+
+    fn reset(&mut self, is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        Ok(FeatureObs(self.0.reset(is_done)?.0.to_vec()))
+    }
+
+    /// Returns both actions followed by random ones, which are sampled with a generator
+    /// separate from that of the environment.
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        self.0
+            .probe_actions(n_random)
+            .into_iter()
+            .map(|a| DiscreteAct(a.0))
+            .collect()
+    }
+
+    /// Resets the environment with the random number generator seeded by `ix`,
+    /// so that evaluation episodes start from the same states.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        Ok(FeatureObs(self.0.reset_with_index(ix)?.0.to_vec()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cartpole() -> Result<()> {
+        let mut env = CartPole::build(&CartPoleConfig::default(), 0)?;
+        let obs = env.reset(None)?;
+        assert!(obs.0.iter().all(|v| v.abs() < 0.05));
+
+        // Pushing the cart to one side lets the pole fall within the limit of steps
+        let n_steps = (1..=500)
+            .find(|_| env.step(&DiscreteAct(1)).0.is_terminated[0] == 1)
+            .unwrap();
+        assert!(n_steps < 100);
+
+        // Episodes with the same index start from the same state
+        assert_eq!(env.reset_with_index(3)?, env.reset_with_index(3)?);
+        Ok(())
+    }
+}
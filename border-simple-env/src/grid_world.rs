@@ -0,0 +1,309 @@
+//! Configurable gridworld.
+use crate::{DiscreteAct, StateObs};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Env, Info, Step,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Info of [`GridWorld`].
+pub struct GridWorldInfo {
+    /// `true` if the agent has reached a goal.
+    pub is_success: bool,
+}
+
+impl Info for GridWorldInfo {}
+
+/// Configuration of [`GridWorld`].
+///
+/// Cells are given as `(row, column)`, where `(0, 0)` is the upper left corner.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct GridWorldConfig {
+    /// The number of rows.
+    pub height: usize,
+
+    /// The number of columns.
+    pub width: usize,
+
+    /// The cell where episodes start.
+    pub start: (usize, usize),
+
+    /// Cells where episodes terminate with `goal_reward`.
+    pub goals: Vec<(usize, usize)>,
+
+    /// Cells where episodes terminate with `trap_reward`.
+    pub traps: Vec<(usize, usize)>,
+
+    /// Cells the agent cannot enter.
+    pub walls: Vec<(usize, usize)>,
+
+    /// Reward for reaching a goal.
+    pub goal_reward: f32,
+
+    /// Reward for falling into a trap.
+    pub trap_reward: f32,
+
+    /// Reward for the other steps.
+    pub step_reward: f32,
+
+    /// Probability that a random action is taken instead of the given one.
+    pub slip_prob: f32,
+
+    /// The maximum number of steps in an episode, after which the episode is truncated.
+    pub max_steps: usize,
+}
+
+impl Default for GridWorldConfig {
+    fn default() -> Self {
+        Self {
+            height: 4,
+            width: 4,
+            start: (0, 0),
+            goals: vec![(3, 3)],
+            traps: vec![],
+            walls: vec![],
+            goal_reward: 1.0,
+            trap_reward: -1.0,
+            step_reward: 0.0,
+            slip_prob: 0.0,
+            max_steps: 100,
+        }
+    }
+}
+
+impl GridWorldConfig {
+    /// Constructs a configuration from a map, given as rows of characters.
+    ///
+    /// `S` is the start, `G` a goal, `X` a trap, `#` a wall and `.` an empty cell.
+    ///
+    /// ```
+    /// use border_simple_env::GridWorldConfig;
+    ///
+    /// let config = GridWorldConfig::from_map(&["S..", ".#X", "..G"]).unwrap();
+    /// assert_eq!((config.height, config.width), (3, 3));
+    /// assert_eq!(config.goals, vec![(2, 2)]);
+    /// ```
+    pub fn from_map(map: &[&str]) -> Result<Self> {
+        let width = map.first().map_or(0, |row| row.chars().count());
+        let mut config = Self {
+            height: map.len(),
+            width,
+            start: (usize::MAX, usize::MAX),
+            goals: vec![],
+            ..Self::default()
+        };
+
+        for (i, row) in map.iter().enumerate() {
+            if row.chars().count() != width {
+                anyhow::bail!("Row {} of the map has a different width from {}", i, width);
+            }
+            for (j, c) in row.chars().enumerate() {
+                match c {
+                    'S' if config.start.0 == usize::MAX => config.start = (i, j),
+                    'S' => anyhow::bail!("The map has more than one start"),
+                    'G' => config.goals.push((i, j)),
+                    'X' => config.traps.push((i, j)),
+                    '#' => config.walls.push((i, j)),
+                    '.' => {}
+                    _ => anyhow::bail!("Unknown cell {:?} in the map", c),
+                }
+            }
+        }
+        if config.start.0 == usize::MAX {
+            anyhow::bail!("The map has no start");
+        }
+
+        Ok(config)
+    }
+
+    /// Returns the number of states, i.e., cells.
+    pub fn n_states(&self) -> usize {
+        self.height * self.width
+    }
+
+    /// Sets the reward for reaching a goal.
+    pub fn goal_reward(mut self, v: f32) -> Self {
+        self.goal_reward = v;
+        self
+    }
+
+    /// Sets the reward for falling into a trap.
+    pub fn trap_reward(mut self, v: f32) -> Self {
+        self.trap_reward = v;
+        self
+    }
+
+    /// Sets the reward for the other steps.
+    pub fn step_reward(mut self, v: f32) -> Self {
+        self.step_reward = v;
+        self
+    }
+
+    /// Sets the probability that a random action is taken.
+    pub fn slip_prob(mut self, v: f32) -> Self {
+        self.slip_prob = v;
+        self
+    }
+
+    /// Sets the maximum number of steps in an episode.
+    pub fn max_steps(mut self, v: usize) -> Self {
+        self.max_steps = v;
+        self
+    }
+}
+
+/// Gridworld configured with [`GridWorldConfig`].
+///
+/// The observation is the index of the cell of the agent, `row * width + column`, so that
+/// tabular agents can be trained without any conversion. Actions `0`, `1`, `2` and `3` move
+/// the agent up, right, down and left, respectively. Moves into walls or out of the grid keep
+/// the agent in its cell. With `slip_prob`, the environment is stochastic.
+pub struct GridWorld {
+    config: GridWorldConfig,
+    pos: (usize, usize),
+    n_steps: usize,
+    seed: i64,
+    rng: SmallRng,
+}
+
+impl GridWorld {
+    /// Number of actions.
+    pub const N_ACTIONS: usize = 4;
+
+    fn obs(&self) -> StateObs {
+        StateObs((self.pos.0 * self.config.width + self.pos.1) as i64)
+    }
+
+    fn reset_state(&mut self) -> StateObs {
+        self.pos = self.config.start;
+        self.n_steps = 0;
+        self.obs()
+    }
+}
+
+impl Env for GridWorld {
+    type Config = GridWorldConfig;
+    type Obs = StateObs;
+    type Act = DiscreteAct;
+    type Info = GridWorldInfo;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        let (h, w) = (config.height, config.width);
+        let cells = config.goals.iter().chain(config.traps.iter());
+        if let Some(cell) = cells
+            .chain(config.walls.iter())
+            .chain(Some(&config.start))
+            .find(|(i, j)| *i >= h || *j >= w)
+        {
+            anyhow::bail!("Cell {:?} is out of the grid of {}x{}", cell, h, w);
+        }
+        if config.walls.contains(&config.start) {
+            anyhow::bail!("The start {:?} is a wall", config.start);
+        }
+
+        Ok(Self {
+            config: config.clone(),
+            pos: config.start,
+            n_steps: 0,
+            seed,
+            rng: SmallRng::seed_from_u64(seed as _),
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let a = match self.rng.gen::<f32>() < self.config.slip_prob {
+            true => self.rng.gen_range(0..4),
+            false => a.0,
+        };
+        let (i, j) = self.pos;
+        let next = match a {
+            0 => (i.saturating_sub(1), j),
+            1 => (i, (j + 1).min(self.config.width - 1)),
+            2 => ((i + 1).min(self.config.height - 1), j),
+            _ => (i, j.saturating_sub(1)),
+        };
+        if !self.config.walls.contains(&next) {
+            self.pos = next;
+        }
+        self.n_steps += 1;
+
+        let is_success = self.config.goals.contains(&self.pos);
+        let is_trapped = self.config.traps.contains(&self.pos);
+        let reward = match (is_success, is_trapped) {
+            (true, _) => self.config.goal_reward,
+            (_, true) => self.config.trap_reward,
+            _ => self.config.step_reward,
+        };
+        let is_terminated = is_success || is_trapped;
+        let is_truncated = !is_terminated && self.n_steps >= self.config.max_steps;
+        let step = Step::new(
+            self.obs(),
+            DiscreteAct(a),
+            vec![reward],
+            vec![is_terminated as i8],
+            vec![is_truncated as i8],
+            GridWorldInfo { is_success },
+            None,
+        );
+        let record = match is_terminated || is_truncated {
+            true => {
+                Record::from_slice(&[("episode_length", RecordValue::Scalar(self.n_steps as f32))])
+            }
+            false => Record::empty(),
+        };
+
+        (step, record)
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        Ok(self.reset_state())
+    }
+
+    /// Returns all actions followed by random ones, which are sampled with a generator
+    /// separate from that of the environment.
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        let mut rng = SmallRng::seed_from_u64(self.seed as _);
+        (0..4)
+            .chain((0..n_random).map(|_| rng.gen_range(0..4)))
+            .map(DiscreteAct)
+            .collect()
+    }
+
+    /// Resets the environment with the random number generator seeded by `ix`,
+    /// so that slips in evaluation episodes are reproducible.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.rng =
+            SmallRng::seed_from_u64(self.seed as u64 ^ (ix as u64).wrapping_mul(0x9e37_79b9));
+        Ok(self.reset_state())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_world() -> Result<()> {
+        let config = GridWorldConfig::from_map(&["S#G", "..X"])?.step_reward(-0.1);
+        let mut env = GridWorld::build(&config, 0)?;
+        assert_eq!(env.reset(None)?, StateObs(0));
+
+        // The wall blocks the agent
+        let (step, _) = env.step(&DiscreteAct(1));
+        assert_eq!((step.obs, step.reward[0]), (StateObs(0), -0.1));
+
+        // The agent falls into the trap
+        env.step(&DiscreteAct(2));
+        env.step(&DiscreteAct(1));
+        let (step, _) = env.step(&DiscreteAct(1));
+        assert_eq!((step.obs, step.reward[0]), (StateObs(5), -1.0));
+        assert_eq!(step.is_terminated[0], 1);
+        assert!(!step.info.is_success);
+
+        assert!(GridWorldConfig::from_map(&["S.", "S."]).is_err());
+        assert!(GridWorldConfig::from_map(&["S.", "."]).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+//! Environments implemented in pure Rust.
+//!
+//! The environments in this crate require neither a Python interpreter nor Atari ROMs,
+//! so that they can be used in CI tests and quickstarts:
+//!
+//! * [`CartPole`] and [`MountainCar`] follow the classic control environments of
+//!   [Gymnasium](https://gymnasium.farama.org/environments/classic_control/), with
+//!   observations of continuous features, [`FeatureObs`].
+//! * [`GridWorld`] is a gridworld configured with a map of start, goals, walls and traps,
+//!   with observations of discrete states, [`StateObs`].
+//! * [`Bandit`] is a multi-armed bandit with Gaussian rewards.
+//!
+//! Actions of all environments are [`DiscreteAct`].
+//!
+//! # Training
+//!
+//! Transitions are collected with [`FeatureStepProcessor`] and [`StateStepProcessor`] into
+//! [`FeatureReplayBuffer`] and [`StateReplayBuffer`], respectively. Observations and actions
+//! are converted into and from [`ndarray`] arrays, as required by the agents of
+//! `border-simple-agent`. For example, `TabularQ` can be trained on [`GridWorld`]:
+//!
+//! ```ignore
+//! let env_config = GridWorldConfig::from_map(&["S..", ".#.", "..G"])?;
+//! let agent_config = TabularQConfig::default().dims(env_config.n_states(), GridWorld::N_ACTIONS);
+//! let mut agent: Box<dyn Agent<GridWorld, StateReplayBuffer>> =
+//!     Box::new(TabularQ::build(agent_config));
+//! let env = GridWorld::build(&env_config, 0)?;
+//! let step_proc = StateStepProcessor::<GridWorld>::build(&SimpleStepProcessorConfig::default());
+//! trainer.train(env, step_proc, &mut agent, &mut buffer, &mut recorder, &mut evaluator)?;
+//! ```
+mod bandit;
+mod base;
+mod cartpole;
+mod grid_world;
+mod mountain_car;
+pub use bandit::{Bandit, BanditConfig, BanditInfo};
+pub use base::{
+    DiscreteAct, DiscreteActBatch, FeatureObs, FeatureObsBatch, FeatureReplayBuffer,
+    FeatureStepProcessor, StateObs, StateObsBatch, StateReplayBuffer, StateStepProcessor,
+};
+pub use cartpole::{CartPole, CartPoleConfig, CartPoleInfo};
+pub use grid_world::{GridWorld, GridWorldConfig, GridWorldInfo};
+pub use mountain_car::{MountainCar, MountainCarConfig, MountainCarInfo};
@@ -0,0 +1,180 @@
+//! MountainCar environment.
+use crate::{DiscreteAct, FeatureObs};
+use anyhow::Result;
+use border_core::{
+    record::{Record, RecordValue},
+    Env, Info, Step,
+};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+const MIN_POSITION: f32 = -1.2;
+const MAX_POSITION: f32 = 0.6;
+const MAX_SPEED: f32 = 0.07;
+const GOAL_POSITION: f32 = 0.5;
+const FORCE: f32 = 0.001;
+const GRAVITY: f32 = 0.0025;
+
+/// Info of [`MountainCar`].
+pub struct MountainCarInfo {
+    /// `true` if the car has reached the goal.
+    pub is_success: bool,
+}
+
+impl Info for MountainCarInfo {}
+
+/// Configuration of [`MountainCar`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct MountainCarConfig {
+    /// The maximum number of steps in an episode, after which the episode is truncated.
+    pub max_steps: usize,
+}
+
+impl Default for MountainCarConfig {
+    fn default() -> Self {
+        Self { max_steps: 200 }
+    }
+}
+
+impl MountainCarConfig {
+    /// Sets the maximum number of steps in an episode.
+    pub fn max_steps(mut self, v: usize) -> Self {
+        self.max_steps = v;
+        self
+    }
+}
+
+/// MountainCar environment.
+///
+/// The dynamics, the reward and the termination condition follow `MountainCar-v0` of
+/// [Gymnasium](https://gymnasium.farama.org/environments/classic_control/mountain_car/).
+/// The observation consists of the position and the velocity of the car. Actions `0`, `1` and
+/// `2` accelerate the car to the left, do not accelerate it and accelerate it to the right,
+/// respectively. The agent receives reward -1 for every step until the car reaches the goal
+/// on the right hill.
+pub struct MountainCar {
+    position: f32,
+    velocity: f32,
+    n_steps: usize,
+    max_steps: usize,
+    seed: i64,
+    rng: SmallRng,
+}
+
+impl MountainCar {
+    /// Dimension of observations.
+    pub const DIM_OBS: usize = 2;
+
+    /// Number of actions.
+    pub const N_ACTIONS: usize = 3;
+
+    fn reset_state(&mut self) -> FeatureObs {
+        self.position = self.rng.gen_range(-0.6..-0.4);
+        self.velocity = 0.0;
+        self.n_steps = 0;
+        FeatureObs(vec![self.position, self.velocity])
+    }
+}
+
+impl Env for MountainCar {
+    type Config = MountainCarConfig;
+    type Obs = FeatureObs;
+    type Act = DiscreteAct;
+    type Info = MountainCarInfo;
+
+    fn build(config: &Self::Config, seed: i64) -> Result<Self> {
+        Ok(Self {
+            position: 0.0,
+            velocity: 0.0,
+            n_steps: 0,
+            max_steps: config.max_steps,
+            seed,
+            rng: SmallRng::seed_from_u64(seed as _),
+        })
+    }
+
+    fn step(&mut self, a: &Self::Act) -> (Step<Self>, Record) {
+        let force = (a.0.clamp(0, 2) - 1) as f32 * FORCE;
+        self.velocity += force - (3.0 * self.position).cos() * GRAVITY;
+        self.velocity = self.velocity.clamp(-MAX_SPEED, MAX_SPEED);
+        self.position = (self.position + self.velocity).clamp(MIN_POSITION, MAX_POSITION);
+        if self.position == MIN_POSITION && self.velocity < 0.0 {
+            self.velocity = 0.0;
+        }
+        self.n_steps += 1;
+
+        let is_terminated = self.position >= GOAL_POSITION && self.velocity >= 0.0;
+        let is_truncated = !is_terminated && self.n_steps >= self.max_steps;
+        let step = Step::new(
+            FeatureObs(vec![self.position, self.velocity]),
+            a.clone(),
+            vec![-1.0],
+            vec![is_terminated as i8],
+            vec![is_truncated as i8],
+            MountainCarInfo {
+                is_success: is_terminated,
+            },
+            None,
+        );
+        let record = match is_terminated || is_truncated {
+            true => {
+                Record::from_slice(&[("episode_length", RecordValue::Scalar(self.n_steps as f32))])
+            }
+            false => Record::empty(),
+        };
+
+        (step, record)
+    }
+
+    fn reset(&mut self, _is_done: Option<&Vec<i8>>) -> Result<Self::Obs> {
+        Ok(self.reset_state())
+    }
+
+    /// Returns all actions followed by random ones, which are sampled with a generator
+    /// separate from that of the environment.
+    fn probe_actions(&mut self, n_random: usize) -> Vec<Self::Act> {
+        let mut rng = SmallRng::seed_from_u64(self.seed as _);
+        (0..3)
+            .chain((0..n_random).map(|_| rng.gen_range(0..3)))
+            .map(DiscreteAct)
+            .collect()
+    }
+
+    /// Resets the environment with the random number generator seeded by `ix`,
+    /// so that evaluation episodes start from the same states.
+    fn reset_with_index(&mut self, ix: usize) -> Result<Self::Obs> {
+        self.rng =
+            SmallRng::seed_from_u64(self.seed as u64 ^ (ix as u64).wrapping_mul(0x9e37_79b9));
+        Ok(self.reset_state())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mountain_car() -> Result<()> {
+        // Constant acceleration cannot climb the hill
+        let mut env = MountainCar::build(&MountainCarConfig::default(), 0)?;
+        env.reset(None)?;
+        let steps = (0..200)
+            .map(|_| env.step(&DiscreteAct(2)).0)
+            .collect::<Vec<_>>();
+        assert!(steps.iter().all(|s| s.is_terminated[0] == 0));
+        assert_eq!(steps[199].is_truncated[0], 1);
+
+        // Accelerating in the direction of the velocity swings the car up to the goal
+        env.reset(None)?;
+        let mut act = DiscreteAct(2);
+        let step = loop {
+            let (step, _) = env.step(&act);
+            if step.is_done() {
+                break step;
+            }
+            act = DiscreteAct(if step.obs.0[1] >= 0.0 { 2 } else { 0 });
+        };
+        assert!(step.info.is_success);
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use anyhow::Result;
+use border_core::{
+    generic_replay_buffer::{SimpleReplayBufferConfig, SimpleStepProcessorConfig},
+    record::{NullRecorder, Recorder},
+    Agent, Configurable, DefaultEvaluator, Env, Evaluator, ReplayBufferBase, StepProcessor,
+    Trainer, TrainerConfig,
+};
+use border_simple_agent::{
+    tabular_q::{TabularQ, TabularQConfig},
+    EpsilonGreedy,
+};
+use border_simple_env::{GridWorld, GridWorldConfig, StateReplayBuffer, StateStepProcessor};
+
+#[test]
+fn test_tabular_q_grid_world() -> Result<()> {
+    let env_config = GridWorldConfig::from_map(&["S..", ".#.", "X.G"])?.max_steps(20);
+    let agent_config = TabularQConfig::default()
+        .dims(env_config.n_states(), GridWorld::N_ACTIONS)
+        .learning_rate(0.5)
+        .discount_factor(0.9)
+        .batch_size(16)
+        .explorer(EpsilonGreedy::new(1.0, 0.1, 500));
+    let mut agent: Box<dyn Agent<GridWorld, StateReplayBuffer>> =
+        Box::new(TabularQ::build(agent_config));
+    let env = GridWorld::build(&env_config, 0)?;
+    let step_proc = StateStepProcessor::<GridWorld>::build(&SimpleStepProcessorConfig::default());
+    let mut buffer = StateReplayBuffer::build(&SimpleReplayBufferConfig::default().capacity(1000));
+    let mut recorder: Box<dyn Recorder<GridWorld, StateReplayBuffer>> =
+        Box::new(NullRecorder::new());
+    let mut evaluator = DefaultEvaluator::<GridWorld>::new(&env_config, 0, 1)?;
+    let mut trainer = Trainer::build(
+        TrainerConfig::default()
+            .max_opts(1000)
+            .warmup_period(100)
+            .eval_interval(500),
+    );
+    trainer.train(
+        env,
+        step_proc,
+        &mut agent,
+        &mut buffer,
+        &mut recorder,
+        &mut evaluator,
+    )?;
+
+    // The trained agent reaches the goal avoiding the trap
+    agent.eval();
+    let (eval_return, _) = evaluator.evaluate(&mut agent)?;
+    assert_eq!(eval_return, 1.0);
+    Ok(())
+}
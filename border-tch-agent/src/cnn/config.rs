@@ -43,4 +43,9 @@ impl OutDim for AtariCnnConfig {
     fn set_out_dim(&mut self, v: i64) {
         self.out_dim = v;
     }
+
+    /// Stacked frames, `[n_stack, 1, 84, 84]`.
+    fn get_in_shape(&self) -> Option<Vec<i64>> {
+        Some(vec![self.n_stack, 1, 84, 84])
+    }
 }
@@ -1,14 +1,17 @@
 //! DQN agent implemented with tch-rs.
-use super::{config::DqnConfig, explorer::DqnExplorer, model::DqnModel};
+use super::{config::DqnConfig, model::DqnModel};
 use crate::{
+    explorer::{Backend, Explorer},
+    export::{export_torchscript, TorchScriptSignature},
     model::{ModelBase, SubModel},
-    util::{track, CriticLoss, OutDim},
+    util::{find_params, params_path, sample_no_grad, track, CriticLoss, OutDim},
 };
 use anyhow::Result;
 use border_core::{
     record::{Record, RecordValue},
     Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
 };
+use rand::{rngs::SmallRng, SeedableRng};
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     convert::{TryFrom, TryInto},
@@ -35,7 +38,8 @@ where
     pub(in crate::dqn) phantom: PhantomData<(E, R)>,
     pub(in crate::dqn) discount_factor: f64,
     pub(in crate::dqn) tau: f64,
-    pub(in crate::dqn) explorer: DqnExplorer,
+    pub(in crate::dqn) explorer: Box<dyn Explorer<Backend>>,
+    pub(in crate::dqn) rng: SmallRng,
     pub(in crate::dqn) device: Device,
     pub(in crate::dqn) n_opts: usize,
     pub(in crate::dqn) double_dqn: bool,
@@ -45,6 +49,8 @@ where
     n_samples_act: usize,
     n_samples_best_act: usize,
     record_verbose_level: usize,
+    noisy_nets: bool,
+    sample_no_grad: bool,
 }
 
 impl<E, Q, R> Dqn<E, Q, R>
@@ -57,6 +63,47 @@ where
     <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input>,
     <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
 {
+    /// Returns the targets of action values, the bootstrapped returns with the target network.
+    fn target(&self, next_obs: &Q::Input, reward: Tensor, is_terminated: Tensor) -> Tensor {
+        no_grad(|| {
+            let q = if self.double_dqn {
+                let x = self.qnet.forward(next_obs);
+                let y = x.argmax(-1, false).unsqueeze(-1);
+                self.qnet_tgt
+                    .forward(next_obs)
+                    .gather(-1, &y, false)
+                    .squeeze()
+            } else {
+                let x = self.qnet_tgt.forward(next_obs);
+                let y = x.argmax(-1, false).unsqueeze(-1);
+                x.gather(-1, &y, false).squeeze()
+            };
+            reward + (1 - is_terminated) * self.discount_factor * q
+        })
+    }
+
+    /// Returns the absolute TD errors of the transitions in a batch, used as their priorities
+    /// in prioritized experience replay.
+    fn td_errors(&mut self, batch: R::Batch) -> Result<Vec<f32>> {
+        let (obs, act, next_obs, reward, is_terminated, _is_truncated, _ixs, _weight) =
+            batch.unpack();
+        let obs = obs.into();
+        let act = act.into().to(self.device);
+        let next_obs = next_obs.into();
+        let reward = Tensor::from_slice(&reward[..]).to(self.device);
+        let is_terminated = Tensor::from_slice(&is_terminated[..]).to(self.device);
+
+        no_grad(|| {
+            let pred = self.qnet.forward(&obs).gather(-1, &act, false).squeeze();
+            let tgt = self.target(&next_obs, reward, is_terminated);
+            let td_errs = match self.clip_td_err {
+                None => (&pred - &tgt).abs(),
+                Some((min, max)) => (&pred - &tgt).abs().clip(min, max),
+            };
+            Ok(Vec::<f32>::try_from(td_errs.reshape(&[-1]))?)
+        })
+    }
+
     fn update_critic(&mut self, buffer: &mut R) -> Record {
         let mut record = Record::empty();
         let batch = buffer.batch(self.batch_size).unwrap();
@@ -88,21 +135,7 @@ where
             record.insert("reward_mean", RecordValue::Scalar(reward_mean));
         }
 
-        let tgt: Tensor = no_grad(|| {
-            let q = if self.double_dqn {
-                let x = self.qnet.forward(&next_obs);
-                let y = x.argmax(-1, false).unsqueeze(-1);
-                self.qnet_tgt
-                    .forward(&next_obs)
-                    .gather(-1, &y, false)
-                    .squeeze()
-            } else {
-                let x = self.qnet_tgt.forward(&next_obs);
-                let y = x.argmax(-1, false).unsqueeze(-1);
-                x.gather(-1, &y, false).squeeze()
-            };
-            reward + (1 - is_terminated) * self.discount_factor * q
-        });
+        let tgt = self.target(&next_obs, reward, is_terminated);
 
         if self.record_verbose_level >= 2 {
             record.insert(
@@ -183,6 +216,10 @@ where
         let mut record_ = Record::empty();
 
         for _ in 0..self.n_updates_per_opt {
+            if self.noisy_nets {
+                self.qnet.reset_noise();
+                self.qnet_tgt.reset_noise();
+            }
             let record = self.update_critic(buffer);
             record_ = record_.merge(record);
         }
@@ -200,6 +237,46 @@ where
     }
 }
 
+impl<E, Q, R> Dqn<E, Q, R>
+where
+    Q: SubModel<Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Sets the explorer used in training, replacing the one given in the configuration.
+    pub fn set_explorer(&mut self, explorer: impl Explorer<Backend> + 'static) {
+        self.explorer = Box::new(explorer);
+    }
+}
+
+impl<E, Q, R> Dqn<E, Q, R>
+where
+    Q: SubModel<Input = Tensor, Output = Tensor>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Exports the Q-network as a TorchScript module.
+    ///
+    /// The module takes observations `obs` and outputs action values `q_values` and
+    /// greedy actions `action`. See [`export`](crate::export) for details.
+    ///
+    /// * `obs` - Example observations with the batch dimension.
+    pub fn export_torchscript(
+        &self,
+        path: impl AsRef<Path>,
+        obs: &Tensor,
+    ) -> Result<TorchScriptSignature> {
+        export_torchscript(
+            path,
+            &[("obs", obs.shallow_clone())],
+            &["q_values", "action"],
+            |xs| {
+                let q = self.qnet.forward(&xs[0]);
+                let a = q.argmax(-1, false);
+                vec![q, a]
+            },
+        )
+    }
+}
+
 impl<E, Q, R> Policy<E> for Dqn<E, Q, R>
 where
     E: Env,
@@ -209,23 +286,20 @@ where
     Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
 {
     fn sample(&mut self, obs: &E::Obs) -> E::Act {
-        no_grad(|| {
+        sample_no_grad(self.sample_no_grad, || {
             let a = self.qnet.forward(&obs.clone().into());
-            let a = if self.train {
+            let a = if self.train && self.noisy_nets {
+                a.argmax(-1, true)
+            } else if self.train {
                 self.n_samples_act += 1;
-                match &mut self.explorer {
-                    DqnExplorer::Softmax(softmax) => softmax.action(&a),
-                    DqnExplorer::EpsilonGreedy(egreedy) => {
-                        if self.record_verbose_level >= 2 {
-                            let (act, best) = egreedy.action_with_best(&a);
-                            if best {
-                                self.n_samples_best_act += 1;
-                            }
-                            act
-                        } else {
-                            egreedy.action(&a)
-                        }
+                if self.record_verbose_level >= 2 {
+                    let (act, best) = self.explorer.action_with_best(&a, &mut self.rng);
+                    if best {
+                        self.n_samples_best_act += 1;
                     }
+                    act
+                } else {
+                    self.explorer.action(&a, &mut self.rng)
                 }
             } else {
                 if fastrand::f32() < 0.01 {
@@ -270,7 +344,8 @@ where
             discount_factor: config.discount_factor,
             tau: config.tau,
             train: config.train,
-            explorer: config.explorer,
+            explorer: Box::new(config.explorer),
+            rng: SmallRng::seed_from_u64(42),
             device,
             n_opts: 0,
             _clip_reward: config.clip_reward,
@@ -280,6 +355,8 @@ where
             n_samples_act: 0,
             n_samples_best_act: 0,
             record_verbose_level: config.record_verbose_level,
+            noisy_nets: config.noisy_nets,
+            sample_no_grad: config.sample_no_grad,
             phantom: PhantomData,
         }
     }
@@ -338,26 +415,52 @@ where
             self.n_samples_best_act = 0;
         }
 
+        if let Some(lr) = self.qnet.learning_rate() {
+            record.insert("lr", RecordValue::Scalar(lr as f32));
+        }
+        if let Some(grad_norm) = self.qnet.grad_norm() {
+            record.insert("grad_norm", RecordValue::Scalar(grad_norm));
+        }
+
         record
     }
 
+    /// Returns the action values for the observation as `q_values`.
+    fn probe(&mut self, obs: &E::Obs) -> Record {
+        let q = no_grad(|| self.qnet.forward(&obs.clone().into()));
+        let q = Vec::<f32>::try_from(&q.flatten(0, -1)).expect("Failed to convert Tensor to f32");
+        Record::from_slice(&[("q_values", RecordValue::Array1(q))])
+    }
+
+    /// Returns the absolute TD errors of the transitions as their priorities.
+    fn priorities(&mut self, batch: R::Batch) -> Result<Option<Vec<f32>>> {
+        Ok(Some(self.td_errors(batch)?))
+    }
+
     /// Save model parameters in the given directory.
     ///
-    /// The parameters of the model are saved as `qnet.pt`.
-    /// The parameters of the target model are saved as `qnet_tgt.pt`.
+    /// The parameters of the model are saved as `qnet.safetensors`.
+    /// The parameters of the target model are saved as `qnet_tgt.safetensors`.
+    /// [`ModelManifest`](border_core::ModelManifest) is saved as `manifest.yaml`.
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
         // TODO: consider to rename the path if it already exists
         fs::create_dir_all(&path)?;
-        let path1 = path.join("qnet.pt.tch").to_path_buf();
-        let path2 = path.join("qnet_tgt.pt.tch").to_path_buf();
+        let path1 = params_path(path.join("qnet"));
+        let path2 = params_path(path.join("qnet_tgt"));
         self.qnet.save(&path1)?;
         self.qnet_tgt.save(&path2)?;
-        Ok(vec![path1, path2])
+        let mut paths = vec![path1, path2];
+        self.qnet
+            .manifest()
+            .clone()
+            .model_type("Dqn")
+            .save_in(path, &mut paths)?;
+        Ok(paths)
     }
 
     fn load_params(&mut self, path: &Path) -> Result<()> {
-        self.qnet.load(path.join("qnet.pt.tch").as_path())?;
-        self.qnet_tgt.load(path.join("qnet_tgt.pt.tch").as_path())?;
+        self.qnet.load(find_params(path.join("qnet")))?;
+        self.qnet_tgt.load(find_params(path.join("qnet_tgt")))?;
         Ok(())
     }
 
@@ -386,17 +489,23 @@ where
     <R::Batch as TransitionBatch>::ObsBatch: Into<Q::Input>,
     <R::Batch as TransitionBatch>::ActBatch: Into<Tensor>,
 {
-    type ModelInfo = NamedTensors;
+    /// Parameters of the Q-network and the target network.
+    ///
+    /// The target network is used to compute the priorities of samples in actors.
+    type ModelInfo = (NamedTensors, NamedTensors);
 
     fn model_info(&self) -> (usize, Self::ModelInfo) {
         (
             self.n_opts,
-            NamedTensors::copy_from(self.qnet.get_var_store()),
+            (
+                NamedTensors::copy_from(self.qnet.get_var_store()),
+                NamedTensors::copy_from(self.qnet_tgt.get_var_store()),
+            ),
         )
     }
 
     fn sync_model(&mut self, model_info: &Self::ModelInfo) {
-        let vs = self.qnet.get_var_store_mut();
-        model_info.copy_to(vs);
+        model_info.0.copy_to(self.qnet.get_var_store_mut());
+        model_info.1.copy_to(self.qnet_tgt.get_var_store_mut());
     }
 }
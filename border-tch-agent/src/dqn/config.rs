@@ -1,12 +1,10 @@
 //! Configuration of DQN agent.
-use super::{
-    explorer::{DqnExplorer, Softmax},
-    DqnModelConfig,
-};
+use super::DqnModelConfig;
 use crate::{
+    explorer::{DiscreteExplorer, Softmax},
     model::SubModel,
     opt::OptimizerConfig,
-    util::{CriticLoss, OutDim},
+    util::{default_true, CriticLoss, OutDim},
     Device,
 };
 use anyhow::Result;
@@ -35,7 +33,7 @@ where
     pub discount_factor: f64,
     pub tau: f64,
     pub train: bool,
-    pub explorer: DqnExplorer,
+    pub explorer: DiscreteExplorer,
     #[serde(default)]
     pub clip_reward: Option<f64>,
     #[serde(default)]
@@ -44,6 +42,11 @@ where
     pub device: Option<Device>,
     pub critic_loss: CriticLoss,
     pub record_verbose_level: usize,
+    #[serde(default)]
+    pub noisy_nets: bool,
+    /// If `true`, actions are sampled without recording operations for autograd.
+    #[serde(default = "default_true")]
+    pub sample_no_grad: bool,
     pub phantom: PhantomData<Q>,
 }
 
@@ -68,6 +71,8 @@ where
             device: self.device.clone(),
             critic_loss: self.critic_loss.clone(),
             record_verbose_level: self.record_verbose_level,
+            noisy_nets: self.noisy_nets,
+            sample_no_grad: self.sample_no_grad,
             phantom: PhantomData,
         }
     }
@@ -89,7 +94,7 @@ where
             tau: 0.005,
             train: false,
             // replay_burffer_capacity: 100,
-            explorer: DqnExplorer::Softmax(Softmax::new()),
+            explorer: DiscreteExplorer::Softmax(Softmax::new()),
             // expr_sampling: ExperienceSampling::Uniform,
             clip_reward: None,
             double_dqn: false,
@@ -97,6 +102,8 @@ where
             device: None,
             critic_loss: CriticLoss::Mse,
             record_verbose_level: 0,
+            noisy_nets: false,
+            sample_no_grad: true,
             phantom: PhantomData,
         }
     }
@@ -138,7 +145,7 @@ where
     }
 
     /// Explorer.
-    pub fn explorer(mut self, v: DqnExplorer) -> Self {
+    pub fn explorer(mut self, v: DiscreteExplorer) -> Self {
         self.explorer = v;
         self
     }
@@ -198,6 +205,22 @@ where
         self
     }
 
+    /// Sets the flag to explore with noisy networks instead of the explorer.
+    ///
+    /// If `true`, the greedy action is taken in training and the noise of the Q-networks is
+    /// resampled at every parameter update. The Q-network should have noisy layers,
+    /// e.g., [`MlpConfig::noisy`](crate::mlp::MlpConfig::noisy).
+    pub fn noisy_nets(mut self, v: bool) -> Self {
+        self.noisy_nets = v;
+        self
+    }
+
+    /// Sets the flag to sample actions without recording operations for autograd.
+    pub fn sample_no_grad(mut self, v: bool) -> Self {
+        self.sample_no_grad = v;
+        self
+    }
+
     /// Loads [`DqnConfig`] from YAML file.
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let path_ = path.as_ref().to_owned();
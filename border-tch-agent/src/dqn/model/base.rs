@@ -2,10 +2,10 @@ use super::DqnModelConfig;
 use crate::{
     model::{ModelBase, SubModel},
     opt::{Optimizer, OptimizerConfig},
-    util::OutDim,
+    util::{manifest, OutDim},
 };
 use anyhow::Result;
-use border_core::record::Record;
+use border_core::{record::Record, ModelManifest};
 use log::{info, trace};
 use serde::{de::DeserializeOwned, Serialize};
 use std::{marker::PhantomData, path::Path};
@@ -35,6 +35,9 @@ where
     opt_config: OptimizerConfig,
     opt: Optimizer,
 
+    // Manifest of saved parameters
+    manifest: ModelManifest,
+
     phantom: PhantomData<Q>,
 }
 
@@ -47,15 +50,18 @@ where
         let out_dim = config.q_config.as_ref().unwrap().get_out_dim();
         let opt_config = config.opt_config.clone();
         let var_store = nn::VarStore::new(device);
-        let q = Q::build(&var_store, config.q_config.unwrap());
+        let q_config = config.q_config.unwrap();
+        let manifest = manifest(&q_config).unwrap();
+        let q = Q::build(&var_store, q_config);
 
-        Self::_build(device, out_dim, opt_config, q, var_store, None)
+        Self::_build(device, out_dim, opt_config, manifest, q, var_store, None)
     }
 
     fn _build(
         device: Device,
         out_dim: i64,
         opt_config: OptimizerConfig,
+        manifest: ModelManifest,
         q: Q,
         mut var_store: nn::VarStore,
         var_store_src: Option<&nn::VarStore>,
@@ -75,6 +81,7 @@ where
             var_store,
             opt,
             q,
+            manifest,
             phantom: PhantomData,
         }
     }
@@ -86,9 +93,19 @@ where
         a
     }
 
+    /// Resamples the noise of noisy layers in the model.
+    pub fn reset_noise(&mut self) {
+        self.q.reset_noise();
+    }
+
     pub fn param_stats(&self) -> Record {
         crate::util::param_stats(&self.var_store)
     }
+
+    /// Returns [`ModelManifest`] of the model.
+    pub fn manifest(&self) -> &ModelManifest {
+        &self.manifest
+    }
 }
 
 impl<Q> Clone for DqnModel<Q>
@@ -107,6 +124,7 @@ where
             device,
             out_dim,
             opt_config,
+            self.manifest.clone(),
             q,
             var_store,
             Some(&self.var_store),
@@ -123,6 +141,14 @@ where
         self.opt.backward_step(loss);
     }
 
+    fn learning_rate(&self) -> Option<f64> {
+        self.opt.learning_rate()
+    }
+
+    fn grad_norm(&self) -> Option<f32> {
+        self.opt.grad_norm()
+    }
+
     fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
         &mut self.var_store
     }
@@ -0,0 +1,32 @@
+//! Exploration strategies of agents with discrete actions.
+//!
+//! Explorers are defined in [`border_core::explorer`], and [`Backend`] converts action values
+//! and actions of them from and into [`Tensor`]s. Built-in explorers are selected with
+//! [`DiscreteExplorer`] in configurations of agents, and any type implementing
+//! `Explorer<Backend>` can be given to agents after they are built, e.g., with
+//! [`Dqn::set_explorer()`](crate::dqn::Dqn::set_explorer).
+use border_core::explorer::ExplorerBackend;
+pub use border_core::explorer::{DiscreteExplorer, EpsilonGreedy, Explorer, Softmax, Ucb};
+use std::convert::TryFrom;
+use tch::{Device, Kind, Tensor};
+
+/// Marker type of the tch backend of explorers.
+pub struct Backend;
+
+impl ExplorerBackend for Backend {
+    type Tensor = Tensor;
+
+    fn values(a: &Tensor) -> Vec<Vec<f32>> {
+        let a = a.to_device(Device::Cpu).to_kind(Kind::Float);
+        let n_actions = a.size()[1] as usize;
+        let v = Vec::<f32>::try_from(a.flatten(0, -1)).unwrap();
+        v.chunks(n_actions).map(|c| c.to_vec()).collect()
+    }
+
+    /// Actions have the shape `[batch_size, 1]` as outputs of `argmax(-1, true)`.
+    fn actions(a: &Tensor, acts: Vec<i64>) -> Tensor {
+        Tensor::from_slice(&acts)
+            .unsqueeze(-1)
+            .to_device(a.device())
+    }
+}
@@ -0,0 +1,156 @@
+//! Export of policies as TorchScript modules.
+//!
+//! A policy is traced with example inputs and saved as a TorchScript module, which can be
+//! loaded with `torch.jit.load()` in Python or `torch::jit::load()` in libtorch.
+//! TorchScript modules do not keep the names of inputs and outputs, so they are saved with
+//! the shapes and the types of tensors in a YAML file next to the module, e.g.,
+//! `policy.yaml` for `policy.pt`. The first dimension of the shapes, the batch dimension,
+//! is saved as `-1`.
+use anyhow::Result;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufReader, Write},
+    path::{Path, PathBuf},
+};
+use tch::{no_grad, CModule, Tensor};
+
+/// Name, shape and type of an input or output of an exported module.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct TensorSpec {
+    /// Name of the tensor.
+    pub name: String,
+
+    /// Shape of the tensor, `-1` for the batch dimension.
+    pub shape: Vec<i64>,
+
+    /// Type of the elements, e.g., `Float`.
+    pub kind: String,
+}
+
+impl TensorSpec {
+    fn new(name: &str, t: &Tensor) -> Self {
+        let mut shape = t.size();
+        if let Some(n) = shape.first_mut() {
+            *n = -1;
+        }
+        Self {
+            name: name.to_string(),
+            shape,
+            kind: format!("{:?}", t.kind()),
+        }
+    }
+}
+
+/// Signature of an exported module, the inputs and outputs of its `forward` method.
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct TorchScriptSignature {
+    /// Inputs in the order of arguments.
+    pub inputs: Vec<TensorSpec>,
+
+    /// Outputs in the order of the returned tuple.
+    pub outputs: Vec<TensorSpec>,
+}
+
+impl TorchScriptSignature {
+    /// Returns the path of the signature file of the given module file.
+    pub fn path(module_path: impl AsRef<Path>) -> PathBuf {
+        module_path.as_ref().with_extension("yaml")
+    }
+
+    /// Loads the signature of the given module file.
+    pub fn load(module_path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(Self::path(module_path))?;
+        let rdr = BufReader::new(file);
+        let b = serde_yaml::from_reader(rdr)?;
+        Ok(b)
+    }
+
+    /// Saves the signature of the given module file.
+    pub fn save(&self, module_path: impl AsRef<Path>) -> Result<()> {
+        let mut file = File::create(Self::path(module_path))?;
+        file.write_all(serde_yaml::to_string(&self)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+/// Traces a function with example inputs and saves it as a TorchScript module.
+///
+/// The function is traced as the `forward` method of the module, and its signature
+/// is saved with [`TorchScriptSignature::save()`].
+///
+/// * `path` - Path of the module file, typically with extension `pt`.
+/// * `inputs` - Names and example values of the inputs.
+/// * `output_names` - Names of the outputs of `f`.
+/// * `f` - Function to be traced. It must not depend on the values of the inputs
+///   through control flow, which is not captured by tracing.
+pub fn export_torchscript<F>(
+    path: impl AsRef<Path>,
+    inputs: &[(&str, Tensor)],
+    output_names: &[&str],
+    mut f: F,
+) -> Result<TorchScriptSignature>
+where
+    F: FnMut(&[Tensor]) -> Vec<Tensor>,
+{
+    let path = path.as_ref();
+    let (names, inputs): (Vec<_>, Vec<_>) = inputs
+        .iter()
+        .map(|(name, t)| (*name, t.shallow_clone()))
+        .unzip();
+
+    let outputs = no_grad(|| f(&inputs));
+    if outputs.len() != output_names.len() {
+        return Err(anyhow::anyhow!(
+            "The number of outputs is {}, but {} names are given",
+            outputs.len(),
+            output_names.len()
+        ));
+    }
+    let signature = TorchScriptSignature {
+        inputs: names
+            .iter()
+            .zip(inputs.iter())
+            .map(|(name, t)| TensorSpec::new(name, t))
+            .collect(),
+        outputs: output_names
+            .iter()
+            .zip(outputs.iter())
+            .map(|(name, t)| TensorSpec::new(name, t))
+            .collect(),
+    };
+
+    let module = no_grad(|| CModule::create_by_tracing("policy", "forward", &inputs, &mut f))?;
+    module.save(path)?;
+    signature.save(path)?;
+    info!("Export TorchScript module to {:?}", path);
+
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_export_torchscript() -> Result<()> {
+        let dir = TempDir::new("export")?;
+        let path = dir.path().join("policy.pt");
+        let obs = Tensor::from_slice(&[1f32, 2., 3., 4.]).reshape(&[2, 2]);
+        let signature =
+            export_torchscript(&path, &[("obs", obs.shallow_clone())], &["action"], |xs| {
+                vec![&xs[0] * 2.0]
+            })?;
+
+        assert_eq!(signature, TorchScriptSignature::load(&path)?);
+        assert_eq!(signature.inputs[0].shape, vec![-1, 2]);
+        assert_eq!(signature.outputs[0].name, "action");
+
+        let module = CModule::load(&path)?;
+        let act = module.forward_ts(&[obs.shallow_clone()])?;
+        assert!(act.equal(&(&obs * 2.0)));
+        Ok(())
+    }
+}
@@ -1,8 +1,9 @@
 //! IQN agent implemented with tch-rs.
-use super::{average, IqnConfig, IqnExplorer, IqnModel, IqnSample};
+use super::{average, IqnConfig, IqnModel, IqnSample};
 use crate::{
+    explorer::{Backend, Explorer},
     model::{ModelBase, SubModel},
-    util::{quantile_huber_loss, track, OutDim},
+    util::{find_params, params_path, quantile_huber_loss, sample_no_grad, track, OutDim},
 };
 use anyhow::Result;
 use border_core::{
@@ -10,6 +11,7 @@ use border_core::{
     Agent, Configurable, Env, Policy, ReplayBufferBase, TransitionBatch,
 };
 use log::trace;
+use rand::{rngs::SmallRng, SeedableRng};
 use serde::{de::DeserializeOwned, Serialize};
 use std::{
     convert::TryFrom,
@@ -43,9 +45,24 @@ where
     pub(in crate::iqn) sample_percents_pred: IqnSample,
     pub(in crate::iqn) sample_percents_tgt: IqnSample,
     pub(in crate::iqn) sample_percents_act: IqnSample,
-    pub(in crate::iqn) explorer: IqnExplorer,
+    pub(in crate::iqn) explorer: Box<dyn Explorer<Backend>>,
+    pub(in crate::iqn) rng: SmallRng,
     pub(in crate::iqn) device: Device,
     pub(in crate::iqn) n_opts: usize,
+    pub(in crate::iqn) sample_no_grad: bool,
+}
+
+impl<E, F, M, R> Iqn<E, F, M, R>
+where
+    F: SubModel<Output = Tensor>,
+    M: SubModel<Input = Tensor, Output = Tensor>,
+    F::Config: DeserializeOwned + Serialize,
+    M::Config: DeserializeOwned + Serialize,
+{
+    /// Sets the explorer used in training, replacing the one given in the configuration.
+    pub fn set_explorer(&mut self, explorer: impl Explorer<Backend> + 'static) {
+        self.explorer = Box::new(explorer);
+    }
 }
 
 impl<E, F, M, R> Iqn<E, F, M, R>
@@ -205,7 +222,7 @@ where
         // Do not support vectorized env
         let batch_size = 1;
 
-        let a = no_grad(|| {
+        let a = sample_no_grad(self.sample_no_grad, || {
             let action_value = average(
                 batch_size,
                 &obs.clone().into(),
@@ -215,10 +232,7 @@ where
             );
 
             if self.train {
-                match &mut self.explorer {
-                    IqnExplorer::Softmax(softmax) => softmax.action(&action_value),
-                    IqnExplorer::EpsilonGreedy(egreedy) => egreedy.action(action_value),
-                }
+                self.explorer.action(&action_value, &mut self.rng)
             } else {
                 action_value.argmax(-1, true)
             }
@@ -262,9 +276,11 @@ where
             sample_percents_tgt: config.sample_percents_tgt,
             sample_percents_act: config.sample_percents_act,
             train: config.train,
-            explorer: config.explorer,
+            explorer: Box::new(config.explorer),
+            rng: SmallRng::seed_from_u64(42),
             device,
             n_opts: 0,
+            sample_no_grad: config.sample_no_grad,
             phantom: PhantomData,
         }
     }
@@ -303,16 +319,22 @@ where
     fn save_params(&self, path: &Path) -> Result<Vec<PathBuf>> {
         // TODO: consider to rename the path if it already exists
         fs::create_dir_all(&path)?;
-        let path1 = path.join("iqn.pt.tch").to_path_buf();
-        let path2 = path.join("iqn_tgt.pt.tch").to_path_buf();
+        let path1 = params_path(path.join("iqn"));
+        let path2 = params_path(path.join("iqn_tgt"));
         self.iqn.save(&path1)?;
         self.iqn_tgt.save(&path2)?;
-        Ok(vec![path1, path2])
+        let mut paths = vec![path1, path2];
+        self.iqn
+            .manifest()
+            .clone()
+            .model_type("Iqn")
+            .save_in(path, &mut paths)?;
+        Ok(paths)
     }
 
     fn load_params(&mut self, path: &Path) -> Result<()> {
-        self.iqn.load(path.join("iqn.pt.tch").as_path())?;
-        self.iqn_tgt.load(path.join("iqn_tgt.pt.tch").as_path())?;
+        self.iqn.load(find_params(path.join("iqn")))?;
+        self.iqn_tgt.load(find_params(path.join("iqn_tgt")))?;
         Ok(())
     }
 
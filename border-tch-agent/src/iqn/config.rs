@@ -1,9 +1,9 @@
 //! Configuration of IQN agent.
 use super::{IqnModelConfig, IqnSample};
 use crate::{
-    iqn::{IqnExplorer, Softmax},
+    explorer::{DiscreteExplorer, Softmax},
     model::SubModel,
-    util::OutDim,
+    util::{default_true, OutDim},
     Device,
 };
 use anyhow::Result;
@@ -32,11 +32,14 @@ where
     pub discount_factor: f64,
     pub tau: f64,
     pub train: bool,
-    pub explorer: IqnExplorer,
+    pub explorer: DiscreteExplorer,
     pub sample_percents_pred: IqnSample,
     pub sample_percents_tgt: IqnSample,
     pub sample_percents_act: IqnSample,
     pub device: Option<Device>,
+    /// If `true`, actions are sampled without recording operations for autograd.
+    #[serde(default = "default_true")]
+    pub sample_no_grad: bool,
     phantom: PhantomData<(F, M)>,
 }
 
@@ -59,9 +62,10 @@ where
             sample_percents_tgt: IqnSample::Uniform8,
             sample_percents_act: IqnSample::Const32,
             train: false,
-            explorer: IqnExplorer::Softmax(Softmax::new()),
-            // explorer: IqnExplorer::EpsilonGreedy(EpsilonGreedy::default()),
+            explorer: DiscreteExplorer::Softmax(Softmax::new()),
+            // explorer: DiscreteExplorer::EpsilonGreedy(EpsilonGreedy::default()),
             device: None,
+            sample_no_grad: true,
             phantom: PhantomData,
         }
     }
@@ -111,7 +115,7 @@ where
     }
 
     /// Set explorer.
-    pub fn explorer(mut self, v: IqnExplorer) -> Self {
+    pub fn explorer(mut self, v: DiscreteExplorer) -> Self {
         self.explorer = v;
         self
     }
@@ -147,6 +151,12 @@ where
         self
     }
 
+    /// Sets the flag to sample actions without recording operations for autograd.
+    pub fn sample_no_grad(mut self, v: bool) -> Self {
+        self.sample_no_grad = v;
+        self
+    }
+
     /// Constructs [`IqnConfig`] from YAML file.
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let file = File::open(path)?;
@@ -228,6 +238,7 @@ where
             train: self.train,
             explorer: self.explorer.clone(),
             device: self.device.clone(),
+            sample_no_grad: self.sample_no_grad,
             phantom: PhantomData,
         }
     }
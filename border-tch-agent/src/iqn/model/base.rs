@@ -6,6 +6,7 @@ use crate::{
     util::OutDim,
 };
 use anyhow::{Context, Result};
+use border_core::ModelManifest;
 use log::{info, trace};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{default::Default, f64::consts::PI, marker::PhantomData, path::Path};
@@ -53,6 +54,9 @@ where
     opt_config: OptimizerConfig,
     opt: Optimizer,
 
+    // Manifest of saved parameters
+    manifest: ModelManifest,
+
     phantom: PhantomData<(F, M)>,
 }
 
@@ -75,6 +79,7 @@ where
         let out_dim = m_config.get_out_dim();
         let opt_config = config.opt_config;
         let var_store = nn::VarStore::new(device);
+        let manifest = Self::manifest_(&f_config, &m_config)?;
 
         // Feature extractor
         let psi = F::build(&var_store, f_config);
@@ -106,6 +111,7 @@ where
             f,
             opt_config,
             opt,
+            manifest,
             phantom: PhantomData,
         })
     }
@@ -122,6 +128,7 @@ where
         let out_dim = m_config.get_out_dim();
         let opt_config = config.opt_config.clone();
         let var_store = nn::VarStore::new(device);
+        let manifest = Self::manifest_(&f_config, &m_config).unwrap();
 
         // Feature extractor
         let psi = F::build(&var_store, f_config);
@@ -154,10 +161,23 @@ where
             f,
             opt_config,
             opt,
+            manifest,
             phantom: PhantomData,
         }
     }
 
+    // Manifest with the hash of the configurations of the sub models.
+    fn manifest_(f_config: &F::Config, m_config: &M::Config) -> Result<ModelManifest> {
+        ModelManifest::new("tch", "")
+            .act_dim(m_config.get_out_dim())
+            .config(&(f_config, m_config))
+    }
+
+    /// Returns [`ModelManifest`] of the model.
+    pub fn manifest(&self) -> &ModelManifest {
+        &self.manifest
+    }
+
     // Cosine embedding.
     fn cos_embed_nn(var_store: &VarStore, feature_dim: i64, embed_dim: i64) -> nn::Sequential {
         let p = &var_store.root();
@@ -247,6 +267,7 @@ where
         let embed_dim = self.embed_dim;
         let out_dim = self.out_dim;
         let opt_config = self.opt_config.clone();
+        let manifest = self.manifest.clone();
         let mut var_store = nn::VarStore::new(device);
 
         // Feature extractor
@@ -281,6 +302,7 @@ where
             f,
             opt_config,
             opt,
+            manifest,
             phantom: PhantomData,
         }
     }
@@ -1,6 +1,8 @@
 //! RL agents implemented with [tch](https://crates.io/crates/tch).
 pub mod cnn;
 pub mod dqn;
+pub mod explorer;
+pub mod export;
 pub mod iqn;
 pub mod mlp;
 pub mod model;
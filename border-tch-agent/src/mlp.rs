@@ -2,9 +2,11 @@
 mod base;
 mod config;
 mod mlp2;
+mod noisy_linear;
 pub use base::Mlp;
 pub use config::MlpConfig;
 pub use mlp2::Mlp2;
+pub use noisy_linear::NoisyLinear;
 use tch::nn;
 
 fn mlp(prefix: &str, var_store: &nn::VarStore, config: &MlpConfig) -> nn::Sequential {
@@ -1,43 +1,53 @@
-use super::{mlp, MlpConfig};
+use super::{mlp, MlpConfig, NoisyLinear};
 use crate::model::{SubModel, SubModel2};
 use tch::{nn, nn::Module, Device, Tensor};
 
 /// Multilayer perceptron with ReLU activation function.
+///
+/// If [`MlpConfig::noisy`] is `true`, [`NoisyLinear`] layers are used.
 pub struct Mlp {
     config: MlpConfig,
     device: Device,
     seq: nn::Sequential,
+
+    // Noisy layers sharing the noise with those in `seq`
+    noisy_layers: Vec<NoisyLinear>,
 }
 
 impl Mlp {
-    fn create_net(var_store: &nn::VarStore, config: &MlpConfig) -> nn::Sequential {
+    fn create_net(
+        var_store: &nn::VarStore,
+        config: &MlpConfig,
+    ) -> (nn::Sequential, Vec<NoisyLinear>) {
         let p = &(var_store.root() / "mlp");
         let mut seq = nn::seq();
+        let mut noisy_layers = vec![];
         let mut in_dim = config.in_dim;
+        let mut add_linear = |seq: nn::Sequential, i: usize, in_dim: i64, out_dim: i64| {
+            let p = p / format!("{}{}", "ln", i);
+            match config.noisy {
+                false => seq.add(nn::linear(p, in_dim, out_dim, Default::default())),
+                true => {
+                    let layer = NoisyLinear::new(p, in_dim, out_dim, 0.5);
+                    noisy_layers.push(layer.shallow_clone());
+                    seq.add(layer)
+                }
+            }
+        };
 
         for (i, &out_dim) in config.units.iter().enumerate() {
-            seq = seq.add(nn::linear(
-                p / format!("{}{}", "ln", i),
-                in_dim,
-                out_dim,
-                Default::default(),
-            ));
+            seq = add_linear(seq, i, in_dim, out_dim);
             seq = seq.add_fn(|x| x.relu());
             in_dim = out_dim;
         }
 
-        seq = seq.add(nn::linear(
-            p / format!("{}{}", "ln", config.units.len()),
-            in_dim,
-            config.out_dim,
-            Default::default(),
-        ));
+        seq = add_linear(seq, config.units.len(), in_dim, config.out_dim);
 
         if config.activation_out {
             seq = seq.add_fn(|x| x.relu());
         }
 
-        seq
+        (seq, noisy_layers)
     }
 }
 
@@ -52,26 +62,32 @@ impl SubModel for Mlp {
 
     fn build(var_store: &nn::VarStore, config: Self::Config) -> Self {
         let device = var_store.device();
-        let seq = Self::create_net(var_store, &config);
+        let (seq, noisy_layers) = Self::create_net(var_store, &config);
 
         Self {
             config,
             device,
             seq,
+            noisy_layers,
         }
     }
 
     fn clone_with_var_store(&self, var_store: &nn::VarStore) -> Self {
         let config = self.config.clone();
         let device = var_store.device();
-        let seq = Self::create_net(&var_store, &config);
+        let (seq, noisy_layers) = Self::create_net(&var_store, &config);
 
         Self {
             config,
             device,
             seq,
+            noisy_layers,
         }
     }
+
+    fn reset_noise(&mut self) {
+        self.noisy_layers.iter().for_each(|l| l.reset_noise());
+    }
 }
 
 impl SubModel2 for Mlp {
@@ -103,18 +119,20 @@ impl SubModel2 for Mlp {
             config,
             device: var_store.device(),
             seq,
+            noisy_layers: vec![],
         }
     }
 
     fn clone_with_var_store(&self, var_store: &nn::VarStore) -> Self {
         let config = self.config.clone();
         let device = var_store.device();
-        let seq = Self::create_net(&var_store, &config);
+        let (seq, noisy_layers) = Self::create_net(&var_store, &config);
 
         Self {
             config,
             device,
             seq,
+            noisy_layers,
         }
     }
 }
@@ -0,0 +1,83 @@
+use std::borrow::Borrow;
+use tch::{nn, no_grad, Device, Kind, Tensor};
+
+/// Linear layer with factorized Gaussian noise on its weights and biases.
+///
+/// Weights are `mu + sigma * eps`, where `mu` and `sigma` are trainable and `eps` is
+/// the noise, resampled with [`NoisyLinear::reset_noise()`].
+/// See <https://arxiv.org/abs/1706.10295>.
+#[derive(Debug)]
+pub struct NoisyLinear {
+    ws_mu: Tensor,
+    ws_sigma: Tensor,
+    bs_mu: Tensor,
+    bs_sigma: Tensor,
+    eps_in: Tensor,
+    eps_out: Tensor,
+}
+
+impl NoisyLinear {
+    /// Constructs a noisy linear layer.
+    ///
+    /// * `sigma0` - Initial scale of the noise, which is 0.5 in the paper.
+    pub fn new<'a, T: Borrow<nn::Path<'a>>>(vs: T, in_dim: i64, out_dim: i64, sigma0: f64) -> Self {
+        let vs = vs.borrow();
+        let bound = 1. / (in_dim as f64).sqrt();
+        let init_mu = nn::Init::Uniform {
+            lo: -bound,
+            up: bound,
+        };
+        let init_sigma = nn::Init::Const(sigma0 * bound);
+        let device = vs.device();
+        let layer = Self {
+            ws_mu: vs.var("weight_mu", &[out_dim, in_dim], init_mu),
+            ws_sigma: vs.var("weight_sigma", &[out_dim, in_dim], init_sigma),
+            bs_mu: vs.var("bias_mu", &[out_dim], init_mu),
+            bs_sigma: vs.var("bias_sigma", &[out_dim], init_sigma),
+            eps_in: Tensor::zeros(&[in_dim], (Kind::Float, device)),
+            eps_out: Tensor::zeros(&[out_dim], (Kind::Float, device)),
+        };
+        layer.reset_noise();
+        layer
+    }
+
+    /// Samples noise `sign(x) * sqrt(|x|)` with `x ~ N(0, 1)`.
+    fn noise(n: i64, device: Device) -> Tensor {
+        let x = Tensor::randn(&[n], (Kind::Float, device));
+        x.sign() * x.abs().sqrt()
+    }
+
+    /// Resamples the noise.
+    ///
+    /// The noise is overwritten in place, so it is shared with the layers obtained by
+    /// [`NoisyLinear::shallow_clone()`].
+    pub fn reset_noise(&self) {
+        no_grad(|| {
+            let mut eps_in = self.eps_in.shallow_clone();
+            let mut eps_out = self.eps_out.shallow_clone();
+            eps_in.copy_(&Self::noise(eps_in.size()[0], eps_in.device()));
+            eps_out.copy_(&Self::noise(eps_out.size()[0], eps_out.device()));
+        });
+    }
+
+    /// Returns a layer sharing the parameters and the noise with this layer.
+    pub fn shallow_clone(&self) -> Self {
+        Self {
+            ws_mu: self.ws_mu.shallow_clone(),
+            ws_sigma: self.ws_sigma.shallow_clone(),
+            bs_mu: self.bs_mu.shallow_clone(),
+            bs_sigma: self.bs_sigma.shallow_clone(),
+            eps_in: self.eps_in.shallow_clone(),
+            eps_out: self.eps_out.shallow_clone(),
+        }
+    }
+}
+
+impl nn::Module for NoisyLinear {
+    fn forward(&self, xs: &Tensor) -> Tensor {
+        let eps_w = self.eps_out.unsqueeze(1) * self.eps_in.unsqueeze(0);
+        let ws = &self.ws_mu + &self.ws_sigma * eps_w;
+        let bs = &self.bs_mu + &self.bs_sigma * &self.eps_out;
+        xs.linear(&ws, Some(&bs))
+    }
+}
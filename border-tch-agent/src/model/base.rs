@@ -19,6 +19,17 @@ pub trait ModelBase {
 
     /// Load parameters of the neural network.
     fn load<T: AsRef<Path>>(&mut self, path: T) -> Result<()>;
+
+    /// Returns the current learning rate of the optimizer if it is available.
+    fn learning_rate(&self) -> Option<f64> {
+        None
+    }
+
+    /// Returns the global norm of gradients before clipping in the last update,
+    /// if gradients are clipped.
+    fn grad_norm(&self) -> Option<f32> {
+        None
+    }
 }
 
 /// Neural networks with a single input and a single output.
@@ -82,6 +93,13 @@ pub trait SubModel {
 
     /// A generalized forward function.
     fn forward(&self, input: &Self::Input) -> Self::Output;
+
+    /// Resamples the noise of noisy layers, e.g., [`NoisyLinear`].
+    ///
+    /// It does nothing by default.
+    ///
+    /// [`NoisyLinear`]: crate::mlp::NoisyLinear
+    fn reset_noise(&mut self) {}
 }
 
 /// Neural network model that can be initialized with [`VarStore`] and configuration.
@@ -1,10 +1,13 @@
 //! Optimizers.
+use crate::util::zero_grad_set_to_none;
 use anyhow::Result;
-use core::f64;
+use border_core::LrScheduler;
+pub use border_core::{LrDecay, SchedulerConfig};
 use serde::{Deserialize, Serialize};
 use tch::{
     // nn,
     nn::{Adam, AdamW, Optimizer as Optimizer_, OptimizerConfig as OptimizerConfig_, VarStore},
+    Kind,
     Tensor,
 };
 
@@ -25,12 +28,57 @@ pub enum OptimizerConfig {
         eps: f64,
         amsgrad: bool,
     },
+
+    /// An optimizer with the learning rate scheduled over optimization steps.
+    ///
+    /// The learning rate of `optimizer` is used as the base learning rate of the schedule.
+    Scheduled {
+        optimizer: Box<OptimizerConfig>,
+        scheduler: SchedulerConfig,
+    },
+
+    /// An optimizer with gradients clipped before each update.
+    Clipped {
+        optimizer: Box<OptimizerConfig>,
+        clip: GradClip,
+    },
+}
+
+/// Clipping of gradients in [`OptimizerConfig::Clipped`].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub enum GradClip {
+    /// Scales gradients so that their global L2 norm over all parameters does not exceed
+    /// `max_norm`.
+    GlobalNorm { max_norm: f64 },
+
+    /// Clamps each element of gradients to `[-max_value, max_value]`.
+    Value { max_value: f64 },
 }
 
 impl OptimizerConfig {
     /// Constructs an optimizer.
     pub fn build(&self, vs: &VarStore) -> Result<Optimizer> {
         match &self {
+            OptimizerConfig::Scheduled {
+                optimizer,
+                scheduler,
+            } => {
+                let mut opt = optimizer.build(vs)?;
+                let scheduler = LrScheduler::new(scheduler.clone(), optimizer.lr());
+                opt.set_lr(scheduler.lr());
+                Ok(Optimizer::Scheduled(Box::new(opt), scheduler))
+            }
+            OptimizerConfig::Clipped { optimizer, clip } => {
+                let opt = optimizer.build(vs)?;
+                Ok(Optimizer::Clipped(
+                    Box::new(opt),
+                    Clipper {
+                        clip: clip.clone(),
+                        vars: vs.trainable_variables(),
+                        grad_norm: None,
+                    },
+                ))
+            }
             OptimizerConfig::Adam { lr } => {
                 let opt = Adam::default().build(vs, *lr)?;
                 Ok(Optimizer::Adam(opt))
@@ -55,6 +103,41 @@ impl OptimizerConfig {
             }
         }
     }
+
+    /// Schedules the learning rate, which is used as the base learning rate.
+    pub fn scheduler(self, scheduler: SchedulerConfig) -> Self {
+        match self {
+            Self::Scheduled { optimizer, .. } => Self::Scheduled {
+                optimizer,
+                scheduler,
+            },
+            opt => Self::Scheduled {
+                optimizer: Box::new(opt),
+                scheduler,
+            },
+        }
+    }
+
+    /// Clips gradients before each update.
+    pub fn grad_clip(self, clip: GradClip) -> Self {
+        match self {
+            Self::Clipped { optimizer, .. } => Self::Clipped { optimizer, clip },
+            opt => Self::Clipped {
+                optimizer: Box::new(opt),
+                clip,
+            },
+        }
+    }
+
+    /// Returns the (base) learning rate.
+    pub fn lr(&self) -> f64 {
+        match self {
+            Self::Adam { lr } => *lr,
+            Self::AdamW { lr, .. } => *lr,
+            Self::Scheduled { optimizer, .. } => optimizer.lr(),
+            Self::Clipped { optimizer, .. } => optimizer.lr(),
+        }
+    }
 }
 
 /// Optimizers.
@@ -67,18 +150,124 @@ pub enum Optimizer {
     Adam(Optimizer_),
 
     AdamW(Optimizer_),
+
+    /// Optimizer with the learning rate scheduled.
+    Scheduled(Box<Optimizer>, LrScheduler),
+
+    /// Optimizer with gradients clipped.
+    Clipped(Box<Optimizer>, Clipper),
+}
+
+/// State of the clipping of gradients.
+pub struct Clipper {
+    clip: GradClip,
+    vars: Vec<Tensor>,
+    grad_norm: Option<f32>,
+}
+
+impl Clipper {
+    /// Clips gradients in place, keeping their global norm before clipping.
+    fn clip(&mut self) {
+        tch::no_grad(|| {
+            let grads = self
+                .vars
+                .iter()
+                .map(|v| v.grad())
+                .filter(|g| g.defined())
+                .collect::<Vec<_>>();
+            let norm = grads
+                .iter()
+                .map(|g| g.square().sum(Kind::Float).double_value(&[]))
+                .sum::<f64>()
+                .sqrt();
+            self.grad_norm = Some(norm as f32);
+
+            for mut g in grads.into_iter() {
+                match self.clip {
+                    GradClip::GlobalNorm { max_norm } if norm > max_norm => {
+                        let _ = g.g_mul_scalar_(max_norm / (norm + 1e-6));
+                    }
+                    GradClip::GlobalNorm { .. } => {}
+                    GradClip::Value { max_value } => {
+                        let _ = g.clamp_(-max_value, max_value);
+                    }
+                }
+            }
+        });
+    }
 }
 
 impl Optimizer {
     /// Applies a backward step pass.
     pub fn backward_step(&mut self, loss: &Tensor) {
+        self.zero_grad();
+        loss.backward();
+        self.step();
+    }
+
+    /// Zeroes the gradients of the parameters.
+    ///
+    /// Gradients are released, or filled with zeros if
+    /// [`PerfConfig::zero_grad_set_to_none`](crate::util::PerfConfig::zero_grad_set_to_none)
+    /// is `false`.
+    pub fn zero_grad(&mut self) {
+        match self {
+            Self::Adam(opt) | Self::AdamW(opt) => match zero_grad_set_to_none() {
+                true => opt.zero_grad(),
+                false => opt
+                    .trainable_variables()
+                    .iter_mut()
+                    .for_each(|v| v.zero_grad()),
+            },
+            Self::Scheduled(opt, _) | Self::Clipped(opt, _) => opt.zero_grad(),
+        }
+    }
+
+    /// Updates the parameters with their gradients, which are clipped in place if configured.
+    pub fn step(&mut self) {
         match self {
-            Self::Adam(opt) => {
-                opt.backward_step(loss);
+            Self::Adam(opt) | Self::AdamW(opt) => opt.step(),
+            Self::Scheduled(opt, scheduler) => {
+                opt.step();
+                opt.set_lr(scheduler.step());
             }
-            Self::AdamW(opt) => {
-                opt.backward_step(loss);
+            Self::Clipped(opt, clipper) => {
+                clipper.clip();
+                opt.step();
             }
         }
     }
+
+    /// Sets the learning rate.
+    ///
+    /// For a scheduled optimizer, it sets the base learning rate of the schedule.
+    pub fn set_lr(&mut self, lr: f64) {
+        match self {
+            Self::Adam(opt) => opt.set_lr(lr),
+            Self::AdamW(opt) => opt.set_lr(lr),
+            Self::Scheduled(opt, scheduler) => opt.set_lr(scheduler.set_base_lr(lr)),
+            Self::Clipped(opt, _) => opt.set_lr(lr),
+        }
+    }
+
+    /// Returns the current learning rate if it is scheduled.
+    ///
+    /// `None` is returned otherwise, as [tch::nn::Optimizer] does not expose the learning rate.
+    pub fn learning_rate(&self) -> Option<f64> {
+        match self {
+            Self::Scheduled(_, scheduler) => Some(scheduler.lr()),
+            Self::Clipped(opt, _) => opt.learning_rate(),
+            _ => None,
+        }
+    }
+
+    /// Returns the global norm of gradients before clipping in the last update,
+    /// if gradients are clipped.
+    pub fn grad_norm(&self) -> Option<f32> {
+        match self {
+            Self::Adam(_) | Self::AdamW(_) => None,
+            Self::Scheduled(opt, _) => opt.grad_norm(),
+            Self::Clipped(_, clipper) => clipper.grad_norm,
+        }
+    }
 }
@@ -2,9 +2,10 @@ use super::ActorConfig;
 use crate::{
     model::{ModelBase, SubModel},
     opt::{Optimizer, OptimizerConfig},
-    util::OutDim,
+    util::{manifest, OutDim},
 };
 use anyhow::{Context, Result};
+use border_core::ModelManifest;
 use log::{info, trace};
 use serde::{de::DeserializeOwned, Serialize};
 use std::path::Path;
@@ -28,6 +29,9 @@ where
     // Optimizer
     opt_config: OptimizerConfig,
     opt: Optimizer,
+
+    // Manifest of saved parameters
+    manifest: ModelManifest,
 }
 
 impl<P> Actor<P>
@@ -41,10 +45,11 @@ where
         let out_dim = pi_config.get_out_dim();
         let opt_config = config.opt_config;
         let var_store = nn::VarStore::new(device);
+        let manifest = manifest(&pi_config)?;
         let pi = P::build(&var_store, pi_config);
 
         Ok(Actor::_build(
-            device, out_dim, opt_config, pi, var_store, None,
+            device, out_dim, opt_config, manifest, pi, var_store, None,
         ))
     }
 
@@ -52,6 +57,7 @@ where
         device: Device,
         out_dim: i64,
         opt_config: OptimizerConfig,
+        manifest: ModelManifest,
         pi: P,
         mut var_store: nn::VarStore,
         var_store_src: Option<&nn::VarStore>,
@@ -71,6 +77,7 @@ where
             var_store,
             opt,
             pi,
+            manifest,
         }
     }
 
@@ -81,6 +88,11 @@ where
         debug_assert_eq!(std.size().as_slice()[1], self.out_dim);
         (mean, std)
     }
+
+    /// Returns [`ModelManifest`] of the policy.
+    pub fn manifest(&self) -> &ModelManifest {
+        &self.manifest
+    }
 }
 
 impl<P> Clone for Actor<P>
@@ -99,6 +111,7 @@ where
             device,
             out_dim,
             opt_config,
+            self.manifest.clone(),
             pi,
             var_store,
             Some(&self.var_store),
@@ -115,6 +128,14 @@ where
         self.opt.backward_step(loss);
     }
 
+    fn learning_rate(&self) -> Option<f64> {
+        self.opt.learning_rate()
+    }
+
+    fn grad_norm(&self) -> Option<f32> {
+        self.opt.grad_norm()
+    }
+
     fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
         &mut self.var_store
     }
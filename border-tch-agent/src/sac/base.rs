@@ -1,7 +1,8 @@
 use super::{Actor, Critic, EntCoef, SacConfig};
 use crate::{
+    export::{export_torchscript, TorchScriptSignature},
     model::{ModelBase, SubModel, SubModel2},
-    util::{track, CriticLoss, OutDim},
+    util::{find_params, params_path, sample_no_grad, track, CriticLoss, OutDim},
 };
 use anyhow::Result;
 use border_core::{
@@ -53,6 +54,7 @@ where
     pub(super) critic_loss: CriticLoss,
     pub(super) phantom: PhantomData<(E, R)>,
     pub(super) device: tch::Device,
+    pub(super) sample_no_grad: bool,
 }
 
 impl<E, Q, P, R> Sac<E, Q, P, R>
@@ -187,14 +189,27 @@ where
         loss_critic /= self.n_updates_per_opt as f32;
         loss_actor /= self.n_updates_per_opt as f32;
 
-        Record::from_slice(&[
+        let mut record = Record::from_slice(&[
             ("loss_critic", RecordValue::Scalar(loss_critic)),
             ("loss_actor", RecordValue::Scalar(loss_actor)),
             (
                 "ent_coef",
                 RecordValue::Scalar(self.ent_coef.alpha().double_value(&[0]) as f32),
             ),
-        ])
+        ]);
+        if let Some(lr) = self.qnets[0].learning_rate() {
+            record.insert("lr_critic", RecordValue::Scalar(lr as f32));
+        }
+        if let Some(lr) = self.pi.learning_rate() {
+            record.insert("lr_actor", RecordValue::Scalar(lr as f32));
+        }
+        if let Some(grad_norm) = self.qnets[0].grad_norm() {
+            record.insert("grad_norm_critic", RecordValue::Scalar(grad_norm));
+        }
+        if let Some(grad_norm) = self.pi.grad_norm() {
+            record.insert("grad_norm_actor", RecordValue::Scalar(grad_norm));
+        }
+        record
     }
 
     pub fn get_policy_net(&self) -> &Actor<P> {
@@ -202,6 +217,31 @@ where
     }
 }
 
+impl<E, Q, P, R> Sac<E, Q, P, R>
+where
+    Q: SubModel2<Output = ActionValue>,
+    P: SubModel<Input = Tensor, Output = (ActMean, ActStd)>,
+    Q::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+    P::Config: DeserializeOwned + Serialize + OutDim + std::fmt::Debug + PartialEq + Clone,
+{
+    /// Exports the policy as a TorchScript module.
+    ///
+    /// The module takes observations `obs` and outputs deterministic actions `action`,
+    /// taken in evaluation mode. See [`export`](crate::export) for details.
+    ///
+    /// * `obs` - Example observations with the batch dimension.
+    pub fn export_torchscript(
+        &self,
+        path: impl AsRef<Path>,
+        obs: &Tensor,
+    ) -> Result<TorchScriptSignature> {
+        export_torchscript(path, &[("obs", obs.shallow_clone())], &["action"], |xs| {
+            let (mean, _) = self.pi.forward(&xs[0]);
+            vec![mean.tanh()]
+        })
+    }
+}
+
 impl<E, Q, P, R> Policy<E> for Sac<E, Q, P, R>
 where
     E: Env,
@@ -214,14 +254,17 @@ where
 {
     fn sample(&mut self, obs: &E::Obs) -> E::Act {
         let obs = obs.clone().into();
-        let (mean, lstd) = self.pi.forward(&obs);
-        let std = lstd.clip(self.min_lstd, self.max_lstd).exp();
-        let act = if self.train {
-            std * Tensor::randn(&mean.size(), tch::kind::FLOAT_CPU).to(self.device) + mean
-        } else {
-            mean
-        };
-        act.tanh().into()
+        let act = sample_no_grad(self.sample_no_grad, || {
+            let (mean, lstd) = self.pi.forward(&obs);
+            let std = lstd.clip(self.min_lstd, self.max_lstd).exp();
+            let act = if self.train {
+                std * Tensor::randn(&mean.size(), tch::kind::FLOAT_CPU).to(self.device) + mean
+            } else {
+                mean
+            };
+            act.tanh()
+        });
+        act.into()
     }
 }
 
@@ -274,6 +317,7 @@ where
             critic_loss: config.critic_loss,
             n_opts: 0,
             device,
+            sample_no_grad: config.sample_no_grad,
             phantom: PhantomData,
         }
     }
@@ -316,31 +360,36 @@ where
         let mut paths = vec![];
 
         for (i, (qnet, qnet_tgt)) in self.qnets.iter().zip(&self.qnets_tgt).enumerate() {
-            let path1 = path.join(format!("qnet_{}.pt.tch", i)).to_path_buf();
-            let path2 = path.join(format!("qnet_tgt_{}.pt.tch", i)).to_path_buf();
+            let path1 = params_path(path.join(format!("qnet_{}", i)));
+            let path2 = params_path(path.join(format!("qnet_tgt_{}", i)));
             qnet.save(&path1)?;
             qnet_tgt.save(&path2)?;
             paths.push(path1);
             paths.push(path2);
         }
 
-        let path_actor = path.join("pi.pt.tch").to_path_buf();
-        let path_ent_coef = path.join("ent_coef.pt.tch").to_path_buf();
+        let path_actor = params_path(path.join("pi"));
+        let path_ent_coef = params_path(path.join("ent_coef"));
         self.pi.save(&path_actor)?;
         self.ent_coef.save(&path_ent_coef)?;
         paths.push(path_actor);
         paths.push(path_ent_coef);
 
+        self.pi
+            .manifest()
+            .clone()
+            .model_type("Sac")
+            .save_in(path, &mut paths)?;
         Ok(paths)
     }
 
     fn load_params(&mut self, path: &Path) -> Result<()> {
         for (i, (qnet, qnet_tgt)) in self.qnets.iter_mut().zip(&mut self.qnets_tgt).enumerate() {
-            qnet.load(path.join(format!("qnet_{}.pt.tch", i)).as_path())?;
-            qnet_tgt.load(path.join(format!("qnet_tgt_{}.pt.tch", i)).as_path())?;
+            qnet.load(find_params(path.join(format!("qnet_{}", i))))?;
+            qnet_tgt.load(find_params(path.join(format!("qnet_tgt_{}", i))))?;
         }
-        self.pi.load(path.join("pi.pt.tch").as_path())?;
-        self.ent_coef.load(path.join("ent_coef.pt.tch").as_path())?;
+        self.pi.load(find_params(path.join("pi")))?;
+        self.ent_coef.load(find_params(path.join("ent_coef")))?;
         Ok(())
     }
 
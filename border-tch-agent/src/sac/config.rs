@@ -3,6 +3,7 @@ use super::{ActorConfig, CriticConfig};
 use crate::{
     model::{SubModel, SubModel2},
     sac::ent_coef::EntCoefMode,
+    util::default_true,
     util::CriticLoss,
     util::OutDim,
     Device,
@@ -43,6 +44,9 @@ where
     pub n_critics: usize,
     pub seed: Option<i64>,
     pub device: Option<Device>,
+    /// If `true`, actions are sampled without recording operations for autograd.
+    #[serde(default = "default_true")]
+    pub sample_no_grad: bool,
     // expr_sampling: ExperienceSampling,
 }
 
@@ -71,6 +75,7 @@ where
             n_critics: self.n_critics.clone(),
             seed: self.seed.clone(),
             device: self.device.clone(),
+            sample_no_grad: self.sample_no_grad,
         }
     }
 }
@@ -100,6 +105,7 @@ where
             n_critics: 1,
             seed: None,
             device: None,
+            sample_no_grad: true,
             // expr_sampling: ExperienceSampling::Uniform,
         }
     }
@@ -186,6 +192,12 @@ where
         self
     }
 
+    /// Sets the flag to sample actions without recording operations for autograd.
+    pub fn sample_no_grad(mut self, v: bool) -> Self {
+        self.sample_no_grad = v;
+        self
+    }
+
     /// Constructs [SacConfig] from YAML file.
     pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let path_ = path.as_ref().to_owned();
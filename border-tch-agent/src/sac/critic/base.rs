@@ -98,6 +98,14 @@ where
         self.opt.backward_step(loss);
     }
 
+    fn learning_rate(&self) -> Option<f64> {
+        self.opt.learning_rate()
+    }
+
+    fn grad_norm(&self) -> Option<f32> {
+        self.opt.grad_norm()
+    }
+
     fn get_var_store_mut(&mut self) -> &mut nn::VarStore {
         &mut self.var_store
     }
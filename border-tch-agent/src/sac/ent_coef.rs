@@ -1,5 +1,6 @@
 //! Entropy coefficient of SAC.
 use anyhow::Result;
+use border_core::Schedule;
 use log::{info, trace};
 use serde::{Deserialize, Serialize};
 use std::{/*borrow::Borrow,*/ path::Path};
@@ -12,6 +13,8 @@ pub enum EntCoefMode {
     Fix(f64),
     /// Automatic tuning given `(target_entropy, learning_rate)`.
     Auto(f64, f64),
+    /// Alpha following a schedule over updates.
+    Scheduled(Schedule),
 }
 
 /// The entropy coefficient of SAC.
@@ -20,6 +23,8 @@ pub struct EntCoef {
     log_alpha: Tensor,
     target_entropy: Option<f64>,
     opt: Option<nn::Optimizer>,
+    schedule: Option<Schedule>,
+    n_updates: usize,
 }
 
 impl EntCoef {
@@ -27,6 +32,7 @@ impl EntCoef {
     pub fn new(mode: EntCoefMode, device: tch::Device) -> Self {
         let var_store = nn::VarStore::new(device);
         let path = &var_store.root();
+        let mut schedule = None;
         let (log_alpha, target_entropy, opt) = match mode {
             EntCoefMode::Fix(alpha) => {
                 let init = nn::Init::Const(alpha.ln());
@@ -43,6 +49,12 @@ impl EntCoef {
                     .unwrap();
                 (log_alpha, Some(target_entropy), Some(opt))
             }
+            EntCoefMode::Scheduled(s) => {
+                let init = nn::Init::Const(s.value(0).ln());
+                let log_alpha = path.var("log_alpha", &[1], init);
+                schedule = Some(s);
+                (log_alpha, None, None)
+            }
         };
 
         Self {
@@ -50,6 +62,8 @@ impl EntCoef {
             log_alpha,
             opt,
             target_entropy,
+            schedule,
+            n_updates: 0,
         }
     }
 
@@ -72,6 +86,13 @@ impl EntCoef {
             let loss = -(&self.log_alpha * (logp + target_entropy).detach()).mean(tch::Kind::Float);
             self.backward_step(&loss);
         }
+        if let Some(schedule) = &self.schedule {
+            self.n_updates += 1;
+            let log_alpha = schedule.value(self.n_updates).ln();
+            tch::no_grad(|| {
+                let _ = self.log_alpha.fill_(log_alpha);
+            });
+        }
     }
 
     /// Save the parameter into a file.
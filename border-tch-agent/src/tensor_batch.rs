@@ -1,4 +1,7 @@
-use border_core::generic_replay_buffer::BatchBase;
+use crate::util::TensorData;
+use border_core::generic_replay_buffer::{BatchBase, DumpBatch};
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use std::path::{Path, PathBuf};
 use tch::{Device, Tensor};
 
 /// Adds capability of constructing [`Tensor`] with a static method.
@@ -118,6 +121,51 @@ impl BatchBase for TensorBatch {
             capacity: ixs.len() as i64,
         }
     }
+
+    fn size_in_bytes(&self) -> Option<usize> {
+        self.buf
+            .as_ref()
+            .map(|t| t.numel() * t.kind().elt_size_in_bytes())
+    }
+}
+
+impl DumpBatch for TensorBatch {
+    /// Writes the `i`-th element as a `.npy` array.
+    fn dump(&self, i: usize, path: &Path) -> anyhow::Result<PathBuf> {
+        let path = path.with_extension("npy");
+        match &self.buf {
+            Some(buf) => buf.get(i as i64).write_npy(&path)?,
+            None => anyhow::bail!("The batch is empty"),
+        }
+        Ok(path)
+    }
+}
+
+/// Serializes the shape, the kind and the values of the buffer.
+///
+/// It is used to send samples over the network, e.g., in the networked mode of
+/// `border-async-trainer`.
+impl Serialize for TensorBatch {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let buf = self
+            .buf
+            .as_ref()
+            .map(TensorData::from_tensor)
+            .transpose()
+            .map_err(S::Error::custom)?;
+        (self.capacity, buf).serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for TensorBatch {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (capacity, buf) = <(i64, Option<(Vec<i64>, TensorData)>)>::deserialize(deserializer)?;
+        let buf = buf
+            .map(|(shape, data)| data.into_tensor(&shape))
+            .transpose()
+            .map_err(D::Error::custom)?;
+        Ok(Self { buf, capacity })
+    }
 }
 
 impl From<TensorBatch> for Tensor {
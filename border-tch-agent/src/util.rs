@@ -3,14 +3,35 @@ use crate::model::ModelBase;
 use log::trace;
 use serde::{Deserialize, Serialize};
 mod named_tensors;
+mod perf;
 mod quantile_loss;
-use border_core::record::{Record, RecordValue};
+mod tensor_data;
+use anyhow::Result;
+pub use border_core::params_path;
+use border_core::{
+    record::{Record, RecordValue},
+    ModelManifest,
+};
 pub use named_tensors::NamedTensors;
 use ndarray::ArrayD;
 use num_traits::cast::AsPrimitive;
+pub(crate) use perf::default_true;
+pub use perf::{zero_grad_set_to_none, PerfConfig};
 pub use quantile_loss::quantile_huber_loss;
-use std::convert::TryFrom;
+use std::{
+    convert::TryFrom,
+    path::{Path, PathBuf},
+};
 use tch::{nn::VarStore, Tensor};
+pub(crate) use tensor_data::TensorData;
+
+/// Calls `f`, without recording operations for autograd if `no_grad` is `true`.
+pub(crate) fn sample_no_grad<T>(no_grad: bool, f: impl FnOnce() -> T) -> T {
+    match no_grad {
+        true => tch::no_grad(f),
+        false => f(),
+    }
+}
 
 /// Critic loss type.
 #[allow(clippy::upper_case_acronyms)]
@@ -58,6 +79,30 @@ pub trait OutDim {
 
     /// Sets the  output dimension.
     fn set_out_dim(&mut self, v: i64);
+
+    /// Returns the shape of an input, e.g., an observation, if known.
+    fn get_in_shape(&self) -> Option<Vec<i64>> {
+        None
+    }
+}
+
+/// Returns the path of a file of parameters to be loaded.
+///
+/// If prefix + `.safetensors` does not exist, prefix + `.pt.tch`, saved in the format of
+/// PyTorch by older versions of this crate, is returned if it exists.
+pub fn find_params(prefix: impl AsRef<Path>) -> PathBuf {
+    border_core::find_params(prefix, "pt.tch")
+}
+
+/// Returns [`ModelManifest`] of a policy network with the given configuration.
+///
+/// The type of the model and the files of parameters are set when it is saved with
+/// [`ModelManifest::save_in()`].
+pub(crate) fn manifest<C: Serialize + OutDim>(config: &C) -> Result<ModelManifest> {
+    ModelManifest::new("tch", "")
+        .obs_shape(config.get_in_shape())
+        .act_dim(config.get_out_dim())
+        .config(config)
 }
 
 /// Returns the mean and standard deviation of the parameters.
@@ -1,5 +1,10 @@
-use std::{collections::HashMap, iter::FromIterator};
-use tch::{nn::VarStore, Device::Cpu, Tensor};
+use serde::{
+    de::{Deserializer, Error as _},
+    ser::{Error as _, SerializeMap, Serializer},
+    Deserialize, Serialize,
+};
+use std::{collections::HashMap, convert::TryFrom, iter::FromIterator};
+use tch::{nn::VarStore, Device::Cpu, Kind, Tensor};
 
 /// Named tensors to send model parameters using a channel.
 pub struct NamedTensors {
@@ -48,6 +53,50 @@ impl Clone for NamedTensors {
     }
 }
 
+/// Serializes tensors as pairs of shapes and values in `f32`.
+///
+/// It is used to send model parameters over the network, e.g., in the networked mode of
+/// `border-async-trainer`.
+impl Serialize for NamedTensors {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.named_tensors.len()))?;
+        for (name, t) in self.named_tensors.iter() {
+            let shape = t.size();
+            let data = Vec::<f32>::try_from(t.to_kind(Kind::Float).flatten(0, -1))
+                .map_err(S::Error::custom)?;
+            map.serialize_entry(name, &(shape, data))?;
+        }
+        map.end()
+    }
+}
+
+/// Returns an error if the number of values does not match the shape of a tensor.
+impl<'de> Deserialize<'de> for NamedTensors {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let src = HashMap::<String, (Vec<i64>, Vec<f32>)>::deserialize(deserializer)?;
+        let mut named_tensors = HashMap::with_capacity(src.len());
+        for (k, (shape, data)) in src.into_iter() {
+            let numel = shape.iter().try_fold(1i64, |n, &d| match d >= 0 {
+                true => n.checked_mul(d),
+                false => None,
+            });
+            if numel != Some(data.len() as i64) {
+                return Err(D::Error::custom(format!(
+                    "{} values do not match shape {:?} of tensor {}",
+                    data.len(),
+                    shape,
+                    k
+                )));
+            }
+            let t = Tensor::from_slice(&data)
+                .f_reshape(shape.as_slice())
+                .map_err(D::Error::custom)?;
+            named_tensors.insert(k, t);
+        }
+        Ok(NamedTensors { named_tensors })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::NamedTensors;
@@ -93,4 +142,14 @@ mod test {
         // println!("{:?}", t2);
         // println!("{:?}", t3);
     }
+
+    #[test]
+    fn test_deserialize_named_tensors() {
+        let nt: NamedTensors = serde_yaml::from_str("w: [[2, 2], [1.0, 2.0, 3.0, 4.0]]").unwrap();
+        assert_eq!(nt.named_tensors["w"].size(), vec![2, 2]);
+
+        // The number of values does not match the shape
+        assert!(serde_yaml::from_str::<NamedTensors>("w: [[2, 2], [1.0, 2.0, 3.0]]").is_err());
+        assert!(serde_yaml::from_str::<NamedTensors>("w: [[-1], [1.0, 2.0]]").is_err());
+    }
 }
@@ -0,0 +1,112 @@
+//! Performance knobs of libtorch.
+//!
+//! The knobs in [`PerfConfig`] change global states of libtorch, which are shared by all agents
+//! in the process. They are applied once with [`PerfConfig::init()`] at the beginning of the
+//! program, before agents are built.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ZERO_GRAD_SET_TO_NONE: AtomicBool = AtomicBool::new(true);
+
+static INITIALIZED: AtomicBool = AtomicBool::new(false);
+
+/// Returns `true` if gradients are released, instead of filled with zeros,
+/// in [`Optimizer::zero_grad()`](crate::opt::Optimizer::zero_grad).
+pub fn zero_grad_set_to_none() -> bool {
+    ZERO_GRAD_SET_TO_NONE.load(Ordering::Relaxed)
+}
+
+pub(crate) fn default_true() -> bool {
+    true
+}
+
+/// Configuration of performance knobs of the process.
+///
+/// The default values keep the behavior of libtorch unchanged.
+///
+/// ```ignore
+/// fn main() -> Result<()> {
+///     PerfConfig::default().cudnn_benchmark(true).init()?;
+///     // Build agents and train them
+/// }
+/// ```
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone)]
+pub struct PerfConfig {
+    /// If `true`, cuDNN benchmarks convolution algorithms and uses the fastest one.
+    ///
+    /// It speeds up models with fixed input sizes, such as CNNs on Atari frames,
+    /// while results are no longer deterministic.
+    #[serde(default)]
+    pub cudnn_benchmark: bool,
+
+    /// If `Some(true)`, matrix multiplications on CUDA devices use TensorFloat-32 (TF32).
+    /// If `Some(false)`, TF32 is disabled in both matrix multiplications and convolutions.
+    /// If `None`, the default of libtorch is used, i.e., TF32 only in convolutions.
+    ///
+    /// This is given to libtorch and CUDA libraries through the environment variables
+    /// `TORCH_ALLOW_TF32_CUBLAS_OVERRIDE` and `NVIDIA_TF32_OVERRIDE`, which are read when
+    /// they are initialized. Thus, [`PerfConfig::init()`] must be called before the first
+    /// operation on CUDA devices in the process.
+    #[serde(default)]
+    pub allow_tf32: Option<bool>,
+
+    /// If `true`, gradients are released instead of filled with zeros before backpropagation.
+    ///
+    /// It saves memory and a kernel launch for each parameter.
+    #[serde(default = "default_true")]
+    pub zero_grad_set_to_none: bool,
+}
+
+impl Default for PerfConfig {
+    fn default() -> Self {
+        Self {
+            cudnn_benchmark: false,
+            allow_tf32: None,
+            zero_grad_set_to_none: true,
+        }
+    }
+}
+
+impl PerfConfig {
+    /// Sets the flag to benchmark convolution algorithms in cuDNN.
+    pub fn cudnn_benchmark(mut self, v: bool) -> Self {
+        self.cudnn_benchmark = v;
+        self
+    }
+
+    /// Sets the flag to use TF32 on CUDA devices.
+    pub fn allow_tf32(mut self, v: Option<bool>) -> Self {
+        self.allow_tf32 = v;
+        self
+    }
+
+    /// Sets the flag to release gradients instead of filling them with zeros.
+    pub fn zero_grad_set_to_none(mut self, v: bool) -> Self {
+        self.zero_grad_set_to_none = v;
+        self
+    }
+
+    /// Applies the knobs to the global states of libtorch.
+    ///
+    /// It should be called at the beginning of the program, before spawning threads,
+    /// e.g., actors of asynchronous training, and before any operation on CUDA devices,
+    /// since environment variables are set for TF32.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if it has already been called in the process.
+    pub fn init(&self) -> Result<()> {
+        if INITIALIZED.swap(true, Ordering::SeqCst) {
+            bail!("PerfConfig::init() has already been called");
+        }
+        match self.allow_tf32 {
+            Some(true) => std::env::set_var("TORCH_ALLOW_TF32_CUBLAS_OVERRIDE", "1"),
+            Some(false) => std::env::set_var("NVIDIA_TF32_OVERRIDE", "0"),
+            None => {}
+        }
+        tch::Cuda::cudnn_set_benchmark(self.cudnn_benchmark);
+        ZERO_GRAD_SET_TO_NONE.store(self.zero_grad_set_to_none, Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+//! Serialization of tensors.
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+use tch::{Device, Kind, Tensor};
+
+/// Values of a tensor, keeping its kind in serialization.
+#[derive(Serialize, Deserialize)]
+pub(crate) enum TensorData {
+    U8(Vec<u8>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+    F32(Vec<f32>),
+    F64(Vec<f64>),
+}
+
+impl TensorData {
+    /// Copies the shape and the values of a tensor.
+    pub fn from_tensor(t: &Tensor) -> Result<(Vec<i64>, Self)> {
+        let shape = t.size();
+        let t = t.detach().to(Device::Cpu).flatten(0, -1);
+        let data = match t.kind() {
+            Kind::Uint8 => Self::U8(Vec::try_from(t)?),
+            Kind::Int => Self::I32(Vec::try_from(t)?),
+            Kind::Int64 => Self::I64(Vec::try_from(t)?),
+            Kind::Float => Self::F32(Vec::try_from(t)?),
+            Kind::Double => Self::F64(Vec::try_from(t)?),
+            kind => bail!("Serialization of tensors of {:?} is not supported", kind),
+        };
+        Ok((shape, data))
+    }
+
+    /// Creates a tensor on CPU.
+    ///
+    /// Returns an error if the number of values does not match `shape`.
+    pub fn into_tensor(self, shape: &[i64]) -> Result<Tensor> {
+        let numel = shape.iter().try_fold(1i64, |n, &d| match d >= 0 {
+            true => n.checked_mul(d),
+            false => None,
+        });
+        let t = match self {
+            Self::U8(v) => Tensor::from_slice(&v),
+            Self::I32(v) => Tensor::from_slice(&v),
+            Self::I64(v) => Tensor::from_slice(&v),
+            Self::F32(v) => Tensor::from_slice(&v),
+            Self::F64(v) => Tensor::from_slice(&v),
+        };
+        if numel != Some(t.numel() as i64) {
+            bail!("{} values do not match shape {:?}", t.numel(), shape);
+        }
+        Ok(t.f_reshape(shape)?)
+    }
+}
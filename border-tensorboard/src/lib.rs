@@ -2,28 +2,85 @@
 //!
 //! [`TensorboardRecorder`] saves TFRecord files and model parameters to a directory
 //! in the local file system during training.
-use anyhow::Result;
+//!
+//! For long runs, [`TensorboardRecorder::with_rotation()`] splits the output into
+//! multiple event files in the same directory and optionally downsamples scalars in
+//! old files. See [`RotationConfig`].
+mod rotation;
+use anyhow::{Context, Result};
 use border_core::{
     record::{Record, RecordValue, Recorder},
-    Env, ReplayBufferBase,
+    Env, ModelMetadata, ReplayBufferBase,
 };
+use rotation::{compact_segment, event_files, open_segment, Segment};
+pub use rotation::{CompactionConfig, RotationConfig};
 use std::{
+    fs,
     marker::PhantomData,
     path::{Path, PathBuf},
 };
 use tensorboard_rs::summary_writer::SummaryWriter;
 
+/// The number of buckets of histograms.
+const HISTOGRAM_BUCKETS: usize = 30;
+
+/// Returns an estimate of the number of bytes of the event of a value in an event file.
+///
+/// An event is a TFRecord of its length, the serialized event and their checksums, where
+/// the event consists of the wall time, the step, the tag and the value. Images are
+/// estimated without compression.
+fn event_bytes(key: &str, value: &RecordValue) -> u64 {
+    const OVERHEAD: usize = 40;
+    let value_bytes = match value {
+        RecordValue::Scalar(_) => 4,
+        RecordValue::Array2(data, _) => 3 * data.len(),
+        RecordValue::Image(_, [h, w, _]) => 3 * h * w,
+        RecordValue::Histogram(_) => 16 * HISTOGRAM_BUCKETS + 40,
+        _ => return 0, // not written
+    };
+    (OVERHEAD + key.len() + value_bytes) as u64
+}
+
+/// Converts an image of shape `[height, width, channels]` with 1 or 3 channels
+/// into a channel-first RGB image.
+///
+/// Returns an error if the number of channels is not supported or the length of `data`
+/// does not match the shape.
+fn to_rgb_chw(data: &[u8], shape: &[usize; 3]) -> Result<Vec<u8>> {
+    let [h, w, c] = *shape;
+    if c != 1 && c != 3 {
+        anyhow::bail!("Unsupported number of channels: {}", c);
+    }
+    let len = h.checked_mul(w).and_then(|n| n.checked_mul(c));
+    if len != Some(data.len()) {
+        anyhow::bail!("{} values do not match shape {:?}", data.len(), shape);
+    }
+
+    let mut chw = Vec::with_capacity(3 * h * w);
+    for ch in 0..3 {
+        let ch = ch.min(c - 1);
+        chw.extend((0..h * w).map(|i| data[i * c + ch]));
+    }
+    Ok(chw)
+}
+
 /// Write records to TFRecord.
 pub struct TensorboardRecorder<E, R>
 where
     E: Env,
     R: ReplayBufferBase,
 {
+    log_dir: PathBuf,
     model_dir: PathBuf,
     writer: SummaryWriter,
+    /// Event file created in [`TensorboardRecorder::new()`], until a record is written to it.
+    initial_file: Option<PathBuf>,
     step_key: String,
     latest_record: Option<Record>,
     ignore_unsupported_value: bool,
+    rotation: Option<RotationConfig>,
+    segment: Option<Segment>,
+    rotated_segments: Vec<Segment>,
     phantom: PhantomData<(E, R)>,
 }
 
@@ -42,15 +99,148 @@ where
         model_dir: impl AsRef<Path>,
         check_unsupported_value: bool,
     ) -> Self {
+        // The file created by the writer is found by comparing the files in the directory
+        let files = event_files(log_dir.as_ref()).unwrap_or_default();
+        let writer = SummaryWriter::new(&log_dir);
+        let initial_file = event_files(log_dir.as_ref())
+            .ok()
+            .and_then(|new_files| new_files.difference(&files).next().cloned());
+
         Self {
+            log_dir: log_dir.as_ref().to_path_buf(),
             model_dir: model_dir.as_ref().to_path_buf(),
-            writer: SummaryWriter::new(log_dir),
+            writer,
+            initial_file,
             step_key: "opt_steps".to_string(),
             ignore_unsupported_value: !check_unsupported_value,
             latest_record: None,
+            rotation: None,
+            segment: None,
+            rotated_segments: vec![],
             phantom: PhantomData,
         }
     }
+
+    /// Enables the rotation of event files.
+    ///
+    /// The event file opened in [`TensorboardRecorder::new()`] is replaced with the first
+    /// one tracked by the recorder, so that its size and steps can be monitored.
+    /// The replaced file is removed unless records have been written to it.
+    pub fn with_rotation(mut self, config: RotationConfig) -> Result<Self> {
+        let (writer, segment) = open_segment(&self.log_dir, 0)?;
+        let mut writer = std::mem::replace(&mut self.writer, writer);
+        writer.flush();
+        drop(writer);
+        if let Some(file) = self.initial_file.take() {
+            fs::remove_file(&file)
+                .with_context(|| format!("Failed to remove the event file {:?}", file))?;
+        }
+        self.segment = Some(segment);
+        self.rotation = Some(config);
+        Ok(self)
+    }
+
+    /// Returns `true` if the current event file should be closed before writing `step`.
+    ///
+    /// The size of the file is the estimate of the bytes written to it, as the size on disk
+    /// does not include bytes buffered by the writer, which is flushed only on rotation and drop.
+    fn needs_rotation(&self, step: usize) -> bool {
+        let (max_steps, max_bytes) = match &self.rotation {
+            Some(config) => (config.max_steps, config.max_bytes),
+            None => return false,
+        };
+        let segment = match self.segment.as_ref() {
+            Some(segment) => segment,
+            None => return false,
+        };
+        let start_step = match segment.start_step {
+            Some(start_step) => start_step,
+            None => return false, // nothing has been written yet
+        };
+
+        max_steps.is_some_and(|max_steps| step >= start_step + max_steps)
+            || max_bytes.is_some_and(|max_bytes| segment.bytes >= max_bytes)
+    }
+
+    /// Closes the current event file, opens a new one and compacts old files.
+    fn rotate(&mut self) -> Result<()> {
+        self.writer.flush();
+        let prev = self.segment.take().context("No event file to rotate")?;
+        let index = self.rotated_segments.len() + 1;
+        let (writer, segment) = open_segment(&self.log_dir, index)?;
+        self.writer = writer; // the previous writer is dropped here
+        self.segment = Some(segment);
+        self.rotated_segments.push(prev);
+
+        let compaction = self.rotation.as_ref().and_then(|c| c.compaction.clone());
+        if let Some(compaction) = compaction {
+            let n = self
+                .rotated_segments
+                .len()
+                .saturating_sub(compaction.keep_full);
+            for segment in self.rotated_segments[..n].iter_mut() {
+                if !segment.compacted {
+                    compact_segment(&self.log_dir, segment, compaction.downsample)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes an image of shape `[height, width, channels]` as an RGB image.
+    fn add_image(&mut self, key: &str, data: &[u8], shape: &[usize; 3], step: usize) -> Result<()> {
+        let chw = to_rgb_chw(data, shape).map_err(|e| e.context(format!("Image {}", key)))?;
+        self.writer
+            .add_image(key, chw.as_slice(), &[3, shape[0], shape[1]], step);
+        Ok(())
+    }
+
+    /// Writes samples as a histogram with equal-width buckets.
+    fn add_histogram(&mut self, key: &str, values: &[f32], step: usize) {
+        if values.is_empty() {
+            return;
+        }
+
+        let values = values.iter().map(|&v| v as f64).collect::<Vec<_>>();
+        let min = values.iter().fold(f64::MAX, |m, &v| v.min(m));
+        let max = values.iter().fold(f64::MIN, |m, &v| v.max(m));
+        let width = ((max - min) / HISTOGRAM_BUCKETS as f64).max(f64::EPSILON);
+        let mut counts = vec![0f64; HISTOGRAM_BUCKETS];
+        for v in values.iter() {
+            counts[(((v - min) / width) as usize).min(HISTOGRAM_BUCKETS - 1)] += 1.0;
+        }
+        let limits = (1..=HISTOGRAM_BUCKETS)
+            .map(|i| min + width * i as f64)
+            .collect::<Vec<_>>();
+
+        self.writer.add_histogram_raw(
+            key,
+            min,
+            max,
+            values.len() as f64,
+            values.iter().sum(),
+            values.iter().map(|v| v * v).sum(),
+            &limits,
+            &counts,
+            step,
+        );
+    }
+
+    /// Keeps track of a value written to the current event file.
+    fn track(&mut self, key: &str, value: &RecordValue, step: usize) {
+        let keep_scalars = match &self.rotation {
+            Some(config) => config.compaction.is_some(),
+            None => false,
+        };
+        if let Some(segment) = self.segment.as_mut() {
+            segment.start_step.get_or_insert(step);
+            segment.bytes += event_bytes(key, value);
+            if let (true, RecordValue::Scalar(v)) = (keep_scalars, value) {
+                segment.scalars.push((key.to_string(), step, *v));
+            }
+        }
+    }
 }
 
 impl<E, R> Recorder<E, R> for TensorboardRecorder<E, R>
@@ -60,19 +250,33 @@ where
 {
     /// Writes a given [`Record`] into a TFRecord.
     ///
-    /// This method handles [RecordValue::Scalar] and [RecordValue::DateTime] in the [`Record`].
-    /// Other variants will be ignored.
+    /// See [`Recorder::try_write()`]. It panics if writing fails.
     fn write(&mut self, record: Record) {
-        // TODO: handle error
-        let step = match record.get(&self.step_key).unwrap() {
-            RecordValue::Scalar(v) => *v as usize,
-            _ => {
-                panic!()
-            }
+        self.try_write(record)
+            .expect("Failed to write a record to TensorBoard");
+    }
+
+    /// Writes a given [`Record`] into a TFRecord.
+    ///
+    /// This method handles [RecordValue::Scalar] and [RecordValue::DateTime] in the [`Record`].
+    /// [RecordValue::Array2] and [RecordValue::Image] are written as image summaries, and
+    /// [RecordValue::Histogram] as histogram summaries. Other variants are ignored if
+    /// `check_unsupported_value` is `false`, otherwise an error is returned. Errors in
+    /// the rotation of event files are also returned.
+    fn try_write(&mut self, record: Record) -> Result<()> {
+        let step = match record.get(&self.step_key) {
+            Some(RecordValue::Scalar(v)) => *v as usize,
+            _ => anyhow::bail!("Record has no scalar of {}", self.step_key),
         };
 
+        if self.needs_rotation(step) {
+            self.rotate().context("Failed to rotate event files")?;
+        }
+        self.initial_file = None;
+
         for (k, v) in record.iter() {
             if *k != self.step_key {
+                self.track(k, v, step);
                 match v {
                     RecordValue::Scalar(v) => self.writer.add_scalar(k, *v as f32, step),
                     RecordValue::DateTime(_) => {} // discard value
@@ -89,14 +293,23 @@ where
                         data.extend(data_.iter());
                         self.writer.add_image(k, data.as_slice(), &shape, step)
                     }
+                    RecordValue::Image(data, shape) => {
+                        if let Err(e) = self.add_image(k, data, shape, step) {
+                            if !self.ignore_unsupported_value {
+                                return Err(e);
+                            }
+                        }
+                    }
+                    RecordValue::Histogram(values) => self.add_histogram(k, values, step),
                     _ => {
                         if !self.ignore_unsupported_value {
-                            panic!("Unsupported value: {:?}", (k, v));
+                            anyhow::bail!("Unsupported value: {:?}", (k, v));
                         }
                     }
                 };
             }
         }
+        Ok(())
     }
 
     fn store(&mut self, record: Record) {
@@ -123,4 +336,166 @@ where
         let path = self.model_dir.join(base);
         agent.load_params(&path)
     }
+
+    /// Saves the metadata in the directory of the model.
+    fn save_model_metadata(&self, base: &Path, metadata: &ModelMetadata) -> Result<()> {
+        metadata.save(self.model_dir.join(base).join(ModelMetadata::FILE))
+    }
+
+    /// Removes the directory of the model.
+    fn remove_model(&self, base: &Path) -> Result<()> {
+        let path = self.model_dir.join(base);
+        if path.exists() {
+            std::fs::remove_dir_all(&path)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, R> Drop for TensorboardRecorder<E, R>
+where
+    E: Env,
+    R: ReplayBufferBase,
+{
+    fn drop(&mut self) {
+        self.writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use border_core::agent_dev::{CartPole, CartPoleBuffer};
+    use std::fs;
+    use tempdir::TempDir;
+
+    type CartPoleRecorder = TensorboardRecorder<CartPole, CartPoleBuffer>;
+
+    fn record(step: usize) -> Record {
+        let mut record = Record::from_scalar("opt_steps", step as f32);
+        record.insert("loss", RecordValue::Scalar(1.0));
+        record
+    }
+
+    /// Returns the sorted paths of the event files of segments, i.e., with indices.
+    fn segment_files(dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = vec![];
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let index = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+            if index.len() == 6 && index.chars().all(|c| c.is_ascii_digit()) {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    #[test]
+    fn test_rotation() -> Result<()> {
+        let dir = TempDir::new("tensorboard")?;
+        let config = RotationConfig::default().max_steps(2);
+        let mut recorder =
+            CartPoleRecorder::new(dir.path(), dir.path(), true).with_rotation(config)?;
+        for step in 0..6 {
+            recorder.try_write(record(step))?;
+        }
+        drop(recorder);
+
+        // Files opened within the same second are distinct and ordered by their indices,
+        // and flushed on rotation and drop
+        let files = segment_files(dir.path())?;
+        let indices = files
+            .iter()
+            .map(|f| f.extension().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(indices, ["000000", "000001", "000002"]);
+        for file in files.iter() {
+            assert!(fs::metadata(file)?.len() > 0);
+        }
+        assert!(!dir.path().join(".segment").exists());
+
+        // The event file opened before enabling the rotation is removed
+        assert_eq!(event_files(dir.path())?.len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rotation_by_bytes() -> Result<()> {
+        // Rotation does not depend on when the writer flushes the file
+        let dir = TempDir::new("tensorboard")?;
+        let bytes = event_bytes("loss", &RecordValue::Scalar(1.0));
+        let config = RotationConfig::default().max_bytes(3 * bytes);
+        let mut recorder =
+            CartPoleRecorder::new(dir.path(), dir.path(), true).with_rotation(config)?;
+        for step in 0..6 {
+            recorder.try_write(record(step))?;
+        }
+        assert_eq!(recorder.rotated_segments.len(), 1);
+        assert_eq!(recorder.segment.as_ref().unwrap().bytes, 3 * bytes);
+        Ok(())
+    }
+
+    #[test]
+    fn test_compaction() -> Result<()> {
+        let dir = TempDir::new("tensorboard")?;
+        let compaction = CompactionConfig::default().keep_full(1).downsample(2);
+        let config = RotationConfig::default()
+            .max_steps(4)
+            .compaction(compaction);
+        let mut recorder =
+            CartPoleRecorder::new(dir.path(), dir.path(), true).with_rotation(config)?;
+        for step in 0..10 {
+            recorder.try_write(record(step))?;
+        }
+
+        // Only the oldest file is compacted, keeping 3 of 4 values
+        let compacted = recorder
+            .rotated_segments
+            .iter()
+            .map(|s| s.compacted)
+            .collect::<Vec<_>>();
+        assert_eq!(compacted, [true, false]);
+        drop(recorder);
+        let files = segment_files(dir.path())?;
+        assert!(fs::metadata(&files[0])?.len() < fs::metadata(&files[1])?.len());
+        assert!(!dir.path().join(".compaction").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_errors() -> Result<()> {
+        let dir = TempDir::new("tensorboard")?;
+        let config = RotationConfig::default().max_bytes(1);
+        let mut recorder =
+            CartPoleRecorder::new(dir.path(), dir.path(), true).with_rotation(config)?;
+
+        // A record without the step is an error
+        assert!(recorder
+            .try_write(Record::from_scalar("loss", 1.0))
+            .is_err());
+
+        // Failure to open a new event file is returned
+        recorder.try_write(record(0))?;
+        fs::remove_dir_all(dir.path())?;
+        fs::write(dir.path(), b"")?;
+        assert!(recorder.try_write(record(1)).is_err());
+        fs::remove_file(dir.path())?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_rgb_chw() -> Result<()> {
+        // Grayscale pixels are copied to all channels
+        assert_eq!(to_rgb_chw(&[1, 2], &[1, 2, 1])?, vec![1, 2, 1, 2, 1, 2]);
+        assert_eq!(
+            to_rgb_chw(&[1, 2, 3, 4, 5, 6], &[1, 2, 3])?,
+            vec![1, 4, 2, 5, 3, 6]
+        );
+
+        assert!(to_rgb_chw(&[1, 2, 3], &[1, 2, 3]).is_err());
+        assert!(to_rgb_chw(&[1, 2, 3, 4], &[1, 2, 2]).is_err());
+        assert!(to_rgb_chw(&[], &[usize::MAX, 2, 3]).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,216 @@
+//! Rotation and compaction of TFRecord event files.
+//!
+//! TensorBoard loads every file whose name contains `tfevents` in a log directory
+//! as a part of the same run, reading them in the lexicographic order of their names.
+//! [`TensorboardRecorder`](crate::TensorboardRecorder) exploits this to split a long run
+//! into multiple event files in the same directory. The names of the files end with
+//! their indices, and compacted files are renamed to the name of the file they replace,
+//! so the order of events is preserved.
+use anyhow::{Context, Result};
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use tensorboard_rs::summary_writer::SummaryWriter;
+
+/// Configuration of the rotation of event files.
+///
+/// A new event file is opened when either of the thresholds is exceeded.
+/// If both thresholds are `None`, rotation is disabled.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RotationConfig {
+    /// Maximum size of an event file in bytes.
+    ///
+    /// The size is estimated from the values written to the file, including those buffered
+    /// by the writer and not yet flushed to the disk.
+    pub max_bytes: Option<u64>,
+
+    /// Maximum number of optimization steps covered by an event file.
+    pub max_steps: Option<usize>,
+
+    /// Downsampling of scalars in old event files.
+    pub compaction: Option<CompactionConfig>,
+}
+
+impl RotationConfig {
+    /// Sets the maximum size of an event file in bytes.
+    pub fn max_bytes(mut self, v: u64) -> Self {
+        self.max_bytes = Some(v);
+        self
+    }
+
+    /// Sets the maximum number of optimization steps covered by an event file.
+    pub fn max_steps(mut self, v: usize) -> Self {
+        self.max_steps = Some(v);
+        self
+    }
+
+    /// Sets the configuration of compaction.
+    pub fn compaction(mut self, v: CompactionConfig) -> Self {
+        self.compaction = Some(v);
+        self
+    }
+}
+
+/// Configuration of the compaction of rotated event files.
+///
+/// Compaction rewrites an old event file keeping only every `downsample`-th value
+/// of each scalar tag (the last value is always kept). Values other than scalars,
+/// like images, are dropped from compacted files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompactionConfig {
+    /// Number of the most recent rotated files kept at full resolution.
+    pub keep_full: usize,
+
+    /// Keeps every `downsample`-th scalar value in compacted files.
+    pub downsample: usize,
+}
+
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            keep_full: 1,
+            downsample: 10,
+        }
+    }
+}
+
+impl CompactionConfig {
+    /// Sets the number of the most recent rotated files kept at full resolution.
+    pub fn keep_full(mut self, v: usize) -> Self {
+        self.keep_full = v;
+        self
+    }
+
+    /// Sets the downsampling factor of scalars.
+    pub fn downsample(mut self, v: usize) -> Self {
+        assert!(v > 0, "downsample must be positive");
+        self.downsample = v;
+        self
+    }
+}
+
+/// Directory in `log_dir` in which event files are created before being moved.
+const OPEN_DIR: &str = ".segment";
+
+/// An event file written by the recorder.
+pub(crate) struct Segment {
+    /// Path of the event file.
+    pub path: PathBuf,
+
+    /// The first step written to the file.
+    pub start_step: Option<usize>,
+
+    /// Estimated number of bytes written to the file.
+    pub bytes: u64,
+
+    /// Scalars written to the file, kept only when compaction is enabled.
+    pub scalars: Vec<(String, usize, f32)>,
+
+    /// `true` if the file has been compacted.
+    pub compacted: bool,
+}
+
+/// Returns event files in a directory.
+pub(crate) fn event_files(dir: &Path) -> Result<HashSet<PathBuf>> {
+    let mut files = HashSet::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_event_file = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.contains("tfevents"));
+        if path.is_file() && is_event_file {
+            files.insert(path);
+        }
+    }
+    Ok(files)
+}
+
+/// Creates a writer and a segment for the `index`-th event file.
+///
+/// The names of event files created by [`SummaryWriter`] have one-second resolution, so
+/// writers opened within the same second would share a file. To avoid it, the writer is
+/// created in an empty directory, and its file is moved into `log_dir` with the suffix
+/// of `index`, e.g., `events.out.tfevents.1700000000.host.000001`.
+pub(crate) fn open_segment(log_dir: &Path, index: usize) -> Result<(SummaryWriter, Segment)> {
+    let open_dir = log_dir.join(OPEN_DIR);
+    if open_dir.exists() {
+        fs::remove_dir_all(&open_dir)?;
+    }
+    fs::create_dir_all(&open_dir)?;
+    let writer = SummaryWriter::new(&open_dir);
+    let file = event_files(&open_dir)?
+        .into_iter()
+        .next()
+        .with_context(|| format!("No event file is created in {:?}", open_dir))?;
+    let mut name = file.file_name().context("Invalid event file")?.to_owned();
+    name.push(format!(".{:06}", index));
+    let path = log_dir.join(name);
+    fs::rename(&file, &path)?;
+    fs::remove_dir(&open_dir)?;
+
+    let segment = Segment {
+        path,
+        start_step: None,
+        bytes: 0,
+        scalars: vec![],
+        compacted: false,
+    };
+    Ok((writer, segment))
+}
+
+/// Rewrites the event file of a segment with downsampled scalars.
+pub(crate) fn compact_segment(
+    log_dir: &Path,
+    segment: &mut Segment,
+    downsample: usize,
+) -> Result<()> {
+    // Select every `downsample`-th value of each tag, keeping the last value
+    let mut tags: Vec<&String> = vec![];
+    for (tag, _, _) in segment.scalars.iter() {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    let mut selected = vec![];
+    for tag in tags {
+        let values = segment
+            .scalars
+            .iter()
+            .filter(|(t, _, _)| t == tag)
+            .collect::<Vec<_>>();
+        let n = values.len();
+        for (i, v) in values.into_iter().enumerate() {
+            if i % downsample == 0 || i == n - 1 {
+                selected.push(v.clone());
+            }
+        }
+    }
+    selected.sort_by_key(|(_, step, _)| *step);
+
+    // Write the selected values into a temporary directory, then replace the original file
+    let tmp_dir = log_dir.join(".compaction");
+    if tmp_dir.exists() {
+        fs::remove_dir_all(&tmp_dir)?;
+    }
+    fs::create_dir_all(&tmp_dir)?;
+    {
+        let mut writer = SummaryWriter::new(&tmp_dir);
+        for (tag, step, value) in selected.iter() {
+            writer.add_scalar(tag, *value, *step);
+        }
+        writer.flush();
+    }
+    let renamed = match event_files(&tmp_dir)?.into_iter().next() {
+        Some(compacted) => fs::rename(compacted, &segment.path).map_err(anyhow::Error::from),
+        None => Err(anyhow::anyhow!("No event file is created in {:?}", tmp_dir)),
+    };
+    fs::remove_dir_all(&tmp_dir)?;
+    renamed.with_context(|| format!("Failed to compact {:?}", segment.path))?;
+
+    segment.scalars = vec![];
+    segment.compacted = true;
+    Ok(())
+}
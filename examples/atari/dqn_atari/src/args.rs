@@ -12,10 +12,15 @@ pub struct Args {
 
     /// Device name.
     /// If set to `"Cpu"`, the CPU will be used.
-    /// Otherwise, the device will be determined by the `cuda_if_available()` method.
+    /// Otherwise, the device will be determined by the `gpu_if_available()` function.
     #[arg(long)]
     pub device: Option<String>,
 
+    /// The number of GPUs for data-parallel training.
+    /// The Q-network is replicated on `Cuda(1)`, ..., `Cuda(n_gpus - 1)`.
+    #[arg(long, default_value_t = 1)]
+    pub n_gpus: usize,
+
     /// Run name of MLflow.
     /// When using this option, an MLflow server must be running.
     /// If no name is provided, the log will be recorded in TensorBoard.
@@ -3,9 +3,12 @@ use crate::types::*;
 use border_atari_env::BorderAtariEnvConfig;
 use border_candle_agent::{
     atari_cnn::{AtariCnn, AtariCnnConfig},
-    dqn::{DqnConfig, DqnExplorer, DqnModelConfig, EpsilonGreedy},
+    dqn::{DqnConfig, DqnModelConfig},
+    explorer::{DiscreteExplorer, EpsilonGreedy},
+    gpu_if_available,
     opt::OptimizerConfig,
-    util::CriticLoss,
+    util::{data_parallel::DataParallelConfig, CriticLoss, SoftUpdateConfig, TargetUpdateMode},
+    Device,
 };
 use border_core::{generic_replay_buffer::SimpleReplayBufferConfig, TrainerConfig};
 use serde::Serialize;
@@ -57,6 +60,9 @@ fn create_replay_buffer_config(_args: &Args) -> SimpleReplayBufferConfig {
         capacity: 262144,
         seed: 42,
         per_config: None,
+        mc_gamma: None,
+        bootstrap: None,
+        memory_limit: None,
     }
 }
 
@@ -64,11 +70,14 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
     let device = if let Some(device) = &args.device {
         match device.as_str() {
             "Cpu" => candle_core::Device::Cpu,
-            _ => candle_core::Device::cuda_if_available(0).unwrap(),
+            _ => gpu_if_available(0).unwrap(),
         }
     } else {
-        candle_core::Device::cuda_if_available(0).unwrap()
+        gpu_if_available(0).unwrap()
     };
+    let data_parallel = (args.n_gpus > 1).then(|| {
+        DataParallelConfig::default().devices((1..args.n_gpus).map(Device::Cuda).collect())
+    });
 
     DqnConfig {
         model_config: DqnModelConfig {
@@ -78,18 +87,22 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
                 skip_linear: false,
             }),
             opt_config: OptimizerConfig::Adam { lr: 0.0001 },
+            amp: None,
+            data_parallel,
         },
-        soft_update_interval: 10000,
+        soft_update: SoftUpdateConfig::default()
+            .update_interval(10000)
+            .update_mode(TargetUpdateMode::Hard),
         n_updates_per_opt: 1,
         batch_size: 32,
         discount_factor: 0.99,
-        tau: 1.0,
         train: false,
-        explorer: DqnExplorer::EpsilonGreedy(EpsilonGreedy {
+        explorer: DiscreteExplorer::EpsilonGreedy(EpsilonGreedy {
             n_opts: 0,
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 1000000,
+            schedule: None,
         }),
         clip_reward: Some(1.0),
         double_dqn: false,
@@ -97,6 +110,11 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
         critic_loss: CriticLoss::Mse,
         record_verbose_level: 0,
         device: Some(device.into()),
+        c51: None,
+        aux_heads: vec![],
+        noisy_nets: false,
+        batch_transforms: vec![],
+        mc_target: false,
         phantom: PhantomData,
     }
 }
@@ -111,5 +129,11 @@ fn create_trainer_config(_args: &Args) -> TrainerConfig {
         flush_record_interval: 3000,
         warmup_period: 32,
         save_interval: 300000,
+        hot_reload: None,
+        eval_threshold: None,
+        eval_threshold_patience: 1,
+        probe: None,
+        eval_seeds: None,
+        best_model: Default::default(),
     }
 }
@@ -5,11 +5,19 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone, Parser, Debug, Serialize, Deserialize)]
 #[command(version, about)]
 pub struct Args {
-    /// "train" or  "eval".
+    /// "train", "eval", "server" or "actor".
     /// In evaluation mode, the trained model is loaded.
+    /// In server mode, the agent is trained with samples from actors run in actor mode,
+    /// possibly in other processes, which connect to the server at `addr`.
     #[arg(long)]
     pub mode: String,
 
+    /// Address of the server in server and actor modes, default to 127.0.0.1:50051.
+    /// Connections are not authenticated, so use a non-loopback address only in a trusted
+    /// network.
+    #[arg(long, default_value = "127.0.0.1:50051")]
+    pub addr: String,
+
     /// Device name.
     /// If set to `"Cpu"`, the CPU will be used.
     /// Otherwise, the device will be determined by the `cuda_if_available()` method.
@@ -40,4 +48,9 @@ pub struct Args {
     /// The maximum value of exploration noise probability, default to 0.4
     #[arg(long, default_value_t = 0.4)]
     pub eps_max: f64,
+
+    /// If set, prioritized experience replay is used with the initial priorities computed
+    /// in actors
+    #[arg(long)]
+    pub per: bool,
 }
@@ -3,10 +3,11 @@ use crate::types::*;
 // use anyhow::Result;
 use border_async_trainer::AsyncTrainerConfig;
 use border_atari_env::BorderAtariEnvConfig;
-use border_core::generic_replay_buffer::SimpleReplayBufferConfig;
+use border_core::generic_replay_buffer::{PerConfig, SimpleReplayBufferConfig};
 use border_tch_agent::{
     cnn::{AtariCnn, AtariCnnConfig},
-    dqn::{DqnConfig, DqnExplorer, DqnModelConfig, EpsilonGreedy},
+    dqn::{DqnConfig, DqnModelConfig},
+    explorer::{DiscreteExplorer, EpsilonGreedy},
     opt::OptimizerConfig,
     util::CriticLoss,
     Device,
@@ -56,11 +57,17 @@ impl DqnAtariAsyncConfig {
     }
 }
 
-fn create_replay_buffer_config(_args: &Args) -> SimpleReplayBufferConfig {
+fn create_replay_buffer_config(args: &Args) -> SimpleReplayBufferConfig {
     SimpleReplayBufferConfig {
         capacity: 262144,
         seed: 42,
-        per_config: None,
+        per_config: match args.per {
+            true => Some(PerConfig::default()),
+            false => None,
+        },
+        mc_gamma: None,
+        bootstrap: None,
+        memory_limit: None,
     }
 }
 
@@ -89,11 +96,12 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
         discount_factor: 0.99,
         tau: 1.0,
         train: false,
-        explorer: DqnExplorer::EpsilonGreedy(EpsilonGreedy {
+        explorer: DiscreteExplorer::EpsilonGreedy(EpsilonGreedy {
             n_opts: 0,
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 1000000,
+            schedule: None,
         }),
         clip_reward: Some(1.0),
         double_dqn: false,
@@ -101,6 +109,8 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
         critic_loss: CriticLoss::Mse,
         record_verbose_level: 0,
         device: Some(device),
+        noisy_nets: false,
+        sample_no_grad: true,
         phantom: PhantomData,
     }
 }
@@ -3,12 +3,19 @@ mod config;
 mod types;
 use anyhow::Result;
 use args::Args;
-use border_async_trainer::{util::train_async, ActorManagerConfig};
+use border_async_trainer::{
+    util::{
+        run_remote_actors, run_remote_actors_with_priorities, train_async, train_async_server,
+        train_async_with_priorities, AsyncTrainConfigs,
+    },
+    ActorManagerConfig, RemoteConfig,
+};
 use border_core::{
     generic_replay_buffer::SimpleStepProcessorConfig, record::Recorder, Agent, Configurable,
     Env as _, Evaluator as _,
 };
 use border_mlflow_tracking::MlflowTrackingClient;
+use border_tch_agent::{cnn::AtariCnn, dqn::DqnConfig};
 use border_tensorboard::TensorboardRecorder;
 use clap::Parser;
 
@@ -52,12 +59,11 @@ pub fn n_actions(env_config: &EnvConfig) -> Result<usize> {
     Ok(Env::build(env_config, 0)?.get_num_actions_atari() as usize)
 }
 
-fn train(config: &DqnAtariAsyncConfig) -> Result<()> {
-    let env_config_train = config.clone_env_config();
-    let env_config_eval = config.clone_env_config().eval();
-    let step_proc_config = SimpleStepProcessorConfig {};
-    let n_actions = n_actions(&env_config_train)?;
-
+/// Returns the configurations of the agent to be trained and the agents in actors.
+fn agent_configs(
+    config: &DqnAtariAsyncConfig,
+) -> Result<(DqnConfig<AtariCnn>, Vec<DqnConfig<AtariCnn>>)> {
+    let n_actions = n_actions(&config.env_config)?;
     let mut agent_config = config.agent_config.clone();
     agent_config.model_config.q_config.as_mut().unwrap().out_dim = n_actions as i64;
     let agent_configs = (0..config.args.n_actors)
@@ -67,6 +73,14 @@ fn train(config: &DqnAtariAsyncConfig) -> Result<()> {
             config
         })
         .collect::<Vec<_>>();
+    Ok((agent_config, agent_configs))
+}
+
+fn train(config: &DqnAtariAsyncConfig) -> Result<()> {
+    let env_config_train = config.clone_env_config();
+    let env_config_eval = config.clone_env_config().eval();
+    let step_proc_config = SimpleStepProcessorConfig::default().terminate_on_life_loss(true);
+    let (agent_config, agent_configs) = agent_configs(config)?;
     let replay_buffer_config = &config.clone_replay_buffer_config();
     let actor_man_config = ActorManagerConfig::default();
     let trainer_config = config.clone_trainer_config();
@@ -74,18 +88,84 @@ fn train(config: &DqnAtariAsyncConfig) -> Result<()> {
     let mut recorder = create_recorder(&config.args, Some(config))?;
     let mut evaluator = Evaluator::new(&env_config_eval, 0, 1)?;
 
-    train_async::<Dqn, Env, types::ReplayBuffer, types::StepProc>(
+    // With prioritized experience replay, actors compute the initial priorities of samples
+    match config.args.per {
+        true => train_async_with_priorities::<Dqn, Env, types::ReplayBuffer, types::StepProc>(
+            &AsyncTrainConfigs {
+                agent_config: &agent_config,
+                agent_configs: &agent_configs,
+                env_config_train: &env_config_train,
+                env_config_eval: &env_config_eval,
+                step_proc_config: &step_proc_config,
+                replay_buffer_config: &replay_buffer_config,
+                actor_man_config: &actor_man_config,
+                async_trainer_config: &trainer_config,
+            },
+            &mut recorder,
+            &mut evaluator,
+        ),
+        false => train_async::<Dqn, Env, types::ReplayBuffer, types::StepProc>(
+            &agent_config,
+            &agent_configs,
+            &env_config_train,
+            &env_config_eval,
+            &step_proc_config,
+            &replay_buffer_config,
+            &actor_man_config,
+            &trainer_config,
+            &mut recorder,
+            &mut evaluator,
+        ),
+    }
+
+    Ok(())
+}
+
+fn train_server(config: &DqnAtariAsyncConfig) -> Result<()> {
+    let env_config_eval = config.clone_env_config().eval();
+    let (agent_config, _) = agent_configs(config)?;
+    let replay_buffer_config = &config.clone_replay_buffer_config();
+    let trainer_config = config.clone_trainer_config();
+
+    let mut recorder = create_recorder(&config.args, Some(config))?;
+    let mut evaluator = Evaluator::new(&env_config_eval, 0, 1)?;
+
+    train_async_server::<Dqn, Env, types::ReplayBuffer>(
         &agent_config,
-        &agent_configs,
-        &env_config_train,
         &env_config_eval,
-        &step_proc_config,
         &replay_buffer_config,
-        &actor_man_config,
         &trainer_config,
         &mut recorder,
         &mut evaluator,
-    );
+        &RemoteConfig::default().addr(config.args.addr.as_str()),
+    )
+}
+
+fn run_actors(config: &DqnAtariAsyncConfig) -> Result<()> {
+    let env_config_train = config.clone_env_config();
+    let step_proc_config = SimpleStepProcessorConfig::default().terminate_on_life_loss(true);
+    let (_, agent_configs) = agent_configs(config)?;
+    let actor_man_config = ActorManagerConfig::default();
+    let remote_config = RemoteConfig::default().addr(config.args.addr.as_str());
+
+    match config.args.per {
+        true => {
+            run_remote_actors_with_priorities::<Dqn, Env, types::ReplayBuffer, types::StepProc>(
+                &agent_configs,
+                &env_config_train,
+                &step_proc_config,
+                &actor_man_config,
+                &remote_config,
+            )?
+        }
+        false => run_remote_actors::<Dqn, Env, types::ReplayBuffer, types::StepProc>(
+            &agent_configs,
+            &env_config_train,
+            &step_proc_config,
+            &actor_man_config,
+            &remote_config,
+        )?,
+    };
 
     Ok(())
 }
@@ -113,7 +193,9 @@ fn main() -> Result<()> {
     match config.args.mode.as_str() {
         "train" => train(&config)?,
         "eval" => eval(&config)?,
-        _ => panic!("mode must be either 'train' or 'eval'"),
+        "server" => train_server(&config)?,
+        "actor" => run_actors(&config)?,
+        _ => panic!("mode must be one of 'train', 'eval', 'server' and 'actor'"),
     }
 
     Ok(())
@@ -4,7 +4,8 @@ use border_atari_env::BorderAtariEnvConfig;
 use border_core::{generic_replay_buffer::SimpleReplayBufferConfig, TrainerConfig};
 use border_tch_agent::{
     cnn::{AtariCnn, AtariCnnConfig},
-    dqn::{DqnConfig, DqnExplorer, DqnModelConfig, EpsilonGreedy},
+    dqn::{DqnConfig, DqnModelConfig},
+    explorer::{DiscreteExplorer, EpsilonGreedy},
     opt::OptimizerConfig,
     util::CriticLoss,
     Device,
@@ -58,6 +59,9 @@ fn create_replay_buffer_config(_args: &Args) -> SimpleReplayBufferConfig {
         capacity: 262144,
         seed: 42,
         per_config: None,
+        mc_gamma: None,
+        bootstrap: None,
+        memory_limit: None,
     }
 }
 
@@ -86,11 +90,12 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
         discount_factor: 0.99,
         tau: 1.0,
         train: false,
-        explorer: DqnExplorer::EpsilonGreedy(EpsilonGreedy {
+        explorer: DiscreteExplorer::EpsilonGreedy(EpsilonGreedy {
             n_opts: 0,
             eps_start: 1.0,
             eps_final: 0.02,
             final_step: 1000000,
+            schedule: None,
         }),
         clip_reward: Some(1.0),
         double_dqn: false,
@@ -98,6 +103,8 @@ fn create_agent_config(args: &Args) -> DqnConfig<AtariCnn> {
         critic_loss: CriticLoss::Mse,
         record_verbose_level: 0,
         device: Some(device),
+        noisy_nets: false,
+        sample_no_grad: true,
         phantom: PhantomData,
     }
 }
@@ -112,5 +119,11 @@ fn create_trainer_config(_args: &Args) -> TrainerConfig {
         flush_record_interval: 3000,
         warmup_period: 32,
         save_interval: 300000,
+        hot_reload: None,
+        eval_threshold: None,
+        eval_threshold_patience: 1,
+        probe: None,
+        eval_seeds: None,
+        best_model: Default::default(),
     }
 }
@@ -48,7 +48,7 @@ fn create_recorder(
 fn train(config: &DqnAtariConfig) -> Result<()> {
     let env_config_train = config.clone_env_config();
     let env_config_eval = config.clone_env_config().eval();
-    let step_proc_config = SimpleStepProcessorConfig {};
+    let step_proc_config = SimpleStepProcessorConfig::default().terminate_on_life_loss(true);
 
     let mut trainer = Trainer::build(config.clone_trainer_config());
     let env = Env::build(&env_config_train, 0)?;
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use border_candle_agent::{
     awac::{Awac, AwacConfig},
+    gpu_if_available,
     mlp::{Mlp, Mlp3, MlpConfig},
     opt::OptimizerConfig,
     util::{
@@ -47,7 +48,7 @@ struct Args {
 
     /// Device name.
     /// If set to `"Cpu"`, the CPU will be used.
-    /// Otherwise, the device will be determined by the `cuda_if_available()` method.
+    /// Otherwise, the device will be determined by the `gpu_if_available()` function.
     #[arg(long)]
     device: Option<String>,
 
@@ -161,10 +162,10 @@ fn create_awac_config(args: &Args) -> Result<AwacConfig<Mlp, Mlp3>> {
     let device = if let Some(device) = &args.device {
         match device.as_str() {
             "cpu" => Device::Cpu,
-            _ => Device::cuda_if_available(0)?,
+            _ => gpu_if_available(0)?,
         }
     } else {
-        Device::cuda_if_available(0)?
+        gpu_if_available(0)?
     };
     log::info!("Device is {:?}", device);
 
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use border_candle_agent::{
     bc::{Bc, BcActionType, BcConfig, BcModelConfig},
+    gpu_if_available,
     mlp::{Mlp, MlpConfig},
     Activation,
 };
@@ -36,7 +37,7 @@ struct Args {
 
     /// Device name.
     /// If set to `"Cpu"`, the CPU will be used.
-    /// Otherwise, the device will be determined by the `cuda_if_available()` method.
+    /// Otherwise, the device will be determined by the `gpu_if_available()` function.
     #[arg(long)]
     device: Option<String>,
 
@@ -120,6 +121,7 @@ fn create_bc_config(args: &Args) -> Result<BcConfig<Mlp>> {
             out_dim: dim_act,
             units: vec![256, 256],
             activation_out: Activation::Tanh,
+            noisy: false,
         };
         BcModelConfig::default().policy_model_config(policy_model_config)
     };
@@ -128,10 +130,10 @@ fn create_bc_config(args: &Args) -> Result<BcConfig<Mlp>> {
     let device = if let Some(device) = &args.device {
         match device.as_str() {
             "cpu" => Device::Cpu,
-            _ => Device::cuda_if_available(0)?,
+            _ => gpu_if_available(0)?,
         }
     } else {
-        Device::cuda_if_available(0)?
+        gpu_if_available(0)?
     };
     log::info!("Device is {:?}", device);
 
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use border_candle_agent::{
+    gpu_if_available,
     iql::{Iql, IqlConfig, ValueConfig},
     mlp::{Mlp, Mlp3, MlpConfig},
     opt::OptimizerConfig,
@@ -17,7 +18,7 @@ use border_core::{
 };
 use border_minari::{
     d4rl::pen::candle::{PenConverter, PenConverterConfig},
-    MinariConverter, MinariDataset, MinariEnv, MinariEvaluator,
+    MinariConverter, MinariDataset, MinariEnv, MinariEvaluator, RewardTransform,
 };
 use border_mlflow_tracking::MlflowTrackingClient;
 use border_tensorboard::TensorboardRecorder;
@@ -47,7 +48,7 @@ struct Args {
 
     /// Device name.
     /// If set to `"Cpu"`, the CPU will be used.
-    /// Otherwise, the device will be determined by the `cuda_if_available()` method.
+    /// Otherwise, the device will be determined by the `gpu_if_available()` function.
     #[arg(long)]
     device: Option<String>,
 
@@ -83,6 +84,14 @@ struct Args {
     /// Action limit type ("clamp" or "tanh")
     #[arg(long, default_value = "clamp")]
     action_limit: String,
+
+    /// Scale of rewards in the dataset
+    #[arg(long, default_value_t = 1.0)]
+    reward_scale: f32,
+
+    /// Shift of rewards in the dataset
+    #[arg(long, default_value_t = 0.0)]
+    reward_shift: f32,
 }
 
 impl Args {
@@ -104,6 +113,13 @@ impl Args {
             _ => panic!("action_limit should be clamp or tanh"),
         }
     }
+
+    pub fn reward_transform(&self) -> Option<RewardTransform> {
+        match (self.reward_scale, self.reward_shift) {
+            (scale, shift) if scale == 1.0 && shift == 0.0 => None,
+            (scale, shift) => Some(RewardTransform::Scale { scale, shift }),
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -111,6 +127,7 @@ struct PenConfig {
     args: Args,
     trainer_config: TrainerConfig,
     agent_config: IqlConfig<Mlp, Mlp3, Mlp>,
+    reward_transform: Option<RewardTransform>,
 }
 
 impl PenConfig {
@@ -121,10 +138,12 @@ impl PenConfig {
             .flush_record_interval(args.record_agent_info_interval)
             .record_agent_info_interval(args.record_agent_info_interval);
         let agent_config = create_iql_config(&args).unwrap();
+        let reward_transform = args.reward_transform();
         Self {
             args,
             trainer_config,
             agent_config,
+            reward_transform,
         }
     }
 }
@@ -169,10 +188,10 @@ fn create_iql_config(args: &Args) -> Result<IqlConfig<Mlp, Mlp3, Mlp>> {
     let device = if let Some(device) = &args.device {
         match device.as_str() {
             "cpu" => Device::Cpu,
-            _ => Device::cuda_if_available(0)?,
+            _ => gpu_if_available(0)?,
         }
     } else {
-        Device::cuda_if_available(0)?
+        gpu_if_available(0)?
     };
     log::info!("Device is {:?}", device);
 
@@ -305,7 +324,8 @@ fn main() -> Result<()> {
     let args = Args::parse();
 
     let config = PenConfig::new(args.clone());
-    let dataset = MinariDataset::load_dataset(args.dataset_name(), true)?;
+    let dataset = MinariDataset::load_dataset(args.dataset_name(), true)?
+        .reward_transform(config.reward_transform.clone());
     let converter = PenConverter::new(PenConverterConfig {}, &dataset)?;
 
     match args.mode.as_str() {
@@ -328,9 +348,12 @@ fn test() -> Result<()> {
         batch_size: 256,
         record_agent_info_interval: 1000,
         action_limit: "clamp".to_string(),
+        reward_scale: 1.0,
+        reward_shift: 0.0,
     };
     let config = PenConfig::new(args.clone());
-    let dataset = MinariDataset::load_dataset(args.dataset_name(), true)?;
+    let dataset = MinariDataset::load_dataset(args.dataset_name(), true)?
+        .reward_transform(config.reward_transform.clone());
     let converter = PenConverter::new(PenConverterConfig {}, &dataset)?;
     train(config, dataset, converter)
 }
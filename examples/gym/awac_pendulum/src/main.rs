@@ -1,6 +1,7 @@
 use anyhow::Result;
 use border_candle_agent::{
     awac::{Awac, AwacConfig},
+    gpu_if_available,
     mlp::{Mlp, Mlp3, MlpConfig},
     opt::OptimizerConfig,
     util::{
@@ -24,7 +25,6 @@ use border_py_gym_env::{
     GymEnv, GymEnvConfig,
 };
 use border_tensorboard::TensorboardRecorder;
-use candle_core::Device;
 use clap::Parser;
 use serde::Serialize;
 
@@ -111,7 +111,7 @@ fn create_awac_config(in_dim: i64, out_dim: i64) -> Result<AwacConfig<Mlp, Mlp3>
         ));
 
     // Device
-    let device = Device::cuda_if_available(0)?;
+    let device = gpu_if_available(0)?;
 
     // Agent config
     let agent_config = AwacConfig::<Mlp, Mlp3>::default()
@@ -180,7 +180,7 @@ impl AwacPendulumConfig {
 
 fn train(args: &Args, max_opts: usize, model_dir: &str, eval_interval: usize) -> Result<()> {
     let config = AwacPendulumConfig::new(DIM_OBS, DIM_ACT, max_opts, eval_interval)?;
-    let step_proc_config = SimpleStepProcessorConfig {};
+    let step_proc_config = SimpleStepProcessorConfig::default();
     let replay_buffer_config = SimpleReplayBufferConfig::default().capacity(REPLAY_BUFFER_CAPACITY);
     let mut recorder = create_recorder(&args, model_dir, Some(&config))?;
     let mut trainer = Trainer::build(config.trainer_config.clone());
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use border_candle_agent::{
     dqn::{Dqn, DqnConfig, DqnModelConfig},
+    gpu_if_available,
     mlp::{Mlp, MlpConfig},
     opt::OptimizerConfig,
     util::CriticLoss,
@@ -26,7 +27,6 @@ use border_py_gym_env::{
     GymEnv, GymEnvConfig,
 };
 use border_tensorboard::TensorboardRecorder;
-use candle_core::Device;
 use clap::Parser;
 use serde::Serialize;
 
@@ -35,8 +35,6 @@ type ReplayBuffer = SimpleReplayBuffer<TensorBatch, TensorBatch>;
 type StepProc = SimpleStepProcessor<Env, TensorBatch, TensorBatch>;
 type Evaluator = DefaultEvaluator<Env>;
 
-const DIM_OBS: i64 = 4;
-const DIM_ACT: i64 = 2;
 const LR_CRITIC: f64 = 1e-3;
 const DISCOUNT_FACTOR: f64 = 0.99;
 const BATCH_SIZE: usize = 64;
@@ -87,7 +85,7 @@ fn create_env_config(render: bool) -> Result<GymEnvConfig<NdarrayConverter>> {
 }
 
 fn create_agent_config(in_dim: i64, out_dim: i64) -> Result<DqnConfig<Mlp>> {
-    let device = Device::cuda_if_available(0)?;
+    let device = gpu_if_available(0)?;
     let opt_config = OptimizerConfig::default().learning_rate(LR_CRITIC);
     let mlp_config = MlpConfig::new(in_dim, vec![256, 256], out_dim, Activation::None);
     let model_config = DqnModelConfig::default()
@@ -139,9 +137,11 @@ pub struct DqnCartpoleConfig {
 }
 
 impl DqnCartpoleConfig {
-    pub fn new(in_dim: i64, out_dim: i64, max_opts: usize, eval_interval: usize) -> Result<Self> {
+    pub fn new(max_opts: usize, eval_interval: usize) -> Result<Self> {
         let env_config = create_env_config(false)?;
-        let agent_config = create_agent_config(in_dim, out_dim)?;
+        let (obs_space, act_space) = env_config.spaces()?;
+        let agent_config =
+            create_agent_config(obs_space.flat_dim() as _, act_space.flat_dim() as _)?;
         let trainer_config = TrainerConfig::default()
             .max_opts(max_opts)
             .opt_interval(OPT_INTERVAL)
@@ -162,8 +162,8 @@ impl DqnCartpoleConfig {
 }
 
 fn train(args: &Args, max_opts: usize, model_dir: &str, eval_interval: usize) -> Result<()> {
-    let config = DqnCartpoleConfig::new(DIM_OBS, DIM_ACT, max_opts, eval_interval)?;
-    let step_proc_config = SimpleStepProcessorConfig {};
+    let config = DqnCartpoleConfig::new(max_opts, eval_interval)?;
+    let step_proc_config = SimpleStepProcessorConfig::default();
     let replay_buffer_config = SimpleReplayBufferConfig::default().capacity(REPLAY_BUFFER_CAPACITY);
     let mut recorder = create_recorder(&args, model_dir, Some(&config))?;
     let mut trainer = Trainer::build(config.trainer_config.clone());
@@ -189,7 +189,9 @@ fn train(args: &Args, max_opts: usize, model_dir: &str, eval_interval: usize) ->
 fn eval(args: &Args, model_dir: &str, render: bool) -> Result<()> {
     let env_config = create_env_config(render)?;
     let mut agent: Box<dyn Agent<_, ReplayBuffer>> = {
-        let agent_config = create_agent_config(DIM_OBS, DIM_ACT)?;
+        let (obs_space, act_space) = env_config.spaces()?;
+        let agent_config =
+            create_agent_config(obs_space.flat_dim() as _, act_space.flat_dim() as _)?;
         let mut agent = Box::new(Dqn::build(agent_config)) as _;
         let recorder = create_recorder(&args, model_dir, None)?;
         recorder.load_model("best".as_ref(), &mut agent)?;
@@ -0,0 +1,247 @@
+use anyhow::Result;
+use border_candle_agent::{
+    conv_encoder::{ConvEncoder, ConvEncoderConfig},
+    drqv2::{DrqV2, DrqV2Config},
+    gpu_if_available,
+    mlp::{Mlp, MlpConfig},
+    opt::OptimizerConfig,
+    util::{critic::MultiCriticConfig, encoder::EncoderConfig},
+    Activation,
+};
+use border_core::{
+    generic_replay_buffer::{
+        SimpleReplayBuffer, SimpleReplayBufferConfig, SimpleStepProcessor,
+        SimpleStepProcessorConfig,
+    },
+    record::Recorder,
+    Agent, Configurable, DefaultEvaluator, Env as _, Evaluator as _, ReplayBufferBase,
+    StepProcessor, Trainer, TrainerConfig,
+};
+use border_mlflow_tracking::MlflowTrackingClient;
+use border_py_gym_env::{
+    candle::{NdarrayConverter, NdarrayConverterConfig, TensorBatch},
+    ActBounds, FrameConfig, GymEnv, GymEnvConfig,
+};
+use border_tensorboard::TensorboardRecorder;
+use clap::Parser;
+use serde::Serialize;
+
+type Env = GymEnv<NdarrayConverter>;
+type ReplayBuffer = SimpleReplayBuffer<TensorBatch, TensorBatch>;
+type StepProc = SimpleStepProcessor<Env, TensorBatch, TensorBatch>;
+type Evaluator = DefaultEvaluator<Env>;
+
+const OBS_SHAPE: [i64; 3] = [3, 84, 84];
+const DIM_FEATURE: i64 = 50;
+const DIM_ACT: i64 = 3;
+const LR: f64 = 1e-4;
+const BATCH_SIZE: usize = 256;
+const WARMUP_PERIOD: usize = 4000;
+const OPT_INTERVAL: usize = 2;
+const MAX_OPTS: usize = 250_000;
+const STD_DECAY_STEPS: usize = 250_000;
+const EVAL_INTERVAL: usize = 10_000;
+const REPLAY_BUFFER_CAPACITY: usize = 50_000;
+const N_EPISODES_PER_EVAL: usize = 5;
+const ENV_NAME: &str = "CarRacing-v2";
+const MODEL_DIR: &str = "./model/candle/drqv2_car_racing";
+const MLFLOW_EXPERIMENT_NAME: &str = "Gym";
+const MLFLOW_RUN_NAME: &str = "drqv2-gym-car-racing-v2-candle";
+const MLFLOW_TAGS: &[(&str, &str)] = &[
+    ("env", "car_racing"),
+    ("algo", "drqv2"),
+    ("backend", "candle"),
+];
+
+/// Train/eval DrQ-v2 agent in car racing environment
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Train DrQ-v2 agent, not evaluate
+    #[arg(short, long, default_value_t = false)]
+    train: bool,
+
+    /// Evaluate DrQ-v2 agent, not train
+    #[arg(short, long, default_value_t = false)]
+    eval: bool,
+
+    /// Log metrics with MLflow
+    #[arg(short, long, default_value_t = false)]
+    mlflow: bool,
+}
+
+fn create_env_config(render: bool) -> Result<GymEnvConfig<NdarrayConverter>> {
+    let [_, height, width] = OBS_SHAPE;
+    let mut env_config = GymEnvConfig::default()
+        .name(ENV_NAME.to_string())
+        .frame(
+            FrameConfig::default()
+                .resize(height as _, width as _)
+                .channel_first(true),
+        )
+        .act_bounds(ActBounds::Rescale)
+        .converter_config(NdarrayConverterConfig {});
+
+    if render {
+        env_config = env_config
+            .render_mode(Some("human".to_string()))
+            .set_wait_in_millis(10);
+    }
+
+    Ok(env_config)
+}
+
+mod agent {
+    use super::*;
+
+    fn create_encoder_config() -> EncoderConfig<ConvEncoderConfig> {
+        EncoderConfig::default()
+            .opt_config(OptimizerConfig::Adam { lr: LR })
+            .encoder_config(ConvEncoderConfig::new(OBS_SHAPE, DIM_FEATURE))
+    }
+
+    fn create_actor_config() -> MlpConfig {
+        MlpConfig::new(DIM_FEATURE, vec![1024, 1024], DIM_ACT, Activation::Tanh)
+    }
+
+    fn create_critic_config() -> MultiCriticConfig<MlpConfig> {
+        MultiCriticConfig::default()
+            .opt_config(OptimizerConfig::Adam { lr: LR })
+            .q_config(MlpConfig::new(
+                DIM_FEATURE + DIM_ACT,
+                vec![1024, 1024],
+                1,
+                Activation::None,
+            ))
+            .tau(0.01)
+    }
+
+    pub fn create_agent_config() -> Result<DrqV2Config<ConvEncoder, Mlp, Mlp>> {
+        let device = gpu_if_available(0)?;
+        let agent_config = DrqV2Config::default()
+            .encoder_config(create_encoder_config())
+            .actor_config(create_actor_config())
+            .actor_opt_config(OptimizerConfig::Adam { lr: LR })
+            .critic_config(create_critic_config())
+            .std_schedule(1.0, 0.1, STD_DECAY_STEPS)
+            .batch_size(BATCH_SIZE)
+            .device(device);
+
+        Ok(agent_config)
+    }
+}
+
+use agent::create_agent_config;
+
+/// `model_dir` - Directory where TFRecord and model parameters are saved with
+///               [`TensorboardRecorder`].
+/// `config` - Configuration parameters for a run of MLflow. These are used for
+///            recording purpose only when a new run is created.
+fn create_recorder(
+    args: &Args,
+    model_dir: &str,
+    config: Option<&DrqV2CarRacingConfig>,
+) -> Result<Box<dyn Recorder<Env, ReplayBuffer>>> {
+    match args.mlflow {
+        true => {
+            let client = MlflowTrackingClient::new("http://localhost:8080")
+                .set_experiment(MLFLOW_EXPERIMENT_NAME)?;
+            let recorder_run = client.create_recorder(MLFLOW_RUN_NAME)?;
+            if let Some(config) = config {
+                recorder_run.log_params(config)?;
+                recorder_run.set_tags(MLFLOW_TAGS)?;
+            }
+            Ok(Box::new(recorder_run))
+        }
+        false => Ok(Box::new(TensorboardRecorder::new(
+            model_dir, model_dir, false,
+        ))),
+    }
+}
+
+#[derive(Serialize)]
+pub struct DrqV2CarRacingConfig {
+    pub env_config: GymEnvConfig<NdarrayConverter>,
+    pub agent_config: DrqV2Config<ConvEncoder, Mlp, Mlp>,
+    pub trainer_config: TrainerConfig,
+}
+
+impl DrqV2CarRacingConfig {
+    pub fn new(max_opts: usize, eval_interval: usize) -> Result<Self> {
+        let env_config = create_env_config(false)?;
+        let agent_config = create_agent_config()?;
+        let trainer_config = TrainerConfig::default()
+            .max_opts(max_opts)
+            .opt_interval(OPT_INTERVAL)
+            .eval_interval(eval_interval)
+            .record_agent_info_interval(eval_interval)
+            .record_compute_cost_interval(eval_interval)
+            .flush_record_interval(eval_interval)
+            .save_interval(eval_interval)
+            .warmup_period(WARMUP_PERIOD);
+        let config = Self {
+            env_config,
+            agent_config,
+            trainer_config,
+        };
+
+        Ok(config)
+    }
+}
+
+fn train(args: &Args, max_opts: usize, model_dir: &str, eval_interval: usize) -> Result<()> {
+    let config = DrqV2CarRacingConfig::new(max_opts, eval_interval)?;
+    let step_proc_config = SimpleStepProcessorConfig::default();
+    let replay_buffer_config = SimpleReplayBufferConfig::default().capacity(REPLAY_BUFFER_CAPACITY);
+    let mut recorder = create_recorder(&args, model_dir, Some(&config))?;
+    let mut trainer = Trainer::build(config.trainer_config.clone());
+
+    let env = Env::build(&config.env_config, 0)?;
+    let step_proc = StepProc::build(&step_proc_config);
+    let mut agent = Box::new(DrqV2::build(config.agent_config)) as _;
+    let mut buffer = ReplayBuffer::build(&replay_buffer_config);
+    let mut evaluator = Evaluator::new(&config.env_config, 0, N_EPISODES_PER_EVAL)?;
+
+    trainer.train(
+        env,
+        step_proc,
+        &mut agent,
+        &mut buffer,
+        &mut recorder,
+        &mut evaluator,
+    )?;
+
+    Ok(())
+}
+
+fn eval(args: &Args, model_dir: &str, render: bool) -> Result<()> {
+    let env_config = create_env_config(render)?;
+    let mut agent: Box<dyn Agent<_, ReplayBuffer>> = {
+        let agent_config = create_agent_config()?;
+        let mut agent = Box::new(DrqV2::build(agent_config)) as _;
+        let recorder = create_recorder(&args, model_dir, None)?;
+        recorder.load_model("best".as_ref(), &mut agent)?;
+        agent.eval();
+        agent
+    };
+    let _ = Evaluator::new(&env_config, 0, 5)?.evaluate(&mut agent);
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+
+    let args = Args::parse();
+
+    if args.train {
+        train(&args, MAX_OPTS, MODEL_DIR, EVAL_INTERVAL)?;
+    } else if args.eval {
+        eval(&args, MODEL_DIR, true)?;
+    } else {
+        train(&args, MAX_OPTS, MODEL_DIR, EVAL_INTERVAL)?;
+        eval(&args, MODEL_DIR, true)?;
+    }
+
+    Ok(())
+}
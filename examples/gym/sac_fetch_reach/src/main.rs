@@ -1,5 +1,6 @@
 use anyhow::Result;
 use border_candle_agent::{
+    gpu_if_available,
     mlp::{Mlp, Mlp2, MlpConfig},
     opt::OptimizerConfig,
     sac::{EntCoefMode, Sac, SacConfig},
@@ -26,7 +27,6 @@ use border_py_gym_env::{
     GymEnv, GymEnvConfig,
 };
 use border_tensorboard::TensorboardRecorder;
-use candle_core::Device;
 use clap::Parser;
 use serde::Serialize;
 
@@ -121,7 +121,7 @@ fn create_critic_config(in_dim: i64, out_dim: i64) -> MultiCriticConfig<MlpConfi
 }
 
 fn create_agent_config(in_dim: i64, out_dim: i64) -> Result<SacConfig<Mlp, Mlp2>> {
-    let device = Device::cuda_if_available(0)?;
+    let device = gpu_if_available(0)?;
     let actor_config = create_actor_config(in_dim, out_dim);
     let critic_config = create_critic_config(in_dim, out_dim);
     let sac_config = SacConfig::default()
@@ -221,7 +221,7 @@ impl SacFetchReachConfig {
 
 fn train(args: &Args, max_opts: usize, model_dir: &str, eval_interval: usize) -> Result<()> {
     let config = SacFetchReachConfig::new(DIM_OBS, DIM_ACT, max_opts, eval_interval)?;
-    let step_proc_config = SimpleStepProcessorConfig {};
+    let step_proc_config = SimpleStepProcessorConfig::default();
     let replay_buffer_config = SimpleReplayBufferConfig::default().capacity(REPLAY_BUFFER_CAPACITY);
     let mut recorder = create_recorder(&args, model_dir, Some(&config))?;
     let mut trainer = Trainer::build(config.trainer_config.clone());
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use border_candle_agent::{
+    gpu_if_available,
     mlp::{Mlp, Mlp2, MlpConfig},
     opt::OptimizerConfig,
     sac::{Sac, SacConfig},
@@ -29,7 +30,6 @@ use border_py_gym_env::{
     GymEnv, GymEnvConfig,
 };
 use border_tensorboard::TensorboardRecorder;
-use candle_core::Device;
 use clap::Parser;
 use serde::Serialize;
 
@@ -116,7 +116,7 @@ mod agent {
     }
 
     pub fn create_agent_config(in_dim: i64, out_dim: i64) -> Result<SacConfig<Mlp, Mlp2>> {
-        let device = Device::cuda_if_available(0)?;
+        let device = gpu_if_available(0)?;
         let actor_config = create_actor_config(in_dim, out_dim);
         let critic_config = create_critic_config(in_dim, out_dim);
         let sac_config = SacConfig::default()
@@ -189,7 +189,7 @@ impl SacPendulumConfig {
 
 fn train(args: &Args, max_opts: usize, model_dir: &str, eval_interval: usize) -> Result<()> {
     let config = SacPendulumConfig::new(DIM_OBS, DIM_ACT, max_opts, eval_interval)?;
-    let step_proc_config = SimpleStepProcessorConfig {};
+    let step_proc_config = SimpleStepProcessorConfig::default();
     let replay_buffer_config = SimpleReplayBufferConfig::default().capacity(REPLAY_BUFFER_CAPACITY);
     let mut recorder = create_recorder(&args, model_dir, Some(&config))?;
     let mut trainer = Trainer::build(config.trainer_config.clone());
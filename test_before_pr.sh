@@ -18,3 +18,4 @@ cd examples/gym/sac_pendulum_tch; cargo test; cd ../../..
 cd examples/d4rl/bc_pen; cargo test; cd ../../..
 cd examples/d4rl/awac_pen; cargo test; cd ../../..
 cd examples/d4rl/iql_pen; cargo test; cd ../../..
+cd examples/d4rl/cql_maze2d; cargo test; cd ../../..